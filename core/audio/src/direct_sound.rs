@@ -0,0 +1,217 @@
+//! GBA direct sound
+//!
+//! Two 8-bit PCM FIFOs (A and B), each clocked by overflows of either timer
+//! 0 or timer 1. Every overflow plays the next sample; once a FIFO is down to
+//! [`FIFO_REFILL_LEVEL`] bytes it asks its sound DMA for another 16 bytes.
+//! Only reachable in GBA mode, DS software has no access to it. Its output
+//! is mixed into the SPU output, see [`DirectSound::mix_into`].
+use std::collections::VecDeque;
+
+use crate::SpuOutput;
+
+/// Output is clipped to the 10-bit GBA range, then scaled by this to the
+/// 16-bit SPU range.
+const OUTPUT_SCALE: i16 = 64;
+
+/// FIFO capacity in bytes.
+pub const FIFO_SIZE: usize = 32;
+/// A FIFO holding this many bytes or fewer requests a sound DMA.
+pub const FIFO_REFILL_LEVEL: usize = 16;
+
+lunaris_ds_bitfield::bitfield! {
+    /// SOUNDCNT_H, direct sound control
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct DirectSoundCnt(u16) {
+        /// PSG volume (0=25%, 1=50%, 2=100%)
+        pub psg_volume: u8 [0..2],
+        /// FIFO A at 100% instead of 50%
+        pub a_full_volume: bool [2],
+        /// FIFO B at 100% instead of 50%
+        pub b_full_volume: bool [3],
+        pub a_right: bool [8],
+        pub a_left: bool [9],
+        /// FIFO A clocked by timer 1 instead of timer 0
+        pub a_timer: bool [10],
+        pub b_right: bool [12],
+        pub b_left: bool [13],
+        /// FIFO B clocked by timer 1 instead of timer 0
+        pub b_timer: bool [14],
+    }
+}
+
+/// One direct sound FIFO.
+#[derive(Debug, Default, Clone)]
+pub struct SoundFifo {
+    buffer: VecDeque<i8>,
+    /// Sample currently being output
+    pub sample: i8,
+}
+
+impl SoundFifo {
+    /// Bytes queued.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.sample = 0;
+    }
+
+    /// Queue four samples, oldest in the low byte. Writes to a full FIFO
+    /// are dropped.
+    pub fn write_word(&mut self, word: u32) {
+        for byte in word.to_le_bytes() {
+            if self.buffer.len() < FIFO_SIZE {
+                self.buffer.push_back(byte as i8);
+            }
+        }
+    }
+
+    /// Advance to the next sample. An empty FIFO keeps the last one.
+    pub fn pop(&mut self) {
+        if let Some(sample) = self.buffer.pop_front() {
+            self.sample = sample;
+        }
+    }
+
+    pub fn needs_refill(&self) -> bool {
+        self.buffer.len() <= FIFO_REFILL_LEVEL
+    }
+
+    /// Queued samples, oldest first.
+    pub fn queued(&self) -> impl ExactSizeIterator<Item = i8> + '_ {
+        self.buffer.iter().copied()
+    }
+
+    /// Replace the queued samples, for savestates. Samples past
+    /// [`FIFO_SIZE`] are dropped.
+    pub fn set_queued(&mut self, samples: impl IntoIterator<Item = i8>) {
+        self.buffer = samples.into_iter().take(FIFO_SIZE).collect();
+    }
+}
+
+/// Direct sound FIFOs A and B with their control register.
+#[derive(Debug, Default, Clone)]
+pub struct DirectSound {
+    pub cnt: DirectSoundCnt,
+    /// FIFO A and B
+    pub fifos: [SoundFifo; 2],
+}
+
+impl DirectSound {
+    pub fn power_on(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn get_cnt(&self) -> u16 {
+        self.cnt.get()
+    }
+
+    /// Write SOUNDCNT_H; bits 11 and 15 reset FIFO A and B.
+    pub fn set_cnt(&mut self, value: u16) {
+        self.cnt.set(value);
+        if value & (1 << 11) != 0 {
+            self.fifos[0].reset();
+        }
+        if value & (1 << 15) != 0 {
+            self.fifos[1].reset();
+        }
+    }
+
+    /// Timer selected by FIFO `fifo`.
+    fn timer(&self, fifo: usize) -> usize {
+        let timer1 = match fifo {
+            0 => self.cnt.a_timer,
+            _ => self.cnt.b_timer,
+        };
+        usize::from(timer1)
+    }
+
+    /// Clock the FIFOs driven by `timer` (0 or 1).
+    ///
+    /// Returns which FIFOs need a sound DMA.
+    pub fn timer_overflow(&mut self, timer: usize) -> [bool; 2] {
+        let mut refill = [false; 2];
+        for (fifo, refill) in refill.iter_mut().enumerate() {
+            if self.timer(fifo) == timer {
+                self.fifos[fifo].pop();
+                *refill = self.fifos[fifo].needs_refill();
+            }
+        }
+        refill
+    }
+
+    /// Current output as (left, right), 10-bit range like the GBA mixer.
+    pub fn output(&self) -> (i16, i16) {
+        let volume = [self.cnt.a_full_volume, self.cnt.b_full_volume];
+        let enabled = [
+            (self.cnt.a_left, self.cnt.a_right),
+            (self.cnt.b_left, self.cnt.b_right),
+        ];
+        let (mut left, mut right) = (0, 0);
+        for fifo in 0..2 {
+            let sample = match volume[fifo] {
+                true => self.fifos[fifo].sample as i16 * 4,
+                false => self.fifos[fifo].sample as i16 * 2,
+            };
+            if enabled[fifo].0 {
+                left += sample;
+            }
+            if enabled[fifo].1 {
+                right += sample;
+            }
+        }
+        (left, right)
+    }
+
+    /// Add the current output to the mix of `output`.
+    pub fn mix_into(&self, output: &mut SpuOutput) {
+        let (left, right) = self.output();
+        for (mix, sample) in output.mix.iter_mut().zip([left, right]) {
+            *mix = mix.saturating_add(sample.clamp(-512, 511) * OUTPUT_SCALE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_clocks_selected_fifo() {
+        let mut sound = DirectSound::default();
+        // A on timer 0 (left, 100%), B on timer 1 (right, 50%)
+        sound.set_cnt((1 << 2) | (1 << 9) | (1 << 12) | (1 << 14));
+        for _ in 0..5 {
+            sound.fifos[0].write_word(0x0403_0201);
+        }
+        sound.fifos[1].write_word(0x8000_00FF);
+
+        assert_eq!(sound.timer_overflow(0), [false, false]);
+        assert_eq!(sound.fifos[0].sample, 1);
+        assert_eq!(sound.fifos[1].sample, 0);
+        assert_eq!(sound.timer_overflow(1), [false, true]);
+        assert_eq!(sound.output(), (4, -2));
+        let mut output = SpuOutput {
+            mix: [100, i16::MIN],
+            ..SpuOutput::default()
+        };
+        sound.mix_into(&mut output);
+        assert_eq!(output.mix, [100 + 4 * 64, i16::MIN]);
+
+        for _ in 0..3 {
+            sound.timer_overflow(0);
+        }
+        assert_eq!(sound.timer_overflow(0), [true, false]);
+        assert_eq!(sound.fifos[0].len(), 15);
+
+        sound.set_cnt(1 << 11);
+        assert!(sound.fifos[0].is_empty());
+        assert_eq!(sound.fifos[1].len(), 3);
+    }
+}
@@ -1,3 +1,17 @@
+mod direct_sound;
+mod playback;
+mod rate_control;
+mod resample;
+mod wav;
+
+use std::collections::VecDeque;
+
+pub use direct_sound::{DirectSound, DirectSoundCnt, FIFO_REFILL_LEVEL, FIFO_SIZE, SoundFifo};
+pub use playback::{CYCLES_PER_SAMPLE, OUTPUT_BUFFER_FRAMES, SpuBus};
+pub use rate_control::{AudioSyncStats, DynamicRateControl, MAX_RATE_DELTA};
+pub use resample::Resampler;
+pub use wav::WavWriter;
+
 /// Sound Processing Unit (SPU) implementation for Nintendo DS
 /// Manages 16 audio channels with mixing and capture capabilities
 /// Audio channel control register
@@ -7,15 +21,15 @@ pub struct ChannelCntReg {
     pub volume: u32,
     /// Frequency divider
     pub divider: u32,
-    /// Hold last sample when stopped
+    /// Keep the last sample when a one-shot sound ends
     pub hold_sample: bool,
     /// Panning (0=left, 64=center, 127=right)
     pub panning: u32,
     /// Wave duty cycle for generators
     pub wave_duty: u32,
-    /// Repeat mode (0=manual, 1=loop, 3=one-shot)
+    /// Repeat mode (0=manual, 1=loop, 2=one-shot)
     pub repeat_mode: u32,
-    /// Audio format (0=PCM8, 1=PCM16, 2=ADPCM, 3=PSG)
+    /// Audio format (0=PCM8, 1=PCM16, 2=IMA-ADPCM, 3=PSG/noise)
     pub format: u32,
     /// Channel is currently playing
     pub busy: bool,
@@ -37,6 +51,32 @@ impl ChannelCntReg {
     }
 }
 
+impl ChannelCntReg {
+    /// SOUNDxCNT as a word.
+    pub fn get(&self) -> u32 {
+        (self.volume & 0x7F)
+            | (self.divider & 0x3) << 8
+            | (self.hold_sample as u32) << 15
+            | (self.panning & 0x7F) << 16
+            | (self.wave_duty & 0x7) << 24
+            | (self.repeat_mode & 0x3) << 27
+            | (self.format & 0x3) << 29
+            | (self.busy as u32) << 31
+    }
+
+    /// Write SOUNDxCNT as a word.
+    pub fn set(&mut self, value: u32) {
+        self.volume = value & 0x7F;
+        self.divider = (value >> 8) & 0x3;
+        self.hold_sample = value & (1 << 15) != 0;
+        self.panning = (value >> 16) & 0x7F;
+        self.wave_duty = (value >> 24) & 0x7;
+        self.repeat_mode = (value >> 27) & 0x3;
+        self.format = (value >> 29) & 0x3;
+        self.busy = value & (1 << 31) != 0;
+    }
+}
+
 impl Default for ChannelCntReg {
     fn default() -> Self {
         Self::new()
@@ -52,10 +92,27 @@ pub struct SoundChannel {
     pub sound_source: u32,
     /// Playback timer/frequency
     pub sound_timer: u16,
-    /// Current playback position
+    /// Loop start, in words from the source address
     pub sound_pnt: u16,
-    /// Length of audio data
-    pub sound_len: u16,
+    /// Length after the loop start, in words
+    pub sound_len: u32,
+    /// Current sample, before volume and panning
+    pub sample: i16,
+    /// Samples (nibbles for ADPCM) played since the start, negative during
+    /// the start delay
+    pub position: i32,
+    /// Timer counter, a sample is due when it reaches 0x10000
+    pub timer: u32,
+    /// ADPCM decoder value and step index
+    pub adpcm_value: i16,
+    pub adpcm_index: u8,
+    /// ADPCM decoder state at the loop start
+    pub adpcm_loop_value: i16,
+    pub adpcm_loop_index: u8,
+    /// ADPCM byte being decoded, low nibble first
+    pub adpcm_byte: u8,
+    /// Noise generator state (channels 14 and 15)
+    pub noise: u16,
 }
 
 impl SoundChannel {
@@ -67,8 +124,31 @@ impl SoundChannel {
             sound_timer: 0,
             sound_pnt: 0,
             sound_len: 0,
+            sample: 0,
+            position: 0,
+            timer: 0,
+            adpcm_value: 0,
+            adpcm_index: 0,
+            adpcm_loop_value: 0,
+            adpcm_loop_index: 0,
+            adpcm_byte: 0,
+            noise: 0,
         }
     }
+
+    /// Output after volume and divider, before panning.
+    pub fn level(&self) -> i32 {
+        let cnt = &self.channel_cnt;
+        let shift = [0, 1, 2, 4][(cnt.divider & 0x3) as usize];
+        (self.sample as i32 * (cnt.volume & 0x7F) as i32 / 128) >> shift
+    }
+
+    /// Left and right output after volume, divider and panning.
+    pub fn output(&self) -> [i32; 2] {
+        let level = self.level();
+        let pan = (self.channel_cnt.panning & 0x7F) as i32;
+        [level * (128 - pan) / 128, level * pan / 128]
+    }
 }
 
 impl Default for SoundChannel {
@@ -82,13 +162,13 @@ impl Default for SoundChannel {
 pub struct SoundCntReg {
     /// Master volume (0-127)
     pub master_volume: u32,
-    /// Left output volume
+    /// Left output (0=mixer, 1=channel 1, 2=channel 3, 3=channels 1+3)
     pub left_output: u32,
-    /// Right output volume
+    /// Right output, like [`Self::left_output`]
     pub right_output: u32,
-    /// Mix channel 1 to output
+    /// Leave channel 1 out of the mixer
     pub output_ch1_mixer: bool,
-    /// Mix channel 3 to output
+    /// Leave channel 3 out of the mixer
     pub output_ch3_mixer: bool,
     /// Master audio enable
     pub master_enable: bool,
@@ -157,8 +237,12 @@ pub struct SndCapture {
     pub busy: bool,
     /// Destination address in memory
     pub destination: u32,
-    /// Capture length
+    /// Capture length, in words
     pub len: u16,
+    /// Bytes written since the start
+    pub position: u32,
+    /// Timer counter, clocked like the channel after the captured one
+    pub timer: u32,
 }
 
 impl SndCapture {
@@ -172,6 +256,8 @@ impl SndCapture {
             busy: false,
             destination: 0,
             len: 0,
+            position: 0,
+            timer: 0,
         }
     }
 
@@ -212,6 +298,15 @@ impl Default for SndCapture {
     }
 }
 
+/// One output sample of the SPU, see [`SPU::output`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpuOutput {
+    /// Left and right of each channel after volume and panning
+    pub channels: [[i16; 2]; 16],
+    /// Left and right mix after master volume
+    pub mix: [i16; 2],
+}
+
 /// Sound Processing Unit
 /// Manages 16 audio channels with mixing and PCM/ADPCM playback
 #[derive(Debug)]
@@ -229,6 +324,11 @@ pub struct SPU {
 
     /// Sound bias (sample offset)
     soundbias: u16,
+
+    /// Mixed samples not yet taken by [`SPU::get_samples`], after
+    /// `resampler`
+    samples: VecDeque<[i16; 2]>,
+    resampler: Resampler,
 }
 
 impl Default for SPU {
@@ -252,6 +352,8 @@ impl SPU {
             sndcap0: SndCapture::new(),
             sndcap1: SndCapture::new(),
             soundbias: 0,
+            samples: VecDeque::new(),
+            resampler: Resampler::new(),
         }
     }
 
@@ -266,94 +368,78 @@ impl SPU {
         self.soundbias = 0x200;
     }
 
+    /// Read SOUNDxCNT; the other channel registers are write-only.
+    pub fn read_channel_word(&self, address: u32) -> u32 {
+        let channel = &self.channels[((address >> 4) & 0xF) as usize];
+        match address & 0xC {
+            0 => channel.channel_cnt.get(),
+            _ => 0,
+        }
+    }
+
     /// Read from channel at address
     pub fn read_channel_byte(&self, address: u32) -> u8 {
-        let channel_idx = ((address >> 4) & 0xF) as usize;
-        let offset = (address & 0xF) as usize;
+        (self.read_channel_word(address) >> ((address & 3) * 8)) as u8
+    }
 
-        if channel_idx >= 16 {
-            return 0;
-        }
+    /// Read halfword from channel
+    pub fn read_channel_halfword(&self, address: u32) -> u16 {
+        (self.read_channel_word(address) >> ((address & 2) * 8)) as u16
+    }
 
-        match offset {
-            0..=3 => ((self.channels[channel_idx].channel_cnt.volume >> (offset * 8)) & 0xFF) as u8,
-            4..=7 => ((self.channels[channel_idx].sound_source >> ((offset - 4) * 8)) & 0xFF) as u8,
-            8..=9 => ((self.channels[channel_idx].sound_timer >> ((offset - 8) * 8)) & 0xFF) as u8,
-            10..=11 => ((self.channels[channel_idx].sound_pnt >> ((offset - 10) * 8)) & 0xFF) as u8,
-            12..=13 => ((self.channels[channel_idx].sound_len >> ((offset - 12) * 8)) & 0xFF) as u8,
-            _ => 0,
+    /// Write the bits of `mask` of the channel register word holding
+    /// `address`, with `value` already shifted into place.
+    fn write_channel(&mut self, address: u32, value: u32, mask: u32) {
+        let channel = &mut self.channels[((address >> 4) & 0xF) as usize];
+        let old = match address & 0xC {
+            0 => channel.channel_cnt.get(),
+            4 => channel.sound_source,
+            8 => channel.sound_timer as u32 | (channel.sound_pnt as u32) << 16,
+            _ => channel.sound_len,
+        };
+        let value = (old & !mask) | (value & mask);
+        match address & 0xC {
+            0 => {
+                let start = !channel.channel_cnt.busy && value & (1 << 31) != 0;
+                channel.channel_cnt.set(value);
+                if start {
+                    channel.start();
+                }
+            }
+            4 => channel.sound_source = value & 0x07FF_FFFC,
+            8 => {
+                channel.sound_timer = value as u16;
+                channel.sound_pnt = (value >> 16) as u16;
+            }
+            _ => channel.sound_len = value & 0x003F_FFFF,
         }
     }
 
     /// Write byte to channel at address
     pub fn write_channel_byte(&mut self, address: u32, byte: u8) {
-        let channel_idx = ((address >> 4) & 0xF) as usize;
-        let offset = (address & 0xF) as usize;
-
-        if channel_idx >= 16 {
-            return;
-        }
-
-        if let 0..=3 = offset {
-            let shift = (offset * 8) as u32;
-            self.channels[channel_idx].channel_cnt.volume &= !(0xFF << shift);
-            self.channels[channel_idx].channel_cnt.volume |= (byte as u32) << shift;
-        }
+        let shift = (address & 3) * 8;
+        self.write_channel(address, (byte as u32) << shift, 0xFF << shift);
     }
 
     /// Write halfword to channel
     pub fn write_channel_halfword(&mut self, address: u32, halfword: u16) {
-        let channel_idx = ((address >> 4) & 0xF) as usize;
-        let offset = (address & 0xF) as usize;
-
-        if channel_idx >= 16 {
-            return;
-        }
-
-        match offset {
-            0..=1 => {
-                self.channels[channel_idx].channel_cnt.volume = (halfword & 0x7F) as u32;
-            }
-            4..=5 => {
-                self.channels[channel_idx].sound_source &= 0xFFFF0000;
-                self.channels[channel_idx].sound_source |= halfword as u32;
-            }
-            8..=9 => {
-                self.channels[channel_idx].sound_timer = halfword;
-            }
-            10..=11 => {
-                self.channels[channel_idx].sound_pnt = halfword;
-            }
-            12..=13 => {
-                self.channels[channel_idx].sound_len = halfword;
-            }
-            _ => {}
-        }
+        let shift = (address & 2) * 8;
+        self.write_channel(address, (halfword as u32) << shift, 0xFFFF << shift);
     }
 
     /// Write word to channel
     pub fn write_channel_word(&mut self, address: u32, word: u32) {
-        let channel_idx = ((address >> 4) & 0xF) as usize;
+        self.write_channel(address, word, u32::MAX);
+    }
 
-        if channel_idx >= 16 {
-            return;
-        }
+    /// Write SNDCAPxDAD of capture `index`.
+    pub fn set_capture_destination(&mut self, index: usize, word: u32) {
+        self.capture_mut(index).destination = word & 0x07FF_FFFC;
+    }
 
-        match address & 0xF {
-            0 => {
-                self.channels[channel_idx].channel_cnt.volume = word & 0x7F;
-            }
-            4 => {
-                self.channels[channel_idx].sound_source = word;
-            }
-            8 => {
-                self.channels[channel_idx].sound_timer = (word & 0xFFFF) as u16;
-            }
-            12 => {
-                self.channels[channel_idx].sound_len = (word & 0xFFFF) as u16;
-            }
-            _ => {}
-        }
+    /// Write SNDCAPxLEN of capture `index`.
+    pub fn set_capture_len(&mut self, index: usize, halfword: u16) {
+        self.capture_mut(index).len = halfword;
     }
 
     /// Get SOUNDCNT register value
@@ -402,12 +488,64 @@ impl SPU {
 
     /// Set SNDCAP0 register
     pub fn set_sndcap0(&mut self, value: u8) {
-        self.sndcap0.set(value);
+        self.set_sndcap(0, value);
     }
 
     /// Set SNDCAP1 register
     pub fn set_sndcap1(&mut self, value: u8) {
-        self.sndcap1.set(value);
+        self.set_sndcap(1, value);
+    }
+
+    /// Setting bit 7 starts capture `index` over, its timer reloaded from
+    /// the channel it is clocked by.
+    fn set_sndcap(&mut self, index: usize, value: u8) {
+        let reload = self.channels[index * 2 + 1].sound_timer as u32;
+        let capture = self.capture_mut(index);
+        let start = !capture.busy && value & 0x80 != 0;
+        capture.set(value);
+        if start {
+            capture.position = 0;
+            capture.timer = reload;
+        }
+    }
+
+    /// Current output of each channel and of the mixer.
+    ///
+    /// SOUNDCNT can leave channels 1 and 3 out of the mixer and send them
+    /// straight to either side instead.
+    pub fn output(&self) -> SpuOutput {
+        let clamp = |value: i32| value.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        let mut output = SpuOutput::default();
+        let mut mix = [0i32; 2];
+        let mut outputs = [[0i32; 2]; 16];
+        for (index, channel) in self.channels.iter().enumerate() {
+            let [left, right] = channel.output();
+            outputs[index] = [left, right];
+            output.channels[index] = [clamp(left), clamp(right)];
+            let excluded = match index {
+                1 => self.soundcnt.output_ch1_mixer,
+                3 => self.soundcnt.output_ch3_mixer,
+                _ => false,
+            };
+            if !excluded {
+                mix[0] += left;
+                mix[1] += right;
+            }
+        }
+        if self.soundcnt.master_enable {
+            let volume = (self.soundcnt.master_volume & 0x7F) as i32;
+            let selects = [self.soundcnt.left_output, self.soundcnt.right_output];
+            for (side, select) in selects.into_iter().enumerate() {
+                let value = match select & 0x3 {
+                    0 => mix[side],
+                    1 => outputs[1][side],
+                    2 => outputs[3][side],
+                    _ => outputs[1][side] + outputs[3][side],
+                };
+                output.mix[side] = clamp(value * volume / 128);
+            }
+        }
+        output
     }
 
     /// Get channel reference
@@ -427,4 +565,20 @@ impl SPU {
             None
         }
     }
+
+    /// Capture unit `index` (0 or 1).
+    pub fn capture(&self, index: usize) -> &SndCapture {
+        match index {
+            0 => &self.sndcap0,
+            _ => &self.sndcap1,
+        }
+    }
+
+    /// Mutable capture unit `index` (0 or 1).
+    pub fn capture_mut(&mut self, index: usize) -> &mut SndCapture {
+        match index {
+            0 => &mut self.sndcap0,
+            _ => &mut self.sndcap1,
+        }
+    }
 }
@@ -0,0 +1,465 @@
+//! SPU playback
+//!
+//! [`SPU::step`] produces one output sample, [`CYCLES_PER_SAMPLE`] system
+//! cycles apart. In that time each channel timer counts 512 ticks at half
+//! the system clock, and each overflow of a timer past 0xFFFF reloads it
+//! from SOUNDxTMR and plays the next sample of the channel. Samples are
+//! fetched through [`SpuBus`], as are capture writes.
+//!
+//! After a start, PCM and ADPCM channels wait two samples before the first
+//! one is read, and ADPCM takes its initial state from a one-word header at
+//! the source address. Channels 8-13 play PSG square waves in the
+//! PSG format and channels 14-15 noise; the others stay silent in it.
+use crate::{SPU, SndCapture, SoundChannel};
+
+/// System cycles per output sample (~32728.5 Hz).
+pub const CYCLES_PER_SAMPLE: u64 = 1024;
+
+/// Output samples kept for [`SPU::get_samples`], one second's worth; older
+/// ones are dropped when nobody takes them.
+pub const OUTPUT_BUFFER_FRAMES: usize = 32768;
+
+/// Channel timer ticks per output sample.
+const TICKS_PER_SAMPLE: u32 = 512;
+
+const ADPCM_INDEX_TABLE: [i8; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+
+#[rustfmt::skip]
+const ADPCM_TABLE: [u16; 89] = [
+    0x0007, 0x0008, 0x0009, 0x000A, 0x000B, 0x000C, 0x000D, 0x000E,
+    0x0010, 0x0011, 0x0013, 0x0015, 0x0017, 0x0019, 0x001C, 0x001F,
+    0x0022, 0x0025, 0x0029, 0x002D, 0x0032, 0x0037, 0x003C, 0x0042,
+    0x0049, 0x0050, 0x0058, 0x0061, 0x006B, 0x0076, 0x0082, 0x008F,
+    0x009D, 0x00AD, 0x00BE, 0x00D1, 0x00E6, 0x00FD, 0x0117, 0x0133,
+    0x0151, 0x0173, 0x0198, 0x01C1, 0x01EE, 0x0220, 0x0256, 0x0292,
+    0x02D4, 0x031C, 0x036C, 0x03C3, 0x0424, 0x048E, 0x0502, 0x0583,
+    0x0610, 0x06AB, 0x0756, 0x0812, 0x08E0, 0x09C3, 0x0ABD, 0x0BD0,
+    0x0CFF, 0x0E4C, 0x0FBA, 0x114C, 0x1307, 0x14EE, 0x1706, 0x1954,
+    0x1BDC, 0x1EA5, 0x21B6, 0x2515, 0x28CA, 0x2CDF, 0x315B, 0x364B,
+    0x3BB9, 0x41B2, 0x4844, 0x4F7E, 0x5771, 0x602F, 0x69CE, 0x7462,
+    0x7FFF,
+];
+
+/// ARM7 memory as seen by the SPU.
+pub trait SpuBus {
+    fn read_byte(&mut self, address: u32) -> u8;
+    fn read_halfword(&mut self, address: u32) -> u16;
+    fn read_word(&mut self, address: u32) -> u32;
+    fn write_byte(&mut self, address: u32, byte: u8);
+    fn write_halfword(&mut self, address: u32, halfword: u16);
+}
+
+impl SoundChannel {
+    /// Restart playback, on SOUNDxCNT bit 31 going from 0 to 1.
+    pub fn start(&mut self) {
+        self.timer = self.sound_timer as u32;
+        self.position = match self.channel_cnt.format {
+            3 => -1,
+            _ => -3,
+        };
+        self.sample = 0;
+        self.noise = 0x7FFF;
+    }
+
+    /// Run the timer for one output sample of channel `index`.
+    fn clock(&mut self, index: usize, bus: &mut impl SpuBus) {
+        self.timer += TICKS_PER_SAMPLE;
+        while self.timer >= 0x10000 && self.channel_cnt.busy {
+            self.timer = self.timer - 0x10000 + self.sound_timer as u32;
+            match (self.channel_cnt.format, index) {
+                (0, _) => self.next_pcm(bus, 1),
+                (1, _) => self.next_pcm(bus, 2),
+                (2, _) => self.next_adpcm(bus),
+                (_, 8..=13) => self.next_psg(),
+                (_, 14..=15) => self.next_noise(),
+                _ => self.sample = 0,
+            }
+        }
+    }
+
+    /// Loop start and end, in bytes from the source address.
+    const fn bounds(&self) -> (u32, u32) {
+        let loop_start = self.sound_pnt as u32 * 4;
+        (loop_start, loop_start + self.sound_len * 4)
+    }
+
+    /// End of the sound: back to the loop start, or stop. Returns whether
+    /// playback goes on.
+    fn end_reached(&mut self, loop_position: i32) -> bool {
+        match self.channel_cnt.repeat_mode {
+            1 => {
+                self.position = loop_position;
+                true
+            }
+            2 => {
+                self.channel_cnt.busy = false;
+                if !self.channel_cnt.hold_sample {
+                    self.sample = 0;
+                }
+                false
+            }
+            // Manual: plays on past the end
+            _ => true,
+        }
+    }
+
+    /// Next PCM8 or PCM16 sample, `size` bytes each.
+    fn next_pcm(&mut self, bus: &mut impl SpuBus, size: u32) {
+        self.position += 1;
+        if self.position < 0 {
+            return;
+        }
+        let (loop_start, end) = self.bounds();
+        if self.position as u32 * size >= end && !self.end_reached((loop_start / size) as i32) {
+            return;
+        }
+        let address = self.sound_source + self.position as u32 * size;
+        self.sample = match size {
+            1 => (bus.read_byte(address) as i8 as i16) << 8,
+            _ => bus.read_halfword(address) as i16,
+        };
+    }
+
+    /// Next IMA-ADPCM nibble. The position counts nibbles, the first eight
+    /// being the header.
+    fn next_adpcm(&mut self, bus: &mut impl SpuBus) {
+        self.position += 1;
+        if self.position < 8 {
+            if self.position == 0 {
+                let header = bus.read_word(self.sound_source);
+                self.adpcm_value = header as i16;
+                self.adpcm_index = ((header >> 16) & 0x7F).min(88) as u8;
+            }
+            return;
+        }
+
+        let (loop_start, end) = self.bounds();
+        let loop_position = (loop_start * 2) as i32;
+        if self.position as u32 >= end * 2 {
+            if !self.end_reached(loop_position) {
+                return;
+            }
+            if self.channel_cnt.repeat_mode == 1 {
+                self.adpcm_value = self.adpcm_loop_value;
+                self.adpcm_index = self.adpcm_loop_index;
+            }
+        }
+        if self.position == loop_position {
+            self.adpcm_loop_value = self.adpcm_value;
+            self.adpcm_loop_index = self.adpcm_index;
+        }
+
+        let address = self.sound_source + (self.position as u32 >> 1);
+        let nibble = match self.position & 1 {
+            0 => {
+                self.adpcm_byte = bus.read_byte(address);
+                self.adpcm_byte & 0xF
+            }
+            _ => self.adpcm_byte >> 4,
+        };
+
+        let step = ADPCM_TABLE[self.adpcm_index as usize] as i32;
+        let mut diff = step >> 3;
+        if nibble & 1 != 0 {
+            diff += step >> 2;
+        }
+        if nibble & 2 != 0 {
+            diff += step >> 1;
+        }
+        if nibble & 4 != 0 {
+            diff += step;
+        }
+        let value = match nibble & 8 {
+            0 => (self.adpcm_value as i32 + diff).min(0x7FFF),
+            _ => (self.adpcm_value as i32 - diff).max(-0x7FFF),
+        };
+        self.adpcm_value = value as i16;
+        self.adpcm_index = (self.adpcm_index as i32
+            + ADPCM_INDEX_TABLE[(nibble & 7) as usize] as i32)
+            .clamp(0, 88) as u8;
+        self.sample = self.adpcm_value;
+    }
+
+    /// Next step of the square wave, high for duty + 1 of every 8 steps
+    /// (never with duty 7).
+    fn next_psg(&mut self) {
+        self.position += 1;
+        let duty = self.channel_cnt.wave_duty & 0x7;
+        let high = duty != 7 && (self.position & 7) as u32 >= 7 - duty;
+        self.sample = match high {
+            true => 0x7FFF,
+            false => -0x7FFF,
+        };
+    }
+
+    /// Next bit of the 15-bit noise generator.
+    fn next_noise(&mut self) {
+        let carry = self.noise & 1 != 0;
+        self.noise >>= 1;
+        self.sample = match carry {
+            true => {
+                self.noise ^= 0x6000;
+                -0x7FFF
+            }
+            false => 0x7FFF,
+        };
+    }
+}
+
+impl SndCapture {
+    /// Run the timer for one output sample, storing `sample` on every
+    /// overflow.
+    fn clock(&mut self, reload: u16, sample: i16, bus: &mut impl SpuBus) {
+        self.timer += TICKS_PER_SAMPLE;
+        while self.timer >= 0x10000 && self.busy {
+            self.timer = self.timer - 0x10000 + reload as u32;
+            let address = self.destination + self.position;
+            match self.capture_pcm8 {
+                true => {
+                    bus.write_byte(address, (sample >> 8) as u8);
+                    self.position += 1;
+                }
+                false => {
+                    bus.write_halfword(address, sample as u16);
+                    self.position += 2;
+                }
+            }
+            // A length of 0 captures one word
+            if self.position >= (self.len as u32).max(1) * 4 {
+                self.position = 0;
+                self.busy = !self.one_shot;
+            }
+        }
+    }
+}
+
+impl SPU {
+    /// Produce the next output sample, reading sound data and writing
+    /// captured samples through `bus`.
+    pub fn step(&mut self, bus: &mut impl SpuBus) {
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            if channel.channel_cnt.busy {
+                channel.clock(index, bus);
+            }
+        }
+
+        let output = self.output();
+        // Capture 0 records the left mixer or channel 0, capture 1 the
+        // right mixer or channel 2, clocked by the timer of channel 1 and 3
+        for index in 0..2 {
+            let capture = self.capture(index);
+            let sample = match capture.capture_source {
+                false => output.mix[index],
+                true => {
+                    let level = self.channels[index * 2].level();
+                    level.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+                }
+            };
+            let reload = self.channels[index * 2 + 1].sound_timer;
+            self.capture_mut(index).clock(reload, sample, bus);
+        }
+
+        self.queue(output.mix);
+    }
+
+    /// Resample `mix` into the output buffer.
+    fn queue(&mut self, mix: [i16; 2]) {
+        let samples = &mut self.samples;
+        self.resampler.push(mix, |sample| {
+            if samples.len() == OUTPUT_BUFFER_FRAMES {
+                samples.pop_front();
+            }
+            samples.push_back(sample);
+        });
+    }
+
+    /// Output samples per mixed sample from now on, `1.0` to keep the mix
+    /// rate. See [`Resampler`](crate::Resampler).
+    pub fn set_output_ratio(&mut self, ratio: f64) {
+        self.resampler.set_ratio(ratio);
+    }
+
+    /// Queue `count` mixed samples of silence without running the
+    /// channels, for frames that are not emulated.
+    pub fn push_silence(&mut self, count: usize) {
+        for _ in 0..count {
+            self.queue([0; 2]);
+        }
+    }
+
+    /// Move buffered output into `buffer` as interleaved left/right pairs,
+    /// oldest first. Returns how many values were written, an even number.
+    pub fn get_samples(&mut self, buffer: &mut [i16]) -> usize {
+        let mut written = 0;
+        for pair in buffer.chunks_exact_mut(2) {
+            let Some(sample) = self.samples.pop_front() else {
+                break;
+            };
+            pair.copy_from_slice(&sample);
+            written += 2;
+        }
+        written
+    }
+
+    /// Hand all buffered output to `sink` as left/right pairs, oldest
+    /// first, in up to two slices.
+    pub fn drain_samples(&mut self, mut sink: impl FnMut(&[[i16; 2]])) {
+        let (first, second) = self.samples.as_slices();
+        for slice in [first, second] {
+            if !slice.is_empty() {
+                sink(slice);
+            }
+        }
+        self.samples.clear();
+    }
+
+    /// Output samples waiting for [`Self::get_samples`].
+    pub fn buffered_samples(&self) -> usize {
+        self.samples.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 64KB of RAM at address 0.
+    struct Ram(Vec<u8>);
+
+    impl SpuBus for Ram {
+        fn read_byte(&mut self, address: u32) -> u8 {
+            self.0[address as usize & 0xFFFF]
+        }
+
+        fn read_halfword(&mut self, address: u32) -> u16 {
+            u16::from_le_bytes([self.read_byte(address), self.read_byte(address + 1)])
+        }
+
+        fn read_word(&mut self, address: u32) -> u32 {
+            self.read_halfword(address) as u32 | (self.read_halfword(address + 2) as u32) << 16
+        }
+
+        fn write_byte(&mut self, address: u32, byte: u8) {
+            self.0[address as usize & 0xFFFF] = byte;
+        }
+
+        fn write_halfword(&mut self, address: u32, halfword: u16) {
+            let [low, high] = halfword.to_le_bytes();
+            self.write_byte(address, low);
+            self.write_byte(address + 1, high);
+        }
+    }
+
+    /// Start channel `index` with SOUNDxCNT `cnt` at one sample per output
+    /// sample (SOUNDxTMR = -512).
+    fn start(spu: &mut SPU, index: u32, cnt: u32, source: u32, len: u32) {
+        let base = 0x0400_0400 + index * 0x10;
+        spu.write_channel_word(base + 4, source);
+        spu.write_channel_halfword(base + 8, (0x10000 - TICKS_PER_SAMPLE) as u16);
+        spu.write_channel_word(base + 0xC, len);
+        spu.write_channel_word(base, cnt | 1 << 31);
+    }
+
+    /// Step once and return the sample of channel `index`.
+    fn play(spu: &mut SPU, ram: &mut Ram, index: usize) -> i16 {
+        spu.step(ram);
+        spu.get_channel(index).unwrap().sample
+    }
+
+    #[test]
+    fn test_pcm() {
+        let mut spu = SPU::new();
+        let mut ram = Ram(vec![0; 0x10000]);
+        ram.0[0x100..0x108].copy_from_slice(&[0x10, 0x20, 0x30, 0x40, 0x00, 0x40, 0x34, 0x12]);
+        // PCM8, one-shot of one word
+        start(&mut spu, 0, 0x7F | 2 << 27, 0x100, 1);
+        assert_eq!(spu.read_channel_byte(0x0400_0403), 0x90);
+        let played: Vec<i16> = (0..7).map(|_| play(&mut spu, &mut ram, 0)).collect();
+        assert_eq!(played, [0, 0, 0x1000, 0x2000, 0x3000, 0x4000, 0]);
+        assert_eq!(spu.read_channel_byte(0x0400_0403), 0x10);
+
+        // PCM16, looping over the second word
+        spu.write_channel_halfword(0x0400_040A, 1);
+        start(&mut spu, 0, 0x7F | 1 << 27 | 1 << 29, 0x100, 1);
+        let played: Vec<i16> = (0..8).map(|_| play(&mut spu, &mut ram, 0)).collect();
+        assert_eq!(
+            played[2..],
+            [0x2010, 0x4030, 0x4000, 0x1234, 0x4000, 0x1234]
+        );
+        assert_eq!(spu.read_channel_word(0x0400_0408), 0);
+    }
+
+    #[test]
+    fn test_adpcm() {
+        let mut spu = SPU::new();
+        let mut ram = Ram(vec![0; 0x10000]);
+        // Header: value 0, index 0; then nibbles 7 and 8 + 7
+        ram.0[0x200..0x205].copy_from_slice(&[0, 0, 0, 0, 0xF7]);
+        start(&mut spu, 0, 2 << 29 | 2 << 27, 0x200, 2);
+        for _ in 0..10 {
+            play(&mut spu, &mut ram, 0);
+        }
+        // Step 7: 7/4 + 7/2 + 7
+        assert_eq!(play(&mut spu, &mut ram, 0), 11);
+        // Step 0x10 at index 8: 11 - (2 + 4 + 8 + 16)
+        assert_eq!(play(&mut spu, &mut ram, 0), -19);
+        assert_eq!(spu.get_channel(0).unwrap().adpcm_index, 16);
+    }
+
+    #[test]
+    fn test_psg_and_noise() {
+        let mut spu = SPU::new();
+        let mut ram = Ram(vec![0; 0x10000]);
+        // Duty 3 (50%) on channel 8; channel 0 has no PSG
+        start(&mut spu, 8, 3 << 24 | 3 << 29, 0, 0);
+        start(&mut spu, 0, 3 << 24 | 3 << 29, 0, 0);
+        let wave: Vec<bool> = (0..8).map(|_| play(&mut spu, &mut ram, 8) > 0).collect();
+        assert_eq!(wave, [false, false, false, false, true, true, true, true]);
+        assert_eq!(spu.get_channel(0).unwrap().sample, 0);
+
+        start(&mut spu, 14, 3 << 29, 0, 0);
+        assert_eq!(play(&mut spu, &mut ram, 14), -0x7FFF);
+        assert_eq!(play(&mut spu, &mut ram, 14), -0x7FFF);
+        assert_eq!(spu.get_channel(14).unwrap().noise, 0x4FFF);
+    }
+
+    #[test]
+    fn test_output_buffer() {
+        let mut spu = SPU::new();
+        let mut ram = Ram(vec![0; 0x10000]);
+        ram.0[0x100] = 0x40;
+        // Full master volume; channel 1 hard right, sent to the left
+        // output and left out of the mixer
+        spu.set_soundcnt(0x907F | 1 << 8);
+        start(&mut spu, 1, 0x7F | 127 << 16, 0x100, 1);
+        for _ in 0..3 {
+            spu.step(&mut ram);
+        }
+
+        let mut buffer = [0; 5];
+        assert_eq!(spu.get_samples(&mut buffer), 4);
+        assert_eq!(spu.buffered_samples(), 1);
+        assert_eq!(spu.get_samples(&mut buffer), 2);
+        // 0x4000 at 127/128 volume, panned 1/128 left, at 127/128 master
+        assert_eq!(buffer[..2], [0x007E, 0]);
+    }
+
+    #[test]
+    fn test_capture() {
+        let mut spu = SPU::new();
+        let mut ram = Ram(vec![0; 0x10000]);
+        ram.0[0x100..0x104].copy_from_slice(&[0x40; 4]);
+        // Channel 0 as the capture source, channel 1 as its clock
+        start(&mut spu, 0, 0x7F | 1 << 27, 0x100, 1);
+        spu.write_channel_halfword(0x0400_0418, (0x10000 - TICKS_PER_SAMPLE) as u16);
+        spu.set_capture_destination(0, 0x800);
+        spu.set_capture_len(0, 1);
+        // Channel source, one-shot, PCM8
+        spu.set_sndcap0(0x80 | 0x08 | 0x04 | 0x02);
+        for _ in 0..8 {
+            spu.step(&mut ram);
+        }
+        assert_eq!(ram.0[0x800..0x805], [0, 0, 0x3F, 0x3F, 0]);
+        assert!(!spu.capture(0).busy);
+    }
+}
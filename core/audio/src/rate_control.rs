@@ -0,0 +1,167 @@
+//! Dynamic audio rate control
+//!
+//! The emulated DS runs at ~59.83 fps while the host display and audio
+//! device run on their own clocks, so a fixed resampling ratio slowly drains
+//! or overfills the output buffer. The controller nudges the ratio by at most
+//! [`MAX_RATE_DELTA`] depending on how full the host buffer is, which is small
+//! enough to be inaudible but keeps the buffer around half full.
+//!
+//! The frontend's audio callback publishes the buffer fill through
+//! [`AudioSyncStats`] (lock-free, so it is safe from a realtime thread) and
+//! the emulator thread updates the ratio once per frame.
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// Largest resampling adjustment, ±0.5%.
+pub const MAX_RATE_DELTA: f64 = 0.005;
+
+/// Audio/video sync state shared between the audio callback and the emulator.
+#[derive(Debug, Default)]
+pub struct AudioSyncStats {
+    /// Samples (frames) queued in the host buffer
+    buffered: AtomicU32,
+    /// Host buffer capacity in samples (frames)
+    capacity: AtomicU32,
+    /// Times the host buffer ran dry
+    underruns: AtomicU64,
+    /// Current ratio adjustment, `f32` bits
+    adjustment: AtomicU32,
+}
+
+impl AudioSyncStats {
+    /// Publish the host buffer fill. Call from the audio callback.
+    pub fn report_fill(&self, buffered: u32, capacity: u32) {
+        self.buffered.store(buffered, Ordering::Relaxed);
+        self.capacity.store(capacity, Ordering::Relaxed);
+    }
+
+    /// Count a buffer underrun. Call from the audio callback.
+    pub fn report_underrun(&self) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Host buffer fill level in `0.0..=1.0`; `0.5` if nothing was reported yet.
+    pub fn fill(&self) -> f64 {
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        if capacity == 0 {
+            return 0.5;
+        }
+        (self.buffered.load(Ordering::Relaxed) as f64 / capacity as f64).clamp(0.0, 1.0)
+    }
+
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+
+    /// Current ratio adjustment, e.g. `0.002` for +0.2%.
+    pub fn adjustment(&self) -> f64 {
+        f32::from_bits(self.adjustment.load(Ordering::Relaxed)) as f64
+    }
+}
+
+/// Per-frame resampling ratio controller.
+#[derive(Debug, Clone)]
+pub struct DynamicRateControl {
+    enabled: bool,
+    stats: Arc<AudioSyncStats>,
+    /// Smoothed adjustment applied to the ratio
+    adjustment: f64,
+}
+
+impl Default for DynamicRateControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DynamicRateControl {
+    /// Weight of the newest measurement; smooths out callback jitter.
+    const SMOOTHING: f64 = 0.1;
+
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            stats: Arc::new(AudioSyncStats::default()),
+            adjustment: 0.0,
+        }
+    }
+
+    /// Handle for the frontend's audio thread.
+    pub fn stats(&self) -> Arc<AudioSyncStats> {
+        Arc::clone(&self.stats)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enable or disable control; disabled means a fixed ratio.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.set_adjustment(0.0);
+        }
+    }
+
+    fn set_adjustment(&mut self, adjustment: f64) {
+        self.adjustment = adjustment;
+        self.stats
+            .adjustment
+            .store((adjustment as f32).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Recompute the adjustment from the last reported fill. Call once per frame.
+    ///
+    /// An emptier buffer yields a positive adjustment (produce more samples),
+    /// a fuller one a negative adjustment.
+    pub fn update(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        let target = (1.0 - 2.0 * self.stats.fill()) * MAX_RATE_DELTA;
+        let adjustment = self.adjustment + (target - self.adjustment) * Self::SMOOTHING;
+        self.set_adjustment(adjustment.clamp(-MAX_RATE_DELTA, MAX_RATE_DELTA));
+    }
+
+    /// Current adjustment, see [`AudioSyncStats::adjustment`].
+    pub fn adjustment(&self) -> f64 {
+        self.adjustment
+    }
+
+    /// Resampling ratio (output samples per input sample) from `input_rate`
+    /// to `output_rate`, including the adjustment.
+    pub fn ratio(&self, input_rate: f64, output_rate: f64) -> f64 {
+        output_rate / input_rate * (1.0 + self.adjustment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adjustment_follows_fill() {
+        let mut drc = DynamicRateControl::new();
+        let stats = drc.stats();
+
+        // Nearly empty buffer: speed up up to +0.5%
+        stats.report_fill(0, 4096);
+        for _ in 0..200 {
+            drc.update();
+        }
+        assert!((drc.adjustment() - MAX_RATE_DELTA).abs() < 1e-4);
+        assert!((stats.adjustment() - drc.adjustment()).abs() < 1e-6);
+        assert!(drc.ratio(32768.0, 48000.0) > 48000.0 / 32768.0);
+
+        // Full buffer: slow down, never past -0.5%
+        stats.report_fill(4096, 4096);
+        for _ in 0..200 {
+            drc.update();
+        }
+        assert!(drc.adjustment() >= -MAX_RATE_DELTA);
+        assert!(drc.adjustment() < -MAX_RATE_DELTA + 1e-4);
+
+        drc.set_enabled(false);
+        assert_eq!(stats.adjustment(), 0.0);
+    }
+}
@@ -0,0 +1,98 @@
+//! Output resampling
+//!
+//! The SPU mixes at ~32728.5 Hz while hosts play at 44.1 or 48 kHz, and
+//! [`DynamicRateControl`](crate::DynamicRateControl) nudges the ratio every
+//! frame. [`Resampler`] interpolates linearly between neighbouring samples
+//! and carries its position across calls, so a ratio change never clicks.
+//! At a ratio of exactly 1 every sample passes through unchanged.
+
+/// Stereo resampler with a ratio that may change between samples.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    /// Output samples per input sample
+    ratio: f64,
+    /// Position of the next output sample, in input samples after `last`
+    position: f64,
+    last: [i16; 2],
+}
+
+impl Default for Resampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resampler {
+    pub const fn new() -> Self {
+        Self {
+            ratio: 1.0,
+            position: 1.0,
+            last: [0; 2],
+        }
+    }
+
+    pub const fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    /// Output samples per input sample from the next one on. Ignored unless
+    /// positive and finite.
+    pub fn set_ratio(&mut self, ratio: f64) {
+        if ratio.is_finite() && ratio > 0.0 {
+            self.ratio = ratio;
+        }
+    }
+
+    /// Feed one input sample, handing the output samples that fall up to it
+    /// to `emit`.
+    pub fn push(&mut self, sample: [i16; 2], mut emit: impl FnMut([i16; 2])) {
+        let step = self.ratio.recip();
+        while self.position <= 1.0 {
+            let t = self.position;
+            emit([0, 1].map(|i| {
+                let (a, b) = (self.last[i] as f64, sample[i] as f64);
+                (a + (b - a) * t).round() as i16
+            }));
+            self.position += step;
+        }
+        self.position -= 1.0;
+        self.last = sample;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(resampler: &mut Resampler, input: impl IntoIterator<Item = [i16; 2]>) -> Vec<[i16; 2]> {
+        let mut output = Vec::new();
+        for sample in input {
+            resampler.push(sample, |sample| output.push(sample));
+        }
+        output
+    }
+
+    #[test]
+    fn test_resampled_sample_count() {
+        let mut resampler = Resampler::new();
+        let input: Vec<_> = (0..1000).map(|i| [i, -i]).collect();
+        assert_eq!(run(&mut resampler, input.iter().copied()), input);
+
+        // One second of SPU output to 48 kHz, then 0.5% faster
+        resampler.set_ratio(48000.0 / 32768.0);
+        let output = run(&mut resampler, (0..32768).map(|_| [0x100, 0]));
+        assert!(output.len().abs_diff(48000) <= 1, "{}", output.len());
+        resampler.set_ratio(48000.0 / 32768.0 * 1.005);
+        let output = run(&mut resampler, (0..32768).map(|_| [0x100, 0]));
+        assert!(output.len().abs_diff(48240) <= 1, "{}", output.len());
+        assert!(output.iter().all(|&sample| sample == [0x100, 0]));
+
+        // Halfway between two samples
+        let mut resampler = Resampler::new();
+        resampler.set_ratio(2.0);
+        assert_eq!(
+            run(&mut resampler, [[100, -100], [200, 0]]),
+            [[100, -100], [150, -50], [200, 0]]
+        );
+    }
+}
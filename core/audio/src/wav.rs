@@ -0,0 +1,109 @@
+//! WAV output for audio dumps
+//!
+//! 16-bit PCM only. The header is written up front with empty sizes and
+//! patched by [`WavWriter::finish`], so a dump cut short by a crash still
+//! holds its samples, just with a header most tools ignore or repair.
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+const HEADER_SIZE: u32 = 44;
+
+/// Streaming 16-bit PCM WAV writer.
+#[derive(Debug)]
+pub struct WavWriter<W: Write + Seek> {
+    inner: W,
+    channels: u16,
+    /// Sample frames written, one sample per channel each
+    frames: u32,
+}
+
+impl WavWriter<BufWriter<File>> {
+    /// Create the file at `path`, replacing an existing one.
+    pub fn create(path: &Path, channels: u16, sample_rate: u32) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?), channels, sample_rate)
+    }
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut inner: W, channels: u16, sample_rate: u32) -> io::Result<Self> {
+        let block_align = channels * 2;
+        let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(HEADER_SIZE - 8).to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        // PCM
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&channels.to_le_bytes());
+        header.extend_from_slice(&sample_rate.to_le_bytes());
+        header.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&16u16.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&0u32.to_le_bytes());
+        inner.write_all(&header)?;
+        Ok(Self {
+            inner,
+            channels,
+            frames: 0,
+        })
+    }
+
+    /// Append one sample frame, one sample per channel.
+    ///
+    /// # Panics
+    /// If `samples` does not hold exactly one sample per channel.
+    pub fn write_frame(&mut self, samples: &[i16]) -> io::Result<()> {
+        assert_eq!(samples.len(), self.channels as usize);
+        for sample in samples {
+            self.inner.write_all(&sample.to_le_bytes())?;
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Sample frames written so far.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Patch the sizes into the header and flush.
+    pub fn finish(mut self) -> io::Result<W> {
+        let data_size = self.frames * self.channels as u32 * 2;
+        self.inner.seek(SeekFrom::Start(4))?;
+        self.inner
+            .write_all(&(HEADER_SIZE - 8 + data_size).to_le_bytes())?;
+        self.inner.seek(SeekFrom::Start(HEADER_SIZE as u64 - 4))?;
+        self.inner.write_all(&data_size.to_le_bytes())?;
+        self.inner.seek(SeekFrom::End(0))?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_wav_header() {
+        let mut wav = WavWriter::new(Cursor::new(Vec::new()), 2, 32768).unwrap();
+        wav.write_frame(&[1, -1]).unwrap();
+        wav.write_frame(&[0x1234, 0]).unwrap();
+        let data = wav.finish().unwrap().into_inner();
+
+        assert_eq!(data.len(), 44 + 8);
+        assert_eq!(&data[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(data[4..8].try_into().unwrap()), 36 + 8);
+        assert_eq!(u16::from_le_bytes([data[22], data[23]]), 2);
+        assert_eq!(u32::from_le_bytes(data[24..28].try_into().unwrap()), 32768);
+        assert_eq!(
+            u32::from_le_bytes(data[28..32].try_into().unwrap()),
+            32768 * 4
+        );
+        assert_eq!(u32::from_le_bytes(data[40..44].try_into().unwrap()), 8);
+        assert_eq!(&data[44..], &[1, 0, 0xFF, 0xFF, 0x34, 0x12, 0, 0]);
+    }
+}
@@ -0,0 +1,239 @@
+//! Register structs with named fields and generated packing.
+//!
+//! [`bitfield!`] declares a plain struct (fields stay public and are used
+//! directly by the emulator) plus `get()`/`set()` to pack and unpack the raw
+//! register value, and a `MASK` of the bits backed by a field:
+//!
+//! ```
+//! lunaris_ds_bitfield::bitfield! {
+//!     /// DISPSTAT
+//!     #[derive(Debug, Default, Clone, Copy)]
+//!     pub struct DispStat(u16) {
+//!         pub is_vblank: bool [0],
+//!         pub irq_on_vblank: bool [3],
+//!         /// Bit 8 of the line lives in bit 7
+//!         pub vcounter: u16 [8..16, 7],
+//!     }
+//! }
+//!
+//! let mut stat = DispStat::default();
+//! stat.set(0x0188);
+//! assert!(stat.irq_on_vblank);
+//! assert_eq!(stat.vcounter, 0x101);
+//! assert_eq!(stat.get(), 0x0188);
+//! assert_eq!(DispStat::MASK, 0xFF89);
+//! ```
+//!
+//! Bits are given as `N` (one bit) or `LO..HI` (bits `LO` to `HI - 1`);
+//! several comma separated parts are concatenated, lowest part first.
+//! Field types implement [`Field`]: `bool`, unsigned and `i32` integers, and
+//! `[bool; N]` for a run of flags.
+
+/// Value of a register field.
+pub trait Field: Copy {
+    /// Convert from the field's bits, right-aligned.
+    fn from_bits(bits: u64) -> Self;
+    /// Convert to right-aligned bits; bits beyond the field width are dropped.
+    fn into_bits(self) -> u64;
+}
+
+impl Field for bool {
+    fn from_bits(bits: u64) -> Self {
+        bits != 0
+    }
+
+    fn into_bits(self) -> u64 {
+        u64::from(self)
+    }
+}
+
+macro_rules! impl_field_int {
+    ($($ty:ty),*) => {
+        $(
+            impl Field for $ty {
+                #[allow(clippy::cast_possible_truncation)]
+                fn from_bits(bits: u64) -> Self {
+                    bits as Self
+                }
+
+                #[allow(clippy::cast_sign_loss)]
+                fn into_bits(self) -> u64 {
+                    self as u64
+                }
+            }
+        )*
+    };
+}
+
+impl_field_int!(u8, u16, u32, u64, i32);
+
+impl<const N: usize> Field for [bool; N] {
+    fn from_bits(bits: u64) -> Self {
+        core::array::from_fn(|i| bits & (1 << i) != 0)
+    }
+
+    fn into_bits(self) -> u64 {
+        self.iter()
+            .enumerate()
+            .fold(0, |bits, (i, &flag)| bits | (u64::from(flag) << i))
+    }
+}
+
+/// Mask of `lo..hi`.
+const fn part_mask(lo: u32, hi: u32) -> u64 {
+    match hi - lo {
+        64.. => u64::MAX,
+        width => ((1 << width) - 1) << lo,
+    }
+}
+
+/// Gather the bits of `parts` (`(lo, hi)` ranges, lowest first) from `raw`.
+#[doc(hidden)]
+pub const fn extract(raw: u64, parts: &[(u32, u32)]) -> u64 {
+    let mut value = 0;
+    let mut shift = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        let (lo, hi) = parts[i];
+        value |= ((raw & part_mask(lo, hi)) >> lo) << shift;
+        shift += hi - lo;
+        i += 1;
+    }
+    value
+}
+
+/// Scatter `value` into the bits of `parts` of `raw`.
+#[doc(hidden)]
+pub const fn insert(mut raw: u64, parts: &[(u32, u32)], value: u64) -> u64 {
+    let mut shift = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        let (lo, hi) = parts[i];
+        let mask = part_mask(lo, hi);
+        raw = (raw & !mask) | (((value >> shift) << lo) & mask);
+        shift += hi - lo;
+        i += 1;
+    }
+    raw
+}
+
+/// Bits covered by `parts`.
+#[doc(hidden)]
+pub const fn mask(parts: &[(u32, u32)]) -> u64 {
+    let mut mask = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        mask |= part_mask(parts[i].0, parts[i].1);
+        i += 1;
+    }
+    mask
+}
+
+/// Declare a register struct, see the [crate docs](crate).
+#[macro_export]
+macro_rules! bitfield {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident($raw:ty) {
+            $(
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident : $field_ty:ty [$($bits:tt)+]
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $field_ty,
+            )*
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            /// Bits of the register backed by a field.
+            pub const MASK: $raw = {
+                let mut mask = 0_u64;
+                $( mask |= $crate::mask(&$crate::bitfield!(@parts [] $($bits)+)); )*
+                mask as $raw
+            };
+
+            /// Register value packed from the fields.
+            pub fn get(&self) -> $raw {
+                let mut raw = 0_u64;
+                $(
+                    raw = $crate::insert(
+                        raw,
+                        &$crate::bitfield!(@parts [] $($bits)+),
+                        $crate::Field::into_bits(self.$field),
+                    );
+                )*
+                raw as $raw
+            }
+
+            /// Unpack a register value into the fields. Bits outside
+            /// [`Self::MASK`] are ignored.
+            pub fn set(&mut self, value: $raw) {
+                let raw = value as u64;
+                $(
+                    self.$field = $crate::Field::from_bits(
+                        $crate::extract(raw, &$crate::bitfield!(@parts [] $($bits)+)),
+                    );
+                )*
+            }
+        }
+    };
+
+    // Bit specs to `[(lo, hi), ...]`
+    (@parts [$($acc:tt)*] $lo:literal .. $hi:literal, $($rest:tt)+) => {
+        $crate::bitfield!(@parts [$($acc)* ($lo, $hi),] $($rest)+)
+    };
+    (@parts [$($acc:tt)*] $lo:literal .. $hi:literal) => {
+        [$($acc)* ($lo, $hi)]
+    };
+    (@parts [$($acc:tt)*] $bit:literal, $($rest:tt)+) => {
+        $crate::bitfield!(@parts [$($acc)* ($bit, $bit + 1),] $($rest)+)
+    };
+    (@parts [$($acc:tt)*] $bit:literal) => {
+        [$($acc)* ($bit, $bit + 1)]
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    bitfield! {
+        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+        struct Test(u32) {
+            flag: bool [0],
+            mode: i32 [1..4],
+            flags: [bool; 4] [8..12],
+            split: u16 [16..24, 4],
+            top: bool [31],
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut reg = Test::default();
+        reg.set(0x8150_0A0B);
+        assert_eq!(
+            reg,
+            Test {
+                flag: true,
+                mode: 5,
+                flags: [false, true, false, true],
+                split: 0x50,
+                top: true,
+            }
+        );
+        assert_eq!(reg.get(), 0x8050_0A0B);
+        assert_eq!(Test::MASK, 0x80FF_0F1F);
+
+        reg.split = 0x1FF;
+        assert_eq!(reg.get() & 0x00FF_0010, 0x00FF_0010);
+        // Out of range values are truncated to the field
+        reg.mode = 0x7F;
+        assert_eq!((reg.get() >> 1) & 0x7, 0x7);
+        assert!(reg.flag);
+    }
+}
@@ -0,0 +1,212 @@
+//! Command line parsing
+//!
+//! The option set is small, so it is parsed by hand instead of pulling in an
+//! argument parser.
+use std::path::PathBuf;
+
+use lunaris_ds_emu::debug::WatchExpr;
+use lunaris_ds_emu::{AccuracyPreset, CpuType};
+use snafu::ResultExt as _;
+
+use crate::error::{CliError, InvalidExprSnafu};
+
+pub const USAGE: &str = "\
+Usage: lunaris-cli [OPTIONS] <ROM>
+
+Options:
+  -n, --frames <N>       Frames to run, the limit when --until is given [default: 60]
+      --until <EXPR>     Stop once the watch expression is non-zero, checked after each frame
+      --cpu <arm9|arm7>  Address space and registers --until reads [default: arm9]
+      --screenshot <PNG> Save both screens (upper above lower) after the run
+      --summary <FILE>   Write the CPU registers and counters as text
+      --ram <FILE>       Dump main RAM
+      --trace <JSON>     Record the last frame as a Chrome trace
+      --coverage <FILE>  Write the executed code ranges of both CPUs as text
+      --wav <FILE>       Record the audio mix of the run as WAV
+      --stems            With --wav, also record each SPU channel next to it
+      --accuracy <NAME>  fast, balanced or accurate [default: balanced]
+      --sync <CYCLES>    Longest slice one CPU runs before the other, overriding --accuracy
+  -h, --help             Print this help
+
+Exit status: 0 when done, 1 when --until never held, 2 on errors";
+
+const DEFAULT_FRAMES: u32 = 60;
+
+/// Parsed command line.
+#[derive(Debug)]
+pub struct Args {
+    pub rom: PathBuf,
+    pub frames: u32,
+    pub until: Option<WatchExpr>,
+    pub cpu: CpuType,
+    pub screenshot: Option<PathBuf>,
+    pub summary: Option<PathBuf>,
+    pub ram: Option<PathBuf>,
+    pub trace: Option<PathBuf>,
+    pub coverage: Option<PathBuf>,
+    pub wav: Option<PathBuf>,
+    pub stems: bool,
+    pub accuracy: AccuracyPreset,
+    pub sync_cycles: Option<u32>,
+}
+
+fn usage(message: impl Into<String>) -> CliError {
+    CliError::Usage {
+        message: message.into(),
+    }
+}
+
+/// Parse the arguments following the program name.
+///
+/// Returns `None` when help was requested.
+///
+/// # Errors
+/// On unknown options, missing values or an invalid `--until` expression.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Args>, CliError> {
+    let mut args = args.into_iter();
+    let mut rom = None;
+    let mut frames = DEFAULT_FRAMES;
+    let mut until = None;
+    let mut cpu = CpuType::Arm9;
+    let mut screenshot = None;
+    let mut summary = None;
+    let mut ram = None;
+    let mut trace = None;
+    let mut coverage = None;
+    let mut wav = None;
+    let mut stems = false;
+    let mut accuracy = AccuracyPreset::default();
+    let mut sync_cycles = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| usage(format!("{arg} needs a value")))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-n" | "--frames" => {
+                let text = value()?;
+                frames = text
+                    .parse()
+                    .map_err(|_| usage(format!("Invalid frame count '{text}'")))?;
+            }
+            "--until" => until = Some(WatchExpr::parse(&value()?).context(InvalidExprSnafu)?),
+            "--cpu" => {
+                cpu = match value()?.to_ascii_lowercase().as_str() {
+                    "arm9" => CpuType::Arm9,
+                    "arm7" => CpuType::Arm7,
+                    other => return Err(usage(format!("Unknown CPU '{other}'"))),
+                };
+            }
+            "--screenshot" => screenshot = Some(value()?.into()),
+            "--summary" => summary = Some(value()?.into()),
+            "--ram" => ram = Some(value()?.into()),
+            "--trace" => trace = Some(value()?.into()),
+            "--coverage" => coverage = Some(value()?.into()),
+            "--wav" => wav = Some(value()?.into()),
+            "--stems" => stems = true,
+            "--accuracy" => {
+                let name = value()?;
+                accuracy = AccuracyPreset::parse(&name)
+                    .ok_or_else(|| usage(format!("Unknown accuracy preset '{name}'")))?;
+            }
+            "--sync" => {
+                let text = value()?;
+                sync_cycles = Some(
+                    text.parse()
+                        .ok()
+                        .filter(|&cycles| cycles > 0)
+                        .ok_or_else(|| usage(format!("Invalid sync cycles '{text}'")))?,
+                );
+            }
+            option if option.starts_with('-') => {
+                return Err(usage(format!("Unknown option '{option}'")));
+            }
+            path => match rom {
+                None => rom = Some(PathBuf::from(path)),
+                Some(_) => return Err(usage(format!("Unexpected argument '{path}'"))),
+            },
+        }
+    }
+
+    let rom = rom.ok_or_else(|| usage("No ROM given"))?;
+    if stems && wav.is_none() {
+        return Err(usage("--stems needs --wav"));
+    }
+    Ok(Some(Args {
+        rom,
+        frames,
+        until,
+        cpu,
+        screenshot,
+        summary,
+        ram,
+        trace,
+        coverage,
+        wav,
+        stems,
+        accuracy,
+        sync_cycles,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(line: &str) -> Result<Option<Args>, CliError> {
+        parse(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let args = parse_str("game.nds -n 300 --until b[0x02000000]==1 --cpu ARM7 --ram ram.bin")
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.rom, PathBuf::from("game.nds"));
+        assert_eq!(args.frames, 300);
+        assert_eq!(args.until.unwrap().source(), "b[0x02000000]==1");
+        assert_eq!(args.cpu, CpuType::Arm7);
+        assert_eq!(args.ram, Some(PathBuf::from("ram.bin")));
+        assert!(args.screenshot.is_none());
+
+        assert!(parse_str("--help").unwrap().is_none());
+        assert!(matches!(parse_str("-n 10"), Err(CliError::Usage { .. })));
+        assert!(matches!(parse_str("a.nds -n"), Err(CliError::Usage { .. })));
+        assert!(matches!(
+            parse_str("a.nds --bogus"),
+            Err(CliError::Usage { .. })
+        ));
+        assert!(matches!(
+            parse_str("a.nds --stems"),
+            Err(CliError::Usage { .. })
+        ));
+        assert!(matches!(
+            parse_str("a.nds --until r0=="),
+            Err(CliError::InvalidExpr { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_accuracy() {
+        let args = parse_str("a.nds").unwrap().unwrap();
+        assert_eq!(args.accuracy, AccuracyPreset::Balanced);
+        assert_eq!(args.sync_cycles, None);
+
+        let args = parse_str("a.nds --accuracy Accurate --sync 2")
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.accuracy, AccuracyPreset::Accurate);
+        assert_eq!(args.sync_cycles, Some(2));
+
+        assert!(matches!(
+            parse_str("a.nds --sync 0"),
+            Err(CliError::Usage { .. })
+        ));
+        assert!(matches!(
+            parse_str("a.nds --accuracy perfect"),
+            Err(CliError::Usage { .. })
+        ));
+    }
+}
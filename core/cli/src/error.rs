@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use lunaris_ds_emu::debug::ExprError;
+use lunaris_ds_emu::{CartridgeError, EmuError};
+
+#[derive(Debug, snafu::Snafu)]
+#[snafu(visibility(pub))]
+pub enum CliError {
+    /// Unknown option, missing value or missing ROM.
+    #[snafu(display("{message}\n\n{}", crate::args::USAGE))]
+    Usage { message: String },
+
+    /// `--until` could not be parsed.
+    #[snafu(display("Invalid --until expression: {source}"))]
+    InvalidExpr { source: ExprError },
+
+    /// `--until` could not be evaluated.
+    #[snafu(display("Failed to evaluate --until: {source}"))]
+    EvalExpr { source: ExprError },
+
+    /// ROM could not be loaded.
+    #[snafu(display("{source}"))]
+    LoadRom { source: CartridgeError },
+
+    /// State summary or RAM dump could not be written.
+    #[snafu(display("Failed to write {}: {source}", path.display()))]
+    WriteOutput {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// Screenshot could not be encoded or written.
+    #[snafu(display("Failed to save {}: {source}", path.display()))]
+    SaveScreenshot {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+
+    /// Audio dump could not be created or written.
+    #[snafu(display("{source}"))]
+    AudioDump { source: EmuError },
+
+    /// Trace could not be written.
+    #[snafu(display("{source}"))]
+    WriteTrace { source: EmuError },
+}
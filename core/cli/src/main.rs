@@ -0,0 +1,115 @@
+//! Headless runner
+//!
+//! Loads a ROM, runs it for a number of frames or until a watch expression
+//! holds, then writes the requested screenshot, register summary, RAM dump,
+//! trace, code coverage and audio. Meant for scripted compatibility sweeps
+//! and for machines without the GUI. Only the public `lunaris_ds_emu` API is
+//! used.
+//!
+//! ```sh
+//! lunaris-cli game.nds --frames 600 --until 'b[0x02000000] == 1' --screenshot out.png
+//! ```
+mod args;
+mod error;
+mod output;
+
+use std::process::ExitCode;
+
+use lunaris_ds_emu::debug::FrameTrace;
+use lunaris_ds_emu::{CpuType, Emulator};
+use snafu::ResultExt as _;
+
+use crate::args::{Args, USAGE};
+use crate::error::{AudioDumpSnafu, CliError, EvalExprSnafu, LoadRomSnafu, WriteTraceSnafu};
+
+/// `--until` did not hold within the frame limit.
+const EXIT_TIMEOUT: u8 = 1;
+/// Bad arguments, or a file could not be read or written.
+const EXIT_ERROR: u8 = 2;
+
+/// Run the ROM and write the outputs.
+///
+/// Returns whether the run ended as asked: always without `--until`,
+/// otherwise only if the condition held.
+fn run(args: &Args) -> Result<bool, CliError> {
+    let mut emu = Box::new(Emulator::new());
+    emu.set_accuracy_preset(args.accuracy);
+    emu.config.accuracy_overrides.sync_cycles = args.sync_cycles;
+    emu.load_rom(&args.rom).context(LoadRomSnafu)?;
+    if args.coverage.is_some() {
+        for cpu in [CpuType::Arm9, CpuType::Arm7] {
+            emu.coverage_mut(cpu).set_enabled(true);
+        }
+    }
+    if let Some(path) = &args.wav {
+        emu.start_audio_dump(path, args.stems)
+            .context(AudioDumpSnafu)?;
+    }
+
+    let mut trace: Option<FrameTrace> = None;
+    let mut frames = 0;
+    let mut condition_met = false;
+    while frames < args.frames && !condition_met {
+        match args.trace.is_some() {
+            true => trace = Some(emu.trace_frame()),
+            false => {
+                emu.run();
+            }
+        }
+        frames += 1;
+
+        if let Some(until) = &args.until {
+            condition_met = until
+                .eval_condition(&emu, args.cpu)
+                .context(EvalExprSnafu)?;
+        }
+    }
+
+    match (&args.until, condition_met) {
+        (Some(until), true) => println!("'{}' held after {frames} frame(s)", until.source()),
+        (Some(until), false) => println!("'{}' did not hold in {frames} frame(s)", until.source()),
+        (None, _) => println!("Ran {frames} frame(s)"),
+    }
+
+    emu.stop_audio_dump().context(AudioDumpSnafu)?;
+    if let Some(path) = &args.screenshot {
+        output::save_screenshot(&emu, path)?;
+    }
+    if let Some(path) = &args.summary {
+        output::write_summary(&emu, frames, path)?;
+    }
+    if let Some(path) = &args.ram {
+        output::write_ram(&emu, path)?;
+    }
+    if let (Some(path), Some(trace)) = (&args.trace, &trace) {
+        trace.write_chrome_json(path).context(WriteTraceSnafu)?;
+    }
+    if let Some(path) = &args.coverage {
+        output::write_coverage(&emu, path)?;
+    }
+
+    Ok(args.until.is_none() || condition_met)
+}
+
+fn main() -> ExitCode {
+    let args = match args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("lunaris-cli: {err}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(EXIT_TIMEOUT),
+        Err(err) => {
+            eprintln!("lunaris-cli: {err}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Files written after a run
+use std::fmt::Write as _;
+use std::path::Path;
+
+use image::{ImageBuffer, Rgba};
+use lunaris_ds_emu::{CpuType, Emulator};
+use lunaris_ds_mem_const::{PIXELS_PER_LINE, SCANLINES};
+use snafu::ResultExt as _;
+
+use crate::error::{CliError, SaveScreenshotSnafu, WriteOutputSnafu};
+
+fn write_file(path: &Path, data: &[u8]) -> Result<(), CliError> {
+    std::fs::write(path, data).with_context(|_| WriteOutputSnafu { path })
+}
+
+/// Save both screens as one PNG, upper screen on top.
+pub fn save_screenshot(emu: &Emulator, path: &Path) -> Result<(), CliError> {
+    let mut pixels = vec![0; PIXELS_PER_LINE * SCANLINES * 2];
+    let (upper, lower) = pixels.split_at_mut(PIXELS_PER_LINE * SCANLINES);
+    emu.get_upper_frame(upper);
+    emu.get_lower_frame(lower);
+
+    // 0xAARRGGBB -> [R, G, B, A]
+    let raw: Vec<u8> = pixels
+        .iter()
+        .flat_map(|px: &u32| {
+            let [b, g, r, a] = px.to_le_bytes();
+            [r, g, b, a]
+        })
+        .collect();
+    let width = PIXELS_PER_LINE as u32;
+    let height = (SCANLINES * 2) as u32;
+    let Some(image) = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, raw) else {
+        unreachable!("buffer is sized for both screens");
+    };
+    image
+        .save(path)
+        .with_context(|_| SaveScreenshotSnafu { path })
+}
+
+/// Write the registers of both CPUs and the frame/cycle counters as text.
+pub fn write_summary(emu: &Emulator, frames: u32, path: &Path) -> Result<(), CliError> {
+    let mut text = format!(
+        "frames: {frames}\nsystem_timestamp: {}\n",
+        emu.system_timestamp
+    );
+    for (name, cpu_type) in [("arm9", CpuType::Arm9), ("arm7", CpuType::Arm7)] {
+        let cpu = emu.get_cpu(cpu_type);
+        let _ = writeln!(
+            text,
+            "\n[{name}]\ncpsr: {:08X}\nhalted: {}",
+            cpu.cpsr.get(),
+            cpu.halted
+        );
+        for (i, reg) in cpu.regs.iter().enumerate() {
+            let _ = writeln!(text, "r{i}: {reg:08X}");
+        }
+    }
+    write_file(path, text.as_bytes())
+}
+
+/// Dump main RAM as raw bytes.
+pub fn write_ram(emu: &Emulator, path: &Path) -> Result<(), CliError> {
+    write_file(path, &emu.main_ram)
+}
+
+/// Write the executed bytes and ranges of both CPUs as text.
+pub fn write_coverage(emu: &Emulator, path: &Path) -> Result<(), CliError> {
+    let mut text = String::new();
+    for (name, cpu_type) in [("arm9", CpuType::Arm9), ("arm7", CpuType::Arm7)] {
+        let coverage = emu.coverage(cpu_type);
+        let _ = writeln!(
+            text,
+            "[{name}]\nexecuted_bytes: {}",
+            coverage.executed_bytes()
+        );
+        for range in coverage.ranges() {
+            let _ = writeln!(text, "{:08X}-{:08X}", range.start, range.end);
+        }
+        text.push('\n');
+    }
+    write_file(path, text.as_bytes())
+}
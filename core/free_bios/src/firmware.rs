@@ -4,7 +4,7 @@
 
 /// DSType enum
 /// DS, Lite, DSi, iQue DS, iQue DS Lite
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DSType {
     Ds,
     Lite,
@@ -15,7 +15,8 @@ pub enum DSType {
 }
 
 impl DSType {
-    const fn model_spec(&self) -> u8 {
+    /// Console type byte stored at firmware header offset 0x1D.
+    pub const fn model_spec(&self) -> u8 {
         match self {
             DSType::Ds => 0xFF,
             DSType::Lite => 0x20,
@@ -24,12 +25,39 @@ impl DSType {
             DSType::Dsi => 0x63,
         }
     }
+
+    /// Inverse of [`DSType::model_spec`].
+    pub const fn from_model_spec(value: u8) -> Option<Self> {
+        match value {
+            0xFF => Some(DSType::Ds),
+            0x20 => Some(DSType::Lite),
+            0x57 => Some(DSType::Ique),
+            0x43 => Some(DSType::IqueLite),
+            0x63 => Some(DSType::Dsi),
+            _ => None,
+        }
+    }
+
+    /// DS Lite style hardware (brightness control in the power management chip).
+    pub const fn is_lite(&self) -> bool {
+        matches!(self, DSType::Lite | DSType::IqueLite)
+    }
+
+    /// Number of selectable backlight levels; the original DS can only
+    /// switch the backlights on or off.
+    pub const fn backlight_levels(&self) -> u8 {
+        match self {
+            DSType::Ds | DSType::Ique => 2,
+            DSType::Lite | DSType::IqueLite => 4,
+            DSType::Dsi => 5,
+        }
+    }
 }
 
 ///
 /// # Example
-/// ```rust
-/// use free_bios::firmware::FIRMWARE_DS;
+/// ```rust,no_run
+/// use lunaris_ds_free_bios::firmware::FIRMWARE_DS;
 /// std::fs::write("firmware.bin", &FIRMWARE_DS).unwrap();
 /// ```
 /// DS firmware for standard DS/Lite (256KB)
@@ -1,56 +1,79 @@
 use lunaris_ds_mem_const::*;
 
-/// Display Control Register (DISPCNT)
-///
-/// Controls the main display settings, background visibility,
-/// object rendering, and display modes.
-#[derive(Debug, Default, Clone, Copy)]
-pub struct DispCnt {
-    /// Background mode (0-5)
-    pub bg_mode: i32,
-    /// Enable 3D mode
-    pub bg_3d: bool,
-    /// Use 1D mapping for character tiles for objects
-    pub tile_obj_1d: bool,
-    /// Use square bitmap objects
-    pub bitmap_obj_square: bool,
-    /// Use 1D mapping for bitmap objects
-    pub bitmap_obj_1d: bool,
-    /// Display background 0
-    pub display_bg0: bool,
-    /// Display background 1
-    pub display_bg1: bool,
-    /// Display background 2
-    pub display_bg2: bool,
-    /// Display background 3
-    pub display_bg3: bool,
-    /// Display objects (sprites)
-    pub display_obj: bool,
-    /// Display window 0
-    pub display_win0: bool,
-    /// Display window 1
-    pub display_win1: bool,
-    /// Display object window
-    pub obj_win_display: bool,
-    /// Display mode for layers
-    pub display_mode: i32,
-    /// VRAM block selection
-    pub vram_block: i32,
-    /// Tile object 1D boundary
-    pub tile_obj_1d_bound: i32,
-    /// Bitmap object 1D boundary
-    pub bitmap_obj_1d_bound: bool,
-    /// HBlank object processing enable
-    pub hblank_obj_processing: bool,
-    /// Character base block
-    pub char_base: i32,
-    /// Screen base block
-    ///FIXME?: u32?(C++ impl is int)
-    pub screen_base: i32,
-    /// Enable extended palette for backgrounds
-    pub bg_extended_palette: bool,
-    /// Enable extended palette for objects
-    pub obj_extended_palette: bool,
+lunaris_ds_bitfield::bitfield! {
+    /// Display Control Register (DISPCNT)
+    ///
+    /// Controls the main display settings, background visibility,
+    /// object rendering, and display modes.
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct DispCnt(u32) {
+        /// Background mode (0-5)
+        pub bg_mode: i32 [0..3],
+        /// Enable 3D mode
+        pub bg_3d: bool [3],
+        /// Use 1D mapping for character tiles for objects
+        pub tile_obj_1d: bool [4],
+        /// Use square bitmap objects
+        pub bitmap_obj_square: bool [5],
+        /// Use 1D mapping for bitmap objects
+        pub bitmap_obj_1d: bool [6],
+        /// Display background 0
+        pub display_bg0: bool [8],
+        /// Display background 1
+        pub display_bg1: bool [9],
+        /// Display background 2
+        pub display_bg2: bool [10],
+        /// Display background 3
+        pub display_bg3: bool [11],
+        /// Display objects (sprites)
+        pub display_obj: bool [12],
+        /// Display window 0
+        pub display_win0: bool [13],
+        /// Display window 1
+        pub display_win1: bool [14],
+        /// Display object window
+        pub obj_win_display: bool [15],
+        /// Display mode for layers
+        pub display_mode: i32 [16..18],
+        /// VRAM block selection
+        pub vram_block: i32 [18..20],
+        /// Tile object 1D boundary
+        pub tile_obj_1d_bound: i32 [20..22],
+        /// Bitmap object 1D boundary
+        pub bitmap_obj_1d_bound: bool [22],
+        /// HBlank object processing enable
+        pub hblank_obj_processing: bool [23],
+        /// Character base block
+        pub char_base: i32 [24..27],
+        /// Screen base block
+        ///FIXME?: u32?(C++ impl is int)
+        pub screen_base: i32 [27..30],
+        /// Enable extended palette for backgrounds
+        pub bg_extended_palette: bool [30],
+        /// Enable extended palette for objects
+        pub obj_extended_palette: bool [31],
+    }
+}
+
+lunaris_ds_bitfield::bitfield! {
+    /// Background Control Register (BGCNT)
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct BgCnt(u16) {
+        /// Drawing priority (0 is highest)
+        pub priority: u8 [0..2],
+        /// Character base block, in 16KB units
+        pub char_base: u32 [2..6],
+        /// Mosaic enable
+        pub mosaic: bool [6],
+        /// 256 colors/1 palette instead of 16 colors/16 palettes
+        pub palette_256: bool [7],
+        /// Screen base block, in 2KB units
+        pub screen_base: u32 [8..13],
+        /// Extended palette slot (BG0/BG1) or display area overflow (BG2/BG3)
+        pub overflow: bool [13],
+        /// Screen size
+        pub screen_size: u16 [14..16],
+    }
 }
 
 /// Display Capture Control Register (DISPCAPCNT)
@@ -80,68 +103,120 @@ pub struct DispCapCnt {
     pub enable_busy: bool,
 }
 
-/// Window Input Register (WININ)
-///
-/// Determines which layers and objects are visible inside windows.
-#[derive(Debug, Default, Clone, Copy)]
-pub struct WinIn {
-    /// Backgrounds enabled for window 0 (BG0-BG3)
-    pub win0_bg_enabled: [bool; 4],
-    /// Objects enabled for window 0
-    pub win0_obj_enabled: bool,
-    /// Special color effects for window 0
-    pub win0_color_special: bool,
-    /// Backgrounds enabled for window 1 (BG0-BG3)
-    pub win1_bg_enabled: [bool; 4],
-    /// Objects enabled for window 1
-    pub win1_obj_enabled: bool,
-    /// Special color effects for window 1
-    pub win1_color_special: bool,
+lunaris_ds_bitfield::bitfield! {
+    /// Window Input Register (WININ)
+    ///
+    /// Determines which layers and objects are visible inside windows.
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct WinIn(u16) {
+        /// Backgrounds enabled for window 0 (BG0-BG3)
+        pub win0_bg_enabled: [bool; 4] [0..4],
+        /// Objects enabled for window 0
+        pub win0_obj_enabled: bool [4],
+        /// Special color effects for window 0
+        pub win0_color_special: bool [5],
+        /// Backgrounds enabled for window 1 (BG0-BG3)
+        pub win1_bg_enabled: [bool; 4] [8..12],
+        /// Objects enabled for window 1
+        pub win1_obj_enabled: bool [12],
+        /// Special color effects for window 1
+        pub win1_color_special: bool [13],
+    }
 }
 
-/// Window Output Register (WINOUT)
-///
-/// Determines which layers and objects are visible outside windows.
-#[derive(Debug, Default, Clone, Copy)]
-pub struct WinOut {
-    /// Backgrounds enabled outside windows (BG0-BG3)
-    pub outside_bg_enabled: [bool; 4],
-    /// Objects enabled outside windows
-    pub outside_obj_enabled: bool,
-    /// Special color effects outside windows
-    pub outside_color_special: bool,
-    /// Backgrounds enabled in object window (BG0-BG3)
-    pub objwin_bg_enabled: [bool; 4],
-    /// Objects enabled in object window
-    pub objwin_obj_enabled: bool,
-    /// Special color effects in object window
-    pub objwin_color_special: bool,
+lunaris_ds_bitfield::bitfield! {
+    /// Window Output Register (WINOUT)
+    ///
+    /// Determines which layers and objects are visible outside windows.
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct WinOut(u16) {
+        /// Backgrounds enabled outside windows (BG0-BG3)
+        pub outside_bg_enabled: [bool; 4] [0..4],
+        /// Objects enabled outside windows
+        pub outside_obj_enabled: bool [4],
+        /// Special color effects outside windows
+        pub outside_color_special: bool [5],
+        /// Backgrounds enabled in object window (BG0-BG3)
+        pub objwin_bg_enabled: [bool; 4] [8..12],
+        /// Objects enabled in object window
+        pub objwin_obj_enabled: bool [12],
+        /// Special color effects in object window
+        pub objwin_color_special: bool [13],
+    }
 }
 
-/// Blend Control Register (BLDCNT)
+lunaris_ds_bitfield::bitfield! {
+    /// Blend Control Register (BLDCNT)
+    ///
+    /// Controls alpha blending, brightness increase/decrease, and
+    /// which layers participate in the blend.
+    #[derive(Debug, Default, Clone, Copy)]
+    pub struct BldCnt(u16) {
+        /// Backgrounds as first target pixels (BG0-BG3)
+        pub bg_first_target_pix: [bool; 4] [0..4],
+        /// Objects as first target pixels
+        pub obj_first_target_pix: bool [4],
+        /// Backdrop as first target pixel
+        pub bd_first_target_pix: bool [5],
+        /// Effect type (0=none, 1=alpha blend, 2=lighten, 3=darken)
+        pub effect: u8 [6..8],
+        /// Backgrounds as second target pixels (BG0-BG3)
+        pub bg_second_target_pix: [bool; 4] [8..12],
+        /// Objects as second target pixels
+        pub obj_second_target_pix: bool [12],
+        /// Backdrop as second target pixel
+        pub bd_second_target_pix: bool [13],
+    }
+}
+
+/// One pixel of the OBJ line, see [`Gpu::draw_sprite_line`].
 ///
-/// Controls alpha blending, brightness increase/decrease, and
-/// which layers participate in the blend.
-#[derive(Debug, Default, Clone, Copy)]
-pub struct BldCnt {
-    /// Backgrounds as first target pixels (BG0-BG3)
-    pub bg_first_target_pix: [bool; 4],
-    /// Objects as first target pixels
-    pub obj_first_target_pix: bool,
-    /// Backdrop as first target pixel
-    pub bd_first_target_pix: bool,
-    /// Effect type (0=none, 1=alpha blend, 2=lighten, 3=darken)
-    pub effect: u8,
-    /// Backgrounds as second target pixels (BG0-BG3)
-    pub bg_second_target_pix: [bool; 4],
-    /// Objects as second target pixels
-    pub obj_second_target_pix: bool,
-    /// Backdrop as second target pixel
-    pub bd_second_target_pix: bool,
+/// [`Gpu::draw_sprite_line`]: crate::gpu_root::Gpu::draw_sprite_line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ObjPixel {
+    /// ARGB of the front OBJ, `None` where no OBJ is drawn
+    pub color: Option<u32>,
+    /// Priority against the BGs of the front OBJ
+    pub priority: u8,
+    /// The front OBJ is semi-transparent (OBJ mode 1)
+    pub semi_transparent: bool,
+    /// Alpha (0-15) of a front bitmap OBJ
+    pub alpha: Option<u8>,
+    /// An OBJ window OBJ is opaque here
+    pub window: bool,
+    /// The front OBJ has mosaic enabled
+    pub mosaic: bool,
+}
+
+/// Layer id of OBJs in [`BlendTargets`]; BG0-BG3 are 0-3, so ids match the
+/// BLDCNT target bits.
+pub const LAYER_OBJ: u8 = 4;
+/// Layer id of the backdrop in [`BlendTargets`].
+pub const LAYER_BACKDROP: u8 = 5;
+
+/// The front layer of one pixel of the current line and the pixel it
+/// covers, the candidates for BLDCNT first and second target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlendTargets {
+    /// Layer of the front pixel
+    pub layer: u8,
+    /// ARGB of the pixel below the front one
+    pub below: u32,
+    /// Layer of the pixel below the front one
+    pub below_layer: u8,
 }
 
+/// DISPCNT bits that only exist on engine A: BG0 3D, display modes 2-3,
+/// VRAM block, bitmap OBJ 1D boundary, character base and screen base.
+pub(crate) const DISPCNT_ENGINE_A_ONLY: u32 =
+    (1 << 3) | (1 << 17) | (0x3 << 18) | (1 << 22) | (0x3F << 24);
+
 #[derive(Debug, Default)]
 pub struct Gpu2DEngine {
+    /// Engine A has 3D, capture, large bitmaps and the main memory display FIFO;
+    /// writes to those bits of engine B are ignored.
+    pub is_engine_a: bool,
+
     // Frame buffers
     /// size: 49152(PIXELS_PER_LINE * SCAN_LINES)
     pub framebuffer: Vec<u32>,
@@ -150,8 +225,10 @@ pub struct Gpu2DEngine {
 
     // size: 512(PIXELS_PER_LINE * 2)
     pub final_bg_priority: Vec<u8>,
-    // size: 512(PIXELS_PER_LINE * 2)
-    pub sprite_scanline: Vec<u32>,
+    /// OBJ layer of the current line, 256(PIXELS_PER_LINE)
+    pub obj_line: Vec<ObjPixel>,
+    /// Blend targets of the current line, 256(PIXELS_PER_LINE)
+    pub blend_line: Vec<BlendTargets>,
     // Scanline buffers 256(PIXELS_PER_LINE)
     pub window_mask: Vec<u8>,
 
@@ -160,7 +237,7 @@ pub struct Gpu2DEngine {
     pub dispcapcnt: DispCapCnt,
     pub captured_lines: i32,
 
-    pub bgcnt: [u16; 4],
+    pub bgcnt: [BgCnt; 4],
     pub bghofs: [u16; 4],
     pub bgvofs: [u16; 4],
 
@@ -200,20 +277,22 @@ pub struct Gpu2DEngine {
 }
 
 impl Gpu2DEngine {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(is_engine_a: bool) -> Self {
         Gpu2DEngine {
+            is_engine_a,
             framebuffer: vec![0; PIXELS_PER_LINE * SCANLINES],
             front_framebuffer: vec![0; PIXELS_PER_LINE * SCANLINES],
 
             final_bg_priority: vec![0; PIXELS_PER_LINE * 2],
-            sprite_scanline: vec![0; PIXELS_PER_LINE * 2],
+            obj_line: vec![ObjPixel::default(); PIXELS_PER_LINE],
+            blend_line: vec![BlendTargets::default(); PIXELS_PER_LINE],
             window_mask: vec![0; PIXELS_PER_LINE],
 
             dispcnt: DispCnt::default(),
             dispcapcnt: DispCapCnt::default(),
             captured_lines: 0,
 
-            bgcnt: [0; 4],
+            bgcnt: [BgCnt::default(); 4],
             bghofs: [0; 4],
             bgvofs: [0; 4],
 
@@ -256,6 +335,40 @@ impl Gpu2DEngine {
     // Framebuffer handling
     // ============================================================
 
+    /// MOSAIC block width and height of BG `index`, 1x1 unless its BGCNT
+    /// enables mosaic.
+    pub const fn bg_mosaic(&self, index: usize) -> (u32, u32) {
+        match self.bgcnt[index].mosaic {
+            true => (
+                (self.mosaic & 0xF) as u32 + 1,
+                ((self.mosaic >> 4) & 0xF) as u32 + 1,
+            ),
+            false => (1, 1),
+        }
+    }
+
+    /// MOSAIC block width and height of OBJs with mosaic enabled.
+    pub const fn obj_mosaic(&self) -> (u32, u32) {
+        (
+            ((self.mosaic >> 8) & 0xF) as u32 + 1,
+            ((self.mosaic >> 12) & 0xF) as u32 + 1,
+        )
+    }
+
+    /// Puts `argb` of `layer` in front of pixel `x` of the line starting at
+    /// `line_start`. The pixel it covers becomes the second blend target,
+    /// unless it belongs to the same layer (3D polygons drawn over each
+    /// other).
+    pub(crate) fn put_pixel(&mut self, line_start: usize, x: usize, argb: u32, layer: u8) {
+        let targets = &mut self.blend_line[x];
+        if targets.layer != layer {
+            targets.below = self.framebuffer[line_start + x];
+            targets.below_layer = targets.layer;
+            targets.layer = layer;
+        }
+        self.framebuffer[line_start + x] = argb;
+    }
+
     /// Copies the internal framebuffer into `buffer`.
     pub fn get_framebuffer(&self, buffer: &mut [u32]) {
         // Ensure the buffer is large enough
@@ -296,51 +409,37 @@ impl Gpu2DEngine {
             self.bg3p_internal[i] = self.bg3p[i];
         }
 
-        self.bg2x_internal = self.bg2x as i32;
-        self.bg2y_internal = self.bg2y as i32;
-        self.bg3x_internal = self.bg3x as i32;
-        self.bg3y_internal = self.bg3y as i32;
+        self.bg2x_internal = reference_point(self.bg2x);
+        self.bg2y_internal = reference_point(self.bg2y);
+        self.bg3x_internal = reference_point(self.bg3x);
+        self.bg3y_internal = reference_point(self.bg3y);
 
         self.dispcapcnt.enable_busy = false;
     }
 
+    /// Move the BG2/BG3 internal reference points to the next line, by
+    /// (PB, PD). Runs after every drawn line, shown or not.
+    pub fn advance_affine_references(&mut self) {
+        let [_, pb, _, pd] = self.bg2p_internal.map(|param| param as i16 as i32);
+        self.bg2x_internal = self.bg2x_internal.wrapping_add(pb);
+        self.bg2y_internal = self.bg2y_internal.wrapping_add(pd);
+        let [_, pb, _, pd] = self.bg3p_internal.map(|param| param as i16 as i32);
+        self.bg3x_internal = self.bg3x_internal.wrapping_add(pb);
+        self.bg3y_internal = self.bg3y_internal.wrapping_add(pd);
+    }
+
     // ============================================================
     // Register getters
     // ============================================================
 
     /// Returns the display control register (DISPCNT).
     pub fn get_dispcnt(&self) -> u32 {
-        let mut reg: u32 = 0;
-
-        reg |= self.dispcnt.bg_mode as u32;
-        reg |= (self.dispcnt.bg_3d as u32) << 3;
-        reg |= (self.dispcnt.tile_obj_1d as u32) << 4;
-        reg |= (self.dispcnt.bitmap_obj_square as u32) << 5;
-        reg |= (self.dispcnt.bitmap_obj_1d as u32) << 6;
-        reg |= (self.dispcnt.display_bg0 as u32) << 8;
-        reg |= (self.dispcnt.display_bg1 as u32) << 9;
-        reg |= (self.dispcnt.display_bg2 as u32) << 10;
-        reg |= (self.dispcnt.display_bg3 as u32) << 11;
-        reg |= (self.dispcnt.display_obj as u32) << 12;
-        reg |= (self.dispcnt.display_win0 as u32) << 13;
-        reg |= (self.dispcnt.display_win1 as u32) << 14;
-        reg |= (self.dispcnt.obj_win_display as u32) << 15;
-        reg |= (self.dispcnt.display_mode as u32) << 16;
-        reg |= (self.dispcnt.vram_block as u32) << 18;
-        reg |= (self.dispcnt.tile_obj_1d_bound as u32) << 20;
-        reg |= (self.dispcnt.bitmap_obj_1d_bound as u32) << 22;
-        reg |= (self.dispcnt.hblank_obj_processing as u32) << 23;
-        reg |= (self.dispcnt.char_base as u32) << 24;
-        reg |= (self.dispcnt.screen_base as u32) << 27;
-        reg |= (self.dispcnt.bg_extended_palette as u32) << 30;
-        reg |= (self.dispcnt.obj_extended_palette as u32) << 31;
-
-        reg
+        self.dispcnt.get()
     }
 
     /// Returns a background control register (BGCNT).
     pub fn get_bgcnt(&self, index: usize) -> u16 {
-        self.bgcnt[index]
+        self.bgcnt[index].get()
     }
 
     pub fn get_bghofs(&self, index: usize) -> u16 {
@@ -360,52 +459,15 @@ impl Gpu2DEngine {
     }
 
     pub fn get_winin(&self) -> u16 {
-        let mut reg: u16 = 0;
-
-        for bit in 0..4 {
-            reg |= (self.winin.win0_bg_enabled[bit] as u16) << bit;
-            reg |= (self.winin.win1_bg_enabled[bit] as u16) << (bit + 8);
-        }
-
-        reg |= (self.winin.win0_obj_enabled as u16) << 4;
-        reg |= (self.winin.win0_color_special as u16) << 5;
-        reg |= (self.winin.win1_obj_enabled as u16) << 12;
-        reg |= (self.winin.win1_color_special as u16) << 13;
-
-        reg
+        self.winin.get()
     }
 
     pub fn get_winout(&self) -> u16 {
-        let mut reg: u16 = 0;
-
-        for bit in 0..4 {
-            reg |= (self.winout.outside_bg_enabled[bit] as u16) << bit;
-            reg |= (self.winout.objwin_bg_enabled[bit] as u16) << (bit + 8);
-        }
-
-        reg |= (self.winout.outside_obj_enabled as u16) << 4;
-        reg |= (self.winout.outside_color_special as u16) << 5;
-        reg |= (self.winout.objwin_obj_enabled as u16) << 12;
-        reg |= (self.winout.objwin_color_special as u16) << 13;
-
-        reg
+        self.winout.get()
     }
 
     pub fn get_bldcnt(&self) -> u16 {
-        let mut reg: u16 = 0;
-
-        for bit in 0..4 {
-            reg |= (self.bldcnt.bg_first_target_pix[bit] as u16) << bit;
-            reg |= (self.bldcnt.bg_second_target_pix[bit] as u16) << (bit + 8);
-        }
-
-        reg |= (self.bldcnt.obj_first_target_pix as u16) << 4;
-        reg |= (self.bldcnt.obj_second_target_pix as u16) << 12;
-        reg |= (self.bldcnt.bd_first_target_pix as u16) << 5;
-        reg |= (self.bldcnt.bd_second_target_pix as u16) << 13;
-        reg |= (self.bldcnt.effect as u16) << 6;
-
-        reg
+        self.bldcnt.get()
     }
 
     pub fn get_bldalpha(&self) -> u16 {
@@ -436,44 +498,75 @@ impl Gpu2DEngine {
         reg
     }
 
+    /// Whether BG `index` is drawn: enabled in DISPCNT and present in the
+    /// BG mode. Mode 6 on engine B falls back to no BGs at all, its only
+    /// layers being the 3D layer and the large bitmap engine B lacks.
+    pub fn bg_displayed(&self, index: usize) -> bool {
+        if !self.is_engine_a && self.dispcnt.bg_mode == 6 {
+            return false;
+        }
+        match index {
+            0 => self.dispcnt.display_bg0,
+            1 => self.dispcnt.display_bg1,
+            2 => self.dispcnt.display_bg2,
+            3 => self.dispcnt.display_bg3,
+            _ => false,
+        }
+    }
+
     // ============================================================
     // Register setters
     // ============================================================
 
+    /// Drops DISPCNT bits engine B does not have, flagging configurations
+    /// neither engine can display.
+    fn restrict_dispcnt(&self, word: u32) -> u32 {
+        let bg_mode = word & 0x7;
+        #[cfg(feature = "tracing")]
+        if bg_mode == 7 {
+            tracing::warn!(
+                "Engine {}: invalid BG mode 7",
+                if self.is_engine_a { 'A' } else { 'B' }
+            );
+        } else if bg_mode == 6 && !self.is_engine_a {
+            tracing::warn!("Engine B: BG mode 6 is engine A only, no BGs are drawn");
+        }
+
+        if self.is_engine_a {
+            return word;
+        }
+
+        #[cfg(feature = "tracing")]
+        if word & DISPCNT_ENGINE_A_ONLY != 0 {
+            tracing::warn!(
+                "Engine B: ignoring engine A only DISPCNT bits {:08X}",
+                word & DISPCNT_ENGINE_A_ONLY
+            );
+        }
+        word & !DISPCNT_ENGINE_A_ONLY
+    }
+
     /// Sets the low 16 bits of DISPCNT.
     pub fn set_dispcnt_lo(&mut self, halfword: u16) {
-        self.dispcnt.bg_mode = (halfword & 0x7) as i32;
-        self.dispcnt.bg_3d = (halfword & (1 << 3)) != 0;
-        self.dispcnt.tile_obj_1d = (halfword & (1 << 4)) != 0;
-        self.dispcnt.bitmap_obj_square = (halfword & (1 << 5)) != 0;
-        self.dispcnt.bitmap_obj_1d = (halfword & (1 << 6)) != 0;
-        self.dispcnt.display_bg0 = (halfword & (1 << 8)) != 0;
-        self.dispcnt.display_bg1 = (halfword & (1 << 9)) != 0;
-        self.dispcnt.display_bg2 = (halfword & (1 << 10)) != 0;
-        self.dispcnt.display_bg3 = (halfword & (1 << 11)) != 0;
-        self.dispcnt.display_obj = (halfword & (1 << 12)) != 0;
-        self.dispcnt.display_win0 = (halfword & (1 << 13)) != 0;
-        self.dispcnt.display_win1 = (halfword & (1 << 14)) != 0;
-        self.dispcnt.obj_win_display = (halfword & (1 << 15)) != 0;
+        let halfword = self.restrict_dispcnt(halfword as u32);
+        self.dispcnt
+            .set((self.dispcnt.get() & 0xFFFF_0000) | halfword);
+    }
+
+    /// Sets the high 16 bits of DISPCNT.
+    pub fn set_dispcnt_hi(&mut self, halfword: u16) {
+        let word = self.restrict_dispcnt(((halfword as u32) << 16) | (self.dispcnt.get() & 0xFFFF));
+        self.dispcnt.set(word);
     }
 
     /// Sets the full 32-bit DISPCNT.
     pub fn set_dispcnt(&mut self, word: u32) {
-        self.set_dispcnt_lo((word & 0xFFFF) as u16);
-
-        self.dispcnt.display_mode = ((word >> 16) & 0x3) as i32;
-        self.dispcnt.vram_block = ((word >> 18) & 0x3) as i32;
-        self.dispcnt.tile_obj_1d_bound = ((word >> 20) & 0x3) as i32;
-        self.dispcnt.bitmap_obj_1d_bound = (word & (1 << 22)) != 0;
-        self.dispcnt.hblank_obj_processing = (word & (1 << 23)) != 0;
-        self.dispcnt.char_base = ((word >> 24) & 0x7) as i32;
-        self.dispcnt.screen_base = ((word >> 27) & 0x7) as i32;
-        self.dispcnt.bg_extended_palette = (word & (1 << 30)) != 0;
-        self.dispcnt.obj_extended_palette = (word & (1 << 31)) != 0;
+        let word = self.restrict_dispcnt(word);
+        self.dispcnt.set(word);
     }
 
     pub fn set_bgcnt(&mut self, halfword: u16, index: usize) {
-        self.bgcnt[index] = halfword;
+        self.bgcnt[index].set(halfword);
     }
 
     pub fn set_bghofs(&mut self, halfword: u16, index: usize) {
@@ -502,28 +595,28 @@ impl Gpu2DEngine {
     pub fn set_bg2x(&mut self, word: u32, vcount: u16) {
         self.bg2x = word;
         if vcount < 192 {
-            self.bg2x_internal = word as i32;
+            self.bg2x_internal = reference_point(word);
         }
     }
 
     pub fn set_bg2y(&mut self, word: u32, vcount: u16) {
         self.bg2y = word;
         if vcount < 192 {
-            self.bg2y_internal = word as i32;
+            self.bg2y_internal = reference_point(word);
         }
     }
 
     pub fn set_bg3x(&mut self, word: u32, vcount: u16) {
         self.bg3x = word;
         if vcount < 192 {
-            self.bg3x_internal = word as i32;
+            self.bg3x_internal = reference_point(word);
         }
     }
 
     pub fn set_bg3y(&mut self, word: u32, vcount: u16) {
         self.bg3y = word;
         if vcount < 192 {
-            self.bg3y_internal = word as i32;
+            self.bg3y_internal = reference_point(word);
         }
     }
 
@@ -548,39 +641,15 @@ impl Gpu2DEngine {
     }
 
     pub fn set_winin(&mut self, halfword: u16) {
-        for bit in 0..4 {
-            self.winin.win0_bg_enabled[bit] = (halfword & (1 << bit)) != 0;
-            self.winin.win1_bg_enabled[bit] = (halfword & (1 << (bit + 8))) != 0;
-        }
-        self.winin.win0_obj_enabled = (halfword & (1 << 4)) != 0;
-        self.winin.win0_color_special = (halfword & (1 << 5)) != 0;
-        self.winin.win1_obj_enabled = (halfword & (1 << 12)) != 0;
-        self.winin.win1_color_special = (halfword & (1 << 13)) != 0;
+        self.winin.set(halfword);
     }
 
     pub fn set_winout(&mut self, halfword: u16) {
-        for bit in 0..4 {
-            self.winout.outside_bg_enabled[bit] = (halfword & (1 << bit)) != 0;
-            self.winout.objwin_bg_enabled[bit] = (halfword & (1 << (bit + 8))) != 0;
-        }
-        self.winout.outside_obj_enabled = (halfword & (1 << 4)) != 0;
-        self.winout.outside_color_special = (halfword & (1 << 5)) != 0;
-        self.winout.objwin_obj_enabled = (halfword & (1 << 12)) != 0;
-        self.winout.objwin_color_special = (halfword & (1 << 13)) != 0;
+        self.winout.set(halfword);
     }
 
     pub fn set_bldcnt(&mut self, halfword: u16) {
-        for bit in 0..4 {
-            self.bldcnt.bg_first_target_pix[bit] = (halfword & (1 << bit)) != 0;
-            self.bldcnt.bg_second_target_pix[bit] = (halfword & (1 << (bit + 8))) != 0;
-        }
-        self.bldcnt.obj_first_target_pix = (halfword & (1 << 4)) != 0;
-        self.bldcnt.obj_second_target_pix = (halfword & (1 << 12)) != 0;
-
-        self.bldcnt.bd_first_target_pix = (halfword & (1 << 5)) != 0;
-        self.bldcnt.bd_second_target_pix = (halfword & (1 << 13)) != 0;
-
-        self.bldcnt.effect = ((halfword >> 6) & 0x3) as u8;
+        self.bldcnt.set(halfword);
     }
 
     pub fn set_bldalpha(&mut self, halfword: u16) {
@@ -596,6 +665,12 @@ impl Gpu2DEngine {
     }
 
     pub fn set_dispcapcnt(&mut self, word: u32) {
+        if !self.is_engine_a {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Engine B has no display capture, ignoring DISPCAPCNT={word:08X}");
+            return;
+        }
+
         // EVA (0..=16 clamp)
         let eva = (word & 0x1F) as u8;
         self.dispcapcnt.eva = eva.min(16);
@@ -633,3 +708,8 @@ impl Gpu2DEngine {
         self.dispcapcnt.enable_busy = enable;
     }
 }
+
+/// Sign extend a 20.8 fixed point BG2X/BG2Y/BG3X/BG3Y value from 28 bits.
+const fn reference_point(word: u32) -> i32 {
+    ((word << 4) as i32) >> 4
+}
@@ -4,10 +4,6 @@
 //!
 use crate::gpu_3d::structs::Matrix;
 
-// Constants to define
-pub const SCANLINES: usize = 256;
-pub const PIXELS_PER_LINE: usize = 256;
-
 #[rustfmt::skip]
 pub static CMD_PARAM_AMOUNTS: [u8; 256] = [
     0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
@@ -257,12 +257,16 @@ impl Gpu3D {
                 + texcoords[1];
         }
 
-        // vtx.colors[0] = (((self.current_color & 0x1F) << 12) + 0xFFF) as i32;
-        // vtx.colors[1] = ((((self.current_color >> 5) & 0x1F) << 12) + 0xFFF) as i32;
-        // vtx.colors[2] = ((((self.current_color >> 10) & 0x1F) << 12) + 0xFFF) as i32;
-        // vtx.texcoords[0] = self.current_texcoords[0] as i32;
-        // vtx.texcoords[1] = self.current_texcoords[1] as i32;
-        // vtx.clipped = false;
+        // The list slot is reused, so every attribute is set anew: the
+        // current color expanded to 5.12 fixed point per channel, the
+        // texture coordinates, and no clipping yet
+        vtx.colors[0] = (((self.current_color & 0x1F) << 12) + 0xFFF) as i32;
+        vtx.colors[1] = ((((self.current_color >> 5) & 0x1F) << 12) + 0xFFF) as i32;
+        vtx.colors[2] = ((((self.current_color >> 10) & 0x1F) << 12) + 0xFFF) as i32;
+        vtx.texcoords[0] = self.current_texcoords[0] as i32;
+        vtx.texcoords[1] = self.current_texcoords[1] as i32;
+        vtx.clipped = false;
+        self.vertex_list[index] = vtx;
 
         self.vertex_list_count += 1;
 
@@ -362,8 +366,11 @@ impl Gpu3D {
         let bz = v2.coords[2] - v1.coords[2];
 
         // Culling code taken shamelessly from melonDS :P
-        let mut normal =
-            [ay * bz - az * by, az * bx - ax * bz, ax * by - ay * bx].map(|x| x as u64);
+        let mut normal = [
+            ay as i64 * bz as i64 - az as i64 * by as i64,
+            az as i64 * bx as i64 - ax as i64 * bz as i64,
+            ax as i64 * by as i64 - ay as i64 * bx as i64,
+        ];
 
         // TODO: check what real DS does. Maybe help StapleButter?
         while (((normal[0] >> 31) ^ (normal[0] >> 63)) != 0)
@@ -375,9 +382,9 @@ impl Gpu3D {
             normal[2] >>= 4;
         }
 
-        let dot = v1.coords[0] * normal[0] as i32
-            + v1.coords[1] * normal[1] as i32
-            + v1.coords[3] * normal[2] as i32;
+        let dot = v1.coords[0] as i64 * normal[0]
+            + v1.coords[1] as i64 * normal[1]
+            + v1.coords[3] as i64 * normal[2];
         let front_view = dot < 0;
 
         let render = match front_view {
@@ -436,7 +443,7 @@ impl Gpu3D {
 
         let mut clipped_list: Vec<Vertex> = self.vertex_list[clip_start..clipped_count].to_vec(); // [10]
         clipped_count = self.clip(&mut clipped_list, clipped_count, clip_start, true);
-        if clipped_count != 0 {
+        if clipped_count == 0 {
             return;
         }
 
@@ -532,3 +539,22 @@ impl Gpu3D {
     // moved gpu_3d.rs in lunaris_emu/gpu
     // pub fn request_fifo_dma(&mut self)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vertex_takes_current_attributes() {
+        let mut gpu = Gpu3D::new();
+        gpu.vertex_list[0].clipped = true;
+        gpu.current_color = 0x7C1F;
+        gpu.current_texcoords = [16, -8];
+        gpu.add_vertex();
+
+        let vertex = &gpu.vertex_list[0];
+        assert_eq!(vertex.colors, [0x1_FFFF, 0xFFF, 0x1_FFFF]);
+        assert_eq!(vertex.texcoords, [16, -8]);
+        assert!(!vertex.clipped);
+    }
+}
@@ -28,6 +28,7 @@ impl Gpu3D {
             gxstat: GxStatReg::default(),
             polygon_type: 0,
             clear_depth: 0,
+            dot_depth: 0,
             clear_color: 0,
             flush_mode: 0,
             gxfifo: VecDeque::new(),
@@ -79,6 +80,7 @@ impl Gpu3D {
             normal_vector: [0; 3],
             shine_table: vec![0; 128],
             using_shine_table: false,
+            pos_test_result: [0; 4],
             vec_test_result: [0; 3],
             mult_params: Matrix::default(),
             mult_params_index: 0,
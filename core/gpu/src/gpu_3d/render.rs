@@ -4,15 +4,19 @@
 //!
 use crate::gpu_3d::structs::{Gpu3D, Matrix, Polygon, Vertex};
 
-fn safe_clone<T>(dest: &mut Vec<T>, src: &mut Vec<T>, size: usize)
-where
-    T: Default + Clone,
-{
-    let src: Vec<T> = core::mem::take(src);
-    for (d, s) in dest.iter_mut().zip(src.iter()).take(size) {
-        *d = s.clone();
-    }
-    let _ = core::mem::replace(dest, src);
+/// The three signed 1.0.9 components packed in a NORMAL, LIGHT_VECTOR or
+/// VEC_TEST parameter.
+const fn unpack_vector(param: u32) -> [i32; 3] {
+    [
+        ((param << 22) as i32) >> 22,
+        ((param << 12) as i32) >> 22,
+        ((param << 2) as i32) >> 22,
+    ]
+}
+
+/// 5-bit channel `c` (0 red, 1 green, 2 blue) of a BGR555 color.
+const fn color_channel(color: u16, c: usize) -> i32 {
+    ((color >> (c * 5)) & 0x1F) as i32
 }
 
 impl Gpu3D {
@@ -77,22 +81,30 @@ impl Gpu3D {
             let geo_vert_count = self.geo_vert_count as usize;
             let geo_poly_count = self.geo_poly_count as usize;
 
-            safe_clone(&mut self.rend_vert, &mut self.geo_vert, geo_vert_count);
-            safe_clone(&mut self.rend_poly, &mut self.geo_poly, geo_poly_count);
+            self.rend_vert[..geo_vert_count].clone_from_slice(&self.geo_vert[..geo_vert_count]);
+            self.rend_poly[..geo_poly_count].clone_from_slice(&self.geo_poly[..geo_poly_count]);
 
             // #[cfg(feature = "tracing")]
             // tracing::info!("Geo_vert_count: {}", geo_vert_count);
             // tracing::info!("Geo_poly_count: {}", geo_poly_count);
 
             self.rend_vert_count = geo_vert_count as i32;
-            self.rend_poly_count = geo_poly_count as i32;
             self.geo_vert_count = 0;
             self.geo_poly_count = 0;
 
-            for i in 0..self.rend_poly_count as usize {
+            // Width 256 / height 192 do not fit the 8-bit coordinates; the
+            // sizes wrap like on hardware when x2 < x1 or y2 < y1
+            let width = (self.viewport.x2 as i32 - self.viewport.x1 as i32 + 1) & 0x1FF;
+            let height = (self.viewport.y1 as i32 - self.viewport.y2 as i32 + 1) & 0xFF;
+
+            let mut visible = Vec::with_capacity(geo_poly_count);
+            for i in 0..geo_poly_count {
                 let vert_index = self.rend_poly[i].vert_index as usize;
                 self.rend_poly[i].top_y = 256;
                 self.rend_poly[i].bottom_y = 0;
+                let mut first_dot = None;
+                let mut one_dot = true;
+                let mut min_w = i32::MAX;
 
                 for j in 0..self.rend_poly[i].vertices as usize {
                     #[cfg(feature = "tracing")]
@@ -110,32 +122,20 @@ impl Gpu3D {
                     #[cfg(feature = "tracing")]
                     tracing::info!("Coords: (x, y, w) = ({}, {}, {})", xx, yy, ww);
 
-                    let final_x: i32;
-                    let final_y: i32;
-
                     if ww == 0 {
                         #[cfg(feature = "tracing")]
                         tracing::info!("Poly {} ww == 0?", i);
                     } else {
-                        let width = self.viewport.x2 - self.viewport.x1 + 1;
-                        let height = self.viewport.y1 - self.viewport.y2 + 1;
-                        // let width = (self.viewport.x2 as u16 - self.viewport.x1 as u16 + 1) & 0x1FF;
-                        // let height = (self.viewport.y1 - self.viewport.y2 + 1) & 0xFF;
-
-                        // NOTE: (x2 - x1, y2 - y1)?
-                        // let width = (self.viewport.x2 - self.viewport.x1 + 1) & 0x1FF;
-                        // let height = self.viewport.y2 - self.viewport.y1 + 1;
-
-                        let screen_x =
-                            (((xx + ww) * width as i32) / (ww << 1)) + self.viewport.x1 as i32;
+                        let screen_x = (((xx + ww) * width) / (ww << 1)) + self.viewport.x1 as i32;
                         let screen_y =
-                            (((-yy + ww) * height as i32) / (ww << 1)) + self.viewport.y2 as i32;
+                            (((-yy + ww) * height) / (ww << 1)) + self.viewport.y2 as i32;
 
-                        final_x = screen_x & 0x1FF;
-                        final_y = screen_y & 0xFF;
+                        // Vertices on the clip planes can round one past the edge
+                        let final_x = screen_x.clamp(0, 255);
+                        let final_y = screen_y.clamp(0, 191);
 
                         #[cfg(feature = "tracing")]
-                        tracing::info!("Screen shit: ({}, {})", final_x, final_y);
+                        tracing::info!("Screen: ({}, {})", final_x, final_y);
 
                         if final_y < self.rend_poly[i].top_y as i32 {
                             self.rend_poly[i].top_y = final_y as u16;
@@ -144,6 +144,12 @@ impl Gpu3D {
                             self.rend_poly[i].bottom_y = final_y as u16;
                         }
 
+                        match first_dot {
+                            None => first_dot = Some((final_x, final_y)),
+                            Some(dot) => one_dot &= dot == (final_x, final_y),
+                        }
+                        min_w = min_w.min(ww);
+
                         self.rend_vert[vert_index + j].coords[0] = final_x;
                         self.rend_vert[vert_index + j].coords[1] = final_y;
                         if (self.flush_mode & 0x2) != 0 {
@@ -151,34 +157,34 @@ impl Gpu3D {
                         }
                     }
                 }
-            }
 
-            //Sort polygons by translucency
-            let mut index = 0;
-            let mut opaque_count = 0;
-            let mut temp: Vec<Polygon> = Vec::new(); // [2048]
-
-            for i in 0..self.rend_poly_count as usize {
-                if !self.rend_poly[i].translucent {
-                    temp[index] = self.rend_poly[i].clone();
-                    index += 1;
-                    opaque_count += 1;
-                }
+                // 1-dot polygons behind DISP_1DOT_DEPTH are dropped unless
+                // POLYGON_ATTR bit 13 asks for them
+                let hidden = one_dot
+                    && first_dot.is_some()
+                    && !self.rend_poly[i].attributes.render_1dot
+                    && min_w > (self.dot_depth << 9) as i32;
+                visible.push(!hidden);
             }
 
-            for i in 0..self.rend_poly_count as usize {
-                if self.rend_poly[i].translucent {
-                    temp[index] = self.rend_poly[i].clone();
-                    index += 1;
-                }
+            //Sort polygons by translucency
+            let (opaque, translucent): (Vec<Polygon>, Vec<Polygon>) = self.rend_poly
+                [..geo_poly_count]
+                .iter()
+                .zip(&visible)
+                .filter(|&(_, &visible)| visible)
+                .map(|(poly, _)| poly.clone())
+                .partition(|poly| !poly.translucent);
+            let opaque_count = opaque.len();
+            self.rend_poly_count = (opaque_count + translucent.len()) as i32;
+            for (dest, poly) in self
+                .rend_poly
+                .iter_mut()
+                .zip(opaque.into_iter().chain(translucent))
+            {
+                *dest = poly;
             }
 
-            safe_clone(
-                &mut self.rend_poly,
-                &mut temp,
-                self.rend_poly_count as usize,
-            );
-
             // y-sorting: opaque = false -> translucent = true
             if (self.flush_mode & 0x1) != 0 {
                 self.rend_poly[..opaque_count].sort_by(|a, b| {
@@ -216,15 +222,24 @@ impl Gpu3D {
         reg |= (self.gxstat.boxtest_result as u32) << 1;
         reg |= ((self.model_view_sp & 0x1F) as u32) << 8;
         reg |= (self.gxstat.mtx_stack_busy as u32) << 14;
-        reg |= (self.gxfifo.len() << 16) as u32;
+        reg |= (self.gxstat.mtx_overflow as u32) << 15;
+        // Commands queued past a full FIFO (during a swap stall) still read 256
+        reg |= (self.gxfifo.len().min(256) << 16) as u32;
         reg |= ((self.gxfifo.len() < 128) as u32) << 25;
         reg |= ((self.gxfifo.is_empty()) as u32) << 26;
-        reg |= (self.gxstat.geo_busy as u32) << 27;
+        // Still busy while waiting for VBlank after SWAP_BUFFERS
+        reg |= ((self.gxstat.geo_busy || self.swap_buffers) as u32) << 27;
         reg |= (self.gxstat.gxfifo_irq_stat as u32) << 30;
 
         reg
     }
 
+    /// GXSTAT bit 27: the last command is still executing, or more are
+    /// queued in GXPIPE.
+    pub fn update_geo_busy(&mut self) {
+        self.gxstat.geo_busy = self.cycles > 0 || !self.gxpipe.is_empty();
+    }
+
     pub fn get_vert_count(&self) -> u16 {
         self.geo_vert_count as u16
     }
@@ -233,20 +248,36 @@ impl Gpu3D {
         self.geo_poly_count as u16
     }
 
-    pub fn read_clip_mtx(&mut self, address: u32) -> u32 {
-        self.update_clip_matrix();
-        let x = ((address - 0x04000640) % 4) as usize;
-        let y = ((address - 0x04000640) / 4) as usize;
-        self.clip_mtx.m[y][x] as u32
+    /// CLIPMTX_RESULT (0x04000640..0x04000680), 4x4 words, row by row.
+    ///
+    /// Takes `&self`, so a stale clip matrix is recomputed for the read
+    /// instead of being cached.
+    pub fn read_clip_mtx(&self, address: u32) -> u32 {
+        let index = ((address - 0x04000640) / 4) as usize;
+        let (y, x) = (index / 4, index % 4);
+        match self.clip_dirty {
+            true => {
+                let value: i32 = (0..4)
+                    .map(|k| self.modelview_mtx.m[y][k] * self.projection_mtx.m[k][x])
+                    .sum();
+                (value >> 12) as u32
+            }
+            false => self.clip_mtx.m[y][x] as u32,
+        }
     }
 
+    /// VECMTX_RESULT (0x04000680..0x040006A4), 3x3 words, row by row.
     pub fn read_vec_mtx(&self, address: u32) -> u32 {
-        let addr = address - 0x04000680;
-        let x = (addr % 3) as usize;
-        let y = (addr / 3) as usize;
-        self.vector_mtx.m[y][x] as u32
+        let index = ((address - 0x04000680) / 4) as usize;
+        self.vector_mtx.m[index / 3][index % 3] as u32
+    }
+
+    /// POS_RESULT (0x04000620..0x04000630), X, Y, Z and W words.
+    pub fn read_pos_test(&self, address: u32) -> u32 {
+        self.pos_test_result[((address - 0x04000620) / 4) as usize] as u32
     }
 
+    /// VEC_RESULT (0x04000630..0x04000636), X, Y and Z halfwords.
     pub fn read_vec_test(&self, address: u32) -> u16 {
         self.vec_test_result[((address - 0x04000630) / 2) as usize] as u16
     }
@@ -280,6 +311,11 @@ impl Gpu3D {
         self.clear_depth = word & 0x7FFF;
     }
 
+    /// DISP_1DOT_DEPTH
+    pub fn set_dot_depth(&mut self, halfword: u16) {
+        self.dot_depth = (halfword & 0x7FFF) as u32;
+    }
+
     pub fn set_mtx_mode(&mut self, word: u32) {
         #[cfg(feature = "tracing")]
         tracing::debug!("Set mtx_mode: {word:08X}");
@@ -449,11 +485,12 @@ impl Gpu3D {
         #[cfg(feature = "tracing")]
         tracing::debug!("VIEWPORT: {word:08X}");
 
-        //viewport y-coords are upside down
+        // Y counts up from the bottom line; lines past 191 are off screen
+        let y = |shift: u32| 191 - ((word >> shift) & 0xFF).min(191) as u8;
         self.viewport.x1 = (word & 0xFF) as u8;
-        self.viewport.y1 = ((191 - ((word >> 8) & 0xFF)) & 0xFF) as u8;
+        self.viewport.y1 = y(8);
         self.viewport.x2 = ((word >> 16) & 0xFF) as u8;
-        self.viewport.y2 = ((191 - ((word >> 24) & 0xFF)) & 0xFF) as u8;
+        self.viewport.y2 = y(24);
     }
 
     pub fn box_test(&mut self) {
@@ -595,8 +632,39 @@ impl Gpu3D {
         }
     }
 
+    /// POS_TEST: set the current vertex like VTX_16 and store its clip
+    /// coordinates in POS_RESULT, without adding a vertex.
+    pub fn pos_test(&mut self) {
+        self.current_vertex[0] = (self.cmd_params[0] & 0xFFFF) as i16;
+        self.current_vertex[1] = (self.cmd_params[0] >> 16) as i16;
+        self.current_vertex[2] = (self.cmd_params[1] & 0xFFFF) as i16;
+
+        self.update_clip_matrix();
+        let coords = [
+            self.current_vertex[0] as i64,
+            self.current_vertex[1] as i64,
+            self.current_vertex[2] as i64,
+            0x1000,
+        ];
+        for i in 0..4 {
+            let value: i64 = (0..4)
+                .map(|k| coords[k] * self.clip_mtx.m[k][i] as i64)
+                .sum();
+            self.pos_test_result[i] = (value >> 12) as i32;
+        }
+    }
+
+    /// VEC_TEST: multiply a 1.0.9 vector by the directional matrix into
+    /// VEC_RESULT (1.3.12, sign expanded to 16 bits).
     pub fn vec_test(&mut self) {
-        todo!()
+        let vector = unpack_vector(self.cmd_params[0]).map(|x| x as i64);
+        for i in 0..3 {
+            let value: i64 = (0..3)
+                .map(|k| vector[k] * self.vector_mtx.m[k][i] as i64)
+                .sum();
+            // Sign of the 1.3.12 result copied into bits 13-15
+            self.vec_test_result[i] = ((((value >> 9) as i32) << 19) >> 19) as i16;
+        }
     }
 
     // Moved to struct Emulator method (Because use emulator method)
@@ -660,58 +728,52 @@ impl Gpu3D {
         self.mult_params_index = 0;
     }
 
+    /// NORMAL: light the vertex color with the lights enabled in the
+    /// polygon attributes latched by BEGIN_VTXS. With no light enabled the
+    /// color is the emission color.
     pub fn normal(&mut self) {
-        // Let's calculate some lighting!
-        self.normal_vector[0] = (((self.cmd_params[0] & 0x3FF) << 6) >> 6) as i16;
-        self.normal_vector[1] = ((((self.cmd_params[0] >> 10) & 0x3FF) << 6) >> 6) as i16;
-        self.normal_vector[2] = ((((self.cmd_params[0] >> 20) & 0x3FF) << 6) >> 6) as i16;
-        let normal_vector_i32 = self.normal_vector.map(|x| x as i32);
+        let normal_vector = unpack_vector(self.cmd_params[0]);
+        self.normal_vector = normal_vector.map(|x| x as i16);
 
         if self.teximage_param.transformation_mode == 2 {
-            self.current_texcoords[0] += ((normal_vector_i32[0] * self.texture_mtx.m[0][0]
-                + normal_vector_i32[1] * self.texture_mtx.m[1][0]
-                + normal_vector_i32[2] * self.texture_mtx.m[2][0])
+            self.current_texcoords[0] += ((normal_vector[0] * self.texture_mtx.m[0][0]
+                + normal_vector[1] * self.texture_mtx.m[1][0]
+                + normal_vector[2] * self.texture_mtx.m[2][0])
                 >> 21) as i16;
-            self.current_texcoords[1] += ((normal_vector_i32[0] * self.texture_mtx.m[0][1]
-                + normal_vector_i32[1] * self.texture_mtx.m[1][1]
-                + normal_vector_i32[2] * self.texture_mtx.m[2][1])
+            self.current_texcoords[1] += ((normal_vector[0] * self.texture_mtx.m[0][1]
+                + normal_vector[1] * self.texture_mtx.m[1][1]
+                + normal_vector[2] * self.texture_mtx.m[2][1])
                 >> 21) as i16;
         }
 
         let mut normal_vec = [0, 0, 0];
         for (i, normal) in normal_vec.iter_mut().enumerate() {
-            *normal = normal_vector_i32[0] * self.vector_mtx.m[0][i]
-                + normal_vector_i32[1] * self.vector_mtx.m[1][i]
-                + normal_vector_i32[2] * self.vector_mtx.m[2][i];
+            *normal = normal_vector[0] * self.vector_mtx.m[0][i]
+                + normal_vector[1] * self.vector_mtx.m[1][i]
+                + normal_vector[2] * self.vector_mtx.m[2][i];
             *normal >>= 12;
         }
 
-        let mut r = (self.emission_color & 0x1F) as u32;
-        let mut g = ((self.emission_color >> 5) & 0x1F) as u32;
-        let mut b = ((self.emission_color >> 10) & 0x1F) as u32;
+        let mut color = [0, 1, 2].map(|c| color_channel(self.emission_color, c));
 
         for light in 0..4 {
-            // if !(self.polygon_attr.light_enable >= 0 & (1 << light)) {
-            //     continue;
-            // }
-
-            let lr = (self.light_color[light] & 0x1F) as u32;
-            let lg = ((self.light_color[light] >> 5) & 0x1F) as u32;
-            let lb = ((self.light_color[light] >> 10) & 0x1F) as u32;
-
-            let light_direction_i32 = self.light_direction.map(|vec| vec.map(|x| x as i32));
+            if self.current_poly_attr.light_enable & (1 << light) == 0 {
+                continue;
+            }
+            let direction = self.light_direction[light].map(|x| x as i32);
 
-            let mut diffuse_level = (-(light_direction_i32[light][0] * normal_vec[0]
-                + light_direction_i32[light][1] * normal_vec[1]
-                + light_direction_i32[light][2] * normal_vec[2]))
+            let mut diffuse_level = -(direction[0] * normal_vec[0]
+                + direction[1] * normal_vec[1]
+                + direction[2] * normal_vec[2])
                 >> 10;
 
             // Overflow handling taken from melonDS (same goes for specular)
             diffuse_level = diffuse_level.clamp(0, 0xFF);
 
-            let mut shine_level = -(((light_direction_i32[light][0] >> 1) * normal_vec[0]
-                + (light_direction_i32[light][1] >> 1) * normal_vec[1]
-                + ((light_direction_i32[light][2] - 0x200) >> 1) * normal_vec[2])
+            // Against the half vector between the light and the line of sight
+            let mut shine_level = -(((direction[0] >> 1) * normal_vec[0]
+                + (direction[1] >> 1) * normal_vec[1]
+                + ((direction[2] - 0x200) >> 1) * normal_vec[2])
                 >> 10);
 
             if shine_level < 0 {
@@ -732,33 +794,33 @@ impl Gpu3D {
                 shine_level = self.shine_table[shine_level as usize] as i32;
             }
 
-            r += ((lr as i32 * (self.specular_color & 0x1F) as i32 * shine_level) >> 13) as u32;
-            g += ((lg as i32 * ((self.specular_color >> 5) & 0x1F) as i32 * shine_level) >> 13)
-                as u32;
-            b += ((lb as i32 * ((self.specular_color >> 10) & 0x1F) as i32 * shine_level) >> 13)
-                as u32;
+            for (c, value) in color.iter_mut().enumerate() {
+                let light_color = color_channel(self.light_color[light], c);
+                *value += (light_color * color_channel(self.specular_color, c) * shine_level) >> 13;
+                *value +=
+                    (light_color * color_channel(self.diffuse_color, c) * diffuse_level) >> 13;
+                *value += (light_color * color_channel(self.ambient_color, c)) >> 5;
+            }
+        }
 
-            r += ((lr as i32 * (self.diffuse_color & 0x1F) as i32 * diffuse_level) >> 13) as u32;
-            g += ((lg as i32 * ((self.diffuse_color >> 5) & 0x1F) as i32 * diffuse_level) >> 13)
-                as u32;
-            b += ((lb as i32 * ((self.diffuse_color >> 10) & 0x1F) as i32 * diffuse_level) >> 13)
-                as u32;
+        self.current_color = color
+            .iter()
+            .enumerate()
+            .map(|(c, &value)| (value.min(0x1F) as u32) << (c * 5))
+            .sum();
+    }
 
-            r += (lr * (self.ambient_color & 0x1F) as u32) >> 5;
-            g += (lg * ((self.ambient_color >> 5) & 0x1F) as u32) >> 5;
-            b += (lb * ((self.ambient_color >> 10) & 0x1F) as u32) >> 5;
+    /// LIGHT_VECTOR: set the direction of a light, through the vector matrix.
+    pub fn light_vector(&mut self) {
+        let param = self.cmd_params[0];
+        let vector = unpack_vector(param);
+        let light = (param >> 30) as usize;
 
-            if r > 0x1F {
-                r = 0x1F;
-            }
-            if g > 0x1F {
-                g = 0x1F;
-            }
-            if b > 0x1F {
-                b = 0x1F;
-            }
+        for i in 0..3 {
+            self.light_direction[light][i] = ((vector[0] * self.vector_mtx.m[0][i]
+                + vector[1] * self.vector_mtx.m[1][i]
+                + vector[2] * self.vector_mtx.m[2][i])
+                >> 12) as i16;
         }
-
-        self.current_color = r + (g << 5) + (b << 10);
     }
 }
@@ -109,6 +109,8 @@ impl PolygonAttrReg {
 }
 
 /// Viewport Register
+///
+/// Stored in screen orientation: `y1` is the bottom line, `y2` the top line.
 #[derive(Debug, Default)]
 pub struct ViewportReg {
     pub x1: u8,
@@ -212,6 +214,9 @@ pub struct Gpu3D {
     pub polygon_type: u32,
     /// Z-buffer clear value
     pub clear_depth: u32,
+    /// DISP_1DOT_DEPTH, W threshold (12.3) above which 1-dot polygons are
+    /// hidden unless their `render_1dot` attribute is set
+    pub dot_depth: u32,
     /// Color buffer clear value
     pub clear_color: u32,
     /// FIFO flush mode
@@ -347,6 +352,8 @@ pub struct Gpu3D {
     /// Use shininess table
     pub using_shine_table: bool,
 
+    /// Position test result (clip coordinates)
+    pub pos_test_result: [i32; 4],
     /// Vector test result
     pub vec_test_result: [i16; 3],
 
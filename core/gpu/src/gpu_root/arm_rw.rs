@@ -3,119 +3,31 @@
 //! gpu.hpp
 //!
 use crate::gpu_root::Gpu;
-use lunaris_ds_mem_const::{VRAM_C_MASK, VRAM_C_SIZE, VRAM_D_MASK, VRAM_D_SIZE, addr_in_range};
+use crate::gpu_root::taint::TaintRegion;
+use crate::gpu_root::vram_map::VramSpace;
 
 const OAM_MASK: u32 = 0x7FF;
 
 impl Gpu {
     pub fn read_arm7_u16(&self, address: u32) -> u16 {
-        {
-            let mut reg = 0;
-
-            if self.vramcnt_c.enabled
-                && addr_in_range(
-                    address,
-                    0x06000000 + self.vramcnt_c.offset * 0x20000,
-                    VRAM_C_SIZE,
-                )
-                && self.vramcnt_c.mst == 2
-            {
-                let start = (address & VRAM_C_MASK) as usize;
-                let end = start + 2;
-                reg |= u16::from_le_bytes(self.vram_c[start..end].try_into().unwrap());
-            }
-
-            if self.vramcnt_d.enabled
-                && addr_in_range(
-                    address,
-                    0x06000000 + self.vramcnt_d.offset * 0x20000,
-                    VRAM_D_SIZE,
-                )
-                && self.vramcnt_d.mst == 2
-            {
-                let start = (address & VRAM_D_MASK) as usize;
-                let end = start + 2;
-                reg |= u16::from_le_bytes(self.vram_d[start..end].try_into().unwrap());
-            }
-
-            reg
-        }
+        u16::from_le_bytes([
+            self.read_cpu_vram_u8(VramSpace::Arm7, address),
+            self.read_cpu_vram_u8(VramSpace::Arm7, address + 1),
+        ])
     }
 
     pub fn read_arm7_u32(&self, address: u32) -> u32 {
-        {
-            let mut reg = 0;
-            if self.vramcnt_c.enabled
-                && addr_in_range(
-                    address,
-                    0x06000000 + self.vramcnt_c.offset * 0x20000,
-                    VRAM_C_SIZE,
-                )
-                && self.vramcnt_c.mst == 2
-            {
-                let start = (address & VRAM_C_MASK) as usize;
-                let end = start + 4;
-                reg |= u32::from_le_bytes(self.vram_c[start..end].try_into().unwrap());
-            }
-
-            if self.vramcnt_d.enabled
-                && addr_in_range(
-                    address,
-                    0x06000000 + self.vramcnt_d.offset * 0x20000,
-                    VRAM_D_SIZE,
-                )
-                && self.vramcnt_d.mst == 2
-            {
-                let start = (address & VRAM_D_MASK) as usize;
-                let end = start + 4;
-                reg |= u32::from_le_bytes(self.vram_d[start..end].try_into().unwrap());
-            }
-
-            reg
-        }
+        self.read_arm7_u16(address) as u32 | (self.read_arm7_u16(address + 2) as u32) << 16
     }
 
     /// Write 32-bit word from ARM7 to VRAM (C and D)
     pub fn write_arm7_u32(&mut self, address: u32, value: u32) {
-        // VRAM C
-        if self.vramcnt_c.enabled {
-            let start = 0x06000000 + self.vramcnt_c.offset * 0x20000;
-            if addr_in_range(address, start, VRAM_C_SIZE) && self.vramcnt_c.mst == 2 {
-                let start = (address & VRAM_C_MASK) as usize;
-                self.vram_c[start..start + 4].copy_from_slice(&value.to_le_bytes());
-            }
-        }
-
-        // VRAM D
-        if self.vramcnt_d.enabled {
-            let start = 0x06000000 + self.vramcnt_d.offset * 0x20000;
-            if addr_in_range(address, start, VRAM_D_SIZE) && self.vramcnt_d.mst == 2 {
-                let start = (address & VRAM_D_MASK) as usize;
-                self.vram_d[start..start + 4].copy_from_slice(&value.to_le_bytes());
-            }
-        }
+        self.write_cpu_vram(VramSpace::Arm7, address, &value.to_le_bytes());
     }
 
     /// Write 8-bit byte from ARM7 to VRAM (C and D)
-    /// Matches the behavior of C++: VRAM_C[address & mask] = value;
     pub fn write_arm7_u8(&mut self, address: u32, value: u8) {
-        // VRAM C
-        if self.vramcnt_c.enabled {
-            let start = 0x06000000 + self.vramcnt_c.offset * 0x20000;
-            if addr_in_range(address, start, VRAM_C_SIZE) && self.vramcnt_c.mst == 2 {
-                let idx = (address & VRAM_C_MASK) as usize;
-                self.vram_c[idx] = value;
-            }
-        }
-
-        // VRAM D
-        if self.vramcnt_d.enabled {
-            let start = 0x06000000 + self.vramcnt_d.offset * 0x20000;
-            if addr_in_range(address, start, VRAM_D_SIZE) && self.vramcnt_d.mst == 2 {
-                let idx = (address & VRAM_D_MASK) as usize;
-                self.vram_d[idx] = value;
-            }
-        }
+        self.write_cpu_vram(VramSpace::Arm7, address, &[value]);
     }
 
     pub fn read_oam_u8(&self, address: u32) -> u8 {
@@ -125,6 +37,7 @@ impl Gpu {
 
     pub fn read_oam_u16(&self, address: u32) -> u16 {
         let idx = (address & OAM_MASK) as usize;
+        self.taint.check(TaintRegion::Oam, idx, 2);
 
         let lo = self.oam[idx];
         let hi = self.oam[(idx + 1) & OAM_MASK as usize];
@@ -145,6 +58,7 @@ impl Gpu {
 
     pub fn read_oam_i16(&self, address: u32) -> i16 {
         let idx = (address & OAM_MASK) as usize;
+        self.taint.check(TaintRegion::Oam, idx, 2);
 
         let lo = self.oam[idx];
         let hi = self.oam[(idx + 1) & OAM_MASK as usize];
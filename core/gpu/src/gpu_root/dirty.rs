@@ -0,0 +1,169 @@
+//! Write tracking of VRAM, palette and OAM
+//!
+//! Every write marks its 1KB block in a [`DirtyMap`], one bitmap per
+//! [`TaintRegion`]. The renderer drains its own map at the start of each
+//! scanline to refresh what it caches across frames; frontends and debug
+//! viewers take theirs with [`Gpu::take_dirty`], so neither consumer hides
+//! writes from the other.
+use crate::gpu_root::Gpu;
+use crate::gpu_root::taint::TaintRegion;
+
+/// Bytes covered by one dirty bit.
+pub const DIRTY_BLOCK_SIZE: usize = 1024;
+
+/// Written blocks of each region.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirtyMap {
+    /// One bit per block, indexed by `TaintRegion as usize`
+    bits: Vec<Vec<u64>>,
+}
+
+impl Default for DirtyMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DirtyMap {
+    /// A map with nothing written.
+    pub fn new() -> Self {
+        Self {
+            bits: TaintRegion::ALL
+                .iter()
+                .map(|region| vec![0; region.size().div_ceil(DIRTY_BLOCK_SIZE).div_ceil(64)])
+                .collect(),
+        }
+    }
+
+    /// Mark the blocks holding `len` bytes from `offset`.
+    #[inline]
+    pub(crate) fn mark(&mut self, region: TaintRegion, offset: usize, len: usize) {
+        if len == 0 {
+            return;
+        }
+        let bits = &mut self.bits[region as usize];
+        for block in offset / DIRTY_BLOCK_SIZE..=(offset + len - 1) / DIRTY_BLOCK_SIZE {
+            if let Some(word) = bits.get_mut(block / 64) {
+                *word |= 1 << (block % 64);
+            }
+        }
+    }
+
+    /// Mark all of `region`.
+    pub(crate) fn mark_all(&mut self, region: TaintRegion) {
+        self.mark(region, 0, region.size());
+    }
+
+    /// Whether block `block` of `region` was written.
+    pub fn is_dirty(&self, region: TaintRegion, block: usize) -> bool {
+        self.bits[region as usize]
+            .get(block / 64)
+            .is_some_and(|word| word & (1 << (block % 64)) != 0)
+    }
+
+    /// Whether anything in `region` was written.
+    pub fn is_region_dirty(&self, region: TaintRegion) -> bool {
+        self.bits[region as usize].iter().any(|&word| word != 0)
+    }
+
+    /// Written blocks of `region`, in order.
+    pub fn blocks(&self, region: TaintRegion) -> impl Iterator<Item = usize> + '_ {
+        self.bits[region as usize]
+            .iter()
+            .enumerate()
+            .flat_map(|(index, &word)| {
+                (0..64)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| index * 64 + bit)
+            })
+    }
+
+    /// Whether nothing was written.
+    pub fn is_clean(&self) -> bool {
+        self.bits.iter().flatten().all(|&word| word == 0)
+    }
+
+    /// Forget `region` and report whether it was written.
+    pub(crate) fn take_region(&mut self, region: TaintRegion) -> bool {
+        let dirty = self.is_region_dirty(region);
+        self.bits[region as usize].fill(0);
+        dirty
+    }
+}
+
+impl Gpu {
+    /// Record a write of `len` bytes from `offset` in `region`.
+    #[inline]
+    pub(crate) fn note_write(&mut self, region: TaintRegion, offset: usize, len: usize) {
+        self.taint.mark(region, offset, len);
+        self.dirty.mark(region, offset, len);
+        self.render_dirty.mark(region, offset, len);
+    }
+
+    /// Blocks written since the last call, see [`dirty`](crate::gpu_root::dirty).
+    pub fn take_dirty(&mut self) -> DirtyMap {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Refresh renderer caches from the writes since the last scanline.
+    pub(crate) fn refresh_render_caches(&mut self) {
+        if self.render_dirty.take_region(TaintRegion::PaletteUpper) {
+            decode_palette(&self.palette_upper, &mut self.palette_argb[0]);
+        }
+        if self.render_dirty.take_region(TaintRegion::PaletteLower) {
+            decode_palette(&self.palette_lower, &mut self.palette_argb[1]);
+        }
+    }
+
+    /// Standard palette entry `index` of an engine as ARGB8888.
+    #[inline]
+    pub(crate) fn palette_argb(&self, is_engine_a: bool, index: usize) -> u32 {
+        self.taint
+            .check(TaintRegion::palette(is_engine_a), index * 2, 2);
+        self.palette_argb[!is_engine_a as usize][index & 0x1FF]
+    }
+}
+
+/// BGR555 to ARGB8888, as the renderer writes it to framebuffers.
+#[inline]
+pub(crate) fn bgr555_to_argb(color: u16) -> u32 {
+    let r = ((color & 0x1F) << 3) as u32;
+    let g = (((color >> 5) & 0x1F) << 3) as u32;
+    let b = (((color >> 10) & 0x1F) << 3) as u32;
+    0xFF000000 | (r << 16) | (g << 8) | b
+}
+
+fn decode_palette(palette: &[u8], argb: &mut [u32]) {
+    for (out, bytes) in argb.iter_mut().zip(palette.chunks_exact(2)) {
+        *out = bgr555_to_argb(u16::from_le_bytes([bytes[0], bytes[1]]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirty_blocks() {
+        let mut gpu = Gpu::new();
+        gpu.power_on();
+        gpu.take_dirty();
+        // LCDC bank A, blocks 0 and 2
+        gpu.set_vramcnt_a(0x80);
+        gpu.write_lcdc(0x0680_0000, 1);
+        gpu.write_lcdc(0x0680_0800, 1);
+        gpu.write_lcdc(0x0680_0802, 1);
+        gpu.write_palette_b(0x3FE, 0x7FFF);
+
+        let dirty = gpu.take_dirty();
+        assert_eq!(dirty.blocks(TaintRegion::VramA).collect::<Vec<_>>(), [0, 2]);
+        assert!(dirty.is_dirty(TaintRegion::PaletteLower, 0));
+        assert!(!dirty.is_region_dirty(TaintRegion::PaletteUpper));
+        assert!(gpu.take_dirty().is_clean());
+
+        // The renderer keeps its own map
+        assert_eq!(gpu.palette_argb(false, 0x1FF), 0xFF000000);
+        gpu.refresh_render_caches();
+        assert_eq!(gpu.palette_argb(false, 0x1FF), 0xFFF8F8F8);
+    }
+}
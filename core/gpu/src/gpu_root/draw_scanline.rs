@@ -3,6 +3,8 @@
 //! gpu.hpp
 //!
 use crate::gpu_root::Gpu;
+use crate::gpu_root::taint::TaintRegion;
+use crate::vram_bytes::{read_u16, write_u16};
 use lunaris_ds_mem_const::*;
 
 impl Gpu {
@@ -12,16 +14,66 @@ impl Gpu {
 
     /// Draws one scanline.
     pub fn draw_scanline(&mut self) {
-        let is_engine_a = if self.power_control_reg.engine_upper {
-            true
-        } else if self.power_control_reg.engine_lower {
-            false
-        } else {
-            return;
-        };
+        self.process_scanline(true);
+    }
+
+    /// Runs a scanline of a frame skipped by frameskip.
+    ///
+    /// Nothing visible is written, but display capture and the main memory
+    /// display FIFO still advance, since games rely on their side effects
+    /// (e.g. motion blur or dual-screen 3D feed the captured frame back).
+    pub fn skip_scanline(&mut self) {
+        self.process_scanline(false);
+    }
 
+    /// Shared scanline pipeline; `visible` selects whether the front
+    /// framebuffer is updated.
+    ///
+    /// Both powered engines compose their line, engine A first, so a frame
+    /// captured by engine A can be shown by engine B on the other screen.
+    fn process_scanline(&mut self, visible: bool) {
+        self.taint.begin_line();
+        self.refresh_render_caches();
+        if self.power_control_reg.engine_upper {
+            self.process_engine_scanline(true, visible);
+        }
+        if self.power_control_reg.engine_lower {
+            self.process_engine_scanline(false, visible);
+        }
+        self.engine_upper.advance_affine_references();
+        self.engine_lower.advance_affine_references();
+        self.taint.end_line(self.get_vcount());
+    }
+
+    fn process_engine_scanline(&mut self, is_engine_a: bool, visible: bool) {
         let line_start = self.get_vcount() as usize * PIXELS_PER_LINE;
 
+        let (capture_enabled, uses_display_fifo) = {
+            let engine = match is_engine_a {
+                true => &self.engine_upper,
+                false => &self.engine_lower,
+            };
+            let capture_enabled = is_engine_a && engine.dispcapcnt.enable_busy;
+            (
+                capture_enabled,
+                is_engine_a
+                    && (engine.dispcnt.display_mode == 3
+                        || (capture_enabled && engine.dispcapcnt.b_display_fifo)),
+            )
+        };
+
+        // The FIFO is drained once per line whether or not the line is shown
+        let fifo_line = match uses_display_fifo {
+            true => self.pop_display_fifo_line(),
+            false => [0u16; PIXELS_PER_LINE],
+        };
+
+        // Capture source A is the composed line, so only skip composition
+        // when nothing would observe it
+        if !visible && !capture_enabled {
+            return;
+        }
+
         // Clear scanline
         for i in 0..PIXELS_PER_LINE {
             let engine = match is_engine_a {
@@ -32,7 +84,9 @@ impl Gpu {
             // safe access
             if (line_start + i) < PIXELS_PER_LINE * SCANLINES {
                 engine.framebuffer[line_start + i] = 0xFF000000;
-                engine.front_framebuffer[line_start + i] = 0xFF000000;
+                if visible {
+                    engine.front_framebuffer[line_start + i] = 0xFF000000;
+                }
             }
         }
 
@@ -48,6 +102,19 @@ impl Gpu {
         // Draw backdrop
         self.draw_backdrop(is_engine_a);
 
+        let (display_obj, display_mode) = {
+            let engine = match is_engine_a {
+                true => &mut self.engine_upper,
+                false => &mut self.engine_lower,
+            };
+            (engine.dispcnt.display_obj, engine.dispcnt.display_mode)
+        };
+
+        // OBJs go first, the OBJ window is made of them
+        if display_obj {
+            self.draw_sprite_line(is_engine_a);
+        }
+
         let window_masked = {
             let engine = match is_engine_a {
                 true => &mut self.engine_upper,
@@ -69,34 +136,22 @@ impl Gpu {
             engine.window_mask.fill(0xFF);
         }
 
-        // Draw BG layers by priority
+        // Draw BG layers back to front, each priority topped by its OBJs,
+        // which cover every BG of the same priority. Within a priority the
+        // lower BG is in front, so the 3D layer in place of BG0 covers
+        // bitmap BG2/BG3 sharing its priority
         for priority in (0..=3).rev() {
-            let bg_enable = {
-                let engine = match is_engine_a {
-                    true => &mut self.engine_upper,
-                    false => &mut self.engine_lower,
-                };
-                engine.bgcnt
-            };
-            for (bg_index, bg_enable_value) in bg_enable.iter().enumerate() {
-                if *bg_enable_value == 0 {
-                    continue;
-                }
+            // Layers are enabled by DISPCNT; BGCNT = 0 is a valid setup
+            for bg_index in (0..4).rev() {
                 let engine = match is_engine_a {
                     true => &mut self.engine_upper,
                     false => &mut self.engine_lower,
                 };
-                if (engine.bgcnt[bg_index] & 0x3) as u8 != priority {
+                if engine.bgcnt[bg_index].priority != priority {
                     continue;
                 }
 
-                if !match bg_index {
-                    0 => engine.dispcnt.display_bg0, // => bool
-                    1 => engine.dispcnt.display_bg1,
-                    2 => engine.dispcnt.display_bg2,
-                    3 => engine.dispcnt.display_bg3,
-                    _ => unreachable!(),
-                } {
+                if !engine.bg_displayed(bg_index) {
                     continue;
                 }
 
@@ -105,42 +160,37 @@ impl Gpu {
                         if is_engine_a && engine.dispcnt.bg_3d {
                             self.draw_3d_scanline(is_engine_a, priority);
                         } else {
-                            self.draw_bg_txt(0, is_engine_a);
+                            self.draw_bg_txt_line(0, is_engine_a);
                         }
                     }
-                    1 => self.draw_bg_txt(1, is_engine_a),
+                    1 => self.draw_bg_txt_line(1, is_engine_a),
                     2 => match engine.dispcnt.bg_mode {
-                        0 | 1 | 3 => self.draw_bg_txt(2, is_engine_a),
-                        5 => self.draw_bg_ext(2, is_engine_a),
+                        0 | 1 | 3 => self.draw_bg_txt_line(2, is_engine_a),
+                        2 | 4 => self.draw_bg_affine_line(2, is_engine_a),
+                        5 => self.draw_bg_extended_line(2, is_engine_a),
                         _ => {}
                     },
                     3 => match engine.dispcnt.bg_mode {
-                        0 | 3 | 4 | 5 => self.draw_bg_ext(3, is_engine_a),
+                        0 => self.draw_bg_txt_line(3, is_engine_a),
+                        1 | 2 => self.draw_bg_affine_line(3, is_engine_a),
+                        3..=5 => self.draw_bg_extended_line(3, is_engine_a),
                         _ => {}
                     },
                     _ => {}
                 }
             }
-        }
-
-        let (display_obj, display_mode) = {
-            let engine = match is_engine_a {
-                true => &mut self.engine_upper,
-                false => &mut self.engine_lower,
-            };
-            (engine.dispcnt.display_obj, engine.dispcnt.display_mode)
-        };
 
-        // Draw sprites
-        if display_obj {
-            self.draw_sprites(is_engine_a);
+            if display_obj {
+                self.draw_sprites(is_engine_a, priority);
+            }
         }
 
-        // Handle blending effects
-        self.handle_bldcnt_effects();
+        // Color special effects
+        self.handle_bldcnt_effects(is_engine_a);
 
         // Display mode handling
         match display_mode {
+            _ if !visible => {}
             0 => {
                 for i in 0..PIXELS_PER_LINE {
                     let engine = match is_engine_a {
@@ -168,48 +218,41 @@ impl Gpu {
                     }
                 }
             }
-            2 => {
-                let vram_block = {
-                    let engine = match is_engine_a {
-                        true => &mut self.engine_upper,
-                        false => &mut self.engine_lower,
-                    };
-                    engine.dispcnt.vram_block
-                };
+            // VRAM and FIFO display are engine A only
+            2 if is_engine_a => {
+                let vram_block = self.engine_upper.dispcnt.vram_block;
                 for x in 0..PIXELS_PER_LINE {
                     let ds_color = {
+                        if let Some(region) = TaintRegion::vram(vram_block) {
+                            self.taint.check(region, (line_start + x) * 2, 2);
+                        }
                         let vram = self.get_vram_block(vram_block);
-                        vram[line_start + x]
+                        read_u16(vram, line_start + x)
                     };
 
                     let r = ((ds_color & 0x1F) << 3) as u32;
                     let g = (((ds_color >> 5) & 0x1F) << 3) as u32;
                     let b = (((ds_color >> 10) & 0x1F) << 3) as u32;
 
-                    let engine = match is_engine_a {
-                        true => &mut self.engine_upper,
-                        false => &mut self.engine_lower,
-                    };
+                    self.engine_upper.front_framebuffer[line_start + x] =
+                        0xFF000000 | (r << 16) | (g << 8) | b
+                }
+            }
+            3 if is_engine_a => {
+                let engine = &mut self.engine_upper;
+                for (x, ds_color) in fifo_line.iter().enumerate() {
+                    let r = ((ds_color & 0x1F) << 3) as u32;
+                    let g = (((ds_color >> 5) & 0x1F) << 3) as u32;
+                    let b = (((ds_color >> 10) & 0x1F) << 3) as u32;
                     engine.front_framebuffer[line_start + x] = 0xFF000000 | (r << 16) | (g << 8) | b
                 }
             }
             _ => {}
         }
 
-        let (enable_a_busy, capture_size) = {
-            let engine = match is_engine_a {
-                true => &mut self.engine_upper,
-                false => &mut self.engine_lower,
-            };
-            (
-                is_engine_a && engine.dispcapcnt.enable_busy,
-                engine.dispcapcnt.capture_size,
-            )
-        };
-
         // Capture (DISPCAPCNT)
-        if enable_a_busy {
-            let (x_size, y_size) = match capture_size {
+        if capture_enabled {
+            let (x_size, y_size) = match self.engine_upper.dispcapcnt.capture_size {
                 0 => (128, 128),
                 1 => (256, 64),
                 2 => (256, 128),
@@ -217,7 +260,8 @@ impl Gpu {
                 _ => (0, 0),
             };
 
-            if self.get_vcount() < y_size {
+            let write_block = self.engine_upper.dispcapcnt.vram_write_block as i32;
+            if self.get_vcount() < y_size && self.is_vram_lcdc(write_block) {
                 let (read_offset, write_offset, vram_write_block, vram_block) = {
                     let engine = match is_engine_a {
                         true => &mut self.engine_upper,
@@ -239,15 +283,22 @@ impl Gpu {
                     )
                 };
 
-                for x in 0..x_size {
+                for (x, &fifo_color) in fifo_line.iter().enumerate().take(x_size) {
                     let engine = match is_engine_a {
                         true => &self.engine_upper,
                         false => &self.engine_lower,
                     };
                     let source_a = engine.framebuffer[line_start + x];
-                    let source_b = {
-                        let vram_src = self.get_vram_block(vram_block);
-                        vram_src[(read_offset + x) & 0xFFFF] as u32
+                    let source_b = match engine.dispcapcnt.b_display_fifo {
+                        true => fifo_color as u32,
+                        false => {
+                            let index = (read_offset + x) & 0xFFFF;
+                            if let Some(region) = TaintRegion::vram(vram_block) {
+                                self.taint.check(region, index * 2, 2);
+                            }
+                            let vram_src = self.get_vram_block(vram_block);
+                            read_u16(vram_src, index) as u32
+                        }
                     };
 
                     let (ra, ga, ba) = (
@@ -287,13 +338,20 @@ impl Gpu {
                     gd = gd.min(0x1F);
                     bd = bd.min(0x1F);
 
+                    let index = (write_offset + x) & 0xFFFF;
                     let vram_dest = self.get_vram_block_mut(vram_write_block);
-                    vram_dest[(write_offset + x) & 0xFFFF] =
-                        rd | (gd << 5) | (bd << 10) | (1 << 15);
+                    write_u16(vram_dest, index, rd | (gd << 5) | (bd << 10) | (1 << 15));
+                    if let Some(region) = TaintRegion::vram(vram_write_block) {
+                        self.note_write(region, index * 2, 2);
+                    }
                 }
             }
         }
 
+        if !visible {
+            return;
+        }
+
         let engine = match is_engine_a {
             true => &mut self.engine_upper,
             false => &mut self.engine_lower,
@@ -343,3 +401,126 @@ impl Gpu {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Engine A with BG0 showing a red tile, display mode 3 and a full
+    /// screen capture of the graphics into bank B.
+    fn capture_setup() -> Gpu {
+        let mut gpu = Gpu::new();
+        gpu.power_on();
+        gpu.set_vramcnt_a(0x81);
+        gpu.set_vramcnt_b(0x80);
+        gpu.set_dispcnt_a(0x0003_0100);
+        gpu.set_bgcnt_a(0x0004, 0);
+        for offset in (0..32).step_by(2) {
+            gpu.write_bga(0x0600_4020 + offset, 0x1111);
+        }
+        gpu.write_bga(0x0600_0000, 0x0001);
+        gpu.write_palette_a(2, 0x001F);
+        gpu.set_dispcapcnt(0x8031_0000);
+        gpu
+    }
+
+    /// Feed one line to the display FIFO and run it, shown or skipped.
+    fn run_frame(gpu: &mut Gpu, visible: bool) {
+        for y in 0..SCANLINES as u16 {
+            gpu.vertical_count = y;
+            for x in 0..128 {
+                let pixel = (y * 128 + x) as u32 & 0x7FFF;
+                gpu.write_disp_mmem_fifo(pixel | (pixel << 16));
+            }
+            match visible {
+                true => gpu.draw_scanline(),
+                false => gpu.skip_scanline(),
+            }
+            assert!(gpu.display_fifo.is_empty(), "line {y}");
+        }
+    }
+
+    #[test]
+    fn test_skipped_frame_side_effects() {
+        let mut full = capture_setup();
+        let mut skipped = capture_setup();
+        let front = skipped.engine_upper.front_framebuffer.clone();
+
+        run_frame(&mut full, true);
+        run_frame(&mut skipped, false);
+
+        // Same capture, nothing shown
+        assert!(full.vram_b.iter().any(|&byte| byte != 0));
+        assert_eq!(skipped.vram_b, full.vram_b);
+        assert_eq!(skipped.engine_upper.front_framebuffer, front);
+        assert_ne!(full.engine_upper.front_framebuffer, front);
+
+        // The FIFO stayed in step, so the next shown frame matches too
+        run_frame(&mut full, true);
+        run_frame(&mut skipped, true);
+        assert_eq!(skipped.vram_b, full.vram_b);
+        assert_eq!(
+            skipped.engine_upper.front_framebuffer,
+            full.engine_upper.front_framebuffer
+        );
+    }
+
+    /// Compose every visible line, as the HBlank events do, then start VBlank.
+    fn draw_frame(gpu: &mut Gpu) {
+        for y in 0..SCANLINES as u16 {
+            gpu.vertical_count = y;
+            gpu.draw_scanline();
+        }
+        gpu.engine_upper.vblank_start();
+    }
+
+    #[test]
+    fn test_dual_screen_capture() {
+        /// Capture 256x192 of source A (engine A output) to bank C/D
+        const CAPTURE_TO_C: u32 = 0x8032_0000;
+        const CAPTURE_TO_D: u32 = 0x8033_0000;
+        // Inside / outside the red tile
+        let (inside, outside) = (3 * PIXELS_PER_LINE + 3, 10 * PIXELS_PER_LINE + 245);
+
+        // Frame 1: engine A on top shows BG0, captured to C in LCDC mode
+        let mut gpu = capture_setup();
+        gpu.set_dispcnt_a(0x0001_0100);
+        gpu.set_vramcnt_c(0x80);
+        gpu.set_vramcnt_d(0x80);
+        gpu.set_powcnt1(0x820F);
+        gpu.set_dispcapcnt(CAPTURE_TO_C);
+        draw_frame(&mut gpu);
+        assert!(!gpu.engine_upper.dispcapcnt.enable_busy);
+        let captured = gpu.get_vram_block(2).to_vec();
+        assert_eq!(read_u16(&captured, inside), 0x801F);
+        assert_eq!(read_u16(&captured, outside), 0x8000);
+
+        // Frame 2: screens swapped, engine B shows C as a direct color
+        // bitmap while engine A draws again and is captured to D
+        gpu.set_vramcnt_c(0x84);
+        gpu.set_dispcnt_b(0x0001_0805);
+        gpu.set_bgcnt_b(0x4084, 3);
+        gpu.set_bg3p_b(0x0100, 0);
+        gpu.set_bg3p_b(0x0100, 3);
+        gpu.set_powcnt1(0x020F);
+        gpu.set_dispcapcnt(CAPTURE_TO_D);
+        draw_frame(&mut gpu);
+
+        let mut upper = vec![0; PIXELS_PER_LINE * SCANLINES];
+        let mut lower = vec![0; PIXELS_PER_LINE * SCANLINES];
+        gpu.get_upper_frame(&mut upper);
+        gpu.get_lower_frame(&mut lower);
+        // Engine A on the lower screen draws live, engine B on the upper one
+        // shows last frame's capture
+        assert_eq!(lower[inside], 0xFFF8_0000);
+        assert_eq!(upper[inside], 0xFFF8_0000);
+        assert_eq!(upper[outside], 0xFF00_0000);
+        assert_eq!(read_u16(gpu.get_vram_block(3), inside), 0x801F);
+
+        // Capture only writes banks mapped to the LCDC
+        gpu.write_palette_a(2, 0x03E0);
+        gpu.set_dispcapcnt(CAPTURE_TO_C);
+        draw_frame(&mut gpu);
+        assert_eq!(gpu.get_vram_block(2), captured);
+    }
+}
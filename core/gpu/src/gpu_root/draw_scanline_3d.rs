@@ -202,10 +202,6 @@ impl Gpu {
             let texparams = poly.texparams.clone();
             let texture_mapping = self.engine_3d.disp3dcnt.texture_mapping && texparams.format != 0;
 
-            let tex_width = 8 << texparams.s_size;
-            let tex_height = 8 << texparams.t_size;
-            let tex_vram_offset = texparams.vram_offset * 8;
-
             // Fill the polygon
             for x in left_x..=right_x {
                 let pix_pos = (x - left_x) as u64;
@@ -287,11 +283,9 @@ impl Gpu {
                     vb += 1;
                 }
 
-                // =================================================
-                // TEXTURE
-                // =================================================
+                // ===== texture =====
                 if texture_mapping {
-                    let mut s = (self.engine_3d.interpolate(
+                    let s = (self.engine_3d.interpolate(
                         pix_pos,
                         line_len,
                         left_s as i64,
@@ -299,7 +293,7 @@ impl Gpu {
                         left_w,
                         right_w,
                     ) >> 4) as i32;
-                    let mut t = (self.engine_3d.interpolate(
+                    let t = (self.engine_3d.interpolate(
                         pix_pos,
                         line_len,
                         left_t as i64,
@@ -308,272 +302,8 @@ impl Gpu {
                         right_w,
                     ) >> 4) as i32;
 
-                    // ---- clamp / repeat S ----
-                    if !texparams.repeat_s {
-                        if s < 0 {
-                            s = 0;
-                        }
-                        if s >= tex_width {
-                            s = tex_width - 1;
-                        }
-                    } else if texparams.flip_s && (s & tex_width) != 0 {
-                        // Repeat
-                        s = (tex_width - 1) - (s & (tex_width - 1));
-                    } else {
-                        s &= tex_width - 1;
-                    }
-
-                    // ---- clamp / repeat T ----
-                    if !texparams.repeat_t {
-                        if t < 0 {
-                            t = 0;
-                        }
-                        if t >= tex_height {
-                            t = tex_height - 1;
-                        }
-                    } else if texparams.flip_t && (t & tex_height) != 0 {
-                        t = (tex_height - 1) - (t & (tex_height - 1));
-                    } else {
-                        t &= tex_height - 1;
-                    }
-
-                    match texparams.format {
-                        1 => {
-                            // A3I5
-                            let mut texel_addr = s as u32;
-                            texel_addr += (t as u32) * tex_width as u32;
-                            texel_addr += tex_vram_offset as u32;
-
-                            let data = self.read_teximage_u8(texel_addr);
-
-                            let color_index = data & 0x1F;
-                            let alpha_index = data >> 5;
-
-                            ta = ((alpha_index << 2) + (alpha_index >> 1)) as u32;
-
-                            if color_index != 0 || !texparams.color0_transparent {
-                                let mut pal_addr = (color_index as u32) * 2;
-                                pal_addr += poly.palette_base * 0x10;
-
-                                let pal_color = self.read_texpal_u16(pal_addr);
-
-                                tr = ((pal_color & 0x1F) << 1) as u32;
-                                tg = (((pal_color >> 5) & 0x1F) << 1) as u32;
-                                tb = (((pal_color >> 10) & 0x1F) << 1) as u32;
-                            } else {
-                                ta = 0;
-                            }
-                        }
-
-                        2 => {
-                            // 4 color palette
-                            let mut texel_addr = s as u32;
-                            texel_addr += (t as u32) * tex_width as u32;
-                            texel_addr /= 4;
-                            texel_addr += tex_vram_offset as u32;
-
-                            let mut data = self.read_teximage_u8(texel_addr);
-                            data = (data >> ((texel_addr & 0x3) * 2)) & 0x3;
-
-                            if data != 0 || !texparams.color0_transparent {
-                                let mut pal_addr = (data as u32) * 2;
-                                pal_addr += poly.palette_base * 0x8;
-
-                                let pal_color = self.read_texpal_u16(pal_addr);
-
-                                tr = ((pal_color & 0x1F) << 1) as u32;
-                                tg = (((pal_color >> 5) & 0x1F) << 1) as u32;
-                                tb = (((pal_color >> 10) & 0x1F) << 1) as u32;
-                            } else {
-                                ta = 0;
-                            }
-                        }
-
-                        3 => {
-                            // 16 color palette
-                            let mut texel_addr = s as u32;
-                            texel_addr += (t as u32) * tex_width as u32;
-                            texel_addr /= 2;
-                            texel_addr += tex_vram_offset as u32;
-
-                            let mut data = self.read_teximage_u8(texel_addr);
-
-                            if (s & 1) != 0 {
-                                data >>= 4;
-                            } else {
-                                data &= 0xF;
-                            }
-
-                            if data != 0 || !texparams.color0_transparent {
-                                let mut pal_addr = (data as u32) * 2;
-                                pal_addr += poly.palette_base * 0x10;
-
-                                let pal_color = self.read_texpal_u16(pal_addr);
-
-                                tr = ((pal_color & 0x1F) << 1) as u32;
-                                tg = (((pal_color >> 5) & 0x1F) << 1) as u32;
-                                tb = (((pal_color >> 10) & 0x1F) << 1) as u32;
-                            } else {
-                                ta = 0;
-                            }
-                        }
-
-                        4 => {
-                            // 256 color palette
-                            let mut texel_addr = s as u32;
-                            texel_addr += (t as u32) * tex_width as u32;
-                            texel_addr += tex_vram_offset as u32;
-
-                            let data = self.read_teximage_u8(texel_addr);
-
-                            if data != 0 || !texparams.color0_transparent {
-                                let mut pal_addr = (data as u32) * 2;
-                                pal_addr += poly.palette_base * 0x10;
-
-                                let pal_color = self.read_texpal_u16(pal_addr);
-
-                                tr = ((pal_color & 0x1F) << 1) as u32;
-                                tg = (((pal_color >> 5) & 0x1F) << 1) as u32;
-                                tb = (((pal_color >> 10) & 0x1F) << 1) as u32;
-                            } else {
-                                ta = 0;
-                            }
-                        }
-
-                        5 => {
-                            // Compressed 4x4
-                            let mut texel_addr = (s as u32) & 0x3FC;
-                            texel_addr += ((t as u32) & 0x3FC) * ((tex_width as u32) >> 2);
-                            texel_addr += tex_vram_offset as u32;
-                            texel_addr += (t as u32) & 0x3;
-
-                            let mut slot1_addr = 0x20000 + ((texel_addr >> 1) & 0xFFFE);
-                            if texel_addr >= 0x40000 {
-                                slot1_addr += 0x10000;
-                            }
-
-                            let mut data = self.read_teximage_u8(texel_addr);
-                            data >>= 2 * (s as u32 & 0x3);
-                            data &= 0x3;
-
-                            let palette_data = self.read_teximage_u16(slot1_addr);
-                            let palette_offset = ((palette_data & 0x3FFF) as u32) << 2;
-
-                            let palette_base = poly.palette_base * 0x10;
-
-                            fn calc_rgb_color(
-                                color0: u16,
-                                color1: u16,
-                                mul: u16,
-                                shift: u16,
-                            ) -> u16 {
-                                let r0 = color0 & 0x1F;
-                                let r1 = color1 & 0x1F;
-                                let g0 = (color0 >> 5) & 0x1F;
-                                let g1 = (color1 >> 5) & 0x1F;
-                                let b0 = (color0 >> 10) & 0x1F;
-                                let b1 = (color1 >> 10) & 0x1F;
-
-                                let r = (r0 * 5 + r1 * mul) >> shift;
-                                let g = (g0 * 5 + g1 * mul) >> shift;
-                                let b = (b0 * 5 + b1 * mul) >> shift;
-                                r | (g << 5) | (b << 10)
-                            }
-
-                            let color: u16 = match data {
-                                0 => self.read_texpal_u16(palette_base + palette_offset),
-                                1 => self.read_texpal_u16(palette_base + palette_offset + 2),
-                                2 => match palette_data >> 14 {
-                                    1 => {
-                                        let color0 =
-                                            self.read_texpal_u16(palette_base + palette_offset);
-                                        let color1 =
-                                            self.read_texpal_u16(palette_base + palette_offset + 2);
-                                        calc_rgb_color(color0, color1, 1, 1)
-                                    }
-                                    3 => {
-                                        let color0 =
-                                            self.read_texpal_u16(palette_base + palette_offset);
-                                        let color1 =
-                                            self.read_texpal_u16(palette_base + palette_offset + 2);
-                                        calc_rgb_color(color0, color1, 3, 3)
-                                    }
-                                    _ => self.read_texpal_u16(palette_base + palette_offset + 4),
-                                },
-                                3 => match palette_data >> 14 {
-                                    2 => self.read_texpal_u16(palette_base + palette_offset + 6),
-                                    3 => {
-                                        let color0 =
-                                            self.read_texpal_u16(palette_base + palette_offset);
-                                        let color1 =
-                                            self.read_texpal_u16(palette_base + palette_offset + 2);
-                                        calc_rgb_color(color0, color1, 5, 3)
-                                    }
-                                    _ => {
-                                        ta = 0;
-                                        0
-                                    }
-                                },
-                                _ => {
-                                    #[cfg(feature = "tracing")]
-                                    tracing::error!("Unrecognized 4x4 texel data type {data}");
-                                    0
-                                }
-                            };
-
-                            tr = ((color & 0x1F) << 1) as u32;
-                            tg = (((color >> 5) & 0x1F) << 1) as u32;
-                            tb = (((color >> 10) & 0x1F) << 1) as u32;
-                        }
-
-                        6 => {
-                            // A5I3
-                            let mut texel_addr = s as u32;
-                            texel_addr += (t as u32) * tex_width as u32;
-                            texel_addr += tex_vram_offset as u32;
-
-                            let data = self.read_teximage_u8(texel_addr);
-
-                            let color_index = data & 0x7;
-                            ta = (data >> 3) as u32;
-
-                            if color_index != 0 || !texparams.color0_transparent {
-                                let mut pal_addr = (color_index as u32) * 2;
-                                pal_addr += poly.palette_base * 0x10;
-
-                                let pal_color = self.read_texpal_u16(pal_addr);
-
-                                tr = ((pal_color & 0x1F) << 1) as u32;
-                                tg = (((pal_color >> 5) & 0x1F) << 1) as u32;
-                                tb = (((pal_color >> 10) & 0x1F) << 1) as u32;
-                            } else {
-                                ta = 0;
-                            }
-                        }
-
-                        7 => {
-                            // Direct color
-                            let mut texel_addr = s as u32;
-                            texel_addr += (t as u32) * tex_width as u32;
-                            texel_addr *= 2;
-                            texel_addr += tex_vram_offset as u32;
-
-                            let data = self.read_teximage_u16(texel_addr);
-
-                            if (data & (1 << 15)) != 0 {
-                                tr = ((data & 0x1F) << 1) as u32;
-                                tg = (((data >> 5) & 0x1F) << 1) as u32;
-                                tb = (((data >> 10) & 0x1F) << 1) as u32;
-                            } else {
-                                ta = 0;
-                            }
-                        }
-
-                        _ => {
-                            #[cfg(feature = "tracing")]
-                            tracing::error!("Unrecognized texture format {}", texparams.format);
-                        }
-                    }
+                    let texel = self.sample_texture(&texparams, poly.palette_base, s, t);
+                    (tr, tg, tb, ta) = (texel.r, texel.g, texel.b, texel.a);
                 }
 
                 // ===== texture normalize =====
@@ -600,6 +330,23 @@ impl Gpu {
                         alpha = ((ta + 1) * (va + 1) - 1) / 32;
                     }
 
+                    1 => {
+                        // Decal: texture alpha blends the texel over the vertex color
+                        (r, g, b) = match ta {
+                            0 => (vr, vg, vb),
+                            0x1F => (tr, tg, tb),
+                            _ => (
+                                (tr * ta + vr * (0x1F - ta)) >> 5,
+                                (tg * ta + vg * (0x1F - ta)) >> 5,
+                                (tb * ta + vb * (0x1F - ta)) >> 5,
+                            ),
+                        };
+                        r <<= 2;
+                        g <<= 2;
+                        b <<= 2;
+                        alpha = va;
+                    }
+
                     2 => {
                         if self.engine_3d.disp3dcnt.highlight_shading {
                             vg = vr;
@@ -639,23 +386,31 @@ impl Gpu {
                     continue;
                 }
 
-                // ===== Z write =====
-                if !poly.translucent || poly.attributes.set_new_trans_depth {
-                    self.engine_3d.z_buffer[line][x_us] = pix_z;
-                }
+                // Translucency is per pixel: texel alpha can make pixels of
+                // an opaque polygon translucent and the reverse. Translucent
+                // polygons come after the opaque ones in rend_poly, so this
+                // is the second pass over what they left behind.
+                let translucent = alpha < 0x1F;
 
-                // ===== alpha blend =====
-                if self.engine_3d.disp3dcnt.alpha_blending && poly.translucent {
+                // A translucent pixel is not drawn over one of the same
+                // polygon ID, so meshes split in polygons only blend once
+                if translucent {
                     if self.engine_3d.trans_poly_ids[x_us] as i32 == poly.attributes.id {
                         continue;
                     }
+                    self.engine_3d.trans_poly_ids[x_us] = poly.attributes.id as u8;
+                }
+
+                // ===== Z write =====
+                if !translucent || poly.attributes.set_new_trans_depth {
+                    self.engine_3d.z_buffer[line][x_us] = pix_z;
+                }
 
+                // ===== alpha blend =====
+                if self.engine_3d.disp3dcnt.alpha_blending && translucent {
                     #[cfg(feature = "tracing")]
                     tracing::trace!("Alpha: {alpha:02X}");
 
-                    // Don't draw translucent polygons over each other if they share the same ID
-                    self.engine_3d.trans_poly_ids[x_us] = poly.attributes.id as u8;
-
                     let engine = if is_engine_a {
                         &mut self.engine_upper
                     } else {
@@ -681,7 +436,7 @@ impl Gpu {
                 } else {
                     &mut self.engine_lower
                 };
-                engine.framebuffer[x_us + y_coord] = final_color;
+                engine.put_pixel(y_coord, x_us, final_color, 0);
                 engine.final_bg_priority[x_us] = bg0_priority;
             }
         }
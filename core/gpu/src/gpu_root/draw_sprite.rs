@@ -2,548 +2,526 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 //! gpu.hpp
 //!
-use crate::gpu_root::{Gpu, read_palette_value};
+//! OBJ layer
+//!
+//! [`Gpu::draw_sprite_line`] walks the 128 OAM entries of an engine once per
+//! line and renders the OBJs crossing it into [`Gpu2DEngine::obj_line`];
+//! [`Gpu::draw_sprites`] then slots that line in between the BGs by
+//! priority, in front of the BGs sharing theirs. Where OBJs overlap, the
+//! lower priority value wins, then the lower OAM index. OBJ window OBJs
+//! only mark [`ObjPixel::window`], whatever their priority.
+//!
+//! [`Gpu2DEngine::obj_line`]: crate::gpu_2d::Gpu2DEngine::obj_line
+use crate::gpu_2d::{LAYER_OBJ, ObjPixel};
+use crate::gpu_root::Gpu;
+use crate::gpu_root::dirty::bgr555_to_argb;
 use lunaris_ds_mem_const::*;
 
-impl Gpu {
-    /// Draws all sprites for the current scanline.
-    pub fn draw_sprites(&mut self, is_engine_a: bool) {
-        // Temporary buffers for this scanline
-        let mut attributes = [0u16; 4];
-        let mut colors = [0u16; PIXELS_PER_LINE * 2];
-
-        {
-            // Clear buffers
-            let engine = match is_engine_a {
-                true => &mut self.engine_upper,
-                false => &mut self.engine_lower,
-            };
-            engine.sprite_scanline.fill(0);
-            colors.fill(0);
-        }
-
-        // Determine OAM and VRAM base for this engine
-        let (oam_base, vram_obj_base) = match is_engine_a {
-            true => (0, VRAM_OBJA_START),
-            false => (1024, VRAM_OBJB_START),
-        };
-
-        // Sprite size tables: [shape][size index]
-        const SPRITE_SIZES: [[u32; 8]; 3] = [
-            [1, 1, 2, 2, 4, 4, 8, 8], // Square
-            [2, 1, 4, 1, 4, 2, 8, 4], // Horizontal
-            [1, 2, 1, 4, 2, 4, 4, 8], // Vertical
-        ];
-
-        // Iterate over BG priorities (highest first)
-        for bg_priority in (0..=3).rev() {
-            // Iterate over 128 sprites (highest index first)
-            for i in (0..128).rev() {
-                // Read sprite attributes from OAM
-                attributes[0] = self.read_oam_u16(oam_base + i * 8);
-                attributes[1] = self.read_oam_u16(oam_base + i * 8 + 2);
-                attributes[2] = self.read_oam_u16(oam_base + i * 8 + 4);
-                attributes[3] = self.read_oam_u16(oam_base + i * 8 + 6);
-
-                let priority = ((attributes[2] >> 10) & 0x3) as u8;
-                if priority != bg_priority {
-                    continue;
-                }
+/// OBJ width and height in pixels, indexed by shape and size
+const OBJ_SIZES: [[(u32, u32); 4]; 3] = [
+    [(8, 8), (16, 16), (32, 32), (64, 64)], // Square
+    [(16, 8), (32, 8), (32, 16), (64, 32)], // Horizontal
+    [(8, 16), (8, 32), (16, 32), (32, 64)], // Vertical
+];
+
+/// OBJ rendering cycles per line (GBATEK); OBJs past the budget are dropped.
+const OBJ_CYCLES_PER_LINE: u32 = 2130;
+/// Budget with DISPCNT bit 23 set, which keeps OBJ VRAM free during H-blank.
+const OBJ_CYCLES_PER_LINE_HBLANK_FREE: u32 = 1616;
+
+/// OAM attribute 0 bits 10-11
+const OBJ_MODE_SEMI_TRANSPARENT: u16 = 1;
+const OBJ_MODE_WINDOW: u16 = 2;
+const OBJ_MODE_BITMAP: u16 = 3;
+
+/// One OAM entry, decoded.
+#[derive(Debug, Clone, Copy)]
+struct Obj {
+    x: i32,
+    y: u32,
+    width: u32,
+    height: u32,
+    /// Affine parameter group, `None` for a regular OBJ
+    affine: Option<u32>,
+    double_size: bool,
+    mode: u16,
+    palette_256: bool,
+    h_flip: bool,
+    v_flip: bool,
+    mosaic: bool,
+    tile: u32,
+    priority: u8,
+    /// Palette, or alpha for bitmap OBJs
+    palette: u32,
+}
 
-                let rotscale = (attributes[0] & (1 << 8)) != 0;
-                if rotscale {
-                    self.draw_rotscale_sprite(&attributes, is_engine_a);
-                    continue;
-                }
+impl Obj {
+    fn decode(attributes: [u16; 3]) -> Option<Self> {
+        let [attr0, attr1, attr2] = attributes;
+        let affine = attr0 & (1 << 8) != 0;
+        // Bit 9 disables a regular OBJ
+        if !affine && attr0 & (1 << 9) != 0 {
+            return None;
+        }
+        let shape = (attr0 >> 14) as usize;
+        if shape == 3 {
+            return None;
+        }
+        let (width, height) = OBJ_SIZES[shape][(attr1 >> 14) as usize];
+
+        Some(Self {
+            // 9-bit signed
+            x: ((attr1 as i32 & 0x1FF) << 23) >> 23,
+            y: (attr0 & 0xFF) as u32,
+            width,
+            height,
+            affine: affine.then_some(((attr1 >> 9) & 0x1F) as u32),
+            double_size: affine && attr0 & (1 << 9) != 0,
+            mode: (attr0 >> 10) & 0x3,
+            palette_256: attr0 & (1 << 13) != 0,
+            h_flip: !affine && attr1 & (1 << 12) != 0,
+            v_flip: !affine && attr1 & (1 << 13) != 0,
+            mosaic: attr0 & (1 << 12) != 0,
+            tile: (attr2 & 0x3FF) as u32,
+            priority: ((attr2 >> 10) & 0x3) as u8,
+            palette: (attr2 >> 12) as u32,
+        })
+    }
 
-                // Skip double-size sprites in normal mode
-                if attributes[0] & (1 << 9) != 0 {
-                    continue;
-                }
+    /// Bounding box, twice the OBJ size for double-size affine OBJs.
+    const fn bounds(&self) -> (u32, u32) {
+        match self.double_size {
+            true => (self.width * 2, self.height * 2),
+            false => (self.width, self.height),
+        }
+    }
 
-                // Sprite position
-                let x = (attributes[1] & 0x1FF) as u32;
-                let mut y = (attributes[0] & 0xFF) as u32;
-                y = (self.get_vcount() as u32 - y) & 0xFF;
+    /// Rendering cycles the OBJ takes from the line budget.
+    const fn cycles(&self) -> u32 {
+        let (width, _) = self.bounds();
+        match self.affine {
+            Some(_) => 10 + width * 2,
+            None => width,
+        }
+    }
+}
 
-                let shape = ((attributes[0] >> 14) & 0x3) as usize;
-                let size = ((attributes[1] >> 14) & 0x3) as usize;
-                let mode = ((attributes[0] >> 10) & 0x3) as usize;
+impl Gpu {
+    /// Renders the OBJs crossing the current line into the engine's OBJ
+    /// line buffer.
+    ///
+    /// Regular and affine (optionally double-size) OBJs are supported, with
+    /// 16-color, 256-color (standard or extended palette) and direct color
+    /// bitmap graphics in 1D or 2D VRAM mapping. OBJ window OBJs only mark
+    /// their opaque pixels. Each OBJ on the line costs rendering cycles;
+    /// once the line budget is spent the remaining OBJs are not drawn.
+    /// Mosaic OBJs repeat the top left pixel of each MOSAIC block.
+    pub fn draw_sprite_line(&mut self, is_engine_a: bool) {
+        let line = (self.get_vcount() & 0xFF) as u32;
+        let oam_base = match is_engine_a {
+            true => 0,
+            false => 1024,
+        };
+        let engine = match is_engine_a {
+            true => &mut self.engine_upper,
+            false => &mut self.engine_lower,
+        };
+        let (mosaic_width, mosaic_height) = engine.obj_mosaic();
+        let budget = match engine.dispcnt.hblank_obj_processing {
+            true => OBJ_CYCLES_PER_LINE_HBLANK_FREE,
+            false => OBJ_CYCLES_PER_LINE,
+        };
+        let mut obj_line = std::mem::take(&mut engine.obj_line);
+        obj_line.fill(ObjPixel::default());
+
+        let mut cycles = 0;
+        for index in 0..128 {
+            let address = oam_base + index * 8;
+            let attributes = [
+                self.read_oam_u16(address),
+                self.read_oam_u16(address + 2),
+                self.read_oam_u16(address + 4),
+            ];
+            let Some(obj) = Obj::decode(attributes) else {
+                continue;
+            };
+            let (_, bound_height) = obj.bounds();
+            let y = line.wrapping_sub(obj.y) & 0xFF;
+            if y >= bound_height {
+                continue;
+            }
 
-                // Check vertical bounds
-                if y >= SPRITE_SIZES[shape][size * 2 + 1] * 8 {
-                    continue;
-                }
+            cycles += obj.cycles();
+            if cycles > budget {
+                break;
+            }
+            let y = match obj.mosaic {
+                true => y.saturating_sub(line % mosaic_height),
+                false => y,
+            };
+            self.draw_obj(&obj, y, oam_base, is_engine_a, &mut obj_line);
+        }
 
-                if mode == 3 {
-                    // Bitmap OBJ mode (special handling)
-                    let alpha = attributes[2] >> 12;
-                    if alpha == 0 {
-                        continue;
-                    }
-                    // alpha += 1;
-
-                    let width = SPRITE_SIZES[shape][size * 2] * 8;
-                    let tile_num = (attributes[2] & 0x3FF) as u32;
-
-                    let tile_addr: u32;
-                    {
-                        let engine = match is_engine_a {
-                            true => &mut self.engine_upper,
-                            false => &mut self.engine_lower,
-                        };
-
-                        tile_addr = if engine.dispcnt.bitmap_obj_1d {
-                            tile_num * (128 << engine.dispcnt.bitmap_obj_1d_bound as u32)
-                                + (y * width * 2)
-                        } else if engine.dispcnt.bitmap_obj_square {
-                            ((tile_num & 0x1F) * 0x10) + ((tile_num & 0x3E0) * 0x80) + (y * 256 * 2)
-                        } else {
-                            ((tile_num & 0xF) * 0x10) + ((tile_num & 0x3F0) * 0x80) + (y * 128 * 2)
-                        };
-                    }
+        // Horizontal mosaic
+        let mut block_start = ObjPixel::default();
+        for (x, pixel) in obj_line.iter_mut().enumerate() {
+            if (x as u32).is_multiple_of(mosaic_width) {
+                block_start = *pixel;
+            } else if pixel.mosaic && block_start.mosaic {
+                *pixel = ObjPixel {
+                    window: pixel.window,
+                    ..block_start
+                };
+            }
+        }
 
-                    let mut pixel_addr = vram_obj_base + tile_addr;
-                    for x_offset in x..(x + width) {
-                        if x_offset >= PIXELS_PER_LINE as u32 {
-                            pixel_addr += 2;
-                            continue;
-                        }
-
-                        {
-                            let engine = match is_engine_a {
-                                true => &mut self.engine_upper,
-                                false => &mut self.engine_lower,
-                            };
-                            if bg_priority > engine.final_bg_priority[x_offset as usize] {
-                                pixel_addr += 2;
-                                continue;
-                            }
-                        }
-
-                        let color = match is_engine_a {
-                            true => self.read_obja_u16(pixel_addr),
-                            false => self.read_objb_u16(pixel_addr),
-                        };
-                        pixel_addr += 2;
-
-                        if (color & (1 << 15)) != 0 {
-                            let engine = match is_engine_a {
-                                true => &mut self.engine_upper,
-                                false => &mut self.engine_lower,
-                            };
-                            engine.sprite_scanline[x_offset as usize] = color as u32 | (1 << 31);
-                        }
-                    }
-                } else {
-                    // Normal palette-based OBJ
-                    let x_tiles = SPRITE_SIZES[shape][size * 2];
-                    let y_tiles = SPRITE_SIZES[shape][size * 2 + 1];
+        let engine = match is_engine_a {
+            true => &mut self.engine_upper,
+            false => &mut self.engine_lower,
+        };
+        engine.obj_line = obj_line;
+    }
 
-                    if attributes[1] & 0x2000 != 0 {
-                        y = ((y_tiles * 8) - 1) - y;
-                    }
+    /// Puts the OBJs of `priority` from the OBJ line in front, where the
+    /// window shows OBJs. Called after the BGs of the same priority, which
+    /// OBJs cover.
+    pub fn draw_sprites(&mut self, is_engine_a: bool, priority: u8) {
+        let scanline = self.get_vcount() as usize * PIXELS_PER_LINE;
+        let engine = match is_engine_a {
+            true => &mut self.engine_upper,
+            false => &mut self.engine_lower,
+        };
 
-                    let tile_num = (attributes[2] & 0x3FF) as u32;
-                    let palette = (attributes[2] >> 12) as u32;
-                    let one_palette_mode = attributes[0] & (1 << 13) != 0;
-
-                    let tile_y = y / 8;
-                    let tile_scanline = y % 8 * if one_palette_mode { 8 } else { 4 };
-                    let x_flip = attributes[1] & (1 << 12) != 0;
-
-                    let y_dimension_num;
-
-                    {
-                        let engine = match is_engine_a {
-                            true => &mut self.engine_upper,
-                            false => &mut self.engine_lower,
-                        };
-
-                        // Determine y-dimension count
-                        y_dimension_num = if engine.dispcnt.tile_obj_1d {
-                            let mut y_dim = x_tiles << one_palette_mode as usize;
-                            y_dim <<= engine.dispcnt.tile_obj_1d_bound;
-                            y_dim
-                        } else {
-                            0x20
-                        };
-                    }
+        for x in 0..PIXELS_PER_LINE {
+            let pixel = engine.obj_line[x];
+            let Some(color) = pixel.color else {
+                continue;
+            };
+            if engine.window_mask[x] & (1 << 4) == 0 || pixel.priority != priority {
+                continue;
+            }
+            engine.put_pixel(scanline, x, color, LAYER_OBJ);
+            engine.final_bg_priority[x] = priority;
+        }
+    }
 
-                    for tile in 0..x_tiles {
-                        let mut tile_id = tile_y * y_dimension_num;
-                        let tile_id_tmp = match x_flip {
-                            true => x_tiles - tile - 1,
-                            false => tile,
-                        };
-                        tile_id += tile_id_tmp << one_palette_mode as u32;
-                        tile_id += tile_num;
-                        tile_id <<= 5;
-
-                        let tile_data = vram_obj_base + tile_id + tile_scanline;
-
-                        if !one_palette_mode {
-                            // 4bpp
-                            let data = match is_engine_a {
-                                true => self.read_obja_u32(tile_data),
-                                false => self.read_objb_u32(tile_data),
-                            };
-
-                            let mut index = x + (tile * 8);
-                            for i in 0..8 {
-                                let color_index = if x_flip {
-                                    (data >> ((7 - i) * 4)) & 0xF
-                                } else {
-                                    (data >> (i * 4)) & 0xF
-                                };
-                                let idx = (index & 0x1FF) as usize;
-                                index += 1;
-
-                                if idx >= PIXELS_PER_LINE || color_index == 0 {
-                                    continue;
-                                }
-
-                                {
-                                    let engine = match is_engine_a {
-                                        true => &mut self.engine_upper,
-                                        false => &mut self.engine_lower,
-                                    };
-
-                                    if priority > engine.final_bg_priority[idx] {
-                                        continue;
-                                    }
-
-                                    let address = 0x200 + palette * 32 + color_index * 2;
-
-                                    engine.sprite_scanline[idx] = match is_engine_a {
-                                        true => read_palette_value(&self.palette_upper, address),
-                                        false => read_palette_value(&self.palette_lower, address),
-                                    }
-                                        as u32
-                                        | (1 << 31);
-                                }
-                            }
-                        } else {
-                            // 8bpp
-                            let data = match is_engine_a {
-                                true => self.read_obja_u64(tile_data),
-                                false => self.read_objb_u64(tile_data),
-                            };
-
-                            let mut index = x + (tile * 8);
-                            for i in 0..8 {
-                                let color_index = if x_flip {
-                                    (data >> ((7 - i) * 8)) & 0xFF
-                                } else {
-                                    (data >> (i * 8)) & 0xFF
-                                } as u32;
-                                let idx = index & 0x1FF;
-                                index += 1;
-
-                                if idx >= PIXELS_PER_LINE as u32 || color_index == 0 {
-                                    continue;
-                                }
-
-                                {
-                                    let (final_bg_priority, obj_extended_palette) = {
-                                        let engine = match is_engine_a {
-                                            true => &mut self.engine_upper,
-                                            false => &mut self.engine_lower,
-                                        };
-                                        (
-                                            engine.final_bg_priority[idx as usize],
-                                            engine.dispcnt.obj_extended_palette,
-                                        )
-                                    };
-
-                                    if priority > final_bg_priority {
-                                        continue;
-                                    }
-
-                                    let obj_address = palette * 512 + color_index * 2;
-                                    let address = 0x200 + color_index * 2;
-
-                                    let sprite_scanline = match is_engine_a {
-                                        true => match obj_extended_palette {
-                                            true => self.read_extpal_obja(obj_address),
-                                            false => {
-                                                read_palette_value(&self.palette_upper, address)
-                                            }
-                                        },
-                                        false => match obj_extended_palette {
-                                            true => self.read_extpal_objb(obj_address),
-                                            false => {
-                                                read_palette_value(&self.palette_lower, address)
-                                            }
-                                        },
-                                    }
-                                        as u32
-                                        | (1 << 31);
-
-                                    {
-                                        let engine = match is_engine_a {
-                                            true => &mut self.engine_upper,
-                                            false => &mut self.engine_lower,
-                                        };
-                                        engine.sprite_scanline[idx as usize] = sprite_scanline;
-                                    }
-                                }
-                            }
-                        }
-                    }
+    /// Renders line `y` of the bounding box of `obj`.
+    fn draw_obj(
+        &self,
+        obj: &Obj,
+        y: u32,
+        oam_base: u32,
+        is_engine_a: bool,
+        obj_line: &mut [ObjPixel],
+    ) {
+        let (bound_width, bound_height) = obj.bounds();
+
+        // Texture coordinates step by (pa, pc) per pixel, in 8.8 fixed point
+        // relative to the center
+        let (pa, pc, mut u, mut v) = match obj.affine {
+            Some(group) => {
+                let base = oam_base + group * 32;
+                let pa = self.read_oam_i16(base + 0x06) as i32;
+                let pb = self.read_oam_i16(base + 0x0E) as i32;
+                let pc = self.read_oam_i16(base + 0x16) as i32;
+                let pd = self.read_oam_i16(base + 0x1E) as i32;
+                let dx = -(bound_width as i32 / 2);
+                let dy = y as i32 - bound_height as i32 / 2;
+                (
+                    pa,
+                    pc,
+                    pa * dx + pb * dy + ((obj.width as i32 / 2) << 8),
+                    pc * dx + pd * dy + ((obj.height as i32 / 2) << 8),
+                )
+            }
+            None => {
+                let v = match obj.v_flip {
+                    true => obj.height - 1 - y,
+                    false => y,
+                };
+                match obj.h_flip {
+                    true => (-0x100, 0, ((obj.width - 1) << 8) as i32, (v << 8) as i32),
+                    false => (0x100, 0, 0, (v << 8) as i32),
                 }
             }
-        }
+        };
+
+        for i in 0..bound_width as i32 {
+            let screen_x = obj.x + i;
+            let (tex_x, tex_y) = (u >> 8, v >> 8);
+            u += pa;
+            v += pc;
 
-        // Copy sprite scanline to framebuffer with RGBA conversion
-        let vcount = self.get_vcount();
-        for i in 0..PIXELS_PER_LINE {
-            let engine = match is_engine_a {
-                true => &mut self.engine_upper,
-                false => &mut self.engine_lower,
+            if !(0..PIXELS_PER_LINE as i32).contains(&screen_x)
+                || !(0..obj.width as i32).contains(&tex_x)
+                || !(0..obj.height as i32).contains(&tex_y)
+            {
+                continue;
+            }
+            let Some((color, alpha)) =
+                self.sample_obj(obj, tex_x as u32, tex_y as u32, is_engine_a)
+            else {
+                continue;
             };
 
-            if (engine.sprite_scanline[i] & (1 << 31)) == 0 {
+            let pixel = &mut obj_line[screen_x as usize];
+            if obj.mode == OBJ_MODE_WINDOW {
+                pixel.window = true;
                 continue;
             }
-            let color = engine.sprite_scanline[i];
-            let r = (color & 0x1F) << 3;
-            let g = ((color >> 5) & 0x1F) << 3;
-            let b = ((color >> 10) & 0x1F) << 3;
-            engine.framebuffer[i + vcount as usize * PIXELS_PER_LINE] =
-                0xFF000000 | (r << 16) | (g << 8) | b;
+            if pixel.color.is_some() && obj.priority >= pixel.priority {
+                continue;
+            }
+            pixel.color = Some(color);
+            pixel.priority = obj.priority;
+            pixel.semi_transparent = obj.mode == OBJ_MODE_SEMI_TRANSPARENT;
+            pixel.alpha = alpha;
+            pixel.mosaic = obj.mosaic;
         }
     }
 
-    /// Draws a single rotation/scaling sprite.
-    ///
-    /// `attributes` points to OAM attribute data.
-    pub fn draw_rotscale_sprite(&mut self, attributes: &[u16; 4], is_engine_a: bool) {
-        // Sprite size tables: [shape][size index]
-        const SPRITE_SIZES: [[usize; 8]; 3] = [
-            [1, 1, 2, 2, 4, 4, 8, 8], // Square
-            [2, 1, 4, 1, 4, 2, 8, 4], // Horizontal
-            [1, 2, 1, 4, 2, 4, 4, 8], // Vertical
-        ];
-
-        // Read position and shape/size/priority
-        let mut x = (attributes[1] & 0x1FF) as i32;
-        let mut y = (attributes[0] & 0xFF) as i32;
-
-        let shape = ((attributes[0] >> 14) & 0x3) as usize;
-        let size = ((attributes[1] >> 14) & 0x3) as usize;
-        let priority = ((attributes[2] >> 10) & 0x3) as u8;
-
-        // Determine number of tiles horizontally and vertically
-        let x_tiles = SPRITE_SIZES[shape][size * 2];
-        let y_tiles = SPRITE_SIZES[shape][size * 2 + 1];
-
-        // Compute width and height in pixels
-        let mut width = (x_tiles * 8) as u32;
-        let mut height = (y_tiles * 8) as u32;
-        let mut x_bound = width;
-        let mut y_bound = height;
-
-        // Double-size flag
-        if attributes[0] & (1 << 9) != 0 {
-            x_bound *= 2;
-            y_bound *= 2;
-        }
-
-        // Clip Y based on current scanline
-        y = (self.get_vcount() as i32 - y) & 0xFF;
-        if y as u32 >= y_bound {
-            return;
-        }
+    /// ARGB and bitmap alpha of the OBJ texel at `(x, y)`, `None` if it is
+    /// transparent.
+    fn sample_obj(
+        &self,
+        obj: &Obj,
+        x: u32,
+        y: u32,
+        is_engine_a: bool,
+    ) -> Option<(u32, Option<u8>)> {
+        let dispcnt = match is_engine_a {
+            true => self.engine_upper.dispcnt,
+            false => self.engine_lower.dispcnt,
+        };
+        let vram_base = match is_engine_a {
+            true => VRAM_OBJA_START,
+            false => VRAM_OBJB_START,
+        };
+        let read_u8 = |address: u32| match is_engine_a {
+            true => self.read_obja_u8(vram_base + address),
+            false => self.read_objb_u8(vram_base + address),
+        };
 
-        // Sign-extend X
-        x = (x << 23) >> 23;
-        if x <= -(x_bound as i32) {
-            return;
+        if obj.mode == OBJ_MODE_BITMAP {
+            // Alpha 0 hides the OBJ
+            if obj.palette == 0 {
+                return None;
+            }
+            let row = match (dispcnt.bitmap_obj_1d, dispcnt.bitmap_obj_square) {
+                (true, _) => {
+                    obj.tile * (128 << dispcnt.bitmap_obj_1d_bound as u32) + y * obj.width * 2
+                }
+                // 2D, in a 256 or 128 pixel wide bitmap
+                (false, true) => (obj.tile & 0x1F) * 0x10 + (obj.tile & 0x3E0) * 0x80 + y * 512,
+                (false, false) => (obj.tile & 0xF) * 0x10 + (obj.tile & 0x3F0) * 0x80 + y * 256,
+            };
+            let color = u16::from_le_bytes([read_u8(row + x * 2), read_u8(row + x * 2 + 1)]);
+            if color & (1 << 15) == 0 {
+                return None;
+            }
+            return Some((bgr555_to_argb(color), Some(obj.palette as u8)));
         }
 
-        let center_x = x_bound / 2;
-        let center_y = y_bound / 2;
+        // Tiles are 32 bytes (16 colors) or 64 bytes (256 colors); 2D mapping
+        // lays them out in rows of 32 tile slots
+        let tile_size = match obj.palette_256 {
+            true => 64,
+            false => 32,
+        };
+        let tile_address = match dispcnt.tile_obj_1d {
+            true => {
+                obj.tile * (32 << dispcnt.tile_obj_1d_bound)
+                    + ((y / 8) * (obj.width / 8) + x / 8) * tile_size
+            }
+            false => obj.tile * 32 + (y / 8) * 1024 + (x / 8) * tile_size,
+        };
 
-        // Determine starting X offset and adjust bounds for clipping
-        let x_offset: i32;
-        if x >= 0 {
-            x_offset = 0;
-            if x + x_bound as i32 > 256 {
-                x_bound = (256 - x) as u32;
+        match obj.palette_256 {
+            false => {
+                let byte = read_u8(tile_address + (y % 8) * 4 + (x % 8) / 2);
+                let color = (byte >> ((x & 1) * 4)) & 0xF;
+                if color == 0 {
+                    return None;
+                }
+                let index = 256 + obj.palette as usize * 16 + color as usize;
+                Some((self.palette_argb(is_engine_a, index), None))
+            }
+            true => {
+                let color = read_u8(tile_address + (y % 8) * 8 + x % 8);
+                if color == 0 {
+                    return None;
+                }
+                let argb = match dispcnt.obj_extended_palette {
+                    true => {
+                        let address = obj.palette * 512 + color as u32 * 2;
+                        bgr555_to_argb(match is_engine_a {
+                            true => self.read_extpal_obja(address),
+                            false => self.read_extpal_objb(address),
+                        })
+                    }
+                    false => self.palette_argb(is_engine_a, 256 + color as usize),
+                };
+                Some((argb, None))
             }
-        } else {
-            x_offset = -x;
-            x = 0;
         }
+    }
+}
 
-        // Determine rotation/scaling matrix from OAM
-        let rot_group = ((attributes[1] >> 9) & 0x1F) * 32;
-        let oam_base = match is_engine_a {
-            true => 0,
-            false => 1024,
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Engine A in mode 0 with only OBJs shown, 1D tile mapping and bank B
+    /// as OBJ memory.
+    fn obj_gpu() -> Gpu {
+        let mut gpu = Gpu::new();
+        gpu.power_on();
+        gpu.set_vramcnt_b(0x82);
+        gpu.set_dispcnt_a(0x0001_1010);
+        gpu
+    }
 
-        let base_address = (oam_base + rot_group) as u32;
-        let rot_a = self.read_oam_i16(base_address + 0x6);
-        let rot_b = self.read_oam_i16(base_address + 0xE);
-        let rot_c = self.read_oam_i16(base_address + 0x16);
-        let rot_d = self.read_oam_i16(base_address + 0x1E);
-
-        // Compute starting rotated coordinates
-        let mut rot_x = ((x_offset - center_x as i32) * rot_a as i32)
-            + ((y - center_y as i32) * rot_b as i32)
-            + (width << 7) as i32;
-        let mut rot_y = ((x_offset - center_x as i32) * rot_c as i32)
-            + ((y - center_y as i32) * rot_d as i32)
-            + (height << 7) as i32;
-
-        width <<= 8;
-        height <<= 8;
-
-        // Tile index and palette information
-        let mut tile_num = (attributes[2] & 0x3FF) as u32;
-        let palette_id = (attributes[2] >> 12) as u32;
-        let one_palette_mode = attributes[0] & 0x2000 != 0;
-
-        // Skip invalid shape
-        if shape == 3 {
-            return;
+    fn write_oam(gpu: &mut Gpu, index: usize, attributes: [u16; 3]) {
+        for (i, attribute) in attributes.iter().enumerate() {
+            gpu.oam[index * 8 + i * 2..][..2].copy_from_slice(&attribute.to_le_bytes());
         }
+    }
 
-        // Determine tile addressing (1D/2D)
-        let y_dimension_num;
-        {
-            let engine = match is_engine_a {
-                true => &mut self.engine_upper,
-                false => &mut self.engine_lower,
-            };
-            y_dimension_num = if engine.dispcnt.tile_obj_1d {
-                tile_num <<= engine.dispcnt.tile_obj_1d_bound;
-                (width >> 11) << one_palette_mode as usize
-            } else {
-                0x20
-            } << 5;
+    #[test]
+    fn test_draw_sprite_line() {
+        let mut gpu = obj_gpu();
+        // Hide every OBJ but the ones set up below
+        for index in 0..128 {
+            write_oam(&mut gpu, index, [0x0200, 0, 0]);
+        }
+        // Tile 1: left half color 1, right half color 2
+        for row in 0..8 {
+            gpu.write_obja(0x0640_0020 + row * 4, 0x1111);
+            gpu.write_obja(0x0640_0022 + row * 4, 0x2222);
         }
+        gpu.write_palette_a(0x202, 0x001F);
+        gpu.write_palette_a(0x204, 0x03E0);
+        let (red, green) = (0xFFF8_0000, 0xFF00_F800);
+        let line = |gpu: &Gpu| gpu.engine_upper.front_framebuffer[..24].to_vec();
+
+        // 8x8 OBJ at x = 4
+        write_oam(&mut gpu, 0, [0x0000, 0x0004, 0x0001]);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..4], [0xFF00_0000; 4]);
+        assert_eq!(line(&gpu)[4..8], [red; 4]);
+        assert_eq!(line(&gpu)[8..12], [green; 4]);
+
+        // Horizontal flip, and a negative X
+        write_oam(&mut gpu, 0, [0x0000, 0x1000 | 0x1FC, 0x0001]);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..4], [red; 4]);
+        assert_eq!(line(&gpu)[4], 0xFF00_0000);
+
+        // An identity affine OBJ draws like the regular one
+        write_oam(&mut gpu, 0, [0x0100, 0x0004, 0x0001]);
+        gpu.oam[0x06..0x08].copy_from_slice(&0x0100_u16.to_le_bytes());
+        gpu.oam[0x1E..0x20].copy_from_slice(&0x0100_u16.to_le_bytes());
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[4..8], [red; 4]);
+        assert_eq!(line(&gpu)[8..12], [green; 4]);
+        // Double size centers it in a 16x16 box
+        write_oam(&mut gpu, 0, [0x0300, 0x0004, 0x0001]);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[4..8], [0xFF00_0000; 4]);
+
+        // The lower priority value wins over the lower OAM index
+        write_oam(&mut gpu, 0, [0x0000, 0x0004, 0x0401]);
+        write_oam(&mut gpu, 1, [0x0000, 0x0008, 0x0001]);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[4..8], [red; 4]);
+        assert_eq!(line(&gpu)[8..12], [red; 4]);
+        assert_eq!(line(&gpu)[12..16], [green; 4]);
+    }
 
-        tile_num <<= 5;
+    #[test]
+    fn test_obj_bg_priority() {
+        let mut gpu = obj_gpu();
+        for index in 0..128 {
+            write_oam(&mut gpu, index, [0x0200, 0, 0]);
+        }
+        // Red OBJ tile 1
+        for row in 0..8 {
+            gpu.write_obja(0x0640_0020 + row * 4, 0x1111);
+            gpu.write_obja(0x0640_0022 + row * 4, 0x1111);
+        }
+        gpu.write_palette_a(0x202, 0x001F);
+        // Green BG0 and blue BG1 over the first 8 pixels, bank A as BG memory
+        gpu.set_vramcnt_a(0x81);
+        gpu.set_dispcnt_a(0x0001_1310);
+        for offset in (0..32).step_by(2) {
+            gpu.write_bga(0x0600_4020 + offset, 0x1111);
+            gpu.write_bga(0x0600_4040 + offset, 0x2222);
+        }
+        gpu.write_bga(0x0600_0000, 0x0001);
+        gpu.write_bga(0x0600_0800, 0x0002);
+        gpu.write_palette_a(0x002, 0x03E0);
+        gpu.write_palette_a(0x004, 0x7C00);
+        let (red, green, blue) = (0xFFF8_0000, 0xFF00_F800, 0xFF00_00F8);
+        let line = |gpu: &Gpu| gpu.engine_upper.front_framebuffer[..12].to_vec();
+
+        // Same priority, the lower BG is in front
+        gpu.set_bgcnt_a(0x0004, 0);
+        gpu.set_bgcnt_a(0x0104, 1);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..8], [green; 8]);
+        gpu.set_bgcnt_a(0x0005, 0);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..8], [blue; 8]);
+
+        // An OBJ is behind the BGs of a lower priority value and in front
+        // of those of its own
+        write_oam(&mut gpu, 0, [0x0000, 0x0004, 0x0401]);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..8], [blue; 8]);
+        assert_eq!(line(&gpu)[8..], [red; 4]);
+        gpu.set_bgcnt_a(0x0105, 1);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..4], [green; 4]);
+        assert_eq!(line(&gpu)[4..], [red; 8]);
+
+        // The OBJ window shows BG0 and the outside BG1, whatever the OBJ
+        // priority, but only with OBJs displayed
+        write_oam(&mut gpu, 0, [0x0800, 0x0004, 0x0C01]);
+        gpu.set_bgcnt_a(0x0004, 0);
+        gpu.set_bgcnt_a(0x0104, 1);
+        gpu.set_winout_a(0x0102);
+        gpu.set_dispcnt_a(0x0001_9310);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..4], [blue; 4]);
+        assert_eq!(line(&gpu)[4..8], [green; 4]);
+        gpu.set_dispcnt_a(0x0001_8310);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..8], [blue; 8]);
+    }
 
-        // Compute pixel data base address
-        let pixel_base = tile_num
-            + match is_engine_a {
-                true => VRAM_OBJA_START,
-                false => VRAM_OBJB_START,
+    #[test]
+    fn test_sprite_cycle_budget() {
+        let mut gpu = obj_gpu();
+        for row in 0..8 {
+            gpu.write_obja(0x0640_0020 + row * 4, 0x1111);
+            gpu.write_obja(0x0640_0022 + row * 4, 0x1111);
+        }
+        gpu.write_palette_a(0x202, 0x001F);
+        // 64x64 OBJs take 64 cycles each, so 33 fit in a line; OBJ 33 is
+        // the only one at x = 200
+        for index in 0..128 {
+            let attributes = match index {
+                0..33 => [0x0000, 0xC000, 0x0001],
+                33 => [0x0000, 0xC000 | 200, 0x0001],
+                _ => [0x0200, 0, 0],
             };
-
-        // Draw sprite pixels along scanline
-        if one_palette_mode {
-            let mut px_offset = x_offset;
-            let mut px = x;
-            while px_offset < x_bound as i32 {
-                if rot_x as u32 <= width && rot_y as u32 <= height {
-                    let mut pixel_addr = pixel_base;
-                    pixel_addr += ((rot_y >> 11) * y_dimension_num as i32) as u32;
-                    pixel_addr += ((rot_y & 0x700) >> 5) as u32;
-                    pixel_addr += ((rot_x >> 11) * 64) as u32;
-                    pixel_addr += ((rot_x & 0x700) >> 8) as u32;
-
-                    let mut color = match is_engine_a {
-                        true => self.read_obja_u8(pixel_addr),
-                        false => self.read_objb_u8(pixel_addr),
-                    } as u16;
-
-                    let (is_priority, obj_extended_palette) = {
-                        let engine = match is_engine_a {
-                            true => &mut self.engine_upper,
-                            false => &mut self.engine_lower,
-                        };
-                        (
-                            priority <= engine.final_bg_priority[px as usize],
-                            engine.dispcnt.obj_extended_palette,
-                        )
-                    };
-
-                    if color != 0 && is_priority {
-                        let palette_address = 0x200 + color as u32 * 2;
-                        color = if obj_extended_palette {
-                            let obj_addr = palette_id * 512 + color as u32 * 2;
-                            if is_engine_a {
-                                self.read_extpal_obja(obj_addr)
-                            } else {
-                                self.read_extpal_objb(obj_addr)
-                            }
-                        } else if is_engine_a {
-                            read_palette_value(&self.palette_upper, palette_address)
-                        } else {
-                            read_palette_value(&self.palette_lower, palette_address)
-                        };
-                        {
-                            let engine = match is_engine_a {
-                                true => &mut self.engine_upper,
-                                false => &mut self.engine_lower,
-                            };
-                            engine.sprite_scanline[px as usize] = color as u32 | (1 << 31);
-                        }
-                    }
-                }
-                rot_x += rot_a as i32;
-                rot_y += rot_c as i32;
-                px_offset += 1;
-                px += 1;
-            }
-        } else {
-            // Two-palette mode
-            let mut px_offset = x_offset;
-            let mut px = x;
-            while px_offset < x_bound as i32 {
-                if rot_x as u32 <= width && rot_y as u32 <= height {
-                    let mut pixel_addr = pixel_base;
-                    pixel_addr += ((rot_y >> 11) * y_dimension_num as i32) as u32;
-                    pixel_addr += ((rot_y & 0x700) >> 6) as u32;
-                    pixel_addr += ((rot_x >> 11) * 32) as u32;
-                    pixel_addr += ((rot_x & 0x700) >> 9) as u32;
-
-                    let mut color = match is_engine_a {
-                        true => self.read_obja_u8(pixel_addr),
-                        false => self.read_objb_u8(pixel_addr),
-                    } as u16;
-
-                    if rot_x & 0x100 != 0 {
-                        color >>= 4;
-                    } else {
-                        color &= 0xF;
-                    }
-
-                    let is_priority = {
-                        let engine = match is_engine_a {
-                            true => &mut self.engine_upper,
-                            false => &mut self.engine_lower,
-                        };
-                        priority <= engine.final_bg_priority[px as usize]
-                    };
-
-                    if color != 0 && is_priority {
-                        let palette_addr = 0x200 + palette_id * 32 + color as u32 * 2;
-
-                        color = if is_engine_a {
-                            read_palette_value(&self.palette_upper, palette_addr)
-                        } else {
-                            read_palette_value(&self.palette_lower, palette_addr)
-                        };
-                        {
-                            let engine = match is_engine_a {
-                                true => &mut self.engine_upper,
-                                false => &mut self.engine_lower,
-                            };
-                            engine.sprite_scanline[px as usize] = color as u32 | (1 << 31);
-                        }
-                    }
-                }
-                rot_x += rot_a as i32;
-                rot_y += rot_c as i32;
-                px_offset += 1;
-                px += 1;
-            }
+            write_oam(&mut gpu, index, attributes);
         }
+        gpu.draw_scanline();
+        assert_eq!(gpu.engine_upper.obj_line[200].color, None);
+
+        write_oam(&mut gpu, 0, [0x0200, 0, 0]);
+        gpu.draw_scanline();
+        assert_eq!(gpu.engine_upper.obj_line[200].color, Some(0xFFF8_0000));
     }
 }
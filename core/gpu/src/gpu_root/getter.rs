@@ -5,19 +5,22 @@
 use crate::gpu_root::Gpu;
 
 impl Gpu {
-    pub fn get_palette(&mut self, engine_a: bool) -> &mut Vec<u8> {
+    /// Standard palette of engine A or B, written with
+    /// [`Gpu::write_palette_a`] and [`Gpu::write_palette_b`].
+    pub fn get_palette(&self, engine_a: bool) -> &[u8] {
         if engine_a {
-            return &mut self.palette_upper;
+            return &self.palette_upper;
         }
-        &mut self.palette_lower
+        &self.palette_lower
     }
 
     // TODO: id to enum?
-    /// Get VRAM bank by ID.
+    /// Get VRAM bank by ID, see [`vram_bytes`](crate::vram_bytes) for
+    /// halfword access.
     /// # Panics
-    /// Returns empty slice if ID is 0..=3 outside valid range.
-    pub fn get_vram_block(&self, id: i32) -> &[u16] {
-        let bytes = match id {
+    /// If ID is outside 0..=3.
+    pub fn get_vram_block(&self, id: i32) -> &[u8] {
+        match id {
             0 => &self.vram_a,
             1 => &self.vram_b,
             2 => &self.vram_c,
@@ -27,17 +30,28 @@ impl Gpu {
                 tracing::error!("Invalid VRAM bank ID: {id}");
                 panic!("Invalid VRAM bank ID: {id}");
             }
+        }
+    }
+
+    /// Whether VRAM bank `id` (0..=3, A..D) is mapped to the LCDC, the only
+    /// mapping display capture writes to.
+    pub fn is_vram_lcdc(&self, id: i32) -> bool {
+        let cnt = match id {
+            0 => &self.vramcnt_a,
+            1 => &self.vramcnt_b,
+            2 => &self.vramcnt_c,
+            3 => &self.vramcnt_d,
+            _ => return false,
         };
-        assert_eq!(bytes.len() % 2, 0, "u8 vector length must be even");
-        unsafe { core::slice::from_raw_parts(bytes.as_ptr() as *mut u16, bytes.len() / 2) }
+        cnt.enabled && cnt.mst == 0
     }
 
     // TODO: id to enum?
     /// Get VRAM bank by ID.
     /// # Panics
-    /// Returns empty slice if ID is 0..=3 outside valid range.
-    pub fn get_vram_block_mut(&mut self, id: i32) -> &mut [u16] {
-        let bytes = match id {
+    /// If ID is outside 0..=3.
+    pub fn get_vram_block_mut(&mut self, id: i32) -> &mut [u8] {
+        match id {
             0 => &mut self.vram_a,
             1 => &mut self.vram_b,
             2 => &mut self.vram_c,
@@ -47,50 +61,6 @@ impl Gpu {
                 tracing::error!("Invalid VRAM bank ID: {id}");
                 panic!("Invalid VRAM bank ID: {id}");
             }
-        };
-        assert_eq!(bytes.len() % 2, 0, "u8 vector length must be even");
-        unsafe { core::slice::from_raw_parts_mut(bytes.as_mut_ptr() as *mut u16, bytes.len() / 2) }
-    }
-
-    // TODO: id to enum?
-    /// Get VRAM bank by ID.
-    /// # Panics
-    /// Returns empty slice if ID is 0..=3 outside valid range.
-    pub fn get_movable_vram_block(&mut self, src_id: i32, dest_id: i32) -> (&[u16], &mut [u16]) {
-        let src_bytes = match src_id {
-            0 => &self.vram_a,
-            1 => &self.vram_b,
-            2 => &self.vram_c,
-            3 => &self.vram_d,
-            _ => {
-                #[cfg(feature = "tracing")]
-                tracing::error!("Invalid VRAM bank ID: {src_id}");
-                panic!("Invalid VRAM bank ID: {src_id}");
-            }
-        };
-        let dest_bytes = match dest_id {
-            0 => &self.vram_a,
-            1 => &self.vram_b,
-            2 => &self.vram_c,
-            3 => &self.vram_d,
-            _ => {
-                #[cfg(feature = "tracing")]
-                tracing::error!("Invalid VRAM bank ID: {dest_id}");
-                panic!("Invalid id VRAM bank ID: {dest_id}");
-            }
-        };
-        assert!(
-            (src_bytes.len() % 2) == 0 && (dest_bytes.len() % 2) == 0,
-            "u8 vector length must be even"
-        );
-        unsafe {
-            (
-                core::slice::from_raw_parts(src_bytes.as_ptr() as *mut u16, src_bytes.len() / 2),
-                core::slice::from_raw_parts_mut(
-                    dest_bytes.as_ptr() as *mut u16,
-                    dest_bytes.len() / 2,
-                ),
-            )
         }
     }
 
@@ -218,47 +188,65 @@ impl Gpu {
 
     /// Get VRAM bank configuration A
     pub fn get_vramcnt_a(&self) -> u8 {
-        ((self.vramcnt_a.mst & 0x7) as u8) | (if self.vramcnt_a.enabled { 0x80 } else { 0 })
+        ((self.vramcnt_a.mst & 0x7) as u8)
+            | ((self.vramcnt_a.offset & 0x3) as u8) << 3
+            | (if self.vramcnt_a.enabled { 0x80 } else { 0 })
     }
 
     /// Get VRAM bank configuration B
     pub fn get_vramcnt_b(&self) -> u8 {
-        ((self.vramcnt_b.mst & 0x7) as u8) | (if self.vramcnt_b.enabled { 0x80 } else { 0 })
+        ((self.vramcnt_b.mst & 0x7) as u8)
+            | ((self.vramcnt_b.offset & 0x3) as u8) << 3
+            | (if self.vramcnt_b.enabled { 0x80 } else { 0 })
     }
 
     /// Get VRAM bank configuration C
     pub fn get_vramcnt_c(&self) -> u8 {
-        ((self.vramcnt_c.mst & 0x7) as u8) | (if self.vramcnt_c.enabled { 0x80 } else { 0 })
+        ((self.vramcnt_c.mst & 0x7) as u8)
+            | ((self.vramcnt_c.offset & 0x3) as u8) << 3
+            | (if self.vramcnt_c.enabled { 0x80 } else { 0 })
     }
 
     /// Get VRAM bank configuration D
     pub fn get_vramcnt_d(&self) -> u8 {
-        ((self.vramcnt_d.mst & 0x7) as u8) | (if self.vramcnt_d.enabled { 0x80 } else { 0 })
+        ((self.vramcnt_d.mst & 0x7) as u8)
+            | ((self.vramcnt_d.offset & 0x3) as u8) << 3
+            | (if self.vramcnt_d.enabled { 0x80 } else { 0 })
     }
 
     /// Get VRAM bank configuration E
     pub fn get_vramcnt_e(&self) -> u8 {
-        ((self.vramcnt_e.mst & 0x7) as u8) | (if self.vramcnt_e.enabled { 0x80 } else { 0 })
+        ((self.vramcnt_e.mst & 0x7) as u8)
+            | ((self.vramcnt_e.offset & 0x3) as u8) << 3
+            | (if self.vramcnt_e.enabled { 0x80 } else { 0 })
     }
 
     /// Get VRAM bank configuration F
     pub fn get_vramcnt_f(&self) -> u8 {
-        ((self.vramcnt_f.mst & 0x7) as u8) | (if self.vramcnt_f.enabled { 0x80 } else { 0 })
+        ((self.vramcnt_f.mst & 0x7) as u8)
+            | ((self.vramcnt_f.offset & 0x3) as u8) << 3
+            | (if self.vramcnt_f.enabled { 0x80 } else { 0 })
     }
 
     /// Get VRAM bank configuration G
     pub fn get_vramcnt_g(&self) -> u8 {
-        ((self.vramcnt_g.mst & 0x7) as u8) | (if self.vramcnt_g.enabled { 0x80 } else { 0 })
+        ((self.vramcnt_g.mst & 0x7) as u8)
+            | ((self.vramcnt_g.offset & 0x3) as u8) << 3
+            | (if self.vramcnt_g.enabled { 0x80 } else { 0 })
     }
 
     /// Get VRAM bank configuration H
     pub fn get_vramcnt_h(&self) -> u8 {
-        ((self.vramcnt_h.mst & 0x7) as u8) | (if self.vramcnt_h.enabled { 0x80 } else { 0 })
+        ((self.vramcnt_h.mst & 0x7) as u8)
+            | ((self.vramcnt_h.offset & 0x3) as u8) << 3
+            | (if self.vramcnt_h.enabled { 0x80 } else { 0 })
     }
 
     /// Get VRAM bank configuration I
     pub fn get_vramcnt_i(&self) -> u8 {
-        ((self.vramcnt_i.mst & 0x7) as u8) | (if self.vramcnt_i.enabled { 0x80 } else { 0 })
+        ((self.vramcnt_i.mst & 0x7) as u8)
+            | ((self.vramcnt_i.offset & 0x3) as u8) << 3
+            | (if self.vramcnt_i.enabled { 0x80 } else { 0 })
     }
 
     /// Get POWCNT1 register value
@@ -277,4 +265,28 @@ impl Gpu {
     pub fn get_poly_count(&self) -> u16 {
         self.engine_3d.get_poly_count()
     }
+
+    /// Read a word of the 3D status and result ports (0x04000600..0x040006A4).
+    ///
+    /// Halfword and byte reads use the containing word.
+    pub fn read_gx_word(&self, address: u32) -> u32 {
+        match address & !0x3 {
+            0x0400_0600 => self.get_gxstat(),
+            // RAM_COUNT
+            0x0400_0604 => self.get_poly_count() as u32 | ((self.get_vert_count() as u32) << 16),
+            0x0400_0620..0x0400_0630 => self.engine_3d.read_pos_test(address & !0x3),
+            0x0400_0630..0x0400_0636 => {
+                let address = address & !0x3;
+                let lo = self.engine_3d.read_vec_test(address) as u32;
+                let hi = match address + 2 < 0x0400_0636 {
+                    true => self.engine_3d.read_vec_test(address + 2) as u32,
+                    false => 0,
+                };
+                lo | (hi << 16)
+            }
+            0x0400_0640..0x0400_0680 => self.engine_3d.read_clip_mtx(address & !0x3),
+            0x0400_0680..0x0400_06A4 => self.engine_3d.read_vec_mtx(address & !0x3),
+            _ => 0,
+        }
+    }
 }
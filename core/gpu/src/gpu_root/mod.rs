@@ -5,6 +5,7 @@
 // Graphics Processing Unit (GPU) implementation for Nintendo DS
 /// Handles 2D and 3D rendering, VRAM management, and display output
 pub(crate) mod arm_rw;
+pub mod dirty;
 pub(crate) mod draw_scanline;
 pub(crate) mod draw_scanline_3d;
 pub(crate) mod draw_sprite;
@@ -14,17 +15,29 @@ pub(crate) mod reader;
 pub mod register;
 pub(crate) mod render_2d;
 pub(crate) mod setter;
+pub mod state;
+pub mod taint;
+pub(crate) mod texture;
+pub mod vram_map;
 pub(crate) mod vram_reader;
 pub(crate) mod writer;
 
+use std::collections::VecDeque;
+
 use crate::gpu_2d::Gpu2DEngine;
 use crate::gpu_3d::structs::Gpu3D;
+use crate::gpu_root::dirty::DirtyMap;
 use crate::gpu_root::register::{DispStatReg, PowerCtrlReg, VramBankCfg};
+use crate::gpu_root::taint::{TaintRegion, VramTaint};
+use crate::gpu_root::vram_map::VramMapping;
 use lunaris_ds_mem_const::{
-    VRAM_A_SIZE, VRAM_B_SIZE, VRAM_C_SIZE, VRAM_D_SIZE, VRAM_E_SIZE, VRAM_F_SIZE, VRAM_G_SIZE,
-    VRAM_H_SIZE, VRAM_I_SIZE,
+    OAM_SIZE, PALETTE_SIZE, PIXELS_PER_LINE, VRAM_A_LEN, VRAM_B_LEN, VRAM_C_LEN, VRAM_D_LEN,
+    VRAM_E_LEN, VRAM_F_LEN, VRAM_G_LEN, VRAM_H_LEN, VRAM_I_LEN,
 };
 
+/// Words of the main memory display FIFO consumed per scanline (256 pixels).
+pub const DISPLAY_FIFO_LINE_WORDS: usize = 128;
+
 /// Graphics Processing Unit
 /// Manages 2D and 3D rendering for both screens
 #[derive(Debug)]
@@ -36,9 +49,9 @@ pub struct Gpu {
 
     pub engine_3d: Gpu3D,
 
-    /// Frame rendering complete flag
-    pub frame_complete: bool,
-    /// Number of frames skipped for frame skip
+    /// Set at VBLANK start, cleared by [`Gpu::poll_frame`]
+    frame_ready: bool,
+    /// Frames skipped in a row before the current one
     pub frames_skipped: u32,
 
     /// Cycle counter
@@ -81,9 +94,23 @@ pub struct Gpu {
     vramcnt_g: VramBankCfg,
     vramcnt_h: VramBankCfg,
     vramcnt_i: VramBankCfg,
+    /// Where each bank A-I is mapped, rebuilt from VRAMCNT
+    vram_map: [Option<VramMapping>; 9],
 
     /// Power control register
     power_control_reg: PowerCtrlReg,
+
+    /// Main memory display FIFO (DISP_MMEM_FIFO), two BGR555 pixels per word
+    display_fifo: VecDeque<u32>,
+
+    /// Uninitialized read tracking, off unless enabled
+    taint: VramTaint,
+    /// Writes not yet taken by [`Gpu::take_dirty`]
+    dirty: DirtyMap,
+    /// Writes not yet seen by the renderer caches
+    render_dirty: DirtyMap,
+    /// Standard palettes of engine A and B decoded to ARGB8888
+    palette_argb: [Vec<u32>; 2],
 }
 
 impl Default for Gpu {
@@ -96,29 +123,29 @@ impl Gpu {
     /// Create new GPU instance
     pub fn new() -> Self {
         Gpu {
-            engine_upper: Gpu2DEngine::new(),
-            engine_lower: Gpu2DEngine::new(),
+            engine_upper: Gpu2DEngine::new(true),
+            engine_lower: Gpu2DEngine::new(false),
             engine_3d: Gpu3D::new(),
 
-            frame_complete: false,
+            frame_ready: false,
             frames_skipped: 0,
 
             cycles: 0,
 
-            vram_a: vec![0u8; VRAM_A_SIZE as usize],
-            vram_b: vec![0u8; VRAM_B_SIZE as usize],
-            vram_c: vec![0u8; VRAM_C_SIZE as usize],
-            vram_d: vec![0u8; VRAM_D_SIZE as usize],
-            vram_e: vec![0u8; VRAM_E_SIZE as usize],
-            vram_f: vec![0u8; VRAM_F_SIZE as usize],
-            vram_g: vec![0u8; VRAM_G_SIZE as usize],
-            vram_h: vec![0u8; VRAM_H_SIZE as usize],
-            vram_i: vec![0u8; VRAM_I_SIZE as usize],
+            vram_a: vec![0u8; VRAM_A_LEN],
+            vram_b: vec![0u8; VRAM_B_LEN],
+            vram_c: vec![0u8; VRAM_C_LEN],
+            vram_d: vec![0u8; VRAM_D_LEN],
+            vram_e: vec![0u8; VRAM_E_LEN],
+            vram_f: vec![0u8; VRAM_F_LEN],
+            vram_g: vec![0u8; VRAM_G_LEN],
+            vram_h: vec![0u8; VRAM_H_LEN],
+            vram_i: vec![0u8; VRAM_I_LEN],
 
-            palette_upper: vec![0u8; 1024],
-            palette_lower: vec![0u8; 1024],
+            palette_upper: vec![0u8; PALETTE_SIZE],
+            palette_lower: vec![0u8; PALETTE_SIZE],
 
-            oam: vec![0u8; 2048],
+            oam: vec![0u8; OAM_SIZE],
 
             display_status_arm7: DispStatReg::new(),
             display_status_arm9: DispStatReg::new(),
@@ -134,18 +161,20 @@ impl Gpu {
             vramcnt_g: VramBankCfg::new(),
             vramcnt_h: VramBankCfg::new(),
             vramcnt_i: VramBankCfg::new(),
+            vram_map: [None; 9],
 
             power_control_reg: PowerCtrlReg::new(),
+
+            display_fifo: VecDeque::with_capacity(DISPLAY_FIFO_LINE_WORDS),
+
+            taint: VramTaint::default(),
+            dirty: DirtyMap::new(),
+            render_dirty: DirtyMap::new(),
+            palette_argb: [vec![0xFF00_0000; 512], vec![0xFF00_0000; 512]],
         }
     }
 }
 
-// TODO: Rename bytes_to_u16_slice()
-pub fn bytes_to_palette(bytes: &[u8]) -> &[u16] {
-    assert_eq!(bytes.len() % 2, 0, "u8 vector length must be even");
-    unsafe { core::slice::from_raw_parts(bytes.as_ptr() as *const u16, bytes.len() / 2) }
-}
-
 /// # Panics
 /// Panics if address is out of bounds(<= 0x3ff == 1023) for palette memory
 pub fn read_palette_value(bytes: &[u8], address: u32) -> u16 {
@@ -154,19 +183,6 @@ pub fn read_palette_value(bytes: &[u8], address: u32) -> u16 {
 }
 
 impl Gpu {
-    pub fn draw_bg_txt_line(&self, index: i32, engine_a: bool) {
-        let (_, _) = (index, engine_a);
-        unimplemented!("C++ code is empty.")
-    }
-    pub fn draw_bg_extended_line(&self, index: i32, engine_a: bool) {
-        let (_, _) = (index, engine_a);
-        unimplemented!("C++ code is empty.")
-    }
-    pub fn draw_sprite_line(&self, engine_a: bool) {
-        let _ = engine_a;
-        unimplemented!("C++ code is empty.")
-    }
-
     // moved draw_scanline.rs
     // pub fn draw_scanline(&self) {}
     // pub fn draw_3d_scanline(&mut self, is_engine_a: bool, bg_priority: u8)
@@ -180,7 +196,7 @@ impl Gpu {
     pub fn power_on(&mut self) {
         // self.eng_3d.power_on();
         self.cycles = 0;
-        self.frame_complete = false;
+        self.frame_ready = false;
         self.frames_skipped = 0;
 
         self.set_powcnt1(0x820F);
@@ -198,19 +214,24 @@ impl Gpu {
             self.set_bgvofs_b(0, i);
         }
 
-        self.vram_a.clear();
-        self.vram_b.clear();
-        self.vram_c.clear();
-        self.vram_d.clear();
-        self.vram_e.clear();
-        self.vram_f.clear();
-        self.vram_g.clear();
-        self.vram_h.clear();
-        self.vram_i.clear();
+        self.vram_a.fill(0);
+        self.vram_b.fill(0);
+        self.vram_c.fill(0);
+        self.vram_d.fill(0);
+        self.vram_e.fill(0);
+        self.vram_f.fill(0);
+        self.vram_g.fill(0);
+        self.vram_h.fill(0);
+        self.vram_i.fill(0);
+        self.display_fifo.clear();
+        self.taint.reset();
+        for region in TaintRegion::ALL {
+            self.dirty.mark_all(region);
+            self.render_dirty.mark_all(region);
+        }
     }
 
     // moved struct Emulator;
-    // pub fn handle_event(&self, event: &SchedulerEvent);
 
     /// Get upper screen framebuffer data
     #[inline]
@@ -232,23 +253,74 @@ impl Gpu {
         engine.get_framebuffer(buffer);
     }
 
-    /// Mark frame start
-    pub fn start_frame(&mut self) {
-        self.frame_complete = false;
+    /// Upper screen framebuffer, borrowed instead of copied
+    pub fn upper_screen(&self) -> &[u32] {
+        match self.power_control_reg.swap_display {
+            true => &self.engine_upper.front_framebuffer,
+            false => &self.engine_lower.front_framebuffer,
+        }
+    }
+
+    /// Lower screen framebuffer, borrowed instead of copied
+    pub fn lower_screen(&self) -> &[u32] {
+        match self.power_control_reg.swap_display {
+            true => &self.engine_lower.front_framebuffer,
+            false => &self.engine_upper.front_framebuffer,
+        }
     }
 
-    /// Mark frame completion
+    /// Mark the frame complete. Done at VBLANK start; calling it from
+    /// outside forces the emulator's frame loop to end after its current
+    /// slice.
     pub fn end_frame(&mut self) {
-        self.frame_complete = true;
+        self.frame_ready = true;
+    }
+
+    /// Whether a frame completed since the last call, clearing the flag.
+    ///
+    /// Check and clear are one step, so a frame is never seen twice or
+    /// lost between a separate query and reset.
+    pub fn poll_frame(&mut self) -> bool {
+        std::mem::take(&mut self.frame_ready)
     }
 
     // moved gpu_3d.rs in lunaris_emu/gpu
     // fn check_gxfifo_dma(&self)
     // fn check_gxfifo_irq(&self)
 
-    /// Check if current frame is complete
-    pub fn is_frame_complete(&self) -> bool {
-        self.frame_complete
+    /// Push a word to the main memory display FIFO (0x04000068).
+    ///
+    /// The FIFO holds at most one scanline; further writes are dropped until
+    /// the line is consumed, as the real FIFO stalls the feeding DMA.
+    pub fn write_disp_mmem_fifo(&mut self, word: u32) {
+        if self.display_fifo.len() < DISPLAY_FIFO_LINE_WORDS {
+            self.display_fifo.push_back(word);
+        }
+    }
+
+    /// Words waiting in the main memory display FIFO.
+    pub fn display_fifo_len(&self) -> usize {
+        self.display_fifo.len()
+    }
+
+    /// Whether the main memory display FIFO holds a whole line.
+    pub fn display_fifo_full(&self) -> bool {
+        self.display_fifo.len() >= DISPLAY_FIFO_LINE_WORDS
+    }
+
+    /// Take one scanline of pixels from the main memory display FIFO.
+    ///
+    /// Missing pixels (FIFO underrun) read as black.
+    pub(crate) fn pop_display_fifo_line(&mut self) -> [u16; PIXELS_PER_LINE] {
+        let mut line = [0u16; PIXELS_PER_LINE];
+        for pixels in line.chunks_exact_mut(2) {
+            let Some(word) = self.display_fifo.pop_front() else {
+                break;
+            };
+            pixels[0] = word as u16;
+            pixels[1] = (word >> 16) as u16;
+        }
+        line
     }
 
     /// Check if display screens are swapped
@@ -296,7 +368,7 @@ impl Gpu {
         self.engine_3d.read_vec_test(address)
     }
 
-    pub fn read_clip_mtx(&mut self, address: u32) -> u32 {
+    pub fn read_clip_mtx(&self, address: u32) -> u32 {
         self.engine_3d.read_clip_mtx(address)
     }
 
@@ -304,3 +376,24 @@ impl Gpu {
         self.engine_3d.read_vec_mtx(address)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_poll_frame() {
+        let mut gpu = Gpu::new();
+        gpu.power_on();
+        assert!(!gpu.poll_frame());
+
+        gpu.end_frame();
+        assert!(gpu.poll_frame());
+        assert!(!gpu.poll_frame());
+
+        // Power on drops a frame nobody polled
+        gpu.end_frame();
+        gpu.power_on();
+        assert!(!gpu.poll_frame());
+    }
+}
@@ -6,7 +6,9 @@
 //!
 //! CorgiDS was calling GPU methods in Engine2D, but this caused a circular reference.
 //! To avoid this, we've implemented the method in the parent here.
-use crate::gpu_root::{Gpu, bytes_to_palette, read_palette_value};
+use crate::gpu_2d::{BlendTargets, LAYER_BACKDROP, LAYER_OBJ};
+use crate::gpu_root::Gpu;
+use crate::gpu_root::dirty::bgr555_to_argb;
 use lunaris_ds_mem_const::*;
 
 impl Gpu {
@@ -47,223 +49,111 @@ impl Gpu {
             engine.window_mask[i] = outside;
         }
 
-        if engine.dispcnt.obj_win_display {
-            // TODO: WINOBJ
-        }
-
-        if engine.dispcnt.display_win1 && engine.win1_active {
-            let x1 = (engine.win1h >> 8) as usize;
-            let x2 = (engine.win1h & 0xFF) as usize;
-            let mask = (engine.get_winin() >> 8) as u8;
-
-            for x in x1..x2 {
-                if x < PIXELS_PER_LINE {
+        // OBJ window, below windows 0 and 1. Its OBJs are only rendered
+        // with the OBJ layer enabled too
+        if engine.dispcnt.obj_win_display && engine.dispcnt.display_obj {
+            let mask = (engine.get_winout() >> 8) as u8;
+            for x in 0..PIXELS_PER_LINE {
+                if engine.obj_line[x].window {
                     engine.window_mask[x] = mask;
                 }
             }
         }
 
-        if engine.dispcnt.display_win0 && engine.win0_active {
-            let x1 = (engine.win0h >> 8) as usize;
-            let x2 = (engine.win0h & 0xFF) as usize;
-            let mask = (engine.get_winin() & 0xFF) as u8;
-
-            for x in x1..x2 {
-                if x < PIXELS_PER_LINE {
-                    engine.window_mask[x] = mask;
-                }
+        // Window 0 has priority over window 1. A left edge past the right
+        // one wraps the window around the screen edge.
+        let windows = [
+            (
+                engine.dispcnt.display_win1 && engine.win1_active,
+                engine.win1h,
+                8,
+            ),
+            (
+                engine.dispcnt.display_win0 && engine.win0_active,
+                engine.win0h,
+                0,
+            ),
+        ];
+        for (enabled, winh, shift) in windows {
+            if !enabled {
+                continue;
             }
-        }
-    }
-
-    pub fn handle_bldcnt_effects(&mut self) {
-        // Nothing impl in CorgiDS
-    }
-
-    /// Draws an extended text background (BG2 or BG3) for the current scanline.
-    pub fn draw_ext_text(&mut self, index: usize, is_engine_a: bool) {
-        let (rot_a, rot_b, rot_c, rot_d): (i16, i16, i16, i16);
-        let (mut x_offset, mut y_offset): (i32, i32);
-        let overflow_mask: u32;
-        let mask: u32;
-        let mut y_factor: u32;
-        let mut screen_base: u32;
-        let mut char_base: u32;
-        let extpal_base: u32;
-
-        {
-            let engine = match is_engine_a {
-                true => &mut self.engine_upper,
-                false => &mut self.engine_lower,
-            };
-
-            // Load rotation/scaling matrix and offsets
-            (rot_a, rot_b, rot_c, rot_d) = match index {
-                2 => (
-                    engine.bg2p_internal[0] as i16,
-                    engine.bg2p_internal[1] as i16,
-                    engine.bg2p_internal[2] as i16,
-                    engine.bg2p_internal[3] as i16,
-                ),
-                _ => (
-                    engine.bg3p_internal[0] as i16,
-                    engine.bg3p_internal[1] as i16,
-                    engine.bg3p_internal[2] as i16,
-                    engine.bg3p_internal[3] as i16,
-                ),
-            };
-
-            (x_offset, y_offset) = match index {
-                2 => (engine.bg2x_internal, engine.bg2y_internal),
-                _ => (engine.bg3x_internal, engine.bg3y_internal),
-            };
-
-            // Determine base addresses for screen and character data
-            (screen_base, char_base) = match is_engine_a {
-                true => (
-                    VRAM_BGA_START + (engine.dispcnt.screen_base as u32 * 1024 * 64),
-                    VRAM_BGA_START + (engine.dispcnt.char_base as u32 * 1024 * 64),
-                ),
-                false => (VRAM_BGB_C, VRAM_BGB_C),
-            };
-
-            // Apply BG-specific offsets
-            screen_base += (((engine.bgcnt[index] >> 8) & 0x1F) as u32) * 1024 * 2;
-            char_base += (((engine.bgcnt[index] >> 2) & 0xF) as u32) * 1024 * 16;
-
-            // Determine screen size and mask
-            let screen_size = (engine.bgcnt[index] >> 14) & 0x3;
-            mask = match screen_size {
-                0 => 0x7800,
-                1 => 0xF800,
-                2 => 0x1F800,
-                3 => 0x3F800,
-                _ => 0x7800, // default fallback
-            };
-            y_factor = screen_size as u32 + 7;
-            y_factor -= 3;
-
-            extpal_base = index as u32 * 1024 * 8;
-            overflow_mask = if (engine.bgcnt[index] & (1 << 13)) != 0 {
-                0
-            } else {
-                !(mask | 0x7FF)
-            };
-        }
-
-        // Iterate over pixels in the current scanline
-        for pixel in 0..PIXELS_PER_LINE {
-            if ((x_offset | y_offset) & overflow_mask as i32) == 0 {
-                // Compute tile offset
-                let mut tile_addr_offset = ((y_offset as u32 & mask) >> 11) << y_factor;
-                tile_addr_offset += (x_offset as u32 & mask) >> 11;
-                tile_addr_offset <<= 1;
-
-                // Read tile ID
-                let tile: u16 = match is_engine_a {
-                    true => self.read_bga_u16(screen_base + tile_addr_offset),
-                    false => self.read_bgb_u16(screen_base + tile_addr_offset),
-                };
-
-                let char_id = (tile & 0x3FF) as u32;
-                let x_flip = (tile & (1 << 10)) != 0;
-                let y_flip = (tile & (1 << 11)) != 0;
-                let palette_id = (tile >> 12) & 0xF;
-
-                // Determine tile pixel coordinates
-                let mut tile_x_offset = ((x_offset >> 8) & 0x7) as u32;
-                let mut tile_y_offset = ((y_offset >> 8) & 0x7) as u32;
-                if x_flip {
-                    tile_x_offset = 7 - tile_x_offset;
-                }
-                if y_flip {
-                    tile_y_offset = 7 - tile_y_offset;
-                }
-
-                // Read color index from character data
-                let address = char_base + (char_id << 6) + (tile_y_offset << 3) + tile_x_offset;
-                let mut color: u16 = match is_engine_a {
-                    true => self.read_bga_u8(address) as u16,
-                    false => self.read_bgb_u8(address) as u16,
+            let x1 = (winh >> 8) as usize;
+            let x2 = (winh & 0xFF) as usize;
+            let mask = (engine.get_winin() >> shift) as u8;
+
+            for x in 0..PIXELS_PER_LINE {
+                let inside = match x1 <= x2 {
+                    true => (x1..x2).contains(&x),
+                    false => x >= x1 || x < x2,
                 };
-
-                if color != 0 {
-                    let bg_extended_palette = {
-                        let engine = match is_engine_a {
-                            true => &mut self.engine_upper,
-                            false => &mut self.engine_lower,
-                        };
-                        engine.dispcnt.bg_extended_palette
-                    };
-
-                    // Convert to true color
-                    color = if bg_extended_palette {
-                        let address = extpal_base + color as u32 * 2 + palette_id as u32 * 512;
-                        match is_engine_a {
-                            true => self.read_extpal_bga_u16(address),
-                            false => self.read_extpal_bgb_u16(address),
-                        }
-                    } else {
-                        match is_engine_a {
-                            true => read_palette_value(&self.palette_upper, color as u32 * 2),
-                            false => read_palette_value(&self.palette_lower, color as u32 * 2),
-                        }
-                    };
-
-                    let r = ((color & 0x1F) << 3) as u32;
-                    let g = (((color >> 5) & 0x1F) << 3) as u32;
-                    let b = (((color >> 10) & 0x1F) << 3) as u32;
-                    let true_color = 0xFF000000 | (r << 16) | (g << 8) | b;
-
-                    let address = pixel + (self.get_vcount() as usize * PIXELS_PER_LINE);
-
-                    let engine = match is_engine_a {
-                        true => &mut self.engine_upper,
-                        false => &mut self.engine_lower,
-                    };
-                    engine.framebuffer[address] = true_color;
-                    engine.final_bg_priority[pixel] = (engine.bgcnt[index] & 0x3) as u8;
+                if inside {
+                    engine.window_mask[x] = mask;
                 }
             }
-
-            // Advance coordinates using rotation/scaling matrix
-            x_offset += rot_a as i32;
-            y_offset += rot_c as i32;
         }
+    }
 
+    /// Applies the BLDCNT color special effect to the current line.
+    ///
+    /// Where the window enables effects and the front pixel is a first
+    /// target, it is alpha blended with the pixel below when that is a
+    /// second target (BLDALPHA), or brightened or darkened (BLDY).
+    /// Semi-transparent and bitmap OBJs blend over a second target whatever
+    /// the effect and window, bitmap OBJs with their own alpha.
+    pub fn handle_bldcnt_effects(&mut self, is_engine_a: bool) {
+        let line_start = self.get_vcount() as usize * PIXELS_PER_LINE;
         let engine = match is_engine_a {
             true => &mut self.engine_upper,
             false => &mut self.engine_lower,
         };
 
-        // Update BG internal offsets for next scanline
-        if index == 2 {
-            engine.bg2x_internal += rot_b as i32;
-            engine.bg2y_internal += rot_d as i32;
-        } else {
-            engine.bg3x_internal += rot_b as i32;
-            engine.bg3y_internal += rot_d as i32;
+        let targets = engine.bldcnt.get();
+        let effect = engine.bldcnt.effect;
+        // Coefficients are 1.4 fixed point, saturating at 16/16
+        let eva = (engine.bldalpha & 0x1F).min(16) as u32;
+        let evb = ((engine.bldalpha >> 8) & 0x1F).min(16) as u32;
+        let evy = (engine.bldy & 0x1F).min(16) as u32;
+
+        for x in 0..PIXELS_PER_LINE {
+            let pixel = engine.blend_line[x];
+            let front = engine.framebuffer[line_start + x];
+            let first_target = targets & (1 << pixel.layer) != 0;
+            let second_target = targets & (1 << (8 + pixel.below_layer)) != 0;
+            let obj = engine.obj_line[x];
+            let obj_blend =
+                pixel.layer == LAYER_OBJ && (obj.semi_transparent || obj.alpha.is_some());
+
+            let color = match effect {
+                _ if obj_blend && second_target => {
+                    let (eva, evb) = match obj.alpha {
+                        Some(alpha) => (alpha as u32 + 1, 15 - alpha as u32),
+                        None => (eva, evb),
+                    };
+                    map_channels(front, pixel.below, |a, b| (a * eva + b * evb) / 16)
+                }
+                _ if engine.window_mask[x] & (1 << 5) == 0 || !first_target => continue,
+                1 if second_target => {
+                    map_channels(front, pixel.below, |a, b| (a * eva + b * evb) / 16)
+                }
+                2 => map_channels(front, 0, |a, _| a + (31 - a) * evy / 16),
+                3 => map_channels(front, 0, |a, _| a - a * evy / 16),
+                _ => continue,
+            };
+            engine.framebuffer[line_start + x] = color;
         }
     }
 
     /// Draws the backdrop (background color layer).
+    ///
+    /// The backdrop starts as both the front layer and its own second target.
     pub fn draw_backdrop(&mut self, is_engine_a: bool) {
-        let palette = bytes_to_palette(match is_engine_a {
-            true => &self.palette_upper,
-            false => &self.palette_lower,
-        });
-        let c = palette[0];
+        let color = self.palette_argb(is_engine_a, 0);
 
         let y = self.get_vcount() as usize;
         let base = y * PIXELS_PER_LINE;
 
         for x in 0..PIXELS_PER_LINE {
-            let r = ((c & 0x1F) << 3) as u32;
-            let g = (((c >> 5) & 0x1F) << 3) as u32;
-            let b = (((c >> 10) & 0x1F) << 3) as u32;
-
-            let color = 0xFF000000 | (r << 16) | (g << 8) | b;
-
             let engine = match is_engine_a {
                 true => &mut self.engine_upper,
                 false => &mut self.engine_lower,
@@ -273,389 +163,585 @@ impl Gpu {
             if (base + x) < PIXELS_PER_LINE * SCANLINES {
                 engine.framebuffer[base + x] = color;
             }
+            engine.blend_line[x] = BlendTargets {
+                layer: LAYER_BACKDROP,
+                below: color,
+                below_layer: LAYER_BACKDROP,
+            };
         }
     }
 
-    /// Draws a text background layer.
+    /// Draws one line of a text background layer.
     ///
-    /// `index` must be 0..=3.
-    pub fn draw_bg_txt(&mut self, index: usize, is_engine_a: bool) {
-        let palette = bytes_to_palette(match is_engine_a {
-            true => &self.palette_upper,
-            false => &self.palette_lower,
-        });
-
+    /// `index` must be 0..=3. The map is 256 or 512 pixels on each side
+    /// (BGCNT screen size) and made of 32x32 tile screen blocks; BGHOFS and
+    /// BGVOFS scroll it with wraparound. Tiles are 16 colors with 16
+    /// palettes, or 256 colors from the standard palette or, with DISPCNT
+    /// bit 30, extended palette slot `index` (slot 2/3 for BG0/BG1 with
+    /// BGCNT bit 13). With BGCNT bit 6 the MOSAIC register stretches each
+    /// block's top left pixel over the block.
+    pub fn draw_bg_txt_line(&mut self, index: usize, is_engine_a: bool) {
         let v_count = self.get_vcount();
-
-        let mut x_offset: u16;
-        let y_offset: u16;
-        let one_palette_mode: bool;
-        let mut screen_base: u32;
-        let mut char_base: u32;
-        let wide_x: u16;
         let scanline = v_count as usize * PIXELS_PER_LINE;
 
-        {
+        let (bgcnt, screen_base, char_base, x_scroll, y, extended_palette, mosaic_width) = {
             let engine = match is_engine_a {
-                true => &mut self.engine_upper,
-                false => &mut self.engine_lower,
+                true => &self.engine_upper,
+                false => &self.engine_lower,
             };
+            let bgcnt = engine.bgcnt[index];
+            let (mosaic_width, mosaic_height) = engine.bg_mosaic(index);
+            let v_count = v_count as u32 - v_count as u32 % mosaic_height;
 
-            x_offset = engine.bghofs[index];
-            y_offset = engine.bgvofs[index].wrapping_add(v_count);
-
-            one_palette_mode = (engine.bgcnt[index] & (1 << 7)) != 0;
-
-            screen_base = match is_engine_a {
-                true => VRAM_BGA_START + (engine.dispcnt.screen_base as u32 * 1024 * 64),
-                false => VRAM_BGB_C,
-            };
-            char_base = match is_engine_a {
-                true => VRAM_BGA_START + (engine.dispcnt.char_base as u32 * 1024 * 64),
-                false => VRAM_BGB_C,
+            // Engine B has no DISPCNT screen/char base
+            let (screen_base, char_base) = match is_engine_a {
+                true => (
+                    VRAM_BGA_START + engine.dispcnt.screen_base as u32 * 0x10000,
+                    VRAM_BGA_START + engine.dispcnt.char_base as u32 * 0x10000,
+                ),
+                false => (VRAM_BGB_C, VRAM_BGB_C),
             };
 
-            screen_base += ((engine.bgcnt[index] >> 8) & 0x1F) as u32 * 1024 * 2;
-
-            if (engine.bgcnt[index] & 0x8000) != 0 {
-                screen_base += ((y_offset & 0x1F8) as u32) * 8;
-                if (engine.bgcnt[index] & 0x4000) != 0 {
-                    screen_base += ((y_offset & 0x100) as u32) * 8;
-                }
+            let height = if bgcnt.screen_size & 0x2 != 0 {
+                512
             } else {
-                screen_base += ((y_offset & 0xF8) as u32) * 8;
-            }
-
-            char_base += ((engine.bgcnt[index] >> 2) & 0xF) as u32 * 1024 * 16;
-
-            wide_x = match (engine.bgcnt[index] & (1 << 14)) != 0 {
-                true => 0x100,
-                false => 0,
+                256
             };
-        }
+            (
+                bgcnt,
+                screen_base + bgcnt.screen_base * 0x800,
+                char_base + bgcnt.char_base * 0x4000,
+                engine.bghofs[index] as u32,
+                (engine.bgvofs[index] as u32 + v_count) % height,
+                engine.dispcnt.bg_extended_palette,
+                mosaic_width,
+            )
+        };
 
-        if !one_palette_mode {
-            // 16-color tiles
-            let mut data: u32 = 0;
-            let mut palette_id = 0;
-            let mut x_flip: bool = false;
+        let width_mask = if bgcnt.screen_size & 0x1 != 0 {
+            0x1FF
+        } else {
+            0xFF
+        };
+        let ext_slot = match index < 2 && bgcnt.overflow {
+            true => index as u32 + 2,
+            false => index as u32,
+        };
+        let read_u16 = |gpu: &Self, address: u32| match is_engine_a {
+            true => gpu.read_bga_u16(address),
+            false => gpu.read_bgb_u16(address),
+        };
 
-            if (x_offset & 0x7) != 0 {
-                let addr = screen_base
-                    + (((x_offset & 0xF8) as u32) >> 2)
-                    + (((x_offset & wide_x) as u32) << 3);
+        // Tile entry and its row of pixel data, refetched per tile
+        let mut tile_column = u32::MAX;
+        let mut tile = 0u16;
+        let mut row = 0u64;
 
-                let tile = match is_engine_a {
-                    true => self.read_bga_u16(addr),
-                    false => self.read_bgb_u16(addr),
+        for pixel in 0..PIXELS_PER_LINE {
+            let x = (x_scroll + pixel as u32 - pixel as u32 % mosaic_width) & width_mask;
+
+            if x / 8 != tile_column {
+                tile_column = x / 8;
+
+                // Screen blocks are 32x32 tiles, laid out left to right,
+                // then top to bottom
+                let block = match bgcnt.screen_size {
+                    1 => x / 256,
+                    2 => y / 256,
+                    3 => x / 256 + (y / 256) * 2,
+                    _ => 0,
                 };
+                let entry = ((y / 8) % 32) * 32 + tile_column % 32;
+                tile = read_u16(self, screen_base + block * 0x800 + entry * 2);
 
-                let tile_num = tile & 0x3FF;
-                x_flip = (tile & (1 << 10)) != 0;
-                let y_flip = (tile & (1 << 11)) != 0;
-                palette_id = tile >> 12;
-
-                let pixel_base = char_base
-                    + (tile_num as u32 * 32)
-                    + if y_flip {
-                        (7 - (y_offset & 0x7)) as u32 * 4
-                    } else {
-                        (y_offset & 0x7) as u32 * 4
-                    };
-
-                data = match is_engine_a {
-                    true => self.read_bga_u32(addr),
-                    false => self.read_bgb_u32(pixel_base),
+                let tile_num = (tile & 0x3FF) as u32;
+                let tile_y = match tile & (1 << 11) != 0 {
+                    true => 7 - (y & 0x7),
+                    false => y & 0x7,
                 };
-            }
-
-            let addr = screen_base
-                + (((x_offset & 0xF8) as u32) >> 2)
-                + (((x_offset & wide_x) as u32) << 3);
-            let tile = match is_engine_a {
-                true => self.read_bga_u16(addr),
-                false => self.read_bgb_u16(addr),
-            };
-
-            if (x_offset & 0x7) == 0 {
-                let tile_num = tile & 0x3FF;
-                x_flip = (tile & (1 << 10)) != 0;
-                let y_flip = (tile & (1 << 11)) != 0;
-                palette_id = tile >> 12;
-
-                let pixel_base = char_base
-                    + (tile_num as u32 * 32)
-                    + if y_flip {
-                        (7 - (y_offset & 0x7)) as u32 * 4
-                    } else {
-                        (y_offset & 0x7) as u32 * 4
-                    };
-                data = match is_engine_a {
-                    true => self.read_bga_u32(addr),
-                    false => self.read_bgb_u32(pixel_base),
+                row = match (bgcnt.palette_256, is_engine_a) {
+                    (false, true) => {
+                        self.read_bga_u32(char_base + tile_num * 32 + tile_y * 4) as u64
+                    }
+                    (false, false) => {
+                        self.read_bgb_u32(char_base + tile_num * 32 + tile_y * 4) as u64
+                    }
+                    (true, true) => self.read_bga_u64(char_base + tile_num * 64 + tile_y * 8),
+                    (true, false) => self.read_bgb_u64(char_base + tile_num * 64 + tile_y * 8),
                 };
             }
 
-            let tile_x = match x_flip {
-                true => 7 - (x_offset & 0x7),
-                false => x_offset & 0x7,
+            let window_mask = match is_engine_a {
+                true => self.engine_upper.window_mask[pixel],
+                false => self.engine_lower.window_mask[pixel],
             };
-            let color = ((data >> (tile_x * 4)) & 0xF) as u16;
-
-            for pixel in 0..PIXELS_PER_LINE {
-                let engine = match is_engine_a {
-                    true => &mut self.engine_upper,
-                    false => &mut self.engine_lower,
-                };
-
-                if color != 0 && (engine.window_mask[pixel] & (1 << index)) != 0 {
-                    let pal_color = palette[(palette_id as usize * 16) + color as usize];
-
-                    let r = ((pal_color & 0x1F) << 3) as u32;
-                    let g = (((pal_color >> 5) & 0x1F) << 3) as u32;
-                    let b = (((pal_color >> 10) & 0x1F) << 3) as u32;
-
-                    engine.framebuffer[pixel + scanline] = 0xFF000000 | (r << 16) | (g << 8) | b;
-                    engine.final_bg_priority[pixel] = (engine.bgcnt[index] & 0x3) as u8;
-                }
-
-                x_offset = x_offset.wrapping_add(1);
+            if (window_mask & (1 << index)) == 0 {
+                continue;
             }
-        } else {
-            // 256-color tiles
-            let mut data: u64 = 0;
-            let mut palette_id: u16 = 0;
-            let mut x_flip: bool = false;
-
-            if (x_offset & 0x7) != 0 {
-                let addr = screen_base
-                    + (((x_offset & 0xF8) as u32) >> 2)
-                    + (((x_offset & wide_x) as u32) << 3);
-
-                let tile = match is_engine_a {
-                    true => self.read_bga_u16(addr),
-                    false => self.read_bgb_u16(addr),
-                };
 
-                let tile_num = tile & 0x3FF;
-                x_flip = (tile & (1 << 10)) != 0;
-                let y_flip = (tile & (1 << 11)) != 0;
-                palette_id = tile >> 12;
-
-                let pixel_base = char_base
-                    + (tile_num as u32 * 64)
-                    + if y_flip {
-                        (7 - (y_offset & 0x7)) as u32 * 8
-                    } else {
-                        (y_offset & 0x7) as u32 * 8
-                    };
-
-                data = match is_engine_a {
-                    true => self.read_bga_u64(addr),
-                    false => self.read_bgb_u64(pixel_base),
-                };
-            }
-
-            for pixel in 0..PIXELS_PER_LINE {
-                if (x_offset & 0x7) == 0 {
-                    let addr = screen_base
-                        + (((x_offset & 0xF8) as u32) >> 2)
-                        + (((x_offset & wide_x) as u32) << 3);
-
-                    let tile = self.read_bga_u16(addr);
-
-                    let tile_num = tile & 0x3FF;
-                    x_flip = (tile & (1 << 10)) != 0;
-                    let y_flip = (tile & (1 << 11)) != 0;
-                    palette_id = tile >> 12;
-
-                    let pixel_base = char_base
-                        + (tile_num as u32 * 64)
-                        + if y_flip {
-                            (7 - (y_offset & 0x7)) as u32 * 8
-                        } else {
-                            (y_offset & 0x7) as u32 * 8
-                        };
-
-                    data = match is_engine_a {
-                        true => self.read_bga_u64(addr),
-                        false => self.read_bgb_u64(pixel_base),
-                    };
+            let tile_x = match tile & (1 << 10) != 0 {
+                true => 7 - (x & 0x7),
+                false => x & 0x7,
+            };
+            let palette_id = (tile >> 12) as u32;
+            let argb = match bgcnt.palette_256 {
+                false => {
+                    let color = ((row >> (tile_x * 4)) & 0xF) as usize;
+                    if color == 0 {
+                        continue;
+                    }
+                    self.palette_argb(is_engine_a, palette_id as usize * 16 + color)
                 }
-
-                let tile_x = if x_flip {
-                    7 - (x_offset & 0x7)
-                } else {
-                    x_offset & 0x7
-                };
-
-                let mut color = ((data >> (tile_x * 8)) & 0xFF) as u16;
-
-                let (window_mask_byte, bg_extended_palette) = match is_engine_a {
-                    true => (
-                        &self.engine_upper.window_mask[pixel],
-                        self.engine_upper.dispcnt.bg_extended_palette,
-                    ),
-                    false => (
-                        &self.engine_lower.window_mask[pixel],
-                        self.engine_upper.dispcnt.bg_extended_palette,
-                    ),
-                };
-
-                if color != 0 && (window_mask_byte & (1 << index)) != 0 {
-                    if bg_extended_palette {
-                        let ext_base = index as u32 * 1024 * 8;
-                        let address = ext_base + (palette_id as u32 * 512) + (color as u32 * 2);
-
-                        color = match is_engine_a {
-                            true => self.read_extpal_bga_u16(address),
-                            false => self.read_extpal_bgb_u16(address),
-                        };
-                    } else {
-                        color = palette[color as usize];
+                true => {
+                    let color = ((row >> (tile_x * 8)) & 0xFF) as u32;
+                    if color == 0 {
+                        continue;
+                    }
+                    match extended_palette {
+                        true => {
+                            let address = ext_slot * 0x2000 + palette_id * 512 + color * 2;
+                            bgr555_to_argb(match is_engine_a {
+                                true => self.read_extpal_bga_u16(address),
+                                false => self.read_extpal_bgb_u16(address),
+                            })
+                        }
+                        false => self.palette_argb(is_engine_a, color as usize),
                     }
-
-                    let r = ((color & 0x1F) << 3) as u32;
-                    let g = (((color >> 5) & 0x1F) << 3) as u32;
-                    let b = (((color >> 10) & 0x1F) << 3) as u32;
-
-                    let engine = match is_engine_a {
-                        true => &mut self.engine_upper,
-                        false => &mut self.engine_lower,
-                    };
-                    engine.framebuffer[pixel + scanline] = 0xFF000000 | (r << 16) | (g << 8) | b;
-                    engine.final_bg_priority[pixel] = (engine.bgcnt[index] & 0x3) as u8;
                 }
+            };
 
-                x_offset = x_offset.wrapping_add(1);
-            }
+            let engine = match is_engine_a {
+                true => &mut self.engine_upper,
+                false => &mut self.engine_lower,
+            };
+            engine.put_pixel(scanline, pixel, argb, index as u8);
+            engine.final_bg_priority[pixel] = bgcnt.priority;
         }
     }
 
-    /// Draws an extended/affine background layer.
+    /// Draws one line of an extended background (BG2/BG3 in modes 3-5).
     ///
-    /// `index` is typically 2 or 3.
-    pub fn draw_bg_ext(&mut self, index: usize, is_engine_a: bool) {
-        // Determine the base VRAM address depending on the engine
-        let mut base: u32 = match is_engine_a {
-            true => VRAM_BGA_START,
-            false => VRAM_BGB_C,
+    /// BGCNT bits 7 and 2 pick the layout: an affine tile map with 16-bit
+    /// entries, a 256-color bitmap or a direct color bitmap. The bitmaps
+    /// start at BGCNT screen base in 16KB units.
+    pub fn draw_bg_extended_line(&mut self, index: usize, is_engine_a: bool) {
+        let bgcnt = match is_engine_a {
+            true => self.engine_upper.bgcnt[index],
+            false => self.engine_lower.bgcnt[index],
+        };
+        let layout = match (bgcnt.palette_256, bgcnt.char_base & 1 != 0) {
+            (false, _) => AffineLayout::ExtendedText,
+            (true, false) => AffineLayout::Bitmap256,
+            (true, true) => AffineLayout::DirectColor,
         };
+        self.draw_affine_line(index, is_engine_a, layout);
+    }
 
-        // Get current vertical scanline
-        let mut y_offset: usize = self.get_vcount() as usize;
-        let v_count = self.get_vcount();
-        let mut bg_mode: u8 = 0;
+    /// Draws one line of a rotscale background (BG2/BG3 in modes 1, 2 and
+    /// 4): an affine tile map with 8-bit entries and 256-color tiles.
+    pub fn draw_bg_affine_line(&mut self, index: usize, is_engine_a: bool) {
+        self.draw_affine_line(index, is_engine_a, AffineLayout::Rotscale);
+    }
 
-        {
+    /// Shared affine pipeline. Pixel `i` of the line samples the layer at
+    /// the internal reference point plus `i` times (PA, PC), in 20.8 fixed
+    /// point; outside the layer it is transparent, or wraps around with
+    /// BGCNT bit 13. Mosaic samples each block at its top left pixel, going
+    /// back up the lines by (PB, PD).
+    fn draw_affine_line(&mut self, index: usize, is_engine_a: bool, layout: AffineLayout) {
+        let scanline = self.get_vcount() as usize * PIXELS_PER_LINE;
+
+        let (bgcnt, params, reference, bg_base, screen_base, char_base, extended_palette, mosaic) = {
             let engine = match is_engine_a {
-                true => &mut self.engine_upper,
-                false => &mut self.engine_lower,
+                true => &self.engine_upper,
+                false => &self.engine_lower,
+            };
+            let bgcnt = engine.bgcnt[index];
+            let (params, reference) = match index {
+                2 => (
+                    engine.bg2p_internal,
+                    (engine.bg2x_internal, engine.bg2y_internal),
+                ),
+                _ => (
+                    engine.bg3p_internal,
+                    (engine.bg3x_internal, engine.bg3y_internal),
+                ),
             };
 
-            // Add BG-specific Y offset
-            if index == 2 {
-                y_offset += (engine.bg2y >> 8) as usize;
-            } else {
-                y_offset += (engine.bg3y >> 8) as usize;
+            // Engine B has no DISPCNT screen/char base
+            let (bg_base, screen_base, char_base) = match is_engine_a {
+                true => (
+                    VRAM_BGA_START,
+                    VRAM_BGA_START + engine.dispcnt.screen_base as u32 * 0x10000,
+                    VRAM_BGA_START + engine.dispcnt.char_base as u32 * 0x10000,
+                ),
+                false => (VRAM_BGB_C, VRAM_BGB_C, VRAM_BGB_C),
+            };
+            (
+                bgcnt,
+                params,
+                reference,
+                bg_base,
+                screen_base + bgcnt.screen_base * 0x800,
+                char_base + bgcnt.char_base * 0x4000,
+                engine.dispcnt.bg_extended_palette,
+                engine.bg_mosaic(index),
+            )
+        };
+
+        let (width, height) = match layout {
+            AffineLayout::Rotscale | AffineLayout::ExtendedText => {
+                (128 << bgcnt.screen_size, 128 << bgcnt.screen_size)
             }
+            AffineLayout::Bitmap256 | AffineLayout::DirectColor => match bgcnt.screen_size {
+                0 => (128, 128),
+                1 => (256, 256),
+                2 => (512, 256),
+                _ => (512, 512),
+            },
+        };
+        let bitmap_base = bg_base + bgcnt.screen_base * 0x4000;
+        let [pa, pb, pc, pd] = params.map(|param| param as i16 as i32);
+        let (mosaic_width, mosaic_height) = mosaic;
+        let mosaic_line = (self.get_vcount() as u32 % mosaic_height) as i32;
+        let (x, y) = (
+            reference.0.wrapping_sub(pb.wrapping_mul(mosaic_line)),
+            reference.1.wrapping_sub(pd.wrapping_mul(mosaic_line)),
+        );
+
+        let read_u8 = |gpu: &Self, address: u32| match is_engine_a {
+            true => gpu.read_bga_u8(address),
+            false => gpu.read_bgb_u8(address),
+        };
+        let read_u16 = |gpu: &Self, address: u32| match is_engine_a {
+            true => gpu.read_bga_u16(address),
+            false => gpu.read_bgb_u16(address),
+        };
+        let palette_color = |gpu: &Self, color: u8| match color {
+            0 => None,
+            color => Some(gpu.palette_argb(is_engine_a, color as usize)),
+        };
 
-            // Calculate base address for the BG tiles
-            base += (((engine.bgcnt[index] >> 8) & 0x1F) as u32) * 1024 * 16;
+        for pixel in 0..PIXELS_PER_LINE {
+            let sample = (pixel as u32 - pixel as u32 % mosaic_width) as i32;
+            let mut layer_x = x.wrapping_add(pa.wrapping_mul(sample)) >> 8;
+            let mut layer_y = y.wrapping_add(pc.wrapping_mul(sample)) >> 8;
 
-            // Determine BG mode (2-bit value)
-            if (engine.bgcnt[index] & (1 << 2)) != 0 {
-                bg_mode += 1;
-            }
-            if (engine.bgcnt[index] & (1 << 7)) != 0 {
-                bg_mode += 2;
+            let window_mask = match is_engine_a {
+                true => self.engine_upper.window_mask[pixel],
+                false => self.engine_lower.window_mask[pixel],
+            };
+            if (window_mask & (1 << index)) == 0 {
+                continue;
             }
-        }
 
-        match bg_mode {
-            0 | 1 => {
-                // Modes 0-1: Text/Tile modes
-                self.draw_ext_text(index, is_engine_a);
+            if bgcnt.overflow {
+                layer_x &= width - 1;
+                layer_y &= height - 1;
+            } else if !(0..width).contains(&layer_x) || !(0..height).contains(&layer_y) {
+                continue;
             }
-
-            2 => {
-                // Mode 2: Rotscale 256-color bitmap
-                for i in 0..PIXELS_PER_LINE {
-                    let address = base + i as u32 + ((v_count as u32) * (PIXELS_PER_LINE as u32));
-
-                    let color_index = match is_engine_a {
-                        true => self.read_bga_u8(address),
-                        false => self.read_bgb_u8(address),
-                    };
-
-                    if color_index == 0 {
-                        continue;
-                    }
-
-                    // Convert palette index to RGB
-                    let address = (color_index * 2) as u32;
-                    let color = match is_engine_a {
-                        true => read_palette_value(&self.palette_upper, address),
-                        false => read_palette_value(&self.palette_lower, address),
+            let (layer_x, layer_y) = (layer_x as u32, layer_y as u32);
+            let map_entry = (layer_y / 8) * (width as u32 / 8) + layer_x / 8;
+
+            let argb = match layout {
+                AffineLayout::Rotscale => {
+                    let tile = read_u8(self, screen_base + map_entry) as u32;
+                    let address = char_base + tile * 64 + (layer_y & 7) * 8 + (layer_x & 7);
+                    palette_color(self, read_u8(self, address))
+                }
+                AffineLayout::ExtendedText => {
+                    let tile = read_u16(self, screen_base + map_entry * 2);
+                    let tile_x = match tile & (1 << 10) != 0 {
+                        true => 7 - (layer_x & 7),
+                        false => layer_x & 7,
                     };
-
-                    let r = ((color & 0x1F) << 3) as u32;
-                    let g = (((color >> 5) & 0x1F) << 3) as u32;
-                    let b = (((color >> 10) & 0x1F) << 3) as u32;
-
-                    let true_color = 0xFF000000 | (r << 16) | (g << 8) | b;
-
-                    // NOTE: index <= 4
-                    let address = i + (v_count as usize * PIXELS_PER_LINE);
-
-                    let engine = match is_engine_a {
-                        true => &mut self.engine_upper,
-                        false => &mut self.engine_lower,
+                    let tile_y = match tile & (1 << 11) != 0 {
+                        true => 7 - (layer_y & 7),
+                        false => layer_y & 7,
                     };
-                    engine.framebuffer[address] = true_color;
-                    engine.final_bg_priority[i] = (engine.bgcnt[index] & 0x3) as u8;
+                    let address = char_base + (tile & 0x3FF) as u32 * 64 + tile_y * 8 + tile_x;
+                    match (read_u8(self, address), extended_palette) {
+                        (0, _) => None,
+                        (color, true) => {
+                            let address = index as u32 * 0x2000
+                                + (tile >> 12) as u32 * 512
+                                + color as u32 * 2;
+                            Some(bgr555_to_argb(match is_engine_a {
+                                true => self.read_extpal_bga_u16(address),
+                                false => self.read_extpal_bgb_u16(address),
+                            }))
+                        }
+                        (color, false) => palette_color(self, color),
+                    }
                 }
+                AffineLayout::Bitmap256 => {
+                    let address = bitmap_base + layer_y * width as u32 + layer_x;
+                    palette_color(self, read_u8(self, address))
+                }
+                AffineLayout::DirectColor => {
+                    let address = bitmap_base + (layer_y * width as u32 + layer_x) * 2;
+                    let color = read_u16(self, address);
+                    // Bit 15 is the alpha bit
+                    (color & (1 << 15) != 0).then(|| bgr555_to_argb(color))
+                }
+            };
+
+            if let Some(argb) = argb {
+                let engine = match is_engine_a {
+                    true => &mut self.engine_upper,
+                    false => &mut self.engine_lower,
+                };
+                engine.put_pixel(scanline, pixel, argb, index as u8);
+                engine.final_bg_priority[pixel] = bgcnt.priority;
             }
+        }
+    }
+}
 
-            3 => {
-                // Mode 3: Direct color bitmap
-                for i in 0..PIXELS_PER_LINE {
-                    let ds_color: u16 = if is_engine_a {
-                        self.read_bga_u16(
-                            base + (i as u32 * 2) + (y_offset as u32 * PIXELS_PER_LINE as u32 * 2),
-                        )
-                    } else {
-                        self.read_bgb_u16(
-                            base + (i as u32 * 2) + (y_offset as u32 * PIXELS_PER_LINE as u32 * 2),
-                        )
-                    };
+/// Applies `f` to the 5-bit R, G and B channels of two ARGB colors,
+/// saturating at 31.
+fn map_channels(a: u32, b: u32, f: impl Fn(u32, u32) -> u32) -> u32 {
+    [16, 8, 0].into_iter().fold(0xFF00_0000, |argb, shift| {
+        let channel = |color: u32| (color >> (shift + 3)) & 0x1F;
+        argb | (f(channel(a), channel(b)).min(31) << (shift + 3))
+    })
+}
 
-                    // Only consider colors with bit 15 set
-                    if (ds_color & (1 << 15)) == 0 {
-                        continue;
-                    }
+/// Pixel storage of an affine background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AffineLayout {
+    /// 8-bit map entries, 256-color tiles
+    Rotscale,
+    /// 16-bit map entries with flips and extended palettes
+    ExtendedText,
+    /// 256-color palette indices
+    Bitmap256,
+    /// BGR555 with an alpha bit
+    DirectColor,
+}
 
-                    let r = ((ds_color & 0x1F) << 3) as u32;
-                    let g = (((ds_color >> 5) & 0x1F) << 3) as u32;
-                    let b = (((ds_color >> 10) & 0x1F) << 3) as u32;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu_2d::DISPCNT_ENGINE_A_ONLY;
+
+    #[test]
+    fn test_draw_bg_txt_line() {
+        let mut gpu = Gpu::new();
+        gpu.power_on();
+        // Bank A as engine A BG memory, BG0 in mode 0, graphics display
+        gpu.set_vramcnt_a(0x81);
+        gpu.set_dispcnt_a(0x0001_0100);
+        // Character base 0x4000, 16 colors
+        gpu.set_bgcnt_a(0x0004, 0);
+        // Tile 1 filled with color 1, at the top left of the map
+        for offset in (0..32).step_by(2) {
+            gpu.write_bga(0x0600_4020 + offset, 0x1111);
+        }
+        gpu.write_bga(0x0600_0000, 0x0001);
+        gpu.write_palette_a(2, 0x001F);
+
+        let red = 0xFFF8_0000;
+        let line = |gpu: &Gpu| gpu.engine_upper.front_framebuffer[..16].to_vec();
+
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..8], [red; 8]);
+        assert_eq!(line(&gpu)[8..], [0xFF00_0000; 8]);
+
+        // Scrolling wraps around the 256 pixel wide map
+        gpu.engine_upper.bghofs[0] = 0x1FC;
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..4], [0xFF00_0000; 4]);
+        assert_eq!(line(&gpu)[4..12], [red; 8]);
+
+        // Horizontal flip, and a line below the tile
+        gpu.engine_upper.bghofs[0] = 0;
+        gpu.write_bga(0x0600_4020, 0x0001);
+        gpu.write_bga(0x0600_4022, 0x0000);
+        gpu.write_bga(0x0600_0000, 0x0401);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..7], [0xFF00_0000; 7]);
+        assert_eq!(line(&gpu)[7], red);
+        gpu.vertical_count = 8;
+        gpu.draw_scanline();
+        assert_eq!(gpu.engine_upper.front_framebuffer[8 * 256], 0xFF00_0000);
+    }
 
-                    let color = 0xFF000000 | (r << 16) | (g << 8) | b;
+    #[test]
+    fn test_color_special_effects() {
+        let mut gpu = Gpu::new();
+        gpu.power_on();
+        // BG0 red over the first 8 pixels of a blue backdrop
+        gpu.set_vramcnt_a(0x81);
+        gpu.set_dispcnt_a(0x0001_0100);
+        gpu.set_bgcnt_a(0x0004, 0);
+        for offset in (0..32).step_by(2) {
+            gpu.write_bga(0x0600_4020 + offset, 0x1111);
+        }
+        gpu.write_bga(0x0600_0000, 0x0001);
+        gpu.write_palette_a(0, 0x7C00);
+        gpu.write_palette_a(2, 0x001F);
+        let (red, blue) = (0xFFF8_0000, 0xFF00_00F8);
+        let line = |gpu: &Gpu| gpu.engine_upper.front_framebuffer[..12].to_vec();
+
+        // Half BG0, half backdrop
+        gpu.set_bldcnt_a(0x2000 | 0x0040 | 0x0001);
+        gpu.set_bldalpha_a(0x0808);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..8], [0xFF78_0078; 8]);
+        assert_eq!(line(&gpu)[8..], [blue; 4]);
+
+        // Full brightness increase, then half decrease
+        gpu.set_bldcnt_a(0x0081);
+        gpu.set_bldy_a(16);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..8], [0xFFF8_F8F8; 8]);
+        gpu.set_bldcnt_a(0x00C1);
+        gpu.set_bldy_a(8);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..8], [0xFF80_0000; 8]);
+
+        // Window 0 over pixels 4-7 shows BG0 without effects
+        gpu.set_dispcnt_a(0x0001_2100);
+        gpu.set_win0h_a(0x0408);
+        gpu.set_win0v_a(0x00C0);
+        gpu.set_winin_a(0x0001);
+        gpu.set_winout_a(0x003F);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..4], [0xFF80_0000; 4]);
+        assert_eq!(line(&gpu)[4..8], [red; 4]);
+
+        // 4 pixel wide mosaic stretches pixel 4 over the scrolled off edge
+        gpu.set_dispcnt_a(0x0001_0100);
+        gpu.set_bldcnt_a(0);
+        gpu.engine_upper.bghofs[0] = 2;
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[6..8], [blue; 2]);
+        gpu.set_bgcnt_a(0x0044, 0);
+        gpu.set_mosaic_a(0x0003);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu)[..8], [red; 8]);
+        assert_eq!(line(&gpu)[8..], [blue; 4]);
+    }
 
-                    // NOTE: index <= 4
-                    let address = i + (v_count as usize * PIXELS_PER_LINE);
+    #[test]
+    fn test_draw_bg_extended_line() {
+        let mut gpu = Gpu::new();
+        gpu.power_on();
+        // Bank A as engine A BG memory, BG3 in mode 5, graphics display
+        gpu.set_vramcnt_a(0x81);
+        gpu.set_dispcnt_a(0x0001_0805);
+        // 256x256 direct color bitmap at 0x06000000
+        gpu.set_bgcnt_a(0x4084, 3);
+        let (red, green, backdrop) = (0xFFF8_0000, 0xFF00_F800, 0xFF00_0000);
+        gpu.write_bga(0x0600_0000, 0x801F);
+        gpu.write_bga(0x0600_0002, 0x801F);
+        gpu.write_bga(0x0600_0200, 0x83E0);
+        gpu.set_bg3p_a(0x100, 0);
+        gpu.set_bg3p_a(0x100, 3);
+        let line =
+            |gpu: &Gpu, y: usize| gpu.engine_upper.front_framebuffer[y * 256..][..3].to_vec();
+
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu, 0), [red, red, backdrop]);
+        // The reference point moved down by PD
+        gpu.vertical_count = 1;
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu, 1)[0], green);
+
+        // Zoomed out 2x
+        gpu.vertical_count = 0;
+        gpu.set_bg3y_a(0);
+        gpu.set_bg3p_a(0x200, 0);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu, 0), [red, backdrop, backdrop]);
+
+        // One pixel left of the bitmap, transparent unless it wraps around
+        gpu.set_bg3p_a(0x100, 0);
+        gpu.set_bg3x_a(0x0FFF_FF00);
+        gpu.set_bg3y_a(0);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu, 0), [backdrop, red, red]);
+        gpu.write_bga(0x0600_01FE, 0x83E0);
+        gpu.set_bgcnt_a(0x6084, 3);
+        gpu.set_bg3y_a(0);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu, 0), [green, red, red]);
+    }
 
-                    let engine = match is_engine_a {
-                        true => &mut self.engine_upper,
-                        false => &mut self.engine_lower,
-                    };
-                    engine.framebuffer[address] = color;
-                    engine.final_bg_priority[i] = (engine.bgcnt[index] & 0x3) as u8;
-                }
-            }
+    /// Engine B with BG0 showing a red tile at the left of the line, and
+    /// bank A full of green as the LCDC block display mode 2 would show.
+    fn engine_b_bg0() -> Gpu {
+        let mut gpu = Gpu::new();
+        gpu.power_on();
+        gpu.set_vramcnt_a(0x80);
+        for offset in (0..256 * 2).step_by(2) {
+            gpu.write_lcdc(VRAM_LCDC_A + offset, 0x03E0);
+        }
+        gpu.set_vramcnt_c(0x84);
+        gpu.set_dispcnt_b(0x0001_0100);
+        gpu.set_bgcnt_b(0x0004, 0);
+        for offset in (0..32).step_by(2) {
+            gpu.write_bgb(0x0620_4020 + offset, 0x1111);
+        }
+        gpu.write_bgb(0x0620_0000, 0x0001);
+        gpu.write_palette_b(2, 0x001F);
+        gpu
+    }
 
-            _ => {
-                panic!("Unrecognized extended mode {}", bg_mode);
-            }
+    #[test]
+    fn test_engine_b_ignores_engine_a_only_bits() {
+        let mut gpu = engine_b_bg0();
+        let line = |gpu: &Gpu| gpu.engine_lower.front_framebuffer[..PIXELS_PER_LINE].to_vec();
+        gpu.draw_scanline();
+        let expected = line(&gpu);
+        assert_eq!(expected[..8], [0xFFF8_0000; 8]);
+        assert_eq!(expected[8], 0xFF00_0000);
+
+        // Bitmap OBJ 1D boundary, engine B has no 256K bitmap OBJ mapping
+        gpu.set_dispcnt_b(0x0001_0100 | (1 << 22));
+        assert_eq!(gpu.get_dispcnt_b(), 0x0001_0100);
+
+        let vram_a = gpu.vram_a.clone();
+        for bit in (0..32).filter(|bit| DISPCNT_ENGINE_A_ONLY & (1 << bit) != 0) {
+            let word = 0x0001_0100 | (1 << bit);
+            gpu.set_dispcnt_b(word);
+            assert_eq!(gpu.get_dispcnt_b(), 0x0001_0100, "bit {bit}");
+            gpu.draw_scanline();
+            assert_eq!(line(&gpu), expected, "bit {bit}");
+
+            gpu.set_dispcnt_b_lo(word as u16);
+            gpu.set_dispcnt_b_hi((word >> 16) as u16);
+            assert_eq!(gpu.get_dispcnt_b(), 0x0001_0100, "halfword bit {bit}");
         }
+
+        // Capture and display FIFO setup on engine B go nowhere
+        gpu.engine_lower.set_dispcapcnt(0x803F_1010);
+        assert_eq!(gpu.engine_lower.get_dispcapcnt(false), 0);
+        assert!(!gpu.engine_lower.dispcapcnt.enable_busy);
+        gpu.draw_scanline();
+        assert_eq!(line(&gpu), expected);
+        assert_eq!(gpu.vram_a, vram_a);
+
+        // Engine A keeps all of them
+        gpu.set_dispcnt_a(0x0001_0100 | DISPCNT_ENGINE_A_ONLY);
+        assert_eq!(gpu.get_dispcnt_a(), 0x0001_0100 | DISPCNT_ENGINE_A_ONLY);
+    }
+
+    #[test]
+    fn test_engine_b_bg_mode_6() {
+        let mut gpu = engine_b_bg0();
+        gpu.set_dispcnt_b(0x0001_0F06);
+        gpu.draw_scanline();
+        assert_eq!(
+            gpu.engine_lower.front_framebuffer[..PIXELS_PER_LINE],
+            [0xFF00_0000; PIXELS_PER_LINE]
+        );
+
+        // Back to mode 0 shows BG0 again
+        gpu.set_dispcnt_b(0x0001_0100);
+        gpu.draw_scanline();
+        assert_eq!(gpu.engine_lower.front_framebuffer[..8], [0xFFF8_0000; 8]);
     }
 }
@@ -21,6 +21,10 @@ impl Gpu {
         self.engine_upper.set_dispcnt_lo(halfword);
     }
 
+    pub fn set_dispcnt_a_hi(&mut self, halfword: u16) {
+        self.engine_upper.set_dispcnt_hi(halfword);
+    }
+
     pub fn set_dispcnt_a(&mut self, word: u32) {
         self.engine_upper.set_dispcnt(word);
     }
@@ -29,18 +33,22 @@ impl Gpu {
         self.engine_lower.set_dispcnt_lo(halfword);
     }
 
+    pub fn set_dispcnt_b_hi(&mut self, halfword: u16) {
+        self.engine_lower.set_dispcnt_hi(halfword);
+    }
+
     pub fn set_dispcnt_b(&mut self, word: u32) {
         self.engine_lower.set_dispcnt(word);
     }
 
     /// Set DISPSTAT7 register value
     pub fn set_dispstat7(&mut self, value: u16) {
-        self.display_status_arm7.set(value);
+        self.display_status_arm7.write(value);
     }
 
     /// Set DISPSTAT9 register value
     pub fn set_dispstat9(&mut self, value: u16) {
-        self.display_status_arm9.set(value);
+        self.display_status_arm9.write(value);
     }
 
     pub fn set_bgcnt_a(&mut self, halfword: u16, index: usize) {
@@ -218,55 +226,73 @@ impl Gpu {
     /// Set VRAM bank configuration A
     pub fn set_vramcnt_a(&mut self, value: u8) {
         self.vramcnt_a.mst = (value & 0x7) as u32;
+        self.vramcnt_a.offset = ((value >> 3) & 0x3) as u32;
         self.vramcnt_a.enabled = (value & 0x80) != 0;
+        self.update_vram_map();
     }
 
     /// Set VRAM bank configuration B
     pub fn set_vramcnt_b(&mut self, value: u8) {
         self.vramcnt_b.mst = (value & 0x7) as u32;
+        self.vramcnt_b.offset = ((value >> 3) & 0x3) as u32;
         self.vramcnt_b.enabled = (value & 0x80) != 0;
+        self.update_vram_map();
     }
 
     /// Set VRAM bank configuration C
     pub fn set_vramcnt_c(&mut self, value: u8) {
         self.vramcnt_c.mst = (value & 0x7) as u32;
+        self.vramcnt_c.offset = ((value >> 3) & 0x3) as u32;
         self.vramcnt_c.enabled = (value & 0x80) != 0;
+        self.update_vram_map();
     }
 
     /// Set VRAM bank configuration D
     pub fn set_vramcnt_d(&mut self, value: u8) {
         self.vramcnt_d.mst = (value & 0x7) as u32;
+        self.vramcnt_d.offset = ((value >> 3) & 0x3) as u32;
         self.vramcnt_d.enabled = (value & 0x80) != 0;
+        self.update_vram_map();
     }
 
     /// Set VRAM bank configuration E
     pub fn set_vramcnt_e(&mut self, value: u8) {
         self.vramcnt_e.mst = (value & 0x7) as u32;
+        self.vramcnt_e.offset = ((value >> 3) & 0x3) as u32;
         self.vramcnt_e.enabled = (value & 0x80) != 0;
+        self.update_vram_map();
     }
 
     /// Set VRAM bank configuration F
     pub fn set_vramcnt_f(&mut self, value: u8) {
         self.vramcnt_f.mst = (value & 0x7) as u32;
+        self.vramcnt_f.offset = ((value >> 3) & 0x3) as u32;
         self.vramcnt_f.enabled = (value & 0x80) != 0;
+        self.update_vram_map();
     }
 
     /// Set VRAM bank configuration G
     pub fn set_vramcnt_g(&mut self, value: u8) {
         self.vramcnt_g.mst = (value & 0x7) as u32;
+        self.vramcnt_g.offset = ((value >> 3) & 0x3) as u32;
         self.vramcnt_g.enabled = (value & 0x80) != 0;
+        self.update_vram_map();
     }
 
     /// Set VRAM bank configuration H
     pub fn set_vramcnt_h(&mut self, value: u8) {
         self.vramcnt_h.mst = (value & 0x7) as u32;
+        self.vramcnt_h.offset = ((value >> 3) & 0x3) as u32;
         self.vramcnt_h.enabled = (value & 0x80) != 0;
+        self.update_vram_map();
     }
 
     /// Set VRAM bank configuration I
     pub fn set_vramcnt_i(&mut self, value: u8) {
         self.vramcnt_i.mst = (value & 0x7) as u32;
+        self.vramcnt_i.offset = ((value >> 3) & 0x3) as u32;
         self.vramcnt_i.enabled = (value & 0x80) != 0;
+        self.update_vram_map();
     }
 
     /// Set POWCNT1 register value
@@ -282,6 +308,10 @@ impl Gpu {
         self.engine_3d.set_clear_depth(word);
     }
 
+    pub fn set_dot_depth(&mut self, halfword: u16) {
+        self.engine_3d.set_dot_depth(halfword);
+    }
+
     pub fn set_mtx_mode(&mut self, word: u32) {
         self.engine_3d.set_mtx_mode(word);
     }
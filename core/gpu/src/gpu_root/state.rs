@@ -0,0 +1,98 @@
+//! Access to GPU state for savestates
+//!
+//! The emulator serializes the GPU; this exposes what is otherwise private:
+//! the memory regions and the registers and timing without a port address.
+//! Engine registers and the 3D state are public fields already.
+use crate::gpu_root::Gpu;
+use crate::gpu_root::taint::TaintRegion;
+
+/// GPU state outside memory and the engines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GpuCoreState {
+    pub cycles: u64,
+    pub frame_ready: bool,
+    /// VRAMCNT_A..VRAMCNT_I
+    pub vramcnt: [u8; 9],
+    pub powcnt1: u16,
+    /// Main memory display FIFO contents
+    pub display_fifo: Vec<u32>,
+}
+
+impl Gpu {
+    /// Bytes of a VRAM bank, palette or OAM.
+    pub fn memory(&self, region: TaintRegion) -> &[u8] {
+        match region {
+            TaintRegion::VramA => &self.vram_a,
+            TaintRegion::VramB => &self.vram_b,
+            TaintRegion::VramC => &self.vram_c,
+            TaintRegion::VramD => &self.vram_d,
+            TaintRegion::VramE => &self.vram_e,
+            TaintRegion::VramF => &self.vram_f,
+            TaintRegion::VramG => &self.vram_g,
+            TaintRegion::VramH => &self.vram_h,
+            TaintRegion::VramI => &self.vram_i,
+            TaintRegion::PaletteUpper => &self.palette_upper,
+            TaintRegion::PaletteLower => &self.palette_lower,
+            TaintRegion::Oam => &self.oam,
+        }
+    }
+
+    /// Replace the contents of `region` with `data`, marking all of it
+    /// written. Bytes past the end of either are left alone.
+    pub fn restore_memory(&mut self, region: TaintRegion, data: &[u8]) {
+        let memory = match region {
+            TaintRegion::VramA => &mut self.vram_a,
+            TaintRegion::VramB => &mut self.vram_b,
+            TaintRegion::VramC => &mut self.vram_c,
+            TaintRegion::VramD => &mut self.vram_d,
+            TaintRegion::VramE => &mut self.vram_e,
+            TaintRegion::VramF => &mut self.vram_f,
+            TaintRegion::VramG => &mut self.vram_g,
+            TaintRegion::VramH => &mut self.vram_h,
+            TaintRegion::VramI => &mut self.vram_i,
+            TaintRegion::PaletteUpper => &mut self.palette_upper,
+            TaintRegion::PaletteLower => &mut self.palette_lower,
+            TaintRegion::Oam => &mut self.oam,
+        };
+        let len = memory.len().min(data.len());
+        memory[..len].copy_from_slice(&data[..len]);
+        self.note_write(region, 0, region.size());
+    }
+
+    pub fn core_state(&self) -> GpuCoreState {
+        GpuCoreState {
+            cycles: self.cycles,
+            frame_ready: self.frame_ready,
+            vramcnt: [
+                self.get_vramcnt_a(),
+                self.get_vramcnt_b(),
+                self.get_vramcnt_c(),
+                self.get_vramcnt_d(),
+                self.get_vramcnt_e(),
+                self.get_vramcnt_f(),
+                self.get_vramcnt_g(),
+                self.get_vramcnt_h(),
+                self.get_vramcnt_i(),
+            ],
+            powcnt1: self.get_powcnt1(),
+            display_fifo: self.display_fifo.iter().copied().collect(),
+        }
+    }
+
+    pub fn restore_core_state(&mut self, state: &GpuCoreState) {
+        self.cycles = state.cycles;
+        self.frame_ready = state.frame_ready;
+        let [a, b, c, d, e, f, g, h, i] = state.vramcnt;
+        self.set_vramcnt_a(a);
+        self.set_vramcnt_b(b);
+        self.set_vramcnt_c(c);
+        self.set_vramcnt_d(d);
+        self.set_vramcnt_e(e);
+        self.set_vramcnt_f(f);
+        self.set_vramcnt_g(g);
+        self.set_vramcnt_h(h);
+        self.set_vramcnt_i(i);
+        self.set_powcnt1(state.powcnt1);
+        self.display_fifo = state.display_fifo.iter().copied().collect();
+    }
+}
@@ -0,0 +1,277 @@
+//! Uninitialized VRAM, palette and OAM reads
+//!
+//! A debug aid: while enabled, every byte of VRAM, palette and OAM counts as
+//! uninitialized until something writes it, and each scanline that samples
+//! such a byte is reported. Garbage on screen with a report was read by the
+//! game, on purpose or not; garbage without one points at the renderer.
+//!
+//! Only reads made while drawing a scanline are checked, CPU reads are not.
+//! Everything counts as unwritten from the moment tracking is enabled and
+//! again after [`Gpu::power_on`], so enable it before loading the ROM.
+use std::cell::Cell;
+
+use lunaris_ds_mem_const::{
+    OAM_SIZE, PALETTE_SIZE, VRAM_A_LEN, VRAM_B_LEN, VRAM_C_LEN, VRAM_D_LEN, VRAM_E_LEN, VRAM_F_LEN,
+    VRAM_G_LEN, VRAM_H_LEN, VRAM_I_LEN,
+};
+
+use crate::gpu_root::Gpu;
+
+/// Reports kept until they are taken, later lines are only logged.
+const MAX_REPORTS: usize = 1024;
+
+/// Memory whose initialization is tracked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaintRegion {
+    VramA,
+    VramB,
+    VramC,
+    VramD,
+    VramE,
+    VramF,
+    VramG,
+    VramH,
+    VramI,
+    PaletteUpper,
+    PaletteLower,
+    Oam,
+}
+
+impl TaintRegion {
+    pub const ALL: [Self; 12] = [
+        Self::VramA,
+        Self::VramB,
+        Self::VramC,
+        Self::VramD,
+        Self::VramE,
+        Self::VramF,
+        Self::VramG,
+        Self::VramH,
+        Self::VramI,
+        Self::PaletteUpper,
+        Self::PaletteLower,
+        Self::Oam,
+    ];
+
+    /// Size in bytes.
+    pub const fn size(self) -> usize {
+        match self {
+            Self::VramA => VRAM_A_LEN,
+            Self::VramB => VRAM_B_LEN,
+            Self::VramC => VRAM_C_LEN,
+            Self::VramD => VRAM_D_LEN,
+            Self::VramE => VRAM_E_LEN,
+            Self::VramF => VRAM_F_LEN,
+            Self::VramG => VRAM_G_LEN,
+            Self::VramH => VRAM_H_LEN,
+            Self::VramI => VRAM_I_LEN,
+            Self::PaletteUpper | Self::PaletteLower => PALETTE_SIZE,
+            Self::Oam => OAM_SIZE,
+        }
+    }
+
+    /// Palette of engine A (upper) or B (lower).
+    pub const fn palette(is_engine_a: bool) -> Self {
+        match is_engine_a {
+            true => Self::PaletteUpper,
+            false => Self::PaletteLower,
+        }
+    }
+
+    /// VRAM bank by ID, 0..=8 for A..I.
+    pub const fn vram(id: i32) -> Option<Self> {
+        match id {
+            0..=8 => Some(Self::ALL[id as usize]),
+            _ => None,
+        }
+    }
+}
+
+/// A scanline that sampled uninitialized memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UninitializedRead {
+    /// VCOUNT of the line
+    pub line: u16,
+    /// Region of the first uninitialized byte read on the line
+    pub region: TaintRegion,
+    /// Offset of that byte in the region
+    pub offset: u32,
+    /// Uninitialized bytes read on the line, repeats included
+    pub count: u32,
+}
+
+/// Written bits of each [`TaintRegion`] and the reads of the current line.
+#[derive(Debug, Default)]
+pub(crate) struct VramTaint {
+    /// One bit per byte, indexed by `TaintRegion as usize`; empty while
+    /// disabled
+    written: Vec<Vec<u64>>,
+    /// Set while a scanline is drawn
+    sampling: bool,
+    first: Cell<Option<(TaintRegion, u32)>>,
+    count: Cell<u32>,
+    reports: Vec<UninitializedRead>,
+}
+
+impl VramTaint {
+    pub(crate) fn is_enabled(&self) -> bool {
+        !self.written.is_empty()
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.written = match enabled {
+            true => TaintRegion::ALL
+                .iter()
+                .map(|region| vec![0; region.size().div_ceil(64)])
+                .collect(),
+            false => Vec::new(),
+        };
+        self.reports.clear();
+    }
+
+    /// Forget all writes.
+    pub(crate) fn reset(&mut self) {
+        for bits in &mut self.written {
+            bits.fill(0);
+        }
+    }
+
+    /// Mark `len` bytes from `offset` as written.
+    #[inline]
+    pub(crate) fn mark(&mut self, region: TaintRegion, offset: usize, len: usize) {
+        let Some(bits) = self.written.get_mut(region as usize) else {
+            return;
+        };
+        for byte in offset..offset + len {
+            if let Some(word) = bits.get_mut(byte / 64) {
+                *word |= 1 << (byte % 64);
+            }
+        }
+    }
+
+    /// Note a renderer read of `len` bytes from `offset`.
+    #[inline]
+    pub(crate) fn check(&self, region: TaintRegion, offset: usize, len: usize) {
+        if !self.sampling {
+            return;
+        }
+        let Some(bits) = self.written.get(region as usize) else {
+            return;
+        };
+        for byte in offset..offset + len {
+            let written = bits
+                .get(byte / 64)
+                .is_none_or(|word| word & (1 << (byte % 64)) != 0);
+            if !written {
+                if self.first.get().is_none() {
+                    self.first.set(Some((region, byte as u32)));
+                }
+                self.count.set(self.count.get() + 1);
+            }
+        }
+    }
+
+    pub(crate) fn begin_line(&mut self) {
+        self.sampling = self.is_enabled();
+        self.first.set(None);
+        self.count.set(0);
+    }
+
+    /// Report the uninitialized reads since [`Self::begin_line`], if any.
+    pub(crate) fn end_line(&mut self, line: u16) {
+        self.sampling = false;
+        let Some((region, offset)) = self.first.take() else {
+            return;
+        };
+        let count = self.count.get();
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            "Line {line} read {count} uninitialized byte(s), first {region:?}+{offset:#X}"
+        );
+        if self.reports.len() < MAX_REPORTS {
+            self.reports.push(UninitializedRead {
+                line,
+                region,
+                offset,
+                count,
+            });
+        }
+    }
+}
+
+impl Gpu {
+    /// Byte `index` of `region`, read by the renderer.
+    #[inline]
+    pub(crate) fn sample_vram(&self, region: TaintRegion, index: usize) -> Option<&u8> {
+        self.taint.check(region, index, 1);
+        match region {
+            TaintRegion::VramA => &self.vram_a,
+            TaintRegion::VramB => &self.vram_b,
+            TaintRegion::VramC => &self.vram_c,
+            TaintRegion::VramD => &self.vram_d,
+            TaintRegion::VramE => &self.vram_e,
+            TaintRegion::VramF => &self.vram_f,
+            TaintRegion::VramG => &self.vram_g,
+            TaintRegion::VramH => &self.vram_h,
+            TaintRegion::VramI => &self.vram_i,
+            TaintRegion::PaletteUpper => &self.palette_upper,
+            TaintRegion::PaletteLower => &self.palette_lower,
+            TaintRegion::Oam => &self.oam,
+        }
+        .get(index)
+    }
+
+    /// Track uninitialized VRAM, palette and OAM reads, see
+    /// [`taint`](crate::gpu_root::taint). Enabling forgets earlier writes.
+    pub fn set_taint_enabled(&mut self, enabled: bool) {
+        self.taint.set_enabled(enabled);
+    }
+
+    pub fn is_taint_enabled(&self) -> bool {
+        self.taint.is_enabled()
+    }
+
+    /// Lines that sampled uninitialized memory since the last call, oldest
+    /// first. At most 1024 are kept in between.
+    pub fn take_uninitialized_reads(&mut self) -> Vec<UninitializedRead> {
+        std::mem::take(&mut self.taint.reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lunaris_ds_mem_const::{PIXELS_PER_LINE, VRAM_LCDC_A};
+
+    #[test]
+    fn test_uninitialized_vram_display() {
+        let mut gpu = Gpu::new();
+        gpu.power_on();
+        gpu.set_taint_enabled(true);
+        // VRAM display mode from bank A, mapped to LCDC
+        gpu.set_dispcnt_a(0x0002_0000);
+        gpu.set_vramcnt_a(0x80);
+        gpu.write_palette_a(0, 0);
+        gpu.write_palette_b(0, 0);
+        for x in 0..PIXELS_PER_LINE as u32 - 1 {
+            gpu.write_lcdc(VRAM_LCDC_A + x * 2, 0x7FFF);
+        }
+
+        gpu.draw_scanline();
+        let reads = gpu.take_uninitialized_reads();
+        assert_eq!(reads.len(), 1);
+        assert_eq!(reads[0].region, TaintRegion::VramA);
+        assert_eq!(reads[0].offset, (PIXELS_PER_LINE as u32 - 1) * 2);
+        assert_eq!(reads[0].line, 0);
+
+        gpu.write_lcdc(VRAM_LCDC_A + (PIXELS_PER_LINE as u32 - 1) * 2, 0);
+        gpu.draw_scanline();
+        assert!(gpu.take_uninitialized_reads().is_empty());
+
+        // CPU reads are not checked
+        gpu.read_lcdc_u16(VRAM_LCDC_A + 0x1000);
+        gpu.read_bga_u16(0x0600_0000);
+        gpu.draw_scanline();
+        assert!(gpu.take_uninitialized_reads().is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+//! Texture sampling for the 3D rasterizer
+//!
+//! Looks up one texel of a polygon's texture in any of the seven
+//! TEXIMAGE_PARAM formats, after applying the repeat, flip or clamp of each
+//! axis. Palette formats index the texture palette at PLTT_BASE.
+use crate::gpu_3d::structs::TexImageParamReg;
+use crate::gpu_root::Gpu;
+
+/// A sampled texel, colors in 6 bits and alpha in 5 like the rasterizer's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Texel {
+    pub r: u32,
+    pub g: u32,
+    pub b: u32,
+    pub a: u32,
+}
+
+impl Texel {
+    const TRANSPARENT: Self = Self {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0,
+    };
+
+    const fn from_bgr555(color: u16, a: u32) -> Self {
+        Self {
+            r: ((color & 0x1F) << 1) as u32,
+            g: (((color >> 5) & 0x1F) << 1) as u32,
+            b: (((color >> 10) & 0x1F) << 1) as u32,
+            a,
+        }
+    }
+}
+
+/// Coordinate inside a texture of `size` texels along one axis.
+fn wrap_coordinate(coord: i32, size: i32, repeat: bool, flip: bool) -> i32 {
+    match (repeat, flip) {
+        (false, _) => coord.clamp(0, size - 1),
+        (true, true) if coord & size != 0 => size - 1 - (coord & (size - 1)),
+        (true, _) => coord & (size - 1),
+    }
+}
+
+/// Per channel `(a * weight_a + b * weight_b) / divisor` of two BGR555 colors.
+const fn mix_bgr555(a: u16, b: u16, weight_a: u16, weight_b: u16, divisor: u16) -> u16 {
+    let mut color = 0;
+    let mut shift = 0;
+    while shift < 15 {
+        let channel_a = (a >> shift) & 0x1F;
+        let channel_b = (b >> shift) & 0x1F;
+        color |= ((channel_a * weight_a + channel_b * weight_b) / divisor) << shift;
+        shift += 5;
+    }
+    color
+}
+
+impl Gpu {
+    /// Texel `(s, t)`, in whole texels, of a texture.
+    pub(crate) fn sample_texture(
+        &self,
+        params: &TexImageParamReg,
+        palette_base: u32,
+        s: i32,
+        t: i32,
+    ) -> Texel {
+        let width = 8 << params.s_size;
+        let height = 8 << params.t_size;
+        let s = wrap_coordinate(s, width, params.repeat_s, params.flip_s);
+        let t = wrap_coordinate(t, height, params.repeat_t, params.flip_t);
+
+        let base = params.vram_offset as u32 * 8;
+        let texel = (s + t * width) as u32;
+        let palette =
+            |index: u32, palette_base: u32| self.read_texpal_u16(palette_base + index * 2);
+        // Indexed formats whose color 0 may be transparent
+        let indexed = |index: u32, palette_base: u32| match index == 0 && params.color0_transparent
+        {
+            true => Texel::TRANSPARENT,
+            false => Texel::from_bgr555(palette(index, palette_base), 0x1F),
+        };
+
+        match params.format {
+            // A3I5
+            1 => {
+                let data = self.read_teximage_u8(base + texel) as u32;
+                let alpha = data >> 5;
+                Texel::from_bgr555(
+                    palette(data & 0x1F, palette_base * 0x10),
+                    (alpha << 2) + (alpha >> 1),
+                )
+            }
+            // 4 color palette
+            2 => {
+                let data = self.read_teximage_u8(base + texel / 4) as u32;
+                indexed((data >> ((texel & 0x3) * 2)) & 0x3, palette_base * 0x8)
+            }
+            // 16 color palette
+            3 => {
+                let data = self.read_teximage_u8(base + texel / 2) as u32;
+                indexed((data >> ((texel & 0x1) * 4)) & 0xF, palette_base * 0x10)
+            }
+            // 256 color palette
+            4 => indexed(
+                self.read_teximage_u8(base + texel) as u32,
+                palette_base * 0x10,
+            ),
+            // Compressed 4x4 texels
+            5 => self.sample_compressed(base, width, palette_base, s, t),
+            // A5I3
+            6 => {
+                let data = self.read_teximage_u8(base + texel) as u32;
+                Texel::from_bgr555(palette(data & 0x7, palette_base * 0x10), data >> 3)
+            }
+            // Direct color
+            7 => {
+                let data = self.read_teximage_u16(base + texel * 2);
+                match data & (1 << 15) != 0 {
+                    true => Texel::from_bgr555(data, 0x1F),
+                    false => Texel::TRANSPARENT,
+                }
+            }
+            _ => Texel::from_bgr555(0x7FFF, 0x1F),
+        }
+    }
+
+    /// Texel of a compressed texture: 4x4 blocks of 2-bit indices in slot
+    /// 0 or 2, each with a palette offset and mode in slot 1.
+    fn sample_compressed(&self, base: u32, width: i32, palette_base: u32, s: i32, t: i32) -> Texel {
+        let row_address = base + (((t / 4) * (width / 4) + s / 4) * 4 + (t & 0x3)) as u32;
+        let data = (self.read_teximage_u8(row_address) >> ((s & 0x3) * 2)) & 0x3;
+
+        let mut info_address = 0x2_0000 + (((row_address & 0x1_FFFF) >> 1) & !1);
+        if row_address >= 0x4_0000 {
+            info_address += 0x1_0000;
+        }
+        let info = self.read_teximage_u16(info_address);
+        let palette_address = palette_base * 0x10 + (info & 0x3FFF) as u32 * 4;
+        let color = |index: u32| self.read_texpal_u16(palette_address + index * 2);
+
+        let color = match (data, info >> 14) {
+            (0, _) => color(0),
+            (1, _) => color(1),
+            (2, 1) => mix_bgr555(color(0), color(1), 1, 1, 2),
+            (2, 3) => mix_bgr555(color(0), color(1), 5, 3, 8),
+            (2, _) => color(2),
+            (3, 2) => color(3),
+            (3, 3) => mix_bgr555(color(0), color(1), 3, 5, 8),
+            _ => return Texel::TRANSPARENT,
+        };
+        Texel::from_bgr555(color, 0x1F)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lunaris_ds_mem_const::VRAM_LCDC_A;
+
+    const VRAM_LCDC_E: u32 = VRAM_LCDC_A + 0x8_0000;
+    const RED: u16 = 0x001F;
+    const GREEN: u16 = 0x03E0;
+    const BLUE: u16 = 0x7C00;
+    const WHITE: u16 = 0x7FFF;
+
+    /// 8x8 textures in bank A as slot 0 and slot 1, palette in bank E.
+    fn gpu_with_texture(slot0: &[u8], slot1: &[u8]) -> Gpu {
+        let mut gpu = Gpu::new();
+        gpu.power_on();
+        gpu.set_vramcnt_a(0x80);
+        gpu.set_vramcnt_b(0x80);
+        gpu.set_vramcnt_e(0x80);
+        gpu.write_lcdc_block(VRAM_LCDC_A, slot0);
+        gpu.write_lcdc_block(VRAM_LCDC_A + 0x2_0000, slot1);
+        for (i, color) in [RED, GREEN, BLUE, WHITE].into_iter().enumerate() {
+            gpu.write_lcdc(VRAM_LCDC_E + i as u32 * 2, color);
+        }
+        gpu.set_vramcnt_a(0x83);
+        gpu.set_vramcnt_b(0x8B);
+        gpu.set_vramcnt_e(0x83);
+        gpu
+    }
+
+    fn params(format: i32) -> TexImageParamReg {
+        TexImageParamReg {
+            format,
+            ..Default::default()
+        }
+    }
+
+    fn colors(
+        gpu: &Gpu,
+        params: &TexImageParamReg,
+        s: impl IntoIterator<Item = i32>,
+    ) -> Vec<Texel> {
+        s.into_iter()
+            .map(|s| gpu.sample_texture(params, 0, s, 0))
+            .collect()
+    }
+
+    #[test]
+    fn test_texture_formats() {
+        let opaque = |color| Texel::from_bgr555(color, 0x1F);
+
+        // 4 color: indices 0-3 in one byte, color 0 transparent if asked
+        let gpu = gpu_with_texture(&[0b11_10_01_00], &[]);
+        let mut four_color = params(2);
+        assert_eq!(
+            colors(&gpu, &four_color, 0..4),
+            [opaque(RED), opaque(GREEN), opaque(BLUE), opaque(WHITE)]
+        );
+        four_color.color0_transparent = true;
+        assert_eq!(gpu.sample_texture(&four_color, 0, 0, 0), Texel::TRANSPARENT);
+
+        // 16 and 256 color
+        let gpu = gpu_with_texture(&[0x21, 0x03], &[]);
+        assert_eq!(
+            colors(&gpu, &params(3), 0..2),
+            [opaque(GREEN), opaque(BLUE)]
+        );
+        assert_eq!(colors(&gpu, &params(4), 1..2), [opaque(WHITE)]);
+
+        // A3I5 and A5I3 alpha, color 0 transparency does not apply
+        let gpu = gpu_with_texture(&[7 << 5, 3 << 5 | 2, 16 << 3 | 1], &[]);
+        let mut a3i5 = params(1);
+        a3i5.color0_transparent = true;
+        assert_eq!(
+            colors(&gpu, &a3i5, 0..2),
+            [opaque(RED), Texel::from_bgr555(BLUE, 13)]
+        );
+        assert_eq!(
+            colors(&gpu, &params(6), 2..3),
+            [Texel::from_bgr555(GREEN, 16)]
+        );
+
+        // Direct color, bit 15 clear is transparent
+        let gpu = gpu_with_texture(&[0x1F, 0x80, 0x1F, 0x00], &[]);
+        assert_eq!(
+            colors(&gpu, &params(7), 0..2),
+            [opaque(RED), Texel::TRANSPARENT]
+        );
+    }
+
+    #[test]
+    fn test_compressed_texture() {
+        // Block 0 in mode 1 (color 2 averaged), block 1 in mode 3 (both mixed)
+        let gpu = gpu_with_texture(
+            &[0b11_10_01_00, 0, 0, 0, 0b11_10_01_00],
+            &[0x00, 0x40, 0x00, 0xC0],
+        );
+        let texels = colors(&gpu, &params(5), 0..8);
+        assert_eq!(
+            texels[..2],
+            [
+                Texel::from_bgr555(RED, 0x1F),
+                Texel::from_bgr555(GREEN, 0x1F)
+            ]
+        );
+        assert_eq!(texels[2], Texel::from_bgr555(15 | 15 << 5, 0x1F));
+        assert_eq!(texels[3], Texel::TRANSPARENT);
+        assert_eq!(texels[6], Texel::from_bgr555(19 | 11 << 5, 0x1F));
+        assert_eq!(texels[7], Texel::from_bgr555(11 | 19 << 5, 0x1F));
+    }
+
+    #[test]
+    fn test_texture_wrapping() {
+        assert_eq!(wrap_coordinate(9, 8, true, false), 1);
+        assert_eq!(wrap_coordinate(9, 8, true, true), 6);
+        assert_eq!(wrap_coordinate(-1, 8, true, true), 0);
+        assert_eq!(wrap_coordinate(-3, 8, false, false), 0);
+        assert_eq!(wrap_coordinate(20, 8, false, true), 7);
+    }
+}
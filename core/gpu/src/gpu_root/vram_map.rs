@@ -0,0 +1,376 @@
+//! VRAM bank mapping
+//!
+//! Each bank A-I is visible in at most one address space at a time, chosen
+//! by the MST and OFS fields of its VRAMCNT register. The table is rebuilt on
+//! every VRAMCNT write and all VRAM accessors translate through it. Banks
+//! mapped over each other all take writes, and reads OR their bytes.
+use lunaris_ds_mem_const::{
+    VRAM_BGA_START, VRAM_BGB_START, VRAM_LCDC_A, VRAM_OBJA_START, VRAM_OBJB_START,
+};
+
+use crate::gpu_root::Gpu;
+use crate::gpu_root::register::VramBankCfg;
+use crate::gpu_root::taint::TaintRegion;
+
+/// An address space VRAM banks are mapped into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VramSpace {
+    /// 0x06800000, each bank at a fixed address, mirrored every 1M
+    Lcdc,
+    /// Engine A BG, 0x06000000
+    BgA,
+    /// Engine A OBJ, 0x06400000
+    ObjA,
+    /// Engine B BG, 0x06200000
+    BgB,
+    /// Engine B OBJ, 0x06600000
+    ObjB,
+    /// ARM7 0x06000000
+    Arm7,
+    /// 3D texture image slots 0-3
+    TexImage,
+    /// 3D texture palette slots 0-5
+    TexPalette,
+    /// Engine A BG extended palette slots 0-3
+    ExtPalBgA,
+    /// Engine A OBJ extended palette
+    ExtPalObjA,
+    /// Engine B BG extended palette slots 0-3
+    ExtPalBgB,
+    /// Engine B OBJ extended palette
+    ExtPalObjB,
+}
+
+impl VramSpace {
+    /// Size after which the space mirrors.
+    const fn size(self) -> u32 {
+        match self {
+            Self::Lcdc => 0x10_0000,
+            Self::BgA | Self::TexImage => 0x8_0000,
+            Self::ObjA | Self::Arm7 => 0x4_0000,
+            Self::BgB | Self::ObjB | Self::TexPalette => 0x2_0000,
+            Self::ExtPalBgA | Self::ExtPalBgB => 0x8000,
+            Self::ExtPalObjA | Self::ExtPalObjB => 0x2000,
+        }
+    }
+
+    /// Whether `bank` can be mapped into the space at all. Lookups only
+    /// consult these banks, so engine B never sees the banks of engine A.
+    const fn can_map(self, bank: TaintRegion) -> bool {
+        use TaintRegion::*;
+
+        match self {
+            Self::Lcdc => true,
+            Self::BgA => matches!(bank, VramA | VramB | VramC | VramD | VramE | VramF | VramG),
+            Self::ObjA => matches!(bank, VramA | VramB | VramE | VramF | VramG),
+            Self::BgB => matches!(bank, VramC | VramH | VramI),
+            Self::ObjB => matches!(bank, VramD | VramI),
+            Self::Arm7 => matches!(bank, VramC | VramD),
+            Self::TexImage => matches!(bank, VramA | VramB | VramC | VramD),
+            Self::TexPalette | Self::ExtPalBgA => matches!(bank, VramE | VramF | VramG),
+            Self::ExtPalObjA => matches!(bank, VramF | VramG),
+            Self::ExtPalBgB => matches!(bank, VramH),
+            Self::ExtPalObjB => matches!(bank, VramI),
+        }
+    }
+
+    /// Offset in the space of a CPU address in the 0x06000000 region.
+    const fn cpu_offset(self, address: u32) -> u32 {
+        let start = match self {
+            Self::Lcdc => VRAM_LCDC_A,
+            Self::BgA | Self::Arm7 => VRAM_BGA_START,
+            Self::ObjA => VRAM_OBJA_START,
+            Self::BgB => VRAM_BGB_START,
+            Self::ObjB => VRAM_OBJB_START,
+            _ => 0,
+        };
+        address.wrapping_sub(start) % self.size()
+    }
+}
+
+/// Where a bank is mapped: its space and the offset of its first byte there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VramMapping {
+    pub space: VramSpace,
+    pub start: u32,
+}
+
+/// Mapping of `bank` under `cnt`, `None` while disabled or for MST values
+/// the bank does not have.
+pub const fn bank_mapping(bank: TaintRegion, cnt: &VramBankCfg) -> Option<VramMapping> {
+    use VramSpace::*;
+
+    if !cnt.enabled {
+        return None;
+    }
+    // A, B, H and I have a two bit MST
+    let mst = match bank {
+        TaintRegion::VramA | TaintRegion::VramB | TaintRegion::VramH | TaintRegion::VramI => {
+            cnt.mst & 0x3
+        }
+        _ => cnt.mst,
+    };
+    let ofs = cnt.offset;
+    // Offset of F and G in BG-A and OBJ-A: 16K steps, then 64K
+    let small_ofs = (ofs & 1) * 0x4000 + (ofs >> 1) * 0x1_0000;
+    let (space, start) = match (bank, mst) {
+        (TaintRegion::VramA, 0) => (Lcdc, 0),
+        (TaintRegion::VramB, 0) => (Lcdc, 0x2_0000),
+        (TaintRegion::VramC, 0) => (Lcdc, 0x4_0000),
+        (TaintRegion::VramD, 0) => (Lcdc, 0x6_0000),
+        (TaintRegion::VramE, 0) => (Lcdc, 0x8_0000),
+        (TaintRegion::VramF, 0) => (Lcdc, 0x9_0000),
+        (TaintRegion::VramG, 0) => (Lcdc, 0x9_4000),
+        (TaintRegion::VramH, 0) => (Lcdc, 0x9_8000),
+        (TaintRegion::VramI, 0) => (Lcdc, 0xA_0000),
+
+        (TaintRegion::VramA | TaintRegion::VramB | TaintRegion::VramC | TaintRegion::VramD, 1) => {
+            (BgA, ofs * 0x2_0000)
+        }
+        (TaintRegion::VramA | TaintRegion::VramB, 2) => (ObjA, (ofs & 1) * 0x2_0000),
+        (TaintRegion::VramC | TaintRegion::VramD, 2) => (Arm7, (ofs & 1) * 0x2_0000),
+        (TaintRegion::VramA | TaintRegion::VramB | TaintRegion::VramC | TaintRegion::VramD, 3) => {
+            (TexImage, ofs * 0x2_0000)
+        }
+        (TaintRegion::VramC, 4) => (BgB, 0),
+        (TaintRegion::VramD, 4) => (ObjB, 0),
+
+        (TaintRegion::VramE, 1) => (BgA, 0),
+        (TaintRegion::VramE, 2) => (ObjA, 0),
+        (TaintRegion::VramE, 3) => (TexPalette, 0),
+        (TaintRegion::VramE, 4) => (ExtPalBgA, 0),
+
+        (TaintRegion::VramF | TaintRegion::VramG, 1) => (BgA, small_ofs),
+        (TaintRegion::VramF | TaintRegion::VramG, 2) => (ObjA, small_ofs),
+        (TaintRegion::VramF | TaintRegion::VramG, 3) => {
+            (TexPalette, ((ofs & 1) + (ofs >> 1) * 4) * 0x4000)
+        }
+        (TaintRegion::VramF | TaintRegion::VramG, 4) => (ExtPalBgA, (ofs & 1) * 0x4000),
+        (TaintRegion::VramF | TaintRegion::VramG, 5) => (ExtPalObjA, 0),
+
+        (TaintRegion::VramH, 1) => (BgB, 0),
+        (TaintRegion::VramH, 2) => (ExtPalBgB, 0),
+
+        (TaintRegion::VramI, 1) => (BgB, 0x8000),
+        (TaintRegion::VramI, 2) => (ObjB, 0),
+        (TaintRegion::VramI, 3) => (ExtPalObjB, 0),
+
+        _ => return None,
+    };
+    Some(VramMapping { space, start })
+}
+
+impl Gpu {
+    /// Rebuild the bank table after a VRAMCNT write.
+    pub(crate) fn update_vram_map(&mut self) {
+        let cnts = [
+            &self.vramcnt_a,
+            &self.vramcnt_b,
+            &self.vramcnt_c,
+            &self.vramcnt_d,
+            &self.vramcnt_e,
+            &self.vramcnt_f,
+            &self.vramcnt_g,
+            &self.vramcnt_h,
+            &self.vramcnt_i,
+        ];
+        for ((mapping, cnt), bank) in self.vram_map.iter_mut().zip(cnts).zip(TaintRegion::ALL) {
+            *mapping = bank_mapping(bank, cnt);
+        }
+    }
+
+    /// Where `bank` is mapped, see [`bank_mapping`].
+    pub fn vram_mapping(&self, bank: TaintRegion) -> Option<VramMapping> {
+        TaintRegion::ALL[..9]
+            .iter()
+            .position(|&region| region == bank)
+            .and_then(|i| self.vram_map[i])
+    }
+
+    /// Banks holding `offset` of `space`, with the index into each.
+    fn vram_targets(
+        &self,
+        space: VramSpace,
+        offset: u32,
+    ) -> impl Iterator<Item = (TaintRegion, usize)> + use<> {
+        let map = self.vram_map;
+        map.into_iter()
+            .zip(TaintRegion::ALL)
+            .filter_map(move |(mapping, bank)| {
+                let mapping = mapping.filter(|_| space.can_map(bank))?;
+                let index = offset.checked_sub(mapping.start)? as usize;
+                (mapping.space == space && index < bank.size()).then_some((bank, index))
+            })
+    }
+
+    /// Byte at `offset` of `space`, 0 where nothing is mapped.
+    pub fn read_vram_u8(&self, space: VramSpace, offset: u32) -> u8 {
+        let offset = offset % space.size();
+        self.vram_targets(space, offset)
+            .filter_map(|(bank, index)| self.sample_vram(bank, index))
+            .fold(0, |value, &byte| value | byte)
+    }
+
+    pub fn read_vram_u16(&self, space: VramSpace, offset: u32) -> u16 {
+        u16::from_le_bytes([
+            self.read_vram_u8(space, offset),
+            self.read_vram_u8(space, offset + 1),
+        ])
+    }
+
+    pub fn read_vram_u32(&self, space: VramSpace, offset: u32) -> u32 {
+        let mut bytes = [0u8; 4];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = self.read_vram_u8(space, offset + i as u32);
+        }
+        u32::from_le_bytes(bytes)
+    }
+
+    pub fn read_vram_u64(&self, space: VramSpace, offset: u32) -> u64 {
+        let mut bytes = [0u8; 8];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = self.read_vram_u8(space, offset + i as u32);
+        }
+        u64::from_le_bytes(bytes)
+    }
+
+    /// Write `data` to every bank mapped at `offset` of `space`.
+    pub fn write_vram(&mut self, space: VramSpace, offset: u32, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            let offset = (offset + i as u32) % space.size();
+            for (bank, index) in self.vram_targets(space, offset) {
+                self.bank_mut(bank)[index] = byte;
+                self.note_write(bank, index, 1);
+            }
+        }
+    }
+
+    /// CPU read of the 0x06000000 region through `space`.
+    pub(crate) fn read_cpu_vram_u8(&self, space: VramSpace, address: u32) -> u8 {
+        self.read_vram_u8(space, space.cpu_offset(address))
+    }
+
+    /// CPU write of the 0x06000000 region through `space`.
+    pub(crate) fn write_cpu_vram(&mut self, space: VramSpace, address: u32, data: &[u8]) {
+        self.write_vram(space, space.cpu_offset(address), data);
+    }
+
+    pub(crate) fn bank_mut(&mut self, bank: TaintRegion) -> &mut [u8] {
+        match bank {
+            TaintRegion::VramA => &mut self.vram_a,
+            TaintRegion::VramB => &mut self.vram_b,
+            TaintRegion::VramC => &mut self.vram_c,
+            TaintRegion::VramD => &mut self.vram_d,
+            TaintRegion::VramE => &mut self.vram_e,
+            TaintRegion::VramF => &mut self.vram_f,
+            TaintRegion::VramG => &mut self.vram_g,
+            TaintRegion::VramH => &mut self.vram_h,
+            TaintRegion::VramI => &mut self.vram_i,
+            TaintRegion::PaletteUpper => &mut self.palette_upper,
+            TaintRegion::PaletteLower => &mut self.palette_lower,
+            TaintRegion::Oam => &mut self.oam,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vram_bank_mapping() {
+        let mut gpu = Gpu::new();
+        gpu.power_on();
+
+        // A as BG-A at offset 1, mirrored every 512K
+        gpu.set_vramcnt_a(0x89);
+        assert_eq!(gpu.get_vramcnt_a(), 0x89);
+        gpu.write_bga(0x0602_0000, 0x1234);
+        assert_eq!(gpu.memory(TaintRegion::VramA)[..2], [0x34, 0x12]);
+        assert_eq!(gpu.read_bga_u16(0x0600_0000), 0);
+        assert_eq!(gpu.read_bga_u16(0x060A_0000), 0x1234);
+
+        // E and F overlapping at the start of BG-A: reads OR, writes reach both
+        gpu.set_vramcnt_e(0x80);
+        gpu.set_vramcnt_f(0x80);
+        gpu.write_lcdc(VRAM_LCDC_A + 0x8_0000, 0x000F);
+        gpu.write_lcdc(VRAM_LCDC_A + 0x9_0000, 0x00F0);
+        gpu.set_vramcnt_e(0x81);
+        gpu.set_vramcnt_f(0x81);
+        assert_eq!(gpu.read_bga_u16(0x0600_0000), 0x00FF);
+        gpu.write_bga(0x0600_0002, 0xABCD);
+        assert_eq!(gpu.memory(TaintRegion::VramE)[2..4], [0xCD, 0xAB]);
+        assert_eq!(gpu.memory(TaintRegion::VramF)[2..4], [0xCD, 0xAB]);
+
+        // F at offset 3 sits at 0x14000 of BG-A
+        gpu.set_vramcnt_f(0x99);
+        assert_eq!(gpu.read_bga_u16(0x0601_4002), 0xABCD);
+
+        // C for the ARM7 at offset 1
+        gpu.set_vramcnt_c(0x8A);
+        assert_eq!(gpu.get_vramstat(), 0x1);
+        gpu.write_arm7_u32(0x0602_0000, 0xDEAD_BEEF);
+        assert_eq!(gpu.read_arm7_u32(0x0606_0000), 0xDEAD_BEEF);
+        assert_eq!(gpu.read_arm7_u32(0x0600_0000), 0);
+
+        // Texture palette slot 5 from G
+        gpu.set_vramcnt_g(0x9B);
+        assert_eq!(
+            gpu.vram_mapping(TaintRegion::VramG),
+            Some(VramMapping {
+                space: VramSpace::TexPalette,
+                start: 0x1_4000,
+            })
+        );
+    }
+
+    #[test]
+    fn test_vram_space_banks() {
+        // Every mapping lands in a space that lists its bank
+        for bank in &TaintRegion::ALL[..9] {
+            for mst in 0..8 {
+                for offset in 0..4 {
+                    let cnt = VramBankCfg {
+                        mst,
+                        offset,
+                        enabled: true,
+                    };
+                    if let Some(mapping) = bank_mapping(*bank, &cnt) {
+                        assert!(mapping.space.can_map(*bank), "{bank:?} in {mapping:?}");
+                    }
+                }
+            }
+        }
+
+        // Engine A BG, OBJ and extended palette banks stay out of engine B
+        let mut gpu = Gpu::new();
+        gpu.power_on();
+        gpu.set_vramcnt_a(0x80);
+        gpu.set_vramcnt_b(0x80);
+        gpu.set_vramcnt_e(0x80);
+        gpu.set_vramcnt_f(0x80);
+        gpu.set_vramcnt_h(0x80);
+        gpu.set_vramcnt_i(0x80);
+        for (bank, lcdc) in [(0, 0x0_0000), (1, 0x2_0000), (4, 0x8_0000), (5, 0x9_0000)] {
+            gpu.write_lcdc(VRAM_LCDC_A + lcdc, 0x1234 + bank);
+        }
+        gpu.set_vramcnt_a(0x81);
+        gpu.set_vramcnt_b(0x82);
+        gpu.set_vramcnt_e(0x84);
+        gpu.set_vramcnt_f(0x85);
+        assert_eq!(gpu.read_bga_u16(0x0600_0000), 0x1234);
+        assert_eq!(gpu.read_extpal_bga_u16(0), 0x1238);
+        assert_eq!(gpu.read_bgb_u16(0x0620_0000), 0);
+        assert_eq!(gpu.read_objb_u16(0x0660_0000), 0);
+        assert_eq!(gpu.read_extpal_bgb_u16(0), 0);
+        assert_eq!(gpu.read_extpal_objb(0), 0);
+
+        // H and I are what engine B reads its extended palettes from
+        gpu.write_lcdc(VRAM_LCDC_A + 0x9_8000, 0x00AA);
+        gpu.write_lcdc(VRAM_LCDC_A + 0xA_0000, 0x00BB);
+        gpu.set_vramcnt_h(0x82);
+        gpu.set_vramcnt_i(0x83);
+        assert_eq!(gpu.read_extpal_bgb_u16(0), 0x00AA);
+        assert_eq!(gpu.read_extpal_objb(0), 0x00BB);
+    }
+}
@@ -1,6 +1,8 @@
 // SPDX-FileCopyrightText: (C) 2017 PSISP
 // SPDX-License-Identifier: GPL-3.0-or-later
+#![forbid(unsafe_code)]
 
 pub mod gpu_2d;
 pub mod gpu_3d;
 pub mod gpu_root;
+pub mod vram_bytes;
@@ -0,0 +1,24 @@
+//! Halfword access to byte-backed VRAM and palette memory
+//!
+//! VRAM banks and palettes are stored as `Vec<u8>`, which is only
+//! byte-aligned, so they cannot be reinterpreted as `[u16]` without unsafe
+//! code. These helpers read and write little-endian halfwords by index
+//! instead.
+
+/// Halfword `index` of `bytes`.
+///
+/// # Panics
+/// If the halfword is out of range, like slice indexing.
+#[inline]
+pub fn read_u16(bytes: &[u8], index: usize) -> u16 {
+    u16::from_le_bytes([bytes[index * 2], bytes[index * 2 + 1]])
+}
+
+/// Set halfword `index` of `bytes`.
+///
+/// # Panics
+/// If the halfword is out of range, like slice indexing.
+#[inline]
+pub fn write_u16(bytes: &mut [u8], index: usize, value: u16) {
+    bytes[index * 2..index * 2 + 2].copy_from_slice(&value.to_le_bytes());
+}
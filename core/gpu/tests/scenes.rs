@@ -0,0 +1,351 @@
+//! 2D renderer golden tests
+//!
+//! Every `tests/scenes/<name>.toml` describes a scene for engine A: display
+//! and BG registers, blending and windows, palette entries, tile and map
+//! data made from simple patterns, and OBJ placements. The scene is
+//! programmed into a fresh [`Gpu`], all 192 lines are drawn and the upper
+//! screen is compared with `tests/scenes/<name>.png`.
+//!
+//! Set `LUNARIS_BLESS=1` to write the golden of a new scene, or to rewrite
+//! them after checking that a change is an improvement.
+//!
+//! # Scene format
+//! ```toml
+//! description = "BG0 text tiles"
+//! dispcnt = 0x0001_0100
+//! vramcnt = { a = 0x81 }
+//! bldcnt = 0x2041    # also bldalpha, bldy, mosaic, win0h, win0v,
+//!                    # win1h, win1v, winin, winout
+//!
+//! [[bg]]
+//! index = 0
+//! bgcnt = 0x0004
+//! hofs = 0          # optional scroll
+//!
+//! [[palette]]
+//! start = 1         # entry; OBJ palettes start at 256
+//! colors = [0x001F, 0x03E0]
+//!
+//! [[tiles]]
+//! address = 0x0600_4020
+//! pattern = "checker" # solid, checker, stripes, gradient
+//! colors = [1, 2]
+//! bpp = 4             # optional, 4 or 8
+//! count = 1           # optional, consecutive tiles
+//!
+//! [[map]]
+//! address = 0x0600_0000
+//! entries = [1, 2]  # halfwords, written `repeat` times
+//! repeat = 512
+//!
+//! [[sprite]]
+//! x = 16
+//! y = 8
+//! tile = 1
+//! shape = "square"  # wide, tall
+//! size = 1          # optional: priority, palette, mode, h_flip, v_flip,
+//!                   # mosaic, palette_256
+//! ```
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use lunaris_ds_gpu::gpu_root::Gpu;
+use lunaris_ds_mem_const::{PIXELS_PER_LINE, SCANLINES};
+use lunaris_ds_test_support::{Frame, assert_golden};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Scene {
+    #[expect(unused)]
+    description: String,
+    dispcnt: u32,
+    #[serde(default)]
+    vramcnt: BTreeMap<String, u8>,
+    #[serde(default)]
+    bldcnt: u16,
+    #[serde(default)]
+    bldalpha: u16,
+    #[serde(default)]
+    bldy: u8,
+    #[serde(default)]
+    mosaic: u16,
+    #[serde(default)]
+    win0h: u16,
+    #[serde(default)]
+    win0v: u16,
+    #[serde(default)]
+    win1h: u16,
+    #[serde(default)]
+    win1v: u16,
+    #[serde(default)]
+    winin: u16,
+    #[serde(default)]
+    winout: u16,
+    #[serde(default)]
+    bg: Vec<Bg>,
+    #[serde(default)]
+    palette: Vec<Palette>,
+    #[serde(default)]
+    tiles: Vec<Tiles>,
+    #[serde(default)]
+    map: Vec<Map>,
+    #[serde(default)]
+    sprite: Vec<Sprite>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Bg {
+    index: usize,
+    bgcnt: u16,
+    #[serde(default)]
+    hofs: u16,
+    #[serde(default)]
+    vofs: u16,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Palette {
+    start: u32,
+    colors: Vec<u16>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Pattern {
+    /// Every pixel `colors[0]`
+    Solid,
+    /// One pixel checkerboard of `colors[0]` and `colors[1]`
+    Checker,
+    /// Columns cycling through `colors`
+    Stripes,
+    /// Pixel `(x, y)` is `colors[0] + y * 8 + x`
+    Gradient,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Tiles {
+    address: u32,
+    pattern: Pattern,
+    colors: Vec<u8>,
+    #[serde(default = "default_bpp")]
+    bpp: u32,
+    #[serde(default = "default_count")]
+    count: u32,
+}
+
+const fn default_bpp() -> u32 {
+    4
+}
+
+const fn default_count() -> u32 {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Map {
+    address: u32,
+    entries: Vec<u16>,
+    #[serde(default = "default_count")]
+    repeat: u32,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Shape {
+    #[default]
+    Square,
+    Wide,
+    Tall,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum ObjMode {
+    #[default]
+    Normal,
+    SemiTransparent,
+    Window,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Sprite {
+    x: i32,
+    y: u8,
+    tile: u16,
+    #[serde(default)]
+    shape: Shape,
+    #[serde(default)]
+    size: u16,
+    #[serde(default)]
+    priority: u16,
+    #[serde(default)]
+    palette: u16,
+    #[serde(default)]
+    mode: ObjMode,
+    #[serde(default)]
+    h_flip: bool,
+    #[serde(default)]
+    v_flip: bool,
+    #[serde(default)]
+    mosaic: bool,
+    #[serde(default)]
+    palette_256: bool,
+}
+
+impl Sprite {
+    /// OAM attributes 0-2.
+    fn attributes(&self) -> [u16; 3] {
+        let attr0 = self.y as u16
+            | (self.mode as u16) << 10
+            | (self.mosaic as u16) << 12
+            | (self.palette_256 as u16) << 13
+            | (self.shape as u16) << 14;
+        let attr1 = (self.x as u16 & 0x1FF)
+            | (self.h_flip as u16) << 12
+            | (self.v_flip as u16) << 13
+            | self.size << 14;
+        let attr2 = self.tile | self.priority << 10 | self.palette << 12;
+        [attr0, attr1, attr2]
+    }
+}
+
+impl Tiles {
+    /// Color index of pixel `(x, y)` of a tile.
+    fn color(&self, x: usize, y: usize) -> u8 {
+        let colors = &self.colors;
+        match self.pattern {
+            Pattern::Solid => colors[0],
+            Pattern::Checker => colors[(x + y) % 2],
+            Pattern::Stripes => colors[x % colors.len()],
+            Pattern::Gradient => colors[0].wrapping_add((y * 8 + x) as u8),
+        }
+    }
+
+    /// Tile data, `count` tiles of 32 or 64 bytes.
+    fn bytes(&self) -> Vec<u8> {
+        let tile: Vec<u8> = match self.bpp {
+            4 => (0..32)
+                .map(|i| {
+                    let (x, y) = ((i % 4) * 2, i / 4);
+                    (self.color(x, y) & 0xF) | (self.color(x + 1, y) & 0xF) << 4
+                })
+                .collect(),
+            8 => (0..64).map(|i| self.color(i % 8, i / 8)).collect(),
+            bpp => panic!("unsupported tile depth {bpp}"),
+        };
+        tile.repeat(self.count as usize)
+    }
+}
+
+/// Write halfwords to engine A BG or OBJ VRAM, by address.
+fn write_vram(gpu: &mut Gpu, address: u32, data: &[u8]) {
+    for (offset, pair) in data.chunks(2).enumerate() {
+        let halfword = u16::from_le_bytes([pair[0], *pair.get(1).unwrap_or(&0)]);
+        let address = address + offset as u32 * 2;
+        match address {
+            0x0600_0000..0x0620_0000 => gpu.write_bga(address, halfword),
+            0x0640_0000..0x0660_0000 => gpu.write_obja(address, halfword),
+            _ => panic!("{address:#010X} is not engine A BG or OBJ VRAM"),
+        }
+    }
+}
+
+fn program(gpu: &mut Gpu, scene: &Scene) {
+    for (bank, &value) in &scene.vramcnt {
+        match bank.as_str() {
+            "a" => gpu.set_vramcnt_a(value),
+            "b" => gpu.set_vramcnt_b(value),
+            "c" => gpu.set_vramcnt_c(value),
+            "d" => gpu.set_vramcnt_d(value),
+            "e" => gpu.set_vramcnt_e(value),
+            "f" => gpu.set_vramcnt_f(value),
+            "g" => gpu.set_vramcnt_g(value),
+            "h" => gpu.set_vramcnt_h(value),
+            "i" => gpu.set_vramcnt_i(value),
+            bank => panic!("unknown VRAM bank {bank}"),
+        }
+    }
+    gpu.set_dispcnt_a(scene.dispcnt);
+    for bg in &scene.bg {
+        gpu.set_bgcnt_a(bg.bgcnt, bg.index);
+        gpu.set_bghofs_a(bg.hofs, bg.index);
+        gpu.set_bgvofs_a(bg.vofs, bg.index);
+    }
+    gpu.set_bldcnt_a(scene.bldcnt);
+    gpu.set_bldalpha_a(scene.bldalpha);
+    gpu.set_bldy_a(scene.bldy);
+    gpu.set_mosaic_a(scene.mosaic);
+    gpu.set_win0h_a(scene.win0h);
+    gpu.set_win0v_a(scene.win0v);
+    gpu.set_win1h_a(scene.win1h);
+    gpu.set_win1v_a(scene.win1v);
+    gpu.set_winin_a(scene.winin);
+    gpu.set_winout_a(scene.winout);
+
+    for palette in &scene.palette {
+        for (i, &color) in palette.colors.iter().enumerate() {
+            gpu.write_palette_a((palette.start + i as u32) * 2, color);
+        }
+    }
+    for tiles in &scene.tiles {
+        write_vram(gpu, tiles.address, &tiles.bytes());
+    }
+    for map in &scene.map {
+        let bytes: Vec<u8> = map.entries.iter().flat_map(|e| e.to_le_bytes()).collect();
+        write_vram(gpu, map.address, &bytes.repeat(map.repeat as usize));
+    }
+
+    // OBJs not in the scene are hidden
+    for index in 0..128 {
+        let attributes = match scene.sprite.get(index) {
+            Some(sprite) => sprite.attributes(),
+            None => [0x0200, 0, 0],
+        };
+        for (i, attribute) in attributes.into_iter().enumerate() {
+            gpu.write_oam((index * 8 + i * 2) as u32, attribute);
+        }
+    }
+}
+
+fn render(scene: &Scene) -> Frame {
+    let mut gpu = Gpu::new();
+    gpu.power_on();
+    program(&mut gpu, scene);
+    for line in 0..SCANLINES as u16 {
+        gpu.vertical_count = line;
+        gpu.draw_scanline();
+    }
+    Frame {
+        width: PIXELS_PER_LINE as u32,
+        height: SCANLINES as u32,
+        pixels: gpu.engine_upper.front_framebuffer.clone(),
+    }
+}
+
+fn scenes_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenes")
+}
+
+#[test]
+fn test_scenes() {
+    let mut paths: Vec<_> = std::fs::read_dir(scenes_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let text = std::fs::read_to_string(&path).unwrap();
+        let scene: Scene =
+            toml::from_str(&text).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+        assert_golden(&path.with_extension("png"), &render(&scene), 0);
+    }
+}
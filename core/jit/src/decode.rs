@@ -0,0 +1,378 @@
+//! The instructions the code generator handles
+//!
+//! Data-processing instructions that always execute, never touch the PC and
+//! shift by an immediate: the bulk of straight-line integer code, and free of
+//! memory accesses, so running them needs nothing but registers and flags.
+
+/// Data-processing operation, numbered as in ARM instruction bits 21-24
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AluOp {
+    And,
+    Eor,
+    Sub,
+    Rsb,
+    Add,
+    Adc,
+    Sbc,
+    Rsc,
+    Tst,
+    Teq,
+    Cmp,
+    Cmn,
+    Orr,
+    Mov,
+    Bic,
+    Mvn,
+}
+
+impl AluOp {
+    const fn from_opcode(opcode: u32) -> Self {
+        match opcode & 0xF {
+            0x0 => Self::And,
+            0x1 => Self::Eor,
+            0x2 => Self::Sub,
+            0x3 => Self::Rsb,
+            0x4 => Self::Add,
+            0x5 => Self::Adc,
+            0x6 => Self::Sbc,
+            0x7 => Self::Rsc,
+            0x8 => Self::Tst,
+            0x9 => Self::Teq,
+            0xA => Self::Cmp,
+            0xB => Self::Cmn,
+            0xC => Self::Orr,
+            0xD => Self::Mov,
+            0xE => Self::Bic,
+            _ => Self::Mvn,
+        }
+    }
+
+    /// Whether C comes from the barrel shifter rather than the adder
+    pub const fn is_logical(self) -> bool {
+        matches!(
+            self,
+            Self::And
+                | Self::Eor
+                | Self::Tst
+                | Self::Teq
+                | Self::Orr
+                | Self::Mov
+                | Self::Bic
+                | Self::Mvn
+        )
+    }
+
+    /// Whether the result goes to Rd, not only to the flags
+    pub const fn writes_result(self) -> bool {
+        !matches!(self, Self::Tst | Self::Teq | Self::Cmp | Self::Cmn)
+    }
+
+    /// Whether Rn is an operand
+    pub const fn reads_rn(self) -> bool {
+        !matches!(self, Self::Mov | Self::Mvn)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shift {
+    Lsl,
+    Lsr,
+    Asr,
+    Ror,
+}
+
+impl Shift {
+    const fn from_bits(bits: u32) -> Self {
+        match bits & 3 {
+            0 => Self::Lsl,
+            1 => Self::Lsr,
+            2 => Self::Asr,
+            _ => Self::Ror,
+        }
+    }
+}
+
+/// Second operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// Constant, with the shifter carry of a rotated ARM immediate; `None`
+    /// leaves C alone
+    Imm { value: u32, carry: Option<bool> },
+    /// Register shifted by 1-31 bits, or LSL #0 to take it as is
+    Reg { rm: u8, shift: Shift, amount: u8 },
+}
+
+/// One data-processing instruction, ARM or Thumb
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AluInstr {
+    pub op: AluOp,
+    pub set_flags: bool,
+    pub rd: u8,
+    pub rn: u8,
+    pub operand: Operand,
+}
+
+const fn reg_operand(rm: u32) -> Operand {
+    Operand::Reg {
+        rm: rm as u8,
+        shift: Shift::Lsl,
+        amount: 0,
+    }
+}
+
+/// Decode an ARM instruction, `None` unless the code generator handles it.
+pub const fn decode_arm(instruction: u32) -> Option<AluInstr> {
+    // Condition AL, data processing
+    if instruction >> 28 != 0xE || (instruction >> 26) & 3 != 0 {
+        return None;
+    }
+    let op = AluOp::from_opcode(instruction >> 21);
+    let set_flags = instruction & (1 << 20) != 0;
+    // TST, TEQ, CMP and CMN without S are MRS, MSR and friends
+    if !op.writes_result() && !set_flags {
+        return None;
+    }
+    let rd = (instruction >> 12) & 0xF;
+    let rn = (instruction >> 16) & 0xF;
+    if rd == 15 || (op.reads_rn() && rn == 15) {
+        return None;
+    }
+
+    let operand = if instruction & (1 << 25) != 0 {
+        let rotate = (instruction >> 7) & 0x1E;
+        let value = (instruction & 0xFF).rotate_right(rotate);
+        Operand::Imm {
+            value,
+            carry: if rotate != 0 {
+                Some(value >> 31 != 0)
+            } else {
+                None
+            },
+        }
+    } else {
+        let rm = instruction & 0xF;
+        let shift = Shift::from_bits(instruction >> 5);
+        let amount = (instruction >> 7) & 0x1F;
+        // Register-specified shifts (and multiplies, halfword transfers)
+        // have bit 4 set; LSR, ASR #0 mean #32 and ROR #0 is RRX
+        if instruction & (1 << 4) != 0 || rm == 15 || (amount == 0 && !matches!(shift, Shift::Lsl))
+        {
+            return None;
+        }
+        Operand::Reg {
+            rm: rm as u8,
+            shift,
+            amount: amount as u8,
+        }
+    };
+
+    Some(AluInstr {
+        op,
+        set_flags,
+        rd: rd as u8,
+        rn: rn as u8,
+        operand,
+    })
+}
+
+/// Decode a Thumb instruction, `None` unless the code generator handles it.
+pub const fn decode_thumb(instruction: u16) -> Option<AluInstr> {
+    let instruction = instruction as u32;
+    let low = instruction & 7;
+    let mid = (instruction >> 3) & 7;
+
+    match instruction >> 10 {
+        // Format 2: ADD/SUB Rd, Rs, Rn/#imm3
+        0b000110 | 0b000111 => {
+            let value = (instruction >> 6) & 7;
+            let operand = match instruction & (1 << 10) != 0 {
+                true => Operand::Imm { value, carry: None },
+                false => reg_operand(value),
+            };
+            Some(AluInstr {
+                op: if instruction & (1 << 9) != 0 {
+                    AluOp::Sub
+                } else {
+                    AluOp::Add
+                },
+                set_flags: true,
+                rd: low as u8,
+                rn: mid as u8,
+                operand,
+            })
+        }
+        // Format 1: LSL/LSR/ASR Rd, Rs, #imm5
+        0b000000..=0b000101 => {
+            let shift = Shift::from_bits(instruction >> 11);
+            let amount = (instruction >> 6) & 0x1F;
+            // LSR, ASR #0 mean #32
+            if amount == 0 && !matches!(shift, Shift::Lsl) {
+                return None;
+            }
+            Some(AluInstr {
+                op: AluOp::Mov,
+                set_flags: true,
+                rd: low as u8,
+                rn: 0,
+                operand: Operand::Reg {
+                    rm: mid as u8,
+                    shift,
+                    amount: amount as u8,
+                },
+            })
+        }
+        // Format 3: MOV/CMP/ADD/SUB Rd, #imm8
+        0b001000..=0b001111 => {
+            let rd = ((instruction >> 8) & 7) as u8;
+            let op = match (instruction >> 11) & 3 {
+                0 => AluOp::Mov,
+                1 => AluOp::Cmp,
+                2 => AluOp::Add,
+                _ => AluOp::Sub,
+            };
+            Some(AluInstr {
+                op,
+                set_flags: true,
+                rd,
+                rn: rd,
+                operand: Operand::Imm {
+                    value: instruction & 0xFF,
+                    carry: None,
+                },
+            })
+        }
+        // Format 4: ALU operations
+        0b010000 => {
+            let op = match (instruction >> 6) & 0xF {
+                0x0 => AluOp::And,
+                0x1 => AluOp::Eor,
+                0x5 => AluOp::Adc,
+                0x6 => AluOp::Sbc,
+                0x8 => AluOp::Tst,
+                // NEG is RSBS Rd, Rs, #0
+                0x9 => {
+                    return Some(AluInstr {
+                        op: AluOp::Rsb,
+                        set_flags: true,
+                        rd: low as u8,
+                        rn: mid as u8,
+                        operand: Operand::Imm {
+                            value: 0,
+                            carry: None,
+                        },
+                    });
+                }
+                0xA => AluOp::Cmp,
+                0xB => AluOp::Cmn,
+                0xC => AluOp::Orr,
+                0xE => AluOp::Bic,
+                0xF => AluOp::Mvn,
+                // Shifts by register and MUL
+                _ => return None,
+            };
+            Some(AluInstr {
+                op,
+                set_flags: true,
+                rd: low as u8,
+                rn: low as u8,
+                operand: reg_operand(mid),
+            })
+        }
+        // Format 5: ADD/CMP/MOV with the high registers, BX
+        0b010001 => {
+            let rd = low | ((instruction >> 4) & 8);
+            let rs = (instruction >> 3) & 0xF;
+            let (op, set_flags) = match (instruction >> 8) & 3 {
+                0 => (AluOp::Add, false),
+                1 => (AluOp::Cmp, true),
+                2 => (AluOp::Mov, false),
+                _ => return None,
+            };
+            if rd == 15 || rs == 15 {
+                return None;
+            }
+            Some(AluInstr {
+                op,
+                set_flags,
+                rd: rd as u8,
+                rn: rd as u8,
+                operand: reg_operand(rs),
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_arm() {
+        // adds r1, r2, r3, lsl #4
+        assert_eq!(
+            decode_arm(0xE092_1203),
+            Some(AluInstr {
+                op: AluOp::Add,
+                set_flags: true,
+                rd: 1,
+                rn: 2,
+                operand: Operand::Reg {
+                    rm: 3,
+                    shift: Shift::Lsl,
+                    amount: 4
+                },
+            })
+        );
+        // movs r0, #0x8000_0000 carries bit 31 out of the rotation
+        assert_eq!(
+            decode_arm(0xE3B0_0102).map(|instr| instr.operand),
+            Some(Operand::Imm {
+                value: 0x8000_0000,
+                carry: Some(true)
+            })
+        );
+
+        // addne, add pc, add r0, pc, mov r0, r1, lsl r2, mov r0, r1, lsr #32,
+        // mrs r0, cpsr, ldr r0, [r1]
+        for instruction in [
+            0x1081_0002,
+            0xE081_F002,
+            0xE08F_0002,
+            0xE1A0_0211,
+            0xE1A0_0021,
+            0xE10F_0000,
+            0xE591_0000,
+        ] {
+            assert_eq!(decode_arm(instruction), None, "{instruction:#010X}");
+        }
+    }
+
+    #[test]
+    fn test_decode_thumb() {
+        // neg r1, r2
+        assert_eq!(
+            decode_thumb(0x4251),
+            Some(AluInstr {
+                op: AluOp::Rsb,
+                set_flags: true,
+                rd: 1,
+                rn: 2,
+                operand: Operand::Imm {
+                    value: 0,
+                    carry: None
+                },
+            })
+        );
+        // mov r8, r1
+        assert_eq!(
+            decode_thumb(0x4688).map(|instr| (instr.op, instr.set_flags, instr.rd)),
+            Some((AluOp::Mov, false, 8))
+        );
+
+        // lsr r0, r1, #32, lsl r0, r1, mul r0, r1, add pc, r0, bx lr, ldr r0, [r1]
+        for instruction in [0x0808, 0x4088, 0x4348, 0x4487, 0x4770, 0x6808] {
+            assert_eq!(decode_thumb(instruction), None, "{instruction:#06X}");
+        }
+    }
+}
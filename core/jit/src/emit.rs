@@ -0,0 +1,251 @@
+//! x86-64 code for [`AluInstr`]s
+//!
+//! The generated function takes a `*mut JitState` in `rdi` (System V) and
+//! runs the instructions one after the other straight on the state: every
+//! instruction loads its operands from it and stores its result and flags
+//! back, so nothing is kept in host registers between instructions. `eax`
+//! holds the result, `ecx` the second operand and `edx` the carry in.
+//!
+//! The host flags line up with the ARM ones: SF, ZF and OF are N, Z and V,
+//! and CF is C after an addition and NOT C after a subtraction, x86 keeping
+//! a borrow where ARM keeps a carry.
+use std::mem::offset_of;
+
+use crate::JitState;
+use crate::decode::{AluInstr, AluOp, Operand, Shift};
+
+const EAX: u8 = 0;
+const ECX: u8 = 1;
+const EDX: u8 = 2;
+
+/// `setcc` condition codes
+const OVERFLOW: u8 = 0x0;
+const CARRY: u8 = 0x2;
+const NOT_CARRY: u8 = 0x3;
+const ZERO: u8 = 0x4;
+const SIGN: u8 = 0x8;
+
+const fn reg_offset(reg: u8) -> u8 {
+    (offset_of!(JitState, regs) + reg as usize * 4) as u8
+}
+
+const N: u8 = offset_of!(JitState, negative) as u8;
+const Z: u8 = offset_of!(JitState, zero) as u8;
+const C: u8 = offset_of!(JitState, carry) as u8;
+const V: u8 = offset_of!(JitState, overflow) as u8;
+
+#[derive(Default)]
+struct Assembler {
+    code: Vec<u8>,
+}
+
+impl Assembler {
+    /// `mov reg, [rdi + offset]`
+    fn load(&mut self, reg: u8, offset: u8) {
+        self.code.extend([0x8B, 0x47 | reg << 3, offset]);
+    }
+
+    /// `mov [rdi + offset], reg`
+    fn store(&mut self, reg: u8, offset: u8) {
+        self.code.extend([0x89, 0x47 | reg << 3, offset]);
+    }
+
+    /// `mov reg, imm32`
+    fn mov_imm(&mut self, reg: u8, value: u32) {
+        self.code.push(0xB8 + reg);
+        self.code.extend(value.to_le_bytes());
+    }
+
+    /// Two register `add`, `sub`, `and`... as `opcode dst, src`
+    fn alu(&mut self, opcode: u8, dst: u8, src: u8) {
+        self.code.extend([opcode, 0xC0 | src << 3 | dst]);
+    }
+
+    /// `shl`, `shr`, `sar` or `ror` by a constant
+    fn shift(&mut self, shift: Shift, reg: u8, amount: u8) {
+        let extension = match shift {
+            Shift::Lsl => 4,
+            Shift::Lsr => 5,
+            Shift::Asr => 7,
+            Shift::Ror => 1,
+        };
+        self.code
+            .extend([0xC1, 0xC0 | extension << 3 | reg, amount]);
+    }
+
+    /// `not reg`
+    fn not(&mut self, reg: u8) {
+        self.code.extend([0xF7, 0xD0 | reg]);
+    }
+
+    /// `neg reg`
+    fn neg(&mut self, reg: u8) {
+        self.code.extend([0xF7, 0xD8 | reg]);
+    }
+
+    /// `setcc byte [rdi + offset]`
+    fn set(&mut self, condition: u8, offset: u8) {
+        self.code.extend([0x0F, 0x90 | condition, 0x47, offset]);
+    }
+
+    /// `mov byte [rdi + offset], value`
+    fn store_byte(&mut self, offset: u8, value: bool) {
+        self.code.extend([0xC6, 0x47, offset, value as u8]);
+    }
+
+    /// `movzx reg, byte [rdi + offset]`
+    fn load_byte(&mut self, reg: u8, offset: u8) {
+        self.code.extend([0x0F, 0xB6, 0x47 | reg << 3, offset]);
+    }
+
+    /// `cmp byte [rdi + offset], value`
+    fn compare_byte(&mut self, offset: u8, value: u8) {
+        self.code.extend([0x80, 0x7F, offset, value]);
+    }
+
+    fn instruction(&mut self, instr: AluInstr) {
+        const ADD: u8 = 0x01;
+        const OR: u8 = 0x09;
+        const ADC: u8 = 0x11;
+        const SBB: u8 = 0x19;
+        const AND: u8 = 0x21;
+        const SUB: u8 = 0x29;
+        const XOR: u8 = 0x31;
+        const CMP: u8 = 0x39;
+        const MOV: u8 = 0x89;
+
+        let AluInstr {
+            op,
+            set_flags,
+            rd,
+            rn,
+            operand,
+        } = instr;
+        let shifter_carry = op.is_logical() && set_flags;
+
+        match operand {
+            Operand::Imm { value, carry } => {
+                self.mov_imm(ECX, value);
+                if let Some(carry) = carry.filter(|_| shifter_carry) {
+                    self.store_byte(C, carry);
+                }
+            }
+            Operand::Reg { rm, shift, amount } => {
+                self.load(ECX, reg_offset(rm));
+                if amount != 0 {
+                    self.shift(shift, ECX, amount);
+                    if shifter_carry {
+                        self.set(CARRY, C);
+                    }
+                }
+            }
+        }
+        if op.reads_rn() {
+            self.load(EAX, reg_offset(rn));
+        }
+
+        // Carry in: CF = C for ADC, NOT C for SBB
+        match op {
+            AluOp::Adc => {
+                self.load_byte(EDX, C);
+                self.neg(EDX);
+            }
+            AluOp::Sbc | AluOp::Rsc => self.compare_byte(C, 1),
+            _ => {}
+        }
+        match op {
+            AluOp::And | AluOp::Tst => self.alu(AND, EAX, ECX),
+            AluOp::Eor | AluOp::Teq => self.alu(XOR, EAX, ECX),
+            AluOp::Orr => self.alu(OR, EAX, ECX),
+            AluOp::Mov => self.alu(MOV, EAX, ECX),
+            AluOp::Bic => {
+                self.not(ECX);
+                self.alu(AND, EAX, ECX);
+            }
+            AluOp::Mvn => {
+                self.alu(MOV, EAX, ECX);
+                self.not(EAX);
+            }
+            AluOp::Add | AluOp::Cmn => self.alu(ADD, EAX, ECX),
+            AluOp::Adc => self.alu(ADC, EAX, ECX),
+            AluOp::Sub => self.alu(SUB, EAX, ECX),
+            AluOp::Sbc => self.alu(SBB, EAX, ECX),
+            AluOp::Cmp => self.alu(CMP, EAX, ECX),
+            // Operands swapped; `mov` leaves the flags alone
+            AluOp::Rsb => {
+                self.alu(SUB, ECX, EAX);
+                self.alu(MOV, EAX, ECX);
+            }
+            AluOp::Rsc => {
+                self.alu(SBB, ECX, EAX);
+                self.alu(MOV, EAX, ECX);
+            }
+        }
+
+        if set_flags {
+            if op.is_logical() {
+                // `mov` and `not` set no flags; V is left alone
+                self.alu(0x85, EAX, EAX);
+                self.set(SIGN, N);
+                self.set(ZERO, Z);
+            } else {
+                self.set(SIGN, N);
+                self.set(ZERO, Z);
+                self.set(OVERFLOW, V);
+                let subtraction = matches!(
+                    op,
+                    AluOp::Sub | AluOp::Rsb | AluOp::Sbc | AluOp::Rsc | AluOp::Cmp
+                );
+                self.set(if subtraction { NOT_CARRY } else { CARRY }, C);
+            }
+        }
+        if op.writes_result() {
+            self.store(EAX, reg_offset(rd));
+        }
+    }
+}
+
+/// Machine code running `instrs`.
+pub fn emit(instrs: &[AluInstr]) -> Vec<u8> {
+    let mut asm = Assembler::default();
+    for &instr in instrs {
+        asm.instruction(instr);
+    }
+    // ret
+    asm.code.push(0xC3);
+    asm.code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emit_adds() {
+        // adds r1, r2, r3
+        let code = emit(&[AluInstr {
+            op: AluOp::Add,
+            set_flags: true,
+            rd: 1,
+            rn: 2,
+            operand: Operand::Reg {
+                rm: 3,
+                shift: Shift::Lsl,
+                amount: 0,
+            },
+        }]);
+        #[rustfmt::skip]
+        let expected = [
+            0x8B, 0x4F, 0x0C, // mov ecx, [rdi + 12]
+            0x8B, 0x47, 0x08, // mov eax, [rdi + 8]
+            0x01, 0xC8, // add eax, ecx
+            0x0F, 0x98, 0x47, 0x40, // sets [rdi + 64]
+            0x0F, 0x94, 0x47, 0x41, // setz [rdi + 65]
+            0x0F, 0x90, 0x47, 0x43, // seto [rdi + 67]
+            0x0F, 0x92, 0x47, 0x42, // setc [rdi + 66]
+            0x89, 0x47, 0x04, // mov [rdi + 4], eax
+            0xC3, // ret
+        ];
+        assert_eq!(code, expected);
+    }
+}
@@ -0,0 +1,67 @@
+//! Executable memory
+//!
+//! Code is copied into a fresh anonymous mapping that is then made read and
+//! execute only, so no page is ever writable and executable at once.
+use std::ffi::c_void;
+use std::ptr;
+
+use crate::JitState;
+
+/// A mapping holding one generated function.
+pub struct Code {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+// The mapping is never written after `Code::new` returns
+unsafe impl Send for Code {}
+unsafe impl Sync for Code {}
+
+impl Code {
+    /// Map `code`, `None` if the host refuses.
+    pub fn new(code: &[u8]) -> Option<Self> {
+        let len = code.len();
+        // SAFETY: a new private anonymous mapping aliases nothing; the copy
+        // stays within the `len` bytes just mapped writable.
+        unsafe {
+            let ptr = libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANON,
+                -1,
+                0,
+            );
+            if ptr == libc::MAP_FAILED {
+                return None;
+            }
+            ptr::copy_nonoverlapping(code.as_ptr(), ptr.cast::<u8>(), len);
+            if libc::mprotect(ptr, len, libc::PROT_READ | libc::PROT_EXEC) != 0 {
+                libc::munmap(ptr, len);
+                return None;
+            }
+            Some(Self { ptr, len })
+        }
+    }
+
+    /// Call the function on `state`.
+    pub fn run(&self, state: &mut JitState) {
+        // SAFETY: the mapping holds a complete function from `emit`, which
+        // only touches the `JitState` it is given.
+        unsafe {
+            let function =
+                std::mem::transmute::<*mut c_void, extern "sysv64" fn(*mut JitState)>(self.ptr);
+            function(state);
+        }
+    }
+}
+
+impl Drop for Code {
+    fn drop(&mut self) {
+        // SAFETY: unmaps exactly what `Code::new` mapped, and nothing can
+        // call into it any more.
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Host code generation for the ARM7TDMI and ARM946E-S
+//!
+//! Compiles runs of ARM or Thumb data-processing instructions (see
+//! [`decode`] for which) into x86-64 functions that work on a
+//! [`JitState`]. The caller copies the guest registers and flags in, calls
+//! [`CompiledBlock::run`] and copies them back out; fetch timing and the PC
+//! stay with the caller.
+//!
+//! Code is only generated on x86-64 Unix hosts. Elsewhere [`compile`]
+//! returns `None` and the caller interprets as before.
+pub mod decode;
+#[cfg(all(target_arch = "x86_64", unix))]
+mod emit;
+#[cfg(all(target_arch = "x86_64", unix))]
+mod exec;
+
+pub use decode::{AluInstr, AluOp, Operand, Shift, decode_arm, decode_thumb};
+
+/// Guest registers and flags as compiled code sees them.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JitState {
+    pub regs: [u32; 16],
+    pub negative: bool,
+    pub zero: bool,
+    pub carry: bool,
+    pub overflow: bool,
+}
+
+/// A run of instructions compiled to host code.
+pub struct CompiledBlock {
+    #[cfg(all(target_arch = "x86_64", unix))]
+    code: exec::Code,
+    instructions: usize,
+}
+
+impl std::fmt::Debug for CompiledBlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompiledBlock")
+            .field("instructions", &self.instructions)
+            .finish()
+    }
+}
+
+impl CompiledBlock {
+    /// Guest instructions compiled
+    pub const fn instructions(&self) -> usize {
+        self.instructions
+    }
+
+    /// Run the instructions on `state`.
+    pub fn run(&self, state: &mut JitState) {
+        #[cfg(all(target_arch = "x86_64", unix))]
+        self.code.run(state);
+        #[cfg(not(all(target_arch = "x86_64", unix)))]
+        let _ = state;
+    }
+}
+
+/// Compile `instrs`, `None` if the host has no code generator or will not
+/// map executable memory.
+pub fn compile(instrs: &[AluInstr]) -> Option<CompiledBlock> {
+    #[cfg(all(target_arch = "x86_64", unix))]
+    {
+        Some(CompiledBlock {
+            code: exec::Code::new(&emit::emit(instrs))?,
+            instructions: instrs.len(),
+        })
+    }
+    #[cfg(not(all(target_arch = "x86_64", unix)))]
+    {
+        let _ = instrs;
+        None
+    }
+}
+
+#[cfg(all(test, target_arch = "x86_64", unix))]
+mod tests {
+    use lunaris_ds_test_support::{AluFlags, arm_alu_reference};
+
+    use super::*;
+
+    const EDGES: [u32; 6] = [0, 1, 0x7FFF_FFFF, 0x8000_0000, 0x8000_0001, 0xFFFF_FFFF];
+
+    const fn flags(state: &JitState) -> AluFlags {
+        AluFlags {
+            negative: state.negative,
+            zero: state.zero,
+            carry: state.carry,
+            overflow: state.overflow,
+        }
+    }
+
+    #[test]
+    fn test_alu_matches_reference() {
+        // <op>S r2, r0, r1 for every opcode, one function each
+        let blocks: Vec<_> = (0..16)
+            .map(|opcode| {
+                let instr = decode_arm(0xE010_2001 | opcode << 21);
+                compile(&[instr.unwrap_or_else(|| panic!("opcode {opcode:X}"))])
+                    .unwrap_or_else(|| panic!("opcode {opcode:X} not compiled"))
+            })
+            .collect();
+
+        for (opcode, block) in (0..16).zip(&blocks) {
+            for &a in &EDGES {
+                for &b in &EDGES {
+                    for flags_in in 0..4 {
+                        let mut state = JitState {
+                            carry: flags_in & 1 != 0,
+                            overflow: flags_in & 2 != 0,
+                            ..JitState::default()
+                        };
+                        state.regs[0] = a;
+                        state.regs[1] = b;
+                        state.regs[2] = 0xDEAD_BEEF;
+                        let flags_in = flags(&state);
+                        block.run(&mut state);
+
+                        let expected = arm_alu_reference(opcode, a, b, flags_in, flags_in.carry);
+                        let context =
+                            format!("opcode {opcode:X}, {a:#010X}, {b:#010X}, {flags_in:?}");
+                        assert_eq!(flags(&state), expected.flags, "{context}");
+                        let value = expected.value.unwrap_or(0xDEAD_BEEF);
+                        assert_eq!(state.regs[2], value, "{context}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        // movs r0, #0x8000_0000; mov r1, r0, asr #4; add r2, r1, r0, lsr #28;
+        // subs r3, r2, #8
+        let instrs = [0xE3B0_0102, 0xE1A0_1240, 0xE081_2E20, 0xE252_3008].map(|instruction| {
+            decode_arm(instruction).unwrap_or_else(|| panic!("{instruction:#010X}"))
+        });
+        let block = compile(&instrs).unwrap_or_else(|| panic!("not compiled"));
+        assert_eq!(block.instructions(), 4);
+
+        let mut state = JitState::default();
+        block.run(&mut state);
+        assert_eq!(
+            state.regs[..4],
+            [0x8000_0000, 0xF800_0000, 0xF800_0008, 0xF800_0000]
+        );
+        assert_eq!(
+            flags(&state),
+            AluFlags {
+                negative: true,
+                zero: false,
+                carry: true,
+                overflow: false,
+            }
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Placement of the two screens in the frame handed to the frontend
+use lunaris_ds_mem_const::{PIXELS_PER_LINE, SCANLINES};
+
+/// Core option choosing the layout, first value the default.
+pub const LAYOUT_OPTION: &std::ffi::CStr = c"lunaris_screen_layout";
+pub const LAYOUT_OPTION_DESCRIPTION: &std::ffi::CStr =
+    c"Screen layout; top/bottom|bottom/top|left/right|right/left";
+
+/// Largest pointer coordinate; -0x7FFF..=0x7FFF spans the frame.
+const POINTER_MAX: i32 = 0x7FFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    #[default]
+    TopBottom,
+    BottomTop,
+    LeftRight,
+    RightLeft,
+}
+
+impl Layout {
+    pub fn from_option(value: &str) -> Option<Self> {
+        match value {
+            "top/bottom" => Some(Self::TopBottom),
+            "bottom/top" => Some(Self::BottomTop),
+            "left/right" => Some(Self::LeftRight),
+            "right/left" => Some(Self::RightLeft),
+            _ => None,
+        }
+    }
+
+    /// Width and height of the frame.
+    pub const fn size(self) -> (usize, usize) {
+        match self {
+            Self::TopBottom | Self::BottomTop => (PIXELS_PER_LINE, SCANLINES * 2),
+            Self::LeftRight | Self::RightLeft => (PIXELS_PER_LINE * 2, SCANLINES),
+        }
+    }
+
+    /// Top left corners of the upper and lower screen.
+    const fn origins(self) -> [(usize, usize); 2] {
+        match self {
+            Self::TopBottom => [(0, 0), (0, SCANLINES)],
+            Self::BottomTop => [(0, SCANLINES), (0, 0)],
+            Self::LeftRight => [(0, 0), (PIXELS_PER_LINE, 0)],
+            Self::RightLeft => [(PIXELS_PER_LINE, 0), (0, 0)],
+        }
+    }
+
+    /// Place both screens in `frame`, resized to [`Layout::size`].
+    pub fn compose(self, upper: &[u32], lower: &[u32], frame: &mut Vec<u32>) {
+        let (width, height) = self.size();
+        frame.resize(width * height, 0);
+        for (screen, (x, y)) in [upper, lower].into_iter().zip(self.origins()) {
+            for (line, pixels) in screen.chunks_exact(PIXELS_PER_LINE).enumerate() {
+                let start = (y + line) * width + x;
+                frame[start..start + PIXELS_PER_LINE].copy_from_slice(pixels);
+            }
+        }
+    }
+
+    /// Touchscreen point under a pointer position, `None` off the lower
+    /// screen.
+    pub fn touch_point(self, x: i16, y: i16) -> Option<(i32, i32)> {
+        let (width, height) = self.size();
+        let to_pixel =
+            |pos: i16, len: usize| (pos as i32 + POINTER_MAX) * len as i32 / (POINTER_MAX * 2 + 1);
+        let (lower_x, lower_y) = self.origins()[1];
+        let x = to_pixel(x, width) - lower_x as i32;
+        let y = to_pixel(y, height) - lower_y as i32;
+        let on_screen =
+            (0..PIXELS_PER_LINE as i32).contains(&x) && (0..SCANLINES as i32).contains(&y);
+        on_screen.then_some((x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let upper = vec![1; PIXELS_PER_LINE * SCANLINES];
+        let lower = vec![2; PIXELS_PER_LINE * SCANLINES];
+        let mut frame = Vec::new();
+        Layout::RightLeft.compose(&upper, &lower, &mut frame);
+        assert_eq!(frame.len(), PIXELS_PER_LINE * SCANLINES * 2);
+        assert_eq!(frame[0], 2);
+        assert_eq!(frame[PIXELS_PER_LINE], 1);
+        assert_eq!(frame[PIXELS_PER_LINE * 2 * SCANLINES - 1], 1);
+
+        assert_eq!(Layout::from_option("bottom/top"), Some(Layout::BottomTop));
+        assert_eq!(Layout::TopBottom.touch_point(0, -0x7FFF), None);
+        assert_eq!(Layout::TopBottom.touch_point(-0x7FFF, 1), Some((0, 0)));
+        assert_eq!(
+            Layout::LeftRight.touch_point(0x7FFF, 0x7FFF),
+            Some((255, 191))
+        );
+    }
+}
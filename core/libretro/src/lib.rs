@@ -0,0 +1,591 @@
+//! libretro core
+//!
+//! Exposes the emulator through the libretro API, so RetroArch and other
+//! libretro frontends can run it without a frontend of its own. The frontend
+//! callbacks are wrapped as the emulator's [`VideoSink`], [`AudioSink`] and
+//! [`InputSource`], so `retro_run` is one [`Emulator::run`]:
+//!
+//! - both screens go out in one frame, laid out by the
+//!   `lunaris_screen_layout` core option
+//! - the SPU output is pushed in batches at [`DS_AUDIO_RATE`]
+//! - the joypad maps to the DS buttons and the pointer to the touchscreen
+//! - savestates, cheats (Action Replay codes) and reset are supported
+//!
+//! The ROM is loaded from its path, like the standalone frontends do.
+mod ffi;
+mod layout;
+
+use std::ffi::{CStr, c_char, c_uint, c_void};
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+use lunaris_ds_emu::{
+    AudioSink, Button, Cheat, DS_AUDIO_RATE, DS_FRAME_RATE, Emulator, InputSource, InputState,
+    Timestamps, VideoSink,
+};
+
+use crate::ffi::*;
+use crate::layout::{LAYOUT_OPTION, LAYOUT_OPTION_DESCRIPTION, Layout};
+
+const LIBRARY_VERSION: &CStr =
+    match CStr::from_bytes_with_nul(concat!(env!("CARGO_PKG_VERSION"), "\0").as_bytes()) {
+        Ok(version) => version,
+        Err(_) => c"",
+    };
+
+/// Joypad buttons of port 0 and the DS buttons they press.
+const JOYPAD: [(c_uint, Button); 12] = [
+    (RETRO_DEVICE_ID_JOYPAD_A, Button::A),
+    (RETRO_DEVICE_ID_JOYPAD_B, Button::B),
+    (RETRO_DEVICE_ID_JOYPAD_X, Button::X),
+    (RETRO_DEVICE_ID_JOYPAD_Y, Button::Y),
+    (RETRO_DEVICE_ID_JOYPAD_L, Button::L),
+    (RETRO_DEVICE_ID_JOYPAD_R, Button::R),
+    (RETRO_DEVICE_ID_JOYPAD_START, Button::Start),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, Button::Select),
+    (RETRO_DEVICE_ID_JOYPAD_UP, Button::Up),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, Button::Down),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, Button::Left),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, Button::Right),
+];
+
+/// Callbacks the frontend registered.
+#[derive(Debug, Clone, Copy)]
+struct Callbacks {
+    environment: Option<RetroEnvironment>,
+    video_refresh: Option<RetroVideoRefresh>,
+    audio_batch: Option<RetroAudioSampleBatch>,
+    input_poll: Option<RetroInputPoll>,
+    input_state: Option<RetroInputState>,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+
+/// The loaded game.
+#[derive(Debug)]
+struct Core {
+    emu: Box<Emulator>,
+    rom: PathBuf,
+    layout: Layout,
+    /// Size reported for states, see [`Emulator::max_state_size`]
+    state_size: usize,
+}
+
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+
+fn callbacks() -> Callbacks {
+    *CALLBACKS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn set_callbacks(f: impl FnOnce(&mut Callbacks)) {
+    f(&mut CALLBACKS.lock().unwrap_or_else(PoisonError::into_inner));
+}
+
+/// Run `f` on the loaded game, `None` without one.
+fn with_core<R>(f: impl FnOnce(&mut Core) -> R) -> Option<R> {
+    CORE.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_mut()
+        .map(f)
+}
+
+/// Presents frames through `retro_video_refresh`.
+#[derive(Debug)]
+struct RetroVideo {
+    refresh: RetroVideoRefresh,
+    layout: Layout,
+    frame: Vec<u32>,
+}
+
+impl VideoSink for RetroVideo {
+    fn present(&mut self, upper: &[u32], lower: &[u32], _time: Timestamps) {
+        self.layout.compose(upper, lower, &mut self.frame);
+        let (width, height) = self.layout.size();
+        // SAFETY: the frame is width * height XRGB8888 pixels, as set up in
+        // retro_load_game, and the frontend copies it during the call
+        unsafe {
+            (self.refresh)(
+                self.frame.as_ptr().cast(),
+                width as c_uint,
+                height as c_uint,
+                width * 4,
+            );
+        }
+    }
+}
+
+/// Pushes audio through `retro_audio_sample_batch`.
+#[derive(Debug)]
+struct RetroAudio {
+    batch: RetroAudioSampleBatch,
+}
+
+impl AudioSink for RetroAudio {
+    fn push_samples(&mut self, mut samples: &[[i16; 2]]) {
+        while !samples.is_empty() {
+            // SAFETY: `samples` holds `len` interleaved stereo frames
+            let taken = unsafe { (self.batch)(samples.as_flattened().as_ptr(), samples.len()) };
+            if taken == 0 {
+                break;
+            }
+            samples = &samples[taken.min(samples.len())..];
+        }
+    }
+}
+
+/// Reads port 0 through `retro_input_poll` and `retro_input_state`.
+#[derive(Debug)]
+struct RetroInput {
+    poll: RetroInputPoll,
+    state: RetroInputState,
+    layout: Layout,
+}
+
+impl RetroInput {
+    fn state(&self, device: c_uint, id: c_uint) -> i16 {
+        // SAFETY: plain frontend callback
+        unsafe { (self.state)(0, device, 0, id) }
+    }
+}
+
+impl InputSource for RetroInput {
+    /// Polls the frontend, the emulator asks for the buttons first.
+    fn buttons(&mut self) -> InputState {
+        // SAFETY: plain frontend callback
+        unsafe { (self.poll)() };
+        let mut input = InputState::default();
+        for (id, button) in JOYPAD {
+            input.set_held(button, self.state(RETRO_DEVICE_JOYPAD, id) != 0);
+        }
+        input
+    }
+
+    fn touch(&mut self) -> Option<(i32, i32)> {
+        if self.state(RETRO_DEVICE_POINTER, RETRO_DEVICE_ID_POINTER_PRESSED) == 0 {
+            return None;
+        }
+        let x = self.state(RETRO_DEVICE_POINTER, RETRO_DEVICE_ID_POINTER_X);
+        let y = self.state(RETRO_DEVICE_POINTER, RETRO_DEVICE_ID_POINTER_Y);
+        self.layout.touch_point(x, y)
+    }
+}
+
+impl Core {
+    /// Route the emulator's output and input through the frontend.
+    fn connect(&mut self, callbacks: &Callbacks) {
+        let layout = self.layout;
+        self.emu
+            .set_video_sink(callbacks.video_refresh.map(|refresh| {
+                Box::new(RetroVideo {
+                    refresh,
+                    layout,
+                    frame: Vec::new(),
+                }) as Box<dyn VideoSink>
+            }));
+        self.emu.set_audio_sink(
+            callbacks
+                .audio_batch
+                .map(|batch| Box::new(RetroAudio { batch }) as Box<dyn AudioSink>),
+        );
+        let input = callbacks.input_poll.zip(callbacks.input_state);
+        self.emu.set_input_source(input.map(|(poll, state)| {
+            Box::new(RetroInput {
+                poll,
+                state,
+                layout,
+            }) as Box<dyn InputSource>
+        }));
+    }
+
+    /// Write the state to `out`, zero padded to its end, so every state
+    /// takes the size reported once.
+    fn serialize(&self, out: &mut [u8]) -> bool {
+        let state = self.emu.save_state();
+        let Some((head, padding)) = out.split_at_mut_checked(state.len()) else {
+            return false;
+        };
+        head.copy_from_slice(&state);
+        padding.fill(0);
+        true
+    }
+}
+
+/// Value of a core option.
+fn variable(environment: RetroEnvironment, key: &CStr) -> Option<String> {
+    let mut variable = RetroVariable {
+        key: key.as_ptr(),
+        value: std::ptr::null(),
+    };
+    // SAFETY: the frontend fills in `value` with a string it keeps alive
+    unsafe {
+        let found = environment(RETRO_ENVIRONMENT_GET_VARIABLE, (&raw mut variable).cast());
+        if !found || variable.value.is_null() {
+            return None;
+        }
+        Some(
+            CStr::from_ptr(variable.value)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+fn layout_option(callbacks: &Callbacks) -> Layout {
+    callbacks
+        .environment
+        .and_then(|environment| variable(environment, LAYOUT_OPTION))
+        .and_then(|value| Layout::from_option(&value))
+        .unwrap_or_default()
+}
+
+fn geometry(layout: Layout) -> RetroGameGeometry {
+    let (width, height) = layout.size();
+    let max = [Layout::TopBottom, Layout::LeftRight].map(Layout::size);
+    RetroGameGeometry {
+        base_width: width as c_uint,
+        base_height: height as c_uint,
+        max_width: max[1].0 as c_uint,
+        max_height: max[0].1 as c_uint,
+        aspect_ratio: width as f32 / height as f32,
+    }
+}
+
+/// Pick up a changed screen layout.
+fn update_options(callbacks: &Callbacks) {
+    let Some(environment) = callbacks.environment else {
+        return;
+    };
+    let mut updated = false;
+    // SAFETY: the frontend writes one bool
+    let ok = unsafe {
+        environment(
+            RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+            (&raw mut updated).cast(),
+        )
+    };
+    if !ok || !updated {
+        return;
+    }
+    let layout = layout_option(callbacks);
+    with_core(|core| {
+        if core.layout != layout {
+            core.layout = layout;
+            core.connect(callbacks);
+            let mut geometry = geometry(layout);
+            // SAFETY: the frontend copies the geometry during the call
+            unsafe {
+                environment(RETRO_ENVIRONMENT_SET_GEOMETRY, (&raw mut geometry).cast());
+            }
+        }
+    });
+}
+
+#[unsafe(no_mangle)]
+pub const extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[unsafe(no_mangle)]
+pub const extern "C" fn retro_init() {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_deinit() {
+    *CORE.lock().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// # Safety
+///
+/// `environment` must be a valid libretro environment callback.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_set_environment(environment: RetroEnvironment) {
+    set_callbacks(|callbacks| callbacks.environment = Some(environment));
+    let mut variables = [
+        RetroVariable {
+            key: LAYOUT_OPTION.as_ptr(),
+            value: LAYOUT_OPTION_DESCRIPTION.as_ptr(),
+        },
+        RetroVariable {
+            key: std::ptr::null(),
+            value: std::ptr::null(),
+        },
+    ];
+    // SAFETY: a null terminated array of static strings
+    unsafe {
+        environment(
+            RETRO_ENVIRONMENT_SET_VARIABLES,
+            variables.as_mut_ptr().cast(),
+        );
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_video_refresh(refresh: RetroVideoRefresh) {
+    set_callbacks(|callbacks| callbacks.video_refresh = Some(refresh));
+}
+
+/// Unused, audio goes out in batches.
+#[unsafe(no_mangle)]
+pub const extern "C" fn retro_set_audio_sample(_sample: RetroAudioSample) {}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_audio_sample_batch(batch: RetroAudioSampleBatch) {
+    set_callbacks(|callbacks| callbacks.audio_batch = Some(batch));
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_poll(poll: RetroInputPoll) {
+    set_callbacks(|callbacks| callbacks.input_poll = Some(poll));
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_set_input_state(state: RetroInputState) {
+    set_callbacks(|callbacks| callbacks.input_state = Some(state));
+}
+
+#[unsafe(no_mangle)]
+pub const extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+/// # Safety
+///
+/// `info` must point to a writable `retro_system_info`.
+#[unsafe(no_mangle)]
+pub const unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    if info.is_null() {
+        return;
+    }
+    // SAFETY: checked for null, valid per the contract
+    unsafe {
+        info.write(RetroSystemInfo {
+            library_name: c"Lunaris".as_ptr(),
+            library_version: LIBRARY_VERSION.as_ptr(),
+            valid_extensions: c"nds|srl".as_ptr(),
+            need_fullpath: true,
+            block_extract: false,
+        });
+    }
+}
+
+/// # Safety
+///
+/// `info` must point to a writable `retro_system_av_info`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    if info.is_null() {
+        return;
+    }
+    let layout = with_core(|core| core.layout).unwrap_or_default();
+    // SAFETY: checked for null, valid per the contract
+    unsafe {
+        info.write(RetroSystemAvInfo {
+            geometry: geometry(layout),
+            timing: RetroSystemTiming {
+                fps: DS_FRAME_RATE,
+                sample_rate: DS_AUDIO_RATE,
+            },
+        });
+    }
+}
+
+/// # Safety
+///
+/// `game` must be null or point to a valid `retro_game_info`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    // SAFETY: valid per the contract, the path a C string
+    let rom = unsafe {
+        match game.as_ref() {
+            Some(game) if !game.path.is_null() => CStr::from_ptr(game.path),
+            _ => return false,
+        }
+    };
+    let rom = PathBuf::from(rom.to_string_lossy().into_owned());
+
+    let callbacks = callbacks();
+    let Some(environment) = callbacks.environment else {
+        return false;
+    };
+    let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+    // SAFETY: the frontend reads one enum value
+    if !unsafe { environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, (&raw mut format).cast()) } {
+        return false;
+    }
+
+    let mut emu = Box::new(Emulator::new());
+    if emu.load_rom(&rom).is_err() {
+        return false;
+    }
+    let mut core = Core {
+        state_size: emu.max_state_size(),
+        emu,
+        rom,
+        layout: layout_option(&callbacks),
+    };
+    core.connect(&callbacks);
+    *CORE.lock().unwrap_or_else(PoisonError::into_inner) = Some(core);
+    true
+}
+
+#[unsafe(no_mangle)]
+pub const extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_unload_game() {
+    *CORE.lock().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+#[unsafe(no_mangle)]
+pub const extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+/// Boot the game again, reloading the ROM.
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_reset() {
+    with_core(|core| {
+        let _ = core.emu.load_rom(&core.rom);
+    });
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_run() {
+    let callbacks = callbacks();
+    update_options(&callbacks);
+    with_core(|core| core.emu.run());
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_serialize_size() -> usize {
+    with_core(|core| core.state_size).unwrap_or_default()
+}
+
+/// # Safety
+///
+/// `data` must point to `size` writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+    // SAFETY: `size` bytes at `data` per the contract
+    let out = unsafe { std::slice::from_raw_parts_mut(data.cast::<u8>(), size) };
+    with_core(|core| core.serialize(out)).unwrap_or(false)
+}
+
+/// # Safety
+///
+/// `data` must point to `size` readable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    if data.is_null() {
+        return false;
+    }
+    // SAFETY: `size` bytes at `data` per the contract
+    let state = unsafe { std::slice::from_raw_parts(data.cast::<u8>(), size) };
+    with_core(|core| core.emu.load_state(state).is_ok()).unwrap_or(false)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn retro_cheat_reset() {
+    with_core(|core| while core.emu.remove_cheat(0).is_some() {});
+}
+
+/// Add an Action Replay code; words may also be joined with `+`.
+///
+/// # Safety
+///
+/// `code` must be null or a valid C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn retro_cheat_set(index: c_uint, enabled: bool, code: *const c_char) {
+    if code.is_null() {
+        return;
+    }
+    // SAFETY: a C string per the contract
+    let code = unsafe { CStr::from_ptr(code) }
+        .to_string_lossy()
+        .replace('+', " ");
+    let Ok(mut cheat) = Cheat::parse(&format!("Cheat {index}"), &code) else {
+        return;
+    };
+    cheat.enabled = enabled;
+    with_core(|core| core.emu.add_cheat(cheat));
+}
+
+/// No memory regions are exposed; saves are kept next to the ROM.
+#[unsafe(no_mangle)]
+pub const extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[unsafe(no_mangle)]
+pub const extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use lunaris_ds_emu::CpuType;
+
+    use super::*;
+
+    /// GXFIFO command port of `BEGIN_VTXS`, `VTX_16` and `SWAP_BUFFERS`
+    const BEGIN_VTXS: u32 = 0x0400_0500;
+    const VTX_16: u32 = 0x0400_048C;
+    const SWAP_BUFFERS: u32 = 0x0400_0540;
+
+    #[test]
+    fn test_serialize_size_is_fixed() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.run();
+        let mut core = Core {
+            state_size: emu.max_state_size(),
+            emu,
+            rom: PathBuf::new(),
+            layout: Layout::default(),
+        };
+        let mut out = vec![0xAA; core.state_size];
+        let mut largest = 0;
+
+        // Mid-frame geometry: triangles queued after SWAP_BUFFERS pile up
+        // in the FIFO until VBlank
+        core.emu.write_word(SWAP_BUFFERS, 0, CpuType::Arm9);
+        for vertex in 0..0x180 {
+            if vertex % 3 == 0 {
+                core.emu.write_word(BEGIN_VTXS, 0, CpuType::Arm9);
+            }
+            core.emu.write_word(VTX_16, vertex, CpuType::Arm9);
+            core.emu.write_word(VTX_16, 0, CpuType::Arm9);
+            if vertex % 0x20 != 0 {
+                continue;
+            }
+
+            largest = largest.max(core.emu.save_state().len());
+            assert!(
+                core.serialize(&mut out),
+                "state {largest} > {}",
+                core.state_size
+            );
+            assert_eq!(core.emu.max_state_size(), core.state_size);
+        }
+        assert!(core.emu.gpu.engine_3d.gxfifo.len() > 256);
+        assert!(out.ends_with(&[0]));
+
+        // The padded state loads, and running it grows the geometry lists
+        core.emu.load_state(&out).unwrap();
+        core.emu.run();
+        assert!(core.serialize(&mut out));
+        assert!(largest <= core.state_size);
+    }
+}
@@ -0,0 +1,68 @@
+//! Texture upload benchmark
+//!
+//! Uploads a 128KB texture to LCDC VRAM bank A the way an `LDMIA`/`STMIA`
+//! copy loop does (8 registers per store), once word by word and once through
+//! the write-combined block store.
+//!
+//! ```sh
+//! cargo bench -p lunaris_ds_emu --bench vram_upload
+//! ```
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use lunaris_ds_emu::Emulator;
+use lunaris_ds_mem_const::VRAM_LCDC_A;
+
+const TEXTURE_SIZE: u32 = 128 * 1024;
+/// Registers per STM
+const STM_WORDS: usize = 8;
+const ITERATIONS: u32 = 50;
+
+fn upload(emu: &mut Emulator, texture: &[u32], combined: bool) {
+    for (i, chunk) in texture.chunks(STM_WORDS).enumerate() {
+        let address = VRAM_LCDC_A + (i * STM_WORDS * 4) as u32;
+        match combined {
+            true => emu.arm9_write_words(address, chunk),
+            false => {
+                for (j, &word) in chunk.iter().enumerate() {
+                    emu.arm9_write_word(address + j as u32 * 4, word);
+                }
+            }
+        }
+    }
+}
+
+fn bench(name: &str, emu: &mut Emulator, texture: &[u32], combined: bool) -> Duration {
+    upload(emu, texture, combined);
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        upload(emu, black_box(texture), combined);
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+
+    let mib_per_sec = TEXTURE_SIZE as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64();
+    println!("{name:>10}: {elapsed:>10.2?} per 128KB upload ({mib_per_sec:.1} MiB/s)");
+    elapsed
+}
+
+fn main() {
+    let mut emu = Box::new(Emulator::new());
+    // Bank A to LCDC
+    emu.arm9_write_byte(0x0400_0240, 0x80);
+
+    let texture: Vec<u32> = (0..TEXTURE_SIZE / 4)
+        .map(|i| i.wrapping_mul(0x9E37_79B9))
+        .collect();
+
+    let per_word = bench("per word", &mut emu, &texture, false);
+    let combined = bench("combined", &mut emu, &texture, true);
+    println!(
+        "   speedup: {:.1}x",
+        per_word.as_secs_f64() / combined.as_secs_f64()
+    );
+
+    for (i, &word) in texture.iter().enumerate().step_by(997) {
+        assert_eq!(emu.arm9_read_word(VRAM_LCDC_A + i as u32 * 4), word);
+    }
+}
@@ -0,0 +1,137 @@
+//! Run a raw ARM or Thumb code blob on the interpreter alone
+//!
+//! The blob is loaded at address 0 of a flat 1MB RAM and stepped until the
+//! PC leaves it, it branches to itself, or the step limit is hit. Registers
+//! are printed after every instruction, with its disassembly.
+//! Without a file a small built-in program summing 1 to 10 is run.
+//!
+//! ```sh
+//! cargo run -p lunaris_ds_emu --no-default-features --example step_blob -- \
+//!     code.bin [--arm7] [--thumb] [--steps N]
+//! ```
+use lunaris_ds_emu::{ArmCpu, Bus, CpuType, PsrMode, disasm_arm, disasm_thumb, step};
+
+const RAM_SIZE: usize = 1024 * 1024;
+
+/// mov r0, #0; mov r1, #10; loop: add r0, r0, r1; subs r1, r1, #1;
+/// bne loop; b .
+const DEMO: [u32; 6] = [
+    0xE3A0_0000,
+    0xE3A0_100A,
+    0xE080_0001,
+    0xE251_1001,
+    0x1AFF_FFFC,
+    0xEAFF_FFFE,
+];
+
+/// One core and a flat RAM, mirrored every megabyte.
+struct FlatBus {
+    cpu: ArmCpu,
+    ram: Vec<u8>,
+}
+
+impl FlatBus {
+    fn index(address: u32) -> usize {
+        address as usize % RAM_SIZE
+    }
+}
+
+impl Bus for FlatBus {
+    fn get_cpu(&self, _cpu_type: CpuType) -> &ArmCpu {
+        &self.cpu
+    }
+
+    fn get_cpu_mut(&mut self, _cpu_type: CpuType) -> &mut ArmCpu {
+        &mut self.cpu
+    }
+
+    fn read_word(&mut self, address: u32, _cpu_type: CpuType) -> u32 {
+        let i = Self::index(address & !3);
+        u32::from_le_bytes(self.ram[i..i + 4].try_into().unwrap())
+    }
+
+    fn read_halfword(&mut self, address: u32, _cpu_type: CpuType) -> u16 {
+        let i = Self::index(address & !1);
+        u16::from_le_bytes([self.ram[i], self.ram[i + 1]])
+    }
+
+    fn read_byte(&mut self, address: u32, _cpu_type: CpuType) -> u8 {
+        self.ram[Self::index(address)]
+    }
+
+    fn write_word(&mut self, address: u32, word: u32, _cpu_type: CpuType) {
+        let i = Self::index(address & !3);
+        self.ram[i..i + 4].copy_from_slice(&word.to_le_bytes());
+    }
+
+    fn write_halfword(&mut self, address: u32, halfword: u16, _cpu_type: CpuType) {
+        let i = Self::index(address & !1);
+        self.ram[i..i + 2].copy_from_slice(&halfword.to_le_bytes());
+    }
+
+    fn write_byte(&mut self, address: u32, byte: u8, _cpu_type: CpuType) {
+        self.ram[Self::index(address)] = byte;
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut path = None;
+    let mut cpu_type = CpuType::Arm9;
+    let mut thumb = false;
+    let mut steps = 1000;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--arm7" => cpu_type = CpuType::Arm7,
+            "--thumb" => thumb = true,
+            "--steps" => {
+                steps = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .expect("--steps takes a number")
+            }
+            _ => path = Some(arg),
+        }
+    }
+
+    let blob = match &path {
+        Some(path) => std::fs::read(path).expect("failed to read the code blob"),
+        None => DEMO.iter().flat_map(|word| word.to_le_bytes()).collect(),
+    };
+    assert!(blob.len() <= RAM_SIZE, "the blob does not fit in RAM");
+
+    let cpu_id = match cpu_type {
+        CpuType::Arm9 => 0,
+        CpuType::Arm7 => 1,
+    };
+    let mut bus = FlatBus {
+        cpu: ArmCpu::new(cpu_id, cpu_type),
+        ram: vec![0; RAM_SIZE],
+    };
+    bus.ram[..blob.len()].copy_from_slice(&blob);
+    bus.cpu.power_on();
+    bus.cpu.update_reg_mode(PsrMode::System);
+    bus.cpu.cpsr.mode = PsrMode::System;
+    bus.cpu.jp(thumb as u32, true);
+
+    let width = if thumb { 2 } else { 4 };
+    for _ in 0..steps {
+        // The PC is a fetch ahead of the next instruction
+        let address = bus.cpu.get_pc().wrapping_sub(width);
+        if address as usize >= blob.len() {
+            break;
+        }
+        let disasm = match thumb {
+            true => disasm_thumb(bus.read_halfword(address, cpu_type), address),
+            false => disasm_arm(bus.read_word(address, cpu_type), address),
+        };
+        step(&mut bus, cpu_type);
+
+        let regs: Vec<String> = bus.cpu.regs.iter().map(|r| format!("{r:08X}")).collect();
+        println!("{address:08X}: {disasm:<24} {}", regs.join(" "));
+        if bus.cpu.get_pc().wrapping_sub(width) == address {
+            break;
+        }
+    }
+    println!("{} cycles", bus.cpu.get_timestamp());
+}
@@ -0,0 +1,9 @@
+//! Arbitrary Thumb instructions on either CPU, see `lunaris_ds_emu::fuzz`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lunaris_ds_emu::fuzz::{FuzzInput, run};
+
+fuzz_target!(|data: &[u8]| {
+    run(&FuzzInput::from_bytes(data, true)).check();
+});
@@ -1,12 +1,89 @@
 // SPDX-FileCopyrightText: (C) 2017 PSISP
 // SPDX-License-Identifier: GPL-3.0-or-later
-use crate::cpu::arm_cpu::CpuType;
+use crate::cpu::arm_cpu::{CpuType, PsrMode, REG_LR, REG_SP, Reg};
 use crate::emulator::Emulator;
 
+/// Offset of the HLE IRQ return stub from the exception base.
+///
+/// The FIQ vector, which the DS never raises. A handler returns here and
+/// [`Emulator::hle_irq_return`] unwinds what the BIOS dispatcher pushed.
+const IRQ_RETURN_VECTOR: u32 = 0x1C;
+
+/// Registers the BIOS IRQ dispatcher saves on the IRQ stack.
+const IRQ_SAVED_REGS: [Reg; 6] = [
+    Reg::new(0),
+    Reg::new(1),
+    Reg::new(2),
+    Reg::new(3),
+    Reg::new(12),
+    REG_LR,
+];
+
 impl Emulator {
+    /// Take an IRQ on `cpu_type`.
+    ///
+    /// With [`Config::hle_bios`](crate::Config::hle_bios) this also does what
+    /// the BIOS IRQ vector does, so direct booted games that install their
+    /// handler (e.g. through libnds `irqSet`) get it called without a BIOS
+    /// image.
+    pub(crate) fn enter_irq(&mut self, cpu_type: CpuType) {
+        self.get_cpu_mut(cpu_type).handle_irq();
+        if self.config.hle_bios {
+            self.hle_irq_dispatch(cpu_type);
+        }
+    }
+
+    /// Address a handler dispatched by [`Self::hle_irq_dispatch`] returns to.
+    pub(crate) fn hle_irq_return_address(&self, cpu_type: CpuType) -> u32 {
+        self.get_cpu(cpu_type).get_exception_base() + IRQ_RETURN_VECTOR
+    }
+
+    /// BIOS IRQ vector: push r0-r3, r12 and lr, then call the handler whose
+    /// address the game stored at the end of DTCM (ARM9) or shared WRAM
+    /// (ARM7, 0x03FFFFFC).
+    fn hle_irq_dispatch(&mut self, cpu_type: CpuType) {
+        let sp = self.get_cpu(cpu_type).get_register(REG_SP).wrapping_sub(24);
+        for (i, reg) in IRQ_SAVED_REGS.into_iter().enumerate() {
+            let value = self.get_cpu(cpu_type).get_register(reg);
+            self.write_word(sp + i as u32 * 4, value, cpu_type);
+        }
+
+        let handler_ptr = match cpu_type {
+            CpuType::Arm9 => self.arm9_cp15.get_dtcm_base() + 0x3FFC,
+            CpuType::Arm7 => 0x03FF_FFFC,
+        };
+        let handler = self.read_word(handler_ptr, cpu_type);
+        let return_address = self.hle_irq_return_address(cpu_type);
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!("HLE IRQ dispatch to {handler:08X} on {cpu_type:?}");
+
+        let arm = self.get_cpu_mut(cpu_type);
+        arm.set_register(REG_SP, sp);
+        arm.set_register(REG_LR, return_address);
+        // `ldr pc` only interworks on the ARMv5 ARM9
+        arm.jp(handler, cpu_type == CpuType::Arm9);
+    }
+
+    /// Return from a handler called by [`Self::hle_irq_dispatch`]: pop the
+    /// saved registers and `subs pc, lr, #4` back to the interrupted code.
+    pub(crate) fn hle_irq_return(&mut self, cpu_type: CpuType) {
+        let sp = self.get_cpu(cpu_type).get_register(REG_SP);
+        for (i, reg) in IRQ_SAVED_REGS.into_iter().enumerate() {
+            let value = self.read_word(sp + i as u32 * 4, cpu_type);
+            self.get_cpu_mut(cpu_type).set_register(reg, value);
+        }
+
+        let arm = self.get_cpu_mut(cpu_type);
+        arm.set_register(REG_SP, sp.wrapping_add(24));
+        let lr = arm.get_register(REG_LR);
+        arm.spsr_to_cpsr();
+        arm.jp(lr.wrapping_sub(4), false);
+    }
+
     /// Retrieves the SWI opcode using the ARM CPU's LR logic.
     /// The handler compensates based on ARM vs THUMB mode.
-    fn get_opcode(&self, cpu_type: CpuType) -> u8 {
+    fn get_opcode(&mut self, cpu_type: CpuType) -> u8 {
         let mut lr = self.get_cpu(cpu_type).get_pc();
         if self.get_cpu(cpu_type).get_cpsr().thumb_on {
             lr -= 2;
@@ -17,28 +94,42 @@ impl Emulator {
         (self.read_halfword(lr - 2, cpu_type) & 0xFF) as u8
     }
 
+    /// Argument `reg` the SWI was called with.
+    fn swi_arg(&self, cpu_type: CpuType, reg: u32) -> u32 {
+        self.get_cpu(cpu_type).get_register(Reg::new(reg))
+    }
+
     /// Implements the BIOS signed division routine.
     /// r0 = quotient, r1 = remainder, r3 = |quotient|
-    #[expect(unused)]
     fn div(&mut self, cpu_type: CpuType) {
-        let dividend = self.get_cpu(cpu_type).get_register(0) as i32;
-        let divisor = self.get_cpu(cpu_type).get_register(1) as i32;
+        let dividend = self.swi_arg(cpu_type, 0) as i32;
+        let divisor = self.swi_arg(cpu_type, 1) as i32;
+        // The BIOS hangs dividing by zero, leave the registers alone instead
+        if divisor == 0 {
+            return;
+        }
 
-        let quotient = dividend / divisor;
+        let quotient = dividend.wrapping_div(divisor);
 
-        self.get_cpu_mut(cpu_type).set_register(0, quotient as u32);
-        self.get_cpu_mut(cpu_type)
-            .set_register(1, (dividend % divisor) as u32);
+        let arm = self.get_cpu_mut(cpu_type);
+        arm.set_register(Reg::new(0), quotient as u32);
+        arm.set_register(Reg::new(1), dividend.wrapping_rem(divisor) as u32);
+        arm.set_register(Reg::new(3), quotient.unsigned_abs());
+    }
+
+    /// r0 = integer square root of r0
+    fn sqrt(&mut self, cpu_type: CpuType) {
+        let value = self.swi_arg(cpu_type, 0);
         self.get_cpu_mut(cpu_type)
-            .set_register(3, quotient.unsigned_abs());
+            .set_register(Reg::new(0), value.isqrt());
     }
 
     /// Implements BIOS CpuSet memory transfer/fill.
     /// Behaviour follows ARM9/ARM7 NDS semantics.
     fn cpu_set(&mut self, cpu_type: CpuType) {
-        let mut source = self.get_cpu_mut(cpu_type).get_register(0);
-        let mut dest = self.get_cpu_mut(cpu_type).get_register(1);
-        let flags = self.get_cpu_mut(cpu_type).get_register(2);
+        let mut source = self.get_cpu_mut(cpu_type).get_register(Reg::new(0));
+        let mut dest = self.get_cpu_mut(cpu_type).get_register(Reg::new(1));
+        let flags = self.get_cpu_mut(cpu_type).get_register(Reg::new(2));
 
         if self.get_cpu_mut(cpu_type).get_id() != 0 && source < 0x4000 && dest < 0x4000 {
             return;
@@ -66,15 +157,43 @@ impl Emulator {
         }
     }
 
+    /// Implements BIOS CpuFastSet: like [`Self::cpu_set`] with words only,
+    /// the count rounded up to a multiple of 8.
+    fn cpu_fast_set(&mut self, cpu_type: CpuType) {
+        let mut source = self.swi_arg(cpu_type, 0) & !3;
+        let mut dest = self.swi_arg(cpu_type, 1) & !3;
+        let flags = self.swi_arg(cpu_type, 2);
+
+        if self.get_cpu(cpu_type).get_id() != 0 && source < 0x4000 && dest < 0x4000 {
+            return;
+        }
+
+        let len = (flags & 0x1FFFFF).next_multiple_of(8);
+        let memfill = flags & (1 << 24) != 0;
+        let fill = self.read_word(source, cpu_type);
+
+        for _ in 0..len {
+            let word = match memfill {
+                true => fill,
+                false => self.read_word(source, cpu_type),
+            };
+            self.write_word(dest, word, cpu_type);
+            if !memfill {
+                source = source.wrapping_add(4);
+            }
+            dest = dest.wrapping_add(4);
+        }
+    }
+
     /// Calculates CRC16 over a sequence of halfwords.
     fn get_crc16(&mut self, cpu_type: CpuType) {
         let crcs: [u16; 8] = [
             0xC0C1, 0xC181, 0xC301, 0xC601, 0xCC01, 0xD801, 0xF001, 0xA001,
         ];
 
-        let mut crc = (self.get_cpu_mut(cpu_type).get_register(0) & 0xFFFF) as u16;
-        let mut addr = self.get_cpu_mut(cpu_type).get_register(1);
-        let len = self.get_cpu_mut(cpu_type).get_register(2);
+        let mut crc = (self.get_cpu_mut(cpu_type).get_register(Reg::new(0)) & 0xFFFF) as u16;
+        let mut addr = self.get_cpu_mut(cpu_type).get_register(Reg::new(1));
+        let len = self.get_cpu_mut(cpu_type).get_register(Reg::new(2));
         let end = addr + len;
 
         while addr < end {
@@ -91,7 +210,299 @@ impl Emulator {
             addr += 2;
         }
 
-        self.get_cpu_mut(cpu_type).set_register(0, crc as u32);
+        self.get_cpu_mut(cpu_type)
+            .set_register(Reg::new(0), crc as u32);
+    }
+
+    /// Implements BIOS IntrWait: with `discard`, forget the `flags` already
+    /// raised, then halt until an IRQ handler raises one of them in the
+    /// check word and acknowledge it there.
+    ///
+    /// The check word is at the end of DTCM (ARM9) or ARM7 WRAM. While
+    /// waiting the SWI is run again after every IRQ.
+    fn intr_wait(&mut self, cpu_type: CpuType, discard: bool, flags: u32) {
+        let check = match cpu_type {
+            CpuType::Arm9 => self.arm9_cp15.get_dtcm_base() + 0x3FF8,
+            CpuType::Arm7 => 0x0380_FFF8,
+        };
+        let waiting = match cpu_type {
+            CpuType::Arm9 => {
+                self.int9_reg.ime = 1;
+                &mut self.intr_wait9
+            }
+            CpuType::Arm7 => {
+                self.int7_reg.ime = 1;
+                &mut self.intr_wait7
+            }
+        };
+        let discard = discard && !*waiting;
+
+        let mut raised = self.read_word(check, cpu_type);
+        if discard {
+            raised &= !flags;
+        }
+        let done = raised & flags != 0;
+        self.write_word(check, raised & !flags, cpu_type);
+        match cpu_type {
+            CpuType::Arm9 => self.intr_wait9 = !done,
+            CpuType::Arm7 => self.intr_wait7 = !done,
+        }
+        if done {
+            return;
+        }
+
+        let arm = self.get_cpu_mut(cpu_type);
+        let swi_address = arm.get_pc() - if arm.cpsr.thumb_on { 4 } else { 8 };
+        arm.jp(swi_address, false);
+        arm.halt();
+    }
+
+    /// Implements BIOS BitUnPack: widen every `src_width` bit unit of the
+    /// source to `dst_width` bits, adding the offset to non-zero units, or
+    /// to all of them with bit 31 of the offset set.
+    fn bit_unpack(&mut self, cpu_type: CpuType) {
+        let mut source = self.swi_arg(cpu_type, 0);
+        let mut dest = self.swi_arg(cpu_type, 1);
+        let info = self.swi_arg(cpu_type, 2);
+
+        let len = self.read_halfword(info, cpu_type);
+        let src_width = self.read_byte(info + 2, cpu_type) as u32;
+        let dst_width = self.read_byte(info + 3, cpu_type) as u32;
+        let offset = self.read_word(info + 4, cpu_type);
+        if !matches!(src_width, 1 | 2 | 4 | 8) || !matches!(dst_width, 1 | 2 | 4 | 8 | 16 | 32) {
+            return;
+        }
+
+        let (mut word, mut bits) = (0_u32, 0);
+        for _ in 0..len {
+            let byte = self.read_byte(source, cpu_type) as u32;
+            source = source.wrapping_add(1);
+            for shift in (0..8).step_by(src_width as usize) {
+                let mut unit = (byte >> shift) & ((1 << src_width) - 1);
+                if unit != 0 || offset & (1 << 31) != 0 {
+                    unit = unit.wrapping_add(offset & 0x7FFF_FFFF);
+                }
+                word |= (unit & (u32::MAX >> (32 - dst_width))) << bits;
+                bits += dst_width;
+                if bits == 32 {
+                    self.write_word(dest, word, cpu_type);
+                    dest = dest.wrapping_add(4);
+                    (word, bits) = (0, 0);
+                }
+            }
+        }
+    }
+
+    /// Decompressed size in the header of compressed data at `source`
+    fn compressed_size(&mut self, source: u32, cpu_type: CpuType) -> usize {
+        (self.read_word(source, cpu_type) >> 8) as usize
+    }
+
+    /// Decode LZ77 data: flag bytes, MSB first, tell literal bytes from
+    /// 2-byte references back into the output.
+    fn lz77_decompress(&mut self, cpu_type: CpuType, source: u32) -> Vec<u8> {
+        let size = self.compressed_size(source, cpu_type);
+        let mut out = Vec::with_capacity(size);
+        let mut src = source.wrapping_add(4);
+        let mut next = |emu: &mut Self| {
+            let byte = emu.read_byte(src, cpu_type);
+            src = src.wrapping_add(1);
+            byte as usize
+        };
+
+        while out.len() < size {
+            let flags = next(self);
+            for bit in (0..8).rev() {
+                if out.len() >= size {
+                    break;
+                }
+                if flags & (1 << bit) == 0 {
+                    out.push(next(self) as u8);
+                    continue;
+                }
+                let (high, low) = (next(self), next(self));
+                let distance = ((high & 0xF) << 8 | low) + 1;
+                for _ in 0..(high >> 4) + 3 {
+                    // Nothing before the output start, read as zero
+                    let byte = out.len().checked_sub(distance).map_or(0, |i| out[i]);
+                    out.push(byte);
+                }
+            }
+        }
+        out.truncate(size);
+        out
+    }
+
+    /// Decode run-length data: flag bytes give a run of one repeated byte
+    /// (bit 7) or of bytes copied as they are.
+    fn rle_decompress(&mut self, cpu_type: CpuType, source: u32) -> Vec<u8> {
+        let size = self.compressed_size(source, cpu_type);
+        let mut out = Vec::with_capacity(size);
+        let mut src = source.wrapping_add(4);
+        let mut next = |emu: &mut Self| {
+            let byte = emu.read_byte(src, cpu_type);
+            src = src.wrapping_add(1);
+            byte
+        };
+
+        while out.len() < size {
+            let flag = next(self);
+            let len = (flag & 0x7F) as usize;
+            if flag & 0x80 != 0 {
+                let byte = next(self);
+                out.extend(std::iter::repeat_n(byte, len + 3));
+            } else {
+                for _ in 0..=len {
+                    out.push(next(self));
+                }
+            }
+        }
+        out.truncate(size);
+        out
+    }
+
+    /// Decode Huffman data: a tree of 1-byte nodes, then a bitstream in
+    /// words, MSB first, walking it from the root to each data node.
+    fn huffman_decompress(&mut self, cpu_type: CpuType, source: u32) -> Vec<u8> {
+        let data_bits = self.read_byte(source, cpu_type) as u32 & 0xF;
+        let size = self.compressed_size(source, cpu_type);
+        let mut out = Vec::with_capacity(size);
+        if !matches!(data_bits, 1 | 2 | 4 | 8) {
+            return out;
+        }
+
+        let tree_size = self.read_byte(source.wrapping_add(4), cpu_type) as u32;
+        let root = source.wrapping_add(5);
+        let mut src = source.wrapping_add(4 + (tree_size + 1) * 2);
+        let (mut node_address, mut node) = (root, self.read_byte(root, cpu_type));
+        let (mut byte, mut bits) = (0_u32, 0);
+        'stream: while out.len() < size {
+            let word = self.read_word(src, cpu_type);
+            src = src.wrapping_add(4);
+            for bit in (0..32).rev() {
+                let right = (word >> bit) & 1;
+                let child_address = (node_address & !1) + (node as u32 & 0x3F) * 2 + 2 + right;
+                let child = self.read_byte(child_address, cpu_type);
+                if node & (0x80 >> right) == 0 {
+                    (node_address, node) = (child_address, child);
+                    continue;
+                }
+
+                byte |= (child as u32 & ((1 << data_bits) - 1)) << bits;
+                bits += data_bits;
+                if bits == 8 {
+                    out.push(byte as u8);
+                    (byte, bits) = (0, 0);
+                    if out.len() >= size {
+                        break 'stream;
+                    }
+                }
+                (node_address, node) = (root, self.read_byte(root, cpu_type));
+            }
+        }
+        out
+    }
+
+    /// Write decompressed data to `dest` in units of `unit` bytes, padding
+    /// the last one with zeros.
+    fn write_decompressed(&mut self, cpu_type: CpuType, dest: u32, data: &[u8], unit: usize) {
+        for (i, chunk) in data.chunks(unit).enumerate() {
+            let mut bytes = [0; 4];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+            let address = dest.wrapping_add((i * unit) as u32);
+            match unit {
+                1 => self.write_byte(address, bytes[0], cpu_type),
+                2 => {
+                    self.write_halfword(address, u16::from_le_bytes([bytes[0], bytes[1]]), cpu_type)
+                }
+                _ => self.write_word(address, u32::from_le_bytes(bytes), cpu_type),
+            }
+        }
+    }
+
+    /// Decompress from r0 to r1 with `decompress`, stored `unit` bytes at a
+    /// time. The callback variants read the source directly, as the
+    /// callbacks games pass do.
+    fn decompress(
+        &mut self,
+        cpu_type: CpuType,
+        decompress: fn(&mut Self, CpuType, u32) -> Vec<u8>,
+        unit: usize,
+    ) {
+        let source = self.swi_arg(cpu_type, 0);
+        let dest = self.swi_arg(cpu_type, 1);
+        let data = decompress(self, cpu_type, source);
+        self.write_decompressed(cpu_type, dest, &data, unit);
+    }
+
+    /// Implements BIOS Diff8bitUnFilter and Diff16bitUnFilter: each output
+    /// unit is the previous one plus the source unit.
+    fn diff_unfilter(&mut self, cpu_type: CpuType, halfwords: bool) {
+        let source = self.swi_arg(cpu_type, 0);
+        let dest = self.swi_arg(cpu_type, 1);
+        let size = self.compressed_size(source, cpu_type) as u32;
+
+        let mut value = 0_u16;
+        for offset in (0..size).step_by(if halfwords { 2 } else { 1 }) {
+            let address = source.wrapping_add(4 + offset);
+            if halfwords {
+                value = value.wrapping_add(self.read_halfword(address, cpu_type));
+                self.write_halfword(dest.wrapping_add(offset), value, cpu_type);
+            } else {
+                value = (value as u8).wrapping_add(self.read_byte(address, cpu_type)) as u16;
+                self.write_byte(dest.wrapping_add(offset), value as u8, cpu_type);
+            }
+        }
+    }
+
+    /// Implements BIOS SoftReset: clear the registers, set up the stacks
+    /// the BIOS uses and jump to the entry point of the loaded binary.
+    fn soft_reset(&mut self, cpu_type: CpuType) {
+        let (entry_pointer, stacks) = match cpu_type {
+            CpuType::Arm9 => (0x027F_FE24, [0x0080_3FC0, 0x0080_3FA0, 0x0080_3EC0]),
+            CpuType::Arm7 => (0x027F_FE34, [0x0380_FFDC, 0x0380_FFB0, 0x0380_FF00]),
+        };
+        let entry = self.read_word(entry_pointer, cpu_type);
+
+        let arm = self.get_cpu_mut(cpu_type);
+        for (mode, sp) in [PsrMode::Supervisor, PsrMode::Irq, PsrMode::System]
+            .into_iter()
+            .zip(stacks)
+        {
+            arm.update_reg_mode(mode);
+            arm.cpsr.mode = mode;
+            arm.set_register(REG_SP, sp);
+            arm.set_register(REG_LR, 0);
+        }
+        for reg in 0..13_u32 {
+            arm.set_register(Reg::new(reg), 0);
+        }
+        arm.jp(entry, true);
+    }
+
+    /// Implements the ARM7 sound tables: GetSineTable, GetPitchTable and
+    /// GetVolumeTable return entry r0 of their table, computed here from
+    /// the curves they sample.
+    fn sound_table(&mut self, opcode: u8) {
+        let index = self.arm7.get_register(Reg::new(0)) as f64;
+        let value = match opcode {
+            // sin(0..pi/2) in 1.15 fixed point, 64 entries
+            0x1A => ((index * std::f64::consts::PI / 128.0).sin() * 32768.0) as u32,
+            // 2^(i/768) - 1 in 0.16 fixed point, 768 entries
+            0x1B => (((index / 768.0).exp2() - 1.0) * 65536.0) as u32,
+            // 0.1 dB steps up to 0 dB, 724 entries, scaled back up where the
+            // sound driver divides the volume down
+            _ => {
+                let divider = match index as u32 {
+                    ..483 => 16.0,
+                    483..603 => 4.0,
+                    603..663 => 2.0,
+                    _ => 1.0,
+                };
+                (127.0 * 10_f64.powf((index - 723.0) / 200.0) * divider).round() as u32
+            }
+        };
+        self.arm7.set_register(Reg::new(0), value);
     }
 
     /// Executes SWI 7 (ARM7 BIOS).
@@ -99,15 +510,30 @@ impl Emulator {
         let opcode = self.get_opcode(CpuType::Arm7);
         match opcode {
             0x03 => {
-                let reg = self.arm7.get_register(0);
+                let reg = self.arm7.get_register(Reg::new(0));
                 self.arm7.add_internal_cycles((reg * 4) as i32)
             }
-            0x06 => self.arm7.halt(),
-            0x0B => self.cpu_set(CpuType::Arm7),
-            0x0E => self.get_crc16(CpuType::Arm7),
+            // Sleep, woken by any IRQ
+            0x07 => self.arm7.halt(),
+            0x08 => {
+                let bias = if self.arm7.get_register(Reg::new(0)) != 0 {
+                    0x200
+                } else {
+                    0
+                };
+                self.write_halfword(0x0400_0504, bias, CpuType::Arm7);
+            }
+            0x1A..=0x1C => self.sound_table(opcode),
+            // CustomHalt: r2 goes to HALTCNT
+            0x1F => {
+                let haltcnt = self.arm7.get_register(Reg::new(2)) as u8;
+                self.write_byte(0x0400_0301, haltcnt, CpuType::Arm7);
+            }
             _ => {
-                #[cfg(feature = "tracing")]
-                tracing::error!("Unrecognized HLE SWI7 ${opcode:02X}");
+                if !self.swi_common(CpuType::Arm7, opcode) {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("Unrecognized HLE SWI7 ${opcode:02X}");
+                }
             }
         }
         1
@@ -118,18 +544,159 @@ impl Emulator {
         let opcode = self.get_opcode(CpuType::Arm9);
         match opcode {
             0x03 => {
-                let value = self.get_cpu(CpuType::Arm9).get_register(0) * 2;
+                let value = self.get_cpu(CpuType::Arm9).get_register(Reg::new(0)) * 2;
                 self.get_cpu_mut(CpuType::Arm9)
                     .add_internal_cycles(value as i32);
             }
-            0x06 => self.get_cpu_mut(CpuType::Arm9).halt(),
-            0x0B => self.cpu_set(CpuType::Arm9),
-            0x0E => self.get_crc16(CpuType::Arm9),
+            0x16 => self.diff_unfilter(CpuType::Arm9, false),
+            0x18 => self.diff_unfilter(CpuType::Arm9, true),
+            // CustomPost: r0 goes to POSTFLG
+            0x1F => self.postflg9 = (self.arm9.get_register(Reg::new(0)) & 1) as u8,
             _ => {
-                #[cfg(feature = "tracing")]
-                tracing::error!("Unrecognized HLE SWI9 ${opcode:02X}");
+                if !self.swi_common(CpuType::Arm9, opcode) {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("Unrecognized HLE SWI9 ${opcode:02X}");
+                }
             }
         }
         1
     }
+
+    /// SWIs both BIOSes have, returning whether `opcode` is one.
+    fn swi_common(&mut self, cpu_type: CpuType, opcode: u8) -> bool {
+        match opcode {
+            0x00 => self.soft_reset(cpu_type),
+            0x04 => {
+                let discard = self.swi_arg(cpu_type, 0) != 0;
+                let flags = self.swi_arg(cpu_type, 1);
+                self.intr_wait(cpu_type, discard, flags);
+            }
+            0x05 => self.intr_wait(cpu_type, true, 1),
+            0x06 => self.get_cpu_mut(cpu_type).halt(),
+            0x09 => self.div(cpu_type),
+            0x0B => self.cpu_set(cpu_type),
+            0x0C => self.cpu_fast_set(cpu_type),
+            0x0D => self.sqrt(cpu_type),
+            0x0E => self.get_crc16(cpu_type),
+            // IsDebugger: never on retail units
+            0x0F => self.get_cpu_mut(cpu_type).set_register(Reg::new(0), 0),
+            0x10 => self.bit_unpack(cpu_type),
+            0x11 => self.decompress(cpu_type, Self::lz77_decompress, 1),
+            0x12 => self.decompress(cpu_type, Self::lz77_decompress, 2),
+            0x13 => self.decompress(cpu_type, Self::huffman_decompress, 4),
+            0x14 => self.decompress(cpu_type, Self::rle_decompress, 1),
+            0x15 => self.decompress(cpu_type, Self::rle_decompress, 2),
+            _ => return false,
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interrupts::Interrupt;
+
+    #[test]
+    fn test_hle_irq_dispatch() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.config.hle_bios = true;
+        // Handler `bx lr`, main loop `b .`, handler pointer at the end of WRAM
+        emu.arm7_write_word(0x0380_0000, 0xE12F_FF1E);
+        emu.arm7_write_word(0x0380_0100, 0xEAFF_FFFE);
+        emu.arm7_write_word(0x03FF_FFFC, 0x0380_0000);
+
+        let arm = &mut emu.arm7;
+        arm.update_reg_mode(PsrMode::Irq);
+        arm.cpsr.mode = PsrMode::Irq;
+        arm.set_register(REG_SP, 0x0380_FF80);
+        arm.update_reg_mode(PsrMode::System);
+        arm.cpsr.mode = PsrMode::System;
+        arm.cpsr.thumb_on = false;
+        arm.cpsr.irq_disabled = false;
+        arm.set_register(Reg::new(0), 0x1111);
+        arm.set_register(REG_LR, 0x2222);
+        arm.jp(0x0380_0100, false);
+        emu.int7_reg.irq_enable = 1;
+        emu.int7_reg.ime = 1;
+        emu.request_interrupt7(Interrupt::VBlank);
+
+        emu.execute(CpuType::Arm7);
+        let return_address = emu.hle_irq_return_address(CpuType::Arm7);
+        assert_eq!(emu.arm7.get_pc(), 0x0380_0004);
+        assert_eq!(emu.arm7.cpsr.mode, PsrMode::Irq);
+        assert_eq!(emu.arm7.get_register(REG_SP), 0x0380_FF80 - 24);
+        assert_eq!(emu.arm7.get_register(REG_LR), return_address);
+        assert_eq!(emu.arm7_read_word(0x0380_FF80 - 24), 0x1111);
+
+        // Acknowledge, run the handler and return
+        emu.int7_reg.irq_flags = 0;
+        emu.arm7.set_register(Reg::new(0), 0);
+        emu.execute(CpuType::Arm7);
+        assert_eq!(emu.arm7.get_pc(), return_address + 4);
+        emu.execute(CpuType::Arm7);
+        assert_eq!(emu.arm7.get_pc(), 0x0380_0104);
+        assert_eq!(emu.arm7.cpsr.mode, PsrMode::System);
+        assert!(!emu.arm7.cpsr.irq_disabled);
+        assert_eq!(emu.arm7.get_register(Reg::new(0)), 0x1111);
+        assert_eq!(emu.arm7.get_register(REG_LR), 0x2222);
+    }
+
+    #[test]
+    fn test_hle_intr_wait() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.config.hle_bios = true;
+        // swi 0x04; b .
+        emu.arm7_write_word(0x0380_0100, 0xEF04_0000);
+        emu.arm7_write_word(0x0380_0104, 0xEAFF_FFFE);
+        emu.arm7_write_word(0x0380_FFF8, 1);
+        emu.arm7.set_register(Reg::new(0), 1);
+        emu.arm7.set_register(Reg::new(1), 1);
+        emu.arm7.jp(0x0380_0100, false);
+
+        // The VBlank flag raised before the call is discarded
+        emu.execute(CpuType::Arm7);
+        assert!(emu.arm7.halted);
+        assert_eq!(emu.arm7.get_pc(), 0x0380_0104);
+        assert_eq!(emu.int7_reg.ime, 1);
+
+        // As if an IRQ handler raised it again
+        emu.arm7_write_word(0x0380_FFF8, 1);
+        emu.arm7.halted = false;
+        emu.execute(CpuType::Arm7);
+        assert!(!emu.arm7.halted);
+        assert_eq!(emu.arm7.get_pc(), 0x0380_0108);
+        assert_eq!(emu.arm7_read_word(0x0380_FFF8), 0);
+    }
+
+    #[test]
+    fn test_hle_decompress() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        let mut store = |address: u32, bytes: &[u8]| {
+            for (i, &byte) in bytes.iter().enumerate() {
+                emu.write_byte(address + i as u32, byte, CpuType::Arm9);
+            }
+        };
+        // "ABC" then a 6 byte reference 3 back
+        store(
+            0x0200_0000,
+            &[0x10, 9, 0, 0, 0x10, b'A', b'B', b'C', 0x30, 0x02],
+        );
+        store(0x0200_0100, &[0x30, 5, 0, 0, 0x81, b'A', 0x00, b'B']);
+        // Root with data nodes 'x' and 'y', stream 0110
+        store(
+            0x0200_0200,
+            &[0x28, 4, 0, 0, 1, 0xC0, b'x', b'y', 0, 0, 0, 0x60],
+        );
+
+        assert_eq!(
+            emu.lz77_decompress(CpuType::Arm9, 0x0200_0000),
+            b"ABCABCABC"
+        );
+        assert_eq!(emu.rle_decompress(CpuType::Arm9, 0x0200_0100), b"AAAAB");
+        assert_eq!(emu.huffman_decompress(CpuType::Arm9, 0x0200_0200), b"xyyx");
+    }
 }
@@ -0,0 +1,223 @@
+//! Skip intro for real BIOS/firmware boot
+//!
+//! When booting through user supplied BIOS and firmware images (direct boot
+//! disabled), the firmware shows its boot animation, the health and safety
+//! screen and the menu before starting the cartridge. With `skip_intro` set
+//! the boot is hybrid:
+//!
+//! - the real BIOS runs its reset code, checks the hardware and loads the
+//!   firmware boot code into RAM as usual
+//! - when the ARM9 reaches the entry of that code, both CPUs are handed the
+//!   cartridge through [`Emulator::direct_boot`] instead, so neither the
+//!   animation nor the health and safety screen, both drawn by the firmware,
+//!   are shown
+//! - without a cartridge the firmware runs, with autostart set in its user
+//!   settings so a cartridge inserted later skips the menu
+//!
+//! The BIOS images themselves are not modified, only recognised by their
+//! CRC32; with unknown dumps nothing is skipped. The autostart flag is set
+//! in the in-memory copy of the firmware, files on disk are never touched.
+use crate::cartridge::HEADER_SIZE;
+use crate::emulator::Emulator;
+use crate::emulator::emu_config::BiosMem;
+use crate::firmware::Firmware;
+use crate::util::crc32;
+
+/// Known BIOS dumps, identified by CRC32 of the whole image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BiosRevision {
+    /// Retail DS / DS Lite ARM9 BIOS
+    NdsArm9,
+    /// Retail DS / DS Lite ARM7 BIOS
+    NdsArm7,
+    /// Built-in replacement BIOS from `lunaris_ds_free_bios`
+    Free,
+    /// Unrecognised image, holds its CRC32
+    Unknown(u32),
+}
+
+const NDS_ARM9_CRC32: u32 = 0x2AB2_3573;
+const NDS_ARM7_CRC32: u32 = 0x1280_F0D5;
+
+/// USER settings flags bit 6: start the cartridge without entering the menu.
+const USER_FLAG_AUTOSTART: u16 = 1 << 6;
+
+impl<const BIOS_SIZE: usize> BiosMem<BIOS_SIZE> {
+    /// Identify the loaded BIOS image.
+    pub fn revision(&self) -> BiosRevision {
+        match self {
+            Self::Const(_) => BiosRevision::Free,
+            Self::User(data) => match crc32(data) {
+                NDS_ARM9_CRC32 => BiosRevision::NdsArm9,
+                NDS_ARM7_CRC32 => BiosRevision::NdsArm7,
+                crc => BiosRevision::Unknown(crc),
+            },
+        }
+    }
+}
+
+impl Firmware {
+    /// Set the autostart flag in the active user settings so the firmware
+    /// boots the cartridge straight after the boot animation.
+    ///
+    /// Returns `false` if no firmware is loaded.
+    pub fn patch_autostart(&mut self) -> bool {
+        if self.user_data == 0 {
+            return false;
+        }
+        let user = self.user_data as usize;
+
+        let flags = u16::from_le_bytes([
+            self.raw_firmware[user + 0x64],
+            self.raw_firmware[user + 0x65],
+        ]) | USER_FLAG_AUTOSTART;
+        self.raw_firmware[user + 0x64..user + 0x66].copy_from_slice(&flags.to_le_bytes());
+
+        let crc = Self::create_crc(&self.raw_firmware[user..], 0x70, 0xFFFF);
+        self.raw_firmware[user + 0x72..user + 0x74].copy_from_slice(&crc.to_le_bytes());
+        true
+    }
+
+    /// Address the BIOS starts the ARM9 part of the firmware boot code at,
+    /// from the firmware header.
+    ///
+    /// Returns `None` if no firmware is loaded.
+    pub fn arm9_boot_entry(&self) -> Option<u32> {
+        if self.user_data == 0 {
+            return None;
+        }
+        let halfword = |offset: usize| {
+            u16::from_le_bytes([self.raw_firmware[offset], self.raw_firmware[offset + 1]]) as u32
+        };
+
+        // 0x0E: 0x02800000 - address >> (2 + shift), shift in bits 3-5 of 0x14
+        let shift = (halfword(0x14) >> 3) & 7;
+        Some(0x0280_0000 - (halfword(0x0E) << (2 + shift)))
+    }
+}
+
+impl Emulator {
+    /// Apply the skip-intro patches if `config.skip_intro` is set.
+    ///
+    /// Only retail BIOS dumps are patched; the free BIOS has no intro and
+    /// unknown dumps are left alone. With a cartridge loaded this arms
+    /// [`Emulator::boot_handover`].
+    pub fn apply_boot_patches(&mut self) {
+        self.boot_handover = None;
+        if !self.config.skip_intro {
+            return;
+        }
+
+        let arm9 = self.arm9_bios.revision();
+        let arm7 = self.arm7_bios.revision();
+        if arm9 != BiosRevision::NdsArm9 || arm7 != BiosRevision::NdsArm7 {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Skip intro: BIOS not recognised ({arm9:?}, {arm7:?}), not patching");
+            return;
+        }
+
+        if self.spi.firmware.patch_autostart() {
+            #[cfg(feature = "tracing")]
+            tracing::info!("Skip intro: firmware autostart enabled");
+        }
+        if self.cart.rom.len() >= HEADER_SIZE {
+            self.boot_handover = self.spi.firmware.arm9_boot_entry();
+        }
+    }
+
+    /// Boot the cartridge directly if the ARM9 is about to run the firmware
+    /// boot code, see [`Emulator::apply_boot_patches`].
+    ///
+    /// Returns `true` if it did; both CPUs then start over at the entry
+    /// points of the cartridge.
+    pub(crate) fn check_boot_handover(&mut self) -> bool {
+        let prefetch = if self.arm9.cpsr.thumb_on { 2 } else { 4 };
+        let instr_addr = self.arm9.get_pc().wrapping_sub(prefetch);
+        if self.boot_handover != Some(instr_addr) {
+            return false;
+        }
+        self.boot_handover = None;
+
+        #[cfg(feature = "tracing")]
+        tracing::info!("Skip intro: booting the cartridge at firmware entry {instr_addr:#010X}");
+        self.direct_boot();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lunaris_ds_mem_const::{BIOS7_SIZE, BIOS9_SIZE};
+
+    use super::*;
+    use crate::emulator::emu_config::BiosMem;
+
+    /// `size` bytes of `fill` ending in the 4 bytes that give them `crc`.
+    fn forge_bios(size: usize, fill: u8, crc: u32) -> Vec<u8> {
+        let mut data = vec![fill; size];
+        let state = !crc32(&data[..size - 4]);
+        // Run the 32 CRC steps of the last word backwards from the result
+        let mut target = !crc;
+        for _ in 0..32 {
+            target = match target >> 31 {
+                1 => ((target ^ 0xEDB8_8320) << 1) | 1,
+                _ => target << 1,
+            };
+        }
+        data[size - 4..].copy_from_slice(&(state ^ target).to_le_bytes());
+        data
+    }
+
+    fn boot(arm9_crc: u32, arm7_crc: u32) -> Box<Emulator> {
+        let mut emu = Box::new(Emulator::new());
+        emu.config.direct_boot_enabled = false;
+        emu.config.skip_intro = true;
+        emu.arm9_bios = BiosMem::User(forge_bios(BIOS9_SIZE, 0x99, arm9_crc));
+        emu.arm7_bios = BiosMem::User(forge_bios(BIOS7_SIZE, 0x77, arm7_crc));
+        emu.spi.firmware.load_firmware("").unwrap();
+        // Firmware boot code at 0x02800000 - 0x1000 << 2
+        emu.spi.firmware.raw_firmware[0x0E..0x10].copy_from_slice(&0x1000_u16.to_le_bytes());
+        emu.spi.firmware.raw_firmware[0x14..0x16].copy_from_slice(&0_u16.to_le_bytes());
+
+        emu.cart.rom = vec![0; 0x400];
+        emu.cart.rom[0x24..0x28].copy_from_slice(&0x0200_0100_u32.to_le_bytes());
+        emu.power_on();
+        emu
+    }
+
+    fn autostart(emu: &Emulator) -> bool {
+        let flags = emu.spi.firmware.user_data as usize + 0x64;
+        emu.spi.firmware.raw_firmware[flags] as u16 & USER_FLAG_AUTOSTART != 0
+    }
+
+    #[test]
+    fn test_known_bios_is_patched() {
+        let mut emu = boot(NDS_ARM9_CRC32, NDS_ARM7_CRC32);
+        assert_eq!(emu.arm9_bios.revision(), BiosRevision::NdsArm9);
+        assert_eq!(emu.arm7_bios.revision(), BiosRevision::NdsArm7);
+        assert!(autostart(&emu));
+        assert_eq!(emu.boot_handover, Some(0x027F_C000));
+
+        // Anywhere but the firmware entry the BIOS keeps running
+        emu.arm9.jp(0xFFFF_0000, false);
+        emu.arm7.jp(0x027F_C000, false);
+        assert!(!emu.check_boot_handover());
+        emu.arm9.jp(0x027F_C000, false);
+        assert!(emu.check_boot_handover());
+        assert_eq!(emu.arm9.get_pc(), 0x0200_0104);
+        assert_eq!(emu.boot_handover, None);
+    }
+
+    #[test]
+    fn test_unknown_bios_is_left_alone() {
+        let mut emu = boot(NDS_ARM9_CRC32 ^ 1, NDS_ARM7_CRC32);
+        assert_eq!(
+            emu.arm9_bios.revision(),
+            BiosRevision::Unknown(NDS_ARM9_CRC32 ^ 1)
+        );
+        assert!(!autostart(&emu));
+        assert_eq!(emu.boot_handover, None);
+        emu.arm9.jp(0x027F_C000, false);
+        assert!(!emu.check_boot_handover());
+    }
+}
@@ -0,0 +1,149 @@
+//! Icon and title banner
+//!
+//! The header points at a banner with the 32x32 icon and the game title in
+//! each language, as the DS menu shows them. It only needs the ROM image,
+//! so frontends can list games with their icons without starting one.
+use super::{NDSCart, RomHeader};
+use crate::firmware::FirmwareLanguage;
+
+/// Width and height of the icon.
+pub const ICON_SIZE: usize = 32;
+/// Size of a version 1 banner: icon, palette and six titles.
+const BANNER_V1_SIZE: usize = 0x840;
+const ICON_OFFSET: usize = 0x20;
+const PALETTE_OFFSET: usize = 0x220;
+const TITLES_OFFSET: usize = 0x240;
+/// Size of one title, UTF-16 padded with zeros
+const TITLE_SIZE: usize = 0x100;
+
+/// Banner of a ROM, see [`Banner::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Banner {
+    pub version: u16,
+    /// Icon, ARGB8888, [`ICON_SIZE`] x [`ICON_SIZE`], transparent pixels 0
+    pub icon: Vec<u32>,
+    /// Titles indexed by [`FirmwareLanguage`], lines separated by `\n`
+    pub titles: Vec<String>,
+}
+
+impl Banner {
+    /// Parse the banner of `rom`, `None` if it has none or it lies past
+    /// the end of the ROM.
+    pub fn parse(rom: &[u8]) -> Option<Self> {
+        let start = RomHeader::parse(rom)?.banner_offset as usize;
+        if start == 0 {
+            return None;
+        }
+        let banner = rom.get(start..start.checked_add(BANNER_V1_SIZE)?)?;
+        let version = u16::from_le_bytes([banner[0], banner[1]]);
+
+        // Version 2 adds a Chinese title, version 3 a Korean one
+        let languages = match version {
+            2 => 7,
+            3.. => 8,
+            _ => 6,
+        };
+        let titles = (0..languages)
+            .map_while(|i| {
+                let title = start + TITLES_OFFSET + i * TITLE_SIZE;
+                rom.get(title..title + TITLE_SIZE)
+            })
+            .map(decode_title)
+            .collect();
+
+        Some(Self {
+            version,
+            icon: decode_icon(
+                &banner[ICON_OFFSET..PALETTE_OFFSET],
+                &banner[PALETTE_OFFSET..TITLES_OFFSET],
+            ),
+            titles,
+        })
+    }
+
+    /// Title in `language`, the English one if the banner has none in it.
+    pub fn title(&self, language: FirmwareLanguage) -> &str {
+        self.titles
+            .get(language as usize)
+            .or_else(|| self.titles.get(FirmwareLanguage::English as usize))
+            .map_or("", String::as_str)
+    }
+}
+
+fn decode_title(raw: &[u8]) -> String {
+    let units = raw
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .take_while(|&unit| unit != 0);
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// 4 bpp tiled bitmap to ARGB8888, palette entry 0 transparent.
+fn decode_icon(bitmap: &[u8], palette: &[u8]) -> Vec<u32> {
+    let colors: Vec<u32> = palette
+        .chunks_exact(2)
+        .map(|color| {
+            let color = u16::from_le_bytes([color[0], color[1]]);
+            let r = ((color & 0x1F) << 3) as u32;
+            let g = (((color >> 5) & 0x1F) << 3) as u32;
+            let b = (((color >> 10) & 0x1F) << 3) as u32;
+            0xFF00_0000 | (r << 16) | (g << 8) | b
+        })
+        .collect();
+
+    let mut icon = vec![0; ICON_SIZE * ICON_SIZE];
+    // 4x4 tiles of 8x8 pixels, two pixels per byte, low nibble first
+    for (i, &byte) in bitmap.iter().enumerate() {
+        let tile = i / 32;
+        let x = (tile % 4) * 8 + (i % 4) * 2;
+        let y = (tile / 4) * 8 + (i % 32) / 4;
+        for (dx, index) in [byte & 0xF, byte >> 4].into_iter().enumerate() {
+            if index != 0 {
+                icon[y * ICON_SIZE + x + dx] = colors[index as usize];
+            }
+        }
+    }
+    icon
+}
+
+impl NDSCart {
+    /// Banner of the loaded ROM, see [`Banner::parse`].
+    pub fn banner(&self) -> Option<Banner> {
+        Banner::parse(&self.rom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_banner() {
+        let mut rom = vec![0_u8; 0x1000];
+        rom[..4].copy_from_slice(b"GAME");
+        rom[0x0C..0x10].copy_from_slice(b"AGME");
+        rom[0x68..0x6C].copy_from_slice(&0x400_u32.to_le_bytes());
+        rom[0x400] = 1;
+        // Top left pixel color 1 (red), the one right of it transparent
+        rom[0x420] = 0x01;
+        rom[0x622..0x624].copy_from_slice(&0x001F_u16.to_le_bytes());
+        for (i, c) in "Game\nMaker".encode_utf16().enumerate() {
+            let offset = 0x400 + TITLES_OFFSET + TITLE_SIZE + i * 2;
+            rom[offset..offset + 2].copy_from_slice(&c.to_le_bytes());
+        }
+
+        let header = RomHeader::parse(&rom).unwrap();
+        assert_eq!(header.title(), "GAME");
+        assert_eq!(&header.game_code, b"AGME");
+        assert_eq!(RomHeader::parse(&rom[..0x100]), None);
+
+        let banner = Banner::parse(&rom).unwrap();
+        assert_eq!(banner.icon[0], 0xFFF8_0000);
+        assert_eq!(banner.icon[1], 0);
+        assert_eq!(banner.titles.len(), 6);
+        assert_eq!(banner.title(FirmwareLanguage::English), "Game\nMaker");
+        assert_eq!(banner.title(FirmwareLanguage::Chinese), "Game\nMaker");
+    }
+}
@@ -0,0 +1,272 @@
+//! Crash-safe save flushing
+//!
+//! Dirty save pages are first written to a journal next to the save file
+//! (`game.sav.journal`) and synced, then patched into the save file, and
+//! only then is the journal removed. A crash while patching leaves a
+//! complete journal that is replayed on the next load. A crash while writing
+//! the journal leaves one that fails its checksum and is discarded; the save
+//! file is still as of the previous flush then.
+//!
+//! Journal layout, little-endian:
+//!
+//! | field             | size                                  |
+//! |-------------------|---------------------------------------|
+//! | magic `LNSJ`      | 4                                     |
+//! | entry count       | 4                                     |
+//! | entries           | offset (4), length (4), data (length) |
+//! | CRC32             | 4, of everything before               |
+use std::collections::BTreeSet;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek as _, SeekFrom, Write as _};
+use std::path::{Path, PathBuf};
+
+use crate::util::crc32;
+
+/// Granularity of dirty tracking, the FLASH page size.
+pub(crate) const SAVE_PAGE_SIZE: usize = 256;
+const JOURNAL_MAGIC: &[u8; 4] = b"LNSJ";
+
+pub(crate) fn journal_path(save_path: &Path) -> PathBuf {
+    let mut name = save_path.as_os_str().to_owned();
+    name.push(".journal");
+    PathBuf::from(name)
+}
+
+/// Write the dirty `pages` of `save` to the save file at `path`.
+///
+/// A missing save file, or one of a different size, is replaced whole
+/// through a temporary file instead.
+pub(crate) fn flush_save(path: &Path, save: &[u8], pages: &BTreeSet<usize>) -> io::Result<()> {
+    let file_len = std::fs::metadata(path).map_or(0, |meta| meta.len());
+    if file_len != save.len() as u64 {
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        write_synced(Path::new(&temp), save)?;
+        std::fs::rename(&temp, path)?;
+        return sync_parent(path);
+    }
+
+    let entries = dirty_entries(save, pages);
+    let journal_path = journal_path(path);
+    write_synced(&journal_path, &encode_journal(&entries))?;
+    sync_parent(&journal_path)?;
+    apply(path, entries)?;
+    std::fs::remove_file(&journal_path)
+}
+
+/// Finish or discard a flush of the save file at `path` that was
+/// interrupted. Returns whether a journal was replayed.
+pub(crate) fn recover(path: &Path) -> io::Result<bool> {
+    let journal_path = journal_path(path);
+    let Some(journal) = read_journal(&journal_path)? else {
+        return Ok(false);
+    };
+
+    let replayed = match decode_journal(&journal) {
+        Some(entries) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Replaying save journal {}", journal_path.display());
+            apply(path, entries)?;
+            true
+        }
+        None => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Discarding incomplete save journal {}",
+                journal_path.display()
+            );
+            false
+        }
+    };
+    std::fs::remove_file(&journal_path)?;
+    Ok(replayed)
+}
+
+/// Patch the journal of an interrupted flush of the save file at `path`
+/// into `save`, the contents of that file, leaving both files as they are.
+/// For read-only mode; returns whether a journal was replayed.
+pub(crate) fn replay_in_memory(path: &Path, save: &mut [u8]) -> io::Result<bool> {
+    let Some(journal) = read_journal(&journal_path(path))? else {
+        return Ok(false);
+    };
+    let Some(entries) = decode_journal(&journal) else {
+        return Ok(false);
+    };
+    for (offset, data) in entries {
+        let start = offset as usize;
+        if let Some(dest) = save.get_mut(start..start + data.len()) {
+            dest.copy_from_slice(data);
+        }
+    }
+    Ok(true)
+}
+
+/// The journal at `path`, `None` if there is none.
+fn read_journal(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(journal) => Ok(Some(journal)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn write_synced(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+/// Sync the directory holding `path`, so a file created or renamed there
+/// is still there after a crash.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+/// Directories cannot be opened for syncing elsewhere.
+#[cfg(not(unix))]
+const fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Patch `(offset, data)` entries into the file at `path`.
+fn apply(path: &Path, entries: Vec<(u32, &[u8])>) -> io::Result<()> {
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    for (offset, data) in entries {
+        file.seek(SeekFrom::Start(offset as u64))?;
+        file.write_all(data)?;
+    }
+    file.sync_all()
+}
+
+/// `(offset, data)` of the dirty `pages` of `save`.
+fn dirty_entries<'a>(save: &'a [u8], pages: &BTreeSet<usize>) -> Vec<(u32, &'a [u8])> {
+    pages
+        .iter()
+        .filter_map(|&page| {
+            let start = page * SAVE_PAGE_SIZE;
+            let end = (start + SAVE_PAGE_SIZE).min(save.len());
+            Some((start as u32, save.get(start..end)?))
+        })
+        .collect()
+}
+
+fn encode_journal(entries: &[(u32, &[u8])]) -> Vec<u8> {
+    let mut journal = JOURNAL_MAGIC.to_vec();
+    journal.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for &(offset, data) in entries {
+        journal.extend_from_slice(&offset.to_le_bytes());
+        journal.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        journal.extend_from_slice(data);
+    }
+    let crc = crc32(&journal);
+    journal.extend_from_slice(&crc.to_le_bytes());
+    journal
+}
+
+/// Entries of a complete journal, `None` if it is truncated or corrupt.
+fn decode_journal(journal: &[u8]) -> Option<Vec<(u32, &[u8])>> {
+    let (body, crc) = journal.split_at_checked(journal.len().checked_sub(4)?)?;
+    if crc32(body).to_le_bytes() != crc || !body.starts_with(JOURNAL_MAGIC) {
+        return None;
+    }
+
+    let word = |at: usize| {
+        body.get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let count = word(4)?;
+    let mut at = 8;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let offset = word(at)?;
+        let len = word(at + 4)? as usize;
+        entries.push((offset, body.get(at + 8..at + 8 + len)?));
+        at += 8 + len;
+    }
+    Some(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_save(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("lunaris_{name}_{}.sav", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(journal_path(&path));
+        path
+    }
+
+    #[test]
+    fn test_flush_pages() {
+        let path = temp_save("flush");
+        let mut save = vec![0xFF; 4 * SAVE_PAGE_SIZE];
+        flush_save(&path, &save, &BTreeSet::new()).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), save);
+
+        save[SAVE_PAGE_SIZE + 3] = 0x12;
+        save[3 * SAVE_PAGE_SIZE] = 0x34;
+        flush_save(&path, &save, &BTreeSet::from([1, 3])).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), save);
+        assert!(!journal_path(&path).exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_recover_truncated_journal() {
+        let path = temp_save("recover");
+        let old = vec![0xFF; 2 * SAVE_PAGE_SIZE];
+        let mut new = old.clone();
+        new[SAVE_PAGE_SIZE..].fill(0xAB);
+        let journal = encode_journal(&dirty_entries(&new, &BTreeSet::from([1])));
+
+        // Crash while writing the journal: the old save survives
+        for len in [0, 3, 8, journal.len() / 2, journal.len() - 1] {
+            std::fs::write(&path, &old).unwrap();
+            std::fs::write(journal_path(&path), &journal[..len]).unwrap();
+            assert!(!recover(&path).unwrap());
+            assert_eq!(std::fs::read(&path).unwrap(), old);
+            assert!(!journal_path(&path).exists());
+        }
+
+        // Crash while patching the save: the journal is replayed
+        let mut torn = old.clone();
+        torn[SAVE_PAGE_SIZE..SAVE_PAGE_SIZE + 16].fill(0xAB);
+        std::fs::write(&path, &torn).unwrap();
+        std::fs::write(journal_path(&path), &journal).unwrap();
+        assert!(recover(&path).unwrap());
+        assert_eq!(std::fs::read(&path).unwrap(), new);
+        assert!(!recover(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_replay_in_memory() {
+        let path = temp_save("replay");
+        let old = vec![0xFF; 2 * SAVE_PAGE_SIZE];
+        let mut new = old.clone();
+        new[SAVE_PAGE_SIZE..].fill(0xAB);
+        let journal = encode_journal(&dirty_entries(&new, &BTreeSet::from([1])));
+        std::fs::write(&path, &old).unwrap();
+
+        let mut save = old.clone();
+        assert!(!replay_in_memory(&path, &mut save).unwrap());
+        assert_eq!(save, old);
+
+        // The save comes back as of the interrupted flush, the files as
+        // they were
+        std::fs::write(journal_path(&path), &journal).unwrap();
+        assert!(replay_in_memory(&path, &mut save).unwrap());
+        assert_eq!(save, new);
+        assert_eq!(std::fs::read(&path).unwrap(), old);
+        assert_eq!(std::fs::read(journal_path(&path)).unwrap(), journal);
+
+        std::fs::remove_file(journal_path(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+//! KEY2 stream cipher
+//!
+//! After the secure area, commands and data on the cartridge bus are XORed
+//! with the output of two 39-bit LFSRs. The console and the card each run a
+//! copy: the console's is seeded from 0x040001B0..0x040001BB when ROMCTRL
+//! bit 15 is written, the card's by the KEY1 command that activates KEY2.
+//! Each copy steps once per byte it handles, so the two only agree while the
+//! game seeds and flags its transfers like the BIOS does. Otherwise commands
+//! reach the card garbled and data reads back encrypted, which is what some
+//! copy protection checks look for.
+//!
+//! Direct boot skips the handshake that activates KEY2 on the card, so the
+//! bus stays unencrypted there, as with a flash cart.
+
+use crate::error::EmuError;
+use crate::savestate::{Savestate, StateReader, StateWriter};
+
+/// Both registers are 39 bits wide.
+const MASK: u64 = 0x7F_FFFF_FFFF;
+
+/// Low byte of the card's seed 0, selected by header byte 0x013.
+const SEED_BYTES: [u8; 8] = [0xE8, 0x4D, 0x5A, 0xB1, 0x17, 0x8F, 0x99, 0xD5];
+
+/// Seed 1 of every card.
+const CARD_SEED1: u64 = 0x5C_879B_9B05;
+
+/// One side of the KEY2 cipher.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Key2 {
+    x: u64,
+    y: u64,
+}
+
+impl Key2 {
+    /// Load two seeds; they enter the registers bit-reversed.
+    pub(crate) fn new(seed0: u64, seed1: u64) -> Self {
+        Self {
+            x: reverse_39(seed0),
+            y: reverse_39(seed1),
+        }
+    }
+
+    /// Seeds 0 and 1 used after the activation command, from its random
+    /// `mmmnnn` field and header byte 0x013.
+    pub(crate) fn card_seeds(mmmnnn: u32, seed_select: u8) -> (u64, u64) {
+        let seed0 = ((mmmnnn as u64 & 0xFF_FFFF) << 15)
+            | 0x6000
+            | SEED_BYTES[seed_select as usize & 7] as u64;
+        (seed0, CARD_SEED1)
+    }
+
+    /// Step both registers and return the next key byte.
+    fn next_byte(&mut self) -> u8 {
+        let x = self.x;
+        let y = self.y;
+        self.x = ((((x >> 5) ^ (x >> 17) ^ (x >> 18) ^ (x >> 31)) & 0xFF) + (x << 8)) & MASK;
+        self.y = ((((y >> 5) ^ (y >> 23) ^ (y >> 18) ^ (y >> 31)) & 0xFF) + (y << 8)) & MASK;
+        (self.x ^ self.y) as u8
+    }
+
+    /// Encrypt or decrypt `bytes` in transfer order.
+    pub(crate) fn apply(&mut self, bytes: &mut [u8]) {
+        for byte in bytes {
+            *byte ^= self.next_byte();
+        }
+    }
+}
+
+impl Savestate for Key2 {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u64(self.x);
+        w.u64(self.y);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.x = r.u64()? & MASK;
+        self.y = r.u64()? & MASK;
+        Ok(())
+    }
+}
+
+fn reverse_39(value: u64) -> u64 {
+    (value & MASK).reverse_bits() >> (64 - 39)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key2_round_trip() {
+        let (seed0, seed1) = Key2::card_seeds(0x12_3456, 0);
+        assert_eq!(seed0, 0x09_1A2B_6000 | 0xE8);
+        let mut console = Key2::new(seed0, seed1);
+        let mut card = console;
+
+        let mut command = [0xB7, 0, 0, 0x80, 0, 0, 0, 0];
+        console.apply(&mut command);
+        assert_ne!(command, [0xB7, 0, 0, 0x80, 0, 0, 0, 0]);
+        card.apply(&mut command);
+        assert_eq!(command, [0xB7, 0, 0, 0x80, 0, 0, 0, 0]);
+        assert_eq!(console, card);
+
+        // Zero seeds give a zero stream
+        let mut data = [0x12, 0x34];
+        Key2::default().apply(&mut data);
+        assert_eq!(data, [0x12, 0x34]);
+    }
+}
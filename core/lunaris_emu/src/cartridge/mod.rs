@@ -1,9 +1,27 @@
 //! Game Cartridge controller for Nintendo DS
 //! Handles ROM loading, encryption/decryption, and cartridge access
 
-use std::io::Write as _;
+pub(crate) mod banner;
+pub(crate) mod journal;
+pub(crate) mod key2;
+pub(crate) mod nitro;
+pub(crate) mod protocol;
+
+use std::collections::BTreeSet;
 use std::path::{Path, PathBuf};
 
+pub use banner::{Banner, ICON_SIZE};
+use key2::Key2;
+use nitro::SAVE_DATABASE_ENTRY_SIZE;
+pub use nitro::{FatEntry, HEADER_SIZE, Overlay, RomFile, RomHeader};
+pub use protocol::CartTraceEvent;
+
+use crate::error::EmuError;
+use crate::savestate::{Savestate, StateReader, StateWriter};
+
+/// Chip ID the card reports, that of a Macronix 64MB mask ROM.
+pub(crate) const CHIP_ID: u32 = 0x0000_3FC2;
+
 /// Cartridge command types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CartCommand {
@@ -27,7 +45,6 @@ pub enum CartCommand {
 
 impl CartCommand {
     /// Convert numeric value to CartCommand
-    #[expect(unused)]
     pub fn from_value(val: u32) -> Self {
         match val {
             0 => CartCommand::Empty,
@@ -64,7 +81,6 @@ pub enum AuxSpiCommand {
 
 impl AuxSpiCommand {
     /// Convert numeric value to AuxSpiCommand
-    #[expect(unused)]
     pub fn from_value(val: u32) -> Self {
         match val {
             0 => AuxSpiCommand::Empty,
@@ -79,29 +95,31 @@ impl AuxSpiCommand {
     }
 }
 
-/// ROM control register
-#[derive(Debug, Clone, Copy)]
-pub struct RegRomCtrl {
-    /// KEY1 gap clocks (timing parameter)
-    pub key1_gap: u32,
-    /// KEY2 data enabled
-    pub key2_data_enabled: bool,
-    /// Apply KEY2 seed
-    pub key2_apply_seed: bool,
-    /// KEY2 gap setting
-    pub key2_gap: u32,
-    /// KEY2 command enabled
-    pub key2_cmd_enabled: bool,
-    /// Word ready signal
-    pub word_ready: bool,
-    /// Block size (transfer size)
-    pub block_size: u32,
-    /// Use slow transfer timing
-    pub slow_transfer: bool,
-    /// KEY1 gap uses clock cycles
-    pub key1_gap_clocks: bool,
-    /// Block transfer in progress
-    pub block_busy: bool,
+lunaris_ds_bitfield::bitfield! {
+    /// ROM control register
+    #[derive(Debug, Clone, Copy)]
+    pub struct RegRomCtrl(u32) {
+        /// KEY1 gap clocks (timing parameter)
+        pub key1_gap: u32 [0..13],
+        /// KEY2 data enabled
+        pub key2_data_enabled: bool [13],
+        /// Apply KEY2 seed
+        pub key2_apply_seed: bool [15],
+        /// KEY2 gap setting
+        pub key2_gap: u32 [16..22],
+        /// KEY2 command enabled
+        pub key2_cmd_enabled: bool [22],
+        /// Word ready signal
+        pub word_ready: bool [23],
+        /// Block size (transfer size)
+        pub block_size: u32 [24..27],
+        /// Use slow transfer timing
+        pub slow_transfer: bool [27],
+        /// KEY1 gap uses clock cycles
+        pub key1_gap_clocks: bool [28],
+        /// Block transfer in progress
+        pub block_busy: bool [31],
+    }
 }
 
 impl RegRomCtrl {
@@ -120,55 +138,6 @@ impl RegRomCtrl {
             block_busy: false,
         }
     }
-
-    /// Get register value as 32-bit word
-    pub fn get(&self) -> u32 {
-        let mut value = 0;
-        value |= self.key1_gap;
-        if self.key2_data_enabled {
-            value |= 1 << 13;
-        }
-        if self.key2_apply_seed {
-            value |= 1 << 15;
-        }
-
-        value |= self.key2_gap << 16;
-        if self.key2_cmd_enabled {
-            value |= 1 << 22;
-        }
-        if self.word_ready {
-            value |= 1 << 23;
-        }
-        value |= self.block_size << 24;
-        if self.slow_transfer {
-            value |= 1 << 27;
-        }
-        if self.key1_gap_clocks {
-            value |= 1 << 28;
-        }
-        value |= 1 << 29; //Always one? Who knows
-
-        if self.block_busy {
-            value |= 1 << 31;
-        }
-
-        value
-    }
-
-    /// Set register value from 32-bit word
-    #[expect(unused)]
-    pub fn set(&mut self, value: u32) {
-        self.key1_gap = value & 0x3F;
-        self.key2_data_enabled = (value & (1 << 6)) != 0;
-        self.key2_apply_seed = (value & (1 << 7)) != 0;
-        self.key2_gap = (value >> 8) & 0x3F;
-        self.key2_cmd_enabled = (value & (1 << 14)) != 0;
-        self.word_ready = (value & (1 << 15)) != 0;
-        self.block_size = (value >> 16) & 0x7;
-        self.slow_transfer = (value & (1 << 19)) != 0;
-        self.key1_gap_clocks = (value & (1 << 20)) != 0;
-        self.block_busy = (value & (1 << 31)) != 0;
-    }
 }
 
 impl Default for RegRomCtrl {
@@ -177,21 +146,23 @@ impl Default for RegRomCtrl {
     }
 }
 
-/// Auxiliary SPI control register
-#[derive(Debug, Clone, Copy)]
-pub struct RegAuxSpiCnt {
-    /// Baud rate divider
-    pub bandwidth: u32,
-    /// Hold chip select after transfer
-    pub hold_chipselect: bool,
-    /// Transfer in progress
-    pub is_busy: bool,
-    /// Serial transfer mode
-    pub serial_transfer: bool,
-    /// Generate interrupt after transfer
-    pub irq_after_transfer: bool,
-    /// SPI enabled
-    pub enabled: bool,
+lunaris_ds_bitfield::bitfield! {
+    /// Auxiliary SPI control register
+    #[derive(Debug, Clone, Copy)]
+    pub struct RegAuxSpiCnt(u16) {
+        /// Baud rate divider
+        pub bandwidth: u32 [0..2],
+        /// Hold chip select after transfer
+        pub hold_chipselect: bool [6],
+        /// Transfer in progress
+        pub is_busy: bool [7],
+        /// Serial transfer mode
+        pub serial_transfer: bool [13],
+        /// Generate interrupt after transfer
+        pub irq_after_transfer: bool [14],
+        /// SPI enabled
+        pub enabled: bool [15],
+    }
 }
 
 impl RegAuxSpiCnt {
@@ -206,39 +177,6 @@ impl RegAuxSpiCnt {
             enabled: false,
         }
     }
-
-    /// Get register value as 16-bit halfword
-    #[expect(unused)]
-    pub fn get(&self) -> u16 {
-        let mut value = 0u16;
-        value |= (self.bandwidth & 0x3) as u16;
-        if self.hold_chipselect {
-            value |= 1 << 3;
-        }
-        if self.is_busy {
-            value |= 1 << 7;
-        }
-        if self.serial_transfer {
-            value |= 1 << 13;
-        }
-        if self.irq_after_transfer {
-            value |= 1 << 14;
-        }
-        if self.enabled {
-            value |= 1 << 15;
-        }
-        value
-    }
-
-    /// Set register value from 16-bit halfword
-    #[expect(unused)]
-    pub fn set(&mut self, value: u16) {
-        self.bandwidth = (value & 0x3) as u32;
-        self.hold_chipselect = (value & (1 << 3)) != 0;
-        self.serial_transfer = (value & (1 << 13)) != 0;
-        self.irq_after_transfer = (value & (1 << 14)) != 0;
-        self.enabled = (value & (1 << 15)) != 0;
-    }
 }
 
 impl Default for RegAuxSpiCnt {
@@ -270,8 +208,10 @@ pub struct NDSCart {
     pub(crate) save_type: u32,
     /// Save data has been modified
     pub(crate) dirty_save: bool,
-    /// Database size
-    pub(crate) database_size: u64,
+    /// Modified save pages, see [`journal::SAVE_PAGE_SIZE`]
+    pub(crate) dirty_save_pages: BTreeSet<usize>,
+    /// Save file of the loaded ROM
+    pub(crate) save_path: Option<PathBuf>,
     /// ROM size
     pub(crate) rom_size: u64,
 
@@ -311,9 +251,16 @@ pub struct NDSCart {
     encrypt_seed0: u64,
     /// Encryption seed 1
     encrypt_seed1: u64,
+    /// Console side of KEY2, see [`key2`]
+    pub(crate) key2_console: Key2,
+    /// Card side of KEY2, `None` until the card activates it
+    pub(crate) key2_card: Option<Key2>,
 
     /// Keycode for encryption (3 words)
     pub(crate) keycode: [u32; 3],
+
+    /// Last command started, until the frame trace takes it
+    pub(crate) pending_trace: Option<CartTraceEvent>,
 }
 
 impl Default for NDSCart {
@@ -335,7 +282,8 @@ impl NDSCart {
             save_size: 0,
             save_type: 0,
             dirty_save: false,
-            database_size: 0,
+            dirty_save_pages: BTreeSet::new(),
+            save_path: None,
             rom_size: 0,
             command_buffer: [0u8; 8],
             data_output: 0,
@@ -353,7 +301,10 @@ impl NDSCart {
             spi_write_enabled: false,
             encrypt_seed0: 0,
             encrypt_seed1: 0,
+            key2_console: Key2::default(),
+            key2_card: None,
             keycode: [0u32; 3],
+            pending_trace: None,
         }
     }
 
@@ -366,6 +317,8 @@ impl NDSCart {
         self.romctrl.word_ready = true;
         self.romctrl.block_busy = false;
         self.cmd_encrypt_mode = 0;
+        self.key2_console = Key2::default();
+        self.key2_card = None;
         self.auxspicnt.hold_chipselect = false;
         self.auxspicnt.is_busy = false;
         self.auxspicnt.serial_transfer = false;
@@ -374,6 +327,8 @@ impl NDSCart {
 
         self.spi_save.fill(0);
         self.dirty_save = false;
+        self.dirty_save_pages.clear();
+        self.save_path = None;
 
         if !self.rom.is_empty() {
             self.rom.fill(0);
@@ -384,6 +339,7 @@ impl NDSCart {
         self.spi_cmd = AuxSpiCommand::Empty;
         self.spi_data = 0;
         self.spi_params = 0;
+        self.pending_trace = None;
     }
 
     /// Loads save database file into memory.
@@ -392,7 +348,6 @@ impl NDSCart {
         use std::io::Read;
 
         self.save_database.clear();
-        self.database_size = 0;
 
         #[cfg(feature = "tracing")]
         tracing::info!("Loading save database: {}", file_name.display());
@@ -418,9 +373,8 @@ impl NDSCart {
         })?;
 
         let size = metadata.len();
-        self.database_size = size;
 
-        if size % 19 != 0 {
+        if size % SAVE_DATABASE_ENTRY_SIZE as u64 != 0 {
             #[cfg(feature = "tracing")]
             tracing::error!(
                 "Invalid save database format: {} size={size}",
@@ -458,27 +412,34 @@ impl NDSCart {
     // Loads ROM image and optional save data.
     // pub fn load_rom( &mut self, file_name: &Path, is_direct_boot_enabled: bool,) -> Result<(), CartridgeError>;
 
-    /// Writes save data to disk if modified.
+    /// Writes modified save pages to disk, see [`journal`].
     pub fn save_check(&mut self) -> Result<(), CartridgeError> {
         if !self.dirty_save {
             return Ok(());
         }
         self.dirty_save = false;
-
-        let path = PathBuf::from(format!("{}.sav", self.rom_name));
+        let pages = core::mem::take(&mut self.dirty_save_pages);
+        let Some(path) = &self.save_path else {
+            return Ok(());
+        };
 
         #[cfg(feature = "tracing")]
-        tracing::debug!("Flushing save file: {}", path.display());
+        tracing::debug!("Flushing {} save page(s): {}", pages.len(), path.display());
 
-        let mut file = std::fs::File::create(&path).map_err(|source| CartridgeError::SaveOpen {
-            path: path.clone(),
-            source,
-        })?;
-
-        file.write_all(&self.spi_save)
-            .map_err(|source| CartridgeError::SaveWrite { path, source })?;
+        journal::flush_save(path, &self.spi_save[..self.save_size], &pages).map_err(|source| {
+            CartridgeError::SaveWrite {
+                path: path.clone(),
+                source,
+            }
+        })
+    }
 
-        Ok(())
+    /// Write one save byte, marking its page for the next flush.
+    pub(crate) fn write_save(&mut self, index: usize, value: u8) {
+        self.spi_save[index] = value;
+        self.dirty_save = true;
+        self.dirty_save_pages
+            .insert(index / journal::SAVE_PAGE_SIZE);
     }
 
     /// Reads a command byte from command buffer.
@@ -522,7 +483,8 @@ impl NDSCart {
 
     /// Returns ROM CTRL register value.
     pub fn get_romctrl(&self) -> u32 {
-        self.romctrl.get()
+        // Bit 29 always reads as set
+        self.romctrl.get() | (1 << 29)
     }
 
     /// Returns cartridge output register value.
@@ -537,7 +499,7 @@ impl NDSCart {
 
     /// Returns AUXSPICNT register value.
     pub fn get_auxspicnt(&self) -> u16 {
-        todo!()
+        self.auxspicnt.get()
     }
 
     /// Reads AUXSPIDATA register value.
@@ -547,16 +509,16 @@ impl NDSCart {
 
     /// Sets high byte of AUXSPICNT register.
     pub fn set_hi_auxspicnt(&mut self, value: u8) {
-        self.auxspicnt.serial_transfer = (value & (1 << 5)) != 0;
-        self.auxspicnt.irq_after_transfer = (value & (1 << 6)) != 0;
-        self.auxspicnt.enabled = (value & (1 << 7)) != 0;
+        let lo = self.auxspicnt.get() & 0xFF;
+        self.auxspicnt.set(lo | ((value as u16) << 8));
     }
 
     /// Sets AUXSPICNT register value.
     pub fn set_auxspicnt(&mut self, value: u16) {
-        self.auxspicnt.bandwidth = (value & 0x3) as u32;
-        self.auxspicnt.hold_chipselect = (value & (1 << 6)) != 0;
-        self.set_hi_auxspicnt((value >> 8) as u8);
+        // Busy is read-only
+        let is_busy = self.auxspicnt.is_busy;
+        self.auxspicnt.set(value);
+        self.auxspicnt.is_busy = is_busy;
     }
 
     /// Handles AUX SPI data write and updates SPI state machine.
@@ -626,9 +588,17 @@ impl NDSCart {
                     #[cfg(not(feature = "tracing"))]
                     tracing::error!("Unrecognized AUXSPI cmd {}", value);
 
+                    self.pending_trace = Some(CartTraceEvent::Spi {
+                        opcode: value,
+                        command: AuxSpiCommand::Empty,
+                    });
                     return;
                 }
             }
+            self.pending_trace = Some(CartTraceEvent::Spi {
+                opcode: value,
+                command: self.spi_cmd,
+            });
         } else {
             match self.spi_cmd {
                 AuxSpiCommand::ReadStatusReg => {
@@ -644,9 +614,7 @@ impl NDSCart {
                             if self.spi_params < 2 {
                                 self.spi_addr = value as u32;
                             } else if self.spi_write_enabled {
-                                self.dirty_save = true;
-                                let idx = (self.spi_addr & 0xFF) as usize;
-                                self.spi_save[idx] = value;
+                                self.write_save((self.spi_addr & 0xFF) as usize, value);
                                 self.spi_addr += 1;
                             }
                         }
@@ -655,9 +623,10 @@ impl NDSCart {
                             if self.spi_params < 3 {
                                 self.spi_addr |= (value as u32) << ((2 - self.spi_params) * 8);
                             } else if self.spi_write_enabled {
-                                self.dirty_save = true;
-                                let idx = (self.spi_addr & (self.save_size as u32 - 1)) as usize;
-                                self.spi_save[idx] = value;
+                                self.write_save(
+                                    (self.spi_addr & (self.save_size as u32 - 1)) as usize,
+                                    value,
+                                );
                                 self.spi_addr += 1;
                             }
                         }
@@ -666,9 +635,10 @@ impl NDSCart {
                             if self.spi_params < 4 {
                                 self.spi_addr |= (value as u32) << ((3 - self.spi_params) * 8);
                             } else if self.spi_write_enabled {
-                                self.dirty_save = true;
-                                let idx = (self.spi_addr & (self.save_size as u32 - 1)) as usize;
-                                self.spi_save[idx] = 0;
+                                self.write_save(
+                                    (self.spi_addr & (self.save_size as u32 - 1)) as usize,
+                                    0,
+                                );
                                 self.spi_addr += 1;
                             }
                         }
@@ -729,9 +699,10 @@ impl NDSCart {
                             #[cfg(feature = "tracing")]
                             tracing::trace!("Page write {:08X}", self.spi_addr);
 
-                            self.dirty_save = true;
-                            let idx = (self.spi_addr & (self.save_size as u32 - 1)) as usize;
-                            self.spi_save[idx] = value;
+                            self.write_save(
+                                (self.spi_addr & (self.save_size as u32 - 1)) as usize,
+                                value,
+                            );
                             self.spi_addr += 1;
                         }
                     }
@@ -741,9 +712,7 @@ impl NDSCart {
                     if self.spi_params < 2 {
                         self.spi_addr = 0x100 | value as u32;
                     } else if self.spi_write_enabled {
-                        self.dirty_save = true;
-                        let idx = (self.spi_addr & 0x1FF) as usize;
-                        self.spi_save[idx] = value;
+                        self.write_save((self.spi_addr & 0x1FF) as usize, value);
                         self.spi_addr += 1;
 
                         if self.spi_addr == 0x200 {
@@ -787,15 +756,16 @@ impl NDSCart {
     pub fn set_romctrl(&mut self, value: u32) {
         let old_transfer_busy = self.romctrl.block_busy;
 
-        self.romctrl.key1_gap = value & 0x1FFF;
-        self.romctrl.key2_data_enabled = ((value >> 13) & 1) != 0;
-        self.romctrl.key2_apply_seed = ((value >> 15) & 1) != 0;
-        self.romctrl.key2_gap = (value >> 16) & 0x3F;
-        self.romctrl.key2_cmd_enabled = ((value >> 22) & 1) != 0;
-        self.romctrl.block_size = (value >> 24) & 0x7;
-        self.romctrl.slow_transfer = ((value >> 27) & 1) != 0;
-        self.romctrl.key1_gap_clocks = ((value >> 28) & 1) != 0;
-        self.romctrl.block_busy = ((value >> 31) & 1) != 0;
+        // Word ready is read-only
+        let word_ready = self.romctrl.word_ready;
+        self.romctrl.set(value);
+        self.romctrl.word_ready = word_ready;
+
+        // Apply seed is write-only
+        if self.romctrl.key2_apply_seed {
+            self.romctrl.key2_apply_seed = false;
+            self.key2_console = Key2::new(self.encrypt_seed0, self.encrypt_seed1);
+        }
 
         if !old_transfer_busy && self.romctrl.block_busy && self.auxspicnt.enabled {
             self.romctrl.word_ready = false;
@@ -809,14 +779,22 @@ impl NDSCart {
             if self.cmd_encrypt_mode != 0 {
                 self.cycles_left += self.romctrl.key1_gap as i32;
             }
+            let key1 = self.cmd_encrypt_mode == 1;
+
+            // Encrypted by the console, decrypted by a card in KEY2 mode
+            if self.romctrl.key2_cmd_enabled {
+                self.key2_console.apply(&mut self.command_buffer);
+            }
+            if let (2, Some(key2)) = (self.cmd_encrypt_mode, &mut self.key2_card) {
+                key2.apply(&mut self.command_buffer);
+            }
 
             if self.cmd_encrypt_mode == 1 {
                 self.cycles_left += self.romctrl.key1_gap as i32;
 
-                let mut data = [0u8; 8];
-                for (data, buffer) in data.iter_mut().zip(self.command_buffer) {
-                    *data = buffer;
-                }
+                // The command goes out most significant byte first
+                let mut data = self.command_buffer;
+                data.reverse();
 
                 // To [u32; 2]
                 let mut data = [
@@ -868,7 +846,13 @@ impl NDSCart {
                 0xB8 => self.command_id = CartCommand::GetChipId,
 
                 _ => match self.command_buffer[0] & 0xF0 {
-                    0x40 => self.command_id = CartCommand::Dummy,
+                    0x40 => {
+                        self.command_id = CartCommand::EnableKey2;
+                        let command = u64::from_be_bytes(self.command_buffer);
+                        let (seed0, seed1) =
+                            Key2::card_seeds((command >> 20) as u32, self.direct_read(0x13));
+                        self.key2_card = Some(Key2::new(seed0, seed1));
+                    }
                     0x10 => self.command_id = CartCommand::GetChipId,
                     0x20 => {
                         self.command_id = CartCommand::GetSecureAreaBlock;
@@ -881,7 +865,33 @@ impl NDSCart {
                     _ => {}
                 },
             }
+
+            self.pending_trace = Some(CartTraceEvent::Rom {
+                command: self.command_id,
+                bytes: self.command_buffer,
+                key1,
+                address: match self.command_id {
+                    CartCommand::ReadRom => self.rom_data_index as u32,
+                    CartCommand::GetSecureAreaBlock => self.secure_area_index,
+                    _ => 0,
+                },
+                length: self.bytes_left as u32,
+            });
+        }
+    }
+
+    /// Put a word from the card on the bus: encrypted by a card in KEY2
+    /// mode, decrypted by the console if ROMCTRL asks for it.
+    pub(crate) fn output_word(&mut self, word: u32) {
+        let mut bytes = word.to_le_bytes();
+        if let Some(key2) = &mut self.key2_card {
+            key2.apply(&mut bytes);
+        }
+        if self.romctrl.key2_data_enabled {
+            self.key2_console.apply(&mut bytes);
         }
+        self.data_output = u32::from_le_bytes(bytes);
+        self.romctrl.word_ready = true;
     }
 
     /// Sets low 32 bits of KEY2 seed 0.
@@ -959,7 +969,7 @@ impl NDSCart {
     }
 
     /// Performs KEY1 decryption on data buffer.
-    fn key1_decrypt(&mut self, data: &mut [u32]) {
+    pub(crate) fn key1_decrypt(&mut self, data: &mut [u32]) {
         let mut y = data[0];
         let mut x = data[1];
 
@@ -996,14 +1006,14 @@ impl NDSCart {
             self.keycode[1] = data[1];
         }
 
-        for i in 0..0x11 {
+        for i in 0..=0x11 {
             let mut value = self.read_keybuf_u32(i);
             value ^= byteswap_word(self.keycode[i % modulo as usize]);
             self.write_keybuf_u32(i, value);
         }
 
         let mut scratch = [0; 2];
-        for i in (0..=0x410).skip(1) {
+        for i in (0..=0x410).step_by(2) {
             {
                 let y = scratch[0];
                 let x = scratch[1];
@@ -1021,6 +1031,77 @@ impl NDSCart {
     // fn init_keycode(&mut self, _idcode: u32, _level: i32, _modulo: u32) ;
 }
 
+/// The ROM and save memory are not part of a savestate, see
+/// [`state`](crate::emulator::state).
+impl Savestate for NDSCart {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.bytes(&self.key1_buffer);
+        for &word in &self.keycode {
+            w.u32(word);
+        }
+        w.u32(self.cmd_encrypt_mode);
+        w.u32(self.romctrl.get());
+        w.u16(self.auxspicnt.get());
+        self.key2_console.save_state(w);
+        w.bool(self.key2_card.is_some());
+        self.key2_card.unwrap_or_default().save_state(w);
+        w.u64(self.encrypt_seed0);
+        w.u64(self.encrypt_seed1);
+
+        // ROM transfer
+        w.bytes(&self.command_buffer);
+        w.u8(self.command_id as u8);
+        for value in [
+            self.data_output,
+            self.rom_data_index as u32,
+            self.secure_area_index,
+            self.cycles_left as u32,
+            self.bytes_left as u32,
+        ] {
+            w.u32(value);
+        }
+
+        // Save memory transfer
+        w.u8(self.spi_cmd as u8);
+        w.u8(self.spi_data);
+        w.u32(self.spi_params as u32);
+        w.u32(self.spi_addr);
+        w.bool(self.spi_write_enabled);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        r.bytes(&mut self.key1_buffer)?;
+        for word in &mut self.keycode {
+            *word = r.u32()?;
+        }
+        self.cmd_encrypt_mode = r.u32()?;
+        self.romctrl.set(r.u32()?);
+        self.auxspicnt.set(r.u16()?);
+        self.key2_console.load_state(r)?;
+        let key2_active = r.bool()?;
+        let mut key2_card = Key2::default();
+        key2_card.load_state(r)?;
+        self.key2_card = key2_active.then_some(key2_card);
+        self.encrypt_seed0 = r.u64()?;
+        self.encrypt_seed1 = r.u64()?;
+
+        r.bytes(&mut self.command_buffer)?;
+        self.command_id = CartCommand::from_value(r.u8()? as u32);
+        self.data_output = r.u32()?;
+        self.rom_data_index = r.u32()? as usize;
+        self.secure_area_index = r.u32()?;
+        self.cycles_left = r.u32()? as i32;
+        self.bytes_left = r.u32()? as i32;
+
+        self.spi_cmd = AuxSpiCommand::from_value(r.u8()? as u32);
+        self.spi_data = r.u8()?;
+        self.spi_params = r.u32()? as i32;
+        self.spi_addr = r.u32()?;
+        self.spi_write_enabled = r.bool()?;
+        Ok(())
+    }
+}
+
 fn byteswap_word(word: u32) -> u32 {
     let mut result = 0;
     result |= word >> 24;
@@ -1097,4 +1178,30 @@ pub enum CartridgeError {
         path: PathBuf,
         source: std::io::Error,
     },
+
+    /// Save profile name is empty or not a plain file name part.
+    #[snafu(display("Invalid save profile name: '{name}'"))]
+    InvalidSaveProfile { name: String },
+
+    /// Directory holding the save profiles could not be read.
+    #[snafu(display("Failed to list save profiles in {}", path.display()))]
+    ListSaveProfiles {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// Save profile could not be deleted.
+    #[snafu(display("Failed to delete save file: {}", path.display()))]
+    DeleteSave {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// Save profile copy failed.
+    #[snafu(display("Failed to copy save {} to {}", from.display(), to.display()))]
+    CopySave {
+        from: PathBuf,
+        to: PathBuf,
+        source: std::io::Error,
+    },
 }
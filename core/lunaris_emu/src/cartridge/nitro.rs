@@ -0,0 +1,446 @@
+//! ROM header, FAT, overlay table and NitroFS parsing
+//!
+//! Everything here reads the loaded ROM image directly. Malformed entries
+//! (pointing outside the ROM) are skipped instead of failing, so tools still
+//! get whatever is usable out of trimmed or hacked dumps.
+use crate::cpu::arm_cpu::CpuType;
+
+use super::NDSCart;
+
+/// Size of one FAT entry (start, end).
+const FAT_ENTRY_SIZE: usize = 8;
+/// Size of one overlay table entry.
+const OVT_ENTRY_SIZE: usize = 32;
+/// Size of one FNT main table entry.
+const FNT_DIR_ENTRY_SIZE: usize = 8;
+/// Directory IDs start here; the root directory is 0xF000.
+const FNT_ROOT_DIR: u16 = 0xF000;
+/// Deeper nesting is treated as a corrupt (cyclic) table.
+const FNT_MAX_DEPTH: usize = 32;
+/// Size of the cartridge header.
+pub const HEADER_SIZE: usize = 0x200;
+/// Size of one save database entry: title and game code, then the save
+/// type in the last byte.
+pub(crate) const SAVE_DATABASE_ENTRY_SIZE: usize = 19;
+
+/// The cartridge header: what identifies the game, and the parts that
+/// locate code and tables.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RomHeader {
+    /// Game title, ASCII padded with zeros, see [`RomHeader::title`]
+    pub game_title: [u8; 12],
+    /// Four character game code, e.g. `*b"ADME"`
+    pub game_code: [u8; 4],
+    /// Two character maker code, e.g. `*b"01"` for Nintendo
+    pub maker_code: [u8; 2],
+    /// 0 for NDS, 2 for NDS + DSi, 3 for DSi only
+    pub unit_code: u8,
+    pub arm9_rom_offset: u32,
+    pub arm9_entry: u32,
+    pub arm9_ram_address: u32,
+    pub arm9_size: u32,
+    pub arm7_rom_offset: u32,
+    pub arm7_entry: u32,
+    pub arm7_ram_address: u32,
+    pub arm7_size: u32,
+    /// File name table
+    pub fnt_offset: u32,
+    pub fnt_size: u32,
+    /// File allocation table
+    pub fat_offset: u32,
+    pub fat_size: u32,
+    /// ARM9 overlay table
+    pub arm9_ovt_offset: u32,
+    pub arm9_ovt_size: u32,
+    /// ARM7 overlay table
+    pub arm7_ovt_offset: u32,
+    pub arm7_ovt_size: u32,
+    /// Icon and title banner, 0 if there is none, see
+    /// [`Banner`](super::Banner)
+    pub banner_offset: u32,
+}
+
+impl RomHeader {
+    /// Parse the header at the start of `rom`, `None` if it is shorter than
+    /// [`HEADER_SIZE`].
+    pub fn parse(rom: &[u8]) -> Option<Self> {
+        let header = rom.get(..HEADER_SIZE)?;
+        let w = |offset: usize| {
+            u32::from_le_bytes([
+                header[offset],
+                header[offset + 1],
+                header[offset + 2],
+                header[offset + 3],
+            ])
+        };
+        Some(Self {
+            game_title: header[0x00..0x0C].try_into().ok()?,
+            game_code: header[0x0C..0x10].try_into().ok()?,
+            maker_code: header[0x10..0x12].try_into().ok()?,
+            unit_code: header[0x12],
+            arm9_rom_offset: w(0x20),
+            arm9_entry: w(0x24),
+            arm9_ram_address: w(0x28),
+            arm9_size: w(0x2C),
+            arm7_rom_offset: w(0x30),
+            arm7_entry: w(0x34),
+            arm7_ram_address: w(0x38),
+            arm7_size: w(0x3C),
+            fnt_offset: w(0x40),
+            fnt_size: w(0x44),
+            fat_offset: w(0x48),
+            fat_size: w(0x4C),
+            arm9_ovt_offset: w(0x50),
+            arm9_ovt_size: w(0x54),
+            arm7_ovt_offset: w(0x58),
+            arm7_ovt_size: w(0x5C),
+            banner_offset: w(0x68),
+        })
+    }
+
+    /// Game title without its zero padding.
+    pub fn title(&self) -> String {
+        let len = self
+            .game_title
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(self.game_title.len());
+        String::from_utf8_lossy(&self.game_title[..len]).into_owned()
+    }
+
+    /// Save size `database` lists for this game, by title and game code.
+    ///
+    /// `None` if the game is missing or its save type is unknown.
+    pub fn database_save_size(&self, database: &[u8]) -> Option<usize> {
+        let entry = database
+            .chunks_exact(SAVE_DATABASE_ENTRY_SIZE)
+            .find(|entry| entry[..12] == self.game_title && entry[12..16] == self.game_code)?;
+
+        #[cfg(feature = "tracing")]
+        tracing::info!("Found ROM entry in database");
+
+        let size_index = entry[SAVE_DATABASE_ENTRY_SIZE - 1];
+        match size_index {
+            0x02 => Some(512),
+            0x03 => Some(1024 * 8),
+            0x04 => Some(1024 * 64),
+            0x05 => Some(1024 * 256),
+            0x06 => Some(1024 * 512),
+            0x07 => Some(1024 * 1024),
+            _ => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Unrecognized save format {}", size_index);
+                None
+            }
+        }
+    }
+}
+
+/// File allocation table entry, ROM offsets `start..end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FatEntry {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl FatEntry {
+    pub const fn len(&self) -> u32 {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// File of the ROM filesystem (NitroFS).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomFile {
+    /// Path from the root, `/` separated, without leading `/`
+    pub path: String,
+    /// File ID, index into the FAT
+    pub id: u16,
+    /// ROM range of the contents
+    pub rom: FatEntry,
+}
+
+/// Overlay table entry.
+#[derive(Debug, Clone, Copy)]
+pub struct Overlay {
+    /// CPU whose overlay table lists this overlay
+    pub cpu: CpuType,
+    pub id: u32,
+    /// Load address in RAM
+    pub ram_address: u32,
+    /// Size of the loaded code/data
+    pub ram_size: u32,
+    /// Zero-filled size following the loaded part
+    pub bss_size: u32,
+    pub static_init_start: u32,
+    pub static_init_end: u32,
+    /// FAT index of the overlay file
+    pub file_id: u32,
+    /// ROM range of the overlay file, if the FAT entry is valid
+    pub rom: Option<FatEntry>,
+}
+
+impl Overlay {
+    /// RAM range covered once loaded, including BSS.
+    pub const fn ram_end(&self) -> u32 {
+        self.ram_address
+            .wrapping_add(self.ram_size)
+            .wrapping_add(self.bss_size)
+    }
+}
+
+impl NDSCart {
+    fn rom_word(&self, offset: usize) -> u32 {
+        self.rom
+            .get(offset..offset + 4)
+            .map_or(0, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// ROM bytes `offset..offset + size`, if they are inside the ROM.
+    pub(crate) fn rom_slice(&self, offset: u32, size: u32) -> Option<&[u8]> {
+        let start = offset as usize;
+        self.rom.get(start..start.checked_add(size as usize)?)
+    }
+
+    /// Four character game code at header 0x0C, e.g. `*b"ADME"`.
+    pub fn game_code(&self) -> [u8; 4] {
+        self.rom_word(0x0C).to_le_bytes()
+    }
+
+    /// CRC16 of the header (0x00..0x15E) stored at header 0x15E.
+    pub fn header_checksum(&self) -> u16 {
+        self.rom_word(0x15E) as u16
+    }
+
+    /// Parse the header of the loaded ROM, reading zeros past its end.
+    pub fn header(&self) -> RomHeader {
+        let mut header = [0; HEADER_SIZE];
+        let len = self.rom.len().min(HEADER_SIZE);
+        header[..len].copy_from_slice(&self.rom[..len]);
+        RomHeader::parse(&header).unwrap_or_default()
+    }
+
+    /// File allocation table, indexed by file ID.
+    ///
+    /// Entries outside the ROM are kept (so IDs stay aligned) but empty.
+    pub fn fat(&self) -> Vec<FatEntry> {
+        let header = self.header();
+        let Some(table) = self.rom_slice(header.fat_offset, header.fat_size) else {
+            return Vec::new();
+        };
+
+        table
+            .chunks_exact(FAT_ENTRY_SIZE)
+            .map(|entry| {
+                let start = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
+                let end = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+                if start > end || end as u64 > self.rom.len() as u64 {
+                    FatEntry { start, end: start }
+                } else {
+                    FatEntry { start, end }
+                }
+            })
+            .collect()
+    }
+
+    /// Overlays of both CPUs, ARM9 first.
+    pub fn overlays(&self) -> Vec<Overlay> {
+        let header = self.header();
+        let fat = self.fat();
+        let tables = [
+            (CpuType::Arm9, header.arm9_ovt_offset, header.arm9_ovt_size),
+            (CpuType::Arm7, header.arm7_ovt_offset, header.arm7_ovt_size),
+        ];
+
+        let mut overlays = Vec::new();
+        for (cpu, offset, size) in tables {
+            let Some(table) = self.rom_slice(offset, size) else {
+                continue;
+            };
+            for entry in table.chunks_exact(OVT_ENTRY_SIZE) {
+                let w = |i: usize| {
+                    u32::from_le_bytes([entry[i], entry[i + 1], entry[i + 2], entry[i + 3]])
+                };
+                let file_id = w(0x18);
+                overlays.push(Overlay {
+                    cpu,
+                    id: w(0x00),
+                    ram_address: w(0x04),
+                    ram_size: w(0x08),
+                    bss_size: w(0x0C),
+                    static_init_start: w(0x10),
+                    static_init_end: w(0x14),
+                    file_id,
+                    rom: fat
+                        .get(file_id as usize)
+                        .copied()
+                        .filter(|entry| !entry.is_empty()),
+                });
+            }
+        }
+        overlays
+    }
+
+    /// All named files of the ROM filesystem, in FNT order.
+    ///
+    /// Overlays have no name and are not listed, see [`NDSCart::overlays`].
+    pub fn list_files(&self) -> Vec<RomFile> {
+        let header = self.header();
+        let (Some(fnt), fat) = (
+            self.rom_slice(header.fnt_offset, header.fnt_size),
+            self.fat(),
+        ) else {
+            return Vec::new();
+        };
+
+        let mut files = Vec::new();
+        walk_fnt_dir(fnt, &fat, FNT_ROOT_DIR, "", 0, &mut files);
+        files
+    }
+
+    /// Contents of the file at `path` (`/` separated, leading `/` optional).
+    pub fn read_file(&self, path: &str) -> Option<&[u8]> {
+        let path = path.trim_start_matches('/');
+        let file = self
+            .list_files()
+            .into_iter()
+            .find(|file| file.path == path)?;
+        self.rom_slice(file.rom.start, file.rom.len())
+    }
+}
+
+/// Append the files of directory `dir_id` (and its subdirectories) to `files`.
+fn walk_fnt_dir(
+    fnt: &[u8],
+    fat: &[FatEntry],
+    dir_id: u16,
+    prefix: &str,
+    depth: usize,
+    files: &mut Vec<RomFile>,
+) {
+    if depth > FNT_MAX_DEPTH {
+        return;
+    }
+    let dir_entry = (dir_id - FNT_ROOT_DIR) as usize * FNT_DIR_ENTRY_SIZE;
+    let Some(entry) = fnt.get(dir_entry..dir_entry + FNT_DIR_ENTRY_SIZE) else {
+        return;
+    };
+    let mut offset = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]) as usize;
+    let mut file_id = u16::from_le_bytes([entry[4], entry[5]]);
+
+    // Sub-table: length/type byte, name, and for directories the directory ID
+    while let Some(&type_len) = fnt.get(offset) {
+        let len = (type_len & 0x7F) as usize;
+        if len == 0 {
+            // End of table (0x00) or reserved (0x80)
+            return;
+        }
+        let Some(name) = fnt.get(offset + 1..offset + 1 + len) else {
+            return;
+        };
+        let path = format!("{prefix}{}", String::from_utf8_lossy(name));
+        offset += 1 + len;
+
+        if type_len & 0x80 != 0 {
+            let Some(id) = fnt.get(offset..offset + 2) else {
+                return;
+            };
+            offset += 2;
+            let sub_dir = u16::from_le_bytes([id[0], id[1]]);
+            if sub_dir > FNT_ROOT_DIR {
+                walk_fnt_dir(fnt, fat, sub_dir, &format!("{path}/"), depth + 1, files);
+            }
+        } else {
+            if let Some(&rom) = fat.get(file_id as usize) {
+                files.push(RomFile {
+                    path,
+                    id: file_id,
+                    rom,
+                });
+            }
+            file_id = file_id.wrapping_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Minimal ROM: header, one ARM9 overlay, and a FAT with two files.
+    pub(crate) fn test_rom() -> Vec<u8> {
+        let mut rom = vec![0u8; 0x400];
+        let mut put = |offset: usize, value: u32| {
+            rom[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+        // ARM9 binary
+        put(0x20, 0x200);
+        put(0x28, 0x0200_0000);
+        put(0x2C, 0x40);
+        // FAT at 0x300, two entries
+        put(0x48, 0x300);
+        put(0x4C, 0x10);
+        put(0x300, 0x380);
+        put(0x304, 0x390);
+        put(0x308, 0x390);
+        put(0x30C, 0x3A0);
+        // ARM9 overlay table at 0x240, overlay 0 is file 0
+        put(0x50, 0x240);
+        put(0x54, 0x20);
+        put(0x244, 0x0210_0000);
+        put(0x248, 0x10);
+        put(0x24C, 0x20);
+        put(0x258, 0);
+        // FNT at 0x280: root holds "a.bin" (file 1) and dir "d"
+        put(0x40, 0x280);
+        put(0x44, 0x40);
+        put(0x280, 0x10);
+        put(0x284, 0x0002_0001);
+        put(0x288, 0x20);
+        put(0x28C, 0xF000_0001);
+        rom[0x290..0x298].copy_from_slice(b"\x05a.bin\x81d");
+        rom[0x298..0x29A].copy_from_slice(&0xF001u16.to_le_bytes());
+        // "d" is empty
+        rom
+    }
+
+    #[test]
+    fn test_fat_and_overlays() {
+        let mut cart = NDSCart::new();
+        cart.rom = test_rom();
+
+        let fat = cart.fat();
+        assert_eq!(fat.len(), 2);
+        assert_eq!(
+            fat[1],
+            FatEntry {
+                start: 0x390,
+                end: 0x3A0
+            }
+        );
+
+        let overlays = cart.overlays();
+        assert_eq!(overlays.len(), 1);
+        assert_eq!(overlays[0].ram_address, 0x0210_0000);
+        assert_eq!(overlays[0].ram_end(), 0x0210_0030);
+        assert_eq!(overlays[0].rom, Some(fat[0]));
+    }
+
+    #[test]
+    fn test_nitro_fs() {
+        let mut cart = NDSCart::new();
+        cart.rom = test_rom();
+        cart.rom[0x390..0x3A0].fill(0xAB);
+
+        let files = cart.list_files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "a.bin");
+        assert_eq!(files[0].id, 1);
+        assert_eq!(cart.read_file("/a.bin"), Some(&[0xAB; 0x10][..]));
+        assert_eq!(cart.read_file("d/missing"), None);
+    }
+}
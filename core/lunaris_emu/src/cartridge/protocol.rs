@@ -0,0 +1,117 @@
+//! Cartridge protocol decoding for the frame trace
+//!
+//! Every ROM command started through ROMCTRL and every save command started
+//! through AUXSPIDATA is kept as a [`CartTraceEvent`], whose `Display` names
+//! the operation instead of dumping bus bytes, e.g. `GetHeader`,
+//! `ReadRom addr=0x00008000 len=0x200` or
+//! `KEY1 GetSecureAreaBlock addr=0x00005000 decrypted=2000500000000000`.
+use std::fmt;
+
+use super::{AuxSpiCommand, CartCommand};
+
+/// A decoded cartridge command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CartTraceEvent {
+    /// A ROM command, as the card interprets it
+    Rom {
+        command: CartCommand,
+        /// Command bytes after KEY1/KEY2 decryption
+        bytes: [u8; 8],
+        /// Sent while the card was in KEY1 mode
+        key1: bool,
+        /// ROM or secure area address of reads
+        address: u32,
+        /// Transfer length in bytes
+        length: u32,
+    },
+    /// First byte of an AUXSPI save command and what it started
+    Spi { opcode: u8, command: AuxSpiCommand },
+}
+
+impl fmt::Display for CartTraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Rom {
+                command,
+                bytes,
+                key1,
+                address,
+                length,
+            } => {
+                if key1 {
+                    f.write_str("KEY1 ")?;
+                }
+                match (command, bytes[0]) {
+                    (CartCommand::Dummy, 0x9F) => f.write_str("Dummy")?,
+                    (CartCommand::Dummy, _) => f.write_str("EnterMainData")?,
+                    (CartCommand::GetHeader, _) => f.write_str("GetHeader")?,
+                    (CartCommand::GetChipId, _) => f.write_str("GetChipId")?,
+                    (CartCommand::EnableKey1, _) => f.write_str("EnableKey1")?,
+                    (CartCommand::EnableKey2, _) => f.write_str("EnableKey2")?,
+                    (CartCommand::GetSecureAreaBlock, _) => {
+                        write!(f, "GetSecureAreaBlock addr={address:#010X}")?
+                    }
+                    (CartCommand::ReadRom, _) => {
+                        write!(f, "ReadRom addr={address:#010X} len={length:#X}")?
+                    }
+                    (CartCommand::Empty, _) => {
+                        write!(f, "Unknown cmd={:016X}", u64::from_be_bytes(bytes))?
+                    }
+                }
+                if key1 {
+                    write!(f, " decrypted={:016X}", u64::from_be_bytes(bytes))?;
+                }
+                Ok(())
+            }
+            Self::Spi { opcode, command } => {
+                let name = match (opcode, command) {
+                    (0x02, _) => "Write",
+                    (0x03, _) => "Read",
+                    (0x04, _) => "WriteDisable",
+                    (0x05, _) => "ReadStatus",
+                    (0x06, _) => "WriteEnable",
+                    (0x0A, AuxSpiCommand::PageWrite) => "PageWrite",
+                    (0x0A, _) => "WriteHigh",
+                    (0x0B, _) => "ReadHigh",
+                    _ => return write!(f, "SPI Unknown cmd={opcode:02X}"),
+                };
+                write!(f, "SPI {name}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cart_trace_event_display() {
+        let read = CartTraceEvent::Rom {
+            command: CartCommand::ReadRom,
+            bytes: [0xB7, 0, 0, 0x80, 0, 0, 0, 0],
+            key1: false,
+            address: 0x8000,
+            length: 0x200,
+        };
+        assert_eq!(read.to_string(), "ReadRom addr=0x00008000 len=0x200");
+
+        let secure_area = CartTraceEvent::Rom {
+            command: CartCommand::GetSecureAreaBlock,
+            bytes: [0x20, 0x00, 0x50, 0, 0, 0, 0, 0],
+            key1: true,
+            address: 0x5000,
+            length: 0x1000,
+        };
+        assert_eq!(
+            secure_area.to_string(),
+            "KEY1 GetSecureAreaBlock addr=0x00005000 decrypted=2000500000000000"
+        );
+
+        let spi = CartTraceEvent::Spi {
+            opcode: 0x05,
+            command: AuxSpiCommand::ReadStatusReg,
+        };
+        assert_eq!(spi.to_string(), "SPI ReadStatus");
+    }
+}
@@ -0,0 +1,272 @@
+//! Action Replay DS cheats
+//!
+//! A code is a list of 32-bit word pairs: writes, conditions, loops and
+//! copies over the ARM9 bus with an offset and a data register, as the
+//! Action Replay runs them from its VBLANK hook. Enabled codes run at the
+//! end of every frame.
+//!
+//! Not emulated: C4 (offset to the code itself) and C5 (counter) lines,
+//! which are skipped.
+use crate::emulator::Emulator;
+use crate::error::{CheatInvalidSnafu, EmuError};
+
+/// Lines one code runs per frame at most, so a runaway loop cannot hang
+/// the emulator.
+const MAX_LINES_PER_FRAME: usize = 0x1_0000;
+/// Longest F (memory copy) line, the size of main RAM.
+const MAX_COPY_LEN: u32 = 0x40_0000;
+
+/// Action Replay code, see [`Emulator::add_cheat`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    pub name: String,
+    pub enabled: bool,
+    /// Code words, in pairs
+    pub code: Vec<u32>,
+}
+
+impl Cheat {
+    /// Parse `code` in the usual listing format, 8-digit hex words
+    /// separated by spaces or newlines. The cheat starts enabled.
+    ///
+    /// # Errors
+    ///
+    /// On anything else, or an odd number of words.
+    pub fn parse(name: &str, code: &str) -> Result<Self, EmuError> {
+        let code = code
+            .split_whitespace()
+            .map(|word| match word.len() {
+                8 => u32::from_str_radix(word, 16).ok(),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .filter(|words| !words.is_empty() && words.len() % 2 == 0)
+            .ok_or_else(|| CheatInvalidSnafu { code }.build())?;
+        Ok(Self {
+            name: name.to_owned(),
+            enabled: true,
+            code,
+        })
+    }
+}
+
+/// Registers of a running code.
+#[derive(Debug, Default)]
+struct ArState {
+    offset: u32,
+    data: u32,
+    /// Lines run while `false`, except the ends of blocks
+    skip: bool,
+    /// `skip` outside each open condition
+    conditions: Vec<bool>,
+    /// Line after the C0 line, iterations left, and the conditions there
+    loop_start: usize,
+    loop_count: u32,
+    loop_conditions: Vec<bool>,
+    loop_skip: bool,
+}
+
+impl ArState {
+    fn push_condition(&mut self, holds: bool) {
+        self.conditions.push(self.skip);
+        self.skip |= !holds;
+    }
+
+    /// D1/D2: back to the loop start while iterations are left.
+    fn next_iteration(&mut self, pc: &mut usize) -> bool {
+        if self.loop_count == 0 {
+            return false;
+        }
+        self.loop_count -= 1;
+        *pc = self.loop_start;
+        self.conditions.clone_from(&self.loop_conditions);
+        self.skip = self.loop_skip;
+        true
+    }
+}
+
+impl Emulator {
+    /// Add `cheat` and return its index.
+    pub fn add_cheat(&mut self, cheat: Cheat) -> usize {
+        self.cheats.push(cheat);
+        self.cheats.len() - 1
+    }
+
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    /// Enable or disable the cheat at `index`, if any.
+    pub fn set_cheat_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(cheat) = self.cheats.get_mut(index) {
+            cheat.enabled = enabled;
+        }
+    }
+
+    pub fn remove_cheat(&mut self, index: usize) -> Option<Cheat> {
+        (index < self.cheats.len()).then(|| self.cheats.remove(index))
+    }
+
+    /// Run the enabled cheats, once per frame.
+    pub(crate) fn cheats_end_frame(&mut self) {
+        let cheats = std::mem::take(&mut self.cheats);
+        for cheat in cheats.iter().filter(|cheat| cheat.enabled) {
+            self.run_cheat(&cheat.code);
+        }
+        self.cheats = cheats;
+    }
+
+    fn run_cheat(&mut self, code: &[u32]) {
+        let mut ar = ArState::default();
+        let mut pc = 0;
+        for _ in 0..MAX_LINES_PER_FRAME {
+            let (Some(&a), Some(&b)) = (code.get(pc), code.get(pc + 1)) else {
+                return;
+            };
+            pc += 2;
+            let address = a & 0x0FFF_FFFF;
+            match a >> 28 {
+                0xD => self.ar_d_line(&mut ar, a >> 24, b, &mut pc),
+                // Skipped lines still open conditions and step over data
+                0x3..=0xA if ar.skip => ar.push_condition(false),
+                0xE if ar.skip => pc += (b as usize).div_ceil(8) * 2,
+                _ if ar.skip => {}
+
+                0x0 => self.arm9_write_word(address.wrapping_add(ar.offset), b),
+                0x1 => self.arm9_write_halfword(address.wrapping_add(ar.offset), b as u16),
+                0x2 => self.arm9_write_byte(address.wrapping_add(ar.offset), b as u8),
+                op @ 0x3..=0xA => {
+                    let holds = self.ar_condition(op, address, ar.offset, b);
+                    ar.push_condition(holds);
+                }
+                0xB => ar.offset = self.arm9_read_word(address.wrapping_add(ar.offset)),
+                0xC => match a >> 24 {
+                    0xC0 => {
+                        ar.loop_start = pc;
+                        ar.loop_count = b;
+                        ar.loop_conditions.clone_from(&ar.conditions);
+                        ar.loop_skip = ar.skip;
+                    }
+                    0xC6 => self.arm9_write_word(b, ar.offset),
+                    _ => {}
+                },
+                0xE => {
+                    let words = (b as usize).div_ceil(8) * 2;
+                    let data = code.get(pc..pc + words).unwrap_or_default();
+                    let bytes = data.iter().flat_map(|word| word.to_le_bytes());
+                    for (i, byte) in (0..b).zip(bytes) {
+                        self.arm9_write_byte(address.wrapping_add(ar.offset).wrapping_add(i), byte);
+                    }
+                    pc += words;
+                }
+                _ => {
+                    for i in 0..b.min(MAX_COPY_LEN) {
+                        let byte = self.arm9_read_byte(ar.offset.wrapping_add(i));
+                        self.arm9_write_byte(address.wrapping_add(i), byte);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Condition lines: 3-6 compare a word with `b`, 7-A a halfword with
+    /// the low half of `b` after clearing the bits set in its high half.
+    /// Address 0 compares at the offset.
+    fn ar_condition(&mut self, op: u32, address: u32, offset: u32, b: u32) -> bool {
+        let address = if address == 0 { offset } else { address };
+        let (expected, value) = match op {
+            0x3..=0x6 => (b, self.arm9_read_word(address)),
+            _ => {
+                let mask = b >> 16;
+                let value = self.arm9_read_halfword(address) as u32 & !mask;
+                (b & 0xFFFF, value)
+            }
+        };
+        match op {
+            0x3 | 0x7 => expected > value,
+            0x4 | 0x8 => expected < value,
+            0x5 | 0x9 => expected == value,
+            _ => expected != value,
+        }
+    }
+
+    /// D lines: block ends and the data register.
+    fn ar_d_line(&mut self, ar: &mut ArState, op: u32, b: u32, pc: &mut usize) {
+        match op {
+            0xD0 => ar.skip = ar.conditions.pop().unwrap_or(false),
+            0xD1 | 0xD2 if ar.next_iteration(pc) => {}
+            // Loop done: D1 leaves it, D2 also resets everything
+            0xD1 => {
+                ar.conditions.clone_from(&ar.loop_conditions);
+                ar.skip = ar.loop_skip;
+            }
+            0xD2 => *ar = ArState::default(),
+            _ if ar.skip => {}
+            0xD3 => ar.offset = b,
+            0xD4 => ar.data = ar.data.wrapping_add(b),
+            0xD5 => ar.data = b,
+            0xD6 => {
+                self.arm9_write_word(b.wrapping_add(ar.offset), ar.data);
+                ar.offset = ar.offset.wrapping_add(4);
+            }
+            0xD7 => {
+                self.arm9_write_halfword(b.wrapping_add(ar.offset), ar.data as u16);
+                ar.offset = ar.offset.wrapping_add(2);
+            }
+            0xD8 => {
+                self.arm9_write_byte(b.wrapping_add(ar.offset), ar.data as u8);
+                ar.offset = ar.offset.wrapping_add(1);
+            }
+            0xD9 => ar.data = self.arm9_read_word(b.wrapping_add(ar.offset)),
+            0xDA => ar.data = self.arm9_read_halfword(b.wrapping_add(ar.offset)) as u32,
+            0xDB => ar.data = self.arm9_read_byte(b.wrapping_add(ar.offset)) as u32,
+            0xDC => ar.offset = ar.offset.wrapping_add(b),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_replay() {
+        assert!(Cheat::parse("bad", "02000000").is_err());
+        assert!(Cheat::parse("bad", "0200000 00000001").is_err());
+
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.arm9_write_word(0x0210_0000, 5);
+        let code = "
+            52100000 00000005
+            12100004 0000BEEF
+            D2000000 00000000
+            62100000 00000005
+            02100008 00000001
+            D0000000 00000000
+            D3000000 02100010
+            C0000000 00000002
+            D5000000 00000007
+            D7000000 00000000
+            D2000000 00000000
+            E2100020 00000005
+            44332211 00000055
+        ";
+        let index = emu.add_cheat(Cheat::parse("test", code).unwrap());
+        emu.cheats_end_frame();
+        assert_eq!(emu.arm9_read_halfword(0x0210_0004), 0xBEEF);
+        assert_eq!(emu.arm9_read_word(0x0210_0008), 0);
+        assert_eq!(emu.arm9_read_word(0x0210_0010), 0x0007_0007);
+        assert_eq!(emu.arm9_read_halfword(0x0210_0014), 0x0007);
+        assert_eq!(emu.arm9_read_word(0x0210_0020), 0x4433_2211);
+        assert_eq!(emu.arm9_read_byte(0x0210_0024), 0x55);
+
+        emu.arm9_write_halfword(0x0210_0004, 0);
+        emu.set_cheat_enabled(index, false);
+        emu.cheats_end_frame();
+        assert_eq!(emu.arm9_read_halfword(0x0210_0004), 0);
+        assert!(emu.remove_cheat(index).is_some());
+        assert!(emu.cheats().is_empty());
+    }
+}
@@ -3,11 +3,41 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 //! cpuinsters.hpp
 //!
+use crate::cpu::coprocessor_15::Cp15;
+use crate::error::EmuError;
+use crate::savestate::{Savestate, StateReader, StateWriter};
+
+/// General-purpose register r0-r15
+///
+/// Register fields decoded from instructions are wrapped with [`Reg::new`],
+/// so an index is always in range. Indices past r15 are caught in debug
+/// builds and wrap around otherwise.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Reg(u8);
+
+impl Reg {
+    #[inline]
+    pub const fn new(index: u32) -> Self {
+        debug_assert!(index < 16, "register index out of range");
+        Self((index & 0xF) as u8)
+    }
 
-/// General-purpose register indices
-pub const REG_SP: u32 = 13;
-pub const REG_LR: u32 = 14;
-pub const REG_PC: u32 = 15;
+    #[inline]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl std::fmt::Display for Reg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "r{}", self.0)
+    }
+}
+
+/// Registers with a fixed role
+pub const REG_SP: Reg = Reg(13);
+pub const REG_LR: Reg = Reg(14);
+pub const REG_PC: Reg = Reg(15);
 
 #[inline]
 pub const fn carry_add(a: u32, b: u32) -> bool {
@@ -132,7 +162,7 @@ impl PsrFlags {
 }
 
 /// This type exists to avoid cross references between emu and cpu.
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum CpuType {
     #[default]
     Arm7,
@@ -160,7 +190,6 @@ pub struct ArmCpu {
     /// Stack pointer for FIQ mode
     sp_fiq: u32,
     /// Stack pointer for abort mode
-    #[expect(unused)]
     sp_abt: u32,
     /// Stack pointer for undefined mode
     sp_und: u32,
@@ -173,7 +202,6 @@ pub struct ArmCpu {
     /// Link register for FIQ mode
     lr_fiq: u32,
     /// Link register for abort mode
-    #[expect(unused)]
     lr_abt: u32,
     /// Link register for undefined mode
     lr_und: u32,
@@ -213,6 +241,12 @@ pub struct ArmCpu {
     /// - Format: [region][access_type]
     /// - Access types: 0=n32, 1=s32, 2=n16, 3=s16
     data_waitstates: [[i32; 4]; 16],
+
+    /// Areas of the waitstate tables whose code fetches hit the
+    /// instruction cache, one bit per area
+    code_cached: u16,
+    /// Areas of the waitstate tables whose data accesses hit the data cache
+    data_cached: u16,
 }
 
 impl ArmCpu {
@@ -341,12 +375,26 @@ impl ArmCpu {
         self.jp(self.exception_base, true);
     }
 
-    /// Boot directly to an entry point
-    pub fn direct_boot(&mut self, entry_point: u32) {
-        self.jp(entry_point, true);
-        self.regs[12] = entry_point;
-        self.regs[13] = entry_point;
+    /// Boot directly to an entry point, in the state the firmware leaves
+    /// behind: System mode with interrupts disabled, r0-r11 cleared, r12 and
+    /// lr at the entry point, and the System, IRQ and Supervisor stacks at
+    /// `stacks`.
+    pub fn direct_boot(&mut self, entry_point: u32, stacks: [u32; 3]) {
+        let [sp_sys, sp_irq, sp_svc] = stacks;
+        self.update_reg_mode(PsrMode::System);
         self.cpsr.mode = PsrMode::System;
+        self.cpsr.irq_disabled = true;
+        self.cpsr.fiq_disabled = true;
+        self.regs = [0; 16];
+        self.regs[12] = entry_point;
+        self.regs[13] = sp_sys;
+        self.regs[14] = entry_point;
+        self.sp_irq = sp_irq;
+        self.sp_svc = sp_svc;
+        self.lr_irq = 0;
+        self.lr_svc = 0;
+        self.halted = false;
+        self.jp(entry_point, true);
     }
 
     // pub fn run(&mut self)
@@ -385,6 +433,18 @@ impl ArmCpu {
         self.cpsr.irq_disabled = true;
         self.jp(self.exception_base + 0x04, true);
     }
+    /// Prefetch abort, raised by BKPT
+    pub fn handle_prefetch_abort(&mut self) {
+        let value = self.cpsr.get();
+        self.spsr[PsrMode::Abort as usize].set(value);
+
+        // Address of the aborting instruction + 4
+        self.lr_abt = self.regs[15].wrapping_sub(if self.cpsr.thumb_on { 0 } else { 4 });
+        self.update_reg_mode(PsrMode::Abort);
+        self.cpsr.mode = PsrMode::Abort;
+        self.cpsr.irq_disabled = true;
+        self.jp(self.exception_base + 0x0C, true);
+    }
     pub fn handle_irq(&mut self) {
         let value = self.cpsr.get();
         self.spsr[PsrMode::Irq as usize].set(value);
@@ -443,11 +503,15 @@ impl ArmCpu {
                 core::mem::swap(&mut self.regs[13], &mut self.sp_svc);
                 core::mem::swap(&mut self.regs[14], &mut self.lr_svc);
             }
+            PsrMode::Abort => {
+                core::mem::swap(&mut self.regs[13], &mut self.sp_abt);
+                core::mem::swap(&mut self.regs[14], &mut self.lr_abt);
+            }
             PsrMode::Undefined => {
                 core::mem::swap(&mut self.regs[13], &mut self.sp_und);
                 core::mem::swap(&mut self.regs[14], &mut self.lr_und);
             }
-            PsrMode::User | PsrMode::Abort | PsrMode::System => {}
+            PsrMode::User | PsrMode::System => {}
         }
 
         match new_mode {
@@ -466,11 +530,15 @@ impl ArmCpu {
                 core::mem::swap(&mut self.regs[13], &mut self.sp_svc);
                 core::mem::swap(&mut self.regs[14], &mut self.lr_svc);
             }
+            PsrMode::Abort => {
+                core::mem::swap(&mut self.regs[13], &mut self.sp_abt);
+                core::mem::swap(&mut self.regs[14], &mut self.lr_abt);
+            }
             PsrMode::Undefined => {
                 core::mem::swap(&mut self.regs[13], &mut self.sp_und);
                 core::mem::swap(&mut self.regs[14], &mut self.lr_und);
             }
-            PsrMode::User | PsrMode::Abort | PsrMode::System => {}
+            PsrMode::User | PsrMode::System => {}
         }
     }
 
@@ -527,18 +595,15 @@ impl ArmCpu {
     }
 
     /// Register access
-    ///
-    /// # Panics
-    /// if id > 15
-    pub const fn get_register(&self, id: i32) -> u32 {
-        self.regs[id as usize]
+    #[inline]
+    pub const fn get_register(&self, reg: Reg) -> u32 {
+        self.regs[reg.index()]
     }
 
     /// Set value to CPU register.
-    /// # Panics
-    /// if id > 15
-    pub const fn set_register(&mut self, id: i32, value: u32) {
-        self.regs[id as usize] = value;
+    #[inline]
+    pub const fn set_register(&mut self, reg: Reg, value: u32) {
+        self.regs[reg.index()] = value;
     }
 
     /// Condition code evaluation
@@ -591,11 +656,25 @@ impl ArmCpu {
         self.timestamp.wrapping_sub(self.last_timestamp) as i64
     }
 
+    /// Base address of the exception vectors
+    pub const fn get_exception_base(&self) -> u32 {
+        self.exception_base
+    }
+
+    /// Follow the exception vector base and caches set in `cp15`.
+    ///
+    /// Cached areas are timed as if every access hits the cache.
+    pub fn apply_cp15(&mut self, cp15: &Cp15) {
+        self.exception_base = cp15.exception_base();
+        self.code_cached = cp15.cached_areas(true);
+        self.data_cached = cp15.cached_areas(false);
+    }
+
     /// Get program counter (Current instruction pointer)
     ///
     /// Via register.15th
     pub const fn get_pc(&self) -> u32 {
-        self.regs[REG_PC as usize]
+        self.regs[REG_PC.index()]
     }
 
     pub const fn get_current_instr(&self) -> u32 {
@@ -614,42 +693,68 @@ impl ArmCpu {
         &mut self.cpsr
     }
 
+    /// Set the waitstates of a 16-bit bus area from the time its first and
+    /// sequential accesses take, in ARM7 cycles. A 32-bit access is split
+    /// into two halfwords.
+    pub const fn set_bus16_timing(&mut self, area: usize, first: i32, second: i32) {
+        let scale = if self.cpu_id <= 0 { 2 } else { 1 };
+        let waits = [first + second, 2 * second, first, second];
+        let mut access = 0;
+        while access < 4 {
+            self.code_waitstates[area][access] = waits[access] * scale - 1;
+            self.data_waitstates[area][access] = waits[access] * scale - 1;
+            access += 1;
+        }
+    }
+
+    /// Waitstates of a code fetch, none on an instruction cache hit
+    const fn code_waitstate(&self, index: usize, access: usize) -> u64 {
+        match self.code_cached & (1 << index) {
+            0 => self.code_waitstates[index][access] as u64,
+            _ => 0,
+        }
+    }
+
+    /// Waitstates of a data access, none on a data cache hit
+    const fn data_waitstate(&self, index: usize, access: usize) -> u64 {
+        match self.data_cached & (1 << index) {
+            0 => self.data_waitstates[index][access] as u64,
+            _ => 0,
+        }
+    }
+
     //WaitState bullshit
     pub const fn add_n32_code(&mut self, address: u32, cycles: i32) {
         let idx = ((address & 0x0F00_0000) >> 24) as usize;
-        self.timestamp += (1 + self.code_waitstates[idx][0]) as u64 * cycles as u64;
+        self.timestamp += (1 + self.code_waitstate(idx, 0)) * cycles as u64;
     }
     pub const fn add_s32_code(&mut self, address: u32, cycles: i32) {
         let idx = ((address & 0x0F00_0000) >> 24) as usize;
-        self.timestamp += (1 + self.code_waitstates[idx][1]) as u64 * cycles as u64;
+        self.timestamp += (1 + self.code_waitstate(idx, 1)) * cycles as u64;
     }
     pub const fn add_n16_code(&mut self, address: u32, cycles: i32) {
         let idx = ((address & 0x0F00_0000) >> 24) as usize;
-        self.timestamp += (1 + self.code_waitstates[idx][2]) as u64 * cycles as u64;
+        self.timestamp += (1 + self.code_waitstate(idx, 2)) * cycles as u64;
     }
     pub const fn add_s16_code(&mut self, address: u32, cycles: i32) {
         let idx = ((address & 0x0F00_0000) >> 24) as usize;
-        self.timestamp += (1 + self.code_waitstates[idx][3]) as u64 * cycles as u64;
+        self.timestamp += (1 + self.code_waitstate(idx, 3)) * cycles as u64;
     }
     pub const fn add_n32_data(&mut self, address: u32, cycles: i32) {
         let index = ((address & 0x0F000000) >> 24) as usize;
-        let data_waitstates = self.data_waitstates[index][0] as u64;
-        self.timestamp += (1 + data_waitstates) * cycles as u64;
+        self.timestamp += (1 + self.data_waitstate(index, 0)) * cycles as u64;
     }
     pub const fn add_s32_data(&mut self, address: u32, cycles: i32) {
         let index = ((address & 0x0F000000) >> 24) as usize;
-        let data_waitstates = self.data_waitstates[index][1] as u64;
-        self.timestamp += (1 + data_waitstates) * cycles as u64;
+        self.timestamp += (1 + self.data_waitstate(index, 1)) * cycles as u64;
     }
     pub const fn add_n16_data(&mut self, address: u32, cycles: i32) {
         let index = ((address & 0x0F000000) >> 24) as usize;
-        let data_waitstates = self.data_waitstates[index][2] as u64;
-        self.timestamp += (1 + data_waitstates) * cycles as u64;
+        self.timestamp += (1 + self.data_waitstate(index, 2)) * cycles as u64;
     }
     pub const fn add_s16_data(&mut self, address: u32, cycles: i32) {
         let index = ((address & 0x0F000000) >> 24) as usize;
-        let data_waitstates = self.data_waitstates[index][3] as u64;
-        self.timestamp += (1 + data_waitstates) * cycles as u64;
+        self.timestamp += (1 + self.data_waitstate(index, 3)) * cycles as u64;
     }
 
     /// Cycle accounting
@@ -662,97 +767,86 @@ impl ArmCpu {
     }
 
     // All data manipulation methods here
-    pub const fn andd(&mut self, dst: i32, src: i32, operand: i32, set_condition_codes: bool) {
-        let result = (src & operand) as u32;
-        self.set_register(dst, result);
 
+    /// Write a data-processing result to `dst`. Writing PC with the S bit
+    /// set returns from an exception: CPSR is restored from SPSR instead of
+    /// taking the flags, so this returns whether the caller still has to set
+    /// them.
+    fn write_alu_result(&mut self, dst: Reg, result: u32, set_condition_codes: bool) -> bool {
+        if dst != REG_PC {
+            self.set_register(dst, result);
+            return set_condition_codes;
+        }
         if set_condition_codes {
-            self.set_zero_neg_flags(result);
+            self.spsr_to_cpsr();
         }
+        self.jp(result, false);
+        false
     }
-    pub const fn orr(&mut self, dst: i32, src: i32, operand: i32, set_condition_codes: bool) {
-        let result = (src | operand) as u32;
-        self.set_register(dst, result);
 
-        if set_condition_codes {
+    pub fn andd(&mut self, dst: Reg, src: u32, operand: u32, set_condition_codes: bool) {
+        let result = src & operand;
+        if self.write_alu_result(dst, result, set_condition_codes) {
+            self.set_zero_neg_flags(result);
+        }
+    }
+    pub fn orr(&mut self, dst: Reg, src: u32, operand: u32, set_condition_codes: bool) {
+        let result = src | operand;
+        if self.write_alu_result(dst, result, set_condition_codes) {
             self.set_zero_neg_flags(result);
         }
     }
     /// XOR
-    pub const fn eor(&mut self, dst: i32, src: i32, operand: i32, set_condition_codes: bool) {
-        let result = (src ^ operand) as u32;
-        self.set_register(dst, result);
-
-        if set_condition_codes {
+    pub fn eor(&mut self, dst: Reg, src: u32, operand: u32, set_condition_codes: bool) {
+        let result = src ^ operand;
+        if self.write_alu_result(dst, result, set_condition_codes) {
             self.set_zero_neg_flags(result);
         }
     }
-    pub fn add(&mut self, dst: u32, src: u32, operand: u32, set_condition_codes: bool) {
-        let unsigned_result: u64 = (src + operand) as u64;
-
-        if dst == REG_PC {
-            if set_condition_codes {
-                #[cfg(feature = "tracing")]
-                tracing::error!("PC: dst and set_condition_codes is unsupported.");
-            } else {
-                self.jp((unsigned_result & 0xFFFFFFFF) as u32, true);
-            }
-        } else {
-            self.set_register(dst as i32, (unsigned_result & 0xFFFFFFFF) as u32);
-            if set_condition_codes {
-                self.cmp(src, operand);
-            }
+    pub fn add(&mut self, dst: Reg, src: u32, operand: u32, set_condition_codes: bool) {
+        let result = src.wrapping_add(operand);
+        if self.write_alu_result(dst, result, set_condition_codes) {
+            self.cmn(src, operand);
         }
     }
-    pub fn sub(&mut self, dst: u32, src: u32, operand: u32, set_condition_codes: bool) {
-        let unsigned_result: u64 = (src - operand) as u64;
-
-        if dst == REG_PC {
-            if set_condition_codes {
-                let index = self.cpsr.mode as usize;
-                self.update_reg_mode(self.spsr[index].mode);
-                self.cpsr.set(self.spsr[index].get());
-                self.jp((unsigned_result & 0xFFFFFFFF) as u32, false);
-            } else {
-                self.jp((unsigned_result & 0xFFFFFFFF) as u32, true);
-            }
-        } else {
-            self.set_register(dst as i32, (unsigned_result & 0xFFFFFFFF) as u32);
-            if set_condition_codes {
-                self.cmp(src, operand);
-            }
+    /// Also RSB, with the operands swapped
+    pub fn sub(&mut self, dst: Reg, src: u32, operand: u32, set_condition_codes: bool) {
+        let result = src.wrapping_sub(operand);
+        if self.write_alu_result(dst, result, set_condition_codes) {
+            self.cmp(src, operand);
         }
     }
-    pub fn adc(&mut self, dst: u32, src: u32, operand: u32, set_condition_codes: bool) {
-        let carry = if self.cpsr.carry { 1 } else { 0 };
-        self.add(dst, src + carry, operand, set_condition_codes);
-
-        if set_condition_codes {
-            let temp = src + operand;
-            let res = temp + carry;
+    pub fn adc(&mut self, dst: Reg, src: u32, operand: u32, set_condition_codes: bool) {
+        let carry = self.cpsr.carry as u32;
+        let temp = src.wrapping_add(operand);
+        let result = temp.wrapping_add(carry);
+        if self.write_alu_result(dst, result, set_condition_codes) {
+            self.set_zero_neg_flags(result);
             self.cpsr.carry = carry_add(src, operand) | carry_add(temp, carry);
-            self.cpsr.overflow = add_overflow(src, operand, temp) | add_overflow(temp, carry, res);
+            // The carry can only push the sum over once, so V is that of the
+            // whole sum
+            self.cpsr.overflow = (src ^ result) & (operand ^ result) & 0x8000_0000 != 0;
         }
     }
-    pub fn sbc(&mut self, dst: u32, src: u32, operand: u32, set_condition_codes: bool) {
-        let borrow = if self.cpsr.carry { 0 } else { 1 };
-        self.add(dst, src + borrow, operand, set_condition_codes);
-
-        if set_condition_codes {
-            let temp = src + operand;
-            let res = temp - borrow;
-            self.cpsr.carry = carry_sub(src, operand) | carry_sub(temp, borrow);
-            self.cpsr.overflow = sub_overflow(src, operand, temp) | sub_overflow(temp, borrow, res);
+    /// Also RSC, with the operands swapped
+    pub fn sbc(&mut self, dst: Reg, src: u32, operand: u32, set_condition_codes: bool) {
+        let borrow = !self.cpsr.carry as u32;
+        let temp = src.wrapping_sub(operand);
+        let result = temp.wrapping_sub(borrow);
+        if self.write_alu_result(dst, result, set_condition_codes) {
+            self.set_zero_neg_flags(result);
+            self.cpsr.carry = carry_sub(src, operand) & carry_sub(temp, borrow);
+            self.cpsr.overflow = sub_overflow(src, operand, result);
         }
     }
 
     pub const fn cmp(&mut self, x: u32, y: u32) {
-        let result = x - y;
+        let result = x.wrapping_sub(y);
         self.set_zero_neg_flags(result);
         self.set_cv_sub_flags(x, y, result);
     }
     pub const fn cmn(&mut self, x: u32, y: u32) {
-        let result = x + y;
+        let result = x.wrapping_add(y);
         self.set_zero_neg_flags(result);
         self.set_cv_add_flags(x, y, result);
     }
@@ -763,48 +857,42 @@ impl ArmCpu {
         self.set_zero_neg_flags(x ^ y);
     }
 
-    pub fn mov(&mut self, dst: u32, operand: u32, alter_flags: bool) {
-        if dst == REG_PC && alter_flags {
-            let index = self.cpsr.mode;
-            self.update_reg_mode(self.spsr[index as usize].mode);
-            self.cpsr.set(self.spsr[index as usize].get());
-            self.jp(operand, true);
+    pub fn mov(&mut self, dst: Reg, operand: u32, alter_flags: bool) {
+        if self.write_alu_result(dst, operand, alter_flags) {
+            self.set_zero_neg_flags(operand);
         }
     }
 
-    pub const fn mul(&mut self, dst: u32, src: u32, operand: u32, set_condition_codes: bool) {
-        let result = (src * operand) as u64;
-        let truncated = (result & 0xFFFFFFFF) as u32;
-        self.set_register(dst as i32, truncated);
+    pub fn mul(&mut self, dst: Reg, src: u32, operand: u32, set_condition_codes: bool) {
+        let truncated = src.wrapping_mul(operand);
+        self.set_register(dst, truncated);
 
         if set_condition_codes {
             self.set_zero_neg_flags(truncated);
         }
     }
 
-    pub const fn bic(&mut self, dst: u32, src: u32, operand: u32, alter_flags: bool) {
+    pub fn bic(&mut self, dst: Reg, src: u32, operand: u32, alter_flags: bool) {
         let result = src & !operand;
-        self.set_register(dst as i32, result);
-        if alter_flags {
+        if self.write_alu_result(dst, result, alter_flags) {
             self.set_zero_neg_flags(result);
         }
     }
 
-    pub const fn mvn(&mut self, dst: u32, operand: u32, alter_flags: bool) {
-        self.set_register(dst as i32, !operand);
-        if alter_flags {
+    pub fn mvn(&mut self, dst: Reg, operand: u32, alter_flags: bool) {
+        if self.write_alu_result(dst, !operand, alter_flags) {
             self.set_zero_neg_flags(!operand);
         }
     }
 
     pub fn mrs(&mut self, instruction: u32) {
         let using_cpsr = (instruction & (1 << 22)) == 0;
-        let dst = (instruction >> 12) & 0xF;
+        let dst = Reg::new((instruction >> 12) & 0xF);
 
         if using_cpsr {
-            self.set_register(dst as i32, self.cpsr.get());
+            self.set_register(dst, self.cpsr.get());
         } else {
-            self.set_register(dst as i32, self.spsr[self.cpsr.mode as usize].get());
+            self.set_register(dst, self.spsr[self.cpsr.mode as usize].get());
         }
     }
 
@@ -824,7 +912,7 @@ impl ArmCpu {
             let shift = (instruction & 0xF00) >> 7;
             self.rotr32(s, shift, false)
         } else {
-            self.get_register((instruction & 0xF) as i32)
+            self.get_register(Reg::new(instruction & 0xF))
         };
 
         let mut bitmask: u32 = 0;
@@ -913,7 +1001,7 @@ impl ArmCpu {
         if shift > 31 {
             if alter_flags {
                 self.set_zero_neg_flags(0);
-                self.cpsr.carry = (value & (1 << 0)) != 0;
+                self.cpsr.carry = shift == 32 && (value & (1 << 0)) != 0;
             }
             return 0;
         }
@@ -931,7 +1019,14 @@ impl ArmCpu {
     }
 
     pub const fn lsr(&mut self, value: u32, shift: i32, alter_flags: bool) -> u32 {
-        if shift > 31 {
+        if shift > 32 {
+            if alter_flags {
+                self.set_zero_neg_flags(0);
+                self.cpsr.carry = false;
+            }
+            return 0;
+        }
+        if shift == 32 {
             return self.lsr_32(value, alter_flags);
         }
         let result = value >> shift;
@@ -955,7 +1050,7 @@ impl ArmCpu {
         if shift > 31 {
             return self.asr_32(value, alter_flags);
         }
-        let result = value >> shift;
+        let result = ((value as i32) >> shift) as u32;
         if alter_flags {
             self.set_zero_neg_flags(result);
             if shift > 0 {
@@ -966,7 +1061,7 @@ impl ArmCpu {
     }
 
     pub const fn asr_32(&mut self, value: u32, alter_flags: bool) -> u32 {
-        let result = value >> 31;
+        let result = ((value as i32) >> 31) as u32;
         if alter_flags {
             self.set_zero_neg_flags(result);
             self.cpsr.carry = (value & (1 << 31)) != 0;
@@ -994,7 +1089,8 @@ impl ArmCpu {
         const MASK: u32 = 0x1F;
 
         if alter_flags && (c > 0) {
-            self.cpsr.carry = (n & (1 << (c - 1))) != 0;
+            // Rotating by a multiple of 32 leaves the value and carries bit 31
+            self.cpsr.carry = (n & (1 << ((c - 1) & MASK))) != 0;
         };
         c &= MASK;
 
@@ -1008,3 +1104,98 @@ impl ArmCpu {
         result
     }
 }
+
+impl Savestate for ArmCpu {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.bool(self.halted);
+        for value in [
+            self.sp_svc,
+            self.sp_irq,
+            self.sp_fiq,
+            self.sp_abt,
+            self.sp_und,
+            self.lr_svc,
+            self.lr_irq,
+            self.lr_fiq,
+            self.lr_abt,
+            self.lr_und,
+        ] {
+            w.u32(value);
+        }
+        for &reg in self.fiq_regs.iter().chain(&self.regs) {
+            w.u32(reg);
+        }
+        w.u32(self.cpsr.get());
+        for spsr in &self.spsr {
+            w.u32(spsr.get());
+        }
+        w.u32(self.exception_base);
+        w.u64(self.timestamp);
+        w.u64(self.last_timestamp);
+        w.u32(self.current_instr);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.halted = r.bool()?;
+        for value in [
+            &mut self.sp_svc,
+            &mut self.sp_irq,
+            &mut self.sp_fiq,
+            &mut self.sp_abt,
+            &mut self.sp_und,
+            &mut self.lr_svc,
+            &mut self.lr_irq,
+            &mut self.lr_fiq,
+            &mut self.lr_abt,
+            &mut self.lr_und,
+        ] {
+            *value = r.u32()?;
+        }
+        for reg in self.fiq_regs.iter_mut().chain(&mut self.regs) {
+            *reg = r.u32()?;
+        }
+        self.cpsr.set(r.u32()?);
+        for spsr in &mut self.spsr {
+            spsr.set(r.u32()?);
+        }
+        self.exception_base = r.u32()?;
+        self.timestamp = r.u64()?;
+        self.last_timestamp = r.u64()?;
+        self.current_instr = r.u32()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shifter_edge_cases() {
+        let mut cpu = ArmCpu::new(0, CpuType::Arm9);
+
+        // ASR keeps the sign
+        assert_eq!(cpu.asr(0x8000_0000, 4, true), 0xF800_0000);
+        assert!(!cpu.cpsr.carry);
+        assert_eq!(cpu.asr(0x8000_0000, 32, true), 0xFFFF_FFFF);
+        assert!(cpu.cpsr.carry);
+
+        // LSL by 32 carries bit 0, by more carries nothing
+        assert_eq!(cpu.lsl(1, 32, true), 0);
+        assert!(cpu.cpsr.carry);
+        assert_eq!(cpu.lsl(1, 33, true), 0);
+        assert!(!cpu.cpsr.carry);
+
+        // LSR by 32 carries bit 31, by more carries nothing
+        assert_eq!(cpu.lsr(0x8000_0000, 32, true), 0);
+        assert!(cpu.cpsr.carry);
+        assert_eq!(cpu.lsr(0x8000_0000, 33, true), 0);
+        assert!(!cpu.cpsr.carry);
+
+        // ROR by 32 leaves the value and carries bit 31
+        assert_eq!(cpu.rotr32(0x8000_0001, 32, true), 0x8000_0001);
+        assert!(cpu.cpsr.carry);
+        assert_eq!(cpu.rotr32(0x8000_0001, 64, true), 0x8000_0001);
+        assert!(cpu.cpsr.carry);
+    }
+}
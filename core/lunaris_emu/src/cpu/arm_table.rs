@@ -2,1038 +2,1044 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 //! armtable.cpp
 //!
+use std::marker::PhantomData;
+
 use super::interpreter::arm_instruction::*;
 use crate::cpu::arm_cpu::CpuType;
-use crate::emulator::Emulator;
+use crate::cpu::bus::Bus;
 
 /// Function pointer type for ARM/Thumb instruction interpreters
-pub type InterpreterFunc = fn(emu: &mut Emulator, cpu_type: CpuType, instruction: u32);
+pub type InterpreterFunc<B> = fn(emu: &mut B, cpu_type: CpuType, instruction: u32);
+
+/// arm interpreter function table, instantiated per bus
+pub struct ArmTable<B>(PhantomData<B>);
 
-/// arm interpreter function table
-#[rustfmt::skip]
-pub static ARM_TABLE: [InterpreterFunc; 4096] = [
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, multiply, data_processing, store_halfword,
-    data_processing, undefined, data_processing, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, multiply, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, multiply, data_processing, store_halfword,
-    data_processing, undefined, data_processing, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, multiply, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, store_halfword,
-    data_processing, undefined, data_processing, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, store_halfword,
-    data_processing, undefined, data_processing, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, multiply_long, data_processing, store_halfword,
-    data_processing, undefined, data_processing, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, multiply_long, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, multiply_long, data_processing, store_halfword,
-    data_processing, undefined, data_processing, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, multiply_long, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, multiply_long, data_processing, store_halfword,
-    data_processing, undefined, data_processing, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, multiply_long, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, multiply_long, data_processing, store_halfword,
-    data_processing, undefined, data_processing, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, multiply_long, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, saturated_op, data_processing, data_processing,
-    signed_halfword_multiply, swap, signed_halfword_multiply, store_halfword,
-    signed_halfword_multiply, undefined, signed_halfword_multiply, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, branch_exchange, data_processing, blx_reg,
-    data_processing, saturated_op, data_processing, data_processing,
-    signed_halfword_multiply, undefined, signed_halfword_multiply, store_halfword,
-    signed_halfword_multiply, undefined, signed_halfword_multiply, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, saturated_op, data_processing, data_processing,
-    signed_halfword_multiply, swap, signed_halfword_multiply, store_halfword,
-    signed_halfword_multiply, undefined, signed_halfword_multiply, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, count_leading_zeros, data_processing, data_processing,
-    data_processing, saturated_op, data_processing, data_processing,
-    signed_halfword_multiply, undefined, signed_halfword_multiply, store_halfword,
-    signed_halfword_multiply, undefined, signed_halfword_multiply, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, store_halfword,
-    data_processing, undefined, data_processing, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, store_halfword,
-    data_processing, undefined, data_processing, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, store_halfword,
-    data_processing, undefined, data_processing, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, store_halfword,
-    data_processing, undefined, data_processing, store_doubleword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, undefined, data_processing, load_halfword,
-    data_processing, load_signed_byte, data_processing, load_signed_halfword,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    data_processing, data_processing, data_processing, data_processing,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    store_word, store_word, store_word, store_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    load_word, load_word, load_word, load_word,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    store_byte, store_byte, store_byte, store_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    load_byte, load_byte, load_byte, load_byte,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    store_block, store_block, store_block, store_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    load_block, load_block, load_block, load_block,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch, branch, branch, branch,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    branch_link, branch_link, branch_link, branch_link,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, undefined, undefined, undefined,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi,
-    swi, swi, swi, swi
-];
+impl<B: Bus> ArmTable<B> {
+    #[rustfmt::skip]
+    pub const TABLE: [InterpreterFunc<B>; 4096] = [
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, multiply, data_processing, store_halfword,
+        data_processing, load_doubleword, data_processing, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, multiply, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, multiply, data_processing, store_halfword,
+        data_processing, load_doubleword, data_processing, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, multiply, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, store_halfword,
+        data_processing, load_doubleword, data_processing, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, store_halfword,
+        data_processing, load_doubleword, data_processing, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, multiply_long, data_processing, store_halfword,
+        data_processing, load_doubleword, data_processing, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, multiply_long, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, multiply_long, data_processing, store_halfword,
+        data_processing, load_doubleword, data_processing, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, multiply_long, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, multiply_long, data_processing, store_halfword,
+        data_processing, load_doubleword, data_processing, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, multiply_long, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, multiply_long, data_processing, store_halfword,
+        data_processing, load_doubleword, data_processing, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, multiply_long, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, saturated_op, data_processing, data_processing,
+        signed_halfword_multiply, swap, signed_halfword_multiply, store_halfword,
+        signed_halfword_multiply, load_doubleword, signed_halfword_multiply, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, branch_exchange, data_processing, blx_reg,
+        data_processing, saturated_op, data_processing, breakpoint,
+        signed_halfword_multiply, undefined, signed_halfword_multiply, store_halfword,
+        signed_halfword_multiply, load_doubleword, signed_halfword_multiply, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, saturated_op, data_processing, data_processing,
+        signed_halfword_multiply, swap, signed_halfword_multiply, store_halfword,
+        signed_halfword_multiply, load_doubleword, signed_halfword_multiply, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, count_leading_zeros, data_processing, data_processing,
+        data_processing, saturated_op, data_processing, data_processing,
+        signed_halfword_multiply, undefined, signed_halfword_multiply, store_halfword,
+        signed_halfword_multiply, load_doubleword, signed_halfword_multiply, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, store_halfword,
+        data_processing, load_doubleword, data_processing, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, store_halfword,
+        data_processing, load_doubleword, data_processing, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, store_halfword,
+        data_processing, load_doubleword, data_processing, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, store_halfword,
+        data_processing, load_doubleword, data_processing, store_doubleword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, undefined, data_processing, load_halfword,
+        data_processing, load_signed_byte, data_processing, load_signed_halfword,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        data_processing, data_processing, data_processing, data_processing,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        store_word, store_word, store_word, store_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        load_word, load_word, load_word, load_word,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        store_byte, store_byte, store_byte, store_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        load_byte, load_byte, load_byte, load_byte,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        store_block, store_block, store_block, store_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        load_block, load_block, load_block, load_block,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch, branch, branch, branch,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        branch_link, branch_link, branch_link, branch_link,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer,
+        coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer,
+        coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer,
+        coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer,
+        coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer,
+        coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer,
+        coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer,
+        coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer, coprocessor_double_transfer,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, undefined, undefined, undefined,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        undefined, coprocessor_reg_transfer, undefined, coprocessor_reg_transfer,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi,
+        swi, swi, swi, swi
+    ];
+}
@@ -0,0 +1,245 @@
+//! Cache of decoded basic blocks
+//!
+//! A CPU built with the `block_cache` feature keeps the code it runs from RAM
+//! fetched and decoded, one basic block per entry, and replays it from here
+//! instead of reading and decoding every instruction again. Blocks end at
+//! the first instruction that may branch; execution leaving a block
+//! anywhere else simply looks up the block at the new PC.
+//!
+//! With the `jit` feature a block that opens with a run of data-processing
+//! instructions also gets that run compiled to host code, see
+//! [`BlockCache::run_compiled`].
+//!
+//! The cache knows nothing about memory: its owner fills it and drops it
+//! when the code may have changed.
+use std::collections::HashMap;
+use std::fmt;
+
+#[cfg(feature = "jit")]
+use lunaris_ds_jit::{AluInstr, CompiledBlock, JitState};
+
+use super::arm_table::InterpreterFunc;
+use super::bus::Bus;
+use super::instruction_table::ThumbInstr;
+use super::interpreter::thumb_instruction::{thumb_decode, thumb_execute};
+use super::interpreter::{arm_execute, arm_handler};
+use crate::cpu::arm_cpu::CpuType;
+
+/// Instructions in a block at most
+pub const MAX_BLOCK_LEN: usize = 64;
+
+/// Shortest run worth compiling, below it the call costs more than it saves
+#[cfg(feature = "jit")]
+const MIN_COMPILED_LEN: usize = 2;
+
+/// A decoded instruction
+pub enum Op<B> {
+    Arm(u32, InterpreterFunc<B>),
+    Thumb(u32, ThumbInstr),
+}
+
+impl<B> Clone for Op<B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<B> Copy for Op<B> {}
+
+impl<B: Bus> Op<B> {
+    pub fn arm(instruction: u32) -> Self {
+        Self::Arm(instruction, arm_handler(instruction))
+    }
+
+    pub fn thumb(instruction: u32) -> Self {
+        Self::Thumb(instruction, thumb_decode(instruction))
+    }
+
+    pub const fn instruction(self) -> u32 {
+        match self {
+            Self::Arm(instruction, _) | Self::Thumb(instruction, _) => instruction,
+        }
+    }
+
+    /// The instruction for the code generator, if it handles it
+    #[cfg(feature = "jit")]
+    pub const fn alu(self) -> Option<AluInstr> {
+        match self {
+            Self::Arm(instruction, _) => lunaris_ds_jit::decode_arm(instruction),
+            Self::Thumb(instruction, _) => lunaris_ds_jit::decode_thumb(instruction as u16),
+        }
+    }
+
+    /// Internal cycles the interpreter spends on the instruction besides
+    /// the fetch, for the ones compiled code runs
+    #[cfg(feature = "jit")]
+    const fn internal_cycles(self) -> i32 {
+        match self {
+            // Thumb LSL, LSR, ASR #imm5
+            Self::Thumb(instruction, _) if instruction >> 11 < 3 => 1,
+            _ => 0,
+        }
+    }
+
+    /// Whether the block ends after this instruction because it may write
+    /// the PC or change the CPU state
+    pub fn ends_block(self) -> bool {
+        match self {
+            Self::Arm(instruction, _) => {
+                instruction >> 28 == 0xF
+                    // B, BL, SWI, coprocessor
+                    || (instruction >> 25) & 7 == 0b101
+                    || (instruction >> 24) & 0xF >= 0xC
+                    // LDM with the PC in the list
+                    || ((instruction >> 25) & 7 == 0b100 && instruction & (1 << 15) != 0)
+                    // BX, BLX, MSR, and anything with Rd = PC
+                    || (instruction >> 12) & 0xF == 15
+                    || instruction & 0x0FB0_0000 == 0x0120_0000
+                    || instruction & 0x0FB0_0000 == 0x0320_0000
+            }
+            Self::Thumb(instruction, opcode) => match opcode {
+                ThumbInstr::Branch
+                | ThumbInstr::CondBranch
+                | ThumbInstr::LongBranch
+                | ThumbInstr::LongBlx
+                | ThumbInstr::Breakpoint
+                | ThumbInstr::Undefined
+                | ThumbInstr::Swi => true,
+                // ADD / MOV / BX with the PC
+                ThumbInstr::HiRegOp => instruction & 0x87 == 0x87 || instruction & 0x300 == 0x300,
+                ThumbInstr::Pop => instruction & 0x100 != 0,
+                _ => false,
+            },
+        }
+    }
+
+    /// Execute the instruction, fetched by
+    /// [`fetched`](super::interpreter::fetched)
+    pub fn execute(self, emu: &mut B, cpu_type: CpuType) {
+        match self {
+            Self::Arm(instruction, handler) => arm_execute(emu, cpu_type, instruction, handler),
+            Self::Thumb(_, opcode) => thumb_execute(emu, cpu_type, opcode),
+        }
+    }
+}
+
+struct Block<B> {
+    ops: Vec<Op<B>>,
+    /// The leading run of `ops` as host code, and its internal cycles
+    #[cfg(feature = "jit")]
+    compiled: Option<(CompiledBlock, i32)>,
+}
+
+/// What [`BlockCache::run_compiled`] ran
+#[cfg(feature = "jit")]
+pub struct CompiledRun {
+    /// Instructions executed
+    pub count: usize,
+    /// The last of them
+    pub last: u32,
+    /// Internal cycles they took, besides their fetches
+    pub internal_cycles: i32,
+}
+
+/// Decoded blocks of one CPU, by start address.
+pub struct BlockCache<B> {
+    /// Keyed by address, bit 0 set for Thumb code
+    blocks: HashMap<u32, Block<B>>,
+    /// Block, index and address of the instruction expected next
+    cursor: Option<(u32, usize, u32)>,
+}
+
+impl<B> Default for BlockCache<B> {
+    fn default() -> Self {
+        Self {
+            blocks: HashMap::new(),
+            cursor: None,
+        }
+    }
+}
+
+impl<B> fmt::Debug for BlockCache<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockCache")
+            .field("blocks", &self.blocks.len())
+            .finish()
+    }
+}
+
+const fn key(address: u32, thumb: bool) -> u32 {
+    address | thumb as u32
+}
+
+impl<B: Bus> BlockCache<B> {
+    /// Drop every block.
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.cursor = None;
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Instruction at `address`, following on from the last one while
+    /// execution runs through a block. `None` if no block holds it.
+    pub fn next(&mut self, address: u32, thumb: bool) -> Option<Op<B>> {
+        let size = if thumb { 2 } else { 4 };
+        let op_at = |block: u32, index: usize| {
+            Some((block, index, *self.blocks.get(&block)?.ops.get(index)?))
+        };
+        let found = self
+            .cursor
+            .filter(|&(block, _, expected)| expected == address && block & 1 == thumb as u32)
+            .and_then(|(block, index, _)| op_at(block, index))
+            .or_else(|| op_at(key(address, thumb), 0));
+        self.cursor = found.map(|(block, index, _)| (block, index + 1, address.wrapping_add(size)));
+        found.map(|(_, _, op)| op)
+    }
+
+    /// Add the block of `ops` starting at `address`.
+    pub fn insert(&mut self, address: u32, thumb: bool, ops: Vec<Op<B>>) {
+        #[cfg(feature = "jit")]
+        let compiled = {
+            let run: Vec<_> = ops.iter().map_while(|op| op.alu()).collect();
+            let cycles = ops[..run.len()].iter().map(|op| op.internal_cycles()).sum();
+            (run.len() >= MIN_COMPILED_LEN)
+                .then(|| lunaris_ds_jit::compile(&run))
+                .flatten()
+                .map(|compiled| (compiled, cycles))
+        };
+        let block = Block {
+            ops,
+            #[cfg(feature = "jit")]
+            compiled,
+        };
+        self.blocks.insert(key(address, thumb), block);
+        self.cursor = None;
+    }
+
+    /// Run the compiled code of the block starting at `address` on `state`,
+    /// moving the cursor past the instructions it covers. `None`, having
+    /// done nothing, if no block starts there or it has no compiled code.
+    #[cfg(feature = "jit")]
+    pub fn run_compiled(
+        &mut self,
+        address: u32,
+        thumb: bool,
+        state: &mut JitState,
+    ) -> Option<CompiledRun> {
+        let block = key(address, thumb);
+        let Block { ops, compiled } = self.blocks.get(&block)?;
+        let (compiled, internal_cycles) = compiled.as_ref()?;
+        compiled.run(state);
+
+        let count = compiled.instructions();
+        let size = if thumb { 2 } else { 4 };
+        self.cursor = Some((block, count, address.wrapping_add(count as u32 * size)));
+        Some(CompiledRun {
+            count,
+            last: ops[count - 1].instruction(),
+            internal_cycles: *internal_cycles,
+        })
+    }
+}
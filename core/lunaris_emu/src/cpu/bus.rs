@@ -0,0 +1,67 @@
+//! What the interpreters need from the system around them
+//!
+//! The ARM7TDMI and ARM946E-S interpreters only touch the outside world
+//! through [`Bus`]: the CPU state of each core, memory, and the few
+//! instructions whose effect belongs to the system (SWI, undefined
+//! instructions, BKPT and CP15 transfers). The emulator implements it for the whole DS;
+//! anything else can implement it over its own memory map and drive the
+//! cores with [`step`](super::step).
+use crate::cpu::arm_cpu::{ArmCpu, CpuType};
+use crate::cpu::coprocessor_15::Cp15;
+
+/// Memory and CPU state seen by the interpreters.
+///
+/// Addresses are passed as the instruction computed them; alignment and
+/// rotation of unaligned loads are done by the interpreter.
+pub trait Bus {
+    fn get_cpu(&self, cpu_type: CpuType) -> &ArmCpu;
+    fn get_cpu_mut(&mut self, cpu_type: CpuType) -> &mut ArmCpu;
+
+    fn read_word(&mut self, address: u32, cpu_type: CpuType) -> u32;
+    fn read_halfword(&mut self, address: u32, cpu_type: CpuType) -> u16;
+    fn read_byte(&mut self, address: u32, cpu_type: CpuType) -> u8;
+    fn write_word(&mut self, address: u32, word: u32, cpu_type: CpuType);
+    fn write_halfword(&mut self, address: u32, halfword: u16, cpu_type: CpuType);
+    fn write_byte(&mut self, address: u32, byte: u8, cpu_type: CpuType);
+
+    /// Whether an `STM` whose lowest address is `address` goes to
+    /// [`Bus::write_words`] in one call instead of word by word.
+    fn burst_write(&self, _address: u32, _cpu_type: CpuType) -> bool {
+        false
+    }
+
+    /// Store consecutive words from `address` up, for [`Bus::burst_write`].
+    fn write_words(&mut self, address: u32, words: &[u32], cpu_type: CpuType) {
+        for (i, &word) in words.iter().enumerate() {
+            self.write_word(address.wrapping_add(i as u32 * 4), word, cpu_type);
+        }
+    }
+
+    /// The CP15 reached by `MRC`/`MCR p15`, `None` to ignore them.
+    fn cp15_mut(&mut self, _cpu_type: CpuType) -> Option<&mut Cp15> {
+        None
+    }
+
+    /// `MCR p15` changed the CP15 of `cpu_type`, e.g. its TCM regions.
+    fn cp15_written(&mut self, _cpu_type: CpuType) {}
+
+    /// `cpu_type` executed `SWI`.
+    fn swi(&mut self, cpu_type: CpuType) {
+        self.get_cpu_mut(cpu_type).handle_swi();
+    }
+
+    /// `cpu_type` executed an undefined instruction.
+    fn undefined_instruction(&mut self, cpu_type: CpuType) {
+        self.get_cpu_mut(cpu_type).handle_undefined();
+    }
+
+    /// The ARM9 executed `BKPT`.
+    fn bkpt(&mut self, cpu_type: CpuType) {
+        self.get_cpu_mut(cpu_type).handle_prefetch_abort();
+    }
+
+    /// Whether to trace the disassembly of every ARM9 instruction.
+    fn trace_instructions(&self) -> bool {
+        false
+    }
+}
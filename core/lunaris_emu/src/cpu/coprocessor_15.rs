@@ -1,5 +1,7 @@
 //! ARM9 Coprocessor 15 (CP15) - System Control Coprocessor
 //! Manages ARM9 memory protection unit, caches, TCM, and control registers
+use crate::error::EmuError;
+use crate::savestate::{Savestate, StateReader, StateWriter};
 
 /// CP15 Control Register
 #[derive(Debug, Clone, Copy)]
@@ -107,6 +109,60 @@ impl Default for ControlReg {
     }
 }
 
+/// Size of a TCM region register: 512 bytes shifted by bits 1-5, at least
+/// 4 KB and at most 2 GB.
+const fn tcm_region_size(region: u32) -> u32 {
+    let shift = (region >> 1) & 0x1F;
+    match shift {
+        0..3 => 0x1000,
+        3..22 => 0x200 << shift,
+        _ => 0x8000_0000,
+    }
+}
+
+/// Control register value at reset: exception vectors at 0xFFFF0000
+const CONTROL_RESET: u32 = 0x0000_2078;
+/// Control register bits 3-6, which always read as one
+const CONTROL_FIXED: u32 = 0x0000_0078;
+
+/// Size of a protection region register: 2 bytes shifted by bits 1-5, at
+/// least 4 KB.
+const fn pu_region_size(region: u32) -> u64 {
+    let shift = (region >> 1) & 0x1F;
+    2 << if shift < 11 { 11 } else { shift }
+}
+
+/// Address the cached areas of [`Cp15::cached_areas`] are looked up at: the
+/// start of each 16 MB area, and the BIOS for the last one.
+const fn area_address(area: u32) -> u32 {
+    match area {
+        0xF => 0xFFFF_0000,
+        _ => area << 24,
+    }
+}
+
+/// Access permissions register as read through the 2 bit per region
+/// encoding of `c5,c0,0` and `c5,c0,1`.
+const fn compress_permissions(extended: u32) -> u32 {
+    let mut value = 0;
+    let mut region = 0;
+    while region < 8 {
+        value |= ((extended >> (region * 4)) & 3) << (region * 2);
+        region += 1;
+    }
+    value
+}
+
+const fn expand_permissions(compressed: u32) -> u32 {
+    let mut value = 0;
+    let mut region = 0;
+    while region < 8 {
+        value |= ((compressed >> (region * 2)) & 3) << (region * 4);
+        region += 1;
+    }
+    value
+}
+
 /// ARM9 Coprocessor 15 System Control
 /// Manages instruction/data TCM, caches, and memory control
 #[derive(Debug)]
@@ -119,6 +175,19 @@ pub struct Cp15 {
     /// Data TCM data register
     dtcm_data: u32,
 
+    /// Protection unit regions 0-7
+    pu_regions: [u32; 8],
+    /// Data cacheable bits, one per region
+    dcache_bits: u32,
+    /// Instruction cacheable bits, one per region
+    icache_bits: u32,
+    /// Write buffer bits, one per region
+    write_buffer_bits: u32,
+    /// Data access permissions, 4 bits per region
+    data_permissions: u32,
+    /// Instruction access permissions, 4 bits per region
+    code_permissions: u32,
+
     /// Instruction TCM size (cached value)
     itcm_size: u32,
     /// Data TCM base address (cached value)
@@ -150,6 +219,12 @@ impl Cp15 {
             control: ControlReg::new(),
             itcm_data: 0,
             dtcm_data: 0,
+            pu_regions: [0; 8],
+            dcache_bits: 0,
+            icache_bits: 0,
+            write_buffer_bits: 0,
+            data_permissions: 0,
+            code_permissions: 0,
             itcm_size: 0,
             dtcm_base: 0,
             dtcm_size: 0,
@@ -162,12 +237,51 @@ impl Cp15 {
 
     /// Power on CP15
     pub const fn power_on(&mut self) {
-        self.control = ControlReg::new();
+        self.control.set_values(CONTROL_RESET);
+        self.itcm_data = 0;
+        self.dtcm_data = 0;
+        self.pu_regions = [0; 8];
+        self.dcache_bits = 0;
+        self.icache_bits = 0;
+        self.write_buffer_bits = 0;
+        self.data_permissions = 0;
+        self.code_permissions = 0;
         self.itcm_size = 0;
         self.dtcm_base = 0;
         self.dtcm_size = 0;
     }
 
+    /// State the firmware leaves behind when it starts a cartridge: caches,
+    /// write buffer and protection unit set up, DTCM at 0x03000000 and the
+    /// ITCM enabled.
+    pub const fn direct_boot(&mut self) {
+        const REGIONS: [u32; 8] = [
+            0x0400_0033, // I/O
+            0x0200_002B, // main RAM
+            0,
+            0x0800_0035, // GBA slot
+            0x0300_001B, // shared WRAM
+            0,
+            0xFFFF_001D, // BIOS
+            0x027F_F017, // shared main RAM
+        ];
+
+        self.mcr(0, 2, 0x42, 0, 0);
+        self.mcr(0, 2, 0x42, 1, 0);
+        self.mcr(0, 3, 0x02, 0, 0);
+        self.mcr(0, 5, 0x1511_1011, 2, 0);
+        self.mcr(0, 5, 0x0510_0011, 3, 0);
+        let mut region = 0;
+        while region < REGIONS.len() {
+            self.mcr(0, 6, REGIONS[region], 0, region as i32);
+            region += 1;
+        }
+        self.mcr(0, 9, 0x0300_000A, 0, 1);
+        self.mcr(0, 9, 0x0000_0020, 1, 1);
+        // Both TCMs on, protection unit and caches still off
+        self.mcr(0, 1, CONTROL_RESET | (1 << 16) | (1 << 18), 0, 0);
+    }
+
     // Removed
     //
     // Link CP15 with ARM9 CPU
@@ -183,7 +297,7 @@ impl Cp15 {
         }
     }
 
-    /// Get data TCM base address
+    /// Get data TCM base address, aligned to the region size
     pub const fn get_dtcm_base(&self) -> u32 {
         self.dtcm_base
     }
@@ -203,113 +317,224 @@ impl Cp15 {
         self.control.dtcm_write_only
     }
 
-    /// Read word from TCM/cache
-    pub fn read_word(&self, address: u32) -> u32 {
-        self.read_byte(address) as u32
-            | ((self.read_byte(address + 1) as u32) << 8)
-            | ((self.read_byte(address + 2) as u32) << 16)
-            | ((self.read_byte(address + 3) as u32) << 24)
+    /// Whether a TCM claims any byte of `address..address + len`.
+    pub fn overlaps_tcm(&self, address: u32, len: u32) -> bool {
+        let end = address.wrapping_add(len);
+        let dtcm_end = self.dtcm_base.wrapping_add(self.get_dtcm_size());
+        address < self.get_itcm_size() || (address < dtcm_end && end > self.dtcm_base)
     }
 
-    /// Read halfword from TCM/cache
-    pub fn read_halfword(&self, address: u32) -> u16 {
-        (self.read_byte(address) as u16) | ((self.read_byte(address + 1) as u16) << 8)
+    /// ITCM size reads see, 0 when disabled or in load mode.
+    pub const fn itcm_read_size(&self) -> u32 {
+        match self.control.itcm_write_only {
+            true => 0,
+            false => self.get_itcm_size(),
+        }
     }
 
-    /// Read byte from TCM/cache
-    pub fn read_byte(&self, address: u32) -> u8 {
-        // Check if address is in instruction TCM
-        let itcm_end = 0x0800_0000u32.saturating_add(self.itcm_size);
-        if address < itcm_end {
-            let idx = (address & (self.itcm.len() as u32 - 1)) as usize;
-            return self.itcm[idx];
+    /// DTCM size reads see, 0 when disabled or in load mode.
+    pub const fn dtcm_read_size(&self) -> u32 {
+        match self.control.dtcm_write_only {
+            true => 0,
+            false => self.get_dtcm_size(),
         }
+    }
 
-        // Check if address is in data TCM
-        if address >= self.dtcm_base && address < (self.dtcm_base + self.dtcm_size) {
-            let idx = (address - self.dtcm_base) as usize;
-            if idx < self.dtcm.len() {
-                return self.dtcm[idx];
-            }
+    /// Base of the exception vectors the control register selects
+    pub const fn exception_base(&self) -> u32 {
+        match self.control.high_exception_vector {
+            true => 0xFFFF_0000,
+            false => 0,
         }
+    }
 
-        0x00
+    /// Protection region `address` falls in, the highest numbered enabled
+    /// one winning where they overlap. `None` with the protection unit off
+    /// or outside every region.
+    pub fn pu_region(&self, address: u32) -> Option<usize> {
+        if !self.control.mmu_pu_enable {
+            return None;
+        }
+        (0..8).rev().find(|&index| {
+            let region = self.pu_regions[index];
+            let size = pu_region_size(region);
+            let base = (region & 0xFFFF_F000) as u64 & !(size - 1);
+            region & 1 != 0 && (address as u64).wrapping_sub(base) < size
+        })
     }
 
-    /// Write word to TCM/cache
-    pub fn write_word(&mut self, address: u32, word: u32) {
-        self.write_byte(address, word & 0xFF);
-        self.write_byte(address + 1, (word >> 8) & 0xFF);
-        self.write_byte(address + 2, (word >> 16) & 0xFF);
-        self.write_byte(address + 3, (word >> 24) & 0xFF);
+    /// Whether code fetches (`code`) or data accesses at `address` go
+    /// through the enabled instruction or data cache
+    pub fn cacheable(&self, address: u32, code: bool) -> bool {
+        let (enabled, bits) = match code {
+            true => (self.control.instruction_cache_on, self.icache_bits),
+            false => (self.control.data_unified_cache_on, self.dcache_bits),
+        };
+        enabled
+            && self
+                .pu_region(address)
+                .is_some_and(|region| bits & (1 << region) != 0)
     }
 
-    /// Write halfword to TCM/cache
-    pub fn write_halfword(&mut self, address: u32, halfword: u16) {
-        self.write_byte(address, (halfword & 0xFF) as u32);
-        self.write_byte(address + 1, ((halfword >> 8) & 0xFF) as u32);
+    /// 16 MB areas, by bits 24-27 of the address, whose code fetches
+    /// (`code`) or data accesses are cached.
+    pub fn cached_areas(&self, code: bool) -> u16 {
+        (0..16)
+            .filter(|&area| self.cacheable(area_address(area), code))
+            .fold(0, |areas, area| areas | 1 << area)
     }
 
-    /// Write byte to TCM/cache
-    pub fn write_byte(&mut self, address: u32, byte: u32) {
-        let byte_val = (byte & 0xFF) as u8;
+    /// Instruction TCM contents
+    pub fn itcm(&self) -> &[u8] {
+        &self.itcm
+    }
 
-        // Check if address is in instruction TCM
-        if address < 0x0800_0000 + self.itcm_size {
-            let idx = (address & (self.itcm.len() as u32 - 1)) as usize;
-            self.itcm[idx] = byte_val;
-            return;
-        }
+    pub fn itcm_mut(&mut self) -> &mut [u8] {
+        &mut self.itcm
+    }
 
-        // Check if address is in data TCM
-        if address >= self.dtcm_base && address < (self.dtcm_base + self.dtcm_size) {
-            // Skip if write-only mode is disabled for reads
-            if !self.control.dtcm_write_only {
-                let idx = (address - self.dtcm_base) as usize;
-                if idx < self.dtcm.len() {
-                    self.dtcm[idx] = byte_val;
-                }
-            }
+    /// Data TCM contents
+    pub fn dtcm(&self) -> &[u8] {
+        &self.dtcm
+    }
+
+    pub fn dtcm_mut(&mut self) -> &mut [u8] {
+        &mut self.dtcm
+    }
+
+    /// TCM and offset in it that `address` reaches, if any.
+    ///
+    /// The ITCM starts at 0 and the DTCM at its base, both mirrored over
+    /// their region size by the low address bits. In load mode a TCM takes
+    /// writes only.
+    fn tcm_offset(&self, address: u32, write: bool) -> Option<(bool, usize)> {
+        let (itcm_size, dtcm_size) = match write {
+            true => (self.get_itcm_size(), self.get_dtcm_size()),
+            false => (self.itcm_read_size(), self.dtcm_read_size()),
+        };
+        if address < itcm_size {
+            return Some((true, address as usize & (self.itcm.len() - 1)));
         }
+        ((address ^ self.dtcm_base) < dtcm_size)
+            .then(|| (false, address as usize & (self.dtcm.len() - 1)))
+    }
+
+    fn read<const N: usize>(&self, address: u32) -> Option<[u8; N]> {
+        let (is_itcm, offset) = self.tcm_offset(address & !(N as u32 - 1), false)?;
+        let memory = if is_itcm { &self.itcm } else { &self.dtcm };
+        memory[offset..offset + N].try_into().ok()
+    }
+
+    fn write<const N: usize>(&mut self, address: u32, bytes: [u8; N]) -> bool {
+        let Some((is_itcm, offset)) = self.tcm_offset(address & !(N as u32 - 1), true) else {
+            return false;
+        };
+        let memory = if is_itcm {
+            &mut self.itcm
+        } else {
+            &mut self.dtcm
+        };
+        memory[offset..offset + N].copy_from_slice(&bytes);
+        true
+    }
+
+    /// Read word from TCM, `None` if no TCM claims `address`
+    pub fn read_word(&self, address: u32) -> Option<u32> {
+        self.read(address).map(u32::from_le_bytes)
+    }
+
+    /// Read halfword from TCM, `None` if no TCM claims `address`
+    pub fn read_halfword(&self, address: u32) -> Option<u16> {
+        self.read(address).map(u16::from_le_bytes)
+    }
+
+    /// Read byte from TCM, `None` if no TCM claims `address`
+    pub fn read_byte(&self, address: u32) -> Option<u8> {
+        self.read(address).map(u8::from_le_bytes)
+    }
+
+    /// Write word to TCM, returning whether a TCM took it
+    pub fn write_word(&mut self, address: u32, word: u32) -> bool {
+        self.write(address, word.to_le_bytes())
+    }
+
+    /// Write halfword to TCM, returning whether a TCM took it
+    pub fn write_halfword(&mut self, address: u32, halfword: u16) -> bool {
+        self.write(address, halfword.to_le_bytes())
+    }
+
+    /// Write byte to TCM, returning whether a TCM took it
+    pub fn write_byte(&mut self, address: u32, byte: u8) -> bool {
+        self.write(address, [byte])
     }
 
     /// MRC instruction - Read from coprocessor
-    /// Moves coprocessor register to ARM register
-    pub const fn mrc(&self, operation: i32, source_reg: i32, _info: i32, operand_reg: i32) -> u32 {
-        match (operation, source_reg, operand_reg) {
-            (0, 0, 0) => self.control.get_values(), // Read control register
+    /// Moves coprocessor register `c<source_reg>, c<operand_reg>, <info>` to
+    /// an ARM register
+    pub const fn mrc(&self, operation: i32, source_reg: i32, info: i32, operand_reg: i32) -> u32 {
+        match (operation, source_reg, operand_reg, info) {
+            // Main ID, cache type and TCM size of the ARM946E-S
+            (0, 0, 0, 0) => 0x4105_9461,
+            (0, 0, 0, 1) => 0x0F0D_2112,
+            (0, 0, 0, 2) => 0x0014_0180,
+            (0, 1, 0, 0) => self.control.get_values() | CONTROL_FIXED,
+            (0, 2, 0, 0) => self.dcache_bits,
+            (0, 2, 0, 1) => self.icache_bits,
+            (0, 3, 0, 0) => self.write_buffer_bits,
+            (0, 5, 0, 0) => compress_permissions(self.data_permissions),
+            (0, 5, 0, 1) => compress_permissions(self.code_permissions),
+            (0, 5, 0, 2) => self.data_permissions,
+            (0, 5, 0, 3) => self.code_permissions,
+            (0, 6, region @ 0..8, 0) => self.pu_regions[region as usize],
+            (0, 9, 1, 0) => self.dtcm_data,
+            (0, 9, 1, 1) => self.itcm_data,
             _ => 0,
         }
     }
 
     /// MCR instruction - Write to coprocessor
     /// Moves ARM register to coprocessor register
+    /// `c<destination_reg>, c<operand_reg>, <info>`
+    ///
+    /// Returns whether the write is a wait for interrupt, which halts the
+    /// CPU until an IRQ is requested.
     pub const fn mcr(
         &mut self,
         operation: i32,
         destination_reg: i32,
         arm_reg_contents: u32,
-        _info: i32,
+        info: i32,
         operand_reg: i32,
-    ) {
-        match (operation, destination_reg, operand_reg) {
-            (0, 1, 0) => {
+    ) -> bool {
+        match (operation, destination_reg, operand_reg, info) {
+            (0, 1, 0, 0) => {
                 // Write control register
                 self.control.set_values(arm_reg_contents);
             }
-            (0, 9, 0) => {
-                // Data TCM region
+            (0, 2, 0, 0) => self.dcache_bits = arm_reg_contents & 0xFF,
+            (0, 2, 0, 1) => self.icache_bits = arm_reg_contents & 0xFF,
+            (0, 3, 0, 0) => self.write_buffer_bits = arm_reg_contents & 0xFF,
+            (0, 5, 0, 0) => self.data_permissions = expand_permissions(arm_reg_contents),
+            (0, 5, 0, 1) => self.code_permissions = expand_permissions(arm_reg_contents),
+            (0, 5, 0, 2) => self.data_permissions = arm_reg_contents,
+            (0, 5, 0, 3) => self.code_permissions = arm_reg_contents,
+            (0, 6, region @ 0..8, 0) => self.pu_regions[region as usize] = arm_reg_contents,
+            // Wait for interrupt, and its alias among the cache operations
+            (0, 7, 0, 4) | (0, 7, 8, 2) => return true,
+            (0, 9, 1, 0) => {
+                // Data TCM region, its base aligned to its size
                 self.dtcm_data = arm_reg_contents;
-                self.dtcm_base = arm_reg_contents & 0xFFFFF000;
-                self.dtcm_size = 512 << ((arm_reg_contents >> 1) & 0x1F);
+                self.dtcm_size = tcm_region_size(arm_reg_contents);
+                self.dtcm_base = arm_reg_contents & 0xFFFFF000 & !(self.dtcm_size - 1);
             }
-            (0, 9, 1) => {
-                // Instruction TCM region
+            (0, 9, 1, 1) => {
+                // Instruction TCM region, its base is fixed at 0
                 self.itcm_data = arm_reg_contents;
-                self.itcm_size = 512 << ((arm_reg_contents >> 1) & 0x1F);
+                self.itcm_size = tcm_region_size(arm_reg_contents);
             }
             _ => {}
         }
+        false
     }
 
     /// Get control register
@@ -332,3 +557,112 @@ impl Cp15 {
         self.dcache.fill(0);
     }
 }
+
+impl Savestate for Cp15 {
+    fn save_state(&self, w: &mut StateWriter) {
+        for value in [
+            self.control.get_values(),
+            self.itcm_data,
+            self.dtcm_data,
+            self.itcm_size,
+            self.dtcm_base,
+            self.dtcm_size,
+            self.dcache_bits,
+            self.icache_bits,
+            self.write_buffer_bits,
+            self.data_permissions,
+            self.code_permissions,
+        ] {
+            w.u32(value);
+        }
+        for &region in &self.pu_regions {
+            w.u32(region);
+        }
+        w.bytes(&self.itcm);
+        w.bytes(&self.dtcm);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.control.set_values(r.u32()?);
+        for value in [
+            &mut self.itcm_data,
+            &mut self.dtcm_data,
+            &mut self.itcm_size,
+            &mut self.dtcm_base,
+            &mut self.dtcm_size,
+            &mut self.dcache_bits,
+            &mut self.icache_bits,
+            &mut self.write_buffer_bits,
+            &mut self.data_permissions,
+            &mut self.code_permissions,
+        ]
+        .into_iter()
+        .chain(&mut self.pu_regions)
+        {
+            *value = r.u32()?;
+        }
+        r.bytes(&mut self.itcm)?;
+        r.bytes(&mut self.dtcm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tcm_regions() {
+        let mut cp15 = Cp15::new();
+        // 16 KB DTCM asked for at an unaligned base, 32 KB ITCM
+        cp15.mcr(0, 9, 0x027C_1000 | (5 << 1), 0, 1);
+        cp15.mcr(0, 9, 6 << 1, 1, 1);
+        assert_eq!(cp15.mrc(0, 9, 0, 1), 0x027C_1000 | (5 << 1));
+        assert_eq!(cp15.mrc(0, 9, 1, 1), 6 << 1);
+        assert!(!cp15.write_word(0x027C_0000, 1));
+
+        cp15.mcr(0, 1, (1 << 16) | (1 << 18), 0, 0);
+        assert_eq!(cp15.mrc(0, 1, 0, 0), (1 << 16) | (1 << 18) | CONTROL_FIXED);
+        assert_eq!(cp15.get_dtcm_base(), 0x027C_0000);
+        assert!(cp15.write_word(0x027C_3FFC, 0x1234_5678));
+        assert_eq!(cp15.read_word(0x027C_3FFC), Some(0x1234_5678));
+        assert_eq!(cp15.read_word(0x027C_4000), None);
+
+        // The ITCM mirrors over its region and wins over the DTCM
+        assert!(cp15.write_byte(0x7FFF, 0xAB));
+        assert_eq!(cp15.read_byte(0xFFFF), None);
+        cp15.mcr(0, 9, 0x11 << 1, 1, 1);
+        assert_eq!(cp15.read_byte(0x0280_7FFF), Some(0xAB));
+        assert_eq!(cp15.read_word(0x027C_3FFC), Some(0));
+    }
+
+    #[test]
+    fn test_protection_unit() {
+        let mut cp15 = Cp15::new();
+        cp15.power_on();
+        assert_eq!(cp15.exception_base(), 0xFFFF_0000);
+
+        // All memory in region 0, main RAM (4 MB) in region 1, its last
+        // 4 KB uncached in region 2
+        cp15.mcr(0, 6, 1 | (31 << 1), 0, 0);
+        cp15.mcr(0, 6, 0x0200_0001 | (21 << 1), 0, 1);
+        cp15.mcr(0, 6, 0x023F_F001 | (11 << 1), 0, 2);
+        cp15.mcr(0, 2, 0b010, 0, 0);
+        cp15.mcr(0, 2, 0b010, 1, 0);
+        assert_eq!(cp15.pu_region(0x0230_0000), None);
+
+        cp15.mcr(0, 1, 1 | (1 << 2) | (1 << 12), 0, 0);
+        assert_eq!(cp15.exception_base(), 0);
+        assert_eq!(cp15.pu_region(0x0230_0000), Some(1));
+        assert_eq!(cp15.pu_region(0x023F_FFFC), Some(2));
+        assert_eq!(cp15.pu_region(0x0400_0000), Some(0));
+        assert!(cp15.cacheable(0x0230_0000, false));
+        assert!(!cp15.cacheable(0x023F_FFFC, true));
+        assert_eq!(cp15.cached_areas(true), 1 << 2);
+
+        // Both encodings of the access permissions
+        cp15.mcr(0, 5, 0b11_01, 0, 0);
+        assert_eq!(cp15.mrc(0, 5, 2, 0), 0x31);
+        assert_eq!(cp15.mrc(0, 5, 0, 0), 0b11_01);
+        assert!(cp15.mcr(0, 7, 0, 4, 0));
+    }
+}
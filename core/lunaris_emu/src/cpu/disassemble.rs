@@ -1,242 +1,645 @@
+//! ARM and Thumb disassembler
+//!
+//! Covers the ARMv5TE instruction set of the ARM946E-S, DSP extensions and
+//! coprocessor instructions included, and its Thumb set; the ARM7TDMI runs
+//! a subset of both. It works on instruction words alone, so debugger
+//! frontends can use it without an emulator.
+//!
+//! Mnemonics are lowercase with the `s` flag before the condition, as in
+//! `addseq`. Immediates are printed as `#0x..` and branch targets as
+//! `$0x..`; PC-relative loads get their address as a comment.
 use crate::cpu::arm_cpu::ArmCpu;
-use crate::cpu::instruction_table::ARMInstr;
 
-pub fn disasm_arm(cpu: &mut ArmCpu, instruction: u32, address: u32) -> String {
-    let opcode = super::interpreter::arm_decode(instruction);
+const UNDEFINED: &str = "(UNDEFINED)";
 
-    match opcode {
-        ARMInstr::DataProcessing => disasm_data_processing(cpu, instruction),
+const fn reg(id: u32) -> &'static str {
+    ArmCpu::get_reg_name(id & 0xF)
+}
 
-        ARMInstr::StoreByte | ARMInstr::LoadByte | ARMInstr::StoreWord | ARMInstr::LoadWord => {
-            disasm_load_store(cpu, instruction)
-        }
+const fn cond(instruction: u32) -> &'static str {
+    ArmCpu::get_condition_name(instruction >> 28)
+}
 
-        ARMInstr::Branch | ARMInstr::BranchWithLink => disasm_branch(cpu, instruction, address),
+const fn bit(instruction: u32, n: u32) -> bool {
+    instruction & (1 << n) != 0
+}
 
-        ARMInstr::BranchExchange => disasm_bx(instruction),
+fn signed_imm(up: bool, value: u32) -> String {
+    match up {
+        true => format!("#0x{value:X}"),
+        false => format!("#-0x{value:X}"),
+    }
+}
 
-        _ => "(UNDEFINED)".to_string(),
+/// `{r0-r3, lr}`, runs of three or more registers as ranges.
+fn reg_list(list: u32) -> String {
+    let mut parts = Vec::new();
+    let mut i = 0;
+    while i < 16 {
+        if !bit(list, i) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < 16 && bit(list, i) {
+            i += 1;
+        }
+        match i - start {
+            1 => parts.push(reg(start).to_owned()),
+            2 => parts.extend([reg(start).to_owned(), reg(start + 1).to_owned()]),
+            _ => parts.push(format!("{}-{}", reg(start), reg(i - 1))),
+        }
     }
+    format!("{{{}}}", parts.join(", "))
 }
 
-fn disasm_data_processing(cpu: &mut ArmCpu, instruction: u32) -> String {
-    let opcode = (instruction >> 21) & 0xF;
-    let first_operand = (instruction >> 16) & 0xF;
-    let destination = (instruction >> 12) & 0xF;
+/// Disassemble the ARM `instruction` at address `pc`.
+pub fn disasm_arm(instruction: u32, pc: u32) -> String {
+    if instruction >> 28 == 0xF {
+        return disasm_arm_unconditional(instruction, pc);
+    }
+    match (instruction >> 25) & 0x7 {
+        0b000 if instruction & 0x90 == 0x90 => disasm_multiply_or_extra(instruction, pc),
+        // Opcodes TST-CMN without S hold the miscellaneous instructions
+        0b000 if instruction & 0x0190_0000 == 0x0100_0000 => disasm_control(instruction),
+        0b000 => disasm_data_processing(instruction),
+        0b001 if instruction & 0x0FB0_F000 == 0x0320_F000 => disasm_msr(instruction),
+        0b001 if instruction & 0x0190_0000 == 0x0100_0000 => UNDEFINED.to_owned(),
+        0b001 => disasm_data_processing(instruction),
+        0b011 if bit(instruction, 4) => UNDEFINED.to_owned(),
+        0b010 | 0b011 => disasm_single_transfer(instruction, pc),
+        0b100 => disasm_block_transfer(instruction),
+        0b101 => disasm_branch(instruction, pc),
+        0b110 => disasm_cop_transfer(instruction, cond(instruction), ""),
+        _ if bit(instruction, 24) => {
+            format!("swi{} #0x{:X}", cond(instruction), instruction & 0xFF_FFFF)
+        }
+        _ => disasm_cop_register(instruction, cond(instruction), ""),
+    }
+}
 
-    let set_cc = (instruction & (1 << 20)) != 0;
-    let is_imm = (instruction & (1 << 25)) != 0;
+/// ARMv5 instructions in the condition 0xF space.
+fn disasm_arm_unconditional(instruction: u32, pc: u32) -> String {
+    match (instruction >> 25) & 0x7 {
+        0b010 | 0b011 if instruction & 0x0D70_F000 == 0x0550_F000 => {
+            let offset = disasm_single_offset(instruction);
+            let address = transfer_address(instruction, offset);
+            format!("pld {address}")
+        }
+        0b101 => {
+            let target = branch_target(instruction, pc).wrapping_add((instruction >> 23) & 2);
+            format!("blx $0x{target:X}")
+        }
+        0b110 => disasm_cop_transfer(instruction, "", "2"),
+        0b111 if !bit(instruction, 24) => disasm_cop_register(instruction, "", "2"),
+        _ => UNDEFINED.to_owned(),
+    }
+}
 
-    let second_operand_str = if is_imm {
-        let mut operand = instruction & 0xFF;
-        let shift = (instruction & 0xF00) >> 7;
-        operand = cpu.rotr32(operand, shift, false);
+fn disasm_data_processing(instruction: u32) -> String {
+    const NAMES: [&str; 16] = [
+        "and", "eor", "sub", "rsb", "add", "adc", "sbc", "rsc", "tst", "teq", "cmp", "cmn", "orr",
+        "mov", "bic", "mvn",
+    ];
+    let opcode = (instruction >> 21) & 0xF;
+    let name = NAMES[opcode as usize];
+    let operand = match bit(instruction, 25) {
+        true => {
+            let rotate = ((instruction >> 8) & 0xF) * 2;
+            format!("#0x{:X}", (instruction & 0xFF).rotate_right(rotate))
+        }
+        false => shifted_register(instruction),
+    };
+    let s = if bit(instruction, 20) { "s" } else { "" };
+    let cond = cond(instruction);
+    let rd = reg(instruction >> 12);
+    let rn = reg(instruction >> 16);
+    match opcode {
+        0x8..=0xB => format!("{name}{cond} {rn}, {operand}"),
+        0xD | 0xF => format!("{name}{s}{cond} {rd}, {operand}"),
+        _ => format!("{name}{s}{cond} {rd}, {rn}, {operand}"),
+    }
+}
 
-        format!("#0x{operand:X}")
-    } else {
-        let reg_name = ArmCpu::get_reg_name(instruction & 0xF);
-
-        let shift_type = (instruction >> 5) & 0x3;
-        let shift_name = match shift_type {
-            0 => "lsl",
-            1 => "lsr",
-            2 => "asr",
-            _ => "ror",
-        };
+/// Register operand shifted by an immediate or a register.
+fn shifted_register(instruction: u32) -> String {
+    const SHIFTS: [&str; 4] = ["lsl", "lsr", "asr", "ror"];
+    let rm = reg(instruction);
+    let shift = (instruction >> 5) & 0x3;
+    let name = SHIFTS[shift as usize];
+    if bit(instruction, 4) {
+        return format!("{rm}, {name} {}", reg(instruction >> 8));
+    }
+    match ((instruction >> 7) & 0x1F, shift) {
+        (0, 0) => rm.to_owned(),
+        (0, 3) => format!("{rm}, rrx"),
+        (0, _) => format!("{rm}, {name} #32"),
+        (amount, _) => format!("{rm}, {name} #{amount}"),
+    }
+}
 
-        let src = if (instruction & (1 << 4)) != 0 {
-            let rs = (instruction >> 8) & 0xF;
-            format!(" {}", ArmCpu::get_reg_name(rs))
-        } else {
-            let imm = (instruction >> 7) & 0x1F;
-            format!(" #{imm}")
+/// Multiplies, swaps and the halfword, signed and doubleword transfers.
+fn disasm_multiply_or_extra(instruction: u32, pc: u32) -> String {
+    let cond = cond(instruction);
+    let s = if bit(instruction, 20) { "s" } else { "" };
+    let (rn, rd, rs, rm) = (
+        reg(instruction >> 16),
+        reg(instruction >> 12),
+        reg(instruction >> 8),
+        reg(instruction),
+    );
+    if instruction & 0x0FC0_00F0 == 0x0000_0090 {
+        return match bit(instruction, 21) {
+            true => format!("mla{s}{cond} {rn}, {rm}, {rs}, {rd}"),
+            false => format!("mul{s}{cond} {rn}, {rm}, {rs}"),
         };
+    }
+    if instruction & 0x0F80_00F0 == 0x0080_0090 {
+        let sign = if bit(instruction, 22) { "s" } else { "u" };
+        let op = if bit(instruction, 21) { "mlal" } else { "mull" };
+        return format!("{sign}{op}{s}{cond} {rd}, {rn}, {rm}, {rs}");
+    }
+    if instruction & 0x0FB0_0FF0 == 0x0100_0090 {
+        let b = if bit(instruction, 22) { "b" } else { "" };
+        return format!("swp{b}{cond} {rd}, {rm}, [{rn}]");
+    }
+    if instruction & 0x60 == 0 {
+        return UNDEFINED.to_owned();
+    }
 
-        format!("{reg_name}, {shift_name}{src}")
+    let name = match ((instruction >> 5) & 0x3, bit(instruction, 20)) {
+        (1, false) => "strh",
+        (1, true) => "ldrh",
+        (2, false) => "ldrd",
+        (2, true) => "ldrsb",
+        (_, false) => "strd",
+        (_, true) => "ldrsh",
     };
-
-    let mut mnemonic = match opcode {
-        0x0 => "and",
-        0x1 => "eor",
-        0x2 => "sub",
-        0x3 => "rsb",
-        0x4 => "add",
-        0x5 => "adc",
-        0x6 => "sbc",
-        0x7 => "rsc",
-
-        0x8 => {
-            if set_cc {
-                "tst"
-            } else {
-                return disasm_mrs(cpu, instruction);
-            }
+    let up = bit(instruction, 23);
+    let offset = match bit(instruction, 22) {
+        true => {
+            let imm = ((instruction >> 4) & 0xF0) | (instruction & 0xF);
+            (signed_imm(up, imm), imm == 0)
         }
+        false => (format!("{}{rm}", if up { "" } else { "-" }), false),
+    };
+    let registers = match name {
+        "ldrd" | "strd" => format!("{rd}, {}", reg((instruction >> 12) + 1)),
+        _ => rd.to_owned(),
+    };
+    let address = transfer_address(instruction, offset);
+    let comment = pc_relative_comment(instruction, pc);
+    format!("{name}{cond} {registers}, {address}{comment}")
+}
 
-        0x9 => {
-            if set_cc {
-                "teq"
-            } else {
-                return disasm_msr(cpu, instruction);
-            }
+/// MRS, MSR, BX, BLX, CLZ, BKPT and the DSP instructions.
+fn disasm_control(instruction: u32) -> String {
+    let cond = cond(instruction);
+    let op = (instruction >> 21) & 0x3;
+    let (rn, rd, rs, rm) = (
+        reg(instruction >> 16),
+        reg(instruction >> 12),
+        reg(instruction >> 8),
+        reg(instruction),
+    );
+    match (instruction >> 4) & 0xF {
+        0x0 if bit(instruction, 21) => disasm_msr(instruction),
+        0x0 => format!("mrs{cond} {rd}, {}", psr_name(instruction)),
+        0x1 if op == 1 => format!("bx{cond} {rm}"),
+        0x1 if op == 3 => format!("clz{cond} {rd}, {rm}"),
+        0x3 if op == 1 => format!("blx{cond} {rm}"),
+        0x5 => {
+            let name = ["qadd", "qsub", "qdadd", "qdsub"][op as usize];
+            format!("{name}{cond} {rd}, {rm}, {rn}")
         }
-
-        0xA => {
-            if set_cc {
-                "cmp"
-            } else {
-                return disasm_mrs(cpu, instruction);
-            }
+        0x7 if op == 1 => {
+            let imm = ((instruction >> 4) & 0xFFF0) | (instruction & 0xF);
+            format!("bkpt #0x{imm:X}")
         }
-
-        0xB => {
-            if set_cc {
-                "cmn"
-            } else {
-                return disasm_msr(cpu, instruction);
+        0x8 | 0xA | 0xC | 0xE => {
+            let x = if bit(instruction, 5) { "t" } else { "b" };
+            let y = if bit(instruction, 6) { "t" } else { "b" };
+            match op {
+                0 => format!("smla{x}{y}{cond} {rn}, {rm}, {rs}, {rd}"),
+                1 if bit(instruction, 5) => format!("smulw{y}{cond} {rn}, {rm}, {rs}"),
+                1 => format!("smlaw{y}{cond} {rn}, {rm}, {rs}, {rd}"),
+                2 => format!("smlal{x}{y}{cond} {rd}, {rn}, {rm}, {rs}"),
+                _ => format!("smul{x}{y}{cond} {rn}, {rm}, {rs}"),
             }
         }
-
-        0xC => "orr",
-        0xD => "mov",
-        0xE => "bic",
-        _ => "mvn",
+        _ => UNDEFINED.to_owned(),
     }
-    .to_string();
+}
 
-    if set_cc {
-        mnemonic.push('s');
+const fn psr_name(instruction: u32) -> &'static str {
+    match bit(instruction, 22) {
+        true => "spsr",
+        false => "cpsr",
     }
+}
 
-    mnemonic.push_str(ArmCpu::get_condition_name(instruction >> 28));
-
+fn disasm_msr(instruction: u32) -> String {
+    let fields: String = [(19, 'f'), (18, 's'), (17, 'x'), (16, 'c')]
+        .into_iter()
+        .filter(|&(n, _)| bit(instruction, n))
+        .map(|(_, field)| field)
+        .collect();
+    let operand = match bit(instruction, 25) {
+        true => {
+            let rotate = ((instruction >> 8) & 0xF) * 2;
+            format!("#0x{:X}", (instruction & 0xFF).rotate_right(rotate))
+        }
+        false => reg(instruction).to_owned(),
+    };
     format!(
-        "{} {}, {}, {}",
-        mnemonic,
-        ArmCpu::get_reg_name(destination),
-        ArmCpu::get_reg_name(first_operand),
-        second_operand_str
+        "msr{} {}_{fields}, {operand}",
+        cond(instruction),
+        psr_name(instruction)
     )
 }
 
-fn disasm_mrs(_cpu: &ArmCpu, instruction: u32) -> String {
-    let cond = ArmCpu::get_condition_name(instruction >> 28);
-    let rd = ArmCpu::get_reg_name((instruction >> 12) & 0xF);
-
-    let using_cpsr = (instruction & (1 << 22)) == 0;
-    let psr = if using_cpsr { "CPSR" } else { "SPSR" };
-
-    format!("mrs{} {}, {}", cond, rd, psr)
+/// Offset of LDR/STR, immediate or shifted register.
+fn disasm_single_offset(instruction: u32) -> (String, bool) {
+    let up = bit(instruction, 23);
+    match bit(instruction, 25) {
+        false => (
+            signed_imm(up, instruction & 0xFFF),
+            instruction & 0xFFF == 0,
+        ),
+        true => {
+            let sign = if up { "" } else { "-" };
+            (format!("{sign}{}", shifted_register(instruction)), false)
+        }
+    }
 }
 
-fn disasm_msr(cpu: &mut ArmCpu, instruction: u32) -> String {
-    let cond = ArmCpu::get_condition_name(instruction >> 28);
-
-    let using_cpsr = (instruction & (1 << 22)) == 0;
-    let psr = if using_cpsr { "CPSR" } else { "SPSR" };
-
-    let is_imm = (instruction & (1 << 25)) != 0;
+/// `[rn, offset]{!}` or `[rn], offset` by the P and W bits, `[rn]` for a
+/// zero pre-indexed offset.
+fn transfer_address(instruction: u32, (offset, zero): (String, bool)) -> String {
+    let rn = reg(instruction >> 16);
+    let writeback = if bit(instruction, 21) { "!" } else { "" };
+    match (bit(instruction, 24), zero) {
+        (true, true) => format!("[{rn}]{writeback}"),
+        (true, false) => format!("[{rn}, {offset}]{writeback}"),
+        (false, _) => format!("[{rn}], {offset}"),
+    }
+}
 
-    let operand_str = if is_imm {
-        let mut op = instruction & 0xFF;
-        let shift = (instruction & 0xFF0) >> 7;
-        op = cpu.rotr32(op, shift, false);
+/// Address of a pre-indexed immediate load from the PC.
+fn pc_relative_comment(instruction: u32, pc: u32) -> String {
+    let immediate = match (instruction >> 25) & 0x7 {
+        0b010 => Some(instruction & 0xFFF),
+        0b000 if bit(instruction, 22) => Some(((instruction >> 4) & 0xF0) | (instruction & 0xF)),
+        _ => None,
+    };
+    match immediate {
+        Some(offset) if (instruction >> 16) & 0xF == 15 && bit(instruction, 24) => {
+            let base = pc.wrapping_add(8);
+            let address = match bit(instruction, 23) {
+                true => base.wrapping_add(offset),
+                false => base.wrapping_sub(offset),
+            };
+            format!(" ; $0x{address:X}")
+        }
+        _ => String::new(),
+    }
+}
 
-        format!("#0x{:X}", op)
+fn disasm_single_transfer(instruction: u32, pc: u32) -> String {
+    let name = if bit(instruction, 20) { "ldr" } else { "str" };
+    let b = if bit(instruction, 22) { "b" } else { "" };
+    // Post-indexed with W set is the user mode access
+    let t = if !bit(instruction, 24) && bit(instruction, 21) {
+        "t"
     } else {
-        ArmCpu::get_reg_name(instruction & 0xF).to_string()
+        ""
     };
+    let offset = disasm_single_offset(instruction);
+    let address = transfer_address(instruction, offset);
+    let comment = pc_relative_comment(instruction, pc);
+    format!(
+        "{name}{b}{t}{} {}, {address}{comment}",
+        cond(instruction),
+        reg(instruction >> 12)
+    )
+}
 
-    format!("msr{} {}, {}", cond, psr, operand_str)
+fn disasm_block_transfer(instruction: u32) -> String {
+    let name = if bit(instruction, 20) { "ldm" } else { "stm" };
+    let mode = match (bit(instruction, 24), bit(instruction, 23)) {
+        (false, false) => "da",
+        (false, true) => "ia",
+        (true, false) => "db",
+        (true, true) => "ib",
+    };
+    let writeback = if bit(instruction, 21) { "!" } else { "" };
+    let user = if bit(instruction, 22) { "^" } else { "" };
+    format!(
+        "{name}{mode}{} {}{writeback}, {}{user}",
+        cond(instruction),
+        reg(instruction >> 16),
+        reg_list(instruction & 0xFFFF)
+    )
 }
 
-fn disasm_load_store(_cpu: &ArmCpu, instruction: u32) -> String {
-    let mut out = String::new();
+/// Target of B and BL, relative to the PC two instructions ahead.
+const fn branch_target(instruction: u32, pc: u32) -> u32 {
+    let offset = ((instruction << 8) as i32) >> 6;
+    pc.wrapping_add(8).wrapping_add(offset as u32)
+}
 
-    if (instruction & (1 << 20)) != 0 {
-        out.push_str("ldr");
-    } else {
-        out.push_str("str");
-    }
+fn disasm_branch(instruction: u32, pc: u32) -> String {
+    let l = if bit(instruction, 24) { "l" } else { "" };
+    format!(
+        "b{l}{} $0x{:X}",
+        cond(instruction),
+        branch_target(instruction, pc)
+    )
+}
 
-    if (instruction & (1 << 22)) != 0 {
-        out.push('b');
+/// LDC, STC, MCRR and MRRC; `two` is "2" for the unconditional forms.
+fn disasm_cop_transfer(instruction: u32, cond: &str, two: &str) -> String {
+    let cp = (instruction >> 8) & 0xF;
+    if two.is_empty() && instruction & 0x0FE0_0000 == 0x0C40_0000 {
+        let name = if bit(instruction, 20) { "mrrc" } else { "mcrr" };
+        return format!(
+            "{name}{cond} p{cp}, {}, {}, {}, c{}",
+            (instruction >> 4) & 0xF,
+            reg(instruction >> 12),
+            reg(instruction >> 16),
+            instruction & 0xF
+        );
     }
-
-    out.push_str(ArmCpu::get_condition_name(instruction >> 28));
-
-    let is_imm = (instruction & (1 << 25)) == 0;
-
-    let offset_str = if is_imm {
-        format!("#0x{:X}", instruction & 0xFFF)
-    } else {
-        "???".to_string()
+    let name = if bit(instruction, 20) { "ldc" } else { "stc" };
+    let l = if bit(instruction, 22) { "l" } else { "" };
+    let rn = reg(instruction >> 16);
+    let offset = (instruction & 0xFF) * 4;
+    let address = match (bit(instruction, 24), bit(instruction, 21)) {
+        (false, false) => format!("[{rn}], {{{}}}", instruction & 0xFF),
+        _ => transfer_address(
+            instruction,
+            (signed_imm(bit(instruction, 23), offset), offset == 0),
+        ),
     };
+    format!(
+        "{name}{two}{l}{cond} p{cp}, c{}, {address}",
+        (instruction >> 12) & 0xF
+    )
+}
 
-    let pre_index = (instruction & (1 << 24)) != 0;
-
-    let reg = (instruction >> 12) & 0xF;
-    let base = (instruction >> 16) & 0xF;
-
-    out.push_str(&format!(
-        " {}, [{}",
-        ArmCpu::get_reg_name(reg),
-        ArmCpu::get_reg_name(base)
-    ));
-
-    if pre_index {
-        out.push_str(&format!(", {}", offset_str));
+/// CDP, MCR and MRC; `two` is "2" for the unconditional forms.
+fn disasm_cop_register(instruction: u32, cond: &str, two: &str) -> String {
+    let cp = (instruction >> 8) & 0xF;
+    let crn = (instruction >> 16) & 0xF;
+    let crm = instruction & 0xF;
+    let op2 = (instruction >> 5) & 0x7;
+    if !bit(instruction, 4) {
+        return format!(
+            "cdp{two}{cond} p{cp}, {}, c{}, c{crn}, c{crm}, {op2}",
+            (instruction >> 20) & 0xF,
+            (instruction >> 12) & 0xF
+        );
     }
+    let name = if bit(instruction, 20) { "mrc" } else { "mcr" };
+    format!(
+        "{name}{two}{cond} p{cp}, {}, {}, c{crn}, c{crm}, {op2}",
+        (instruction >> 21) & 0x7,
+        reg(instruction >> 12)
+    )
+}
 
-    out.push(']');
-
-    if (instruction & (1 << 21)) != 0 {
-        out.push('!');
+/// Disassemble the Thumb `instruction` at address `pc`.
+///
+/// The halves of a BL or BLX pair are shown on their own with the part of
+/// the offset they hold; see [`disasm_thumb_bl`] for the whole branch.
+pub fn disasm_thumb(instruction: u16, pc: u32) -> String {
+    let instruction = instruction as u32;
+    let rd = reg(instruction & 0x7);
+    let rs = reg((instruction >> 3) & 0x7);
+    match instruction >> 11 {
+        op @ 0x00..=0x02 => {
+            let name = ["lsl", "lsr", "asr"][op as usize];
+            let amount = match (instruction >> 6) & 0x1F {
+                0 if op != 0 => 32,
+                amount => amount,
+            };
+            format!("{name} {rd}, {rs}, #{amount}")
+        }
+        0x03 => {
+            let name = if bit(instruction, 9) { "sub" } else { "add" };
+            let operand = (instruction >> 6) & 0x7;
+            match bit(instruction, 10) {
+                true => format!("{name} {rd}, {rs}, #0x{operand:X}"),
+                false => format!("{name} {rd}, {rs}, {}", reg(operand)),
+            }
+        }
+        op @ 0x04..=0x07 => {
+            let name = ["mov", "cmp", "add", "sub"][op as usize - 4];
+            format!(
+                "{name} {}, #0x{:X}",
+                reg((instruction >> 8) & 0x7),
+                instruction & 0xFF
+            )
+        }
+        0x08 if bit(instruction, 10) => disasm_thumb_hi_reg(instruction),
+        0x08 => {
+            const NAMES: [&str; 16] = [
+                "and", "eor", "lsl", "lsr", "asr", "adc", "sbc", "ror", "tst", "neg", "cmp", "cmn",
+                "orr", "mul", "bic", "mvn",
+            ];
+            format!("{} {rd}, {rs}", NAMES[((instruction >> 6) & 0xF) as usize])
+        }
+        0x09 => {
+            let offset = (instruction & 0xFF) * 4;
+            let address = (pc.wrapping_add(4) & !3).wrapping_add(offset);
+            format!(
+                "ldr {}, [pc, #0x{offset:X}] ; $0x{address:X}",
+                reg((instruction >> 8) & 0x7)
+            )
+        }
+        0x0A | 0x0B => {
+            let name = match ((instruction >> 10) & 0x3, bit(instruction, 9)) {
+                (0, false) => "str",
+                (1, false) => "strb",
+                (2, false) => "ldr",
+                (_, false) => "ldrb",
+                (0, true) => "strh",
+                (1, true) => "ldrsb",
+                (2, true) => "ldrh",
+                (_, true) => "ldrsh",
+            };
+            format!("{name} {rd}, [{rs}, {}]", reg((instruction >> 6) & 0x7))
+        }
+        op @ 0x0C..=0x11 => {
+            let (name, scale) = match op {
+                0x0C => ("str", 4),
+                0x0D => ("ldr", 4),
+                0x0E => ("strb", 1),
+                0x0F => ("ldrb", 1),
+                0x10 => ("strh", 2),
+                _ => ("ldrh", 2),
+            };
+            let offset = ((instruction >> 6) & 0x1F) * scale;
+            format!("{name} {rd}, [{rs}, #0x{offset:X}]")
+        }
+        op @ 0x12..=0x15 => {
+            let rd = reg((instruction >> 8) & 0x7);
+            let offset = (instruction & 0xFF) * 4;
+            match op {
+                0x12 => format!("str {rd}, [sp, #0x{offset:X}]"),
+                0x13 => format!("ldr {rd}, [sp, #0x{offset:X}]"),
+                0x14 => format!("add {rd}, pc, #0x{offset:X}"),
+                _ => format!("add {rd}, sp, #0x{offset:X}"),
+            }
+        }
+        0x16 | 0x17 => disasm_thumb_misc(instruction),
+        op @ (0x18 | 0x19) => {
+            let name = if op == 0x19 { "ldmia" } else { "stmia" };
+            format!(
+                "{name} {}!, {}",
+                reg((instruction >> 8) & 0x7),
+                reg_list(instruction & 0xFF)
+            )
+        }
+        0x1A | 0x1B => match (instruction >> 8) & 0xF {
+            0xE => UNDEFINED.to_owned(),
+            0xF => format!("swi #0x{:X}", instruction & 0xFF),
+            condition => {
+                let offset = (instruction as u8 as i8 as i32) << 1;
+                let target = pc.wrapping_add(4).wrapping_add(offset as u32);
+                format!("b{} $0x{target:X}", ArmCpu::get_condition_name(condition))
+            }
+        },
+        0x1C => {
+            let offset = ((instruction << 21) as i32) >> 20;
+            format!("b $0x{:X}", pc.wrapping_add(4).wrapping_add(offset as u32))
+        }
+        0x1D if bit(instruction, 0) => UNDEFINED.to_owned(),
+        0x1D => format!("blx (suffix) lr + #0x{:X}", (instruction & 0x7FF) << 1),
+        0x1E => {
+            let offset = ((instruction << 21) as i32) >> 9;
+            let lr = pc.wrapping_add(4).wrapping_add(offset as u32);
+            format!("bl (prefix) lr = $0x{lr:X}")
+        }
+        _ => format!("bl (suffix) lr + #0x{:X}", (instruction & 0x7FF) << 1),
     }
+}
 
-    if !pre_index {
-        out.push_str(&format!(", {}", offset_str));
+/// ADD, CMP and MOV on any register, BX and BLX.
+fn disasm_thumb_hi_reg(instruction: u32) -> String {
+    let rd = reg((instruction & 0x7) | ((instruction >> 4) & 0x8));
+    let rs = reg((instruction >> 3) & 0xF);
+    match (instruction >> 8) & 0x3 {
+        0 => format!("add {rd}, {rs}"),
+        1 => format!("cmp {rd}, {rs}"),
+        2 => format!("mov {rd}, {rs}"),
+        _ if bit(instruction, 7) => format!("blx {rs}"),
+        _ => format!("bx {rs}"),
     }
-
-    out
 }
 
-fn disasm_branch(cpu: &ArmCpu, instruction: u32, address: u32) -> String {
-    let condition = instruction >> 28;
-
-    let mut offset = ((instruction & 0xFFFFFF) << 2) as i32;
-    offset <<= 6;
-    offset >>= 6;
-
-    if condition == 15 {
-        if cpu.get_id() != 0 {
-            return "UNDEFINED".into();
-        } else {
-            if (instruction & (1 << 24)) != 0 {
-                offset += 2;
-            }
-
-            return format!("blx $0x{:X}", address.wrapping_add(offset as u32));
+/// SP adjustment, PUSH, POP and BKPT.
+fn disasm_thumb_misc(instruction: u32) -> String {
+    let list = instruction & 0xFF;
+    match (instruction >> 8) & 0xF {
+        0x0 => {
+            let name = if bit(instruction, 7) { "sub" } else { "add" };
+            format!("{name} sp, #0x{:X}", (instruction & 0x7F) * 4)
         }
+        0x4 | 0x5 => {
+            let lr = if bit(instruction, 8) { 1 << 14 } else { 0 };
+            format!("push {}", reg_list(list | lr))
+        }
+        0xC | 0xD => {
+            let pc = if bit(instruction, 8) { 1 << 15 } else { 0 };
+            format!("pop {}", reg_list(list | pc))
+        }
+        0xE => format!("bkpt #0x{list:X}"),
+        _ => UNDEFINED.to_owned(),
     }
+}
 
-    let mut out = String::from("b");
-
-    if (instruction & (1 << 24)) != 0 {
-        out.push('l');
+/// Disassemble the Thumb BL or BLX pair of `prefix` at `pc` and `suffix`
+/// after it, `None` if they are not one.
+pub fn disasm_thumb_bl(prefix: u16, suffix: u16, pc: u32) -> Option<String> {
+    if prefix >> 11 != 0x1E {
+        return None;
+    }
+    let high = (((prefix as u32) << 21) as i32) >> 9;
+    let target = pc
+        .wrapping_add(4)
+        .wrapping_add(high as u32)
+        .wrapping_add((suffix as u32 & 0x7FF) << 1);
+    match suffix >> 11 {
+        0x1F => Some(format!("bl $0x{target:X}")),
+        0x1D if suffix & 1 == 0 => Some(format!("blx $0x{:X}", target & !3)),
+        _ => None,
     }
-
-    out.push_str(ArmCpu::get_condition_name(condition));
-
-    out.push_str(&format!(" $0x{:X}", address.wrapping_add(offset as u32)));
-
-    out
 }
 
-pub fn disasm_bx(instruction: u32) -> String {
-    format!(
-        "bx{} {}",
-        ArmCpu::get_condition_name(instruction >> 28),
-        ArmCpu::get_reg_name(instruction & 0xF)
-    )
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disasm_arm() {
+        let pc = 0x0200_0000;
+        for (instruction, text) in [
+            (0xE3A0_0001, "mov r0, #0x1"),
+            (0xE091_0002, "adds r0, r1, r2"),
+            (0xE1A0_0081, "mov r0, r1, lsl #1"),
+            (0x0350_0000, "cmpeq r0, #0x0"),
+            (0xE12F_FF1E, "bx lr"),
+            (0xEAFF_FFFE, "b $0x2000000"),
+            (0xEB00_0000, "bl $0x2000008"),
+            (0xFA00_0000, "blx $0x2000008"),
+            (0xE59F_0004, "ldr r0, [pc, #0x4] ; $0x200000C"),
+            (0xE4D1_3001, "ldrb r3, [r1], #0x1"),
+            (0xE92D_4010, "stmdb sp!, {r4, lr}"),
+            (0xE8BD_800F, "ldmia sp!, {r0-r3, pc}"),
+            (0xE16F_0F11, "clz r0, r1"),
+            (0xE1C0_00D8, "ldrd r0, r1, [r0, #0x8]"),
+            (0xE002_0190, "mul r2, r0, r1"),
+            (0xE0C1_0392, "smull r0, r1, r2, r3"),
+            (0xE103_0281, "smlabb r3, r1, r2, r0"),
+            (0xE101_0052, "qadd r0, r2, r1"),
+            (0xE10F_0000, "mrs r0, cpsr"),
+            (0xE129_F000, "msr cpsr_fc, r0"),
+            (0xEE11_0F10, "mrc p15, 0, r0, c1, c0, 0"),
+            (0xEF00_0005, "swi #0x5"),
+            (0xE120_0070, "bkpt #0x0"),
+            (0xE7F0_00F0, "(UNDEFINED)"),
+        ] {
+            assert_eq!(disasm_arm(instruction, pc), text, "{instruction:08X}");
+        }
+    }
+
+    #[test]
+    fn test_disasm_thumb() {
+        let pc = 0x0200_0000;
+        for (instruction, text) in [
+            (0x2001, "mov r0, #0x1"),
+            (0x1840, "add r0, r0, r1"),
+            (0x4770, "bx lr"),
+            (0xB510, "push {r4, lr}"),
+            (0xBD10, "pop {r4, pc}"),
+            (0xE7FE, "b $0x2000000"),
+            (0xD0FE, "beq $0x2000000"),
+            (0x4801, "ldr r0, [pc, #0x4] ; $0x2000008"),
+            (0x8842, "ldrh r2, [r0, #0x2]"),
+            (0xDF05, "swi #0x5"),
+            (0xDE00, "(UNDEFINED)"),
+        ] {
+            assert_eq!(disasm_thumb(instruction, pc), text, "{instruction:04X}");
+        }
+        assert_eq!(
+            disasm_thumb_bl(0xF000, 0xF800, pc).as_deref(),
+            Some("bl $0x2000004")
+        );
+        assert_eq!(
+            disasm_thumb_bl(0xF000, 0xE802, pc).as_deref(),
+            Some("blx $0x2000008")
+        );
+        assert_eq!(disasm_thumb_bl(0x2001, 0xF800, pc), None);
+    }
 }
@@ -0,0 +1,333 @@
+//! Single instruction harness for fuzzing the interpreters
+//!
+//! Runs one arbitrary ARM or Thumb instruction word with arbitrary registers,
+//! flags and mode on a lone CPU behind [`NullBus`], which reads zero, drops
+//! stores and records every access. The fuzz targets in
+//! `core/lunaris_emu/fuzz` feed it libFuzzer input:
+//!
+//! ```sh
+//! cd core/lunaris_emu/fuzz
+//! cargo +nightly fuzz run arm
+//! cargo +nightly fuzz run thumb
+//! ```
+//!
+//! Panics are the failures libFuzzer reports. [`FuzzStep::check`] adds the
+//! per-instruction cycle and access bounds, and that block transfers move
+//! each listed register once.
+use crate::cpu::arm_cpu::{ArmCpu, CpuType, PsrMode};
+use crate::cpu::bus::Bus;
+use crate::cpu::coprocessor_15::Cp15;
+use crate::cpu::interpreter::{fetched, interpret};
+
+/// Most cycles one instruction may take: a 16 register `LDM` with `pc` in
+/// the list from the slowest memory, with room to spare.
+pub const MAX_INSTRUCTION_CYCLES: u64 = 1024;
+
+/// Most data accesses one instruction may make: a 16 register `LDM`/`STM`.
+pub const MAX_INSTRUCTION_ACCESSES: usize = 16;
+
+/// Address the instruction is placed at: main RAM on the ARM9, ARM7 WRAM on
+/// the ARM7.
+const fn code_address(cpu_type: CpuType) -> u32 {
+    match cpu_type {
+        CpuType::Arm9 => 0x0200_1000,
+        CpuType::Arm7 => 0x0380_1000,
+    }
+}
+
+/// Modes an input can start in, to exercise register banking.
+const MODES: [PsrMode; 7] = [
+    PsrMode::User,
+    PsrMode::Fiq,
+    PsrMode::Irq,
+    PsrMode::Supervisor,
+    PsrMode::Abort,
+    PsrMode::Undefined,
+    PsrMode::System,
+];
+
+/// One instruction and the CPU state it runs in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzInput {
+    pub cpu_type: CpuType,
+    pub thumb: bool,
+    /// Instruction word; Thumb uses the low halfword
+    pub instr: u32,
+    /// r0-r14
+    pub regs: [u32; 15],
+    /// NZCV in the top nibble, mode index (into the valid modes) below
+    pub flags: u8,
+}
+
+impl FuzzInput {
+    /// Decode fuzzer bytes: CPU select, instruction word, flags, then
+    /// registers. Missing bytes read as zero, so every input is valid.
+    pub fn from_bytes(data: &[u8], thumb: bool) -> Self {
+        let byte = |i: usize| data.get(i).copied().unwrap_or(0);
+        let word = |i: usize| u32::from_le_bytes([byte(i), byte(i + 1), byte(i + 2), byte(i + 3)]);
+        Self {
+            cpu_type: match byte(0) & 1 {
+                0 => CpuType::Arm9,
+                _ => CpuType::Arm7,
+            },
+            thumb,
+            instr: word(1),
+            flags: byte(5),
+            regs: core::array::from_fn(|i| word(6 + i * 4)),
+        }
+    }
+
+    /// Registers an `LDM`/`STM`, `PUSH` or `POP` transfers, `None` for
+    /// other instructions and empty lists.
+    const fn block_transfer_count(&self) -> Option<u32> {
+        let count = match self.thumb {
+            false if (self.instr >> 25) & 7 == 0b100 => self.instr & 0xFFFF,
+            // PUSH {..., lr}, POP {..., pc}
+            true if (self.instr >> 12) & 0xF == 0b1011 && (self.instr >> 9) & 3 == 0b10 => {
+                self.instr & 0x1FF
+            }
+            // LDMIA, STMIA
+            true if (self.instr >> 12) & 0xF == 0b1100 => self.instr & 0xFF,
+            _ => 0,
+        }
+        .count_ones();
+        match count {
+            0 => None,
+            count => Some(count),
+        }
+    }
+}
+
+/// One load or store the instruction made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Access {
+    pub address: u32,
+    /// 1, 2 or 4 bytes
+    pub size: u8,
+    /// Value stored, `None` for a load
+    pub stored: Option<u32>,
+}
+
+/// A lone CPU on a bus that reads zero, drops stores and records both.
+#[derive(Debug)]
+pub struct NullBus {
+    pub cpu: ArmCpu,
+    pub cp15: Cp15,
+    pub accesses: Vec<Access>,
+}
+
+impl NullBus {
+    pub fn new(cpu_type: CpuType) -> Self {
+        let cpu_id = match cpu_type {
+            CpuType::Arm9 => 0,
+            CpuType::Arm7 => 1,
+        };
+        let mut cpu = ArmCpu::new(cpu_id, cpu_type);
+        cpu.power_on();
+        Self {
+            cpu,
+            cp15: Cp15::new(),
+            accesses: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, address: u32, size: u8, stored: Option<u32>) {
+        self.accesses.push(Access {
+            address,
+            size,
+            stored,
+        });
+    }
+}
+
+impl Bus for NullBus {
+    fn get_cpu(&self, _cpu_type: CpuType) -> &ArmCpu {
+        &self.cpu
+    }
+
+    fn get_cpu_mut(&mut self, _cpu_type: CpuType) -> &mut ArmCpu {
+        &mut self.cpu
+    }
+
+    fn read_word(&mut self, address: u32, _cpu_type: CpuType) -> u32 {
+        self.record(address, 4, None);
+        0
+    }
+
+    fn read_halfword(&mut self, address: u32, _cpu_type: CpuType) -> u16 {
+        self.record(address, 2, None);
+        0
+    }
+
+    fn read_byte(&mut self, address: u32, _cpu_type: CpuType) -> u8 {
+        self.record(address, 1, None);
+        0
+    }
+
+    fn write_word(&mut self, address: u32, word: u32, _cpu_type: CpuType) {
+        self.record(address, 4, Some(word));
+    }
+
+    fn write_halfword(&mut self, address: u32, halfword: u16, _cpu_type: CpuType) {
+        self.record(address, 2, Some(halfword as u32));
+    }
+
+    fn write_byte(&mut self, address: u32, byte: u8, _cpu_type: CpuType) {
+        self.record(address, 1, Some(byte as u32));
+    }
+
+    fn cp15_mut(&mut self, cpu_type: CpuType) -> Option<&mut Cp15> {
+        (cpu_type == CpuType::Arm9).then_some(&mut self.cp15)
+    }
+
+    fn cp15_written(&mut self, _cpu_type: CpuType) {
+        self.cpu.apply_cp15(&self.cp15);
+    }
+}
+
+/// What one instruction did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzStep {
+    pub input: FuzzInput,
+    pub regs_before: [u32; 16],
+    pub regs_after: [u32; 16],
+    pub cycles: u64,
+    /// Loads and stores in the order they were made, the fetch excluded
+    pub accesses: Vec<Access>,
+}
+
+impl FuzzStep {
+    /// Panic if the instruction broke an invariant the interpreter cannot
+    /// check itself.
+    pub fn check(&self) {
+        assert!(
+            self.cycles <= MAX_INSTRUCTION_CYCLES,
+            "instruction took {} cycles",
+            self.cycles
+        );
+        assert!(
+            self.accesses.len() <= MAX_INSTRUCTION_ACCESSES,
+            "instruction made {} accesses",
+            self.accesses.len()
+        );
+        for access in &self.accesses {
+            assert!(
+                matches!(access.size, 1 | 2 | 4),
+                "{access:X?} has a bad size"
+            );
+            let mask = u32::MAX >> (32 - access.size as u32 * 8);
+            assert!(
+                access.stored.is_none_or(|value| value & !mask == 0),
+                "{access:X?} stores more than its size"
+            );
+        }
+
+        // Skipped by its condition, or one word per listed register
+        if let Some(count) = self.input.block_transfer_count() {
+            assert!(
+                self.accesses.is_empty()
+                    || (self.accesses.len() == count as usize
+                        && self.accesses.iter().all(|access| access.size == 4)),
+                "block transfer of {count} registers made {:X?}",
+                self.accesses
+            );
+        }
+    }
+}
+
+/// Run `input` on a new CPU.
+pub fn run(input: &FuzzInput) -> FuzzStep {
+    let cpu_type = input.cpu_type;
+    let mut bus = NullBus::new(cpu_type);
+
+    let arm = &mut bus.cpu;
+    let mode = MODES[(input.flags & 0xF) as usize % MODES.len()];
+    arm.update_reg_mode(mode);
+    arm.cpsr.mode = mode;
+    arm.cpsr.negative = input.flags & 0x80 != 0;
+    arm.cpsr.zero = input.flags & 0x40 != 0;
+    arm.cpsr.carry = input.flags & 0x20 != 0;
+    arm.cpsr.overflow = input.flags & 0x10 != 0;
+    arm.cpsr.thumb_on = input.thumb;
+    arm.regs[..15].copy_from_slice(&input.regs);
+    arm.jp(code_address(cpu_type), false);
+
+    let regs_before = arm.regs;
+    let timestamp = arm.timestamp;
+    let instr = match input.thumb {
+        true => input.instr & 0xFFFF,
+        false => input.instr,
+    };
+    fetched(&mut bus, cpu_type, instr);
+    interpret(&mut bus, cpu_type);
+
+    FuzzStep {
+        input: *input,
+        regs_before,
+        regs_after: bus.cpu.regs,
+        cycles: bus.cpu.timestamp.wrapping_sub(timestamp),
+        accesses: bus.accesses,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzz_step() {
+        // mvn r0, #5 on the ARM7
+        let mut data = vec![1];
+        data.extend_from_slice(&0xE3E0_0005u32.to_le_bytes());
+        let input = FuzzInput::from_bytes(&data, false);
+        assert_eq!(input.cpu_type, CpuType::Arm7);
+        let step = run(&input);
+        step.check();
+        assert_eq!(step.regs_after[0], !5);
+        assert_eq!(step.regs_after[15], step.regs_before[15] + 4);
+        assert!(step.accesses.is_empty());
+
+        // ldmia r0, {r0-r15} on the ARM9 still fits the bounds
+        let mut data = vec![0];
+        data.extend_from_slice(&0xE890_FFFFu32.to_le_bytes());
+        let step = run(&FuzzInput::from_bytes(&data, false));
+        step.check();
+        assert_eq!(step.accesses.len(), 16);
+
+        // str r1, [r0] with r0 = 0x0200_0000, r1 = 5
+        let mut data = vec![0];
+        data.extend_from_slice(&0xE580_1000u32.to_le_bytes());
+        data.push(0);
+        data.extend_from_slice(&0x0200_0000u32.to_le_bytes());
+        data.extend_from_slice(&5u32.to_le_bytes());
+        let step = run(&FuzzInput::from_bytes(&data, false));
+        step.check();
+        assert_eq!(
+            step.accesses,
+            [Access {
+                address: 0x0200_0000,
+                size: 4,
+                stored: Some(5),
+            }]
+        );
+
+        // Thumb mvn r0, r1
+        let data = [0, 0xC8, 0x43, 0, 0, 0, 0, 0, 0, 0, 5];
+        let step = run(&FuzzInput::from_bytes(&data, true));
+        step.check();
+        assert_eq!(step.regs_after[0], !5);
+        assert_eq!(step.regs_after[15], step.regs_before[15] + 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "block transfer of 2 registers")]
+    fn test_check_block_transfer() {
+        // stmia r0, {r1, r2} that only stored r1
+        let mut data = vec![0];
+        data.extend_from_slice(&0xE880_0006u32.to_le_bytes());
+        let mut step = run(&FuzzInput::from_bytes(&data, false));
+        step.check();
+        step.accesses.pop();
+        step.check();
+    }
+}
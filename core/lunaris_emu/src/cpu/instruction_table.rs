@@ -31,7 +31,10 @@ pub enum ARMInstr {
     StoreBlock,
     LoadBlock,
     CopRegTransfer,
+    CopDoubleTransfer, // MCRR, MRRC
     CopDataOp,
+    Breakpoint,
+    Preload,
     Swi,
 }
 
@@ -69,6 +72,7 @@ pub enum ThumbInstr {
     LongBranchPrep,
     LongBranch,
     LongBlx,
+    Breakpoint,
     #[expect(unused)]
     Swi,
 }
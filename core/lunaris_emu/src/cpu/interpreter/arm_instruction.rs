@@ -1,6 +1,6 @@
 #![allow(clippy::missing_const_for_fn)]
-use crate::cpu::arm_cpu::{CpuType, PsrMode, REG_LR, REG_PC, add_overflow, sub_overflow};
-use crate::emulator::Emulator;
+use crate::cpu::arm_cpu::{CpuType, PsrMode, REG_LR, REG_PC, Reg, add_overflow, sub_overflow};
+use crate::cpu::bus::Bus;
 
 /// Loads or stores a value using a shifted register addressing mode.
 ///
@@ -65,10 +65,10 @@ use crate::emulator::Emulator;
 /// ## References
 /// Single Data Transfer — Register Offset
 #[allow(clippy::missing_const_for_fn)]
-pub fn load_store_shift_reg(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) -> u32 {
+pub fn load_store_shift_reg<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) -> u32 {
     let mut reg = emu
         .get_cpu_mut(cpu_type)
-        .get_register((instruction & 0xF) as i32);
+        .get_register(Reg::new(instruction & 0xF));
 
     let shift_type = (instruction >> 5) & 0x3;
     let mut shift = (instruction >> 7) & 0x1F;
@@ -111,24 +111,24 @@ pub fn load_store_shift_reg(emu: &mut Emulator, cpu_type: CpuType, instruction:
 }
 
 /// Undefined instruction handler
-pub fn undefined(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn undefined<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let _ = instruction;
     #[cfg(feature = "tracing")]
     tracing::warn!("Unrecognized ARM opcode {instruction:08X}");
 
-    emu.get_cpu_mut(cpu_type).handle_undefined();
+    emu.undefined_instruction(cpu_type);
 }
 
 /// Data processing instruction
 #[allow(clippy::missing_const_for_fn)]
-pub fn data_processing(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn data_processing<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let opcode = (instruction >> 21) & 0xF;
 
-    let first_operand = ((instruction >> 16) & 0xF) as i32;
+    let first_operand = Reg::new((instruction >> 16) & 0xF);
     let first_operand_contents = emu.get_cpu_mut(cpu_type).get_register(first_operand);
 
     let set_condition_codes = (instruction & (1 << 20)) != 0;
-    let destination = (instruction >> 12) & 0xF;
+    let destination = Reg::new((instruction >> 12) & 0xF);
 
     let is_operand_imm = (instruction & (1 << 25)) != 0;
 
@@ -141,20 +141,21 @@ pub fn data_processing(emu: &mut Emulator, cpu_type: CpuType, instruction: u32)
         let shift = (instruction & 0xF00) >> 7;
         emu.get_cpu_mut(cpu_type).rotr32(imm, shift, set_carry)
     } else {
-        let rm = (instruction & 0xF) as i32;
+        let rm = Reg::new(instruction & 0xF);
         let mut value = emu.get_cpu_mut(cpu_type).get_register(rm);
         let shift_type = (instruction >> 5) & 0x3;
 
         let shift = if (instruction & (1 << 4)) != 0 {
             // Shift by register
-            let rs = ((instruction >> 8) & 0xF) as i32;
+            let rs = Reg::new((instruction >> 8) & 0xF);
             emu.get_cpu_mut(cpu_type).add_internal_cycles(1);
 
-            if rm == (REG_PC as i32) {
+            if rm == REG_PC {
                 value = emu.get_cpu(cpu_type).get_pc() + 4;
             }
 
-            emu.get_cpu_mut(cpu_type).get_register(rs)
+            // Only the bottom byte of Rs counts
+            emu.get_cpu_mut(cpu_type).get_register(rs) & 0xFF
         } else {
             // Shift by immediate
             (instruction >> 7) & 0x1F
@@ -196,15 +197,15 @@ pub fn data_processing(emu: &mut Emulator, cpu_type: CpuType, instruction: u32)
 
     match opcode {
         0x0 => emu.get_cpu_mut(cpu_type).andd(
-            destination as i32,
-            first_operand_contents as i32,
-            second_operand as i32,
+            destination,
+            first_operand_contents,
+            second_operand,
             set_condition_codes,
         ),
         0x1 => emu.get_cpu_mut(cpu_type).eor(
-            destination as i32,
-            first_operand_contents as i32,
-            second_operand as i32,
+            destination,
+            first_operand_contents,
+            second_operand,
             set_condition_codes,
         ),
         0x2 => emu.get_cpu_mut(cpu_type).sub(
@@ -278,9 +279,9 @@ pub fn data_processing(emu: &mut Emulator, cpu_type: CpuType, instruction: u32)
         }
 
         0xC => emu.get_cpu_mut(cpu_type).orr(
-            destination as i32,
-            first_operand_contents as i32,
-            second_operand as i32,
+            destination,
+            first_operand_contents,
+            second_operand,
             set_condition_codes,
         ),
         0xD => emu
@@ -308,18 +309,18 @@ pub fn data_processing(emu: &mut Emulator, cpu_type: CpuType, instruction: u32)
 }
 
 /// Counts the leading zeros in a value
-pub fn count_leading_zeros(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
-    // CLZ is undefined when ID flag is set
-    if emu.get_cpu(cpu_type).get_id() <= 0 {
+pub fn count_leading_zeros<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
+    // CLZ is ARMv5, undefined on the ARM7
+    if emu.get_cpu(cpu_type).get_id() > 0 {
         #[cfg(feature = "tracing")]
-        tracing::error!("CLZ executed while ID flag set (instr={instruction:#010X})");
+        tracing::error!("CLZ executed on the ARM7 (instr={instruction:#010X})");
 
-        emu.get_cpu_mut(cpu_type).handle_undefined();
+        emu.undefined_instruction(cpu_type);
         return;
     }
 
-    let source_reg = (instruction & 0xF) as i32;
-    let destination = ((instruction >> 12) & 0xF) as i32;
+    let source_reg = Reg::new(instruction & 0xF);
+    let destination = Reg::new((instruction >> 12) & 0xF);
 
     // if emu.get_cpu_mut(cpu_type).can_disassemble() { ... }
 
@@ -344,23 +345,23 @@ pub fn count_leading_zeros(emu: &mut Emulator, cpu_type: CpuType, instruction: u
 }
 
 /// Saturated operation
-pub fn saturated_op(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
-    // Saturated ops are undefined when ID flag is set
-    if emu.get_cpu(cpu_type).get_id() <= 0 {
+pub fn saturated_op<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
+    // Saturated ops are ARMv5, undefined on the ARM7
+    if emu.get_cpu(cpu_type).get_id() > 0 {
         #[cfg(feature = "tracing")]
-        tracing::error!("Saturated op executed while ID flag set (instr={instruction:#010X})");
+        tracing::error!("Saturated op executed on the ARM7 (instr={instruction:#010X})");
 
-        emu.get_cpu_mut(cpu_type).handle_undefined();
+        emu.undefined_instruction(cpu_type);
         return;
     }
 
     let opcode = (instruction >> 20) & 0xF;
-    let operand = (instruction >> 16) & 0xF;
-    let destination = (instruction >> 12) & 0xF;
-    let source = (instruction & 0xF) as usize;
+    let operand = Reg::new((instruction >> 16) & 0xF);
+    let destination = Reg::new((instruction >> 12) & 0xF);
+    let source = Reg::new(instruction & 0xF);
 
-    let operand_reg = emu.get_cpu_mut(cpu_type).get_register(operand as i32);
-    let source_reg = emu.get_cpu_mut(cpu_type).get_register(source as i32);
+    let operand_reg = emu.get_cpu_mut(cpu_type).get_register(operand);
+    let source_reg = emu.get_cpu_mut(cpu_type).get_register(source);
 
     let mut result: u32;
 
@@ -379,8 +380,7 @@ pub fn saturated_op(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
                 };
             }
 
-            emu.get_cpu_mut(cpu_type)
-                .set_register(destination as i32, result);
+            emu.get_cpu_mut(cpu_type).set_register(destination, result);
         }
 
         0x2 => {
@@ -397,11 +397,26 @@ pub fn saturated_op(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
                 };
             }
 
-            emu.get_cpu_mut(cpu_type)
-                .set_register(destination as i32, result);
+            emu.get_cpu_mut(cpu_type).set_register(destination, result);
+        }
+
+        0x4 | 0x6 => {
+            // QDADD / QDSUB, doubling Rn saturates on its own
+            let (doubled, doubled_saturated) = saturate(i64::from(operand_reg as i32) * 2);
+            let doubled = i64::from(doubled as i32);
+            let source = i64::from(source_reg as i32);
+            let (value, saturated) = saturate(if opcode == 0x4 {
+                source + doubled
+            } else {
+                source - doubled
+            });
+
+            if doubled_saturated || saturated {
+                emu.get_cpu_mut(cpu_type).get_cpsr_mut().sticky_overflow = true;
+            }
+            emu.get_cpu_mut(cpu_type).set_register(destination, value);
         }
 
-        // QDADD / QDSUB intentionally unimplemented (same as C++)
         _ => {
             #[cfg(feature = "tracing")]
             tracing::error!("Unrecognized saturated opcode {opcode} (instr={instruction:#010X})");
@@ -409,15 +424,21 @@ pub fn saturated_op(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
     }
 }
 
+/// `value` clamped to the signed 32 bit range, and whether it was clamped
+fn saturate(value: i64) -> (u32, bool) {
+    let clamped = value.clamp(i64::from(i32::MIN), i64::from(i32::MAX));
+    (clamped as i32 as u32, clamped != value)
+}
+
 /// Multiply instruction
 #[allow(clippy::missing_const_for_fn)]
-pub fn multiply(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn multiply<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let accumulate = instruction & (1 << 21);
     let set_condition_codes = instruction & (1 << 20);
-    let destination = ((instruction >> 16) & 0xF) as i32;
-    let first_operand = (instruction & 0xF) as i32;
-    let second_operand = ((instruction >> 8) & 0xF) as i32;
-    let third_operand = ((instruction >> 12) & 0xF) as i32;
+    let destination = Reg::new((instruction >> 16) & 0xF);
+    let first_operand = Reg::new(instruction & 0xF);
+    let second_operand = Reg::new((instruction >> 8) & 0xF);
+    let third_operand = Reg::new((instruction >> 12) & 0xF);
 
     let mut result = emu.get_cpu_mut(cpu_type).get_register(first_operand)
         * emu.get_cpu_mut(cpu_type).get_register(second_operand);
@@ -440,15 +461,15 @@ pub fn multiply(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
 }
 
 /// Long multiply instruction
-pub fn multiply_long(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn multiply_long<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let is_signed = (instruction & (1 << 22)) != 0;
     let accumulate = (instruction & (1 << 21)) != 0;
     let set_condition_codes = (instruction & (1 << 20)) != 0;
 
-    let dest_hi = ((instruction >> 16) & 0xF) as i32;
-    let dest_lo = ((instruction >> 12) & 0xF) as i32;
-    let first_operand = ((instruction >> 8) & 0xF) as i32;
-    let second_operand = (instruction & 0xF) as i32;
+    let dest_hi = Reg::new((instruction >> 16) & 0xF);
+    let dest_lo = Reg::new((instruction >> 12) & 0xF);
+    let first_operand = Reg::new((instruction >> 8) & 0xF);
+    let second_operand = Reg::new(instruction & 0xF);
 
     let first_operand = emu.get_cpu_mut(cpu_type).get_register(first_operand);
     let second_operand = emu.get_cpu_mut(cpu_type).get_register(second_operand);
@@ -495,16 +516,16 @@ pub fn multiply_long(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
 }
 
 /// Signed halfword multiply instruction
-pub fn signed_halfword_multiply(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn signed_halfword_multiply<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     // No-op if ID bit set (matches C++)
     if emu.get_cpu(cpu_type).get_id() > 0 {
         return;
     }
 
-    let destination = ((instruction >> 16) & 0xF) as i32;
-    let accumulate = ((instruction >> 12) & 0xF) as i32;
-    let first_operand = ((instruction >> 8) & 0xF) as i32;
-    let second_operand = (instruction & 0xF) as i32;
+    let destination = Reg::new((instruction >> 16) & 0xF);
+    let accumulate = Reg::new((instruction >> 12) & 0xF);
+    let first_operand = Reg::new((instruction >> 8) & 0xF);
+    let second_operand = Reg::new(instruction & 0xF);
     let opcode = (instruction >> 21) & 0xF;
 
     let first_op_top = (instruction & (1 << 6)) != 0;
@@ -570,6 +591,35 @@ pub fn signed_halfword_multiply(emu: &mut Emulator, cpu_type: CpuType, instructi
             }
         }
 
+        0xA => {
+            // SMLALxy, a 64 bit accumulate in RdHi:RdLo
+            let op1 = emu.get_cpu_mut(cpu_type).get_register(first_operand);
+            let op2 = emu.get_cpu_mut(cpu_type).get_register(second_operand);
+
+            product = if first_op_top {
+                (op1 >> 16) as i16 as i32
+            } else {
+                (op1 & 0xFFFF) as i16 as i32
+            };
+
+            let rhs = if second_op_top {
+                (op2 >> 16) as i16 as i32
+            } else {
+                (op2 & 0xFFFF) as i16 as i32
+            };
+
+            product *= rhs;
+
+            let low = emu.get_cpu_mut(cpu_type).get_register(accumulate);
+            let high = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let sum =
+                ((u64::from(high) << 32) | u64::from(low)).wrapping_add(product as i64 as u64);
+
+            emu.get_cpu_mut(cpu_type)
+                .set_register(accumulate, sum as u32);
+            result = (sum >> 32) as u32;
+        }
+
         0xB => {
             // SMULxy
             let op1 = emu.get_cpu_mut(cpu_type).get_register(first_operand);
@@ -602,11 +652,11 @@ pub fn signed_halfword_multiply(emu: &mut Emulator, cpu_type: CpuType, instructi
 }
 
 /// Swap instruction
-pub fn swap(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn swap<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let is_byte = (instruction & (1 << 22)) != 0;
-    let base = ((instruction >> 16) & 0xF) as i32;
-    let destination = ((instruction >> 12) & 0xF) as i32;
-    let source = (instruction & 0xF) as i32;
+    let base = Reg::new((instruction >> 16) & 0xF);
+    let destination = Reg::new((instruction >> 12) & 0xF);
+    let source = Reg::new(instruction & 0xF);
 
     let address = emu.get_cpu_mut(cpu_type).get_register(base);
 
@@ -638,9 +688,9 @@ pub fn swap(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
 }
 
 /// Store a word to memory
-pub fn store_word(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
-    let base = (instruction >> 16) & 0xF;
-    let source = (instruction >> 12) & 0xF;
+pub fn store_word<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
+    let base = Reg::new((instruction >> 16) & 0xF);
+    let source = Reg::new((instruction >> 12) & 0xF);
 
     let is_imm = (instruction & (1 << 25)) == 0;
     let is_preindexing = (instruction & (1 << 24)) != 0;
@@ -653,8 +703,8 @@ pub fn store_word(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         load_store_shift_reg(emu, cpu_type, instruction)
     };
 
-    let mut address = emu.get_cpu(cpu_type).get_register(base as i32);
-    let value = emu.get_cpu(cpu_type).get_register(source as i32);
+    let mut address = emu.get_cpu(cpu_type).get_register(base);
+    let value = emu.get_cpu(cpu_type).get_register(source);
 
     if is_preindexing {
         if is_adding_offset {
@@ -664,7 +714,7 @@ pub fn store_word(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         }
 
         if is_writing_back {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
 
         emu.get_cpu_mut(cpu_type).add_n32_data(address, 1);
@@ -679,14 +729,14 @@ pub fn store_word(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
             address = address.wrapping_sub(offset);
         }
 
-        emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+        emu.get_cpu_mut(cpu_type).set_register(base, address);
     }
 }
 
 /// Load a word from memory
-pub fn load_word(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
-    let base = (instruction >> 16) & 0xF;
-    let destination = (instruction >> 12) & 0xF;
+pub fn load_word<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
+    let base = Reg::new((instruction >> 16) & 0xF);
+    let destination = Reg::new((instruction >> 12) & 0xF);
 
     let is_imm = (instruction & (1 << 25)) == 0;
     let is_pre_indexing = (instruction & (1 << 24)) != 0;
@@ -699,7 +749,7 @@ pub fn load_word(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         load_store_shift_reg(emu, cpu_type, instruction)
     };
 
-    let mut address = emu.get_cpu(cpu_type).get_register(base as i32);
+    let mut address = emu.get_cpu(cpu_type).get_register(base);
     emu.get_cpu_mut(cpu_type).add_n32_data(address, 1);
 
     if is_pre_indexing {
@@ -710,7 +760,7 @@ pub fn load_word(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         }
 
         if is_writing_back {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
 
         let value = emu.read_word(address & !0x3, cpu_type);
@@ -723,8 +773,7 @@ pub fn load_word(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
             let has_change_cpu_id = emu.get_cpu(cpu_type).get_id() <= 0;
             emu.get_cpu_mut(cpu_type).jp(word, has_change_cpu_id);
         } else {
-            emu.get_cpu_mut(cpu_type)
-                .set_register(destination as i32, word);
+            emu.get_cpu_mut(cpu_type).set_register(destination, word);
         }
     } else {
         let value = emu.read_word(address & !0x3, cpu_type);
@@ -737,8 +786,7 @@ pub fn load_word(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
             let has_change_cpu_id = emu.get_cpu(cpu_type).get_id() <= 0;
             emu.get_cpu_mut(cpu_type).jp(word, has_change_cpu_id);
         } else {
-            emu.get_cpu_mut(cpu_type)
-                .set_register(destination as i32, word);
+            emu.get_cpu_mut(cpu_type).set_register(destination, word);
         }
 
         if is_adding_offset {
@@ -748,15 +796,15 @@ pub fn load_word(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         }
 
         if base != destination {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
     }
 }
 
 /// Store a byte to memory
-pub fn store_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
-    let base = (instruction >> 16) & 0xF;
-    let source = (instruction >> 12) & 0xF;
+pub fn store_byte<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
+    let base = Reg::new((instruction >> 16) & 0xF);
+    let source = Reg::new((instruction >> 12) & 0xF);
 
     let is_imm = (instruction & (1 << 25)) == 0;
     let is_preindexing = (instruction & (1 << 24)) != 0;
@@ -769,8 +817,8 @@ pub fn store_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         load_store_shift_reg(emu, cpu_type, instruction)
     };
 
-    let mut address = emu.get_cpu(cpu_type).get_register(base as i32);
-    let value = (emu.get_cpu(cpu_type).get_register(source as i32) & 0xFF) as u8;
+    let mut address = emu.get_cpu(cpu_type).get_register(base);
+    let value = (emu.get_cpu(cpu_type).get_register(source) & 0xFF) as u8;
 
     emu.get_cpu_mut(cpu_type).add_n16_data(address, 1);
 
@@ -781,7 +829,7 @@ pub fn store_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         }
 
         if is_writing_back {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
         emu.write_byte(address, value, cpu_type);
     } else {
@@ -792,14 +840,14 @@ pub fn store_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
             false => address = address.wrapping_sub(offset),
         }
 
-        emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+        emu.get_cpu_mut(cpu_type).set_register(base, address);
     }
 }
 
 /// Load a byte from memory
-pub fn load_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
-    let base = (instruction >> 16) & 0xF;
-    let destination = (instruction >> 12) & 0xF;
+pub fn load_byte<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
+    let base = Reg::new((instruction >> 16) & 0xF);
+    let destination = Reg::new((instruction >> 12) & 0xF);
 
     let is_imm = (instruction & (1 << 25)) == 0;
     let is_preindexing = (instruction & (1 << 24)) != 0;
@@ -812,7 +860,7 @@ pub fn load_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         load_store_shift_reg(emu, cpu_type, instruction)
     };
 
-    let mut address = emu.get_cpu(cpu_type).get_register(base as i32);
+    let mut address = emu.get_cpu(cpu_type).get_register(base);
 
     // Timing behavior matches original C++:
     emu.get_cpu_mut(cpu_type).add_n16_data(address, 1);
@@ -826,16 +874,14 @@ pub fn load_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         }
 
         if is_writing_back {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
 
         let value = emu.read_byte(address, cpu_type) as u32;
-        emu.get_cpu_mut(cpu_type)
-            .set_register(destination as i32, value);
+        emu.get_cpu_mut(cpu_type).set_register(destination, value);
     } else {
         let value = emu.read_byte(address, cpu_type) as u32;
-        emu.get_cpu_mut(cpu_type)
-            .set_register(destination as i32, value);
+        emu.get_cpu_mut(cpu_type).set_register(destination, value);
 
         if is_adding_offset {
             address = address.wrapping_add(offset);
@@ -844,19 +890,19 @@ pub fn load_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         }
 
         if base != destination {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
     }
 }
 
 /// Store a halfword to memory
-pub fn store_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn store_halfword<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let is_preindexing = (instruction & (1 << 24)) != 0;
     let is_adding_offset = (instruction & (1 << 23)) != 0;
     let is_writing_back = (instruction & (1 << 21)) != 0;
 
-    let base = (instruction >> 16) & 0xF;
-    let source = (instruction >> 12) & 0xF;
+    let base = Reg::new((instruction >> 16) & 0xF);
+    let source = Reg::new((instruction >> 12) & 0xF);
 
     let is_imm_offset = (instruction & (1 << 22)) != 0;
 
@@ -864,11 +910,11 @@ pub fn store_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
     if is_imm_offset {
         offset |= (instruction >> 4) & 0xF0;
     } else {
-        offset = emu.get_cpu(cpu_type).get_register(offset as i32);
+        offset = emu.get_cpu(cpu_type).get_register(Reg::new(offset));
     }
 
-    let mut address = emu.get_cpu(cpu_type).get_register(base as i32);
-    let halfword = (emu.get_cpu(cpu_type).get_register(source as i32) & 0xFFFF) as u16;
+    let mut address = emu.get_cpu(cpu_type).get_register(base);
+    let halfword = (emu.get_cpu(cpu_type).get_register(source) & 0xFFFF) as u16;
 
     if is_preindexing {
         if is_adding_offset {
@@ -880,7 +926,7 @@ pub fn store_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         emu.write_halfword(address, halfword, cpu_type);
 
         if is_writing_back {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
     } else {
         emu.write_halfword(address, halfword, cpu_type);
@@ -891,20 +937,20 @@ pub fn store_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
             address = address.wrapping_sub(offset);
         }
 
-        emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+        emu.get_cpu_mut(cpu_type).set_register(base, address);
     }
 
     emu.get_cpu_mut(cpu_type).add_n16_data(address, 1);
 }
 
 /// Load a halfword from memory
-pub fn load_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn load_halfword<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let is_preindexing = (instruction & (1 << 24)) != 0;
     let is_adding_offset = (instruction & (1 << 23)) != 0;
     let is_writing_back = (instruction & (1 << 21)) != 0;
 
-    let base = (instruction >> 16) & 0xF;
-    let destination = (instruction >> 12) & 0xF;
+    let base = Reg::new((instruction >> 16) & 0xF);
+    let destination = Reg::new((instruction >> 12) & 0xF);
 
     let is_imm_offset = (instruction & (1 << 22)) != 0;
 
@@ -912,10 +958,10 @@ pub fn load_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
     if is_imm_offset {
         offset |= (instruction >> 4) & 0xF0;
     } else {
-        offset = emu.get_cpu(cpu_type).get_register(offset as i32);
+        offset = emu.get_cpu(cpu_type).get_register(Reg::new(offset));
     }
 
-    let mut address = emu.get_cpu(cpu_type).get_register(base as i32);
+    let mut address = emu.get_cpu(cpu_type).get_register(base);
 
     if is_preindexing {
         if is_adding_offset {
@@ -925,16 +971,14 @@ pub fn load_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         }
 
         if is_writing_back && base != destination {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
 
         let value = emu.read_halfword(address, cpu_type) as u32;
-        emu.get_cpu_mut(cpu_type)
-            .set_register(destination as i32, value);
+        emu.get_cpu_mut(cpu_type).set_register(destination, value);
     } else {
         let value = emu.read_halfword(address, cpu_type) as u32;
-        emu.get_cpu_mut(cpu_type)
-            .set_register(destination as i32, value);
+        emu.get_cpu_mut(cpu_type).set_register(destination, value);
 
         if is_adding_offset {
             address = address.wrapping_add(offset);
@@ -943,7 +987,7 @@ pub fn load_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         }
 
         if base != destination {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
     }
 
@@ -951,13 +995,13 @@ pub fn load_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
 }
 
 /// Load a signed byte from memory
-pub fn load_signed_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn load_signed_byte<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let is_preindexing = (instruction & (1 << 24)) != 0;
     let is_adding_offset = (instruction & (1 << 23)) != 0;
     let is_writing_back = (instruction & (1 << 21)) != 0;
 
-    let base = (instruction >> 16) & 0xF;
-    let destination = (instruction >> 12) & 0xF;
+    let base = Reg::new((instruction >> 16) & 0xF);
+    let destination = Reg::new((instruction >> 12) & 0xF);
 
     let is_imm_offset = (instruction & (1 << 22)) != 0;
 
@@ -965,10 +1009,10 @@ pub fn load_signed_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32)
     if is_imm_offset {
         offset |= (instruction >> 4) & 0xF0;
     } else {
-        offset = emu.get_cpu(cpu_type).get_register(offset as i32);
+        offset = emu.get_cpu(cpu_type).get_register(Reg::new(offset));
     }
 
-    let mut address = emu.get_cpu(cpu_type).get_register(base as i32);
+    let mut address = emu.get_cpu(cpu_type).get_register(base);
 
     if is_preindexing {
         if is_adding_offset {
@@ -978,18 +1022,16 @@ pub fn load_signed_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32)
         }
 
         if is_writing_back {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
 
         let byte = emu.read_byte(address, cpu_type) as i8;
         let value = byte as i32 as u32;
-        emu.get_cpu_mut(cpu_type)
-            .set_register(destination as i32, value);
+        emu.get_cpu_mut(cpu_type).set_register(destination, value);
     } else {
         let byte = emu.read_byte(address, cpu_type) as i8;
         let value = byte as i32 as u32;
-        emu.get_cpu_mut(cpu_type)
-            .set_register(destination as i32, value);
+        emu.get_cpu_mut(cpu_type).set_register(destination, value);
 
         if is_adding_offset {
             address = address.wrapping_add(offset);
@@ -998,7 +1040,7 @@ pub fn load_signed_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32)
         }
 
         if base != destination {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
     }
 
@@ -1006,13 +1048,13 @@ pub fn load_signed_byte(emu: &mut Emulator, cpu_type: CpuType, instruction: u32)
 }
 
 /// Load a signed halfword from memory
-pub fn load_signed_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn load_signed_halfword<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let is_preindexing = (instruction & (1 << 24)) != 0;
     let is_adding_offset = (instruction & (1 << 23)) != 0;
     let is_writing_back = (instruction & (1 << 21)) != 0;
 
-    let base = (instruction >> 16) & 0xF;
-    let destination = (instruction >> 12) & 0xF;
+    let base = Reg::new((instruction >> 16) & 0xF);
+    let destination = Reg::new((instruction >> 12) & 0xF);
 
     let is_imm_offset = (instruction & (1 << 22)) != 0;
 
@@ -1020,10 +1062,10 @@ pub fn load_signed_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction:
     if is_imm_offset {
         offset |= (instruction >> 4) & 0xF0;
     } else {
-        offset = emu.get_cpu(cpu_type).get_register(offset as i32);
+        offset = emu.get_cpu(cpu_type).get_register(Reg::new(offset));
     }
 
-    let mut address = emu.get_cpu(cpu_type).get_register(base as i32);
+    let mut address = emu.get_cpu(cpu_type).get_register(base);
 
     if is_preindexing {
         if is_adding_offset {
@@ -1033,18 +1075,16 @@ pub fn load_signed_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction:
         }
 
         if is_writing_back {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
 
         let half = emu.read_halfword(address, cpu_type) as i16;
         let value = half as i32 as u32;
-        emu.get_cpu_mut(cpu_type)
-            .set_register(destination as i32, value);
+        emu.get_cpu_mut(cpu_type).set_register(destination, value);
     } else {
         let half = emu.read_halfword(address, cpu_type) as i16;
         let value = half as i32 as u32;
-        emu.get_cpu_mut(cpu_type)
-            .set_register(destination as i32, value);
+        emu.get_cpu_mut(cpu_type).set_register(destination, value);
 
         if is_adding_offset {
             address = address.wrapping_add(offset);
@@ -1053,7 +1093,7 @@ pub fn load_signed_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction:
         }
 
         if base != destination {
-            emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+            emu.get_cpu_mut(cpu_type).set_register(base, address);
         }
     }
 
@@ -1061,59 +1101,96 @@ pub fn load_signed_halfword(emu: &mut Emulator, cpu_type: CpuType, instruction:
 }
 
 /// Store a doubleword to memory
-pub fn store_doubleword(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn store_doubleword<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     // Only supported on ARM9 (matches C++ behavior)
     if emu.get_cpu(cpu_type).get_id() != 0 {
-        emu.get_cpu_mut(cpu_type).handle_undefined();
+        emu.undefined_instruction(cpu_type);
         return;
     }
 
     let is_preindexing = (instruction & (1 << 24)) != 0;
-    let add_offset = (instruction & (1 << 23)) != 0;
-    let is_imm_offset = (instruction & (1 << 22)) != 0;
-    let write_back = (instruction & (1 << 21)) != 0;
+    let is_writing_back = (instruction & (1 << 21)) != 0;
+    let base = Reg::new((instruction >> 16) & 0xF);
+    let source = Reg::new((instruction >> 12) & 0xF);
+
+    let (address, new_base) = doubleword_address(emu, cpu_type, instruction);
 
-    let base = (instruction >> 16) & 0xF;
-    let source = (instruction >> 12) & 0xF;
+    let low = emu.get_cpu(cpu_type).get_register(source);
+    let high = emu
+        .get_cpu(cpu_type)
+        .get_register(Reg::new((source.index() as u32 + 1) & 0xF));
+    emu.write_word(address, low, cpu_type);
+    emu.write_word(address.wrapping_add(4), high, cpu_type);
+
+    if is_writing_back || !is_preindexing {
+        emu.get_cpu_mut(cpu_type).set_register(base, new_base);
+    }
+
+    emu.get_cpu_mut(cpu_type).add_n32_data(address, 2);
+}
+
+/// Load a doubleword from memory
+pub fn load_doubleword<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
+    // Only supported on ARM9, like STRD
+    if emu.get_cpu(cpu_type).get_id() != 0 {
+        emu.undefined_instruction(cpu_type);
+        return;
+    }
+
+    let is_preindexing = (instruction & (1 << 24)) != 0;
+    let is_writing_back = (instruction & (1 << 21)) != 0;
+    let base = Reg::new((instruction >> 16) & 0xF);
+    let destination = Reg::new((instruction >> 12) & 0xF);
+
+    let (address, new_base) = doubleword_address(emu, cpu_type, instruction);
+
+    let low = emu.read_word(address, cpu_type);
+    let high = emu.read_word(address.wrapping_add(4), cpu_type);
+
+    // Writeback first, a loaded base wins
+    if is_writing_back || !is_preindexing {
+        emu.get_cpu_mut(cpu_type).set_register(base, new_base);
+    }
+    emu.get_cpu_mut(cpu_type).set_register(destination, low);
+    emu.get_cpu_mut(cpu_type)
+        .set_register(Reg::new((destination.index() as u32 + 1) & 0xF), high);
+
+    emu.get_cpu_mut(cpu_type).add_n32_data(address, 2);
+    emu.get_cpu_mut(cpu_type).add_internal_cycles(1);
+}
+
+/// Address LDRD/STRD transfer at, and the base after the offset is applied
+fn doubleword_address<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) -> (u32, u32) {
+    let is_preindexing = (instruction & (1 << 24)) != 0;
+    let is_adding_offset = (instruction & (1 << 23)) != 0;
+    let is_imm_offset = (instruction & (1 << 22)) != 0;
+    let base = Reg::new((instruction >> 16) & 0xF);
 
     let mut offset = instruction & 0xF;
     if is_imm_offset {
         offset |= (instruction >> 4) & 0xF0;
     } else {
-        offset = emu.get_cpu(cpu_type).get_register(offset as i32);
+        offset = emu.get_cpu(cpu_type).get_register(Reg::new(offset));
     }
 
-    let mut address = emu.get_cpu(cpu_type).get_register(base as i32);
+    let address = emu.get_cpu(cpu_type).get_register(base);
+    let new_base = if is_adding_offset {
+        address.wrapping_add(offset)
+    } else {
+        address.wrapping_sub(offset)
+    };
 
     if is_preindexing {
-        if add_offset {
-            address = address.wrapping_add(offset);
-        } else {
-            address = address.wrapping_sub(offset);
-        }
-
-        let low = emu.get_cpu(cpu_type).get_register(source as i32);
-        let high = emu.get_cpu(cpu_type).get_register((source + 1) as i32);
-
-        emu.write_word(address, low, cpu_type);
-        emu.write_word(address.wrapping_add(4), high, cpu_type);
-
-        if write_back {
-            // Mirrors the original C++ exactly (even though it looks odd)
-            let base_reg = emu.get_cpu(cpu_type).get_register(base as i32);
-            emu.get_cpu_mut(cpu_type)
-                .set_register(base_reg as i32, address.wrapping_add(4));
-        }
+        (new_base, new_base)
     } else {
-        #[cfg(feature = "tracing")]
-        tracing::error!("store post_indexing not supported");
+        (address, new_base)
     }
 }
 
 /// Load multiple registers from memory (LDM)
-pub fn load_block(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn load_block<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let reg_list = instruction & 0xFFFF;
-    let base = (instruction >> 16) & 0xF;
+    let base = Reg::new((instruction >> 16) & 0xF);
 
     let is_writing_back = (instruction & (1 << 21)) != 0;
     let load_psr = (instruction & (1 << 22)) != 0;
@@ -1122,7 +1199,7 @@ pub fn load_block(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
 
     let user_bank_transfer = load_psr && (reg_list & (1 << 15)) == 0;
 
-    let mut address = emu.get_cpu(cpu_type).get_register(base as i32);
+    let mut address = emu.get_cpu(cpu_type).get_register(base);
 
     let offset: i32 = if is_adding_offset { 4 } else { -4 };
 
@@ -1145,10 +1222,10 @@ pub fn load_block(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
                 if is_preindexing {
                     address = address.wrapping_add(offset as u32);
                     let value = emu.read_word(address, cpu_type);
-                    emu.get_cpu_mut(cpu_type).set_register(i, value);
+                    emu.get_cpu_mut(cpu_type).set_register(Reg::new(i), value);
                 } else {
                     let value = emu.read_word(address, cpu_type);
-                    emu.get_cpu_mut(cpu_type).set_register(i, value);
+                    emu.get_cpu_mut(cpu_type).set_register(Reg::new(i), value);
                     address = address.wrapping_add(offset as u32);
                 }
             }
@@ -1211,10 +1288,10 @@ pub fn load_block(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
                 if is_preindexing {
                     address = address.wrapping_add(offset as u32);
                     let value = emu.read_word(address, cpu_type);
-                    emu.get_cpu_mut(cpu_type).set_register(i, value);
+                    emu.get_cpu_mut(cpu_type).set_register(Reg::new(i), value);
                 } else {
                     let value = emu.read_word(address, cpu_type);
-                    emu.get_cpu_mut(cpu_type).set_register(i, value);
+                    emu.get_cpu_mut(cpu_type).set_register(Reg::new(i), value);
                     address = address.wrapping_add(offset as u32);
                 }
             }
@@ -1235,15 +1312,17 @@ pub fn load_block(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
     emu.get_cpu_mut(cpu_type).add_internal_cycles(1);
 
     // Writeback (blocked when base is in list on ARM9)
-    if is_writing_back && !((reg_list & (1 << base)) != 0 && emu.get_cpu(cpu_type).get_id() != 0) {
-        emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+    if is_writing_back
+        && !((reg_list & (1 << base.index())) != 0 && emu.get_cpu(cpu_type).get_id() != 0)
+    {
+        emu.get_cpu_mut(cpu_type).set_register(base, address);
     }
 }
 
 /// Store a block of registers to memory
-pub fn store_block(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn store_block<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let reg_list = instruction & 0xFFFF;
-    let base = (instruction >> 16) & 0xF;
+    let base = Reg::new((instruction >> 16) & 0xF);
 
     let is_writing_back = (instruction & (1 << 21)) != 0;
     let load_psr = (instruction & (1 << 22)) != 0;
@@ -1252,7 +1331,7 @@ pub fn store_block(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
 
     let user_bank_transfer = load_psr && (reg_list & (1 << 15)) == 0;
 
-    let mut address = emu.get_cpu(cpu_type).get_register(base as i32);
+    let mut address = emu.get_cpu(cpu_type).get_register(base);
 
     let offset: i32 = if is_adding_offset { 4 } else { -4 };
 
@@ -1264,9 +1343,30 @@ pub fn store_block(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
         emu.get_cpu_mut(cpu_type).get_cpsr_mut().mode = PsrMode::User;
     }
 
+    let count = reg_list.count_ones();
+    let lowest = match (is_adding_offset, is_preindexing) {
+        (true, true) => address.wrapping_add(4),
+        (true, false) => address,
+        (false, true) => address.wrapping_sub(count * 4),
+        (false, false) => address.wrapping_sub(count * 4).wrapping_add(4),
+    };
+
     let mut regs = 0;
 
-    if is_adding_offset {
+    if emu.burst_write(lowest, cpu_type) {
+        // Registers are stored lowest first in both directions, so the whole
+        // list can go to the bus as one run
+        let mut words = [0u32; 16];
+        for i in (0..16).filter(|i| (reg_list & (1 << i)) != 0) {
+            words[regs as usize] = emu.get_cpu(cpu_type).get_register(Reg::new(i));
+            regs += 1;
+        }
+        emu.write_words(lowest, &words[..regs as usize], cpu_type);
+        address = match is_adding_offset {
+            true => address.wrapping_add(count * 4),
+            false => address.wrapping_sub(count * 4),
+        };
+    } else if is_adding_offset {
         // Incrementing: low → high
         for i in 0..16 {
             if (reg_list & (1 << i)) != 0 {
@@ -1274,10 +1374,10 @@ pub fn store_block(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
 
                 if is_preindexing {
                     address = address.wrapping_add(offset as u32);
-                    let value = emu.get_cpu(cpu_type).get_register(i);
+                    let value = emu.get_cpu(cpu_type).get_register(Reg::new(i));
                     emu.write_word(address, value, cpu_type);
                 } else {
-                    let value = emu.get_cpu(cpu_type).get_register(i);
+                    let value = emu.get_cpu(cpu_type).get_register(Reg::new(i));
                     emu.write_word(address, value, cpu_type);
                     address = address.wrapping_add(offset as u32);
                 }
@@ -1291,10 +1391,10 @@ pub fn store_block(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
 
                 if is_preindexing {
                     address = address.wrapping_add(offset as u32);
-                    let value = emu.get_cpu(cpu_type).get_register(i);
+                    let value = emu.get_cpu(cpu_type).get_register(Reg::new(i));
                     emu.write_word(address, value, cpu_type);
                 } else {
-                    let value = emu.get_cpu(cpu_type).get_register(i);
+                    let value = emu.get_cpu(cpu_type).get_register(Reg::new(i));
                     emu.write_word(address, value, cpu_type);
                     address = address.wrapping_add(offset as u32);
                 }
@@ -1315,12 +1415,12 @@ pub fn store_block(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
     emu.get_cpu_mut(cpu_type).add_n32_data(address, 2);
 
     if is_writing_back {
-        emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+        emu.get_cpu_mut(cpu_type).set_register(base, address);
     }
 }
 
 /// Branch instruction
-pub fn branch(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn branch<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let mut address = emu.get_cpu(cpu_type).get_pc();
 
     // 24-bit signed offset, shifted left by 2
@@ -1336,7 +1436,7 @@ pub fn branch(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
 }
 
 /// Branch with link instruction
-pub fn branch_link(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn branch_link<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let address = emu.get_cpu(cpu_type).get_pc();
 
     // 24-bit signed offset, shifted left by 2
@@ -1352,24 +1452,45 @@ pub fn branch_link(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
 
     // Link register gets address of next instruction minus 4
     emu.get_cpu_mut(cpu_type)
-        .set_register(REG_LR as i32, address.wrapping_sub(4));
+        .set_register(REG_LR, address.wrapping_sub(4));
 }
 
 /// Branch and exchange instruction
-pub fn branch_exchange(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
-    let reg_id = instruction & 0xF;
-    let new_address = emu.get_cpu(cpu_type).get_register(reg_id as i32);
+pub fn branch_exchange<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
+    let reg_id = Reg::new(instruction & 0xF);
+    let new_address = emu.get_cpu(cpu_type).get_register(reg_id);
 
     emu.get_cpu_mut(cpu_type).jp(new_address, true);
 }
 
+/// Coprocessor double register transfer (MCRR, MRRC)
+///
+/// CP15 does not take two-register transfers and there is no other
+/// coprocessor, so like on hardware they end in an undefined instruction
+/// exception.
+pub fn coprocessor_double_transfer<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
+    let _ = instruction;
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        "{} to p{} rejected",
+        if (instruction & (1 << 20)) != 0 {
+            "MRRC"
+        } else {
+            "MCRR"
+        },
+        (instruction >> 8) & 0xF
+    );
+
+    emu.undefined_instruction(cpu_type);
+}
+
 /// Coprocessor register transfer
-pub fn coprocessor_reg_transfer(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn coprocessor_reg_transfer<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let operation_mode = (instruction >> 21) & 0x7;
     let is_loading = (instruction & (1 << 20)) != 0;
 
     let cp_reg = (instruction >> 16) & 0xF;
-    let arm_reg = (instruction >> 12) & 0xF;
+    let arm_reg = Reg::new((instruction >> 12) & 0xF);
 
     let coprocessor_id = (instruction >> 8) & 0xF;
     let coprocessor_info = (instruction >> 5) & 0x7;
@@ -1382,14 +1503,15 @@ pub fn coprocessor_reg_transfer(emu: &mut Emulator, cpu_type: CpuType, instructi
 
         match coprocessor_id {
             15 => {
-                let value = emu.arm9_cp15.mrc(
-                    operation_mode as i32,
-                    cp_reg as i32,
-                    coprocessor_info as i32,
-                    coprocessor_operand as i32,
-                );
-                emu.get_cpu_mut(cpu_type)
-                    .set_register(arm_reg as i32, value);
+                if let Some(cp15) = emu.cp15_mut(cpu_type) {
+                    let value = cp15.mrc(
+                        operation_mode as i32,
+                        cp_reg as i32,
+                        coprocessor_info as i32,
+                        coprocessor_operand as i32,
+                    );
+                    emu.get_cpu_mut(cpu_type).set_register(arm_reg, value);
+                }
             }
             _ => {
                 // mirrors printf + exit(1)
@@ -1404,14 +1526,20 @@ pub fn coprocessor_reg_transfer(emu: &mut Emulator, cpu_type: CpuType, instructi
 
         match coprocessor_id {
             15 => {
-                let value = emu.get_cpu_mut(cpu_type).get_register(arm_reg as i32);
-                emu.arm9_cp15.mcr(
-                    operation_mode as i32,
-                    cp_reg as i32,
-                    value,
-                    coprocessor_info as i32,
-                    coprocessor_operand as i32,
-                );
+                let value = emu.get_cpu_mut(cpu_type).get_register(arm_reg);
+                if let Some(cp15) = emu.cp15_mut(cpu_type) {
+                    let wait_for_interrupt = cp15.mcr(
+                        operation_mode as i32,
+                        cp_reg as i32,
+                        value,
+                        coprocessor_info as i32,
+                        coprocessor_operand as i32,
+                    );
+                    emu.cp15_written(cpu_type);
+                    if wait_for_interrupt {
+                        emu.get_cpu_mut(cpu_type).halt();
+                    }
+                }
             }
             _ => {
                 // mirrors printf + exit(1)
@@ -1423,21 +1551,29 @@ pub fn coprocessor_reg_transfer(emu: &mut Emulator, cpu_type: CpuType, instructi
 }
 
 /// Branch with Link and Exchange (BLX, register)
-pub fn blx_reg(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn blx_reg<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     // Save return address
     let pc = emu.get_cpu(cpu_type).get_pc();
     emu.get_cpu_mut(cpu_type)
-        .set_register(REG_LR as i32, pc.wrapping_sub(4));
+        .set_register(REG_LR, pc.wrapping_sub(4));
 
-    let reg_id = instruction & 0xF;
-    let new_address = emu.get_cpu(cpu_type).get_register(reg_id as i32);
+    let reg_id = Reg::new(instruction & 0xF);
+    let new_address = emu.get_cpu(cpu_type).get_register(reg_id);
 
     // Branch and exchange
     emu.get_cpu_mut(cpu_type).jp(new_address, true);
 }
 
+/// Breakpoint (BKPT), ARMv5 only
+pub fn breakpoint<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
+    match cpu_type {
+        CpuType::Arm9 => emu.bkpt(cpu_type),
+        CpuType::Arm7 => undefined(emu, cpu_type, instruction),
+    }
+}
+
 /// Branch with Link and Exchange (BLX, immediate)
-pub fn blx(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn blx<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let address = emu.get_cpu(cpu_type).get_pc();
 
     // 24-bit signed offset, shifted left by 2
@@ -1454,7 +1590,7 @@ pub fn blx(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
 
     // Set link register
     emu.get_cpu_mut(cpu_type)
-        .set_register(REG_LR as i32, address.wrapping_sub(4));
+        .set_register(REG_LR, address.wrapping_sub(4));
 
     // Branch and exchange (force Thumb)
     let target = address.wrapping_add(offset as u32).wrapping_add(1);
@@ -1463,7 +1599,7 @@ pub fn blx(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
 }
 
 /// Software interrupt
-pub fn swi(emu: &mut Emulator, cpu_type: CpuType, instruction: u32) {
+pub fn swi<B: Bus>(emu: &mut B, cpu_type: CpuType, instruction: u32) {
     let _ = instruction;
-    emu.get_cpu_mut(cpu_type).handle_swi();
+    emu.swi(cpu_type);
 }
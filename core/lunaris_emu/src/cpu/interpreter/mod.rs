@@ -6,20 +6,72 @@ pub mod arm_instruction;
 pub mod thumb_instruction;
 
 use crate::cpu::arm_cpu::CpuType;
-use crate::emulator::Emulator;
+use crate::cpu::bus::Bus;
 
-use self::arm_instruction::blx;
-use super::arm_table;
+use self::arm_instruction::{blx, undefined};
+use self::thumb_instruction::thumb_interpret;
+use super::arm_table::{self, InterpreterFunc};
 use super::instruction_table::ARMInstr;
 
+/// Fetches the instruction at the PC of `cpu_type` and moves the PC one
+/// instruction ahead, returning the instruction's address
+pub fn fetch<B: Bus>(emu: &mut B, cpu_type: CpuType) -> u32 {
+    let thumb_on = emu.get_cpu(cpu_type).cpsr.thumb_on;
+    let pc = emu.get_cpu(cpu_type).get_pc();
+
+    let value = match thumb_on {
+        true => emu.read_halfword(pc.wrapping_sub(2), cpu_type) as u32,
+        false => emu.read_word(pc.wrapping_sub(4), cpu_type),
+    };
+    fetched(emu, cpu_type, value)
+}
+
+/// Moves the PC of `cpu_type` one instruction ahead like [`fetch`], with
+/// `value` as the instruction instead of reading it
+pub fn fetched<B: Bus>(emu: &mut B, cpu_type: CpuType, value: u32) -> u32 {
+    let arm = emu.get_cpu_mut(cpu_type);
+    let pc = arm.get_pc();
+    arm.current_instr = value;
+
+    if arm.cpsr.thumb_on {
+        let address = pc.wrapping_sub(2);
+        arm.add_s16_code(address, 1);
+        arm.regs[15] = pc.wrapping_add(2);
+        address
+    } else {
+        let address = pc.wrapping_sub(4);
+        arm.add_s32_code(address, 1);
+        arm.regs[15] = pc.wrapping_add(4);
+        address
+    }
+}
+
+/// Interprets the fetched instruction in the current state
+pub fn interpret<B: Bus>(emu: &mut B, cpu_type: CpuType) {
+    match emu.get_cpu(cpu_type).cpsr.thumb_on {
+        true => thumb_interpret(emu, cpu_type),
+        false => arm_interpret(emu, cpu_type),
+    }
+}
+
+/// Fetches and executes one instruction of `cpu_type`
+///
+/// Halting, DMA and interrupts are left to the caller, which can check
+/// [`ArmCpu::halted`](crate::cpu::arm_cpu::ArmCpu::halted) and call
+/// [`ArmCpu::handle_irq`](crate::cpu::arm_cpu::ArmCpu::handle_irq) between
+/// steps.
+pub fn step<B: Bus>(emu: &mut B, cpu_type: CpuType) {
+    fetch(emu, cpu_type);
+    interpret(emu, cpu_type);
+}
+
 /// Interprets an ARM instruction
-pub fn arm_interpret(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn arm_interpret<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u32 = emu.get_cpu_mut(cpu_type).get_current_instr();
-    let condition: u32 = (instruction & 0xF000_0000) >> 28;
 
     // In ARM, PC reads as current + 8
     let cpu_id = emu.get_cpu(cpu_type).get_id();
-    let test_mode = emu.config.test;
+    let test_mode = emu.trace_instructions();
 
     // Debug
     if cpu_id <= 0 && test_mode {
@@ -30,27 +82,47 @@ pub fn arm_interpret(emu: &mut Emulator, cpu_type: CpuType) {
             #[cfg(feature = "tracing")]
             tracing::trace!("(7A)");
         }
-        let pc: u32 = emu.get_cpu(cpu_type).get_pc().wrapping_sub(8);
-
         #[cfg(feature = "tracing")]
-        tracing::trace!("[{pc:08X}] {instruction:08X} - ");
+        {
+            let pc: u32 = emu.get_cpu(cpu_type).get_pc().wrapping_sub(8);
+            tracing::trace!("[{pc:08X}] {instruction:08X} - ");
 
-        let cpu = emu.get_cpu_mut(cpu_type);
-        let disasm = crate::cpu::disassemble::disasm_arm(cpu, instruction, pc);
-        #[cfg(feature = "tracing")]
-        tracing::trace!("disasm: {disasm}");
+            let disasm = crate::cpu::disassemble::disasm_arm(instruction, pc);
+            tracing::trace!("disasm: {disasm}");
+        }
     }
 
-    // Build opcode
-    let op: u32 = ((instruction >> 4) & 0xF) | ((instruction >> 16) & 0xFF0);
+    arm_execute(emu, cpu_type, instruction, arm_handler(instruction));
+}
+
+/// Handler of `instruction` in the ARM table
+pub fn arm_handler<B: Bus>(instruction: u32) -> InterpreterFunc<B> {
+    let op = ((instruction >> 4) & 0xF) | ((instruction >> 16) & 0xFF0);
+    let table = &arm_table::ArmTable::<B>::TABLE;
+    table[op as usize]
+}
+
+/// Executes ARM `instruction` with its [`arm_handler`] if its condition holds
+pub fn arm_execute<B: Bus>(
+    emu: &mut B,
+    cpu_type: CpuType,
+    instruction: u32,
+    handler: InterpreterFunc<B>,
+) {
+    let condition = instruction >> 28;
+    let cpu_id = emu.get_cpu(cpu_type).get_id();
 
-    // Special BLX handling
-    match condition == 15 && (instruction & 0xFE00_0000) == 0xFA00_0000 && cpu_id <= 0 {
-        true => blx(emu, cpu_type, instruction),
+    // ARMv5 unconditional space, the ARM7 never executes it
+    match condition == 15 && cpu_id <= 0 {
+        true => match arm_decode(instruction) {
+            ARMInstr::BranchLinkExchange => blx(emu, cpu_type, instruction),
+            // Cache hint, the caches are not emulated
+            ARMInstr::Preload => {}
+            _ => undefined(emu, cpu_type, instruction),
+        },
         false => {
             if emu.get_cpu_mut(cpu_type).check_condition(condition as i32) {
-                // Assuming arm_table is indexed by u32 and stores fn(&mut Cpu, u32)
-                arm_table::ARM_TABLE[op as usize](emu, cpu_type, instruction);
+                handler(emu, cpu_type, instruction);
             }
         }
     }
@@ -60,6 +132,11 @@ pub fn arm_interpret(emu: &mut Emulator, cpu_type: CpuType) {
 pub const fn arm_decode(instruction: u32) -> ARMInstr {
     // Here be dragons
 
+    // BLX immediate
+    if (instruction & 0xFE00_0000) == 0xFA00_0000 {
+        return ARMInstr::BranchLinkExchange;
+    }
+
     // Branch
     if ((instruction & 0x0F00_0000) >> 24) == 0xA {
         return ARMInstr::Branch;
@@ -81,6 +158,14 @@ pub const fn arm_decode(instruction: u32) -> ARMInstr {
     }
 
     // ARMv5TE-only instructions
+    if (instruction & 0xFD70_F000) == 0xF550_F000 {
+        return ARMInstr::Preload;
+    }
+
+    if (instruction & 0x0FF0_00F0) == 0x0120_0070 {
+        return ARMInstr::Breakpoint;
+    }
+
     if ((instruction >> 16) & 0xFFF) == 0x16F && ((instruction >> 4) & 0xFF) == 0xF1 {
         return ARMInstr::CountLeadingZeros;
     }
@@ -174,6 +259,10 @@ pub const fn arm_decode(instruction: u32) -> ARMInstr {
     }
 
     // Coprocessor
+    if (instruction & 0x0FE0_0000) == 0x0C40_0000 {
+        return ARMInstr::CopDoubleTransfer;
+    }
+
     if ((instruction >> 24) & 0xF) == 0xE {
         if (instruction & (1 << 4)) != 0 {
             return ARMInstr::CopRegTransfer;
@@ -189,3 +278,118 @@ pub const fn arm_decode(instruction: u32) -> ARMInstr {
 
     ARMInstr::Undefined
 }
+
+#[cfg(all(test, feature = "ds"))]
+mod tests {
+    use lunaris_ds_test_support::{AluFlags, arm_alu_reference};
+
+    use super::*;
+    use crate::cpu::arm_cpu::{PsrMode, REG_LR, Reg};
+    use crate::emulator::Emulator;
+
+    #[test]
+    fn test_armv5te_decode() {
+        assert_eq!(arm_decode(0xE120_0070), ARMInstr::Breakpoint);
+        assert_eq!(arm_decode(0xF5D0_F000), ARMInstr::Preload);
+        assert_eq!(arm_decode(0xFA00_0000), ARMInstr::BranchLinkExchange);
+        assert_eq!(arm_decode(0xEC41_0F00), ARMInstr::CopDoubleTransfer);
+        assert_eq!(arm_decode(0xEC51_0F00), ARMInstr::CopDoubleTransfer);
+    }
+
+    #[test]
+    fn test_bkpt() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.write_word(0x0200_1000, 0xF5D0_F000, CpuType::Arm9); // pld [r0]
+        emu.write_word(0x0200_1004, 0xE120_0070, CpuType::Arm9); // bkpt #0
+        emu.arm9.jp(0x0200_1000, false);
+
+        emu.execute(CpuType::Arm9);
+        assert_eq!(emu.arm9.get_pc(), 0x0200_1008);
+
+        emu.stop_on_bkpt = true;
+        emu.execute(CpuType::Arm9);
+        assert_eq!(emu.take_breakpoint_hit(), Some(CpuType::Arm9));
+        assert_ne!(emu.arm9.cpsr.mode, PsrMode::Abort);
+
+        emu.stop_on_bkpt = false;
+        emu.arm9.jp(0x0200_1004, false);
+        emu.execute(CpuType::Arm9);
+        assert_eq!(emu.arm9.cpsr.mode, PsrMode::Abort);
+        assert_eq!(emu.arm9.get_register(REG_LR), 0x0200_1008);
+    }
+
+    #[test]
+    fn test_thumb_halfword_imm_offset() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        // strh r0, [r1, #2]; ldrh r2, [r1, #4]
+        emu.write_halfword(0x0380_1000, 0x8048, CpuType::Arm7);
+        emu.write_halfword(0x0380_1002, 0x888A, CpuType::Arm7);
+        emu.write_halfword(0x0380_2004, 0xBEEF, CpuType::Arm7);
+        emu.arm7.set_register(Reg::new(0), 0x1234);
+        emu.arm7.set_register(Reg::new(1), 0x0380_2000);
+        emu.arm7.jp(0x0380_1001, true);
+
+        emu.execute(CpuType::Arm7);
+        assert_eq!(emu.read_halfword(0x0380_2002, CpuType::Arm7), 0x1234);
+        emu.execute(CpuType::Arm7);
+        assert_eq!(emu.arm7.get_register(Reg::new(2)), 0xBEEF);
+    }
+
+    #[test]
+    fn test_data_processing_flags() {
+        const EDGES: [u32; 6] = [0, 1, 0x7FFF_FFFF, 0x8000_0000, 0x8000_0001, 0xFFFF_FFFF];
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+
+        // xorshift32, fixed seed so failures reproduce
+        let mut seed = 0x2545_F491_u32;
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+        let mut pairs: Vec<(u32, u32)> = EDGES
+            .iter()
+            .flat_map(|&a| EDGES.iter().map(move |&b| (a, b)))
+            .collect();
+        pairs.extend((0..2000).map(|_| (random(), random())));
+
+        for opcode in 0..16 {
+            // <op>S r2, r0, r1
+            let instruction = 0xE010_2001 | opcode << 21;
+            for &(a, b) in &pairs {
+                for flags_in in 0..4 {
+                    let flags = AluFlags {
+                        carry: flags_in & 1 != 0,
+                        overflow: flags_in & 2 != 0,
+                        ..AluFlags::default()
+                    };
+                    let cpu = &mut emu.arm9;
+                    cpu.set_register(Reg::new(0), a);
+                    cpu.set_register(Reg::new(1), b);
+                    cpu.set_register(Reg::new(2), 0xDEAD_BEEF);
+                    cpu.cpsr.carry = flags.carry;
+                    cpu.cpsr.overflow = flags.overflow;
+                    arm_instruction::data_processing(&mut *emu, CpuType::Arm9, instruction);
+
+                    // LSL #0 passes C through the shifter
+                    let expected = arm_alu_reference(opcode, a, b, flags, flags.carry);
+                    let cpu = &emu.arm9;
+                    let actual = AluFlags {
+                        negative: cpu.cpsr.negative,
+                        zero: cpu.cpsr.zero,
+                        carry: cpu.cpsr.carry,
+                        overflow: cpu.cpsr.overflow,
+                    };
+                    let context = format!("opcode {opcode:X}, {a:#010X}, {b:#010X}, {flags:?}");
+                    assert_eq!(actual, expected.flags, "{context}");
+                    let value = expected.value.unwrap_or(0xDEAD_BEEF);
+                    assert_eq!(cpu.get_register(Reg::new(2)), value, "{context}");
+                }
+            }
+        }
+    }
+}
@@ -3,12 +3,12 @@
 //! instrthumb.cpp
 //!
 
-use crate::cpu::arm_cpu::{CpuType, REG_LR, REG_PC, REG_SP};
+use crate::cpu::arm_cpu::{CpuType, REG_LR, REG_PC, REG_SP, Reg};
+use crate::cpu::bus::Bus;
 use crate::cpu::instruction_table::ThumbInstr;
-use crate::emulator::Emulator;
 
 /// Interprets a Thumb instruction
-pub fn thumb_interpret(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_interpret<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     // NOTE: tracing output is gated behind the `tracing` feature
     let instruction = emu.get_cpu_mut(cpu_type).get_current_instr() & 0xFFFF;
     let cpu_id = emu.get_cpu(cpu_type).get_id();
@@ -30,8 +30,16 @@ pub fn thumb_interpret(emu: &mut Emulator, cpu_type: CpuType) {
     }
 
     // This value is only referenced by the Thumb decode.
-    let opcode = thumb_decode(instruction);
+    thumb_execute(emu, cpu_type, thumb_decode(instruction));
 
+    if cpu_id > 0 {
+        #[cfg(feature = "tracing")]
+        tracing::error!("");
+    }
+}
+
+/// Executes the fetched Thumb instruction, decoded to `opcode`
+pub fn thumb_execute<B: Bus>(emu: &mut B, cpu_type: CpuType, opcode: ThumbInstr) {
     match opcode {
         ThumbInstr::MovShift => thumb_mov_shift(emu, cpu_type),
         ThumbInstr::AddReg => thumb_add_reg(emu, cpu_type),
@@ -63,6 +71,7 @@ pub fn thumb_interpret(emu: &mut Emulator, cpu_type: CpuType) {
         ThumbInstr::LongBranchPrep => thumb_long_branch_prep(emu, cpu_type),
         ThumbInstr::LongBranch => thumb_long_branch(emu, cpu_type),
         ThumbInstr::LongBlx => thumb_long_blx(emu, cpu_type),
+        ThumbInstr::Breakpoint => thumb_breakpoint(emu, cpu_type),
 
         _ => {
             #[cfg(feature = "tracing")]
@@ -70,14 +79,8 @@ pub fn thumb_interpret(emu: &mut Emulator, cpu_type: CpuType) {
                 "Unrecognized Thumb opcode ${:04X}",
                 emu.get_cpu_mut(cpu_type).get_current_instr() & 0xFFFF
             );
-            return;
         }
     }
-
-    if cpu_id > 0 {
-        #[cfg(feature = "tracing")]
-        tracing::error!("");
-    }
 }
 
 /// Decodes a Thumb instruction into an enum or struct
@@ -134,6 +137,9 @@ pub fn thumb_decode(instruction: u32) -> ThumbInstr {
         }
         0xA => return ThumbInstr::LoadAddress,
         0xB => {
+            if (instruction & 0xFF00) == 0xBE00 {
+                return ThumbInstr::Breakpoint;
+            }
             if ((instruction >> 9) & 0x3) == 0x2 {
                 if (instruction & (1 << 11)) != 0 {
                     return ThumbInstr::Pop;
@@ -164,14 +170,14 @@ pub fn thumb_decode(instruction: u32) -> ThumbInstr {
 
 /// Thumb instruction: MOV with shift
 #[allow(clippy::missing_const_for_fn)]
-pub fn thumb_mov_shift(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_mov_shift<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
     let opcode = ((instruction >> 11) & 0x3) as u32;
     let mut shift = ((instruction >> 6) & 0x1F) as u32;
-    let source = ((instruction >> 3) & 0x7) as u32;
-    let destination = (instruction & 0x7) as u32;
+    let source = Reg::new(((instruction >> 3) & 0x7) as u32);
+    let destination = Reg::new((instruction & 0x7) as u32);
 
-    let mut value: u32 = emu.get_cpu_mut(cpu_type).get_register(source as i32);
+    let mut value: u32 = emu.get_cpu_mut(cpu_type).get_register(source);
 
     match opcode {
         0 => {
@@ -205,16 +211,15 @@ pub fn thumb_mov_shift(emu: &mut Emulator, cpu_type: CpuType) {
     }
 
     emu.get_cpu_mut(cpu_type).add_internal_cycles(1); // Extra cycle due to register shift
-    emu.get_cpu_mut(cpu_type)
-        .set_register(destination as i32, value);
+    emu.get_cpu_mut(cpu_type).set_register(destination, value);
     // Optionally: emu.get_cpu_mut(cpu_type).set_lo_register(destination, value);
 }
 
 /// Thumb instruction: ADD register
-pub fn thumb_add_reg(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_add_reg<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
-    let destination = (instruction & 0x7) as u32;
-    let source = ((instruction >> 3) & 0x7) as u32;
+    let destination = Reg::new((instruction & 0x7) as u32);
+    let source = Reg::new(((instruction >> 3) & 0x7) as u32);
     let mut operand = ((instruction >> 6) & 0x7) as u32;
     let is_imm = (instruction & (1 << 10)) != 0;
 
@@ -222,21 +227,21 @@ pub fn thumb_add_reg(emu: &mut Emulator, cpu_type: CpuType) {
         // if emu.get_cpu(cpu_type).get_id() > 0 {
         //     // println!("ADD {{{}}}, {{{}}}, {{{}}}", destination, source, operand);
         // }
-        operand = emu.get_cpu_mut(cpu_type).get_register(operand as i32);
+        operand = emu.get_cpu_mut(cpu_type).get_register(Reg::new(operand));
     }
     // else if emu.get_cpu(cpu_type).get_id() > 0 {
     //     // println!("ADD {{{}}}, {{{}}}, ${:08X}", destination, source, operand);
     // }
-    let src = emu.get_cpu_mut(cpu_type).get_register(source as i32);
+    let src = emu.get_cpu_mut(cpu_type).get_register(source);
     emu.get_cpu_mut(cpu_type)
         .add(destination, src, operand, true);
 }
 
 /// Thumb instruction: SUB register
-pub fn thumb_sub_reg(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_sub_reg<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
-    let destination = (instruction & 0x7) as u32;
-    let source = ((instruction >> 3) & 0x7) as u32;
+    let destination = Reg::new((instruction & 0x7) as u32);
+    let source = Reg::new(((instruction >> 3) & 0x7) as u32);
     let mut operand = ((instruction >> 6) & 0x7) as u32;
     let is_imm = (instruction & (1 << 10)) != 0;
 
@@ -244,21 +249,21 @@ pub fn thumb_sub_reg(emu: &mut Emulator, cpu_type: CpuType) {
         // if emu.get_cpu(cpu_type).get_id() > 0 {
         //     // println!("SUB {{{}}}, {{{}}}, {{{}}}", destination, source, operand);
         // }
-        operand = emu.get_cpu(cpu_type).get_register(operand as i32);
+        operand = emu.get_cpu(cpu_type).get_register(Reg::new(operand));
     } else if emu.get_cpu(cpu_type).get_id() > 0 {
         // println!("SUB {{{}}}, {{{}}}, ${:08X}", destination, source, operand);
     }
 
-    let src = emu.get_cpu(cpu_type).get_register(source as i32);
+    let src = emu.get_cpu(cpu_type).get_register(source);
     emu.get_cpu_mut(cpu_type)
         .sub(destination, src, operand, true);
 }
 
 /// Thumb instruction: MOV immediate
-pub fn thumb_mov(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_mov<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
     let offset = (instruction & 0xFF) as u32;
-    let reg = ((instruction >> 8) & 0x7) as u32;
+    let reg = Reg::new(((instruction >> 8) & 0x7) as u32);
 
     // if emu.get_cpu(cpu_type).get_id() > 0 {
     //     // println!("MOV {{{}}}, ${:02X}", reg, offset);
@@ -268,53 +273,53 @@ pub fn thumb_mov(emu: &mut Emulator, cpu_type: CpuType) {
 }
 
 /// Thumb instruction: CMP
-pub fn thumb_cmp(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_cmp<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
     let offset: u32 = (instruction & 0xFF) as u32;
-    let reg: u32 = ((instruction >> 8) & 0x7) as u32;
+    let reg = Reg::new(((instruction >> 8) & 0x7) as u32);
 
     // if emu.get_cpu(cpu_type).get_id() > 0 {
     //     // println!("CMP {{{}}}, ${:02X}", reg, offset);
     // }
 
-    let x = emu.get_cpu(cpu_type).get_register(reg as i32);
+    let x = emu.get_cpu(cpu_type).get_register(reg);
     emu.get_cpu_mut(cpu_type).cmp(x, offset);
 }
 
 /// Thumb instruction: ADD
-pub fn thumb_add(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_add<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
     let offset: u32 = (instruction & 0xFF) as u32;
-    let reg: u32 = ((instruction >> 8) & 0x7) as u32;
+    let reg = Reg::new(((instruction >> 8) & 0x7) as u32);
 
     // if emu.get_cpu(cpu_type).get_id() > 0 {
     //     // println!("ADD {{{}}}, ${:02X}", reg, offset);
     // }
 
-    let src = emu.get_cpu_mut(cpu_type).get_register(reg as i32);
+    let src = emu.get_cpu_mut(cpu_type).get_register(reg);
     emu.get_cpu_mut(cpu_type).add(reg, src, offset, true);
 }
 
 /// Thumb instruction: SUB
-pub fn thumb_sub(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_sub<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
     let offset: u32 = (instruction & 0xFF) as u32;
-    let reg: u32 = ((instruction >> 8) & 0x7) as u32;
+    let reg = Reg::new(((instruction >> 8) & 0x7) as u32);
 
     // if emu.get_cpu(cpu_type).get_id() > 0 {
     //     // println!("SUB {{{}}}, ${:02X}", reg, offset);
     // }
-    let src = emu.get_cpu_mut(cpu_type).get_register(reg as i32);
+    let src = emu.get_cpu_mut(cpu_type).get_register(reg);
     emu.get_cpu_mut(cpu_type).sub(reg, src, offset, true);
 }
 
 /// Thumb instruction: ALU operations
 #[allow(clippy::missing_const_for_fn)]
-pub fn thumb_alu_op(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_alu_op<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
 
-    let destination = (instruction & 0x7) as u32;
-    let source = ((instruction >> 3) & 0x7) as u32;
+    let destination = Reg::new((instruction & 0x7) as u32);
+    let source = Reg::new(((instruction >> 3) & 0x7) as u32);
     let opcode = ((instruction >> 6) & 0xF) as u32;
 
     match opcode {
@@ -323,61 +328,58 @@ pub fn thumb_alu_op(emu: &mut Emulator, cpu_type: CpuType) {
             // if emu.get_cpu(cpu_type).get_id() > 0 {
             //     // println!("AND {{{}}}, {{{}}}", destination, source);
             // }
-            let src = emu.get_cpu_mut(cpu_type).get_register(destination as i32) as i32;
-            let operand = emu.get_cpu_mut(cpu_type).get_register(source as i32) as i32;
+            let src = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let operand = emu.get_cpu_mut(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type)
-                .andd(destination as i32, src, operand, true);
+                .andd(destination, src, operand, true);
         }
         0x1 => {
             // EOR
             // if emu.get_cpu(cpu_type).get_id() > 0 {
             //     // println!("EOR {{{}}}, {{{}}}", destination, source);
             // }
-            let src = emu.get_cpu_mut(cpu_type).get_register(destination as i32) as i32;
-            let operand = emu.get_cpu_mut(cpu_type).get_register(source as i32) as i32;
+            let src = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let operand = emu.get_cpu_mut(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type)
-                .eor(destination as i32, src, operand, true);
+                .eor(destination, src, operand, true);
         }
         0x2 => {
             // LSL
             // if emu.get_cpu(cpu_type).get_id() > 0 {
             //     // println!("LSL {{{}}}, {{{}}}", destination, source);
             // }
-            let mut reg = emu.get_cpu_mut(cpu_type).get_register(destination as i32);
-            let shift = emu.get_cpu_mut(cpu_type).get_register(source as i32) as i32;
+            let mut reg = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let shift = (emu.get_cpu_mut(cpu_type).get_register(source) & 0xFF) as i32;
             reg = emu.get_cpu_mut(cpu_type).lsl(reg, shift, true);
-            emu.get_cpu_mut(cpu_type)
-                .set_register(destination as i32, reg);
+            emu.get_cpu_mut(cpu_type).set_register(destination, reg);
         }
         0x3 => {
             // LSR
             // if emu.get_cpu(cpu_type).get_id() > 0 {
             //     // println!("LSR {{{}}}, {{{}}}", destination, source);
             // }
-            let mut reg = emu.get_cpu_mut(cpu_type).get_register(destination as i32);
-            let shift = emu.get_cpu_mut(cpu_type).get_register(source as i32) as i32;
+            let mut reg = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let shift = (emu.get_cpu_mut(cpu_type).get_register(source) & 0xFF) as i32;
             reg = emu.get_cpu_mut(cpu_type).lsr(reg, shift, true);
-            emu.get_cpu_mut(cpu_type)
-                .set_register(destination as i32, reg);
+            emu.get_cpu_mut(cpu_type).set_register(destination, reg);
         }
         0x4 => {
             // ASR
             // if emu.get_cpu(cpu_type).get_id() > 0 {
             //     // println!("ASR {{{}}}, {{{}}}", destination, source);
             // }
-            let mut reg = emu.get_cpu_mut(cpu_type).get_register(destination as i32);
-            let shift = emu.get_cpu_mut(cpu_type).get_register(source as i32) as i32;
+            let mut reg = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let shift = (emu.get_cpu_mut(cpu_type).get_register(source) & 0xFF) as i32;
             reg = emu.get_cpu_mut(cpu_type).asr(reg, shift, true);
-            emu.get_cpu_mut(cpu_type)
-                .set_register(destination as i32, reg);
+            emu.get_cpu_mut(cpu_type).set_register(destination, reg);
         }
         0x5 => {
             // ADC
             // if emu.get_cpu(cpu_type).get_id() > 0 {
             //     // println!("ADC {{{}}}, {{{}}}", destination, source);
             // }
-            let src = emu.get_cpu_mut(cpu_type).get_register(destination as i32);
-            let operand = emu.get_cpu_mut(cpu_type).get_register(source as i32);
+            let src = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let operand = emu.get_cpu_mut(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type)
                 .adc(destination, src, operand, true);
         }
@@ -386,8 +388,8 @@ pub fn thumb_alu_op(emu: &mut Emulator, cpu_type: CpuType) {
             // if emu.get_cpu(cpu_type).get_id() > 0 {
             //     // println!("SBC {{{}}}, {{{}}}", destination, source);
             // }
-            let src = emu.get_cpu_mut(cpu_type).get_register(destination as i32);
-            let operand = emu.get_cpu_mut(cpu_type).get_register(source as i32);
+            let src = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let operand = emu.get_cpu_mut(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type)
                 .sbc(destination, src, operand, true);
         }
@@ -396,19 +398,18 @@ pub fn thumb_alu_op(emu: &mut Emulator, cpu_type: CpuType) {
             if emu.get_cpu(cpu_type).get_id() > 0 {
                 // println!("ROR {{{}}}, {{{}}}", destination, source);
             }
-            let mut reg = emu.get_cpu(cpu_type).get_register(destination as i32);
-            let c = emu.get_cpu(cpu_type).get_register(source as i32);
+            let mut reg = emu.get_cpu(cpu_type).get_register(destination);
+            let c = emu.get_cpu(cpu_type).get_register(source) & 0xFF;
             reg = emu.get_cpu_mut(cpu_type).rotr32(reg, c, true);
-            emu.get_cpu_mut(cpu_type)
-                .set_register(destination as i32, reg);
+            emu.get_cpu_mut(cpu_type).set_register(destination, reg);
         }
         0x8 => {
             // TST
             // if emu.get_cpu(cpu_type).get_id() > 0 {
             //     // println!("TST {{{}}}, {{{}}}", destination, source);
             // }
-            let x = emu.get_cpu_mut(cpu_type).get_register(destination as i32);
-            let y = emu.get_cpu_mut(cpu_type).get_register(source as i32);
+            let x = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let y = emu.get_cpu_mut(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type).tst(x, y);
         }
         0x9 => {
@@ -417,7 +418,7 @@ pub fn thumb_alu_op(emu: &mut Emulator, cpu_type: CpuType) {
             //     // println!("NEG {{{}}}, {{{}}}", destination, source);
             // }
 
-            let operand = emu.get_cpu_mut(cpu_type).get_register(source as i32);
+            let operand = emu.get_cpu_mut(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type).sub(destination, 0, operand, true);
         }
         0xA => {
@@ -425,8 +426,8 @@ pub fn thumb_alu_op(emu: &mut Emulator, cpu_type: CpuType) {
             // if emu.get_cpu(cpu_type).get_id() > 0 {
             //     // println!("CMP {{{}}}, {{{}}}", destination, source);
             // }
-            let x = emu.get_cpu_mut(cpu_type).get_register(destination as i32);
-            let y = emu.get_cpu_mut(cpu_type).get_register(source as i32);
+            let x = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let y = emu.get_cpu_mut(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type).cmp(x, y);
         }
         0xB => {
@@ -434,8 +435,8 @@ pub fn thumb_alu_op(emu: &mut Emulator, cpu_type: CpuType) {
             if emu.get_cpu(cpu_type).get_id() > 0 {
                 // println!("CMN {{{}}}, {{{}}}", destination, source);
             }
-            let x = emu.get_cpu_mut(cpu_type).get_register(destination as i32);
-            let y = emu.get_cpu_mut(cpu_type).get_register(source as i32);
+            let x = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let y = emu.get_cpu_mut(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type).cmn(x, y);
         }
         0xC => {
@@ -443,24 +444,23 @@ pub fn thumb_alu_op(emu: &mut Emulator, cpu_type: CpuType) {
             if emu.get_cpu(cpu_type).get_id() > 0 {
                 // println!("ORR {{{}}}, {{{}}}", destination, source);
             }
-            let reg = emu.get_cpu_mut(cpu_type).get_register(destination as i32) as i32;
-            let src = emu.get_cpu_mut(cpu_type).get_register(source as i32) as i32;
-            emu.get_cpu_mut(cpu_type)
-                .orr(destination as i32, reg, src, true);
+            let reg = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let src = emu.get_cpu_mut(cpu_type).get_register(source);
+            emu.get_cpu_mut(cpu_type).orr(destination, reg, src, true);
         }
         0xD => {
             // MUL
             // if emu.get_cpu(cpu_type).get_id() > 0 {
             //     // println!("MUL {{{}}}, {{{}}}", destination, source);
             // }
-            let src = emu.get_cpu_mut(cpu_type).get_register(destination as i32);
-            let operand = emu.get_cpu_mut(cpu_type).get_register(source as i32);
+            let src = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let operand = emu.get_cpu_mut(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type)
                 .mul(destination, src, operand, true);
             if !emu.get_cpu(cpu_type).get_id() > 0 {
                 emu.get_cpu_mut(cpu_type).add_internal_cycles(3);
             } else {
-                let multiplicand = emu.get_cpu_mut(cpu_type).get_register(destination as i32);
+                let multiplicand = emu.get_cpu_mut(cpu_type).get_register(destination);
                 if multiplicand & 0xFF000000 != 0 {
                     emu.get_cpu_mut(cpu_type).add_internal_cycles(4);
                 } else if multiplicand & 0x00FF0000 != 0 {
@@ -477,8 +477,8 @@ pub fn thumb_alu_op(emu: &mut Emulator, cpu_type: CpuType) {
             if emu.get_cpu(cpu_type).get_id() > 0 {
                 // println!("BIC {{{}}}, {{{}}}", destination, source);
             }
-            let src = emu.get_cpu_mut(cpu_type).get_register(destination as i32);
-            let operand = emu.get_cpu_mut(cpu_type).get_register(source as i32);
+            let src = emu.get_cpu_mut(cpu_type).get_register(destination);
+            let operand = emu.get_cpu_mut(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type)
                 .bic(destination, src, operand, true);
         }
@@ -488,7 +488,7 @@ pub fn thumb_alu_op(emu: &mut Emulator, cpu_type: CpuType) {
             //     // println!("MVN {{{}}}, {{{}}}", destination, source);
             // }
 
-            let operand = emu.get_cpu(cpu_type).get_register(source as i32);
+            let operand = emu.get_cpu(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type).mvn(destination, operand, true);
         }
         _ => {
@@ -500,22 +500,15 @@ pub fn thumb_alu_op(emu: &mut Emulator, cpu_type: CpuType) {
 
 /// Thumb instruction: High register operations
 #[allow(clippy::missing_const_for_fn)]
-pub fn thumb_hi_reg_op(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_hi_reg_op<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
 
     let opcode: u32 = ((instruction >> 8) & 0x3) as u32;
     let high_source: bool = (instruction & (1 << 6)) != 0;
     let high_dest: bool = (instruction & (1 << 7)) != 0;
 
-    let mut source: u32 = ((instruction >> 3) & 0x7) as u32;
-    if high_source {
-        source |= 8;
-    }
-
-    let mut destination: u32 = (instruction & 0x7) as u32;
-    if high_dest {
-        destination |= 8;
-    }
+    let source = Reg::new(((instruction >> 3) & 0x7) as u32 | (high_source as u32) << 3);
+    let destination = Reg::new((instruction & 0x7) as u32 | (high_dest as u32) << 3);
 
     match opcode {
         0x0 => {
@@ -524,12 +517,12 @@ pub fn thumb_hi_reg_op(emu: &mut Emulator, cpu_type: CpuType) {
             //     // println!("ADD {{{}}}, {{{}}}", destination, source);
             // }
             if destination == REG_PC {
-                let reg = emu.get_cpu(cpu_type).get_register(source as i32);
+                let reg = emu.get_cpu(cpu_type).get_register(source);
                 let new_addr = emu.get_cpu(cpu_type).get_pc().wrapping_add(reg);
                 emu.get_cpu_mut(cpu_type).jp(new_addr, false);
             } else {
-                let src = emu.get_cpu(cpu_type).get_register(destination as i32);
-                let operand = emu.get_cpu(cpu_type).get_register(source as i32);
+                let src = emu.get_cpu(cpu_type).get_register(destination);
+                let operand = emu.get_cpu(cpu_type).get_register(source);
                 emu.get_cpu_mut(cpu_type)
                     .add(destination, src, operand, false);
             }
@@ -539,8 +532,8 @@ pub fn thumb_hi_reg_op(emu: &mut Emulator, cpu_type: CpuType) {
             // if emu.get_cpu(cpu_type).get_id() > 0 {
             //     // println!("CMP {{{}}}, {{{}}}", destination, source);
             // }
-            let x = emu.get_cpu(cpu_type).get_register(destination as i32);
-            let y = emu.get_cpu(cpu_type).get_register(source as i32);
+            let x = emu.get_cpu(cpu_type).get_register(destination);
+            let y = emu.get_cpu(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type).cmp(x, y);
         }
         0x2 => {
@@ -549,10 +542,10 @@ pub fn thumb_hi_reg_op(emu: &mut Emulator, cpu_type: CpuType) {
             //     // println!("MOV {{{}}}, {{{}}}", destination, source);
             // }
             if destination == REG_PC {
-                let new_addr = emu.get_cpu(cpu_type).get_register(source as i32);
+                let new_addr = emu.get_cpu(cpu_type).get_register(source);
                 emu.get_cpu_mut(cpu_type).jp(new_addr, false);
             } else {
-                let operand = emu.get_cpu(cpu_type).get_register(source as i32);
+                let operand = emu.get_cpu(cpu_type).get_register(source);
                 emu.get_cpu_mut(cpu_type).mov(destination, operand, false);
             }
         }
@@ -563,12 +556,12 @@ pub fn thumb_hi_reg_op(emu: &mut Emulator, cpu_type: CpuType) {
                 //     // println!("BLX {{{}}}", source);
                 // }
                 let value = emu.get_cpu(cpu_type).get_pc().wrapping_sub(1);
-                emu.get_cpu_mut(cpu_type).set_register(REG_LR as i32, value);
+                emu.get_cpu_mut(cpu_type).set_register(REG_LR, value);
             } else if emu.get_cpu(cpu_type).get_id() > 0 {
                 #[cfg(feature = "tracing")]
                 tracing::error!("BX {{{source}}}");
             }
-            let new_addr = emu.get_cpu(cpu_type).get_register(source as i32);
+            let new_addr = emu.get_cpu(cpu_type).get_register(source);
             emu.get_cpu_mut(cpu_type).jp(new_addr, true);
         }
         _ => {
@@ -579,10 +572,10 @@ pub fn thumb_hi_reg_op(emu: &mut Emulator, cpu_type: CpuType) {
 }
 
 /// Thumb instruction: PC-relative load
-pub fn thumb_pc_rel_load(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_pc_rel_load<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
 
-    let destination: u32 = ((instruction >> 8) & 0x7) as u32;
+    let destination = Reg::new(((instruction >> 8) & 0x7) as u32);
     let mut address: u32 = emu.get_cpu(cpu_type).get_pc();
     address = address.wrapping_add(((instruction & 0xFF) as u32) << 2);
     address &= !0x3; // 4-byte alignment
@@ -594,23 +587,22 @@ pub fn thumb_pc_rel_load(emu: &mut Emulator, cpu_type: CpuType) {
     emu.get_cpu_mut(cpu_type).add_n32_data(address, 1);
     emu.get_cpu_mut(cpu_type).add_internal_cycles(1);
     let value = emu.read_word(address, cpu_type);
-    emu.get_cpu_mut(cpu_type)
-        .set_register(destination as i32, value);
+    emu.get_cpu_mut(cpu_type).set_register(destination, value);
 }
 
 /// Thumb instruction: Store register with offset
-pub fn thumb_store_reg_offset(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_store_reg_offset<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
 
     let is_byte: bool = (instruction & (1 << 10)) != 0;
-    let base: u32 = ((instruction >> 3) & 0x7) as u32;
-    let source: u32 = (instruction & 0x7) as u32;
-    let offset: u32 = ((instruction >> 6) & 0x7) as u32;
+    let base = Reg::new(((instruction >> 3) & 0x7) as u32);
+    let source = Reg::new((instruction & 0x7) as u32);
+    let offset = Reg::new(((instruction >> 6) & 0x7) as u32);
 
-    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base as i32);
-    address = address.wrapping_add(emu.get_cpu(cpu_type).get_register(offset as i32));
+    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base);
+    address = address.wrapping_add(emu.get_cpu(cpu_type).get_register(offset));
 
-    let source_contents: u32 = emu.get_cpu(cpu_type).get_register(source as i32);
+    let source_contents: u32 = emu.get_cpu(cpu_type).get_register(source);
 
     if is_byte {
         // if emu.get_cpu(cpu_type).get_id() > 0 {
@@ -628,16 +620,16 @@ pub fn thumb_store_reg_offset(emu: &mut Emulator, cpu_type: CpuType) {
 }
 
 /// Thumb instruction: Load register with offset
-pub fn thumb_load_reg_offset(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_load_reg_offset<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
 
     let is_byte: bool = (instruction & (1 << 10)) != 0;
-    let base = ((instruction >> 3) & 0x7) as u32;
-    let destination = (instruction & 0x7) as u32;
-    let offset = ((instruction >> 6) & 0x7) as u32;
+    let base = Reg::new(((instruction >> 3) & 0x7) as u32);
+    let destination = Reg::new((instruction & 0x7) as u32);
+    let offset = Reg::new(((instruction >> 6) & 0x7) as u32);
 
-    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base as i32);
-    address = address.wrapping_add(emu.get_cpu(cpu_type).get_register(offset as i32));
+    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base);
+    address = address.wrapping_add(emu.get_cpu(cpu_type).get_register(offset));
 
     if is_byte {
         // if emu.get_cpu(cpu_type).get_id() > 0 {
@@ -647,7 +639,7 @@ pub fn thumb_load_reg_offset(emu: &mut Emulator, cpu_type: CpuType) {
         emu.get_cpu_mut(cpu_type).add_internal_cycles(1);
         let value = emu.read_byte(address, cpu_type);
         emu.get_cpu_mut(cpu_type)
-            .set_register(destination as i32, value as u32);
+            .set_register(destination, value as u32);
     } else {
         if emu.get_cpu(cpu_type).get_id() > 0 {
             // println!("LDR {{{}}}, [{{{}}}, {{{}}}]", destination, base, offset);
@@ -658,24 +650,23 @@ pub fn thumb_load_reg_offset(emu: &mut Emulator, cpu_type: CpuType) {
         let rotate = (address & 0x3) * 8;
         let addr = emu.read_word(aligned_addr, cpu_type);
         let word = emu.get_cpu_mut(cpu_type).rotr32(addr, rotate, false);
-        emu.get_cpu_mut(cpu_type)
-            .set_register(destination as i32, word);
+        emu.get_cpu_mut(cpu_type).set_register(destination, word);
     }
 }
 
-/// Thumb instruction: Load halfword
-pub fn thumb_load_halfword(emu: &mut Emulator, cpu_type: CpuType) {
+/// Thumb instruction: Store halfword
+pub fn thumb_store_halfword<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
 
     let offset: u32 = (((instruction >> 6) & 0x1F) << 1) as u32;
-    let base: u32 = ((instruction >> 3) & 0x7) as u32;
-    let source: u32 = (instruction & 0x7) as u32;
+    let base = Reg::new(((instruction >> 3) & 0x7) as u32);
+    let source = Reg::new((instruction & 0x7) as u32);
 
     let address: u32 = emu
         .get_cpu_mut(cpu_type)
-        .get_register(base as i32)
+        .get_register(base)
         .wrapping_add(offset);
-    let value = (emu.get_cpu(cpu_type).get_register(source as i32) & 0xFFFF) as u16;
+    let value = (emu.get_cpu(cpu_type).get_register(source) & 0xFFFF) as u16;
 
     if emu.get_cpu(cpu_type).get_id() > 0 {
         // println!("STRH {{{}}}, [{{{}}}, ${:04X}]", source, base, offset);
@@ -685,17 +676,17 @@ pub fn thumb_load_halfword(emu: &mut Emulator, cpu_type: CpuType) {
     emu.write_halfword(address, value, cpu_type);
 }
 
-/// Thumb instruction: Store halfword
-pub fn thumb_store_halfword(emu: &mut Emulator, cpu_type: CpuType) {
+/// Thumb instruction: Load halfword
+pub fn thumb_load_halfword<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
 
     let offset: u32 = (((instruction >> 6) & 0x1F) << 1) as u32;
-    let base = ((instruction >> 3) & 0x7) as u32;
-    let destination = (instruction & 0x7) as u32;
+    let base = Reg::new(((instruction >> 3) & 0x7) as u32);
+    let destination = Reg::new((instruction & 0x7) as u32);
 
     let address: u32 = emu
         .get_cpu(cpu_type)
-        .get_register(base as i32)
+        .get_register(base)
         .wrapping_add(offset);
 
     if emu.get_cpu(cpu_type).get_id() > 0 {
@@ -705,19 +696,19 @@ pub fn thumb_store_halfword(emu: &mut Emulator, cpu_type: CpuType) {
     emu.get_cpu_mut(cpu_type).add_n16_data(address, 1);
     let value = emu.read_halfword(address, cpu_type);
     emu.get_cpu_mut(cpu_type)
-        .set_register(destination as i32, value as u32);
+        .set_register(destination, value as u32);
 }
 
 /// Thumb instruction: Store with immediate offset
-pub fn thumb_store_imm_offset(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_store_imm_offset<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
 
-    let source: u32 = (instruction & 0x7) as u32;
-    let base: u32 = ((instruction >> 3) & 0x7) as u32;
+    let source = Reg::new((instruction & 0x7) as u32);
+    let base = Reg::new(((instruction >> 3) & 0x7) as u32);
     let mut offset: u32 = ((instruction >> 6) & 0x1F) as u32;
     let is_byte: bool = (instruction & (1 << 12)) != 0;
 
-    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base as i32);
+    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base);
 
     if is_byte {
         address = address.wrapping_add(offset);
@@ -727,7 +718,7 @@ pub fn thumb_store_imm_offset(emu: &mut Emulator, cpu_type: CpuType) {
         }
 
         emu.get_cpu_mut(cpu_type).add_n16_data(address, 1);
-        let value = (emu.get_cpu_mut(cpu_type).get_register(source as i32) & 0xFF) as u8;
+        let value = (emu.get_cpu_mut(cpu_type).get_register(source) & 0xFF) as u8;
         emu.write_byte(address, value, cpu_type);
     } else {
         offset <<= 2;
@@ -738,21 +729,21 @@ pub fn thumb_store_imm_offset(emu: &mut Emulator, cpu_type: CpuType) {
         }
 
         emu.get_cpu_mut(cpu_type).add_n32_data(address, 1);
-        let value = emu.get_cpu_mut(cpu_type).get_register(source as i32);
+        let value = emu.get_cpu_mut(cpu_type).get_register(source);
         emu.write_word(address, value, cpu_type);
     }
 }
 
 /// Thumb instruction: Load with immediate offset
-pub fn thumb_load_imm_offset(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_load_imm_offset<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
 
-    let destination: u32 = (instruction & 0x7) as u32;
-    let base: u32 = ((instruction >> 3) & 0x7) as u32;
+    let destination = Reg::new((instruction & 0x7) as u32);
+    let base = Reg::new(((instruction >> 3) & 0x7) as u32);
     let mut offset: u32 = ((instruction >> 6) & 0x1F) as u32;
     let is_byte: bool = (instruction & (1 << 12)) != 0;
 
-    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base as i32);
+    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base);
 
     if is_byte {
         address = address.wrapping_add(offset);
@@ -765,7 +756,7 @@ pub fn thumb_load_imm_offset(emu: &mut Emulator, cpu_type: CpuType) {
         emu.get_cpu_mut(cpu_type).add_n16_data(address, 1);
         let value = emu.read_byte(address, cpu_type);
         emu.get_cpu_mut(cpu_type)
-            .set_register(destination as i32, value as u32);
+            .set_register(destination, value as u32);
     } else {
         offset <<= 2;
         address = address.wrapping_add(offset);
@@ -781,24 +772,23 @@ pub fn thumb_load_imm_offset(emu: &mut Emulator, cpu_type: CpuType) {
         let addr = emu.read_word(aligned_addr, cpu_type);
         let word = emu.get_cpu_mut(cpu_type).rotr32(addr, rotate, false);
 
-        emu.get_cpu_mut(cpu_type)
-            .set_register(destination as i32, word);
+        emu.get_cpu_mut(cpu_type).set_register(destination, word);
     }
 
     emu.get_cpu_mut(cpu_type).add_internal_cycles(1);
 }
 
 /// Thumb instruction: Load/store signed halfword
-pub fn thumb_load_store_sign_halfword(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_load_store_sign_halfword<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
 
-    let destination: u32 = (instruction & 0x7) as u32;
-    let base: u32 = ((instruction >> 3) & 0x7) as u32;
-    let offset: u32 = ((instruction >> 6) & 0x7) as u32;
+    let destination = Reg::new((instruction & 0x7) as u32);
+    let base = Reg::new(((instruction >> 3) & 0x7) as u32);
+    let offset = Reg::new(((instruction >> 6) & 0x7) as u32);
     let opcode: u32 = ((instruction >> 10) & 0x3) as u32;
 
-    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base as i32);
-    address = address.wrapping_add(emu.get_cpu(cpu_type).get_register(offset as i32));
+    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base);
+    address = address.wrapping_add(emu.get_cpu(cpu_type).get_register(offset));
 
     match opcode {
         0 => {
@@ -806,7 +796,7 @@ pub fn thumb_load_store_sign_halfword(emu: &mut Emulator, cpu_type: CpuType) {
             if emu.get_cpu(cpu_type).get_id() > 0 {
                 // println!("STRH {{{}}}, [{{{}}}, {{{}}}]", destination, base, offset);
             }
-            let value = (emu.get_cpu(cpu_type).get_register(destination as i32) & 0xFFFF) as u16;
+            let value = (emu.get_cpu(cpu_type).get_register(destination) & 0xFFFF) as u16;
             emu.write_halfword(address, value, cpu_type);
             emu.get_cpu_mut(cpu_type).add_n32_data(address, 1);
         }
@@ -819,7 +809,7 @@ pub fn thumb_load_store_sign_halfword(emu: &mut Emulator, cpu_type: CpuType) {
             let mut extended_byte: u32 = emu.read_byte(address, cpu_type).into();
             extended_byte = (extended_byte as i8 as i32) as u32;
             emu.get_cpu_mut(cpu_type)
-                .set_register(destination as i32, extended_byte);
+                .set_register(destination, extended_byte);
             emu.get_cpu_mut(cpu_type).add_internal_cycles(1);
             emu.get_cpu_mut(cpu_type).add_n16_data(address, 1);
         }
@@ -831,7 +821,7 @@ pub fn thumb_load_store_sign_halfword(emu: &mut Emulator, cpu_type: CpuType) {
             }
             let value = emu.read_halfword(address, cpu_type);
             emu.get_cpu_mut(cpu_type)
-                .set_register(destination as i32, value as u32);
+                .set_register(destination, value as u32);
             emu.get_cpu_mut(cpu_type).add_n16_data(address, 1);
             emu.get_cpu_mut(cpu_type).add_internal_cycles(1);
         }
@@ -844,7 +834,7 @@ pub fn thumb_load_store_sign_halfword(emu: &mut Emulator, cpu_type: CpuType) {
             let mut extended_halfword: u32 = emu.read_halfword(address, cpu_type).into();
             extended_halfword = (extended_halfword as i16 as i32) as u32;
             emu.get_cpu_mut(cpu_type)
-                .set_register(destination as i32, extended_halfword);
+                .set_register(destination, extended_halfword);
             emu.get_cpu_mut(cpu_type).add_internal_cycles(1);
             emu.get_cpu_mut(cpu_type).add_n16_data(address, 1);
         }
@@ -857,13 +847,13 @@ pub fn thumb_load_store_sign_halfword(emu: &mut Emulator, cpu_type: CpuType) {
 }
 
 /// Thumb instruction: Stack pointer-relative store
-pub fn thumb_sp_rel_store(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_sp_rel_store<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
 
-    let source: u32 = ((instruction >> 8) & 0x7) as u32;
+    let source = Reg::new(((instruction >> 8) & 0x7) as u32);
     let offset: u32 = ((instruction & 0x00FF) as u32) << 2;
 
-    let mut address: u32 = emu.get_cpu(cpu_type).get_register(REG_SP as i32);
+    let mut address: u32 = emu.get_cpu(cpu_type).get_register(REG_SP);
     address = address.wrapping_add(offset);
 
     // if emu.get_cpu_mut(cpu_type).can_disassemble() {
@@ -871,18 +861,18 @@ pub fn thumb_sp_rel_store(emu: &mut Emulator, cpu_type: CpuType) {
     // }
 
     emu.get_cpu_mut(cpu_type).add_n32_data(address, 1);
-    let value = emu.get_cpu(cpu_type).get_register(source as i32);
+    let value = emu.get_cpu(cpu_type).get_register(source);
     emu.write_word(address, value, cpu_type);
 }
 
 /// Thumb instruction: Stack pointer-relative load
-pub fn thumb_sp_rel_load(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_sp_rel_load<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
 
-    let destination: u32 = ((instruction >> 8) & 0x7) as u32;
+    let destination = Reg::new(((instruction >> 8) & 0x7) as u32);
     let offset: u32 = ((instruction & 0x00FF) as u32) << 2;
 
-    let mut address: u32 = emu.get_cpu(cpu_type).get_register(REG_SP as i32);
+    let mut address: u32 = emu.get_cpu(cpu_type).get_register(REG_SP);
     address = address.wrapping_add(offset);
 
     // if emu.get_cpu_mut(cpu_type).can_disassemble() {
@@ -893,12 +883,11 @@ pub fn thumb_sp_rel_load(emu: &mut Emulator, cpu_type: CpuType) {
     emu.get_cpu_mut(cpu_type).add_internal_cycles(1);
 
     let value = emu.read_word(address, cpu_type);
-    emu.get_cpu_mut(cpu_type)
-        .set_register(destination as i32, value);
+    emu.get_cpu_mut(cpu_type).set_register(destination, value);
 }
 
 /// Thumb instruction: Offset SP operation
-pub fn thumb_offset_sp(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_offset_sp<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
 
     let mut offset: i16 = ((instruction & 0x007F) << 2) as i16;
@@ -907,9 +896,9 @@ pub fn thumb_offset_sp(emu: &mut Emulator, cpu_type: CpuType) {
         offset = -offset;
     }
 
-    let sp: u32 = emu.get_cpu(cpu_type).get_register(REG_SP as i32);
+    let sp: u32 = emu.get_cpu(cpu_type).get_register(REG_SP);
     emu.get_cpu_mut(cpu_type)
-        .set_register(REG_SP as i32, sp.wrapping_add(offset as u32));
+        .set_register(REG_SP, sp.wrapping_add(offset as u32));
 
     // if emu.get_cpu_mut(cpu_type).can_disassemble() {
     //     println!("ADD {{SP}}, ${:04X}", offset);
@@ -917,15 +906,15 @@ pub fn thumb_offset_sp(emu: &mut Emulator, cpu_type: CpuType) {
 }
 
 /// Thumb instruction: Load address
-pub fn thumb_load_address(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_load_address<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
 
-    let destination: u32 = ((instruction >> 8) & 0x7) as u32;
+    let destination = Reg::new(((instruction >> 8) & 0x7) as u32);
     let offset: u32 = ((instruction & 0x00FF) as u32) << 2;
     let adding_sp: bool = (instruction & (1 << 11)) != 0;
 
     let address: u32 = if adding_sp {
-        emu.get_cpu(cpu_type).get_register(REG_SP as i32)
+        emu.get_cpu(cpu_type).get_register(REG_SP)
     } else {
         // Set bit 1 to zero for alignment safety
         emu.get_cpu(cpu_type).get_pc() & !0x2
@@ -940,11 +929,11 @@ pub fn thumb_load_address(emu: &mut Emulator, cpu_type: CpuType) {
 }
 
 /// Thumb instruction: PUSH
-pub fn thumb_push(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_push<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
     let reg_list: u8 = (instruction & 0x00FF) as u8;
 
-    let mut stack_pointer: u32 = emu.get_cpu(cpu_type).get_register(REG_SP as i32);
+    let mut stack_pointer: u32 = emu.get_cpu(cpu_type).get_register(REG_SP);
 
     // if emu.get_cpu_mut(cpu_type).can_disassemble() {
     //     println!("PUSH ${:02X}", reg_list);
@@ -955,7 +944,7 @@ pub fn thumb_push(emu: &mut Emulator, cpu_type: CpuType) {
     if (instruction & (1 << 8)) != 0 {
         regs += 1;
         stack_pointer = stack_pointer.wrapping_sub(4);
-        let lr = emu.get_cpu(cpu_type).get_register(REG_LR as i32);
+        let lr = emu.get_cpu(cpu_type).get_register(REG_LR);
         emu.write_word(stack_pointer, lr, cpu_type);
     }
 
@@ -964,7 +953,7 @@ pub fn thumb_push(emu: &mut Emulator, cpu_type: CpuType) {
         if (reg_list & bit) != 0 {
             regs += 1;
             stack_pointer = stack_pointer.wrapping_sub(4);
-            let value = emu.get_cpu(cpu_type).get_register(i);
+            let value = emu.get_cpu(cpu_type).get_register(Reg::new(i));
             emu.write_word(stack_pointer, value, cpu_type);
         }
     }
@@ -976,15 +965,15 @@ pub fn thumb_push(emu: &mut Emulator, cpu_type: CpuType) {
     }
 
     emu.get_cpu_mut(cpu_type)
-        .set_register(REG_SP as i32, stack_pointer);
+        .set_register(REG_SP, stack_pointer);
 }
 
 /// Thumb instruction: POP
-pub fn thumb_pop(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_pop<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
     let reg_list: u8 = (instruction & 0x00FF) as u8;
 
-    let mut stack_pointer: u32 = emu.get_cpu(cpu_type).get_register(REG_SP as i32);
+    let mut stack_pointer: u32 = emu.get_cpu(cpu_type).get_register(REG_SP);
 
     // if emu.get_cpu_mut(cpu_type).can_disassemble() {
     //     println!("POP ${:02X}", reg_list);
@@ -997,7 +986,7 @@ pub fn thumb_pop(emu: &mut Emulator, cpu_type: CpuType) {
         if (reg_list & bit) != 0 {
             regs += 1;
             let value = emu.read_word(stack_pointer, cpu_type);
-            emu.get_cpu_mut(cpu_type).set_register(i, value);
+            emu.get_cpu_mut(cpu_type).set_register(Reg::new(i), value);
             stack_pointer = stack_pointer.wrapping_add(4);
         }
     }
@@ -1020,16 +1009,16 @@ pub fn thumb_pop(emu: &mut Emulator, cpu_type: CpuType) {
     emu.get_cpu_mut(cpu_type).add_internal_cycles(1);
 
     emu.get_cpu_mut(cpu_type)
-        .set_register(REG_SP as i32, stack_pointer);
+        .set_register(REG_SP, stack_pointer);
 }
 
 /// Thumb instruction: Store multiple registers
-pub fn thumb_store_multiple(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_store_multiple<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
     let reg_list: u8 = (instruction & 0x00FF) as u8;
-    let base: u32 = ((instruction >> 8) & 0x7) as u32;
+    let base = Reg::new(((instruction >> 8) & 0x7) as u32);
 
-    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base as i32);
+    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base);
 
     // if emu.get_cpu_mut(cpu_type).can_disassemble() {
     //     println!("STMIA {{{}}}, ${:02X}", base, reg_list);
@@ -1041,7 +1030,7 @@ pub fn thumb_store_multiple(emu: &mut Emulator, cpu_type: CpuType) {
         let bit: u8 = 1 << reg;
         if (reg_list & bit) != 0 {
             regs += 1;
-            let value = emu.get_cpu(cpu_type).get_register(reg);
+            let value = emu.get_cpu(cpu_type).get_register(Reg::new(reg));
             emu.write_word(address, value, cpu_type);
             address = address.wrapping_add(4);
         }
@@ -1052,16 +1041,16 @@ pub fn thumb_store_multiple(emu: &mut Emulator, cpu_type: CpuType) {
         emu.get_cpu_mut(cpu_type).add_s32_data(address, regs - 2);
     }
 
-    emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+    emu.get_cpu_mut(cpu_type).set_register(base, address);
 }
 
 /// Thumb instruction: Load multiple registers
-fn thumb_load_multiple(emu: &mut Emulator, cpu_type: CpuType) {
+fn thumb_load_multiple<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
     let reg_list: u8 = (instruction & 0x00FF) as u8;
-    let base: u32 = ((instruction >> 8) & 0x7) as u32;
+    let base = Reg::new(((instruction >> 8) & 0x7) as u32);
 
-    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base as i32);
+    let mut address: u32 = emu.get_cpu(cpu_type).get_register(base);
 
     // if emu.get_cpu_mut(cpu_type).can_disassemble() {
     //     println!("LDMIA {{{}}}, ${:02X}", base, reg_list);
@@ -1074,7 +1063,7 @@ fn thumb_load_multiple(emu: &mut Emulator, cpu_type: CpuType) {
         if (reg_list & bit) != 0 {
             regs += 1;
             let value = emu.read_word(address, cpu_type);
-            emu.get_cpu_mut(cpu_type).set_register(reg, value);
+            emu.get_cpu_mut(cpu_type).set_register(Reg::new(reg), value);
             address = address.wrapping_add(4);
         }
     }
@@ -1085,14 +1074,14 @@ fn thumb_load_multiple(emu: &mut Emulator, cpu_type: CpuType) {
     }
     emu.get_cpu_mut(cpu_type).add_internal_cycles(1);
 
-    let base_bit: u8 = 1 << base;
+    let base_bit: u8 = 1 << base.index();
     if (reg_list & base_bit) == 0 {
-        emu.get_cpu_mut(cpu_type).set_register(base as i32, address);
+        emu.get_cpu_mut(cpu_type).set_register(base, address);
     }
 }
 
 /// Thumb instruction: Branch
-pub fn thumb_branch(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_branch<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
     let mut address: u32 = emu.get_cpu(cpu_type).get_pc();
 
@@ -1112,7 +1101,7 @@ pub fn thumb_branch(emu: &mut Emulator, cpu_type: CpuType) {
 }
 
 /// Thumb instruction: Conditional branch
-fn thumb_cond_branch(emu: &mut Emulator, cpu_type: CpuType) {
+fn thumb_cond_branch<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
     let condition: u32 = ((instruction >> 8) & 0xF) as u32;
 
@@ -1120,7 +1109,7 @@ fn thumb_cond_branch(emu: &mut Emulator, cpu_type: CpuType) {
         // if emu.get_cpu_mut(cpu_type).can_disassemble() {
         //     println!("SWI ${:02X}", instruction & 0xFF);
         // }
-        emu.get_cpu_mut(cpu_type).handle_swi();
+        emu.swi(cpu_type);
         return;
     }
 
@@ -1143,7 +1132,7 @@ fn thumb_cond_branch(emu: &mut Emulator, cpu_type: CpuType) {
 
 /// Thumb instruction: Prepare long branch
 #[allow(clippy::missing_const_for_fn)]
-pub fn thumb_long_branch_prep(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_long_branch_prep<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
     let mut upper_address: u32 = emu.get_cpu(cpu_type).get_pc();
 
@@ -1156,13 +1145,13 @@ pub fn thumb_long_branch_prep(emu: &mut Emulator, cpu_type: CpuType) {
     // }
 
     emu.get_cpu_mut(cpu_type)
-        .set_register(REG_LR as i32, upper_address);
+        .set_register(REG_LR, upper_address);
 }
 
 /// Thumb instruction: Long branch
-pub fn thumb_long_branch(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_long_branch<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu(cpu_type).get_current_instr() as u16;
-    let mut address: u32 = emu.get_cpu(cpu_type).get_register(REG_LR as i32);
+    let mut address: u32 = emu.get_cpu(cpu_type).get_register(REG_LR);
 
     // address += (instruction & 0x7FF) << 1;
     address = address.wrapping_add(((instruction & 0x07FF) as u32) << 1);
@@ -1174,15 +1163,14 @@ pub fn thumb_long_branch(emu: &mut Emulator, cpu_type: CpuType) {
     //     println!("BL: ${:08X}", address);
     // }
 
-    emu.get_cpu_mut(cpu_type)
-        .set_register(REG_LR as i32, new_lr);
+    emu.get_cpu_mut(cpu_type).set_register(REG_LR, new_lr);
     emu.get_cpu_mut(cpu_type).jp(address, false);
 }
 
 /// Thumb instruction: Long branch with link and exchange
-pub fn thumb_long_blx(emu: &mut Emulator, cpu_type: CpuType) {
+pub fn thumb_long_blx<B: Bus>(emu: &mut B, cpu_type: CpuType) {
     let instruction: u16 = emu.get_cpu_mut(cpu_type).get_current_instr() as u16;
-    let mut address: u32 = emu.get_cpu_mut(cpu_type).get_register(REG_LR as i32);
+    let mut address: u32 = emu.get_cpu_mut(cpu_type).get_register(REG_LR);
     address += (instruction & 0x7FF) as u32 * 2; // << 1
 
     let mut new_lr = emu.get_cpu(cpu_type).get_pc() - 2;
@@ -1193,9 +1181,20 @@ pub fn thumb_long_blx(emu: &mut Emulator, cpu_type: CpuType) {
     // }
 
     // Set LR to return address
-    emu.get_cpu_mut(cpu_type)
-        .set_register(REG_LR as i32, new_lr);
+    emu.get_cpu_mut(cpu_type).set_register(REG_LR, new_lr);
 
     // Switch to ARM mode
     emu.get_cpu_mut(cpu_type).jp(address, true);
 }
+
+/// Thumb instruction: BKPT, ARMv5 only
+pub fn thumb_breakpoint<B: Bus>(emu: &mut B, cpu_type: CpuType) {
+    match cpu_type {
+        CpuType::Arm9 => emu.bkpt(cpu_type),
+        CpuType::Arm7 => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Unrecognized Thumb opcode BKPT on ARM7");
+            emu.undefined_instruction(cpu_type);
+        }
+    }
+}
@@ -2,9 +2,16 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 pub mod arm_cpu;
+#[cfg(feature = "block_cache")]
+pub mod block_cache;
+pub mod bus;
 pub mod coprocessor_15;
 pub mod disassemble;
+#[cfg(any(test, feature = "fuzzing"))]
+pub mod fuzz;
 pub mod interpreter;
 
 mod arm_table;
 mod instruction_table;
+
+pub use interpreter::step;
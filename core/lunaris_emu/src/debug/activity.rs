@@ -0,0 +1,181 @@
+//! Hardware activity map
+//!
+//! While enabled, every scanline of a frame gets a byte of [`Activity`]
+//! flags: which DMA channels ran, which IRQs were raised, whether the
+//! geometry engine was busy and whether any SPU channel played during it.
+//! Frontends draw the 263 x 8 grid next to the screens to see at a glance
+//! where in the frame work happens, which is what timing glitches are
+//! usually about. Flags are sampled once per CPU slice, so a DMA shorter
+//! than a slice can be missed.
+use lunaris_ds_mem_const::LINES_PER_FRAME;
+
+use crate::emulator::Emulator;
+
+/// One column of the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activity {
+    /// An ARM9 DMA channel was transferring
+    Dma9,
+    /// An ARM7 DMA channel was transferring
+    Dma7,
+    /// An ARM9 IRQ was requested
+    Irq9,
+    /// An ARM7 IRQ was requested
+    Irq7,
+    /// The geometry engine had commands queued or was busy
+    GxBusy,
+    /// A sound channel was playing
+    Spu,
+}
+
+impl Activity {
+    pub const ALL: [Self; 6] = [
+        Self::Dma9,
+        Self::Dma7,
+        Self::Irq9,
+        Self::Irq7,
+        Self::GxBusy,
+        Self::Spu,
+    ];
+
+    /// Bit of the activity in a line byte.
+    pub const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Activity of each scanline of one frame, see [`Emulator::activity_map`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivityMap {
+    /// [`Activity::bit`] flags, indexed by VCOUNT
+    lines: Vec<u8>,
+}
+
+impl Default for ActivityMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ActivityMap {
+    pub fn new() -> Self {
+        Self {
+            lines: vec![0; LINES_PER_FRAME as usize],
+        }
+    }
+
+    /// One byte of [`Activity::bit`] flags per scanline, VCOUNT 0 first.
+    pub fn lines(&self) -> &[u8] {
+        &self.lines
+    }
+
+    /// Whether `activity` happened on `line`.
+    pub fn get(&self, line: u16, activity: Activity) -> bool {
+        self.lines
+            .get(line as usize)
+            .is_some_and(|flags| flags & activity.bit() != 0)
+    }
+
+    fn mark(&mut self, line: u16, flags: u8) {
+        if let Some(entry) = self.lines.get_mut(line as usize) {
+            *entry |= flags;
+        }
+    }
+}
+
+impl Emulator {
+    /// Start or stop recording the activity map. Recording starts with the
+    /// next frame.
+    pub fn set_activity_map_enabled(&mut self, enabled: bool) {
+        self.activity = enabled.then(ActivityMap::new);
+        self.activity_frame = None;
+    }
+
+    /// Activity of the last completed frame, `None` while disabled or before
+    /// a frame was recorded.
+    pub fn activity_map(&self) -> Option<&ActivityMap> {
+        self.activity_frame.as_ref()
+    }
+
+    /// Flag `activity` on the current scanline.
+    #[inline]
+    pub(crate) fn mark_activity(&mut self, activity: Activity) {
+        let line = self.gpu.vertical_count;
+        if let Some(map) = &mut self.activity {
+            map.mark(line, activity.bit());
+        }
+    }
+
+    /// Sample the DMA, geometry engine and SPU state for the current line.
+    pub(crate) fn sample_activity(&mut self) {
+        if self.activity.is_none() {
+            return;
+        }
+        let mut flags = 0;
+        if self.dma.active_dmas & 0x0F != 0 {
+            flags |= Activity::Dma9.bit();
+        }
+        if self.dma.active_dmas & 0xF0 != 0 {
+            flags |= Activity::Dma7.bit();
+        }
+        let gx = &self.gpu.engine_3d;
+        if gx.gxstat.geo_busy || !gx.gxfifo.is_empty() || !gx.gxpipe.is_empty() {
+            flags |= Activity::GxBusy.bit();
+        }
+        if (0..16).any(|index| {
+            self.spu
+                .get_channel(index)
+                .is_some_and(|channel| channel.channel_cnt.busy)
+        }) {
+            flags |= Activity::Spu.bit();
+        }
+        let line = self.gpu.vertical_count;
+        if let Some(map) = &mut self.activity {
+            map.mark(line, flags);
+        }
+    }
+
+    /// Publish the map of the frame that just ended and start a new one.
+    pub(crate) fn finish_activity_frame(&mut self) {
+        if let Some(map) = &mut self.activity {
+            self.activity_frame = Some(std::mem::take(map));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interrupts::Interrupt;
+
+    #[test]
+    fn test_activity_map() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.run();
+        assert!(emu.activity_map().is_none());
+
+        emu.set_activity_map_enabled(true);
+        emu.run();
+        let map = emu.activity_map().unwrap();
+        assert_eq!(map.lines().len(), LINES_PER_FRAME as usize);
+        // VBLANK IRQs are only raised when enabled in DISPSTAT
+        assert!(
+            !map.lines()
+                .iter()
+                .any(|&flags| flags & Activity::Irq9.bit() != 0)
+        );
+
+        emu.gpu.vertical_count = 100;
+        emu.request_interrupt7(Interrupt::Timer0);
+        emu.finish_activity_frame();
+        let map = emu.activity_map().unwrap();
+        assert!(map.get(100, Activity::Irq7));
+        assert!(!map.get(100, Activity::Irq9));
+        assert!(!map.get(101, Activity::Irq7));
+
+        emu.set_activity_map_enabled(false);
+        emu.run();
+        assert!(emu.activity_map().is_none());
+    }
+}
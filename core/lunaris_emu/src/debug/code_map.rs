@@ -0,0 +1,412 @@
+//! ARM / Thumb code marking for disassembly.
+//!
+//! A sparse bitmap over the address space records, per halfword, whether it
+//! is known to hold ARM or Thumb code. It is filled from three sources:
+//! explicit [`Emulator::mark_as_arm`] / [`Emulator::mark_as_thumb`] calls,
+//! static analysis of the ROM's binaries and overlays (entry points, static
+//! initializers and direct branch targets), and optionally every executed
+//! instruction.
+use std::collections::BTreeMap;
+
+use crate::cpu::arm_cpu::CpuType;
+use crate::emulator::Emulator;
+
+/// Instruction set of a code address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeMode {
+    Arm,
+    Thumb,
+}
+
+/// Page size in bytes; pages are only allocated once something is marked.
+const PAGE_SHIFT: u32 = 12;
+/// `u64` words per bitmap of one page, one bit per halfword.
+const PAGE_WORDS: usize = (1 << PAGE_SHIFT) / 2 / 64;
+/// Upper bound of instructions decoded by one [`CodeMap::analyze`] call.
+const MAX_ANALYZE_STEPS: usize = 1 << 22;
+
+#[derive(Debug, Clone, Default)]
+struct Page {
+    known: [u64; PAGE_WORDS],
+    thumb: [u64; PAGE_WORDS],
+}
+
+/// ARM / Thumb bitmap of one CPU's address space.
+#[derive(Debug, Clone, Default)]
+pub struct CodeMap {
+    pages: BTreeMap<u32, Box<Page>>,
+    track_execution: bool,
+}
+
+const fn bit(address: u32) -> (usize, u64) {
+    let halfword = (address as usize >> 1) & ((1 << (PAGE_SHIFT - 1)) - 1);
+    (halfword / 64, 1 << (halfword % 64))
+}
+
+impl CodeMap {
+    fn mark_halfword(&mut self, address: u32, mode: CodeMode) {
+        let page = self.pages.entry(address >> PAGE_SHIFT).or_default();
+        let (word, mask) = bit(address);
+        page.known[word] |= mask;
+        match mode {
+            CodeMode::Arm => page.thumb[word] &= !mask,
+            CodeMode::Thumb => page.thumb[word] |= mask,
+        }
+    }
+
+    /// Mark the 32-bit instruction at `address` as ARM.
+    pub fn mark_as_arm(&mut self, address: u32) {
+        let address = address & !3;
+        self.mark_halfword(address, CodeMode::Arm);
+        self.mark_halfword(address + 2, CodeMode::Arm);
+    }
+
+    /// Mark the 16-bit instruction at `address` as Thumb.
+    pub fn mark_as_thumb(&mut self, address: u32) {
+        self.mark_halfword(address & !1, CodeMode::Thumb);
+    }
+
+    /// Mode of the code at `address`, `None` if unknown (or data).
+    pub fn mode_at(&self, address: u32) -> Option<CodeMode> {
+        let page = self.pages.get(&(address >> PAGE_SHIFT))?;
+        let (word, mask) = bit(address);
+        if page.known[word] & mask == 0 {
+            return None;
+        }
+        Some(match page.thumb[word] & mask {
+            0 => CodeMode::Arm,
+            _ => CodeMode::Thumb,
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.pages.clear();
+    }
+
+    /// Whether executed instructions are marked as they run.
+    pub const fn tracks_execution(&self) -> bool {
+        self.track_execution
+    }
+
+    /// Mark every executed instruction from now on. Costs a map lookup per
+    /// instruction, so it is off by default.
+    pub fn set_track_execution(&mut self, enabled: bool) {
+        self.track_execution = enabled;
+    }
+
+    /// Contiguous runs of known code as `(start, end, mode)`, `end` exclusive.
+    pub fn regions(&self) -> Vec<(u32, u32, CodeMode)> {
+        let mut regions: Vec<(u32, u32, CodeMode)> = Vec::new();
+        for (&page_index, page) in &self.pages {
+            let base = page_index << PAGE_SHIFT;
+            for halfword in 0..PAGE_WORDS * 64 {
+                let (word, mask) = (halfword / 64, 1u64 << (halfword % 64));
+                if page.known[word] & mask == 0 {
+                    continue;
+                }
+                let mode = match page.thumb[word] & mask {
+                    0 => CodeMode::Arm,
+                    _ => CodeMode::Thumb,
+                };
+                let address = base + halfword as u32 * 2;
+                match regions.last_mut() {
+                    Some((_, end, last)) if *end == address && *last == mode => *end += 2,
+                    _ => regions.push((address, address + 2, mode)),
+                }
+            }
+        }
+        regions
+    }
+
+    /// Follow code from `entries` through `image`, loaded at `base`.
+    ///
+    /// Decoding runs linearly until an unconditional branch or return, and
+    /// queues the targets of direct branches (`B`, `BL`, `BLX`), switching
+    /// mode for `BLX`. Addresses that are already marked are not decoded
+    /// again, so earlier (e.g. execution) results win over the analysis.
+    /// Returns the number of newly marked instructions.
+    pub fn analyze(
+        &mut self,
+        image: &[u8],
+        base: u32,
+        entries: impl IntoIterator<Item = (u32, CodeMode)>,
+    ) -> usize {
+        let end = base as u64 + image.len() as u64;
+        let in_image = |address: u32, size: u64| address >= base && address as u64 + size <= end;
+        let word = |address: u32| {
+            let i = (address - base) as usize;
+            u32::from_le_bytes([image[i], image[i + 1], image[i + 2], image[i + 3]])
+        };
+        let halfword = |address: u32| {
+            let i = (address - base) as usize;
+            u16::from_le_bytes([image[i], image[i + 1]])
+        };
+
+        let mut queue: Vec<(u32, CodeMode)> = entries.into_iter().collect();
+        let mut marked = 0;
+        let mut steps = 0;
+        while let Some((mut address, mode)) = queue.pop() {
+            loop {
+                steps += 1;
+                if steps > MAX_ANALYZE_STEPS || self.mode_at(address).is_some() {
+                    break;
+                }
+                match mode {
+                    CodeMode::Arm => {
+                        address &= !3;
+                        if !in_image(address, 4) {
+                            break;
+                        }
+                        self.mark_as_arm(address);
+                        marked += 1;
+                        let (next, target) = arm_flow(word(address), address);
+                        queue.extend(target);
+                        match next {
+                            true => address += 4,
+                            false => break,
+                        }
+                    }
+                    CodeMode::Thumb => {
+                        address &= !1;
+                        if !in_image(address, 2) {
+                            break;
+                        }
+                        let hw = halfword(address);
+                        // BL / BLX pair
+                        if hw & 0xF800 == 0xF000 && in_image(address, 4) {
+                            let low = halfword(address + 2);
+                            if matches!(low & 0xF800, 0xF800 | 0xE800) {
+                                self.mark_as_thumb(address);
+                                self.mark_as_thumb(address + 2);
+                                marked += 2;
+                                let offset =
+                                    (((hw as i32) << 21) >> 9) | ((low as i32 & 0x7FF) << 1);
+                                let target = address.wrapping_add(4).wrapping_add_signed(offset);
+                                queue.push(match low & 0xF800 {
+                                    0xF800 => (target, CodeMode::Thumb),
+                                    _ => (target & !3, CodeMode::Arm),
+                                });
+                                address += 4;
+                                continue;
+                            }
+                        }
+                        self.mark_as_thumb(address);
+                        marked += 1;
+                        let (next, target) = thumb_flow(hw, address);
+                        queue.extend(target);
+                        match next {
+                            true => address += 2,
+                            false => break,
+                        }
+                    }
+                }
+            }
+        }
+        marked
+    }
+}
+
+/// Control flow of an ARM instruction: whether execution can fall through
+/// to the next instruction, and the direct branch target if any.
+fn arm_flow(instruction: u32, address: u32) -> (bool, Option<(u32, CodeMode)>) {
+    let cond = instruction >> 28;
+    let always = cond == 0xE;
+    let offset = ((instruction << 8) as i32) >> 6;
+    let target = address.wrapping_add(8).wrapping_add_signed(offset);
+
+    if instruction & 0x0E00_0000 == 0x0A00_0000 {
+        if cond == 0xF {
+            // BLX immediate
+            let h = (instruction >> 23) & 2;
+            return (true, Some((target | h, CodeMode::Thumb)));
+        }
+        let link = instruction & (1 << 24) != 0;
+        return (link || !always, Some((target, CodeMode::Arm)));
+    }
+    if cond == 0xF {
+        return (true, None);
+    }
+    // BX / BLX register
+    if instruction & 0x0FFF_FFD0 == 0x012F_FF10 {
+        let link = instruction & (1 << 5) != 0;
+        return (link || !always, None);
+    }
+
+    let writes_pc = (instruction >> 12) & 0xF == 15;
+    let ends = match (instruction >> 25) & 7 {
+        // Data processing; TST/TEQ/CMP/CMN have no destination
+        0 | 1 => writes_pc && !matches!((instruction >> 21) & 0xF, 8..=11),
+        // LDR pc
+        2 | 3 => writes_pc && instruction & (1 << 20) != 0,
+        // LDM with pc in the list
+        4 => instruction & (1 << 20) != 0 && instruction & (1 << 15) != 0,
+        _ => false,
+    };
+    (!(ends && always), None)
+}
+
+/// Control flow of a Thumb instruction (BL pairs are handled by the caller).
+fn thumb_flow(instruction: u16, address: u32) -> (bool, Option<(u32, CodeMode)>) {
+    let pc = address.wrapping_add(4);
+    match instruction {
+        // B
+        _ if instruction & 0xF800 == 0xE000 => {
+            let offset = ((instruction as i32) << 21) >> 20;
+            (
+                false,
+                Some((pc.wrapping_add_signed(offset), CodeMode::Thumb)),
+            )
+        }
+        // B<cond>, excluding undefined and SWI
+        _ if instruction & 0xF000 == 0xD000 && (instruction >> 8) & 0xF < 0xE => {
+            let offset = ((instruction as i32) << 24) >> 23;
+            (
+                true,
+                Some((pc.wrapping_add_signed(offset), CodeMode::Thumb)),
+            )
+        }
+        // BX / BLX register
+        _ if instruction & 0xFF00 == 0x4700 => (instruction & 0x80 != 0, None),
+        // POP {.., pc}
+        _ if instruction & 0xFF00 == 0xBD00 => (false, None),
+        // ADD / MOV pc, Rs
+        _ if matches!(instruction & 0xFF00, 0x4400 | 0x4600) && instruction & 0x87 == 0x87 => {
+            (false, None)
+        }
+        _ => (true, None),
+    }
+}
+
+impl Emulator {
+    pub fn code_map(&self, cpu: CpuType) -> &CodeMap {
+        match cpu {
+            CpuType::Arm7 => &self.code_map7,
+            CpuType::Arm9 => &self.code_map9,
+        }
+    }
+
+    pub fn code_map_mut(&mut self, cpu: CpuType) -> &mut CodeMap {
+        match cpu {
+            CpuType::Arm7 => &mut self.code_map7,
+            CpuType::Arm9 => &mut self.code_map9,
+        }
+    }
+
+    /// Mark the instruction at `address` as ARM code.
+    pub fn mark_as_arm(&mut self, cpu: CpuType, address: u32) {
+        self.code_map_mut(cpu).mark_as_arm(address);
+    }
+
+    /// Mark the instruction at `address` as Thumb code.
+    pub fn mark_as_thumb(&mut self, cpu: CpuType, address: u32) {
+        self.code_map_mut(cpu).mark_as_thumb(address);
+    }
+
+    /// Populate the code maps from the loaded ROM.
+    ///
+    /// Starts at the binaries' entry points and at the static initializers
+    /// of every overlay. Binaries compressed by the SDK only yield their
+    /// uncompressed entry code.
+    pub fn analyze_rom_code(&mut self) {
+        let header = self.cart.header();
+        let binaries = [
+            (
+                CpuType::Arm9,
+                header.arm9_rom_offset,
+                header.arm9_size,
+                header.arm9_ram_address,
+                header.arm9_entry,
+            ),
+            (
+                CpuType::Arm7,
+                header.arm7_rom_offset,
+                header.arm7_size,
+                header.arm7_ram_address,
+                header.arm7_entry,
+            ),
+        ];
+        for (cpu, rom_offset, size, ram_address, entry) in binaries {
+            if let Some(image) = self.cart.rom_slice(rom_offset, size) {
+                let map = match cpu {
+                    CpuType::Arm7 => &mut self.code_map7,
+                    CpuType::Arm9 => &mut self.code_map9,
+                };
+                map.analyze(image, ram_address, [(entry, CodeMode::Arm)]);
+            }
+        }
+
+        for overlay in self.cart.overlays() {
+            let Some(image) = overlay
+                .rom
+                .and_then(|fat| self.cart.rom_slice(fat.start, fat.len()))
+            else {
+                continue;
+            };
+
+            // Static initializer table: function pointers, bit 0 set for Thumb
+            let mut entries = Vec::new();
+            let mut pointer = overlay.static_init_start;
+            while pointer < overlay.static_init_end {
+                let i = pointer.wrapping_sub(overlay.ram_address) as usize;
+                let Some(bytes) = image.get(i..i + 4) else {
+                    break;
+                };
+                let function = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                match function & 1 {
+                    0 if function != 0 => entries.push((function, CodeMode::Arm)),
+                    1 => entries.push((function & !1, CodeMode::Thumb)),
+                    _ => {}
+                }
+                pointer += 4;
+            }
+
+            let map = match overlay.cpu {
+                CpuType::Arm7 => &mut self.code_map7,
+                CpuType::Arm9 => &mut self.code_map9,
+            };
+            map.analyze(image, overlay.ram_address, entries);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_branch_analysis() {
+        let mut image = Vec::new();
+        // 0x00: BL 0x10
+        image.extend_from_slice(&0xEB00_0002u32.to_le_bytes());
+        // 0x04: BLX 0x14 (Thumb)
+        image.extend_from_slice(&0xFA00_0002u32.to_le_bytes());
+        // 0x08: BX lr
+        image.extend_from_slice(&0xE12F_FF1Eu32.to_le_bytes());
+        // 0x0C: data
+        image.extend_from_slice(&0xFFFF_FFFFu32.to_le_bytes());
+        // 0x10: BX lr
+        image.extend_from_slice(&0xE12F_FF1Eu32.to_le_bytes());
+        // 0x14: Thumb MOVS r0, #0; BX lr
+        image.extend_from_slice(&[0x00, 0x20, 0x70, 0x47]);
+
+        let mut map = CodeMap::default();
+        let base = 0x0200_0000;
+        assert_eq!(map.analyze(&image, base, [(base, CodeMode::Arm)]), 6);
+        assert_eq!(map.mode_at(base + 0x08), Some(CodeMode::Arm));
+        assert_eq!(map.mode_at(base + 0x0C), None);
+        assert_eq!(map.mode_at(base + 0x10), Some(CodeMode::Arm));
+        assert_eq!(map.mode_at(base + 0x16), Some(CodeMode::Thumb));
+        assert_eq!(
+            map.regions(),
+            [
+                (base, base + 0x0C, CodeMode::Arm),
+                (base + 0x10, base + 0x14, CodeMode::Arm),
+                (base + 0x14, base + 0x18, CodeMode::Thumb),
+            ]
+        );
+
+        // Explicit marks override
+        map.mark_as_thumb(base + 0x0C);
+        assert_eq!(map.mode_at(base + 0x0C), Some(CodeMode::Thumb));
+    }
+}
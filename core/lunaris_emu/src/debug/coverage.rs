@@ -0,0 +1,155 @@
+//! Executed code coverage
+//!
+//! Records which halfwords each CPU has fetched an instruction from, for
+//! coverage-guided fuzzing and for reporting how much of a game a test run
+//! exercised. Unlike [`CodeMap`](super::CodeMap) nothing but execution is
+//! recorded, so the result is exactly what ran.
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::cpu::arm_cpu::CpuType;
+use crate::emulator::Emulator;
+
+/// Page size in bytes; pages are only allocated once something runs there.
+const PAGE_SHIFT: u32 = 12;
+/// `u64` words per bitmap of one page, one bit per halfword.
+const PAGE_WORDS: usize = (1 << PAGE_SHIFT) / 2 / 64;
+
+/// Executed halfwords of one CPU's address space.
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    pages: BTreeMap<u32, Box<[u64; PAGE_WORDS]>>,
+    halfwords: usize,
+    enabled: bool,
+}
+
+impl Coverage {
+    pub const fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Record executed instructions from now on. Costs a map lookup per
+    /// instruction, so it is off by default.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Mark the `size` byte instruction at `address` as executed. Returns
+    /// whether any of it was new.
+    pub fn record(&mut self, address: u32, size: u32) -> bool {
+        let mut new = false;
+        for halfword in (address & !1..address.saturating_add(size)).step_by(2) {
+            let page = self.pages.entry(halfword >> PAGE_SHIFT).or_default();
+            let (word, mask) = bit(halfword);
+            if page[word] & mask == 0 {
+                page[word] |= mask;
+                self.halfwords += 1;
+                new = true;
+            }
+        }
+        new
+    }
+
+    pub fn contains(&self, address: u32) -> bool {
+        let (word, mask) = bit(address);
+        self.pages
+            .get(&(address >> PAGE_SHIFT))
+            .is_some_and(|page| page[word] & mask != 0)
+    }
+
+    /// Number of executed bytes.
+    pub const fn executed_bytes(&self) -> usize {
+        self.halfwords * 2
+    }
+
+    /// Executed address ranges in ascending order, adjacent ones merged.
+    pub fn ranges(&self) -> impl Iterator<Item = Range<u32>> + '_ {
+        let mut halfwords = self.halfwords().peekable();
+        std::iter::from_fn(move || {
+            let start = halfwords.next()?;
+            let mut end = start.saturating_add(2);
+            while halfwords.next_if_eq(&end).is_some() {
+                end = end.saturating_add(2);
+            }
+            Some(start..end)
+        })
+    }
+
+    fn halfwords(&self) -> impl Iterator<Item = u32> + '_ {
+        self.pages.iter().flat_map(|(&index, page)| {
+            let base = index << PAGE_SHIFT;
+            page.iter().enumerate().flat_map(move |(word, &bits)| {
+                (0..64)
+                    .filter(move |i| bits >> i & 1 != 0)
+                    .map(move |i| base + (word as u32 * 64 + i) * 2)
+            })
+        })
+    }
+
+    /// Add everything `other` executed, e.g. to accumulate a fuzzing corpus.
+    /// Returns the number of newly covered bytes.
+    pub fn merge(&mut self, other: &Coverage) -> usize {
+        let before = self.halfwords;
+        for (&index, other_page) in &other.pages {
+            let page = self.pages.entry(index).or_default();
+            for (bits, &other_bits) in page.iter_mut().zip(other_page.iter()) {
+                self.halfwords += (other_bits & !*bits).count_ones() as usize;
+                *bits |= other_bits;
+            }
+        }
+        (self.halfwords - before) * 2
+    }
+
+    pub fn clear(&mut self) {
+        self.pages.clear();
+        self.halfwords = 0;
+    }
+}
+
+const fn bit(address: u32) -> (usize, u64) {
+    let halfword = (address as usize >> 1) & ((1 << (PAGE_SHIFT - 1)) - 1);
+    (halfword / 64, 1 << (halfword % 64))
+}
+
+impl Emulator {
+    pub fn coverage(&self, cpu: CpuType) -> &Coverage {
+        match cpu {
+            CpuType::Arm7 => &self.coverage7,
+            CpuType::Arm9 => &self.coverage9,
+        }
+    }
+
+    pub fn coverage_mut(&mut self, cpu: CpuType) -> &mut Coverage {
+        match cpu {
+            CpuType::Arm7 => &mut self.coverage7,
+            CpuType::Arm9 => &mut self.coverage9,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranges_and_merge() {
+        let mut run = Coverage::default();
+        assert!(run.record(0x0200_0000, 4));
+        assert!(run.record(0x0200_0004, 2));
+        assert!(!run.record(0x0200_0000, 4));
+        // Across a page boundary
+        run.record(0x0200_0FFE, 4);
+        assert_eq!(run.executed_bytes(), 10);
+        assert_eq!(
+            run.ranges().collect::<Vec<_>>(),
+            [0x0200_0000..0x0200_0006, 0x0200_0FFE..0x0200_1002]
+        );
+
+        let mut total = Coverage::default();
+        total.record(0x0200_0000, 4);
+        assert_eq!(total.merge(&run), 6);
+        assert_eq!(total.merge(&run), 0);
+        assert!(total.contains(0x0200_1000));
+        assert!(!total.contains(0x0200_1002));
+    }
+}
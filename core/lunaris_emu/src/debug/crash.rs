@@ -0,0 +1,374 @@
+//! Crash bundles
+//!
+//! With [`Config::crash_bundle_dir`] set, the core keeps the last
+//! [`CRASH_TRACE_LENGTH`] executed instructions and counts IO register
+//! accesses. When a CPU then crashes, i.e. executes an undefined instruction
+//! or takes a prefetch abort, which games never do on purpose, everything a
+//! bug report needs is written to a `<game code>-<time>` directory below it:
+//!
+//! - `screens.ppm`: both screens, upper screen on top
+//! - `registers.txt`: what crashed and the registers of both CPUs
+//! - `trace.txt`: the last instructions of both CPUs, oldest first
+//! - `io.txt`: IO register accesses since power on, most frequent first
+//! - `state.bin`: savestate taken at the crash
+//!
+//! Only the first crash after power on is written: a crashed game usually
+//! keeps crashing, and the first one is the interesting one.
+//!
+//! [`Config::crash_bundle_dir`]: crate::Config::crash_bundle_dir
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use lunaris_ds_mem_const::{PIXELS_PER_LINE, SCANLINES};
+use snafu::ResultExt as _;
+
+use crate::cpu::arm_cpu::CpuType;
+use crate::debug::{MapEntryKind, MemoryMap};
+use crate::emulator::Emulator;
+use crate::error::{EmuError, FailedWriteFileSnafu};
+
+/// Instructions kept for `trace.txt`, both CPUs together.
+pub const CRASH_TRACE_LENGTH: usize = 256;
+
+/// Why the guest is considered crashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashReason {
+    UndefinedInstruction,
+    PrefetchAbort,
+}
+
+/// A guest crash, see [`Emulator::last_crash`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GuestCrash {
+    pub cpu: CpuType,
+    pub reason: CrashReason,
+    /// Address of the offending instruction
+    pub address: u32,
+}
+
+/// One entry of the instruction trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TracedInstruction {
+    pub cpu: CpuType,
+    pub address: u32,
+    pub opcode: u32,
+    pub thumb: bool,
+}
+
+/// Accesses of one IO register.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoAccessCount {
+    pub reads: u64,
+    pub writes: u64,
+}
+
+/// What the core records for crash bundles while they are enabled.
+#[derive(Debug, Default)]
+pub struct CrashRecorder {
+    instructions: VecDeque<TracedInstruction>,
+    /// Keyed by CPU (ARM9 first) and address. Reads only borrow the
+    /// emulator, hence the cell.
+    io: RefCell<BTreeMap<(bool, u32), IoAccessCount>>,
+}
+
+impl CrashRecorder {
+    /// The last executed instructions, oldest first.
+    pub fn instructions(&self) -> impl Iterator<Item = &TracedInstruction> {
+        self.instructions.iter()
+    }
+
+    /// Accesses of the IO register at `address` by `cpu`.
+    pub fn io_accesses(&self, cpu: CpuType, address: u32) -> IoAccessCount {
+        let key = (cpu == CpuType::Arm7, address);
+        self.io.borrow().get(&key).copied().unwrap_or_default()
+    }
+
+    fn record_instruction(&mut self, instruction: TracedInstruction) {
+        if self.instructions.len() == CRASH_TRACE_LENGTH {
+            self.instructions.pop_front();
+        }
+        self.instructions.push_back(instruction);
+    }
+
+    fn record_io(&self, cpu: CpuType, address: u32, write: bool) {
+        let mut io = self.io.borrow_mut();
+        let count = io.entry((cpu == CpuType::Arm7, address)).or_default();
+        match write {
+            true => count.writes += 1,
+            false => count.reads += 1,
+        }
+    }
+}
+
+impl Emulator {
+    /// The first crash since power on, if any.
+    pub const fn last_crash(&self) -> Option<&GuestCrash> {
+        self.crash.as_ref()
+    }
+
+    /// Directory of the bundle written for [`Emulator::last_crash`].
+    pub fn crash_bundle(&self) -> Option<&Path> {
+        self.crash_bundle.as_deref()
+    }
+
+    /// Record the instruction `cpu_type` just fetched from `address`.
+    #[inline]
+    pub(crate) fn record_instruction(&mut self, cpu_type: CpuType, address: u32) {
+        if self.config.crash_bundle_dir.is_none() {
+            return;
+        }
+        let cpu = self.get_cpu(cpu_type);
+        let instruction = TracedInstruction {
+            cpu: cpu_type,
+            address,
+            opcode: cpu.current_instr,
+            thumb: cpu.cpsr.thumb_on,
+        };
+        self.crash_recorder
+            .get_or_insert_with(CrashRecorder::default)
+            .record_instruction(instruction);
+    }
+
+    /// Count an access to `address` if it is an IO register.
+    #[inline]
+    pub(crate) fn record_io_access(&self, cpu_type: CpuType, address: u32, write: bool) {
+        if address >> 24 != 0x04 {
+            return;
+        }
+        if let Some(recorder) = &self.crash_recorder {
+            recorder.record_io(cpu_type, address, write);
+        }
+    }
+
+    /// `cpu_type` executed an undefined instruction: take the exception and
+    /// report the crash.
+    pub(crate) fn undefined_instruction(&mut self, cpu_type: CpuType) {
+        self.guest_crash(cpu_type, CrashReason::UndefinedInstruction);
+        self.get_cpu_mut(cpu_type).handle_undefined();
+    }
+
+    /// Remember the crash of the instruction `cpu_type` is executing and
+    /// write its bundle, before the CPU enters the exception handler.
+    pub(crate) fn guest_crash(&mut self, cpu_type: CpuType, reason: CrashReason) {
+        if self.crash.is_some() {
+            return;
+        }
+        let cpu = self.get_cpu(cpu_type);
+        // PC is two instructions ahead once fetched
+        let address = match cpu.cpsr.thumb_on {
+            true => cpu.regs[15].wrapping_sub(4),
+            false => cpu.regs[15].wrapping_sub(8),
+        };
+        let crash = GuestCrash {
+            cpu: cpu_type,
+            reason,
+            address,
+        };
+        self.crash = Some(crash);
+        #[cfg(feature = "tracing")]
+        tracing::error!("Guest crash: {crash:?}");
+
+        let Some(dir) = self.config.crash_bundle_dir.clone() else {
+            return;
+        };
+        if self.config.read_only {
+            return;
+        }
+        match self.write_crash_bundle(&dir, &crash) {
+            Ok(bundle) => self.crash_bundle = Some(bundle),
+            Err(_error) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Failed to write crash bundle: {_error}");
+            }
+        }
+    }
+
+    /// Write the bundle for `crash` to a new directory below `dir` and
+    /// return its path. Frontends can call this for hangs the core does not
+    /// detect.
+    ///
+    /// # Errors
+    /// If the directory or one of its files cannot be written.
+    pub fn write_crash_bundle(&self, dir: &Path, crash: &GuestCrash) -> Result<PathBuf, EmuError> {
+        let time = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let game_code = String::from_utf8_lossy(&self.cart.game_code())
+            .replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+        let bundle = dir.join(format!("{game_code}-{time}"));
+        std::fs::create_dir_all(&bundle).context(FailedWriteFileSnafu { path: &bundle })?;
+
+        let files = [
+            ("screens.ppm", self.crash_screens()),
+            ("registers.txt", self.crash_registers(crash).into_bytes()),
+            ("trace.txt", self.crash_trace().into_bytes()),
+            ("io.txt", self.crash_io_histogram().into_bytes()),
+            ("state.bin", self.save_state()),
+        ];
+        for (name, data) in files {
+            let path = bundle.join(name);
+            std::fs::write(&path, data).context(FailedWriteFileSnafu { path })?;
+        }
+        Ok(bundle)
+    }
+
+    /// Both screens as a binary PPM, upper screen on top.
+    fn crash_screens(&self) -> Vec<u8> {
+        let mut pixels = vec![0; PIXELS_PER_LINE * SCANLINES * 2];
+        let (upper, lower) = pixels.split_at_mut(PIXELS_PER_LINE * SCANLINES);
+        self.get_upper_frame(upper);
+        self.get_lower_frame(lower);
+
+        let mut ppm = format!("P6\n{} {}\n255\n", PIXELS_PER_LINE, SCANLINES * 2).into_bytes();
+        // 0xAARRGGBB -> [R, G, B]
+        ppm.extend(pixels.iter().flat_map(|px| {
+            let [b, g, r, _] = px.to_le_bytes();
+            [r, g, b]
+        }));
+        ppm
+    }
+
+    fn crash_registers(&self, crash: &GuestCrash) -> String {
+        let mut text = format!(
+            "crash: {:?} on {:?} at {:08X}\nsystem_timestamp: {}\n",
+            crash.reason, crash.cpu, crash.address, self.system_timestamp
+        );
+        for (name, cpu_type) in [("arm9", CpuType::Arm9), ("arm7", CpuType::Arm7)] {
+            let cpu = self.get_cpu(cpu_type);
+            let _ = writeln!(
+                text,
+                "\n[{name}]\ncpsr: {:08X}\nhalted: {}",
+                cpu.cpsr.get(),
+                cpu.halted
+            );
+            for (i, reg) in cpu.regs.iter().enumerate() {
+                let _ = writeln!(text, "r{i}: {reg:08X}");
+            }
+        }
+        text
+    }
+
+    fn crash_trace(&self) -> String {
+        let mut text = String::new();
+        let Some(recorder) = &self.crash_recorder else {
+            return text;
+        };
+        for instruction in recorder.instructions() {
+            let cpu = match instruction.cpu {
+                CpuType::Arm9 => "arm9",
+                CpuType::Arm7 => "arm7",
+            };
+            let _ = match instruction.thumb {
+                true => writeln!(
+                    text,
+                    "{cpu} {:08X}: {:04X}",
+                    instruction.address, instruction.opcode as u16
+                ),
+                false => writeln!(
+                    text,
+                    "{cpu} {:08X}: {:08X}",
+                    instruction.address, instruction.opcode
+                ),
+            };
+        }
+        text
+    }
+
+    fn crash_io_histogram(&self) -> String {
+        let mut text = String::new();
+        let Some(recorder) = &self.crash_recorder else {
+            return text;
+        };
+        let mut accesses: Vec<_> = recorder
+            .io
+            .borrow()
+            .iter()
+            .map(|(&key, &count)| (key, count))
+            .collect();
+        accesses.sort_by_key(|&(key, count)| (std::cmp::Reverse(count.reads + count.writes), key));
+
+        let maps = [
+            MemoryMap::base(CpuType::Arm9),
+            MemoryMap::base(CpuType::Arm7),
+        ];
+        for ((is_arm7, address), count) in accesses {
+            let name = maps[is_arm7 as usize]
+                .entries_of(MapEntryKind::Register)
+                .find(|entry| (entry.address..entry.address + entry.size).contains(&address))
+                .map_or("", |entry| &entry.name);
+            let cpu = if is_arm7 { "arm7" } else { "arm9" };
+            let _ = writeln!(
+                text,
+                "{cpu} {address:08X} {name:<20} reads {:>8} writes {:>8}",
+                count.reads, count.writes
+            );
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Emulator about to run `mov r0, #0`, then an undefined unconditional
+    /// instruction.
+    fn crashing_emu(dir: &Path) -> Box<Emulator> {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.config.crash_bundle_dir = Some(dir.to_path_buf());
+        emu.write_word(0x0200_1000, 0xE3A0_0000, CpuType::Arm9);
+        emu.write_word(0x0200_1004, 0xF000_0000, CpuType::Arm9);
+        emu.arm9.jp(0x0200_1000, false);
+        emu
+    }
+
+    #[test]
+    fn test_crash_bundle() {
+        let dir = std::env::temp_dir().join(format!("lunaris-crash-{}", std::process::id()));
+        let mut emu = crashing_emu(&dir);
+
+        emu.execute(CpuType::Arm9);
+        assert!(emu.last_crash().is_none());
+        emu.write_halfword(0x0400_0208, 0, CpuType::Arm7);
+        emu.execute(CpuType::Arm9);
+        let crash = *emu.last_crash().unwrap();
+        assert_eq!(crash.cpu, CpuType::Arm9);
+        assert_eq!(crash.reason, CrashReason::UndefinedInstruction);
+        assert_eq!(crash.address, 0x0200_1004);
+
+        let recorder = emu.crash_recorder.as_ref().unwrap();
+        assert_eq!(recorder.instructions().count(), 2);
+        assert_eq!(recorder.io_accesses(CpuType::Arm7, 0x0400_0208).writes, 1);
+
+        let bundle = emu.crash_bundle().unwrap().to_path_buf();
+        let trace = std::fs::read_to_string(bundle.join("trace.txt")).unwrap();
+        let io = std::fs::read_to_string(bundle.join("io.txt")).unwrap();
+        assert!(io.starts_with("arm7 04000208"));
+        assert!(trace.ends_with("arm9 02001004: F0000000\n"));
+        let registers = std::fs::read_to_string(bundle.join("registers.txt")).unwrap();
+        assert!(registers.starts_with("crash: UndefinedInstruction on Arm9 at 02001004"));
+        let state = std::fs::read(bundle.join("state.bin")).unwrap();
+        assert!(emu.load_state(&state).is_ok());
+        assert!(
+            std::fs::read(bundle.join("screens.ppm"))
+                .unwrap()
+                .starts_with(b"P6\n256 384\n")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_only_crash_writes_nothing() {
+        let dir = std::env::temp_dir().join(format!("lunaris-crash-ro-{}", std::process::id()));
+        let mut emu = crashing_emu(&dir);
+        emu.config.read_only = true;
+
+        emu.execute(CpuType::Arm9);
+        emu.execute(CpuType::Arm9);
+        assert!(emu.last_crash().is_some());
+        assert!(emu.crash_bundle().is_none());
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,507 @@
+//! Watch expressions
+//!
+//! Small expression language for watch windows and conditional breakpoints,
+//! e.g. `[0x02000F00]+r3*2` or `r0 == 0 && b[sp+4] != 0xFF`.
+//!
+//! Operators, from lowest to highest precedence:
+//! - `||`
+//! - `&&`
+//! - `==` `!=` `<` `<=` `>` `>=`
+//! - `|`
+//! - `^`
+//! - `&`
+//! - `<<` `>>`
+//! - `+` `-`
+//! - `*` `/` `%`
+//! - unary `-` `~` `!`
+//!
+//! Operands:
+//! - numbers: `0x1F`, `$1F`, `31`
+//! - registers: `r0`-`r15`, `sp`, `lr`, `pc`, `cpsr`
+//! - memory: `[addr]` / `w[addr]` (word), `h[addr]` (halfword), `b[addr]` (byte)
+//!
+//! Memory operands are peeked at without going through the bus, so only RAM
+//! can be read, with addresses aligned down to the access width.
+//!
+//! All arithmetic wraps at 32 bits, comparisons are unsigned and yield 0 or 1.
+use crate::cpu::arm_cpu::{CpuType, REG_LR, REG_PC, REG_SP, Reg};
+use crate::emulator::Emulator;
+
+#[derive(Debug, snafu::Snafu)]
+#[snafu(visibility(pub))]
+pub enum ExprError {
+    /// Unexpected character in the source text.
+    #[snafu(display("Unexpected character '{ch}' at {pos}"))]
+    UnexpectedChar { ch: char, pos: usize },
+
+    /// Number literal does not fit in 32 bits or has no digits.
+    #[snafu(display("Invalid number '{text}'"))]
+    InvalidNumber { text: String },
+
+    /// Identifier is neither a register nor a memory accessor.
+    #[snafu(display("Unknown identifier '{name}'"))]
+    UnknownIdent { name: String },
+
+    /// Token stream does not form a valid expression.
+    #[snafu(display("Unexpected {found} at {pos}"))]
+    UnexpectedToken { found: String, pos: usize },
+
+    /// `/` or `%` by zero while evaluating.
+    #[snafu(display("Division by zero"))]
+    DivisionByZero,
+
+    /// Memory operand outside RAM, e.g. an I/O register.
+    #[snafu(display("Cannot read {addr:#010X} without side effects"))]
+    UnreadableMemory { addr: u32 },
+}
+
+/// Memory access width of `[..]`, `h[..]` and `b[..]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Width {
+    Byte,
+    Half,
+    Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnOp {
+    Neg,
+    Not,
+    LogicalNot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    LogicalOr,
+    LogicalAnd,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Or,
+    Xor,
+    And,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Const(u32),
+    Reg(Reg),
+    Cpsr,
+    Mem(Width, Box<Node>),
+    Unary(UnOp, Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Num(u32),
+    Ident(String),
+    Op(&'static str),
+    End,
+}
+
+/// Operators sorted so that two-character ones are matched first.
+const OPERATORS: [&str; 24] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "<", ">", "|", "^", "&", "+", "-", "*", "/",
+    "%", "~", "!", "(", ")", "[", "]",
+];
+
+fn tokenize(src: &str) -> Result<Vec<(Token, usize)>, ExprError> {
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    'outer: while pos < bytes.len() {
+        let ch = bytes[pos] as char;
+        if ch.is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        }
+
+        if ch.is_ascii_digit() || ch == '$' {
+            let start = pos;
+            pos += 1;
+            while pos < bytes.len() && (bytes[pos] as char).is_ascii_alphanumeric() {
+                pos += 1;
+            }
+            let text = &src[start..pos];
+            let parsed = if let Some(hex) = text.strip_prefix('$') {
+                u32::from_str_radix(hex, 16)
+            } else if let Some(hex) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+                u32::from_str_radix(hex, 16)
+            } else {
+                text.parse()
+            };
+            let value = parsed.map_err(|_| ExprError::InvalidNumber {
+                text: text.to_string(),
+            })?;
+            tokens.push((Token::Num(value), start));
+            continue;
+        }
+
+        if ch.is_ascii_alphabetic() || ch == '_' {
+            let start = pos;
+            while pos < bytes.len()
+                && ((bytes[pos] as char).is_ascii_alphanumeric() || bytes[pos] == b'_')
+            {
+                pos += 1;
+            }
+            tokens.push((Token::Ident(src[start..pos].to_ascii_lowercase()), start));
+            continue;
+        }
+
+        for op in OPERATORS {
+            if src[pos..].starts_with(op) {
+                tokens.push((Token::Op(op), pos));
+                pos += op.len();
+                continue 'outer;
+            }
+        }
+
+        return Err(ExprError::UnexpectedChar { ch, pos });
+    }
+
+    tokens.push((Token::End, src.len()));
+    Ok(tokens)
+}
+
+/// Binary operators grouped by precedence level, lowest first.
+const BINARY_LEVELS: [&[(&str, BinOp)]; 9] = [
+    &[("||", BinOp::LogicalOr)],
+    &[("&&", BinOp::LogicalAnd)],
+    &[
+        ("==", BinOp::Eq),
+        ("!=", BinOp::Ne),
+        ("<", BinOp::Lt),
+        ("<=", BinOp::Le),
+        (">", BinOp::Gt),
+        (">=", BinOp::Ge),
+    ],
+    &[("|", BinOp::Or)],
+    &[("^", BinOp::Xor)],
+    &[("&", BinOp::And)],
+    &[("<<", BinOp::Shl), (">>", BinOp::Shr)],
+    &[("+", BinOp::Add), ("-", BinOp::Sub)],
+    &[("*", BinOp::Mul), ("/", BinOp::Div), ("%", BinOp::Rem)],
+];
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.index].0
+    }
+
+    fn next(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.index].clone();
+        if self.index + 1 < self.tokens.len() {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn unexpected(&self) -> ExprError {
+        let (token, pos) = &self.tokens[self.index];
+        let found = match token {
+            Token::Num(value) => format!("number {value:#X}"),
+            Token::Ident(name) => format!("'{name}'"),
+            Token::Op(op) => format!("'{op}'"),
+            Token::End => "end of expression".to_string(),
+        };
+        ExprError::UnexpectedToken { found, pos: *pos }
+    }
+
+    fn expect(&mut self, op: &'static str) -> Result<(), ExprError> {
+        if *self.peek() == Token::Op(op) {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Node, ExprError> {
+        if level == BINARY_LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level + 1)?;
+        'outer: loop {
+            for &(text, op) in BINARY_LEVELS[level] {
+                if *self.peek() == Token::Op(text) {
+                    self.next();
+                    let rhs = self.binary(level + 1)?;
+                    lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+                    continue 'outer;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, ExprError> {
+        let op = match self.peek() {
+            Token::Op("-") => UnOp::Neg,
+            Token::Op("~") => UnOp::Not,
+            Token::Op("!") => UnOp::LogicalNot,
+            _ => return self.primary(),
+        };
+        self.next();
+        Ok(Node::Unary(op, Box::new(self.unary()?)))
+    }
+
+    fn memory(&mut self, width: Width) -> Result<Node, ExprError> {
+        self.expect("[")?;
+        let addr = self.binary(0)?;
+        self.expect("]")?;
+        Ok(Node::Mem(width, Box::new(addr)))
+    }
+
+    fn primary(&mut self) -> Result<Node, ExprError> {
+        match self.peek().clone() {
+            Token::Num(value) => {
+                self.next();
+                Ok(Node::Const(value))
+            }
+            Token::Op("(") => {
+                self.next();
+                let node = self.binary(0)?;
+                self.expect(")")?;
+                Ok(node)
+            }
+            Token::Op("[") => self.memory(Width::Word),
+            Token::Ident(name) => {
+                self.next();
+                let width = match name.as_str() {
+                    "b" => Some(Width::Byte),
+                    "h" => Some(Width::Half),
+                    "w" => Some(Width::Word),
+                    _ => None,
+                };
+                if let Some(width) = width
+                    && *self.peek() == Token::Op("[")
+                {
+                    return self.memory(width);
+                }
+
+                match name.as_str() {
+                    "sp" => Ok(Node::Reg(REG_SP)),
+                    "lr" => Ok(Node::Reg(REG_LR)),
+                    "pc" => Ok(Node::Reg(REG_PC)),
+                    "cpsr" => Ok(Node::Cpsr),
+                    _ => match name.strip_prefix('r').and_then(|id| id.parse::<u32>().ok()) {
+                        Some(id) if id < 16 => Ok(Node::Reg(Reg::new(id))),
+                        _ => Err(ExprError::UnknownIdent { name }),
+                    },
+                }
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+}
+
+/// Parsed watch expression.
+///
+/// Parse once with [`WatchExpr::parse`] and evaluate as often as needed, e.g.
+/// every step for a conditional breakpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchExpr {
+    source: String,
+    root: Node,
+}
+
+impl WatchExpr {
+    /// Parse an expression.
+    ///
+    /// # Errors
+    /// If `src` is not a valid expression.
+    pub fn parse(src: &str) -> Result<Self, ExprError> {
+        let mut parser = Parser {
+            tokens: tokenize(src)?,
+            index: 0,
+        };
+        let root = parser.binary(0)?;
+        if *parser.peek() != Token::End {
+            return Err(parser.unexpected());
+        }
+
+        Ok(Self {
+            source: src.to_string(),
+            root,
+        })
+    }
+
+    /// Source text the expression was parsed from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluate against the registers and address space of `cpu_type`,
+    /// leaving the emulator untouched.
+    ///
+    /// # Errors
+    /// On division by zero, or a memory operand outside RAM.
+    pub fn eval(&self, emu: &Emulator, cpu_type: CpuType) -> Result<u32, ExprError> {
+        eval_node(&self.root, emu, cpu_type)
+    }
+
+    /// Evaluate as a breakpoint condition: any non-zero result is true.
+    ///
+    /// # Errors
+    /// On division by zero, or a memory operand outside RAM.
+    pub fn eval_condition(&self, emu: &Emulator, cpu_type: CpuType) -> Result<bool, ExprError> {
+        Ok(self.eval(emu, cpu_type)? != 0)
+    }
+}
+
+impl core::str::FromStr for WatchExpr {
+    type Err = ExprError;
+
+    fn from_str(src: &str) -> Result<Self, Self::Err> {
+        Self::parse(src)
+    }
+}
+
+impl core::fmt::Display for WatchExpr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+fn eval_node(node: &Node, emu: &Emulator, cpu_type: CpuType) -> Result<u32, ExprError> {
+    Ok(match node {
+        Node::Const(value) => *value,
+        Node::Reg(reg) => emu.get_cpu(cpu_type).get_register(*reg),
+        Node::Cpsr => emu.get_cpu(cpu_type).get_cpsr().get(),
+        Node::Mem(width, addr) => {
+            let addr = eval_node(addr, emu, cpu_type)?;
+            let value = match width {
+                Width::Byte => emu
+                    .page_read(cpu_type, addr)
+                    .map(|b| u8::from_le_bytes(b) as u32),
+                Width::Half => emu
+                    .page_read(cpu_type, addr)
+                    .map(|b| u16::from_le_bytes(b) as u32),
+                Width::Word => emu.page_read(cpu_type, addr).map(u32::from_le_bytes),
+            };
+            value.ok_or(ExprError::UnreadableMemory { addr })?
+        }
+        Node::Unary(op, operand) => {
+            let value = eval_node(operand, emu, cpu_type)?;
+            match op {
+                UnOp::Neg => value.wrapping_neg(),
+                UnOp::Not => !value,
+                UnOp::LogicalNot => (value == 0) as u32,
+            }
+        }
+        Node::Binary(BinOp::LogicalOr, lhs, rhs) => {
+            (eval_node(lhs, emu, cpu_type)? != 0 || eval_node(rhs, emu, cpu_type)? != 0) as u32
+        }
+        Node::Binary(BinOp::LogicalAnd, lhs, rhs) => {
+            (eval_node(lhs, emu, cpu_type)? != 0 && eval_node(rhs, emu, cpu_type)? != 0) as u32
+        }
+        Node::Binary(op, lhs, rhs) => {
+            let lhs = eval_node(lhs, emu, cpu_type)?;
+            let rhs = eval_node(rhs, emu, cpu_type)?;
+            match op {
+                BinOp::Eq => (lhs == rhs) as u32,
+                BinOp::Ne => (lhs != rhs) as u32,
+                BinOp::Lt => (lhs < rhs) as u32,
+                BinOp::Le => (lhs <= rhs) as u32,
+                BinOp::Gt => (lhs > rhs) as u32,
+                BinOp::Ge => (lhs >= rhs) as u32,
+                BinOp::Or => lhs | rhs,
+                BinOp::Xor => lhs ^ rhs,
+                BinOp::And => lhs & rhs,
+                BinOp::Shl => lhs.checked_shl(rhs).unwrap_or(0),
+                BinOp::Shr => lhs.checked_shr(rhs).unwrap_or(0),
+                BinOp::Add => lhs.wrapping_add(rhs),
+                BinOp::Sub => lhs.wrapping_sub(rhs),
+                BinOp::Mul => lhs.wrapping_mul(rhs),
+                BinOp::Div => lhs.checked_div(rhs).ok_or(ExprError::DivisionByZero)?,
+                BinOp::Rem => lhs.checked_rem(rhs).ok_or(ExprError::DivisionByZero)?,
+                BinOp::LogicalOr | BinOp::LogicalAnd => unreachable!(),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precedence_and_registers() {
+        let mut emu = Emulator::new();
+        emu.arm9.set_register(Reg::new(3), 5);
+        emu.arm9.set_register(REG_SP, 0x0200_0000);
+
+        let expr = WatchExpr::parse("1 + r3 * 2 << 1").unwrap();
+        assert_eq!(expr.eval(&emu, CpuType::Arm9).unwrap(), 22);
+
+        let expr = WatchExpr::parse("sp == $2000000 && !(r3 < 5)").unwrap();
+        assert!(expr.eval_condition(&emu, CpuType::Arm9).unwrap());
+    }
+
+    #[test]
+    fn test_memory_operands() {
+        let mut emu = Emulator::new();
+        emu.power_on();
+        emu.arm7_write_word(0x0200_0F00, 0x1234_5678);
+        emu.arm7.set_register(Reg::new(3), 2);
+
+        let expr = WatchExpr::parse("[0x02000F00]+r3*2").unwrap();
+        assert_eq!(expr.eval(&emu, CpuType::Arm7).unwrap(), 0x1234_567C);
+
+        let expr = WatchExpr::parse("h[0x02000F02] | b[0x02000F00 + 1]").unwrap();
+        assert_eq!(expr.eval(&emu, CpuType::Arm7).unwrap(), 0x1234 | 0x56);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            WatchExpr::parse("r16"),
+            Err(ExprError::UnknownIdent { .. })
+        ));
+        assert!(matches!(
+            WatchExpr::parse("[r0"),
+            Err(ExprError::UnexpectedToken { .. })
+        ));
+        assert!(matches!(
+            WatchExpr::parse("0x1FFFFFFFF"),
+            Err(ExprError::InvalidNumber { .. })
+        ));
+
+        let mut emu = Emulator::new();
+        let expr = WatchExpr::parse("1 / r0").unwrap();
+        assert!(matches!(
+            expr.eval(&emu, CpuType::Arm7),
+            Err(ExprError::DivisionByZero)
+        ));
+
+        // Peeking leaves the IPC FIFO alone
+        emu.power_on();
+        emu.arm9_write_halfword(0x0400_0184, 0x8000);
+        emu.arm7_write_halfword(0x0400_0184, 0x8000);
+        emu.arm9_write_word(0x0400_0188, 0x1234_5678);
+        let expr = WatchExpr::parse("[0x04100000]").unwrap();
+        assert!(matches!(
+            expr.eval(&emu, CpuType::Arm7),
+            Err(ExprError::UnreadableMemory { addr: 0x0410_0000 })
+        ));
+        assert_eq!(emu.arm7_read_word(0x0410_0000), 0x1234_5678);
+    }
+}
@@ -0,0 +1,464 @@
+//! Memory map annotations for reverse engineering tools.
+//!
+//! Collects the memory regions, named IO registers and the overlays of the
+//! loaded ROM for one CPU, and writes them as a Ghidra
+//! `ImportSymbolsScript.py` symbol list or as an IDA IDC script.
+use core::fmt::Write as _;
+
+use crate::cpu::arm_cpu::CpuType;
+use crate::emulator::Emulator;
+
+/// What an annotation describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapEntryKind {
+    /// Memory block, e.g. main RAM
+    Region,
+    /// Memory mapped register
+    Register,
+    /// Code loaded from the ROM (ARM binary or overlay)
+    Code,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapEntry {
+    pub kind: MapEntryKind,
+    pub name: String,
+    pub address: u32,
+    pub size: u32,
+    pub comment: String,
+}
+
+/// Output format of [`MemoryMap::export`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapFormat {
+    /// `name address type` lines for Ghidra's `ImportSymbolsScript.py`
+    GhidraSymbols,
+    /// IDC script creating segments, names and comments
+    Idc,
+}
+
+/// Annotations of one CPU's address space.
+#[derive(Debug, Clone)]
+pub struct MemoryMap {
+    pub cpu: CpuType,
+    pub entries: Vec<MapEntry>,
+}
+
+/// `(start, size, name, writable)`; the IDC export uses `writable` for the
+/// segment permissions.
+type Region = (u32, u32, &'static str, bool);
+
+const REGIONS_ARM9: &[Region] = &[
+    (0x0000_0000, 0x8000, "ITCM", true),
+    (0x0200_0000, 0x40_0000, "MAIN_RAM", true),
+    (0x0300_0000, 0x8000, "SHARED_WRAM", true),
+    (0x0400_0000, 0x1_0000, "IO", true),
+    (0x0410_0000, 0x20, "IO_FIFO", true),
+    (0x0500_0000, 0x800, "PALETTE", true),
+    (0x0600_0000, 0x8_0000, "VRAM_BG_A", true),
+    (0x0620_0000, 0x2_0000, "VRAM_BG_B", true),
+    (0x0640_0000, 0x4_0000, "VRAM_OBJ_A", true),
+    (0x0660_0000, 0x2_0000, "VRAM_OBJ_B", true),
+    (0x0680_0000, 0xA_4000, "VRAM_LCDC", true),
+    (0x0700_0000, 0x800, "OAM", true),
+    (0x0800_0000, 0x200_0000, "GBA_ROM", false),
+    (0x0A00_0000, 0x1_0000, "GBA_RAM", true),
+    (0xFFFF_0000, 0x1000, "BIOS9", false),
+];
+
+const REGIONS_ARM7: &[Region] = &[
+    (0x0000_0000, 0x4000, "BIOS7", false),
+    (0x0200_0000, 0x40_0000, "MAIN_RAM", true),
+    (0x0300_0000, 0x8000, "SHARED_WRAM", true),
+    (0x0380_0000, 0x1_0000, "ARM7_WRAM", true),
+    (0x0400_0000, 0x1_0000, "IO", true),
+    (0x0410_0000, 0x20, "IO_FIFO", true),
+    (0x0480_0000, 0x1_0000, "WIFI", true),
+    (0x0600_0000, 0x4_0000, "VRAM_ARM7", true),
+    (0x0800_0000, 0x200_0000, "GBA_ROM", false),
+    (0x0A00_0000, 0x1_0000, "GBA_RAM", true),
+];
+
+/// `(address, width in bytes, name)`
+type Register = (u32, u32, &'static str);
+
+/// Registers present on both CPUs.
+const REGISTERS_COMMON: &[Register] = &[
+    (0x0400_0004, 2, "DISPSTAT"),
+    (0x0400_0006, 2, "VCOUNT"),
+    (0x0400_00B0, 4, "DMA0SAD"),
+    (0x0400_00B4, 4, "DMA0DAD"),
+    (0x0400_00B8, 4, "DMA0CNT"),
+    (0x0400_00BC, 4, "DMA1SAD"),
+    (0x0400_00C0, 4, "DMA1DAD"),
+    (0x0400_00C4, 4, "DMA1CNT"),
+    (0x0400_00C8, 4, "DMA2SAD"),
+    (0x0400_00CC, 4, "DMA2DAD"),
+    (0x0400_00D0, 4, "DMA2CNT"),
+    (0x0400_00D4, 4, "DMA3SAD"),
+    (0x0400_00D8, 4, "DMA3DAD"),
+    (0x0400_00DC, 4, "DMA3CNT"),
+    (0x0400_0100, 2, "TM0CNT_L"),
+    (0x0400_0102, 2, "TM0CNT_H"),
+    (0x0400_0104, 2, "TM1CNT_L"),
+    (0x0400_0106, 2, "TM1CNT_H"),
+    (0x0400_0108, 2, "TM2CNT_L"),
+    (0x0400_010A, 2, "TM2CNT_H"),
+    (0x0400_010C, 2, "TM3CNT_L"),
+    (0x0400_010E, 2, "TM3CNT_H"),
+    (0x0400_0130, 2, "KEYINPUT"),
+    (0x0400_0132, 2, "KEYCNT"),
+    (0x0400_0180, 4, "IPCSYNC"),
+    (0x0400_0184, 2, "IPCFIFOCNT"),
+    (0x0400_0188, 4, "IPCFIFOSEND"),
+    (0x0400_01A0, 2, "AUXSPICNT"),
+    (0x0400_01A2, 2, "AUXSPIDATA"),
+    (0x0400_01A4, 4, "ROMCTRL"),
+    (0x0400_01A8, 8, "CARD_COMMAND"),
+    (0x0400_01B0, 4, "CARD_1B0"),
+    (0x0400_01B4, 4, "CARD_1B4"),
+    (0x0400_01B8, 2, "CARD_1B8"),
+    (0x0400_01BA, 2, "CARD_1BA"),
+    (0x0400_0204, 2, "EXMEMCNT"),
+    (0x0400_0208, 4, "IME"),
+    (0x0400_0210, 4, "IE"),
+    (0x0400_0214, 4, "IF"),
+    (0x0400_0300, 1, "POSTFLG"),
+    (0x0410_0000, 4, "IPCFIFORECV"),
+    (0x0410_0010, 4, "CARD_DATA_RD"),
+];
+
+const REGISTERS_ARM9: &[Register] = &[
+    (0x0400_0000, 4, "DISPCNT"),
+    (0x0400_0008, 2, "BG0CNT"),
+    (0x0400_000A, 2, "BG1CNT"),
+    (0x0400_000C, 2, "BG2CNT"),
+    (0x0400_000E, 2, "BG3CNT"),
+    (0x0400_0010, 2, "BG0HOFS"),
+    (0x0400_0012, 2, "BG0VOFS"),
+    (0x0400_0040, 2, "WIN0H"),
+    (0x0400_0048, 2, "WININ"),
+    (0x0400_004A, 2, "WINOUT"),
+    (0x0400_004C, 2, "MOSAIC"),
+    (0x0400_0050, 2, "BLDCNT"),
+    (0x0400_0052, 2, "BLDALPHA"),
+    (0x0400_0054, 2, "BLDY"),
+    (0x0400_0060, 2, "DISP3DCNT"),
+    (0x0400_0064, 4, "DISPCAPCNT"),
+    (0x0400_0068, 4, "DISP_MMEM_FIFO"),
+    (0x0400_006C, 2, "MASTER_BRIGHT"),
+    (0x0400_00E0, 16, "DMA_FILL"),
+    (0x0400_0240, 1, "VRAMCNT_A"),
+    (0x0400_0241, 1, "VRAMCNT_B"),
+    (0x0400_0242, 1, "VRAMCNT_C"),
+    (0x0400_0243, 1, "VRAMCNT_D"),
+    (0x0400_0244, 1, "VRAMCNT_E"),
+    (0x0400_0245, 1, "VRAMCNT_F"),
+    (0x0400_0246, 1, "VRAMCNT_G"),
+    (0x0400_0247, 1, "WRAMCNT"),
+    (0x0400_0248, 1, "VRAMCNT_H"),
+    (0x0400_0249, 1, "VRAMCNT_I"),
+    (0x0400_0280, 2, "DIVCNT"),
+    (0x0400_0290, 8, "DIV_NUMER"),
+    (0x0400_0298, 8, "DIV_DENOM"),
+    (0x0400_02A0, 8, "DIV_RESULT"),
+    (0x0400_02A8, 8, "DIVREM_RESULT"),
+    (0x0400_02B0, 2, "SQRTCNT"),
+    (0x0400_02B4, 4, "SQRT_RESULT"),
+    (0x0400_02B8, 8, "SQRT_PARAM"),
+    (0x0400_0304, 2, "POWCNT1"),
+    (0x0400_0320, 2, "RDLINES_COUNT"),
+    (0x0400_0330, 16, "EDGE_COLOR"),
+    (0x0400_0340, 1, "ALPHA_TEST_REF"),
+    (0x0400_0350, 4, "CLEAR_COLOR"),
+    (0x0400_0354, 2, "CLEAR_DEPTH"),
+    (0x0400_0356, 2, "CLRIMAGE_OFFSET"),
+    (0x0400_0358, 4, "FOG_COLOR"),
+    (0x0400_035C, 2, "FOG_OFFSET"),
+    (0x0400_0360, 32, "FOG_TABLE"),
+    (0x0400_0380, 64, "TOON_TABLE"),
+    (0x0400_0400, 64, "GXFIFO"),
+    (0x0400_0600, 4, "GXSTAT"),
+    (0x0400_0604, 4, "RAM_COUNT"),
+    (0x0400_0610, 2, "DISP_1DOT_DEPTH"),
+    (0x0400_0620, 16, "POS_RESULT"),
+    (0x0400_0630, 6, "VEC_RESULT"),
+    (0x0400_0640, 64, "CLIPMTX_RESULT"),
+    (0x0400_0680, 36, "VECMTX_RESULT"),
+    (0x0400_1000, 4, "DB_DISPCNT"),
+    (0x0400_106C, 2, "DB_MASTER_BRIGHT"),
+];
+
+const REGISTERS_ARM7: &[Register] = &[
+    (0x0400_0134, 2, "RCNT"),
+    (0x0400_0136, 2, "EXTKEYIN"),
+    (0x0400_0138, 1, "RTC"),
+    (0x0400_01C0, 2, "SPICNT"),
+    (0x0400_01C2, 2, "SPIDATA"),
+    (0x0400_0241, 1, "WRAMSTAT"),
+    (0x0400_0301, 1, "HALTCNT"),
+    (0x0400_0304, 2, "POWCNT2"),
+    (0x0400_0308, 4, "BIOSPROT"),
+    (0x0400_0500, 2, "SOUNDCNT"),
+    (0x0400_0504, 2, "SOUNDBIAS"),
+    (0x0400_0508, 1, "SNDCAP0CNT"),
+    (0x0400_0509, 1, "SNDCAP1CNT"),
+    (0x0400_0510, 4, "SNDCAP0DAD"),
+    (0x0400_0514, 2, "SNDCAP0LEN"),
+    (0x0400_0518, 4, "SNDCAP1DAD"),
+    (0x0400_051C, 2, "SNDCAP1LEN"),
+    (0x0480_8000, 2, "W_ID"),
+    (0x0480_8004, 2, "W_MODE_RST"),
+    (0x0480_8010, 2, "W_IF"),
+    (0x0480_8012, 2, "W_IE"),
+    (0x0480_8036, 2, "W_POWER_US"),
+    (0x0480_803C, 2, "W_POWERSTATE"),
+    (0x0480_8040, 2, "W_POWERFORCE"),
+    (0x0480_80E8, 2, "W_US_COUNTCNT"),
+    (0x0480_80EA, 2, "W_US_COMPARECNT"),
+    (0x0480_80F0, 8, "W_US_COMPARE"),
+    (0x0480_80F8, 8, "W_US_COUNT"),
+    (0x0480_8158, 2, "W_BB_CNT"),
+    (0x0480_815A, 2, "W_BB_WRITE"),
+    (0x0480_815C, 2, "W_BB_READ"),
+    (0x0480_815E, 2, "W_BB_BUSY"),
+    (0x0480_8184, 2, "W_RF_CNT"),
+];
+
+impl MemoryMap {
+    /// Fixed regions and registers of `cpu`, without ROM specific entries.
+    pub fn base(cpu: CpuType) -> Self {
+        let (regions, registers) = match cpu {
+            CpuType::Arm9 => (REGIONS_ARM9, REGISTERS_ARM9),
+            CpuType::Arm7 => (REGIONS_ARM7, REGISTERS_ARM7),
+        };
+
+        let mut entries: Vec<MapEntry> = regions
+            .iter()
+            .map(|&(address, size, name, writable)| MapEntry {
+                kind: MapEntryKind::Region,
+                name: name.to_string(),
+                address,
+                size,
+                comment: if writable {
+                    String::new()
+                } else {
+                    "read-only".to_string()
+                },
+            })
+            .collect();
+        entries.extend(
+            REGISTERS_COMMON
+                .iter()
+                .chain(registers)
+                .map(|&(address, size, name)| MapEntry {
+                    kind: MapEntryKind::Register,
+                    name: format!("REG_{name}"),
+                    address,
+                    size,
+                    comment: String::new(),
+                }),
+        );
+
+        let mut map = Self { cpu, entries };
+        map.sort();
+        map
+    }
+
+    fn sort(&mut self) {
+        self.entries
+            .sort_by_key(|entry| (entry.address, entry.kind != MapEntryKind::Region));
+    }
+
+    /// Entries of one kind.
+    pub fn entries_of(&self, kind: MapEntryKind) -> impl Iterator<Item = &MapEntry> {
+        self.entries.iter().filter(move |entry| entry.kind == kind)
+    }
+
+    /// Write the map in `format`.
+    pub fn export(&self, format: MapFormat) -> String {
+        match format {
+            MapFormat::GhidraSymbols => self.to_ghidra_symbols(),
+            MapFormat::Idc => self.to_idc(),
+        }
+    }
+
+    fn to_ghidra_symbols(&self) -> String {
+        // ImportSymbolsScript.py: "<name> <address> [f|l]"; all entries are labels
+        let mut out = String::new();
+        for entry in &self.entries {
+            let _ = writeln!(
+                out,
+                "{} 0x{:08X} l",
+                symbol_name(&entry.name),
+                entry.address
+            );
+        }
+        out
+    }
+
+    fn to_idc(&self) -> String {
+        let mut out = String::from("#include <idc.idc>\n\nstatic main() {\n");
+        for entry in &self.entries {
+            let name = symbol_name(&entry.name);
+            let comment = escape(&entry.comment);
+            match entry.kind {
+                MapEntryKind::Region => {
+                    let end = entry.address as u64 + entry.size as u64;
+                    let perm = if entry.comment == "read-only" { 5 } else { 7 };
+                    let _ = writeln!(
+                        out,
+                        "    AddSeg(0x{:08X}, 0x{end:08X}, 0, 1, saRelByte, scPub);\n    \
+                         set_segm_name(0x{:08X}, \"{name}\");\n    \
+                         set_segm_attr(0x{:08X}, SEGATTR_PERM, {perm});",
+                        entry.address, entry.address, entry.address
+                    );
+                }
+                MapEntryKind::Register | MapEntryKind::Code => {
+                    let _ = writeln!(
+                        out,
+                        "    set_name(0x{:08X}, \"{name}\", SN_NOCHECK | SN_NOWARN);",
+                        entry.address
+                    );
+                }
+            }
+            if !comment.is_empty() {
+                let _ = writeln!(
+                    out,
+                    "    set_cmt(0x{:08X}, \"{comment}\", 1);",
+                    entry.address
+                );
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Symbol names must not contain whitespace.
+fn symbol_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl Emulator {
+    /// Memory map of `cpu` including the loaded ROM's binaries and overlays.
+    ///
+    /// Overlays sharing a load address are all listed; which one is resident
+    /// at a given time is up to the game.
+    pub fn memory_map(&self, cpu: CpuType) -> MemoryMap {
+        let mut map = MemoryMap::base(cpu);
+
+        if cpu == CpuType::Arm9 {
+            let dtcm_size = self.arm9_cp15.get_dtcm_size();
+            if dtcm_size != 0 {
+                map.entries.push(MapEntry {
+                    kind: MapEntryKind::Region,
+                    name: "DTCM".to_string(),
+                    address: self.arm9_cp15.get_dtcm_base(),
+                    size: dtcm_size,
+                    comment: "current CP15 setting".to_string(),
+                });
+            }
+        }
+
+        let header = self.cart.header();
+        let (rom_offset, ram_address, size, entry, label) = match cpu {
+            CpuType::Arm9 => (
+                header.arm9_rom_offset,
+                header.arm9_ram_address,
+                header.arm9_size,
+                header.arm9_entry,
+                "arm9",
+            ),
+            CpuType::Arm7 => (
+                header.arm7_rom_offset,
+                header.arm7_ram_address,
+                header.arm7_size,
+                header.arm7_entry,
+                "arm7",
+            ),
+        };
+        if size != 0 {
+            map.entries.push(MapEntry {
+                kind: MapEntryKind::Code,
+                name: format!("{label}_static"),
+                address: ram_address,
+                size,
+                comment: format!("{label} binary, ROM 0x{rom_offset:08X}"),
+            });
+            map.entries.push(MapEntry {
+                kind: MapEntryKind::Code,
+                name: format!("{label}_entry"),
+                address: entry,
+                size: 0,
+                comment: String::new(),
+            });
+        }
+
+        for overlay in self.cart.overlays().iter().filter(|o| o.cpu == cpu) {
+            let rom = overlay.rom.map_or_else(
+                || "missing FAT entry".to_string(),
+                |fat| format!("ROM 0x{:08X}..0x{:08X}", fat.start, fat.end),
+            );
+            map.entries.push(MapEntry {
+                kind: MapEntryKind::Code,
+                name: format!("{label}_overlay_{}", overlay.id),
+                address: overlay.ram_address,
+                size: overlay.ram_end().wrapping_sub(overlay.ram_address),
+                comment: format!(
+                    "overlay {} (file {}), {rom}, bss 0x{:X}",
+                    overlay.id, overlay.file_id, overlay.bss_size
+                ),
+            });
+        }
+
+        map.sort();
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::nitro::tests::test_rom;
+
+    #[test]
+    fn test_export_overlays() {
+        let mut emu = Box::new(Emulator::new());
+        emu.cart.rom = test_rom();
+
+        let map = emu.memory_map(CpuType::Arm9);
+        let code: Vec<_> = map.entries_of(MapEntryKind::Code).collect();
+        assert_eq!(code.len(), 3);
+        assert_eq!(code[2].name, "arm9_overlay_0");
+        assert_eq!(code[2].size, 0x30);
+
+        let ghidra = map.export(MapFormat::GhidraSymbols);
+        assert!(ghidra.contains("REG_DISPCNT 0x04000000 l\n"));
+        assert!(ghidra.contains("arm9_overlay_0 0x02100000 l\n"));
+
+        let idc = map.export(MapFormat::Idc);
+        assert!(idc.contains("set_segm_name(0x02000000, \"MAIN_RAM\");"));
+        assert!(idc.contains("ROM 0x00000380..0x00000390"));
+
+        // ARM7 view has no ARM9 overlays
+        let arm7 = emu.memory_map(CpuType::Arm7);
+        assert_eq!(arm7.entries_of(MapEntryKind::Code).count(), 0);
+        assert!(
+            arm7.export(MapFormat::GhidraSymbols)
+                .contains("REG_W_MODE_RST")
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! Debugger support shared by frontends.
+//!
+//! Everything here runs inside the core so a frontend without a GDB
+//! connection can still offer watch windows, conditional breaks and traces.
+mod activity;
+mod code_map;
+mod coverage;
+mod crash;
+mod expr;
+mod memory_map;
+mod reverse;
+mod trace;
+mod watchpoint;
+
+pub use activity::{Activity, ActivityMap};
+pub use code_map::{CodeMap, CodeMode};
+pub use coverage::Coverage;
+pub use crash::{
+    CRASH_TRACE_LENGTH, CrashReason, CrashRecorder, GuestCrash, IoAccessCount, TracedInstruction,
+};
+pub use expr::{ExprError, WatchExpr};
+pub use memory_map::{MapEntry, MapEntryKind, MapFormat, MemoryMap};
+pub use reverse::{ReverseHistory, SNAPSHOT_COUNT, SNAPSHOT_INTERVAL};
+pub use trace::{FrameTrace, TraceEvent, TraceTrack};
+pub use watchpoint::{WatchAccess, WatchHit, Watchpoint, Watchpoints};
@@ -0,0 +1,391 @@
+//! Reverse execution
+//!
+//! While enabled, [`Emulator::run`] keeps a ring of savestates taken every
+//! [`SNAPSHOT_INTERVAL`] retired instructions. Going back restores the
+//! newest snapshot before the target and re-executes forward, counting
+//! instructions, until the target is reached, so guest code can be stepped
+//! backwards the way rr does it.
+//!
+//! Each CPU counts its own instructions: stops and single steps change how
+//! the two interleave, so re-execution reaches the same instruction of the
+//! stopped CPU, but the other one may be a few instructions off. While
+//! re-executing, the frontend is detached, so nothing is drawn or played,
+//! and the buttons held now stand in for the ones held back then.
+use std::collections::VecDeque;
+
+use crate::cpu::arm_cpu::CpuType;
+use crate::emulator::Emulator;
+use crate::emulator::run_mode::RunMode;
+
+/// Instructions retired by both CPUs between two snapshots.
+pub const SNAPSHOT_INTERVAL: u64 = 1 << 20;
+/// Snapshots kept; older ones are dropped.
+pub const SNAPSHOT_COUNT: usize = 16;
+/// Re-execution giving up after this many frames without reaching its
+/// target, in case a snapshot does not replay the way it ran.
+const MAX_REPLAY_FRAMES: u32 = 600;
+
+/// Instructions retired by the ARM9 and the ARM7.
+type Retired = [u64; 2];
+
+const fn cpu_index(cpu_type: CpuType) -> usize {
+    match cpu_type {
+        CpuType::Arm9 => 0,
+        CpuType::Arm7 => 1,
+    }
+}
+
+const fn total(retired: Retired) -> u64 {
+    retired[0] + retired[1]
+}
+
+#[derive(Debug, Clone)]
+struct Snapshot {
+    retired: Retired,
+    state: Vec<u8>,
+}
+
+/// Where re-execution stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReplayStop {
+    /// Before the CPU starts the instruction with this count
+    At(CpuType, u64),
+    /// Once both CPUs retired these counts, recording the breakpoints
+    /// passed on the way
+    Scan(Retired),
+}
+
+#[derive(Debug)]
+struct Replay {
+    stop: ReplayStop,
+    /// The breakpoints, held here so they record instead of stopping
+    breakpoints: Vec<(CpuType, u32)>,
+    /// Breakpoint hits passed while scanning, with the count of the CPU
+    hits: Vec<(CpuType, u64)>,
+    /// Set once stopped: whether at the target, or past it
+    reached: Option<bool>,
+}
+
+/// Snapshots and instruction counts, see [`Emulator::set_reverse_enabled`].
+#[derive(Debug, Default)]
+pub struct ReverseHistory {
+    snapshots: VecDeque<Snapshot>,
+    retired: Retired,
+    replay: Option<Replay>,
+}
+
+impl ReverseHistory {
+    /// Instructions `cpu_type` retired since recording started.
+    pub const fn retired(&self, cpu_type: CpuType) -> u64 {
+        self.retired[cpu_index(cpu_type)]
+    }
+}
+
+impl Emulator {
+    /// Start or stop recording snapshots for reverse execution. Starting
+    /// drops the snapshots of an earlier recording.
+    pub fn set_reverse_enabled(&mut self, enabled: bool) {
+        self.reverse = enabled.then(ReverseHistory::default);
+    }
+
+    /// Go back to just before the last instruction `cpu_type` executed.
+    ///
+    /// Returns `false`, leaving the system as it was, if the instruction is
+    /// older than every snapshot or recording is disabled.
+    pub fn reverse_step_instruction(&mut self, cpu_type: CpuType) -> bool {
+        let cpu = cpu_index(cpu_type);
+        let Some(history) = &self.reverse else {
+            return false;
+        };
+        let Some(target) = history.retired[cpu].checked_sub(1) else {
+            return false;
+        };
+        let Some(index) = history
+            .snapshots
+            .iter()
+            .rposition(|snapshot| snapshot.retired[cpu] <= target)
+        else {
+            return false;
+        };
+
+        let current = self.save_reverse_position();
+        if self
+            .replay_snapshot(index, ReplayStop::At(cpu_type, target))
+            .is_some()
+        {
+            return true;
+        }
+        self.restore_reverse_position(current);
+        false
+    }
+
+    /// Go back to the last breakpoint hit before the current position,
+    /// stopping there as [`Emulator::run`] would have.
+    ///
+    /// Returns the CPU that hit it, or `None`, leaving the system as it
+    /// was, if no breakpoint was hit since the oldest snapshot.
+    pub fn reverse_continue_to_breakpoint(&mut self) -> Option<CpuType> {
+        let history = self.reverse.as_ref()?;
+        let mut end = history.retired;
+        let snapshots = history.snapshots.len();
+        let current = self.save_reverse_position();
+
+        for index in (0..snapshots).rev() {
+            let start = self.reverse.as_ref()?.snapshots[index].retired;
+            if start == end {
+                continue;
+            }
+            let Some(&(cpu_type, retired)) = self
+                .replay_snapshot(index, ReplayStop::Scan(end))
+                .as_ref()
+                .and_then(|hits| hits.last())
+            else {
+                end = start;
+                continue;
+            };
+            if self
+                .replay_snapshot(index, ReplayStop::At(cpu_type, retired))
+                .is_some()
+            {
+                return Some(cpu_type);
+            }
+            break;
+        }
+        self.restore_reverse_position(current);
+        None
+    }
+
+    /// Take a snapshot when one is due. Called between CPU slices, where
+    /// [`Emulator::run`] can pick a restored state up.
+    #[inline]
+    pub(crate) fn reverse_snapshot(&mut self) {
+        let Some(history) = &self.reverse else {
+            return;
+        };
+        if history.replay.is_some()
+            || history.snapshots.back().is_some_and(|last| {
+                total(history.retired) - total(last.retired) < SNAPSHOT_INTERVAL
+            })
+        {
+            return;
+        }
+        let state = self.save_state();
+        if let Some(history) = &mut self.reverse {
+            history.snapshots.push_back(Snapshot {
+                retired: history.retired,
+                state,
+            });
+            if history.snapshots.len() > SNAPSHOT_COUNT {
+                history.snapshots.pop_front();
+            }
+        }
+    }
+
+    /// Whether re-execution stops before `cpu_type` executes `address`.
+    #[inline]
+    pub(crate) fn check_reverse_stop(&mut self, cpu_type: CpuType, address: u32) -> bool {
+        let Some((replay, retired)) = self.reverse.as_mut().and_then(|history| {
+            let retired = history.retired;
+            history.replay.as_mut().map(|replay| (replay, retired))
+        }) else {
+            return false;
+        };
+        let count = retired[cpu_index(cpu_type)];
+        match replay.stop {
+            ReplayStop::At(cpu, target) if cpu == cpu_type && count >= target => {
+                replay.reached = Some(count == target);
+            }
+            ReplayStop::At(..) => return false,
+            ReplayStop::Scan(end) => {
+                if count < end[cpu_index(cpu_type)]
+                    && replay.breakpoints.contains(&(cpu_type, address))
+                {
+                    replay.hits.push((cpu_type, count));
+                }
+                return false;
+            }
+        }
+        self.stop_before(cpu_type, address);
+        self.watch_hit = None;
+        true
+    }
+
+    /// Count an instruction `cpu_type` executed. Stops a scan once both
+    /// CPUs got where it ends.
+    #[inline]
+    pub(crate) fn retire_instruction(&mut self, cpu_type: CpuType) {
+        let Some(history) = &mut self.reverse else {
+            return;
+        };
+        history.retired[cpu_index(cpu_type)] += 1;
+        let retired = history.retired;
+        if let Some(replay) = &mut history.replay
+            && let ReplayStop::Scan(end) = replay.stop
+            && retired[0] >= end[0]
+            && retired[1] >= end[1]
+        {
+            replay.reached = Some(true);
+            self.breakpoint_hit = Some(cpu_type);
+            self.hit_before = None;
+        }
+    }
+
+    /// Restore snapshot `index` and run until `stop`. Returns the
+    /// breakpoint hits passed, or `None` if the stop was not reached.
+    ///
+    /// The snapshots after `index` are dropped, re-execution takes them
+    /// again.
+    fn replay_snapshot(&mut self, index: usize, stop: ReplayStop) -> Option<Vec<(CpuType, u64)>> {
+        let history = self.reverse.as_mut()?;
+        history.snapshots.truncate(index + 1);
+        let snapshot = history.snapshots.get(index)?.clone();
+        history.retired = snapshot.retired;
+        history.replay = Some(Replay {
+            stop,
+            breakpoints: self.breakpoints.clone(),
+            hits: Vec::new(),
+            reached: None,
+        });
+        let loaded = self.load_state(&snapshot.state).is_ok();
+
+        // Nothing of the re-execution may reach the frontend or stop it
+        // on the way
+        let frontend = std::mem::take(&mut self.frontend);
+        let breakpoints = std::mem::take(&mut self.breakpoints);
+        let watchpoints = std::mem::take(&mut self.watchpoints);
+        let run_mode = std::mem::replace(&mut self.run_mode, RunMode::Running);
+        let completed_frame = self.completed_frame;
+        self.breakpoint_hit = None;
+        self.resume_past = None;
+
+        let mut frames = 0;
+        while loaded && frames < MAX_REPLAY_FRAMES {
+            self.run();
+            if self
+                .reverse
+                .as_ref()
+                .and_then(|history| history.replay.as_ref())
+                .is_some_and(|replay| replay.reached.is_some())
+            {
+                break;
+            }
+            self.breakpoint_hit = None;
+            self.watch_hit = None;
+            if self.take_completed_frame() {
+                frames += 1;
+            }
+        }
+
+        self.frontend = frontend;
+        self.breakpoints = breakpoints;
+        self.watchpoints = watchpoints;
+        self.run_mode = run_mode;
+        self.completed_frame = completed_frame;
+        // Resuming from where the replay stopped runs that instruction
+        self.take_breakpoint_hit();
+        self.watch_hit = None;
+        let replay = self.reverse.as_mut()?.replay.take()?;
+        (replay.reached == Some(true)).then_some(replay.hits)
+    }
+
+    /// The current state with its counts, taken before going back.
+    fn save_reverse_position(&self) -> Option<Snapshot> {
+        self.reverse.as_ref().map(|history| Snapshot {
+            retired: history.retired,
+            state: self.save_state(),
+        })
+    }
+
+    /// Go back to where a failed reverse execution started.
+    fn restore_reverse_position(&mut self, current: Option<Snapshot>) {
+        let Some(current) = current else {
+            return;
+        };
+        // The state was just saved by this version, so it loads
+        let _ = self.load_state(&current.state);
+        if let Some(history) = &mut self.reverse {
+            history.retired = current.retired;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Power on with the ARM7 at `code` in main RAM.
+    fn boot_arm7(code: &[u32]) -> Box<Emulator> {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        for (i, opcode) in code.iter().enumerate() {
+            emu.main_ram[i * 4..i * 4 + 4].copy_from_slice(&opcode.to_le_bytes());
+        }
+        emu.arm7.jp(0x0200_0000, false);
+        emu.set_reverse_enabled(true);
+        emu
+    }
+
+    fn run_to_breakpoint(emu: &mut Emulator) {
+        while !emu.run() || emu.breakpoint_hit.is_none() {}
+        assert_eq!(emu.take_breakpoint_hit(), Some(CpuType::Arm7));
+    }
+
+    #[test]
+    fn test_reverse_step_instruction() {
+        let mut emu = boot_arm7(&[
+            0xE280_0001, // add r0, r0, #1
+            0xE280_0001, // add r0, r0, #1
+            0xEAFF_FFFE, // b .
+        ]);
+        emu.arm7.regs[0] = 5;
+        emu.add_breakpoint(CpuType::Arm7, 0x0200_0008);
+        run_to_breakpoint(&mut emu);
+        assert_eq!(emu.arm7.regs[0], 7);
+        emu.remove_breakpoint(CpuType::Arm7, 0x0200_0008);
+
+        // Back over both adds, one at a time
+        assert!(emu.reverse_step_instruction(CpuType::Arm7));
+        assert_eq!(emu.arm7.regs[0], 6);
+        assert_eq!(emu.arm7.get_pc(), 0x0200_0008);
+        assert!(emu.reverse_step_instruction(CpuType::Arm7));
+        assert_eq!(emu.arm7.regs[0], 5);
+        assert_eq!(emu.arm7.get_pc(), 0x0200_0004);
+        assert_eq!(emu.take_breakpoint_hit(), None);
+
+        // Running forward again redoes the writes
+        emu.add_breakpoint(CpuType::Arm7, 0x0200_0008);
+        run_to_breakpoint(&mut emu);
+        assert_eq!(emu.arm7.regs[0], 7);
+    }
+
+    #[test]
+    fn test_reverse_continue_to_breakpoint() {
+        let mut emu = boot_arm7(&[
+            0xE280_0001, // add r0, r0, #1
+            0xEAFF_FFFD, // b 0x02000000
+        ]);
+        emu.arm7.regs[0] = 0;
+        emu.add_breakpoint(CpuType::Arm7, 0x0200_0004);
+        run_to_breakpoint(&mut emu);
+        assert_eq!(emu.arm7.regs[0], 1);
+        assert_eq!(emu.reverse_continue_to_breakpoint(), None);
+        assert_eq!(emu.arm7.regs[0], 1);
+
+        // Step over the breakpoint and hit it twice more
+        for expected in [2, 3] {
+            emu.remove_breakpoint(CpuType::Arm7, 0x0200_0004);
+            emu.execute(CpuType::Arm7);
+            emu.add_breakpoint(CpuType::Arm7, 0x0200_0004);
+            run_to_breakpoint(&mut emu);
+            assert_eq!(emu.arm7.regs[0], expected);
+        }
+
+        assert_eq!(emu.reverse_continue_to_breakpoint(), Some(CpuType::Arm7));
+        assert_eq!(emu.arm7.regs[0], 2);
+        assert_eq!(emu.arm7.get_pc(), 0x0200_0008);
+        assert_eq!(emu.reverse_continue_to_breakpoint(), Some(CpuType::Arm7));
+        assert_eq!(emu.arm7.regs[0], 1);
+        assert_eq!(emu.reverse_continue_to_breakpoint(), None);
+        assert_eq!(emu.arm7.regs[0], 1);
+    }
+}
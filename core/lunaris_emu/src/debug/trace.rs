@@ -0,0 +1,283 @@
+//! Frame event trace
+//!
+//! Records scheduler events, DMA transfers, IRQ requests and CPU slice
+//! boundaries of a frame and exports them in the Chrome trace event format,
+//! which can be opened with `chrome://tracing` or <https://ui.perfetto.dev>.
+//! Cartridge commands are decoded into named operations, see
+//! [`CartTraceEvent`].
+use std::fmt::Write as _;
+use std::path::Path;
+
+use lunaris_ds_mem_const::SYSTEM_CLOCK_HZ;
+use snafu::ResultExt as _;
+
+use crate::cartridge::CartTraceEvent;
+use crate::emulator::Emulator;
+use crate::emulator::event::Timestamps;
+use crate::error::{EmuError, FailedWriteFileSnafu};
+
+/// Row the event is drawn on in the trace viewer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceTrack {
+    Arm9,
+    Arm7,
+    Scheduler,
+    Dma,
+    Irq,
+    Cartridge,
+}
+
+impl TraceTrack {
+    const ALL: [Self; 6] = [
+        Self::Arm9,
+        Self::Arm7,
+        Self::Scheduler,
+        Self::Dma,
+        Self::Irq,
+        Self::Cartridge,
+    ];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Arm9 => "ARM9",
+            Self::Arm7 => "ARM7",
+            Self::Scheduler => "Scheduler",
+            Self::Dma => "DMA",
+            Self::Irq => "IRQ",
+            Self::Cartridge => "Cartridge",
+        }
+    }
+}
+
+/// One recorded event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub name: String,
+    pub track: TraceTrack,
+    /// Start time in system (ARM7) cycles.
+    pub start: u64,
+    /// Length in system cycles; `None` for instant events.
+    pub duration: Option<u64>,
+    /// Per-CPU cycle counters of instant events.
+    pub cpu_time: Option<Timestamps>,
+    /// Extra key/value pairs shown in the viewer's detail pane.
+    pub args: Vec<(&'static str, u64)>,
+}
+
+/// Events recorded while tracing is enabled.
+#[derive(Debug, Clone, Default)]
+pub struct FrameTrace {
+    events: Vec<TraceEvent>,
+}
+
+impl FrameTrace {
+    pub const fn new() -> Self {
+        Self { events: Vec::new() }
+    }
+
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    /// Record an event spanning `start..end` system cycles.
+    pub fn slice(&mut self, track: TraceTrack, name: impl Into<String>, start: u64, end: u64) {
+        self.events.push(TraceEvent {
+            name: name.into(),
+            track,
+            start,
+            duration: Some(end.saturating_sub(start)),
+            cpu_time: None,
+            args: Vec::new(),
+        });
+    }
+
+    /// Record a point-in-time event.
+    pub fn instant(
+        &mut self,
+        track: TraceTrack,
+        name: impl Into<String>,
+        time: Timestamps,
+        args: Vec<(&'static str, u64)>,
+    ) {
+        self.events.push(TraceEvent {
+            name: name.into(),
+            track,
+            start: time.system,
+            duration: None,
+            cpu_time: Some(time),
+            args,
+        });
+    }
+
+    /// Serialize to a Chrome trace event JSON document.
+    pub fn to_chrome_json(&self) -> String {
+        let mut entries = Vec::with_capacity(TraceTrack::ALL.len() + self.events.len());
+
+        // Name the rows so the viewer shows "ARM9" instead of a bare tid.
+        for track in TraceTrack::ALL {
+            entries.push(format!(
+                "{{\"name\":\"thread_name\",\"ph\":\"M\",\"pid\":0,\"tid\":{},\"args\":{{\"name\":\"{}\"}}}}",
+                track as u32,
+                track.name()
+            ));
+        }
+
+        for event in &self.events {
+            let mut out = String::new();
+            let _ = write!(
+                out,
+                "{{\"name\":\"{}\",\"cat\":\"{}\",\"pid\":0,\"tid\":{},\"ts\":{:.3}",
+                escape_json(&event.name),
+                event.track.name(),
+                event.track as u32,
+                cycles_to_us(event.start)
+            );
+            match event.duration {
+                Some(duration) => {
+                    let _ = write!(out, ",\"ph\":\"X\",\"dur\":{:.3}", cycles_to_us(duration));
+                }
+                None => out.push_str(",\"ph\":\"i\",\"s\":\"t\""),
+            }
+
+            out.push_str(",\"args\":{");
+            let _ = write!(out, "\"cycle\":{}", event.start);
+            if let Some(time) = event.cpu_time {
+                let _ = write!(
+                    out,
+                    ",\"arm9_cycle\":{},\"arm7_cycle\":{}",
+                    time.arm9, time.arm7
+                );
+            }
+            for (key, value) in &event.args {
+                let _ = write!(out, ",\"{key}\":\"0x{value:X}\"");
+            }
+            out.push_str("}}");
+            entries.push(out);
+        }
+
+        format!(
+            "{{\"traceEvents\":[\n{}\n],\"displayTimeUnit\":\"ns\"}}\n",
+            entries.join(",\n")
+        )
+    }
+
+    /// Write [`Self::to_chrome_json`] to `path`.
+    ///
+    /// # Errors
+    /// If the file could not be written.
+    pub fn write_chrome_json(&self, path: &Path) -> Result<(), EmuError> {
+        std::fs::write(path, self.to_chrome_json()).with_context(|_| FailedWriteFileSnafu { path })
+    }
+}
+
+fn cycles_to_us(cycles: u64) -> f64 {
+    cycles as f64 * 1_000_000.0 / SYSTEM_CLOCK_HZ as f64
+}
+
+fn escape_json(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            ch if ch.is_control() => {
+                let _ = write!(out, "\\u{:04X}", ch as u32);
+            }
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+impl Emulator {
+    /// Run one frame with tracing enabled and return what was recorded.
+    ///
+    /// Any trace already in progress is discarded.
+    pub fn trace_frame(&mut self) -> FrameTrace {
+        self.trace = Some(FrameTrace::new());
+        self.run();
+        self.trace.take().unwrap_or_default()
+    }
+
+    /// Record an event if tracing is enabled.
+    #[inline]
+    pub(crate) fn trace_event(&mut self, record: impl FnOnce(&mut FrameTrace, Timestamps)) {
+        let now = self.timestamps();
+        if let Some(trace) = &mut self.trace {
+            record(trace, now);
+        }
+    }
+
+    /// Record the cartridge command the last ROMCTRL or AUXSPIDATA write
+    /// started, if tracing is enabled.
+    #[inline]
+    pub(crate) fn trace_cart_command(&mut self) {
+        let Some(event) = self.cart.pending_trace.take() else {
+            return;
+        };
+        self.trace_event(|trace, now| {
+            let args = match event {
+                CartTraceEvent::Rom { bytes, .. } => vec![("command", u64::from_be_bytes(bytes))],
+                CartTraceEvent::Spi { opcode, .. } => vec![("command", opcode as u64)],
+            };
+            trace.instant(TraceTrack::Cartridge, event.to_string(), now, args);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chrome_json() {
+        let mut trace = FrameTrace::new();
+        trace.slice(TraceTrack::Arm9, "run \"main\" \\ loop", 0, 33_514);
+        let time = Timestamps {
+            system: 335_140,
+            arm9: 670_280,
+            arm7: 335_140,
+        };
+        trace.instant(
+            TraceTrack::Irq,
+            "IRQ\tVBlank\n",
+            time,
+            vec![("flags", 0x1F)],
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&trace.to_chrome_json()).unwrap();
+        let events = json["traceEvents"].as_array().unwrap();
+        assert_eq!(events.len(), TraceTrack::ALL.len() + 2);
+
+        // Rows first, named after their tracks
+        for (row, track) in events.iter().zip(TraceTrack::ALL) {
+            assert_eq!(row["ph"], "M");
+            assert_eq!(row["tid"], track as u32);
+            assert_eq!(row["args"]["name"], track.name());
+        }
+
+        // Names come back unescaped, times in microseconds
+        let slice = &events[TraceTrack::ALL.len()];
+        assert_eq!(slice["name"], "run \"main\" \\ loop");
+        assert_eq!(slice["ph"], "X");
+        assert_eq!(slice["tid"], TraceTrack::Arm9 as u32);
+        assert_eq!(slice["ts"].as_f64(), Some(0.0));
+        assert!((slice["dur"].as_f64().unwrap() - 1000.0).abs() < 0.01);
+
+        let instant = &events[TraceTrack::ALL.len() + 1];
+        assert_eq!(instant["name"], "IRQ\tVBlank\n");
+        assert_eq!(instant["ph"], "i");
+        assert_eq!(instant["cat"], "IRQ");
+        assert!((instant["ts"].as_f64().unwrap() - 10_000.0).abs() < 0.01);
+        assert_eq!(instant["args"]["arm9_cycle"], 670_280);
+        assert_eq!(instant["args"]["flags"], "0x1F");
+
+        // A frame without events still names its rows
+        let json: serde_json::Value =
+            serde_json::from_str(&FrameTrace::new().to_chrome_json()).unwrap();
+        assert_eq!(
+            json["traceEvents"].as_array().map(Vec::len),
+            Some(TraceTrack::ALL.len())
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Memory watchpoints
+//!
+//! A watchpoint stops emulation when a CPU reads, writes or executes an
+//! address range. Only the CPUs' own accesses are watched, not DMA or the
+//! debugger's. Data accesses stop after the instruction that made them,
+//! execution stops before the instruction like a breakpoint does; either way
+//! [`Emulator::run`] returns as for a breakpoint, and
+//! [`Emulator::take_watch_hit`] tells what happened.
+use crate::cpu::arm_cpu::CpuType;
+use crate::emulator::Emulator;
+
+/// Kind of access that hit a watchpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchAccess {
+    Read,
+    Write,
+    Execute,
+}
+
+/// Address range watched on one CPU's bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub cpu: CpuType,
+    pub start: u32,
+    /// Length of the range in bytes
+    pub len: u32,
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+impl Watchpoint {
+    /// Whether an access of `size` bytes at `address` touches the range.
+    const fn overlaps(&self, address: u32, size: u32) -> bool {
+        address.wrapping_sub(self.start) < self.len || self.start.wrapping_sub(address) < size
+    }
+
+    const fn watches(&self, access: WatchAccess) -> bool {
+        match access {
+            WatchAccess::Read => self.read,
+            WatchAccess::Write => self.write,
+            WatchAccess::Execute => self.execute,
+        }
+    }
+}
+
+/// A watchpoint hit, see [`Emulator::take_watch_hit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub cpu: CpuType,
+    /// Address of the instruction that made the access
+    pub pc: u32,
+    pub address: u32,
+    /// Value read or written, the opcode for execution
+    pub value: u32,
+    pub access: WatchAccess,
+}
+
+/// Watchpoints of both CPUs.
+#[derive(Debug, Clone, Default)]
+pub struct Watchpoints {
+    list: Vec<Watchpoint>,
+}
+
+impl Watchpoints {
+    pub fn add(&mut self, watchpoint: Watchpoint) {
+        if !self.list.contains(&watchpoint) {
+            self.list.push(watchpoint);
+        }
+    }
+
+    /// Returns whether `watchpoint` was set.
+    pub fn remove(&mut self, watchpoint: &Watchpoint) -> bool {
+        let len = self.list.len();
+        self.list.retain(|w| w != watchpoint);
+        self.list.len() != len
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Watchpoint> {
+        self.list.iter()
+    }
+
+    fn hits(&self, cpu: CpuType, address: u32, size: u32, access: WatchAccess) -> bool {
+        self.list
+            .iter()
+            .any(|w| w.cpu == cpu && w.watches(access) && w.overlaps(address, size))
+    }
+}
+
+impl Emulator {
+    /// The watchpoint behind the last stop, if it was one. Taking the hit
+    /// alone does not let [`Emulator::run`] continue, see
+    /// [`Emulator::take_breakpoint_hit`].
+    pub const fn take_watch_hit(&mut self) -> Option<WatchHit> {
+        self.watch_hit.take()
+    }
+
+    /// Check a data access of `size` bytes by `cpu_type`, stopping after
+    /// the current instruction on a hit.
+    #[inline]
+    pub(crate) fn check_watchpoint(
+        &mut self,
+        cpu_type: CpuType,
+        address: u32,
+        size: u32,
+        value: u32,
+        access: WatchAccess,
+    ) {
+        if self.watchpoints.is_empty()
+            || self.breakpoint_hit.is_some()
+            || !self.watchpoints.hits(cpu_type, address, size, access)
+        {
+            return;
+        }
+        // The PC is a whole instruction past the one executing
+        let cpu = self.get_cpu(cpu_type);
+        let size = if cpu.cpsr.thumb_on { 2 } else { 4 };
+        let pc = cpu.get_pc().wrapping_sub(2 * size);
+        self.watch_hit = Some(WatchHit {
+            cpu: cpu_type,
+            pc,
+            address,
+            value,
+            access,
+        });
+        self.breakpoint_hit = Some(cpu_type);
+        self.hit_before = None;
+    }
+
+    /// Whether `cpu_type` has to stop before executing `address`.
+    #[inline]
+    pub(crate) fn check_execute_watchpoint(&mut self, cpu_type: CpuType, address: u32) -> bool {
+        let thumb = self.get_cpu(cpu_type).cpsr.thumb_on;
+        let size = if thumb { 2 } else { 4 };
+        if self.watchpoints.is_empty()
+            || !self
+                .watchpoints
+                .hits(cpu_type, address, size, WatchAccess::Execute)
+        {
+            return false;
+        }
+        let value = match thumb {
+            true => self
+                .page_read(cpu_type, address)
+                .map(|bytes| u16::from_le_bytes(bytes) as u32),
+            false => self.page_read(cpu_type, address).map(u32::from_le_bytes),
+        };
+        self.watch_hit = Some(WatchHit {
+            cpu: cpu_type,
+            pc: address,
+            address,
+            value: value.unwrap_or_default(),
+            access: WatchAccess::Execute,
+        });
+        self.stop_before(cpu_type, address);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchpoints() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        // str r1, [r0]; b .
+        emu.main_ram[..4].copy_from_slice(&0xE580_1000_u32.to_le_bytes());
+        emu.main_ram[4..8].copy_from_slice(&0xEAFF_FFFE_u32.to_le_bytes());
+        emu.arm7.regs[0] = 0x0200_0102;
+        emu.arm7.regs[1] = 0x1234;
+        emu.arm7.jp(0x0200_0000, false);
+
+        let mut watchpoint = Watchpoint {
+            cpu: CpuType::Arm7,
+            start: 0x0200_0100,
+            len: 4,
+            read: false,
+            write: true,
+            execute: false,
+        };
+        emu.watchpoints.add(watchpoint);
+        emu.run();
+        assert!(!emu.completed_frame);
+        assert_eq!(emu.take_breakpoint_hit(), Some(CpuType::Arm7));
+        let hit = WatchHit {
+            cpu: CpuType::Arm7,
+            pc: 0x0200_0000,
+            address: 0x0200_0100,
+            value: 0x1234,
+            access: WatchAccess::Write,
+        };
+        assert_eq!(emu.take_watch_hit(), Some(hit));
+
+        // The branch stops before it runs, over and over
+        assert!(emu.watchpoints.remove(&watchpoint));
+        watchpoint.start = 0x0200_0004;
+        watchpoint.execute = true;
+        emu.watchpoints.add(watchpoint);
+        emu.run();
+        assert_eq!(emu.take_breakpoint_hit(), Some(CpuType::Arm7));
+        let hit = emu.take_watch_hit().unwrap();
+        assert_eq!(hit.access, WatchAccess::Execute);
+        assert_eq!(hit.value, 0xEAFF_FFFE);
+
+        emu.watchpoints.clear();
+        emu.run();
+        assert!(emu.completed_frame);
+        assert_eq!(emu.take_watch_hit(), None);
+    }
+}
@@ -4,6 +4,12 @@
 //!
 //! Direct Memory Access (DMA) controller for Nintendo DS
 //! Manages high-speed memory transfers between memory regions
+//!
+//! Each CPU has four channels with SAD, DAD and length/CNT registers at
+//! 0x040000B0 + 12 * channel. The transfers themselves and their start
+//! conditions live in `emulator/dma.rs`.
+use crate::error::EmuError;
+use crate::savestate::{Savestate, StateReader, StateWriter};
 
 /// DMA control register
 #[derive(Debug, Clone, Copy)]
@@ -16,7 +22,9 @@ pub struct DmaCnt {
     pub repeat: bool,
     /// Use 32-bit transfers instead of 16-bit
     pub word_transfer: bool,
-    /// Start timing (0=immediate, 1=VBLANK, 2=HBLANK, 3=sync/special)
+    /// Start timing, bits 11-13 (ARM9: 0=immediate, 1=VBLANK, 2=HBLANK,
+    /// 3=display sync, 4=main memory display, 5=DS cart, 6=GBA cart,
+    /// 7=GXFIFO). ARM7 timings use bits 12-13 and read as 0, 2, 4 and 6.
     pub timing: u32,
     /// Generate interrupt when transfer completes
     pub irq_after_transfer: bool,
@@ -50,7 +58,7 @@ impl DmaCnt {
         if self.word_transfer {
             value |= 1 << 10;
         }
-        value |= ((self.timing & 0x3) as u16) << 11;
+        value |= ((self.timing & 0x7) as u16) << 11;
         if self.irq_after_transfer {
             value |= 1 << 14;
         }
@@ -66,7 +74,7 @@ impl DmaCnt {
         self.source_control = ((value >> 7) & 0x3) as u32;
         self.repeat = (value & (1 << 9)) != 0;
         self.word_transfer = (value & (1 << 10)) != 0;
-        self.timing = ((value >> 11) & 0x3) as u32;
+        self.timing = ((value >> 11) & 0x7) as u32;
         self.irq_after_transfer = (value & (1 << 14)) != 0;
         self.enabled = (value & (1 << 15)) != 0;
     }
@@ -190,6 +198,46 @@ impl NDSDma {
         self.active_dmas != 0
     }
 
+    /// Check if a DMA channel of one CPU is active, stalling that CPU.
+    pub const fn is_active_for(&self, is_arm9: bool) -> bool {
+        let mask = match is_arm9 {
+            true => 0x0F,
+            false => 0xF0,
+        };
+        self.active_dmas & mask != 0
+    }
+
+    /// Read the halfword at `offset` (0..12) of the registers of channel
+    /// `index`.
+    pub fn read_halfword(&self, index: usize, offset: u32) -> u16 {
+        let dma = &self.dmas[index];
+        match offset {
+            0 => dma.source as u16,
+            2 => (dma.source >> 16) as u16,
+            4 => dma.destination as u16,
+            6 => (dma.destination >> 16) as u16,
+            8 => self.read_len(index),
+            _ => self.read_cnt(index),
+        }
+    }
+
+    /// Write the halfword at `offset` (0..10) of the SAD, DAD and length
+    /// registers of channel `index`. CNT writes go through
+    /// `Emulator::dma_write_cnt`.
+    pub fn write_halfword(&mut self, index: usize, offset: u32, halfword: u16) {
+        let dma = self.dmas[index];
+        let halfword = halfword as u32;
+        match offset {
+            0 => self.write_source(index, (dma.source & 0xFFFF_0000) | halfword),
+            2 => self.write_source(index, (dma.source & 0xFFFF) | (halfword << 16)),
+            4 => self.write_dest(index, (dma.destination & 0xFFFF_0000) | halfword),
+            6 => self.write_dest(index, (dma.destination & 0xFFFF) | (halfword << 16)),
+            // ARM9 lengths keep their upper bits from CNT
+            8 => self.write_len(index, (dma.length & 0x1F_0000) | halfword),
+            _ => {}
+        }
+    }
+
     /// Read source address of DMA channel
     pub fn read_source(&self, index: usize) -> u32 {
         match index < 8 {
@@ -206,10 +254,18 @@ impl NDSDma {
         }
     }
 
-    /// Read control register of DMA channel
+    /// Read control register of DMA channel. On the ARM9, bits 0-4 are
+    /// bits 16-20 of the length.
     pub fn read_cnt(&self, index: usize) -> u16 {
         match index < 8 {
-            true => self.dmas[index].cnt.get(),
+            true => {
+                let dma = &self.dmas[index];
+                let length_high = match dma.is_arm9 {
+                    true => (dma.length >> 16) as u16 & 0x1F,
+                    false => 0,
+                };
+                dma.cnt.get() | length_high
+            }
             false => 0,
         }
     }
@@ -217,31 +273,31 @@ impl NDSDma {
     /// Write source address to DMA channel
     pub fn write_source(&mut self, index: usize, source: u32) {
         if index < 8 {
-            self.dmas[index].source = source;
+            self.dmas[index].source = source & 0x0FFF_FFFF;
         }
     }
 
     /// Write destination address to DMA channel
     pub fn write_dest(&mut self, index: usize, dest: u32) {
         if index < 8 {
-            self.dmas[index].destination = dest;
+            self.dmas[index].destination = dest & 0x0FFF_FFFF;
         }
     }
 
-    /// Write transfer length to DMA channel
-    pub fn write_len(&mut self, index: usize, len: u16) {
+    /// Write transfer length to DMA channel: 21 bits on the ARM9, 14 bits
+    /// on ARM7 channels 0-2 and 16 bits on ARM7 channel 3. Zero means the
+    /// maximum length.
+    pub fn write_len(&mut self, index: usize, len: u32) {
         let dma = &mut self.dmas[index];
 
-        let is_ch7 = index == 7;
-        let len32 = len as u32;
-
-        dma.length = match (dma.is_arm9, len == 0, is_ch7) {
-            (true, true, _) => 0x200000,
-            (true, false, _) => len32 & 0x1FFFFF,
-            (false, true, true) => 0x10000,
-            (false, true, false) => 0x4000,
-            (false, false, true) => len32 & 0xFFFF,
-            (false, false, false) => 0x3FFF,
+        let mask = match (dma.is_arm9, index == 7) {
+            (true, _) => 0x1F_FFFF,
+            (false, true) => 0xFFFF,
+            (false, false) => 0x3FFF,
+        };
+        dma.length = match len & mask {
+            0 => mask + 1,
+            len => len,
         };
     }
 
@@ -252,3 +308,51 @@ impl NDSDma {
     // pub fn gamecart_request(&mut self);
     // pub fn gfxfifo_request(&mut self);
 }
+
+/// Channel index and register offset (0..12) of an address in the DMA
+/// register block 0x040000B0..0x040000E0 of one CPU.
+pub(crate) const fn register_index(address: u32, is_arm9: bool) -> (usize, u32) {
+    let offset = address - 0x040000B0;
+    let base = match is_arm9 {
+        true => 0,
+        false => 4,
+    };
+    (base + (offset / 12) as usize, offset % 12)
+}
+
+impl Savestate for NDSDma {
+    fn save_state(&self, w: &mut StateWriter) {
+        for dma in &self.dmas {
+            for value in [
+                dma.source,
+                dma.internal_source,
+                dma.destination,
+                dma.internal_dest,
+                dma.length,
+                dma.internal_len,
+            ] {
+                w.u32(value);
+            }
+            w.u16(dma.cnt.get());
+        }
+        w.u8(self.active_dmas);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        for dma in &mut self.dmas {
+            for value in [
+                &mut dma.source,
+                &mut dma.internal_source,
+                &mut dma.destination,
+                &mut dma.internal_dest,
+                &mut dma.length,
+                &mut dma.internal_len,
+            ] {
+                *value = r.u32()?;
+            }
+            dma.cnt.set(r.u16()?);
+        }
+        self.active_dmas = r.u8()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+//! Accuracy presets
+//!
+//! Bundles the speed/accuracy trade-offs into a few named presets so users
+//! pick one of [`AccuracyPreset`] instead of tuning each knob. Any single
+//! setting can still be pinned with [`AccuracyOverrides`]; the effective
+//! values are [`Config::accuracy`]. Both are plain config fields and may be
+//! changed between frames.
+//!
+//! Toggles for subsystems without a fast path yet (idle loop skipping, the
+//! ARM9 cache model, threaded 3D) get a field here when they land.
+use crate::emulator::Emulator;
+use crate::emulator::clock_stress::DEFAULT_SYNC_CYCLES;
+use crate::emulator::emu_config::Config;
+
+/// Named bundle of accuracy settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccuracyPreset {
+    /// Long CPU slices and every other frame skipped, for slow hosts
+    Fast,
+    /// Hardware-like results for nearly all games
+    #[default]
+    Balanced,
+    /// Short CPU slices for games relying on tight ARM9/ARM7 interleaving
+    Accurate,
+}
+
+/// Effective values of every setting a preset controls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccuracySettings {
+    /// Longest slice (in system cycles) a CPU runs before the other one
+    pub sync_cycles: u32,
+    /// Frames skipped between rendered frames
+    pub frameskip: u32,
+}
+
+/// Per-setting overrides on top of the preset, `None` follows the preset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccuracyOverrides {
+    pub sync_cycles: Option<u32>,
+    pub frameskip: Option<u32>,
+}
+
+impl AccuracyPreset {
+    /// Every preset, fastest first.
+    pub const ALL: [Self; 3] = [Self::Fast, Self::Balanced, Self::Accurate];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Fast => "fast",
+            Self::Balanced => "balanced",
+            Self::Accurate => "accurate",
+        }
+    }
+
+    /// Parse a preset [`name`](Self::name), ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub const fn settings(self) -> AccuracySettings {
+        match self {
+            Self::Fast => AccuracySettings {
+                sync_cycles: 64,
+                frameskip: 1,
+            },
+            Self::Balanced => AccuracySettings {
+                sync_cycles: DEFAULT_SYNC_CYCLES,
+                frameskip: 0,
+            },
+            Self::Accurate => AccuracySettings {
+                sync_cycles: 4,
+                frameskip: 0,
+            },
+        }
+    }
+}
+
+impl Config {
+    /// Settings of the selected preset with the overrides applied.
+    pub fn accuracy(&self) -> AccuracySettings {
+        let preset = self.accuracy_preset.settings();
+        let overrides = self.accuracy_overrides;
+        AccuracySettings {
+            sync_cycles: overrides.sync_cycles.unwrap_or(preset.sync_cycles).max(1),
+            frameskip: overrides.frameskip.unwrap_or(preset.frameskip),
+        }
+    }
+}
+
+impl Emulator {
+    /// Select `preset`, keeping any overrides.
+    pub fn set_accuracy_preset(&mut self, preset: AccuracyPreset) {
+        #[cfg(feature = "tracing")]
+        tracing::info!("Accuracy preset: {}", preset.name());
+        self.config.accuracy_preset = preset;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accuracy_presets() {
+        assert_eq!(AccuracyPreset::parse(" Fast"), Some(AccuracyPreset::Fast));
+        assert_eq!(AccuracyPreset::parse("ludicrous"), None);
+
+        let mut emu = Box::new(Emulator::new());
+        assert_eq!(emu.sync_cycles(), DEFAULT_SYNC_CYCLES);
+
+        emu.set_accuracy_preset(AccuracyPreset::Fast);
+        assert_eq!(emu.config.accuracy(), AccuracyPreset::Fast.settings());
+        emu.config.accuracy_overrides.frameskip = Some(0);
+        assert_eq!(emu.config.accuracy().frameskip, 0);
+        assert_eq!(emu.sync_cycles(), 64);
+
+        // Overrides survive a preset change
+        emu.set_accuracy_preset(AccuracyPreset::Accurate);
+        emu.config.accuracy_overrides.sync_cycles = Some(0);
+        assert_eq!(emu.sync_cycles(), 1);
+        assert_eq!(emu.config.accuracy().frameskip, 0);
+    }
+}
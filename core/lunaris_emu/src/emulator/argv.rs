@@ -0,0 +1,118 @@
+//! Homebrew argv protocol.
+//!
+//! Homebrew loaders (hbmenu, nds-bootstrap) pass command line arguments to
+//! the launched program through a structure at [`ARGV_ADDRESS`], which the
+//! libnds startup code turns into `argc` / `argv`:
+//!
+//! | offset | content                                      |
+//! |--------|----------------------------------------------|
+//! | 0x00   | magic `_arg` ([`ARGV_MAGIC`])                |
+//! | 0x04   | pointer to the NUL separated arguments       |
+//! | 0x08   | length of the arguments in bytes             |
+//!
+//! The remaining fields are filled in by the program itself.
+use lunaris_ds_mem_const::{MAIN_RAM_MASK, MAIN_RAM_START};
+
+use crate::emulator::Emulator;
+
+/// Address of the argv structure in main RAM.
+pub const ARGV_ADDRESS: u32 = 0x02FF_FE70;
+pub const ARGV_MAGIC: u32 = 0x5F61_7267;
+/// Fallback location of the arguments when the ARM9 binary fills main RAM,
+/// the same as the libnds bootloader's. Arguments never reach past it.
+const ARGV_TEMP_END: u32 = 0x02FF_E000;
+/// Largest ARM9 binary after which the arguments are placed directly.
+const ARM9_MAX_INLINE_LEN: u32 = 0x38_0000;
+
+impl Emulator {
+    fn main_ram_write(&mut self, address: u32, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            let index = (address.wrapping_add(i as u32) & MAIN_RAM_MASK) as usize;
+            self.main_ram[index] = byte;
+        }
+    }
+
+    /// Write `config.argv` for the loaded ROM, like a homebrew loader would.
+    ///
+    /// The arguments go right after the ARM9 binary, where the libnds
+    /// bootloader puts them. Does nothing if `config.argv` is empty or does
+    /// not fit in main RAM there.
+    pub fn write_homebrew_argv(&mut self) {
+        if self.config.argv.is_empty() {
+            return;
+        }
+
+        let mut command_line = Vec::new();
+        for arg in &self.config.argv {
+            command_line.extend_from_slice(arg.as_bytes());
+            command_line.push(0);
+        }
+        let length = command_line.len() as u32;
+
+        let header = self.cart.header();
+        let address = match header.arm9_size {
+            size if size > ARM9_MAX_INLINE_LEN => ARGV_TEMP_END.checked_sub(length),
+            size => Some(header.arm9_ram_address.wrapping_add(size).wrapping_add(3)),
+        }
+        .map(|address| address & !3)
+        .filter(|&address| {
+            address >= MAIN_RAM_START
+                && address
+                    .checked_add(length)
+                    .is_some_and(|end| end <= ARGV_TEMP_END)
+        });
+        let Some(address) = address else {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("argv: {length} bytes do not fit in main RAM, not passed");
+            return;
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::info!("argv: {} bytes at {address:08X}", length);
+
+        self.main_ram_write(address, &command_line);
+        self.main_ram_write(ARGV_ADDRESS, &ARGV_MAGIC.to_le_bytes());
+        self.main_ram_write(ARGV_ADDRESS + 4, &address.to_le_bytes());
+        self.main_ram_write(ARGV_ADDRESS + 8, &length.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_argv_after_arm9_binary() {
+        let mut emu = Box::new(Emulator::new());
+        emu.cart.rom = vec![0; 0x200];
+        emu.cart.rom[0x28..0x2C].copy_from_slice(&0x0200_0000u32.to_le_bytes());
+        emu.cart.rom[0x2C..0x30].copy_from_slice(&0x1001u32.to_le_bytes());
+        emu.config.argv = vec!["fat:/game.nds".into(), "-v".into()];
+        emu.write_homebrew_argv();
+
+        assert_eq!(emu.arm9_read_word(ARGV_ADDRESS), ARGV_MAGIC);
+        let args = emu.arm9_read_word(ARGV_ADDRESS + 4);
+        assert_eq!(args, 0x0200_1004);
+        let length = emu.arm9_read_word(ARGV_ADDRESS + 8);
+        let bytes: Vec<u8> = (0..length).map(|i| emu.arm9_read_byte(args + i)).collect();
+        assert_eq!(bytes, b"fat:/game.nds\0-v\0");
+    }
+
+    #[test]
+    fn test_argv_that_does_not_fit() {
+        let mut emu = Box::new(Emulator::new());
+        emu.cart.rom = vec![0; 0x200];
+        // ARM9 binary loaded at the top of the address space
+        emu.cart.rom[0x28..0x2C].copy_from_slice(&0xFFFF_FF00u32.to_le_bytes());
+        emu.cart.rom[0x2C..0x30].copy_from_slice(&0x1000u32.to_le_bytes());
+        emu.config.argv = vec!["fat:/game.nds".into()];
+        emu.write_homebrew_argv();
+        assert_eq!(emu.arm9_read_word(ARGV_ADDRESS), 0);
+
+        // Larger than all of main RAM below the fallback location
+        emu.cart.rom[0x2C..0x30].copy_from_slice(&0x40_0000u32.to_le_bytes());
+        emu.config.argv = vec!["x".repeat(0x100_0000)];
+        emu.write_homebrew_argv();
+        assert_eq!(emu.arm9_read_word(ARGV_ADDRESS), 0);
+    }
+}
@@ -0,0 +1,186 @@
+//! Audio dumps
+//!
+//! Writes the SPU mix, and optionally one file per channel ("stems"), to
+//! 16-bit stereo WAV. One sample is taken every 1024 system cycles in step
+//! with emulation, so sample `n` lines up with the frame running at that
+//! time and two runs of the same input give identical files.
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+pub(crate) use lunaris_ds_audio::CYCLES_PER_SAMPLE;
+use lunaris_ds_audio::{SpuOutput, WavWriter};
+use snafu::ResultExt as _;
+
+use crate::emulator::Emulator;
+use crate::emulator::frame_timing::DS_AUDIO_RATE;
+use crate::error::{EmuError, FailedWriteFileSnafu};
+
+type Wav = WavWriter<BufWriter<File>>;
+
+/// An audio dump in progress, see [`Emulator::start_audio_dump`].
+#[derive(Debug)]
+pub struct AudioDump {
+    path: PathBuf,
+    mix: Wav,
+    /// One writer per channel when stems were requested
+    stems: Vec<Wav>,
+    next_sample: u64,
+    /// First write error; the dump stops at it and reports it when stopped
+    error: Option<EmuError>,
+}
+
+/// Path of the stem of `channel` for a dump to `path`: `music.wav` gives
+/// `music.ch00.wav` to `music.ch15.wav`.
+pub fn stem_path(path: &Path, channel: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}.ch{channel:02}.wav"))
+}
+
+fn create_wav(path: PathBuf) -> Result<Wav, EmuError> {
+    WavWriter::create(&path, 2, DS_AUDIO_RATE.round() as u32).context(FailedWriteFileSnafu { path })
+}
+
+impl AudioDump {
+    fn write_sample(&mut self, output: SpuOutput) -> std::io::Result<()> {
+        self.mix.write_frame(&output.mix)?;
+        for (stem, channel) in self.stems.iter_mut().zip(&output.channels) {
+            stem.write_frame(channel)?;
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), EmuError> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        let path = self.path;
+        self.mix
+            .finish()
+            .with_context(|_| FailedWriteFileSnafu { path: path.clone() })?;
+        for (channel, stem) in self.stems.into_iter().enumerate() {
+            stem.finish().with_context(|_| FailedWriteFileSnafu {
+                path: stem_path(&path, channel),
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Emulator {
+    /// Start writing the SPU mix to the WAV file at `path`, and with `stems`
+    /// each of the 16 channels next to it (see [`stem_path`]). A dump
+    /// already running is finished first.
+    ///
+    /// # Errors
+    /// If a file cannot be created, or finishing the previous dump failed.
+    pub fn start_audio_dump(&mut self, path: &Path, stems: bool) -> Result<(), EmuError> {
+        self.stop_audio_dump()?;
+        let mix = create_wav(path.to_path_buf())?;
+        let stems = match stems {
+            true => (0..16)
+                .map(|channel| create_wav(stem_path(path, channel)))
+                .collect::<Result<_, _>>()?,
+            false => Vec::new(),
+        };
+        self.audio_dump = Some(AudioDump {
+            path: path.to_path_buf(),
+            mix,
+            stems,
+            next_sample: self.system_timestamp,
+            error: None,
+        });
+        Ok(())
+    }
+
+    /// Finish the running audio dump, if any.
+    ///
+    /// # Errors
+    /// The first error writing the dump, which also stopped it.
+    pub fn stop_audio_dump(&mut self) -> Result<(), EmuError> {
+        match self.audio_dump.take() {
+            Some(dump) => dump.finish(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_dumping_audio(&self) -> bool {
+        self.audio_dump.is_some()
+    }
+
+    /// Write the samples due up to the current system time.
+    pub(crate) fn audio_dump_catch_up(&mut self) {
+        let Some(dump) = &mut self.audio_dump else {
+            return;
+        };
+        if dump.error.is_some() {
+            return;
+        }
+        while dump.next_sample <= self.system_timestamp {
+            let mut output = self.spu.output();
+            if self.gba_mode {
+                self.direct_sound.mix_into(&mut output);
+            }
+            if let Err(source) = dump.write_sample(output) {
+                #[cfg(feature = "tracing")]
+                tracing::error!("Audio dump to {} failed: {source}", dump.path.display());
+                dump.error = Some(EmuError::FailedWriteFile {
+                    source,
+                    path: dump.path.clone(),
+                });
+                return;
+            }
+            dump.next_sample += CYCLES_PER_SAMPLE;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_dump_stems() {
+        const SOUNDCNT: u32 = 0x0400_0500;
+        let path =
+            std::env::temp_dir().join(format!("lunaris_audio_dump_{}.wav", std::process::id()));
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        // Master enable at full volume, channel 3 at full volume, hard left
+        emu.arm7_write_halfword(SOUNDCNT, 0x807F);
+        let channel = emu.spu.get_channel_mut(3).unwrap();
+        channel.channel_cnt.volume = 0x7F;
+        channel.channel_cnt.panning = 0;
+        channel.sample = 0x4000;
+
+        emu.start_audio_dump(&path, true).unwrap();
+        let start = emu.system_timestamp;
+        emu.run();
+        let samples = (emu.system_timestamp - start) / CYCLES_PER_SAMPLE + 1;
+        emu.stop_audio_dump().unwrap();
+        assert!(!emu.is_dumping_audio());
+
+        let sample_at = |wav: &[u8], index: usize| {
+            let at = 44 + index * 4;
+            [
+                i16::from_le_bytes([wav[at], wav[at + 1]]),
+                i16::from_le_bytes([wav[at + 2], wav[at + 3]]),
+            ]
+        };
+        let mix = std::fs::read(&path).unwrap();
+        assert_eq!(mix.len() as u64, 44 + samples * 4);
+        assert_eq!(sample_at(&mix, 0), [0x3F01, 0]);
+        for channel in 0..16 {
+            let stem_path = stem_path(&path, channel);
+            let stem = std::fs::read(&stem_path).unwrap();
+            assert_eq!(stem.len(), mix.len());
+            let expected = match channel {
+                3 => [0x3F80, 0],
+                _ => [0, 0],
+            };
+            assert_eq!(sample_at(&stem, 0), expected);
+            std::fs::remove_file(stem_path).unwrap();
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,287 @@
+//! Running code from the block caches
+//!
+//! Blocks are only built from RAM pages of the page tables, so every
+//! instruction in them has a place in RAM. Each 256 byte chunk of RAM that
+//! holds cached code is flagged; a store to a flagged chunk drops the
+//! caches of both CPUs, since either may run code from shared memory.
+//!
+//! Compiled code runs a whole run of instructions per call, so it is only
+//! used while nothing has to look at each of them: no breakpoints,
+//! watchpoints, reverse execution, code map, coverage or crash recording.
+//! Interrupts are taken after the run rather than between its instructions.
+#[cfg(feature = "jit")]
+use lunaris_ds_jit::JitState;
+
+use crate::cpu::arm_cpu::CpuType;
+use crate::cpu::block_cache::{BlockCache, MAX_BLOCK_LEN, Op};
+use crate::cpu::interpreter::fetched;
+use crate::emulator::Emulator;
+
+const CHUNK_SHIFT: u32 = 8;
+/// `u64` words of the chunk flags over all RAM
+pub(super) const CODE_CHUNK_WORDS: usize = (super::page_table::RAM_SIZE >> CHUNK_SHIFT) / 64;
+
+impl Emulator {
+    fn block_cache(&mut self, cpu_type: CpuType) -> &mut BlockCache<Self> {
+        match cpu_type {
+            CpuType::Arm9 => &mut self.block_cache9,
+            CpuType::Arm7 => &mut self.block_cache7,
+        }
+    }
+
+    /// Fetch and execute the instruction at the PC from the block cache,
+    /// building its block on a miss, or the compiled run of instructions
+    /// starting there. Returns how many instructions ran, 0 having done
+    /// nothing if the code is not in RAM.
+    pub(crate) fn execute_cached(&mut self, cpu_type: CpuType) -> usize {
+        let cpu = self.get_cpu(cpu_type);
+        let thumb = cpu.cpsr.thumb_on;
+        let address = cpu.get_pc().wrapping_sub(if thumb { 2 } else { 4 });
+
+        #[cfg(feature = "jit")]
+        if self.jit_allowed()
+            && let Some(count) = self.execute_compiled(cpu_type, address, thumb)
+        {
+            return count;
+        }
+
+        let op = match self.block_cache(cpu_type).next(address, thumb) {
+            Some(op) => op,
+            None => {
+                let Some(ops) = self.build_block(cpu_type, address, thumb) else {
+                    return 0;
+                };
+                self.block_cache(cpu_type).insert(address, thumb, ops);
+                return self.execute_cached(cpu_type);
+            }
+        };
+        let address = fetched(self, cpu_type, op.instruction());
+        self.record_instruction(cpu_type, address);
+        op.execute(self, cpu_type);
+        1
+    }
+
+    /// Whether nothing needs to see every instruction, see the module docs.
+    #[cfg(feature = "jit")]
+    fn jit_allowed(&self) -> bool {
+        self.breakpoints.is_empty()
+            && self.watchpoints.is_empty()
+            && self.reverse.is_none()
+            && !self.code_map9.tracks_execution()
+            && !self.code_map7.tracks_execution()
+            && !self.coverage9.is_enabled()
+            && !self.coverage7.is_enabled()
+            && self.config.crash_bundle_dir.is_none()
+    }
+
+    /// Run the compiled code of the block at `address`, `None` if there is
+    /// none.
+    #[cfg(feature = "jit")]
+    fn execute_compiled(&mut self, cpu_type: CpuType, address: u32, thumb: bool) -> Option<usize> {
+        let cpu = self.get_cpu(cpu_type);
+        let mut state = JitState {
+            regs: cpu.regs,
+            negative: cpu.cpsr.negative,
+            zero: cpu.cpsr.zero,
+            carry: cpu.cpsr.carry,
+            overflow: cpu.cpsr.overflow,
+        };
+        let run = self
+            .block_cache(cpu_type)
+            .run_compiled(address, thumb, &mut state)?;
+
+        // Compiled code never touches the PC, fetch as the interpreter would
+        let cpu = self.get_cpu_mut(cpu_type);
+        let size = if thumb { 2 } else { 4 };
+        for i in 0..run.count as u32 {
+            let pc = address.wrapping_add(i * size);
+            match thumb {
+                true => cpu.add_s16_code(pc, 1),
+                false => cpu.add_s32_code(pc, 1),
+            }
+        }
+        cpu.add_internal_cycles(run.internal_cycles);
+        cpu.regs[..15].copy_from_slice(&state.regs[..15]);
+        cpu.regs[15] = address.wrapping_add((run.count as u32 + 1) * size);
+        cpu.current_instr = run.last;
+        cpu.cpsr.negative = state.negative;
+        cpu.cpsr.zero = state.zero;
+        cpu.cpsr.carry = state.carry;
+        cpu.cpsr.overflow = state.overflow;
+        Some(run.count)
+    }
+
+    /// Decode the block at `address`, `None` if it is not in RAM.
+    fn build_block(
+        &mut self,
+        cpu_type: CpuType,
+        address: u32,
+        thumb: bool,
+    ) -> Option<Vec<Op<Self>>> {
+        let mut ops = Vec::new();
+        let mut pc = address;
+        while ops.len() < MAX_BLOCK_LEN {
+            let Some(offset) = self.ram_offset(cpu_type, pc) else {
+                break;
+            };
+            let op = match thumb {
+                true => Op::thumb(u16::from_le_bytes(self.page_read(cpu_type, pc)?) as u32),
+                false => Op::arm(u32::from_le_bytes(self.page_read(cpu_type, pc)?)),
+            };
+            let chunk = offset >> CHUNK_SHIFT;
+            self.code_chunks[chunk / 64] |= 1 << (chunk % 64);
+            ops.push(op);
+            pc = pc.wrapping_add(if thumb { 2 } else { 4 });
+            if op.ends_block() {
+                break;
+            }
+        }
+        (!ops.is_empty()).then_some(ops)
+    }
+
+    /// `cpu_type` stored to `address`: drop the cached code if it was there.
+    pub(crate) fn code_written(&mut self, cpu_type: CpuType, address: u32) {
+        let Some(offset) = self.ram_offset(cpu_type, address) else {
+            return;
+        };
+        let chunk = offset >> CHUNK_SHIFT;
+        if self.code_chunks[chunk / 64] & (1 << (chunk % 64)) != 0 {
+            self.clear_block_caches();
+        }
+    }
+
+    /// Drop the cached code of both CPUs.
+    pub(crate) fn clear_block_caches(&mut self) {
+        self.block_cache9.clear();
+        self.block_cache7.clear();
+        self.code_chunks.fill(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::arm_cpu::Reg;
+
+    #[test]
+    fn test_self_modifying_code() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        // mov r0, #1; b .-4
+        emu.write_word(0x0200_0000, 0xE3A0_0001, CpuType::Arm9);
+        emu.write_word(0x0200_0004, 0xEAFF_FFFD, CpuType::Arm9);
+        emu.arm9.jp(0x0200_0000, false);
+        for _ in 0..4 {
+            emu.execute(CpuType::Arm9);
+        }
+        assert_eq!(emu.arm9.get_register(Reg::new(0)), 1);
+        assert_eq!(emu.block_cache9.len(), 1);
+
+        // mov r0, #2, stored by the ARM7 over the cached code
+        emu.write_word(0x0200_0000, 0xE3A0_0002, CpuType::Arm7);
+        assert_eq!(emu.block_cache9.len(), 0);
+        for _ in 0..2 {
+            emu.execute(CpuType::Arm9);
+        }
+        assert_eq!(emu.arm9.get_register(Reg::new(0)), 2);
+        assert_eq!(emu.arm9.get_pc(), 0x0200_0004);
+    }
+
+    /// Run `code` at `start` on the CPU from `regs` (the last one holding C
+    /// and V), up to the `b .` after it.
+    #[cfg(feature = "jit")]
+    fn run_block(
+        emu: &mut Emulator,
+        cpu_type: CpuType,
+        start: u32,
+        code: &[u32],
+        regs: &[u32],
+    ) -> (Vec<u32>, [bool; 4], u64) {
+        let thumb = start & 1 != 0;
+        let size = if thumb { 2 } else { 4 };
+        let start = start & !1;
+        for (i, &instruction) in code.iter().enumerate() {
+            let address = start + i as u32 * size;
+            match thumb {
+                true => emu.write_halfword(address, instruction as u16, cpu_type),
+                false => emu.write_word(address, instruction, cpu_type),
+            }
+        }
+        let end = start + code.len() as u32 * size;
+        match thumb {
+            true => emu.write_halfword(end, 0xE7FE, cpu_type),
+            false => emu.write_word(end, 0xEAFF_FFFE, cpu_type),
+        }
+
+        let cpu = emu.get_cpu_mut(cpu_type);
+        cpu.regs[..15].copy_from_slice(&regs[..15]);
+        cpu.cpsr.carry = regs[15] & 1 != 0;
+        cpu.cpsr.overflow = regs[15] & 2 != 0;
+        cpu.jp(start | thumb as u32, true);
+        let timestamp = cpu.timestamp;
+        while emu.get_cpu(cpu_type).get_pc() != end + size {
+            emu.execute(cpu_type);
+        }
+        let cpu = emu.get_cpu(cpu_type);
+        let flags = [
+            cpu.cpsr.negative,
+            cpu.cpsr.zero,
+            cpu.cpsr.carry,
+            cpu.cpsr.overflow,
+        ];
+        (cpu.regs.to_vec(), flags, cpu.timestamp - timestamp)
+    }
+
+    #[test]
+    #[cfg(feature = "jit")]
+    fn test_compiled_matches_interpreter() {
+        let mut compiled = Box::new(Emulator::new());
+        compiled.power_on();
+        let mut interpreted = Box::new(Emulator::new());
+        interpreted.power_on();
+        interpreted.config.test = true;
+
+        // xorshift32, fixed seed so failures reproduce
+        let mut seed = 0x2545_F491_u32;
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed
+        };
+
+        for round in 0..400 {
+            let (cpu_type, start) = match round % 2 {
+                0 => (CpuType::Arm9, 0x0200_0000),
+                _ => (CpuType::Arm7, 0x0380_1001),
+            };
+            let len = 2 + random() as usize % 16;
+            let code: Vec<u32> = (0..len)
+                .map(|_| {
+                    loop {
+                        let instruction = random();
+                        match cpu_type {
+                            CpuType::Arm9 => {
+                                let instruction = instruction & 0x03FF_FFFF | 0xE000_0000;
+                                if lunaris_ds_jit::decode_arm(instruction).is_some() {
+                                    break instruction;
+                                }
+                            }
+                            CpuType::Arm7 => {
+                                if lunaris_ds_jit::decode_thumb(instruction as u16).is_some() {
+                                    break instruction & 0xFFFF;
+                                }
+                            }
+                        }
+                    }
+                })
+                .collect();
+            let regs: Vec<u32> = (0..16).map(|_| random()).collect();
+
+            let expected = run_block(&mut interpreted, cpu_type, start, &code, &regs);
+            let actual = run_block(&mut compiled, cpu_type, start, &code, &regs);
+            assert_eq!(actual, expected, "{cpu_type:?} {code:08X?}");
+        }
+        assert!(compiled.block_cache9.len() + compiled.block_cache7.len() > 0);
+    }
+}
@@ -0,0 +1,196 @@
+//! Execution breakpoints
+//!
+//! A breakpoint stops the CPU before it executes the instruction at its
+//! address. [`Emulator::run`] then returns in the middle of the frame, and
+//! keeps returning right away until the hit is taken with
+//! [`Emulator::take_breakpoint_hit`]; the next call picks the frame up where
+//! it stopped, executing the instruction it stopped before even if the
+//! breakpoint is still set.
+use crate::cpu::arm_cpu::CpuType;
+use crate::debug::CrashReason;
+use crate::emulator::Emulator;
+
+impl Emulator {
+    /// Stop `cpu_type` before it executes `address`.
+    pub fn add_breakpoint(&mut self, cpu_type: CpuType, address: u32) {
+        if !self.breakpoints.contains(&(cpu_type, address)) {
+            self.breakpoints.push((cpu_type, address));
+        }
+    }
+
+    /// Returns whether there was a breakpoint at `address`.
+    pub fn remove_breakpoint(&mut self, cpu_type: CpuType, address: u32) -> bool {
+        let len = self.breakpoints.len();
+        self.breakpoints
+            .retain(|&breakpoint| breakpoint != (cpu_type, address));
+        self.breakpoints.len() != len
+    }
+
+    /// The CPU stopped at a breakpoint since the last call, if any. Taking
+    /// the hit lets [`Emulator::run`] continue, past the instruction it
+    /// stopped before.
+    pub fn take_breakpoint_hit(&mut self) -> Option<CpuType> {
+        let cpu_type = self.breakpoint_hit.take()?;
+        self.resume_past = self.hit_before.take().map(|address| (cpu_type, address));
+        Some(cpu_type)
+    }
+
+    /// Let `cpu_type` execute the instruction at its PC even if a
+    /// breakpoint is set there, as if it had stopped before it. For
+    /// debuggers that take hits themselves and resume later, possibly after
+    /// stepping onto a breakpoint.
+    pub fn resume_at_pc(&mut self, cpu_type: CpuType) {
+        let cpu = self.get_cpu(cpu_type);
+        let prefetch = if cpu.cpsr.thumb_on { 2 } else { 4 };
+        self.resume_past = Some((cpu_type, cpu.get_pc().wrapping_sub(prefetch)));
+    }
+
+    /// Execute the instruction at the PC of `cpu_type`, past any breakpoint
+    /// there, then run the rest of the system up to the time it reached.
+    /// The other CPU may stop at a breakpoint on the way, as in
+    /// [`Emulator::run`].
+    pub fn step_instruction(&mut self, cpu_type: CpuType) {
+        self.resume_at_pc(cpu_type);
+        if cpu_type == CpuType::Arm7 || !self.check_boot_handover() {
+            self.execute(cpu_type);
+        }
+        // The stepped CPU is already at the sync target, only the other one
+        // runs in the slices
+        let time = self.cpu_system_time(cpu_type);
+        while self.system_timestamp < time && self.breakpoint_hit.is_none() {
+            self.calculate_system_timestamp();
+            self.system_timestamp = self.system_timestamp.min(time);
+            self.run_slice();
+        }
+    }
+
+    /// Whether `cpu_type` resumes past `address`, which then goes through
+    /// the breakpoint checks once. Any other instruction of that CPU ends
+    /// the resume.
+    #[inline]
+    pub(crate) fn resumes_past(&mut self, cpu_type: CpuType, address: u32) -> bool {
+        match self.resume_past {
+            Some((cpu, resume)) if cpu == cpu_type => {
+                self.resume_past = None;
+                resume == address
+            }
+            _ => false,
+        }
+    }
+
+    /// Stop `cpu_type` before it executes `address`.
+    pub(crate) const fn stop_before(&mut self, cpu_type: CpuType, address: u32) {
+        self.breakpoint_hit = Some(cpu_type);
+        self.hit_before = Some(address);
+    }
+
+    /// Execute a BKPT instruction: stop like at a breakpoint while
+    /// [`Emulator::stop_on_bkpt`] is set, take the prefetch abort otherwise.
+    /// The stop comes after the instruction, so resuming moves on.
+    pub(crate) fn bkpt(&mut self, cpu_type: CpuType) {
+        if self.stop_on_bkpt {
+            self.breakpoint_hit = Some(cpu_type);
+            self.hit_before = None;
+            self.watch_hit = None;
+            return;
+        }
+        self.guest_crash(cpu_type, CrashReason::PrefetchAbort);
+        self.get_cpu_mut(cpu_type).handle_prefetch_abort();
+    }
+
+    /// Whether `cpu_type` has to stop before executing `address`.
+    #[inline]
+    pub(crate) fn check_breakpoint(&mut self, cpu_type: CpuType, address: u32) -> bool {
+        if self.breakpoints.is_empty() || !self.breakpoints.contains(&(cpu_type, address)) {
+            return false;
+        }
+        self.stop_before(cpu_type, address);
+        self.watch_hit = None;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakpoint() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        // b . at the start of main RAM
+        emu.main_ram[..4].copy_from_slice(&0xEAFF_FFFE_u32.to_le_bytes());
+        emu.arm7.jp(0x0200_0000, false);
+
+        emu.add_breakpoint(CpuType::Arm7, 0x0200_0000);
+        emu.run();
+        assert!(!emu.completed_frame);
+        assert_eq!(emu.take_breakpoint_hit(), Some(CpuType::Arm7));
+        assert_eq!(emu.arm7.get_pc(), 0x0200_0004);
+
+        assert!(emu.remove_breakpoint(CpuType::Arm7, 0x0200_0000));
+        assert!(!emu.remove_breakpoint(CpuType::Arm7, 0x0200_0000));
+        emu.run();
+        assert!(emu.completed_frame);
+        assert_eq!(emu.take_breakpoint_hit(), None);
+    }
+
+    #[test]
+    fn test_resume_past_breakpoint() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        // mov r0, #1; b .-4
+        emu.main_ram[..4].copy_from_slice(&0xE3A0_0001_u32.to_le_bytes());
+        emu.main_ram[4..8].copy_from_slice(&0xEAFF_FFFD_u32.to_le_bytes());
+        emu.arm7.jp(0x0200_0000, false);
+        emu.add_breakpoint(CpuType::Arm7, 0x0200_0000);
+
+        emu.run();
+        assert_eq!(emu.take_breakpoint_hit(), Some(CpuType::Arm7));
+        assert_eq!(emu.arm7.get_pc(), 0x0200_0004);
+        assert_eq!(emu.arm7.regs[0], 0);
+
+        // Still set: the next run executes the loop once and stops again
+        emu.run();
+        assert_eq!(emu.breakpoint_hit, Some(CpuType::Arm7));
+        assert_eq!(emu.arm7.regs[0], 1);
+        assert_eq!(emu.arm7.get_pc(), 0x0200_0004);
+        assert!(!emu.completed_frame);
+
+        // Without taking the hit nothing runs
+        emu.arm7.regs[0] = 0;
+        emu.run();
+        assert_eq!(emu.arm7.regs[0], 0);
+        assert_eq!(emu.take_breakpoint_hit(), Some(CpuType::Arm7));
+        emu.run();
+        assert_eq!(emu.arm7.regs[0], 1);
+    }
+    #[test]
+    fn test_step_instruction() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        // mov r0, #1; add r0, r0, #1; b .-8
+        emu.main_ram[..4].copy_from_slice(&0xE3A0_0001_u32.to_le_bytes());
+        emu.main_ram[4..8].copy_from_slice(&0xE280_0001_u32.to_le_bytes());
+        emu.main_ram[8..12].copy_from_slice(&0xEAFF_FFFC_u32.to_le_bytes());
+        emu.arm7.jp(0x0200_0000, false);
+        emu.add_breakpoint(CpuType::Arm7, 0x0200_0000);
+        // ARM7 timer 0 at F/1
+        emu.arm7_write_word(0x0400_0100, 0x0080_0000);
+        let start = emu.arm7.get_timestamp();
+
+        // Steps onto the breakpoint and off it again
+        for expected in [1, 2, 2, 1] {
+            emu.step_instruction(CpuType::Arm7);
+            assert_eq!(emu.arm7.regs[0], expected);
+        }
+        assert_eq!(emu.arm7.get_pc(), 0x0200_0008);
+        assert_eq!(emu.breakpoint_hit, None);
+
+        // The rest of the system kept up with it
+        let time = emu.arm7.get_timestamp();
+        assert_eq!(emu.system_timestamp, time);
+        assert!(emu.arm9.get_timestamp() >> 1 >= time);
+        assert_eq!(emu.arm7_read_halfword(0x0400_0100), (time - start) as u16);
+    }
+}
@@ -0,0 +1,99 @@
+//! The DS memory map as the interpreters' [`Bus`]
+//!
+//! Accesses through here are the CPUs' own, so this is where data
+//! watchpoints are checked; instruction fetches bypass it.
+use crate::cpu::arm_cpu::{ArmCpu, CpuType};
+use crate::cpu::bus::Bus;
+use crate::cpu::coprocessor_15::Cp15;
+use crate::debug::WatchAccess;
+use crate::emulator::Emulator;
+use lunaris_ds_mem_const::{OAM_START, VRAM_LCDC_A};
+
+impl Bus for Emulator {
+    fn get_cpu(&self, cpu_type: CpuType) -> &ArmCpu {
+        Self::get_cpu(self, cpu_type)
+    }
+
+    fn get_cpu_mut(&mut self, cpu_type: CpuType) -> &mut ArmCpu {
+        Self::get_cpu_mut(self, cpu_type)
+    }
+
+    fn read_word(&mut self, address: u32, cpu_type: CpuType) -> u32 {
+        let word = Self::read_word(self, address, cpu_type);
+        self.check_watchpoint(cpu_type, address, 4, word, WatchAccess::Read);
+        word
+    }
+
+    fn read_halfword(&mut self, address: u32, cpu_type: CpuType) -> u16 {
+        let halfword = Self::read_halfword(self, address, cpu_type);
+        self.check_watchpoint(cpu_type, address, 2, halfword as u32, WatchAccess::Read);
+        halfword
+    }
+
+    fn read_byte(&mut self, address: u32, cpu_type: CpuType) -> u8 {
+        let byte = Self::read_byte(self, address, cpu_type);
+        self.check_watchpoint(cpu_type, address, 1, byte as u32, WatchAccess::Read);
+        byte
+    }
+
+    fn write_word(&mut self, address: u32, word: u32, cpu_type: CpuType) {
+        Self::write_word(self, address, word, cpu_type);
+        self.check_watchpoint(cpu_type, address, 4, word, WatchAccess::Write);
+    }
+
+    fn write_halfword(&mut self, address: u32, halfword: u16, cpu_type: CpuType) {
+        Self::write_halfword(self, address, halfword, cpu_type);
+        self.check_watchpoint(cpu_type, address, 2, halfword as u32, WatchAccess::Write);
+    }
+
+    fn write_byte(&mut self, address: u32, byte: u8, cpu_type: CpuType) {
+        Self::write_byte(self, address, byte, cpu_type);
+        self.check_watchpoint(cpu_type, address, 1, byte as u32, WatchAccess::Write);
+    }
+
+    /// ARM9 block stores to VRAM are combined, see [`Emulator::write_words`]
+    fn burst_write(&self, address: u32, cpu_type: CpuType) -> bool {
+        cpu_type == CpuType::Arm9 && (VRAM_LCDC_A..OAM_START).contains(&address)
+    }
+
+    fn write_words(&mut self, address: u32, words: &[u32], cpu_type: CpuType) {
+        Self::write_words(self, address, words, cpu_type);
+        for (i, &word) in words.iter().enumerate() {
+            let address = address.wrapping_add(i as u32 * 4);
+            self.check_watchpoint(cpu_type, address, 4, word, WatchAccess::Write);
+        }
+    }
+
+    fn cp15_mut(&mut self, _cpu_type: CpuType) -> Option<&mut Cp15> {
+        Some(&mut self.arm9_cp15)
+    }
+
+    /// The TCM regions, exception vectors or caches may have changed
+    fn cp15_written(&mut self, _cpu_type: CpuType) {
+        self.arm9.apply_cp15(&self.arm9_cp15);
+        self.rebuild_page_table9();
+    }
+
+    /// With [`Config::hle_bios`](crate::Config::hle_bios) the BIOS function
+    /// runs here instead of through the SWI vector
+    fn swi(&mut self, cpu_type: CpuType) {
+        match self.config.hle_bios {
+            true => {
+                self.hle_bios(self.get_cpu(cpu_type).cpu_id);
+            }
+            false => self.get_cpu_mut(cpu_type).handle_swi(),
+        }
+    }
+
+    fn undefined_instruction(&mut self, cpu_type: CpuType) {
+        Self::undefined_instruction(self, cpu_type);
+    }
+
+    fn bkpt(&mut self, cpu_type: CpuType) {
+        Self::bkpt(self, cpu_type);
+    }
+
+    fn trace_instructions(&self) -> bool {
+        self.config.test
+    }
+}
@@ -2,128 +2,174 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 //! emulator.hpp
 //!
+//! Button input, see [`crate::input`]. The per-button methods are kept as
+//! wrappers over [`Emulator::input_event`].
 use crate::emulator::Emulator;
+use crate::input::{Button, InputEvent, InputState};
+
+impl Emulator {
+    /// Replace the held and turbo buttons.
+    pub fn set_input(&mut self, input: InputState) {
+        self.input = input;
+        self.apply_input();
+    }
+
+    /// Update the input with one event from a key mapping.
+    pub fn input_event(&mut self, event: InputEvent) {
+        self.input.apply(event);
+        self.apply_input();
+    }
+
+    /// Held and turbo buttons.
+    pub const fn input(&self) -> InputState {
+        self.input
+    }
+
+    /// Advance turbo buttons at the end of a frame.
+    pub(crate) fn input_end_frame(&mut self) {
+        self.turbo_frame = self.turbo_frame.wrapping_add(1);
+        self.apply_input();
+    }
+
+    /// Write the pressed buttons to KEYINPUT and EXTKEYIN.
+    fn apply_input(&mut self) {
+        let pressed = self.input.pressed(self.turbo_frame);
+        let is_pressed = |button: Button| pressed & button.mask() != 0;
+        self.key_input.button_a = is_pressed(Button::A);
+        self.key_input.button_b = is_pressed(Button::B);
+        self.key_input.select = is_pressed(Button::Select);
+        self.key_input.start = is_pressed(Button::Start);
+        self.key_input.right = is_pressed(Button::Right);
+        self.key_input.left = is_pressed(Button::Left);
+        self.key_input.up = is_pressed(Button::Up);
+        self.key_input.down = is_pressed(Button::Down);
+        self.key_input.button_r = is_pressed(Button::R);
+        self.key_input.button_l = is_pressed(Button::L);
+        self.ext_key_in.button_x = is_pressed(Button::X);
+        self.ext_key_in.button_y = is_pressed(Button::Y);
+    }
+}
 
 impl Emulator {
     /// Handle up button press
     pub fn button_up_pressed(&mut self) {
-        self.key_input.up = true;
+        self.input_event(InputEvent::Pressed(Button::Up));
     }
 
     /// Handle down button press
     pub fn button_down_pressed(&mut self) {
-        self.key_input.down = true;
+        self.input_event(InputEvent::Pressed(Button::Down));
     }
 
     /// Handle left button press
     pub fn button_left_pressed(&mut self) {
-        self.key_input.left = true;
+        self.input_event(InputEvent::Pressed(Button::Left));
     }
 
     /// Handle start button press
     pub fn button_start_pressed(&mut self) {
-        self.key_input.start = true;
+        self.input_event(InputEvent::Pressed(Button::Start));
     }
 
     /// Handle select button press
     pub fn button_select_pressed(&mut self) {
-        self.key_input.select = true;
+        self.input_event(InputEvent::Pressed(Button::Select));
     }
 
     /// Handle A button press
     pub fn button_a_pressed(&mut self) {
-        self.key_input.button_a = true;
+        self.input_event(InputEvent::Pressed(Button::A));
     }
 
     /// Handle B button press
     pub fn button_b_pressed(&mut self) {
-        self.key_input.button_b = true;
+        self.input_event(InputEvent::Pressed(Button::B));
     }
 
     /// Handle X button press
     pub fn button_x_pressed(&mut self) {
-        self.ext_key_in.button_x = true;
+        self.input_event(InputEvent::Pressed(Button::X));
     }
 
     /// Handle Y button press
     pub fn button_y_pressed(&mut self) {
-        self.ext_key_in.button_y = true;
+        self.input_event(InputEvent::Pressed(Button::Y));
     }
 
     /// Handle L button press
     pub fn button_l_pressed(&mut self) {
-        self.key_input.button_l = true;
+        self.input_event(InputEvent::Pressed(Button::L));
     }
 
     /// Handle R button press
     pub fn button_r_pressed(&mut self) {
-        self.key_input.button_r = true;
+        self.input_event(InputEvent::Pressed(Button::R));
     }
 
     /// Handle right button press
     pub fn button_right_pressed(&mut self) {
-        self.key_input.right = true;
+        self.input_event(InputEvent::Pressed(Button::Right));
     }
 }
 
 impl Emulator {
     /// Handle up button release
     pub fn button_up_released(&mut self) {
-        self.key_input.up = false;
+        self.input_event(InputEvent::Released(Button::Up));
     }
 
     /// Handle down button release
     pub fn button_down_released(&mut self) {
-        self.key_input.down = false;
+        self.input_event(InputEvent::Released(Button::Down));
     }
 
     /// Handle left button release
     pub fn button_left_released(&mut self) {
-        self.key_input.left = false;
+        self.input_event(InputEvent::Released(Button::Left));
     }
 
     /// Handle right button release
     pub fn button_right_released(&mut self) {
-        self.key_input.right = false;
+        self.input_event(InputEvent::Released(Button::Right));
     }
 
     /// Handle start button release
     pub fn button_start_released(&mut self) {
-        self.key_input.start = false;
+        self.input_event(InputEvent::Released(Button::Start));
     }
 
     /// Handle select button release
     pub fn button_select_released(&mut self) {
-        self.key_input.select = false;
+        self.input_event(InputEvent::Released(Button::Select));
     }
 
     /// Handle A button release
     pub fn button_a_released(&mut self) {
-        self.key_input.button_a = false;
+        self.input_event(InputEvent::Released(Button::A));
     }
 
     /// Handle B button release
     pub fn button_b_released(&mut self) {
-        self.key_input.button_b = false;
+        self.input_event(InputEvent::Released(Button::B));
     }
 
     /// Handle X button release
     pub fn button_x_released(&mut self) {
-        self.ext_key_in.button_x = false;
+        self.input_event(InputEvent::Released(Button::X));
     }
 
     /// Handle Y button release
     pub fn button_y_released(&mut self) {
-        self.ext_key_in.button_y = false;
+        self.input_event(InputEvent::Released(Button::Y));
     }
 
     /// Handle L button release
     pub fn button_l_released(&mut self) {
-        self.key_input.button_l = false;
+        self.input_event(InputEvent::Released(Button::L));
     }
 
     /// Handle R button release
     pub fn button_r_released(&mut self) {
-        self.key_input.button_r = false;
+        self.input_event(InputEvent::Released(Button::R));
     }
 }
@@ -1,14 +1,36 @@
 use std::path::Path;
 
 use crate::Emulator;
-use crate::cartridge::{CartCommand, CartridgeError};
+use crate::cartridge::{CHIP_ID, CartCommand, CartridgeError, journal};
+use crate::emulator::save_profile::{is_valid_profile_name, save_profile_path};
+use crate::scheduler::EventKind;
 
 impl Emulator {
+    /// Write modified save data to disk, unless in read-only mode.
+    pub(crate) fn flush_save(&mut self) {
+        if self.config.read_only {
+            return;
+        }
+        if let Err(err) = self.cart.save_check() {
+            #[cfg(feature = "tracing")]
+            tracing::error!("{err}");
+        }
+    }
+
     /// Loads ROM image and optional save data.
     pub fn cartridge_load_rom(&mut self, rom_file: &Path) -> Result<(), CartridgeError> {
         use std::fs::File;
         use std::io::Read as _;
 
+        // The profile name becomes part of the save file name
+        if let Some(name) =
+            (self.config.save_profile.as_deref()).filter(|name| !is_valid_profile_name(name))
+        {
+            return Err(CartridgeError::InvalidSaveProfile {
+                name: name.to_string(),
+            });
+        }
+
         self.power_on();
 
         #[cfg(feature = "tracing")]
@@ -52,8 +74,19 @@ impl Emulator {
             self.cart.rom_name = stem.to_string_lossy().to_string();
         }
 
-        // Try load save
-        let save_path = rom_file.with_extension("sav");
+        // Try load save, finishing a flush that was cut short first
+        let save_path = save_profile_path(
+            rom_file,
+            self.cart.game_code(),
+            self.config.save_profile.as_deref(),
+        );
+        self.cart.save_path = Some(save_path.clone());
+        if !self.config.read_only {
+            journal::recover(&save_path).map_err(|source| CartridgeError::ReadSave {
+                path: save_path.clone(),
+                source,
+            })?;
+        }
 
         if let Ok(mut save_file) = File::open(&save_path) {
             let meta =
@@ -74,6 +107,16 @@ impl Emulator {
 
                 self.cart.save_size = file_size;
 
+                // The journal is never removed, so replay it into memory
+                // on every load
+                if self.config.read_only {
+                    journal::replay_in_memory(&save_path, &mut self.cart.spi_save[..file_size])
+                        .map_err(|source| CartridgeError::ReadSave {
+                            path: save_path.clone(),
+                            source,
+                        })?;
+                }
+
                 #[cfg(feature = "tracing")]
                 tracing::info!(
                     "Loaded save {} size {}",
@@ -83,45 +126,15 @@ impl Emulator {
             }
         } else {
             // Database fallback
-            if !self.cart.save_database.is_empty() {
-                let entries = self.cart.database_size as usize / 19;
-
-                for index in 0..entries {
-                    let base = index * 19;
+            if let Some(size) = self
+                .cart
+                .header()
+                .database_save_size(&self.cart.save_database)
+            {
+                self.cart.save_size = size;
 
-                    let mut match_ok = true;
-
-                    for c in 0..16 {
-                        if self.cart.rom[c] != self.cart.save_database[base + c] {
-                            match_ok = false;
-                            break;
-                        }
-                    }
-
-                    if match_ok {
-                        #[cfg(feature = "tracing")]
-                        tracing::info!("Found ROM entry in database");
-
-                        let size_index = self.cart.save_database[base + 18];
-
-                        self.cart.save_size = match size_index {
-                            0x02 => 512,
-                            0x03 => 1024 * 8,
-                            0x04 => 1024 * 64,
-                            0x05 => 1024 * 256,
-                            0x06 => 1024 * 512,
-                            0x07 => 1024 * 1024,
-                            _ => {
-                                #[cfg(feature = "tracing")]
-                                tracing::error!("Unrecognized save format {}", size_index);
-                                0
-                            }
-                        };
-
-                        #[cfg(feature = "tracing")]
-                        tracing::info!("Save size {}", self.cart.save_size);
-                    }
-                }
+                #[cfg(feature = "tracing")]
+                tracing::info!("Save size {}", self.cart.save_size);
             }
         }
 
@@ -133,72 +146,114 @@ impl Emulator {
             self.cart.save_type = 2;
         }
 
-        if self.config.direct_boot_enabled {
-            return Ok(());
+        let game_code = self.cart.direct_read_word(0xC);
+        match self.config.direct_boot_enabled {
+            // Without the BIOS boot the secure area is only ever read plain
+            true => self.cartridge_decrypt_secure_area(),
+            false => self.cartridge_encrypt_secure_area(),
         }
+        self.cartridge_init_keycode(game_code, 2, 2);
 
-        // Safe little-endian reads
-        let read_u32 = |buf: &[u8], offset: usize| -> u32 {
-            u32::from_le_bytes([
-                buf[offset],
-                buf[offset + 1],
-                buf[offset + 2],
-                buf[offset + 3],
-            ])
-        };
+        Ok(())
+    }
 
-        let game_code = read_u32(&self.cart.rom, 0xC);
-        let arm_rom_base = read_u32(&self.cart.rom, 0x20) as usize;
+    /// Secure area of the loaded ROM, if its ARM9 binary starts inside it.
+    fn secure_area_range(&self) -> Option<std::ops::Range<usize>> {
+        let base = self.cart.direct_read_word(0x20) as usize;
+        (0x4000..0x8000)
+            .contains(&base)
+            .then_some(base..base + 0x800)
+            .filter(|range| range.end <= self.cart.rom.len())
+    }
 
-        if (0x4000..0x8000).contains(&arm_rom_base) {
-            let sig0 = read_u32(&self.cart.rom, arm_rom_base);
-            let sig1 = read_u32(&self.cart.rom, arm_rom_base + 0x10);
+    /// Encrypt the secure area of a decrypted dump, like it is stored on a
+    /// real card, so the BIOS can boot it through KEY1.
+    ///
+    /// Dumps keep the 0xE7FFDEFF marker left by decryption in the first
+    /// word; the one at 0x10 tells apart a ROM without secure area.
+    fn cartridge_encrypt_secure_area(&mut self) {
+        let Some(range) = self.secure_area_range() else {
+            return;
+        };
+        let base = range.start;
+        if self.cart.direct_read_word(base as u32) != 0xE7FF_DEFF
+            || self.cart.direct_read_word(base as u32 + 0x10) == 0xE7FF_DEFF
+        {
+            return;
+        }
 
-            if sig0 == 0xE7FFDEFF && sig1 != 0xE7FFDEFF {
-                #[cfg(feature = "tracing")]
-                tracing::info!("Encrypting secure area");
+        #[cfg(feature = "tracing")]
+        tracing::info!("Encrypting secure area");
+
+        let game_code = self.cart.direct_read_word(0xC);
+        self.cart.rom[base..base + 8].copy_from_slice(b"encryObj");
+        self.cartridge_init_keycode(game_code, 3, 2);
+        for ptr in range.step_by(8) {
+            let [y, x] = self.cart_rom_block(ptr);
+            let block = self.cart.key1_encrypt(y, x);
+            self.set_cart_rom_block(ptr, block);
+        }
 
-                self.cart.rom[arm_rom_base..arm_rom_base + 8].copy_from_slice(b"encryObj");
+        // The ID is encrypted twice
+        self.cartridge_init_keycode(game_code, 2, 2);
+        let [y, x] = self.cart_rom_block(base);
+        let block = self.cart.key1_encrypt(y, x);
+        self.set_cart_rom_block(base, block);
+    }
 
-                self.cartridge_init_keycode(game_code, 3, 2);
+    /// Decrypt the secure area of an encrypted dump in place, undoing
+    /// [`Self::cartridge_encrypt_secure_area`]. The ID is replaced with the
+    /// 0xE7FFDEFF marker; an area whose ID does not decrypt to "encryObj"
+    /// is left alone.
+    fn cartridge_decrypt_secure_area(&mut self) {
+        let Some(range) = self.secure_area_range() else {
+            return;
+        };
+        let base = range.start;
+        let game_code = self.cart.direct_read_word(0xC);
+        let mut area: Vec<[u32; 2]> = range
+            .step_by(8)
+            .map(|ptr| self.cart_rom_block(ptr))
+            .collect();
 
-                for i in (0..0x800).step_by(8) {
-                    let ptr = arm_rom_base + i;
-                    let block = [
-                        read_u32(&self.cart.rom, ptr),
-                        read_u32(&self.cart.rom, ptr + 4),
-                    ];
-                    let data = self.cart.key1_encrypt(block[0], block[1]);
-                    self.cart.rom[ptr..ptr + 4].copy_from_slice(&data[0].to_le_bytes());
-                    self.cart.rom[ptr + 4..ptr + 8].copy_from_slice(&data[1].to_le_bytes());
-                }
+        self.cartridge_init_keycode(game_code, 2, 2);
+        self.cart.key1_decrypt(&mut area[0]);
+        self.cartridge_init_keycode(game_code, 3, 2);
+        for block in &mut area {
+            self.cart.key1_decrypt(block);
+        }
+        let id = [area[0][0].to_le_bytes(), area[0][1].to_le_bytes()].concat();
+        if id != b"encryObj" {
+            return;
+        }
 
-                self.cartridge_init_keycode(game_code, 2, 2);
+        #[cfg(feature = "tracing")]
+        tracing::info!("Decrypted secure area");
 
-                let ptr = arm_rom_base;
-                let block = [
-                    read_u32(&self.cart.rom, ptr),
-                    read_u32(&self.cart.rom, ptr + 4),
-                ];
-                let data = self.cart.key1_encrypt(block[0], block[1]);
-                self.cart.rom[ptr..ptr + 4].copy_from_slice(&data[0].to_le_bytes());
-                self.cart.rom[ptr + 4..ptr + 8].copy_from_slice(&data[1].to_le_bytes());
-            }
+        area[0] = [0xE7FF_DEFF; 2];
+        for (i, block) in area.into_iter().enumerate() {
+            self.set_cart_rom_block(base + i * 8, block);
         }
+    }
 
-        self.cartridge_init_keycode(game_code, 2, 2);
+    /// Two ROM words at `ptr`, as one KEY1 block.
+    fn cart_rom_block(&self, ptr: usize) -> [u32; 2] {
+        [
+            self.cart.direct_read_word(ptr as u32),
+            self.cart.direct_read_word(ptr as u32 + 4),
+        ]
+    }
 
-        Ok(())
+    fn set_cart_rom_block(&mut self, ptr: usize, block: [u32; 2]) {
+        self.cart.rom[ptr..ptr + 4].copy_from_slice(&block[0].to_le_bytes());
+        self.cart.rom[ptr + 4..ptr + 8].copy_from_slice(&block[1].to_le_bytes());
     }
 
     /// Initializes keycode from chip ID and level.
     pub fn cartridge_init_keycode(&mut self, idcode: u32, level: i32, modulo: u32) {
-        if let Some(bios) = &self.arm7_bios.get(0x30..0x30 + 0x1048) {
-            self.cart.key1_buffer[..0x1048].copy_from_slice(bios);
-        } else {
-            #[cfg(feature = "tracing")]
-            tracing::warn!("arm7_bios need: 0x30..0x30 + 0x1048");
-        }
+        let mut key1_buffer = std::mem::take(&mut self.cart.key1_buffer);
+        self.cart_copy_keybuffer(&mut key1_buffer);
+        self.cart.key1_buffer = key1_buffer;
 
         self.cart.keycode[0] = idcode;
         self.cart.keycode[1] = idcode >> 1;
@@ -216,69 +271,72 @@ impl Emulator {
         }
     }
 
-    /// Executes cartridge transfer logic for a given number of cycles.
-    pub fn cartridge_run(&mut self, cycles: i32) {
+    /// Schedule the next word of a ROM transfer once the last one was read,
+    /// `cycles_left` system cycles from now.
+    pub(crate) fn cartridge_schedule(&mut self) {
+        if self.cart.romctrl.block_busy
+            && !self.cart.romctrl.word_ready
+            && self.scheduler.due_time(EventKind::CartTransfer).is_none()
+        {
+            let cycles = self.cart.cycles_left.max(0) as u64;
+            self.schedule_event(EventKind::CartTransfer, cycles);
+        }
+    }
+
+    /// Put the next word of the running ROM transfer on the bus.
+    pub(crate) fn cartridge_handle_event(&mut self) {
         // #[cfg(feature = "tracing")]
         // tracing::trace!(%self.cart.romctrl.block_busy,%self.cart.romctrl.word_ready);
 
         if self.cart.romctrl.block_busy && !self.cart.romctrl.word_ready {
-            self.cart.cycles_left -= cycles;
-
-            if self.cart.cycles_left > 0 {
-                return;
-            }
-
             self.cart.cycles_left = 8;
 
             match self.cart.command_id {
-                CartCommand::Dummy => {
-                    self.cart.data_output = 0xFFFF_FFFF;
-                }
+                CartCommand::Dummy => self.cart.output_word(0xFFFF_FFFF),
 
                 CartCommand::GetHeader => {
-                    self.cart.data_output =
-                        self.cart.direct_read_word(self.cart.rom_data_index as u32);
+                    let word = self.cart.direct_read_word(self.cart.rom_data_index as u32);
+                    self.cart.output_word(word);
                     self.cart.rom_data_index += 4;
 
                     if self.cart.rom_data_index > 0xFFF {
                         self.cart.rom_data_index = 0;
                     }
-
-                    self.cart.romctrl.word_ready = true;
                 }
 
-                CartCommand::GetChipId => {
-                    // Macronix 64MB ROM compatible ID
-                    self.cart.data_output = 0x0000_3FC2;
-                    self.cart.romctrl.word_ready = true;
-                }
+                CartCommand::GetChipId => self.cart.output_word(CHIP_ID),
 
                 CartCommand::EnableKey1 => {
                     self.cart.cmd_encrypt_mode = 1;
                 }
 
+                // The card seeded KEY2 when it received the command
+                CartCommand::EnableKey2 => {}
+
                 CartCommand::GetSecureAreaBlock => {
-                    self.cart.data_output = self.cart.direct_read_word(self.cart.secure_area_index);
+                    let word = self.cart.direct_read_word(self.cart.secure_area_index);
+                    self.cart.output_word(word);
                     self.cart.secure_area_index += 4;
-                    self.cart.romctrl.word_ready = true;
                 }
 
                 CartCommand::ReadRom => {
-                    if self.cart.rom_data_index < 0x8000 {
+                    let word = if self.cart.rom_data_index < 0x8000 {
                         let addr = 0x8000 + (self.cart.rom_data_index & 0x1FF);
-                        self.cart.data_output = self.cart.direct_read_word(addr as u32);
+                        self.cart.direct_read_word(addr as u32)
                     } else {
-                        self.cart.data_output =
-                            self.cart.direct_read_word(self.cart.rom_data_index as u32);
-                    }
+                        self.cart.direct_read_word(self.cart.rom_data_index as u32)
+                    };
+                    self.cart.output_word(word);
 
                     self.cart.rom_data_index += 4;
-                    self.cart.romctrl.word_ready = true;
                 }
 
-                _ => {
+                CartCommand::Empty => {
                     #[cfg(feature = "tracing")]
-                    tracing::error!("Unknown cart command: {:02X?}", self.cart.command_buffer);
+                    tracing::warn!("Unknown cart command: {:02X?}", self.cart.command_buffer);
+
+                    // Nothing drives the bus
+                    self.cart.output_word(0xFFFF_FFFF);
                 }
             }
 
@@ -300,3 +358,134 @@ impl Emulator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::key2::Key2;
+
+    #[test]
+    fn test_read_only_save() {
+        let path = std::env::temp_dir().join(format!("lunaris_ro_{}.sav", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut emu = Box::new(Emulator::new());
+        emu.cart.power_on();
+        emu.cart.save_path = Some(path.clone());
+        emu.config.read_only = true;
+        emu.cart.write_save(0x10, 0xAB);
+        emu.flush_save();
+        assert!(!path.exists());
+
+        // Changes made while read-only are kept in memory
+        emu.config.read_only = false;
+        emu.flush_save();
+        assert_eq!(std::fs::read(&path).unwrap()[0x10], 0xAB);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_rom_invalid_save_profile() {
+        let mut emu = Box::new(Emulator::new());
+        emu.config.save_profile = Some("../other".to_string());
+        assert!(matches!(
+            emu.cartridge_load_rom(Path::new("game.nds")),
+            Err(CartridgeError::InvalidSaveProfile { name }) if name == "../other"
+        ));
+    }
+
+    #[test]
+    fn test_key2_transfer() {
+        const ROMCTRL: u32 = 0x0400_01A4;
+        // Busy, 4 byte block, KEY2 commands and data
+        const CHIP_ID_READ: u32 = (1 << 31) | (7 << 24) | (1 << 22) | (1 << 13);
+        const APPLY_SEED: u32 = 1 << 15;
+
+        let mut emu = Box::new(Emulator::new());
+        emu.cart.power_on();
+        emu.cart.auxspicnt.enabled = true;
+        // Card after activating KEY2 and switching to KEY2 commands
+        let (seed0, seed1) = Key2::card_seeds(0x12_3456, 0);
+        emu.cart.key2_card = Some(Key2::new(seed0, seed1));
+        emu.cart.cmd_encrypt_mode = 2;
+        // Console seeded like the BIOS does
+        emu.arm7_write_word(0x0400_01B0, seed0 as u32);
+        emu.arm7_write_word(0x0400_01B4, seed1 as u32);
+        emu.arm7_write_halfword(0x0400_01B8, (seed0 >> 32) as u16);
+        emu.arm7_write_halfword(0x0400_01BA, (seed1 >> 32) as u16);
+
+        let mut read_chip_id = |romctrl: u32| {
+            emu.cart.command_buffer = [0xB8, 0, 0, 0, 0, 0, 0, 0];
+            emu.arm7_write_word(ROMCTRL, romctrl);
+            emu.cartridge_handle_event();
+            emu.cart.get_output()
+        };
+        assert_eq!(read_chip_id(CHIP_ID_READ | APPLY_SEED), 0x3FC2);
+        assert_eq!(read_chip_id(CHIP_ID_READ), 0x3FC2);
+        // Without KEY2 data the console sees the bus as is
+        assert_ne!(read_chip_id(CHIP_ID_READ & !(1 << 13)), 0x3FC2);
+        assert_eq!(emu.cart.get_romctrl() & APPLY_SEED, 0);
+    }
+
+    /// A ROM with an ARM9 binary at 0x4000 and a decrypted secure area.
+    fn secure_area_rom(emu: &mut Emulator) -> Vec<u8> {
+        let mut rom = vec![0; 0x8000];
+        rom[0xC..0x10].copy_from_slice(b"ALNE");
+        rom[0x20..0x24].copy_from_slice(&0x4000u32.to_le_bytes());
+        for (i, byte) in rom[0x4000..0x4800].iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        rom[0x4000..0x4008].copy_from_slice(&[0xFF, 0xDE, 0xFF, 0xE7, 0xFF, 0xDE, 0xFF, 0xE7]);
+        emu.cart.rom = rom.clone();
+        rom
+    }
+
+    #[test]
+    fn test_secure_area_round_trip() {
+        let mut emu = Box::new(Emulator::new());
+        let plain = secure_area_rom(&mut emu);
+        emu.cartridge_encrypt_secure_area();
+        assert_ne!(emu.cart.rom[0x4000..0x4800], plain[0x4000..0x4800]);
+        assert_eq!(emu.cart.rom[..0x4000], plain[..0x4000]);
+
+        emu.cartridge_decrypt_secure_area();
+        assert_eq!(emu.cart.rom, plain);
+
+        // Not an encrypted area: left alone
+        emu.cartridge_decrypt_secure_area();
+        assert_eq!(emu.cart.rom, plain);
+    }
+
+    #[test]
+    fn test_key1_command() {
+        const ROMCTRL: u32 = 0x0400_01A4;
+        // Busy, 0x1000 byte block
+        const SECURE_AREA_READ: u32 = (1 << 31) | (4 << 24);
+
+        let mut emu = Box::new(Emulator::new());
+        emu.cart.power_on();
+        emu.cart.auxspicnt.enabled = true;
+        secure_area_rom(&mut emu);
+        emu.cartridge_init_keycode(u32::from_le_bytes(*b"ALNE"), 2, 2);
+        emu.cart.cmd_encrypt_mode = 1;
+
+        // GetSecureAreaBlock of block 4, encrypted like the BIOS does
+        let mut command = [0x20, 0x00, 0x40, 0x12, 0x34, 0x56, 0x78, 0x9A];
+        command.reverse();
+        let [y, x] = emu.cart.key1_encrypt(
+            u32::from_le_bytes(command[..4].try_into().unwrap()),
+            u32::from_le_bytes(command[4..].try_into().unwrap()),
+        );
+        command = [y.to_le_bytes(), x.to_le_bytes()]
+            .concat()
+            .try_into()
+            .unwrap();
+        command.reverse();
+
+        emu.cart.command_buffer = command;
+        emu.arm7_write_word(ROMCTRL, SECURE_AREA_READ);
+        assert_eq!(emu.cart.command_id, CartCommand::GetSecureAreaBlock);
+        assert_eq!(emu.cart.secure_area_index, 0x4000);
+        assert_eq!(emu.cart.command_buffer[..3], [0x20, 0x00, 0x40]);
+    }
+}
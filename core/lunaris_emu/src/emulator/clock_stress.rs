@@ -0,0 +1,145 @@
+//! ARM9:ARM7 clock ratio stress mode.
+//!
+//! Skews how far the ARM9 runs relative to the ARM7 and how often the two
+//! are synchronized, to shake out emulation bugs that only show up with a
+//! particular interleaving of IPC/FIFO traffic. Games are expected to stay
+//! correct (if slower) under any ratio; one that doesn't usually points at
+//! a missing IRQ, a FIFO edge case or a bad register read-back.
+//!
+//! Developer option only, the timing it produces is not hardware accurate.
+use crate::cpu::arm_cpu::CpuType;
+use crate::emulator::Emulator;
+
+/// Environment variable read by [`ClockStress::from_env`].
+pub const CLOCK_STRESS_ENV: &str = "LUNARIS_CLOCK_STRESS";
+/// System cycles between CPU syncs with the balanced accuracy preset.
+pub const DEFAULT_SYNC_CYCLES: u32 = 20;
+
+/// Clock ratio and sync granularity override, see [`crate::Config::clock_stress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockStress {
+    /// ARM9 speed multiplier `arm9_num / arm9_den` on top of the normal 2:1
+    pub arm9_num: u32,
+    pub arm9_den: u32,
+    /// Longest slice (in system cycles) a CPU runs before the other one
+    pub sync_cycles: u32,
+    /// Run the ARM7 before the ARM9 in each slice
+    pub arm7_first: bool,
+}
+
+impl Default for ClockStress {
+    fn default() -> Self {
+        Self {
+            arm9_num: 1,
+            arm9_den: 1,
+            sync_cycles: DEFAULT_SYNC_CYCLES,
+            arm7_first: false,
+        }
+    }
+}
+
+impl ClockStress {
+    /// Parse `RATIO[,SYNC][,arm7-first]`, e.g. `3/2`, `1/2,4` or `1,1,arm7-first`.
+    ///
+    /// `RATIO` is the ARM9 speed multiplier (`num/den` or an integer) and
+    /// `SYNC` the slice length in system cycles.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut parts = spec.split(',').map(str::trim);
+        let mut stress = Self::default();
+
+        let ratio = parts.next()?;
+        let (num, den) = ratio.split_once('/').unwrap_or((ratio, "1"));
+        stress.arm9_num = num.trim().parse().ok().filter(|&n| n > 0)?;
+        stress.arm9_den = den.trim().parse().ok().filter(|&n| n > 0)?;
+
+        for part in parts {
+            match part {
+                "arm7-first" => stress.arm7_first = true,
+                sync => stress.sync_cycles = sync.parse().ok().filter(|&n| n > 0)?,
+            }
+        }
+        Some(stress)
+    }
+
+    /// Read the stress settings from [`CLOCK_STRESS_ENV`], if set and valid.
+    pub fn from_env() -> Option<Self> {
+        let spec = std::env::var(CLOCK_STRESS_ENV).ok()?;
+        let stress = Self::parse(&spec);
+        #[cfg(feature = "tracing")]
+        if stress.is_none() {
+            tracing::warn!("Ignoring invalid {CLOCK_STRESS_ENV}={spec:?}");
+        }
+        stress
+    }
+}
+
+impl Emulator {
+    /// Timestamp, in the CPU's own clock, that `cpu_type` runs up to in the
+    /// current slice.
+    pub(crate) fn cpu_sync_target(&self, cpu_type: CpuType) -> u64 {
+        match (cpu_type, self.config.clock_stress) {
+            (CpuType::Arm7, _) => self.system_timestamp,
+            (CpuType::Arm9, None) => self.system_timestamp << 1,
+            (CpuType::Arm9, Some(stress)) => {
+                ((self.system_timestamp as u128 * 2 * stress.arm9_num as u128)
+                    / stress.arm9_den as u128) as u64
+            }
+        }
+    }
+
+    /// System time `cpu_type` has run up to, the inverse of
+    /// [`Emulator::cpu_sync_target`] rounded down.
+    pub(crate) fn cpu_system_time(&self, cpu_type: CpuType) -> u64 {
+        match (cpu_type, self.config.clock_stress) {
+            (CpuType::Arm7, _) => self.arm7.get_timestamp(),
+            (CpuType::Arm9, None) => self.arm9.get_timestamp() >> 1,
+            (CpuType::Arm9, Some(stress)) => {
+                ((self.arm9.get_timestamp() as u128 * stress.arm9_den as u128)
+                    / (2 * stress.arm9_num as u128)) as u64
+            }
+        }
+    }
+
+    /// Length of one slice in system cycles.
+    pub(crate) fn sync_cycles(&self) -> u32 {
+        match self.config.clock_stress {
+            Some(stress) => stress.sync_cycles,
+            None => self.config.accuracy().sync_cycles,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clock_stress() {
+        assert_eq!(
+            ClockStress::parse("3/2, 7, arm7-first"),
+            Some(ClockStress {
+                arm9_num: 3,
+                arm9_den: 2,
+                sync_cycles: 7,
+                arm7_first: true,
+            })
+        );
+        assert_eq!(
+            ClockStress::parse("2"),
+            Some(ClockStress {
+                arm9_num: 2,
+                ..Default::default()
+            })
+        );
+        assert_eq!(ClockStress::parse("1/0"), None);
+        assert_eq!(ClockStress::parse("1,fast"), None);
+
+        let mut emu = Box::new(Emulator::new());
+        emu.system_timestamp = 100;
+        assert_eq!(emu.cpu_sync_target(CpuType::Arm9), 200);
+        emu.config.clock_stress = ClockStress::parse("1/4,3");
+        assert_eq!(emu.cpu_sync_target(CpuType::Arm9), 50);
+        assert_eq!(emu.cpu_sync_target(CpuType::Arm7), 100);
+        assert_eq!(emu.sync_cycles(), 3);
+    }
+}
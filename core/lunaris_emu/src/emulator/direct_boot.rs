@@ -0,0 +1,208 @@
+//! Direct boot
+//!
+//! Starts the cartridge without running the BIOS and firmware, leaving the
+//! system the way the firmware does after its boot menu:
+//!
+//! - both binaries copied from the ROM to their load addresses, and the
+//!   header to 0x027FFE00
+//! - the chip ID, header CRC and user settings in the shared area at the
+//!   end of main RAM
+//! - CP15 with the DTCM at 0x03000000, see [`Cp15::direct_boot`]
+//! - both CPUs in System mode at their entry points, with the stacks of
+//!   each mode in place
+//! - POSTFLG set and all shared WRAM mapped to the ARM7
+//!
+//! [`Cp15::direct_boot`]: crate::cpu::coprocessor_15::Cp15::direct_boot
+use crate::cartridge::{CHIP_ID, HEADER_SIZE};
+use crate::emulator::Emulator;
+
+/// Where the firmware leaves a copy of the header.
+const HEADER_COPY: u32 = 0x027F_FE00;
+/// Bytes of the header the firmware copies.
+const HEADER_COPY_LEN: usize = 0x170;
+/// Largest binary the firmware loads, for either CPU.
+const MAX_BINARY_SIZE: usize = 0x3B_FE00;
+
+/// System, IRQ and Supervisor stacks of the ARM9, inside the DTCM.
+const ARM9_STACKS: [u32; 3] = [0x0300_2F7C, 0x0300_3F80, 0x0300_3FC0];
+/// System, IRQ and Supervisor stacks of the ARM7, at the end of its WRAM.
+const ARM7_STACKS: [u32; 3] = [0x0380_FD80, 0x0380_FFB0, 0x0380_FFDC];
+
+impl Emulator {
+    /// Perform a direct boot sequence.
+    ///
+    /// Without a ROM only the firmware values are written and both CPUs
+    /// stay at their reset vectors.
+    pub fn direct_boot(&mut self) {
+        self.direct_boot_firmware_values();
+        if self.cart.rom.len() >= HEADER_SIZE {
+            self.direct_boot_cartridge();
+        }
+        self.write_homebrew_argv();
+    }
+
+    /// Boot flag and the firmware data the boot menu hands on.
+    fn direct_boot_firmware_values(&mut self) {
+        // Write zero to boot flag
+        self.arm7_write_word(0x027FF864, 0);
+
+        // Write shifted value from firmware[0x20]
+        let value_0x20 = u16::from_le_bytes([
+            self.spi.firmware.raw_firmware[0x20],
+            self.spi.firmware.raw_firmware[0x21],
+        ]);
+        self.arm7_write_word(0x027FF868, (value_0x20 as u32) << 3);
+
+        // Write halfword from firmware[0x26]
+        let value_0x26 = u16::from_le_bytes([
+            self.spi.firmware.raw_firmware[0x26],
+            self.spi.firmware.raw_firmware[0x27],
+        ]);
+        self.arm7_write_halfword(0x027FF874, value_0x26);
+
+        // Write halfword from firmware[0x04]
+        let value_0x04 = u16::from_le_bytes([
+            self.spi.firmware.raw_firmware[0x04],
+            self.spi.firmware.raw_firmware[0x05],
+        ]);
+        self.arm7_write_halfword(0x027FF876, value_0x04);
+
+        // Copy USER data block (0x70 bytes, word-aligned)
+        for i in (0..0x70).step_by(4) {
+            let offset = (self.spi.firmware.user_data as usize) + i;
+
+            let word = u32::from_le_bytes([
+                self.spi.firmware.raw_firmware[offset],
+                self.spi.firmware.raw_firmware[offset + 1],
+                self.spi.firmware.raw_firmware[offset + 2],
+                self.spi.firmware.raw_firmware[offset + 3],
+            ]);
+
+            self.arm7_write_word(0x027FFC80 + i as u32, word);
+        }
+    }
+
+    /// Load the binaries and set up the CPUs and I/O for the loaded ROM.
+    fn direct_boot_cartridge(&mut self) {
+        let header = self.cart.header();
+
+        // All shared WRAM to the ARM7 before its binary may land there
+        self.wram_cnt = 3;
+        self.postflg9 = 1;
+        self.postflg7 = 1;
+
+        let header_copy = self.cart.rom[..HEADER_COPY_LEN].to_vec();
+        self.direct_boot_copy(&header_copy, HEADER_COPY, Emulator::arm9_write_word);
+        let arm9 = self.rom_binary(header.arm9_rom_offset, header.arm9_size);
+        self.direct_boot_copy(&arm9, header.arm9_ram_address, Emulator::arm9_write_word);
+        let arm7 = self.rom_binary(header.arm7_rom_offset, header.arm7_size);
+        self.direct_boot_copy(&arm7, header.arm7_ram_address, Emulator::arm7_write_word);
+
+        // Chip ID and header CRC, twice: for the card in the slot and the
+        // one booted
+        let header_crc = self.cart.direct_read_halfword(0x15E);
+        for base in [0x027F_F800, 0x027F_FC00] {
+            self.arm9_write_word(base, CHIP_ID);
+            self.arm9_write_word(base + 4, CHIP_ID);
+            self.arm9_write_halfword(base + 8, header_crc);
+        }
+        // Booted from the cartridge
+        self.arm9_write_halfword(0x027F_FC40, 1);
+
+        self.arm9_cp15.direct_boot();
+        self.arm9.apply_cp15(&self.arm9_cp15);
+        self.rebuild_page_tables();
+        self.arm9.direct_boot(header.arm9_entry, ARM9_STACKS);
+        self.arm7.direct_boot(header.arm7_entry, ARM7_STACKS);
+    }
+
+    /// `size` bytes of the ROM from `offset`, cut to what the firmware
+    /// loads and what the ROM holds.
+    fn rom_binary(&self, offset: u32, size: u32) -> Vec<u8> {
+        let start = (offset as usize).min(self.cart.rom.len());
+        let end = start
+            .saturating_add((size as usize).min(MAX_BINARY_SIZE))
+            .min(self.cart.rom.len());
+        self.cart.rom[start..end].to_vec()
+    }
+
+    /// Copy `data` to `address` in words through `write`.
+    fn direct_boot_copy(&mut self, data: &[u8], address: u32, write: fn(&mut Self, u32, u32)) {
+        for (i, chunk) in data.chunks(4).enumerate() {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            write(
+                self,
+                address.wrapping_add(i as u32 * 4),
+                u32::from_le_bytes(word),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::arm_cpu::PsrMode;
+
+    fn test_rom() -> Vec<u8> {
+        let mut rom = vec![0; 0x400];
+        let header: [(usize, u32); 8] = [
+            (0x20, 0x200),
+            (0x24, 0x0200_0004),
+            (0x28, 0x0200_0000),
+            (0x2C, 8),
+            (0x30, 0x300),
+            (0x34, 0x0380_0000),
+            (0x38, 0x0380_0000),
+            (0x3C, 6),
+        ];
+        for (offset, value) in header {
+            rom[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        rom[0x200..0x208].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        rom[0x300..0x306].copy_from_slice(&[9, 10, 11, 12, 13, 14]);
+        rom[0x15E] = 0x34;
+        rom[0x15F] = 0x12;
+        rom
+    }
+
+    #[test]
+    fn test_direct_boot_loads_binaries() {
+        let mut emu = Box::new(Emulator::new());
+        emu.cart.rom = test_rom();
+        emu.direct_boot();
+
+        assert_eq!(emu.arm9_read_word(0x0200_0004), 0x0807_0605);
+        assert_eq!(emu.arm7_read_word(0x0380_0004), 0x0000_0E0D);
+        assert_eq!(emu.arm9_read_word(HEADER_COPY + 0x24), 0x0200_0004);
+        assert_eq!(emu.arm9_read_word(0x027F_F800), CHIP_ID);
+        assert_eq!(emu.arm9_read_halfword(0x027F_FC08), 0x1234);
+        assert_eq!((emu.postflg9, emu.postflg7, emu.wram_cnt), (1, 1, 3));
+
+        // Entry points in System mode, PC one instruction ahead
+        assert_eq!(emu.arm9.cpsr.mode, PsrMode::System);
+        assert_eq!(emu.arm9.get_pc(), 0x0200_0008);
+        assert_eq!(emu.arm9.regs[13], ARM9_STACKS[0]);
+        assert_eq!(emu.arm9.regs[14], 0x0200_0004);
+        assert_eq!(emu.arm7.get_pc(), 0x0380_0004);
+        assert_eq!(emu.arm7.regs[13], ARM7_STACKS[0]);
+        emu.arm7.update_reg_mode(PsrMode::Irq);
+        assert_eq!(emu.arm7.regs[13], ARM7_STACKS[1]);
+
+        assert_eq!(emu.arm9_cp15.get_dtcm_base(), 0x0300_0000);
+        assert_eq!(emu.arm9_cp15.get_dtcm_size(), 0x4000);
+        assert_eq!(emu.arm9_cp15.get_itcm_size(), 0x0200_0000);
+    }
+
+    #[test]
+    fn test_direct_boot_wraps_load_address() {
+        let mut emu = Box::new(Emulator::new());
+        let mut rom = test_rom();
+        // ARM9 binary loaded across the end of the address space
+        rom[0x28..0x2C].copy_from_slice(&0xFFFF_FFFCu32.to_le_bytes());
+        emu.cart.rom = rom;
+        emu.direct_boot();
+        assert_eq!(emu.arm9.get_pc(), 0x0200_0008);
+    }
+}
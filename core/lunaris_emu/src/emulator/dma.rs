@@ -4,8 +4,21 @@
 //!
 //! Direct Memory Access (DMA) controller for Nintendo DS
 //! Manages high-speed memory transfers between memory regions
+//!
+//! A start condition marks its channels active and schedules the DMA
+//! event, which runs every active channel by priority (lowest index
+//! first). While a channel of a CPU is active that CPU is stalled, and the
+//! bus cycles of the transfer are taken from it afterwards.
+use std::ops::Range;
+
+use crate::cpu::arm_cpu::CpuType;
+use crate::dma::register_index;
 use crate::emulator::Emulator;
 use crate::interrupts::Interrupt;
+use crate::scheduler::EventKind;
+
+/// System cycles before the first unit of a transfer.
+const DMA_STARTUP_CYCLES: u64 = 4;
 
 impl Emulator {
     /// Handle scheduler event
@@ -13,160 +26,196 @@ impl Emulator {
         #[cfg(feature = "tracing")]
         tracing::trace!("Emulator::dma_handle_event has called.");
 
-        self.dma_event.processing = false;
-        let event_id = self.dma_event.id;
+        // A transfer can start further channels, e.g. by writing ROMCTRL
+        while self.dma.active_dmas != 0 {
+            let index = self.dma.active_dmas.trailing_zeros() as usize;
+            self.dma_run_channel(index);
+        }
+    }
 
-        loop {
-            let (is_arm9, internal_len, length, irq_after_transfer, interrupt7, interrupt9) = {
-                let active_dma = &self.dma.dmas[event_id as usize];
-                let is_arm9 = active_dma.is_arm9;
-                let internal_len = active_dma.internal_len;
-                let length = active_dma.length;
-                let irq_after_transfer = active_dma.cnt.irq_after_transfer;
-                let interrupt9 = (8 + active_dma.index) as usize;
-                let interrupt7 = (4 + active_dma.index) as usize;
+    /// Run channel `index` until its length is done, or for GXFIFO DMAs
+    /// until 112 units went to the FIFO, and clear its active bit.
+    fn dma_run_channel(&mut self, index: usize) {
+        let is_arm9 = self.dma.dmas[index].is_arm9;
+        let mut cycles = DMA_STARTUP_CYCLES;
+        let mut display_fifo_len = self.gpu.display_fifo_len();
 
+        loop {
+            let (internal_len, length, irq_after_transfer) = {
+                let active_dma = &self.dma.dmas[index];
                 (
-                    is_arm9,
-                    internal_len,
-                    length,
-                    irq_after_transfer,
-                    interrupt7,
-                    interrupt9,
+                    active_dma.internal_len,
+                    active_dma.length,
+                    active_dma.cnt.irq_after_transfer,
                 )
             };
 
-            if internal_len > length {
+            if internal_len >= length {
                 if irq_after_transfer {
-                    if is_arm9 {
-                        if let Some(interrupt) = Interrupt::from_usize(interrupt9) {
-                            self.request_interrupt9(interrupt);
-                        } else {
-                            #[cfg(feature = "tracing")]
-                            tracing::error!("Out of index {interrupt9}");
-                        }
-                    } else if let Some(interrupt) = Interrupt::from_usize(interrupt7) {
-                        self.request_interrupt7(interrupt);
-                    } else {
-                        #[cfg(feature = "tracing")]
-                        tracing::error!("Out of index {interrupt7}");
+                    // DMA0-3 are interrupts 8-11 on both CPUs
+                    let interrupt = Interrupt::from_usize(8 + index % 4).unwrap();
+                    match is_arm9 {
+                        true => self.request_interrupt9(interrupt),
+                        false => self.request_interrupt7(interrupt),
                     }
                 }
 
-                let repeat = {
-                    let active_dma = &mut self.dma.dmas[event_id as usize];
-                    active_dma.internal_len = 0;
-                    active_dma.cnt.repeat
-                };
-                if !repeat {
-                    let active_dma = &mut self.dma.dmas[event_id as usize];
+                let active_dma = &mut self.dma.dmas[index];
+                active_dma.internal_len = 0;
+                // Repeat is ignored for immediate transfers
+                if !active_dma.cnt.repeat || active_dma.cnt.timing == 0 {
                     active_dma.cnt.enabled = false;
-                    self.dma.active_dmas &= (!(1 << event_id)) as u8;
                 } else {
-                    //Reload dest
-                    let (dest_control, timing) = {
-                        let active_dma = &self.dma.dmas[event_id as usize];
-                        (active_dma.cnt.dest_control, active_dma.cnt.timing)
-                    };
-
-                    if dest_control == 3 {
-                        let active_dma = &mut self.dma.dmas[event_id as usize];
+                    if active_dma.cnt.dest_control == 3 {
                         active_dma.internal_dest = active_dma.destination;
                     }
-                    if timing != 0 {
-                        self.dma.active_dmas &= (!(1 << event_id)) as u8;
-                    } else {
-                        let length = self.dma.dmas[event_id as usize].length;
-                        self.add_dma_event(event_id, length as u64);
+                    // Main memory display DMAs keep feeding the display FIFO
+                    // until it holds a whole line
+                    if is_arm9 && active_dma.cnt.timing == 4 {
+                        let len = self.gpu.display_fifo_len();
+                        if len > display_fifo_len && !self.gpu.display_fifo_full() {
+                            display_fifo_len = len;
+                            continue;
+                        }
                     }
                 }
-                return;
+                break;
             }
 
-            // transfer 1 unit
-            let (is_arm9, word_transfer, internal_source, internal_dest) = {
-                let active_dma = &self.dma.dmas[event_id as usize];
-                (
-                    active_dma.is_arm9,
-                    active_dma.cnt.word_transfer,
-                    active_dma.internal_source,
-                    active_dma.internal_dest,
-                )
+            let transferred = internal_len;
+            if !self.dma_transfer_block(index) {
+                self.dma_transfer_unit(index);
+            }
+            let active_dma = &self.dma.dmas[index];
+            let unit_cycles = match active_dma.cnt.word_transfer {
+                true => 2,
+                false => 1,
             };
+            let units = (active_dma.internal_len - transferred) as u64;
+            cycles += units * unit_cycles;
+            // Two bytes per cycle
+            self.counters.dma_bytes += units * unit_cycles * 2;
 
-            let (_value, offset) = if word_transfer {
-                let value = if is_arm9 {
-                    let v = self.arm9_read_word(internal_source);
-                    self.arm9_write_word(internal_dest, v);
-                    v
-                } else {
-                    let v = self.arm7_read_word(internal_source);
-                    self.arm7_write_word(internal_dest, v);
-                    v
-                };
-                (value, 4_u32)
+            // special ARM9 timing 7 behavior
+            if is_arm9 && active_dma.cnt.timing == 7 && active_dma.internal_len >= 112 {
+                let active_dma = &mut self.dma.dmas[index];
+                active_dma.internal_len = 0;
+                active_dma.length -= 112;
+                break;
+            }
+        }
+
+        self.dma.active_dmas &= !(1 << index);
+        self.dma_steal_cycles(is_arm9, cycles);
+    }
+
+    /// Charge the bus cycles of a transfer to the CPU that owns the channel.
+    /// Timers keep counting meanwhile, on the system clock.
+    fn dma_steal_cycles(&mut self, is_arm9: bool, cycles: u64) {
+        match is_arm9 {
+            true => self.arm9.timestamp += cycles << 1,
+            false => self.arm7.timestamp += cycles,
+        }
+    }
+
+    /// Transfer one unit of channel `index` and step its addresses.
+    fn dma_transfer_unit(&mut self, index: usize) {
+        // transfer 1 unit
+        let (is_arm9, word_transfer, internal_source, internal_dest) = {
+            let active_dma = &self.dma.dmas[index];
+            (
+                active_dma.is_arm9,
+                active_dma.cnt.word_transfer,
+                active_dma.internal_source,
+                active_dma.internal_dest,
+            )
+        };
+
+        let (_value, offset) = if word_transfer {
+            let value = if is_arm9 {
+                let v = self.arm9_read_word(internal_source);
+                self.arm9_write_word(internal_dest, v);
+                v
             } else {
-                let value = if is_arm9 {
-                    let v = self.arm9_read_halfword(internal_source) as u32;
-                    self.arm9_write_halfword(internal_dest, v as u16);
-                    v
-                } else {
-                    let v = self.arm7_read_halfword(internal_source) as u32;
-                    self.arm7_write_halfword(internal_dest, v as u16);
-                    v
-                };
-                (value, 2_u32)
+                let v = self.arm7_read_word(internal_source);
+                self.arm7_write_word(internal_dest, v);
+                v
+            };
+            (value, 4_u32)
+        } else {
+            let value = if is_arm9 {
+                let v = self.arm9_read_halfword(internal_source) as u32;
+                self.arm9_write_halfword(internal_dest, v as u16);
+                v
+            } else {
+                let v = self.arm7_read_halfword(internal_source) as u32;
+                self.arm7_write_halfword(internal_dest, v as u16);
+                v
             };
+            (value, 2_u32)
+        };
+        #[cfg(feature = "block_cache")]
+        match is_arm9 {
+            true => self.code_written(CpuType::Arm9, internal_dest),
+            false => self.code_written(CpuType::Arm7, internal_dest),
+        }
 
-            // destination control
-            {
-                let active_dma = &mut self.dma.dmas[event_id as usize];
-                match active_dma.cnt.dest_control {
-                    0 | 3 => {
-                        active_dma.internal_dest = active_dma.internal_dest.wrapping_add(offset)
-                    }
-                    1 => active_dma.internal_dest = active_dma.internal_dest.wrapping_sub(offset),
-                    2 => {}
-                    v => {
-                        #[cfg(feature = "tracing")]
-                        tracing::error!("Unrecognized DMA dest control {v}");
-                    }
+        // destination control
+        {
+            let active_dma = &mut self.dma.dmas[index];
+            active_dma.internal_len += 1;
+            match active_dma.cnt.dest_control {
+                0 | 3 => active_dma.internal_dest = active_dma.internal_dest.wrapping_add(offset),
+                1 => active_dma.internal_dest = active_dma.internal_dest.wrapping_sub(offset),
+                2 => {}
+                v => {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("Unrecognized DMA dest control {v}");
                 }
             }
+        }
 
-            // source control
-            {
-                let active_dma = &mut self.dma.dmas[event_id as usize];
-                match active_dma.cnt.source_control {
-                    0 => {
-                        active_dma.internal_source = active_dma.internal_source.wrapping_add(offset)
-                    }
-                    1 => {
-                        active_dma.internal_source = active_dma.internal_source.wrapping_sub(offset)
-                    }
-                    2 => {}
-                    v => {
-                        #[cfg(feature = "tracing")]
-                        tracing::error!("Unrecognized DMA source control {v}");
-                    }
+        // source control
+        {
+            let active_dma = &mut self.dma.dmas[index];
+            match active_dma.cnt.source_control {
+                0 => active_dma.internal_source = active_dma.internal_source.wrapping_add(offset),
+                1 => active_dma.internal_source = active_dma.internal_source.wrapping_sub(offset),
+                2 => {}
+                v => {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("Unrecognized DMA source control {v}");
                 }
             }
+        }
+    }
 
-            // special ARM9 timing 7 behavior
-            let special_stop = {
-                let active_dma = &self.dma.dmas[event_id as usize];
-                active_dma.is_arm9 && active_dma.cnt.timing == 7 && active_dma.internal_len >= 112
-            };
-
-            if special_stop {
-                let active_dma = &mut self.dma.dmas[event_id as usize];
-                active_dma.internal_len = 0;
-                active_dma.length -= 112;
+    /// Transfer the rest of an ARM9 main RAM to VRAM DMA as one block copy.
+    ///
+    /// Returns `false` if the transfer has to go unit by unit.
+    fn dma_transfer_block(&mut self, index: usize) -> bool {
+        let dma = self.dma.dmas[index];
+        let incrementing = matches!(dma.cnt.dest_control, 0 | 3) && dma.cnt.source_control == 0;
+        // GXFIFO DMAs stop every 112 units
+        if !dma.is_arm9 || !incrementing || dma.cnt.timing == 7 {
+            return false;
+        }
 
-                self.dma.active_dmas &= (!(1 << event_id)) as u8;
-                return;
-            }
+        let unit = match dma.cnt.word_transfer {
+            true => 4,
+            false => 2,
+        };
+        let units = dma.length - dma.internal_len;
+        let len = units * unit;
+        if !self.dma_copy_to_vram(dma.internal_source, dma.internal_dest, len) {
+            return false;
         }
+
+        let active_dma = &mut self.dma.dmas[index];
+        active_dma.internal_source = active_dma.internal_source.wrapping_add(len);
+        active_dma.internal_dest = active_dma.internal_dest.wrapping_add(len);
+        active_dma.internal_len = active_dma.length;
+        true
     }
 
     /// Placeholder for DMA event processing.
@@ -179,14 +228,50 @@ impl Emulator {
         panic!("C++ code is not called.")
     }
 
+    /// Read a halfword of the DMA registers of one CPU.
+    pub(crate) fn dma_read_halfword(&self, cpu_type: CpuType, address: u32) -> u16 {
+        let (index, offset) = register_index(address, cpu_type == CpuType::Arm9);
+        self.dma.read_halfword(index, offset)
+    }
+
+    /// Write a halfword to the DMA registers of one CPU.
+    pub(crate) fn dma_write_halfword(&mut self, cpu_type: CpuType, address: u32, halfword: u16) {
+        let (index, offset) = register_index(address, cpu_type == CpuType::Arm9);
+        match offset {
+            10 => self.dma_write_cnt(index, halfword),
+            _ => self.dma.write_halfword(index, offset, halfword),
+        }
+    }
+
+    /// Write a word to the DMA registers of one CPU.
+    pub(crate) fn dma_write_word(&mut self, cpu_type: CpuType, address: u32, word: u32) {
+        let (index, offset) = register_index(address, cpu_type == CpuType::Arm9);
+        match offset {
+            0 => self.dma.write_source(index, word),
+            4 => self.dma.write_dest(index, word),
+            _ => self.dma_write_len_cnt(index, word),
+        }
+    }
+
     /// Write control register to DMA channel
     pub fn dma_write_cnt(&mut self, index: usize, cnt: u16) {
         let dma = &mut self.dma.dmas[index];
         let old_enabled = dma.cnt.enabled;
 
         dma.cnt.set(cnt);
+        if dma.is_arm9 {
+            // Bits 0-4 are the upper length bits
+            let length = (dma.length & 0xFFFF) | ((cnt as u32 & 0x1F) << 16);
+            self.dma.write_len(index, length);
+        }
 
-        if !old_enabled && (cnt & (1 << 15) != 0) {
+        let dma = &mut self.dma.dmas[index];
+        if !dma.cnt.enabled {
+            self.dma.active_dmas &= !(1 << index);
+            if self.dma.active_dmas == 0 {
+                self.scheduler.cancel(EventKind::Dma);
+            }
+        } else if !old_enabled {
             dma.internal_source = dma.source;
             dma.internal_dest = dma.destination;
             dma.internal_len = 0;
@@ -194,9 +279,9 @@ impl Emulator {
             match dma.cnt.timing {
                 0 => {
                     self.dma.active_dmas |= 1 << index;
-                    self.add_dma_event(index as i32, 0);
+                    self.schedule_event(EventKind::Dma, 0);
                 }
-                7 => self.check_gxfifo_dma(),
+                7 if dma.is_arm9 => self.check_gxfifo_dma(),
                 _ => {}
             }
         }
@@ -208,55 +293,113 @@ impl Emulator {
             return;
         }
 
-        self.dma.write_len(index, (word & 0xFFFF) as u16);
+        self.dma.write_len(index, word);
         self.dma_write_cnt(index, (word >> 16) as u16);
     }
 
-    /// Request HBLANK-triggered DMA transfers
-    pub fn hblank_request(&mut self) {
-        for i in 0..4 {
+    /// Start every enabled channel in `channels` waiting for `timing`.
+    fn dma_trigger(&mut self, channels: Range<usize>, timing: u32) {
+        for i in channels {
             let dma = &self.dma.dmas[i];
-            if dma.cnt.enabled && dma.cnt.timing == 2 && self.dma.active_dmas == 0 {
+            if dma.cnt.enabled && dma.cnt.timing == timing && self.dma.active_dmas & (1 << i) == 0 {
                 self.dma.active_dmas |= 1 << i;
-                self.add_dma_event(i as i32, 0);
-                break;
+                self.schedule_event(EventKind::Dma, 0);
             }
         }
     }
 
+    /// Request VBLANK-triggered DMA transfers on both CPUs
+    pub fn vblank_request(&mut self) {
+        self.dma_trigger(0..4, 1);
+        self.dma_trigger(4..8, 2);
+    }
+
+    /// Request HBLANK-triggered DMA transfers (ARM9 only)
+    pub fn hblank_request(&mut self) {
+        self.dma_trigger(0..4, 2);
+    }
+
+    /// Request DMA transfers synchronized to the start of the display
+    pub fn display_start_request(&mut self) {
+        self.dma_trigger(0..4, 3);
+    }
+
+    /// Request main memory display DMA transfers, at the start of a line
+    pub fn main_memory_display_request(&mut self) {
+        self.dma_trigger(0..4, 4);
+    }
+
     /// Request game cartridge DMA transfer
     pub fn gamecart_request(&mut self) {
-        if self.arm7_has_cart_rights() {
-            // NDS7 DMAs
-            for i in 4..8 {
-                let dma = &self.dma.dmas[i];
-                if dma.cnt.enabled && dma.cnt.timing == 4 {
-                    self.dma.active_dmas |= 1 << i;
-                    self.add_dma_event(i as i32, 0);
-                    break;
-                }
-            }
-        } else {
-            for i in 0..4 {
-                let dma = &self.dma.dmas[i];
-                if dma.cnt.enabled && dma.cnt.timing == 5 {
-                    self.dma.active_dmas |= 1 << i;
-                    self.add_dma_event(i as i32, 0);
-                    break;
-                }
-            }
+        match self.arm7_has_cart_rights() {
+            true => self.dma_trigger(4..8, 4),
+            false => self.dma_trigger(0..4, 5),
         }
     }
 
     /// Request GXFIFO DMA transfer
     pub fn gxfifo_request(&mut self) {
+        self.dma_trigger(0..4, 7);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vblank_dma_both_cpus() {
+        let mut emu = Box::new(Emulator::new());
+        emu.dma.power_on();
         for i in 0..4 {
-            let dma = &self.dma.dmas[i];
-            if dma.cnt.enabled && dma.cnt.timing == 7 {
-                self.dma.active_dmas |= 1 << i;
-                self.add_dma_event(i as i32, 0);
-                break;
-            }
+            emu.arm9_write_word(0x0200_0000 + i * 4, i + 1);
         }
+
+        // ARM9 DMA1: VBlank, repeat, 32-bit, IRQ; ARM7 DMA0: VBlank, 16-bit
+        emu.arm9_write_word(0x0400_00BC, 0x0200_0000);
+        emu.arm9_write_word(0x0400_00C0, 0x0210_0000);
+        emu.arm9_write_word(0x0400_00C4, 0xCE00_0004);
+        emu.arm7_write_word(0x0400_00B0, 0x0200_0000);
+        emu.arm7_write_word(0x0400_00B4, 0x0220_0000);
+        emu.arm7_write_word(0x0400_00B8, 0x9000_0002);
+        assert!(!emu.dma_active());
+
+        emu.vblank_request();
+        assert!(emu.dma.is_active_for(true) && emu.dma.is_active_for(false));
+        let arm9_timestamp = emu.arm9.timestamp;
+        emu.dma_handle_event();
+        assert!(!emu.dma_active());
+        assert!(emu.arm9.timestamp > arm9_timestamp);
+
+        let copied: Vec<u32> = (0..5)
+            .map(|i| emu.arm9_read_word(0x0210_0000 + i * 4))
+            .collect();
+        assert_eq!(copied, [1, 2, 3, 4, 0]);
+        assert_eq!(emu.arm7_read_word(0x0220_0000), 1);
+        assert_eq!(emu.arm7_read_word(0x0220_0004), 0);
+        assert_ne!(emu.int9_reg.irq_flags & (1 << Interrupt::Dma1 as u32), 0);
+
+        // Repeating channels stay enabled, one-shot ones do not
+        assert_eq!(emu.arm9_read_halfword(0x0400_00C6) >> 15, 1);
+        assert_eq!(emu.arm7_read_halfword(0x0400_00BA) >> 15, 0);
+    }
+
+    #[test]
+    fn test_arm9_length_bits() {
+        let mut emu = Box::new(Emulator::new());
+        emu.dma.power_on();
+        emu.arm9_write_word(0x0400_00DC, 0x0001_2345);
+        assert_eq!(emu.dma.dmas[3].length, 0x1_2345);
+        assert_eq!(emu.arm9_read_word(0x0400_00DC), 0x0001_2345);
+
+        // Halfword writes keep the other half
+        emu.arm9_write_halfword(0x0400_00DC, 0x0010);
+        emu.arm9_write_halfword(0x0400_00DE, 0x0002);
+        assert_eq!(emu.dma.dmas[3].length, 0x2_0010);
+
+        emu.arm7_write_word(0x0400_00B8, 0);
+        assert_eq!(emu.dma.dmas[4].length, 0x4000);
+        emu.arm7_write_halfword(0x0400_00B8, 0x1234);
+        assert_eq!(emu.dma.dmas[4].length, 0x1234);
     }
 }
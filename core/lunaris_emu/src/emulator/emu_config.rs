@@ -2,6 +2,14 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 //! emulator.hpp
 //!
+use std::path::PathBuf;
+
+use crate::emulator::accuracy::{AccuracyOverrides, AccuracyPreset};
+use crate::emulator::clock_stress::ClockStress;
+use crate::emulator::frame_timing::{DS_AUDIO_RATE, FrameTiming};
+use crate::emulator::run_mode::PausedAudio;
+use crate::firmware::FirmwareOverrides;
+use lunaris_ds_free_bios::firmware::DSType;
 use lunaris_ds_mem_const::*;
 
 #[derive(Debug)]
@@ -14,21 +22,81 @@ pub struct Config {
     /// Enable direct boot
     pub direct_boot_enabled: bool,
 
+    /// Patch real BIOS/firmware boot to skip the firmware menu
+    pub skip_intro: bool,
+
     /// Pause emulator when window is unfocused
     pub pause_when_unfocused: bool,
 
+    /// Audio behavior while paused
+    pub paused_audio: PausedAudio,
+
     /// Background enable flags
     pub bg_enable: [bool; 4],
-    pub frameskip: u32,
+
+    /// Speed/accuracy trade-off, see [`Config::accuracy`]
+    pub accuracy_preset: AccuracyPreset,
+
+    /// Individual settings pinned regardless of `accuracy_preset`
+    pub accuracy_overrides: AccuracyOverrides,
 
     /// Enable frame limiter
     pub enable_framelimiter: bool,
 
-    /// Use HLE BIOS
+    /// Also skip frames while emulation runs behind real time, see
+    /// [`Emulator::set_frameskip`](crate::Emulator::set_frameskip)
+    pub auto_frameskip: bool,
+
+    /// Pacing the frame limiter and audio resampling follow
+    pub frame_timing: FrameTiming,
+
+    /// Host audio rate in Hz that [`Emulator::get_samples`] and the audio
+    /// sink deliver at, resampled with [`Emulator::audio_rate`]
+    ///
+    /// [`Emulator::get_samples`]: crate::Emulator::get_samples
+    /// [`Emulator::audio_rate`]: crate::Emulator::audio_rate
+    pub audio_output_rate: f64,
+
+    /// Use HLE BIOS: SWIs and IRQ dispatch are emulated instead of running
+    /// the BIOS code
     pub hle_bios: bool,
 
+    /// Apply game-specific hacks, see [`GameHack`](crate::GameHack)
+    pub game_hacks: bool,
+
     /// Test mode
     pub test: bool,
+
+    /// Never write to disk: save data and SD card writes stay in memory
+    /// (firmware writes always do) and no crash bundles are written. For
+    /// archival verification and untrusted ROMs; set before loading a ROM
+    /// or mounting an SD card.
+    pub read_only: bool,
+
+    /// Named save profile to load, `None` for the default `game.sav`.
+    /// See [`save_profile`](crate::save_profile).
+    pub save_profile: Option<String>,
+
+    /// Language, birthday, favorite color and console type presented to games
+    pub firmware_overrides: FirmwareOverrides,
+
+    /// Console model to emulate (power management registers, backlight
+    /// levels, firmware console type). `None` follows the loaded firmware.
+    pub console_model: Option<DSType>,
+
+    /// Command line passed to homebrew on direct boot, `argv[0]` being the
+    /// ROM path (e.g. `fat:/game.nds`). Empty means no argv structure.
+    pub argv: Vec<String>,
+
+    /// Developer option: skew the ARM9:ARM7 clock ratio and sync granularity
+    /// to flush out synchronization bugs. Set before loading a ROM.
+    pub clock_stress: Option<ClockStress>,
+
+    /// Write a diagnostic bundle below this directory when the guest
+    /// crashes, see [`Emulator::crash_bundle`](crate::Emulator::crash_bundle).
+    /// `None` also turns off the instruction and IO recording it needs.
+    /// Nothing is written in [`read_only`](Self::read_only) mode.
+    pub crash_bundle_dir: Option<PathBuf>,
 }
 
 impl Default for Config {
@@ -46,154 +114,66 @@ impl Config {
             firmware_path: Default::default(),
             savelist_path: Default::default(),
             direct_boot_enabled: true,
+            skip_intro: Default::default(),
             pause_when_unfocused: Default::default(),
+            paused_audio: Default::default(),
             bg_enable: Default::default(),
-            frameskip: Default::default(),
+            accuracy_preset: Default::default(),
+            accuracy_overrides: Default::default(),
             enable_framelimiter: Default::default(),
+            auto_frameskip: false,
+            frame_timing: Default::default(),
+            audio_output_rate: DS_AUDIO_RATE,
             hle_bios: Default::default(),
+            game_hacks: true,
             test: Default::default(),
+            read_only: false,
+            save_profile: None,
+            firmware_overrides: Default::default(),
+            console_model: None,
+            argv: Default::default(),
+            clock_stress: None,
+            crash_bundle_dir: None,
         }
     }
 }
 
-/// Button input register for standard DS buttons
-#[derive(Debug, Clone, Copy, Default)]
-pub struct KeyInputReg {
-    pub button_a: bool,
-    pub button_b: bool,
-    pub select: bool,
-    pub start: bool,
-    pub right: bool,
-    pub left: bool,
-    pub up: bool,
-    pub down: bool,
-    pub button_r: bool,
-    pub button_l: bool,
-}
-
-impl KeyInputReg {
-    /// Get the current key input register value (bit-packed format)
-    pub fn get_value(&self) -> u16 {
-        let mut value = 0u16;
-        if self.button_a {
-            value |= 0x0001;
-        }
-        if self.button_b {
-            value |= 0x0002;
-        }
-        if self.select {
-            value |= 0x0004;
-        }
-        if self.start {
-            value |= 0x0008;
-        }
-        if self.right {
-            value |= 0x0010;
-        }
-        if self.left {
-            value |= 0x0020;
-        }
-        if self.up {
-            value |= 0x0040;
-        }
-        if self.down {
-            value |= 0x0080;
-        }
-        if self.button_r {
-            value |= 0x0100;
-        }
-        if self.button_l {
-            value |= 0x0200;
-        }
-        value
-    }
-
-    /// Set value from bit-packed register format
-    pub fn set_value(&mut self, value: u16) {
-        self.button_a = (value & 0x0001) != 0;
-        self.button_b = (value & 0x0002) != 0;
-        self.select = (value & 0x0004) != 0;
-        self.start = (value & 0x0008) != 0;
-        self.right = (value & 0x0010) != 0;
-        self.left = (value & 0x0020) != 0;
-        self.up = (value & 0x0040) != 0;
-        self.down = (value & 0x0080) != 0;
-        self.button_r = (value & 0x0100) != 0;
-        self.button_l = (value & 0x0200) != 0;
+lunaris_ds_bitfield::bitfield! {
+    /// Button input register for standard DS buttons
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct KeyInputReg(u16) {
+        pub button_a: bool [0],
+        pub button_b: bool [1],
+        pub select: bool [2],
+        pub start: bool [3],
+        pub right: bool [4],
+        pub left: bool [5],
+        pub up: bool [6],
+        pub down: bool [7],
+        pub button_r: bool [8],
+        pub button_l: bool [9],
     }
 }
 
-/// Extended key input register for additional buttons (X, Y, pen, hinge)
-#[derive(Debug, Clone, Copy, Default)]
-pub struct ExtKeyInReg {
-    pub button_x: bool,
-    pub button_y: bool,
-    pub pen_down: bool,
-    pub hinge_closed: bool,
-}
-
-impl ExtKeyInReg {
-    /// Get the extended key input register value
-    pub fn get_value(&self) -> u16 {
-        let mut value = 0u16;
-        if self.button_x {
-            value |= 0x0001;
-        }
-        if self.button_y {
-            value |= 0x0002;
-        }
-        if self.pen_down {
-            value |= 0x0004;
-        }
-        if self.hinge_closed {
-            value |= 0x0008;
-        }
-        value
-    }
-
-    /// Set value from bit-packed register format
-    pub fn set_value(&mut self, value: u16) {
-        self.button_x = (value & 0x0001) != 0;
-        self.button_y = (value & 0x0002) != 0;
-        self.pen_down = (value & 0x0004) != 0;
-        self.hinge_closed = (value & 0x0008) != 0;
+lunaris_ds_bitfield::bitfield! {
+    /// Extended key input register for additional buttons (X, Y, pen, hinge)
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct ExtKeyInReg(u16) {
+        pub button_x: bool [0],
+        pub button_y: bool [1],
+        pub pen_down: bool [6],
+        pub hinge_closed: bool [7],
     }
 }
 
-/// Power control register for power management
-#[derive(Debug, Clone, Copy, Default)]
-pub struct PowCnt2Reg {
-    pub speakers: bool,
-    pub wifi: bool,
-    pub led: bool,
-    pub cartridge: bool,
-}
-
-impl PowCnt2Reg {
-    /// Get the power control register value
-    pub fn get_value(&self) -> u16 {
-        let mut value = 0u16;
-        if self.speakers {
-            value |= 0x0001;
-        }
-        if self.wifi {
-            value |= 0x0002;
-        }
-        if self.led {
-            value |= 0x0004;
-        }
-        if self.cartridge {
-            value |= 0x0008;
-        }
-        value
-    }
-
-    /// Set value from bit-packed register format
-    pub fn set_value(&mut self, value: u16) {
-        self.speakers = (value & 0x0001) != 0;
-        self.wifi = (value & 0x0002) != 0;
-        self.led = (value & 0x0004) != 0;
-        self.cartridge = (value & 0x0008) != 0;
+lunaris_ds_bitfield::bitfield! {
+    /// Power control register for power management
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct PowCnt2Reg(u16) {
+        pub speakers: bool [0],
+        pub wifi: bool [1],
+        pub led: bool [2],
+        pub cartridge: bool [3],
     }
 }
 
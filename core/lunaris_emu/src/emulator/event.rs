@@ -0,0 +1,118 @@
+//! Events for the frontend
+//!
+//! Requests from the emulated console to the host, queued until the frontend
+//! polls them with [`Emulator::poll_event`]. Each event carries the
+//! [`Timestamps`] it was raised at, as do [trace events](crate::debug::TraceEvent).
+use crate::emulator::Emulator;
+use crate::interrupts::Interrupt;
+use crate::scheduler::EventKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemEvent {
+    /// The power management shutdown bit was written; the console is off and
+    /// the frontend should close.
+    ShutdownRequested,
+}
+
+/// Cycle counters at one point of emulation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timestamps {
+    /// System (ARM7 bus) cycles, the clock the scheduler runs on
+    pub system: u64,
+    /// ARM9 cycles, twice the system clock
+    pub arm9: u64,
+    pub arm7: u64,
+}
+
+/// A [`SystemEvent`] and when it was raised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimedEvent {
+    pub event: SystemEvent,
+    pub time: Timestamps,
+}
+
+impl Emulator {
+    /// Current cycle counters.
+    pub fn timestamps(&self) -> Timestamps {
+        Timestamps {
+            system: self.system_timestamp,
+            arm9: self.arm9.get_timestamp(),
+            arm7: self.arm7.get_timestamp(),
+        }
+    }
+
+    /// Next queued event, oldest first.
+    pub fn poll_event(&mut self) -> Option<TimedEvent> {
+        self.events.pop_front()
+    }
+
+    /// End of the SPI transfer started by the last SPIDATA write.
+    pub(crate) fn spi_handle_event(&mut self) {
+        if self.spi.finish_transfer() {
+            self.request_interrupt7(Interrupt::Spi);
+        }
+    }
+
+    fn push_event(&mut self, event: SystemEvent) {
+        let time = self.timestamps();
+        self.events.push_back(TimedEvent { event, time });
+    }
+
+    /// SPIDATA write from the ARM7.
+    pub(crate) fn write_spidata(&mut self, data: u8) {
+        if self.spi.write_spidata(data) {
+            self.schedule_event(EventKind::Serial, self.spi.transfer_cycles());
+        }
+        if self.spi.power.take_shutdown_request() {
+            self.push_event(SystemEvent::ShutdownRequested);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPICNT: u32 = 0x0400_01C0;
+    const SPIDATA: u32 = 0x0400_01C2;
+
+    #[test]
+    fn test_shutdown_event() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        // Enabled, power management device
+        emu.arm7_write_halfword(SPICNT, 0x8000);
+        emu.arm7_write_halfword(SPIDATA, 0x00);
+        emu.arm7_write_halfword(SPIDATA, 0x0D);
+        assert_eq!(emu.poll_event(), None);
+
+        emu.arm7_write_halfword(SPIDATA, 0x00);
+        emu.arm7.timestamp = 1234;
+        emu.arm7_write_byte(SPIDATA, 0x40);
+        let event = emu.poll_event().unwrap();
+        assert_eq!(event.event, SystemEvent::ShutdownRequested);
+        assert_eq!(event.time.arm7, 1234);
+        assert_eq!(emu.poll_event(), None);
+    }
+    #[test]
+    fn test_spi_transfer_takes_time() {
+        const BUSY: u16 = 1 << 7;
+        let spi_irq = 1 << Interrupt::Spi as u32;
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.run_due_events();
+        // Enabled, IRQ, firmware, 2MHz
+        emu.arm7_write_halfword(SPICNT, 0xC101);
+        emu.arm7_write_halfword(SPIDATA, 0x00);
+        assert_ne!(emu.arm7_read_halfword(SPICNT) & BUSY, 0);
+
+        emu.system_timestamp += 127;
+        emu.run_due_events();
+        assert_ne!(emu.arm7_read_halfword(SPICNT) & BUSY, 0);
+        assert_eq!(emu.int7_reg.irq_flags & spi_irq, 0);
+        emu.system_timestamp += 1;
+        emu.run_due_events();
+        assert_eq!(emu.arm7_read_halfword(SPICNT) & BUSY, 0);
+        assert_eq!(emu.int7_reg.irq_flags & spi_irq, spi_irq);
+    }
+}
@@ -0,0 +1,107 @@
+//! Pull-based frame stepping
+//!
+//! [`Emulator::run_frame`] runs exactly one frame, up to the next VBLANK,
+//! and hands back everything the frontend presents for it: both screens and
+//! the SPU mix sampled during the frame. The same state and input always
+//! give the same result, so it also suits movie playback and tests.
+use lunaris_ds_mem_const::{CYCLES_PER_FRAME, PIXELS_PER_LINE, SCANLINES};
+
+use crate::emulator::Emulator;
+use crate::emulator::audio_dump::CYCLES_PER_SAMPLE;
+
+/// Pixels of one screen.
+pub const SCREEN_PIXELS: usize = PIXELS_PER_LINE * SCANLINES;
+
+/// SPU samples in one frame, rounded down.
+const SAMPLES_PER_FRAME: usize = (CYCLES_PER_FRAME / CYCLES_PER_SAMPLE) as usize;
+
+/// Output of one frame, see [`Emulator::run_frame`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameResult {
+    /// Upper screen, ARGB8888, [`SCREEN_PIXELS`] long
+    pub upper: Vec<u32>,
+    /// Lower screen, ARGB8888, [`SCREEN_PIXELS`] long
+    pub lower: Vec<u32>,
+    /// Left/right SPU mix at [`DS_AUDIO_RATE`](crate::DS_AUDIO_RATE)
+    pub audio: Vec<[i16; 2]>,
+}
+
+/// Audio collected during [`Emulator::run_frame`].
+#[derive(Debug, Default)]
+pub struct FrameAudio {
+    samples: Vec<[i16; 2]>,
+    next_sample: u64,
+}
+
+impl Emulator {
+    /// Run one frame and return its screens and audio.
+    ///
+    /// Returns `None` without emulating anything while paused, like
+    /// [`Emulator::run`].
+    pub fn run_frame(&mut self) -> Option<FrameResult> {
+        // Sample on multiples of the sample period, so the samples only
+        // depend on the frame and not on earlier calls
+        self.frame_audio = Some(FrameAudio {
+            samples: Vec::with_capacity(SAMPLES_PER_FRAME + 1),
+            next_sample: (self.system_timestamp / CYCLES_PER_SAMPLE + 1) * CYCLES_PER_SAMPLE,
+        });
+        let ran = self.run();
+        let audio = self.frame_audio.take().unwrap_or_default().samples;
+        if !ran {
+            return None;
+        }
+        self.take_completed_frame();
+
+        let mut upper = vec![0; SCREEN_PIXELS];
+        let mut lower = vec![0; SCREEN_PIXELS];
+        self.get_upper_frame(&mut upper);
+        self.get_lower_frame(&mut lower);
+        Some(FrameResult {
+            upper,
+            lower,
+            audio,
+        })
+    }
+
+    /// Collect the samples due up to the current system time.
+    pub(crate) fn frame_audio_catch_up(&mut self) {
+        let Some(audio) = &mut self.frame_audio else {
+            return;
+        };
+        while audio.next_sample <= self.system_timestamp {
+            let mut output = self.spu.output();
+            if self.gba_mode {
+                self.direct_sound.mix_into(&mut output);
+            }
+            audio.samples.push(output.mix);
+            audio.next_sample += CYCLES_PER_SAMPLE;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_frame() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.run_frame().unwrap();
+        let frame = emu.run_frame().unwrap();
+        assert_eq!(frame.upper.len(), SCREEN_PIXELS);
+        assert_eq!(frame.lower.len(), SCREEN_PIXELS);
+        assert!((SAMPLES_PER_FRAME..=SAMPLES_PER_FRAME + 1).contains(&frame.audio.len()));
+        assert!(!emu.take_completed_frame());
+
+        emu.pause();
+        assert_eq!(emu.run_frame(), None);
+
+        // Same state, same frame
+        emu.resume();
+        let state = emu.save_state();
+        let first = emu.run_frame().unwrap();
+        emu.load_state(&state).unwrap();
+        assert_eq!(emu.run_frame().unwrap(), first);
+    }
+}
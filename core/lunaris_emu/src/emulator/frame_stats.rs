@@ -0,0 +1,26 @@
+//! Per-frame statistics for frontends to display.
+use crate::emulator::Emulator;
+
+/// Snapshot of the emulator's sync state, see [`Emulator::frame_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameStats {
+    /// Host audio buffer fill level in `0.0..=1.0`, as last reported
+    pub audio_buffer_fill: f64,
+    /// Dynamic rate control adjustment of the resampling ratio, e.g. `-0.001`
+    /// for -0.1%
+    pub audio_rate_adjust: f64,
+    /// Host audio buffer underruns since start
+    pub audio_underruns: u64,
+}
+
+impl Emulator {
+    /// Statistics of the last emulated frame.
+    pub fn frame_stats(&self) -> FrameStats {
+        let audio = self.audio_rate.stats();
+        FrameStats {
+            audio_buffer_fill: audio.fill(),
+            audio_rate_adjust: self.audio_rate.adjustment(),
+            audio_underruns: audio.underruns(),
+        }
+    }
+}
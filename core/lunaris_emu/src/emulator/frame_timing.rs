@@ -0,0 +1,92 @@
+//! Frame pacing
+//!
+//! Emulation always follows the DS LCD: 263 lines of 355 dots at 6 system
+//! cycles per dot, 2130 cycles per line, so a frame is
+//! [`CYCLES_PER_FRAME`] cycles and ~59.8261 fps. Audio is derived from the
+//! same clock. [`FrameTiming`] only decides how the frontend presents that:
+//! at the exact hardware rate, or sped up to a 60 Hz host display with audio
+//! resampled to match.
+use std::time::Duration;
+
+use lunaris_ds_mem_const::{CYCLES_PER_FRAME, SYSTEM_CLOCK_HZ};
+
+use crate::emulator::Emulator;
+
+/// Exact DS frame rate, ~59.8261 fps.
+pub const DS_FRAME_RATE: f64 = SYSTEM_CLOCK_HZ as f64 / CYCLES_PER_FRAME as f64;
+
+/// SPU output rate, one sample every 1024 system cycles (~32728.5 Hz).
+pub const DS_AUDIO_RATE: f64 = SYSTEM_CLOCK_HZ as f64 / 1024.0;
+
+/// How emulated frames are paced on the host.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FrameTiming {
+    /// Present frames at [`DS_FRAME_RATE`]; audio plays at its native rate.
+    #[default]
+    Exact,
+    /// Present one frame per 60 Hz host refresh, ~0.29% faster than the DS.
+    /// Audio is resampled by the same factor so it stays in sync.
+    Host60,
+}
+
+impl FrameTiming {
+    /// Frames presented per second.
+    pub fn frame_rate(self) -> f64 {
+        match self {
+            Self::Exact => DS_FRAME_RATE,
+            Self::Host60 => 60.0,
+        }
+    }
+
+    /// Host time one frame is presented for.
+    pub fn frame_duration(self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.frame_rate())
+    }
+
+    /// Playback speed relative to the DS, `1.0` for [`Self::Exact`].
+    pub fn speed(self) -> f64 {
+        self.frame_rate() / DS_FRAME_RATE
+    }
+}
+
+impl Emulator {
+    /// Rate emulated audio is resampled from to
+    /// [`Config::audio_output_rate`], with [`DynamicRateControl::ratio`]:
+    /// the SPU rate scaled by the [`FrameTiming`] speed.
+    ///
+    /// [`Config::audio_output_rate`]: crate::Config::audio_output_rate
+    /// [`DynamicRateControl::ratio`]: lunaris_ds_audio::DynamicRateControl::ratio
+    pub fn audio_input_rate(&self) -> f64 {
+        DS_AUDIO_RATE * self.config.frame_timing.speed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lunaris_ds_mem_const::CYCLES_PER_LINE;
+
+    #[test]
+    fn test_frame_timing() {
+        assert_eq!(CYCLES_PER_LINE, 2130);
+        assert!((DS_FRAME_RATE - 59.8261).abs() < 1e-4);
+        assert_eq!(FrameTiming::Exact.speed(), 1.0);
+
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.run();
+        let start = emu.system_timestamp;
+        for _ in 0..10 {
+            emu.run();
+        }
+        // Frames end on the first CPU slice past VBLANK start, so single
+        // frames jitter by up to a slice
+        let frames = emu.system_timestamp - start;
+        assert!(frames.abs_diff(10 * CYCLES_PER_FRAME) < CYCLES_PER_LINE);
+
+        // 60 Hz: one presented frame carries one DS frame of audio
+        emu.config.frame_timing = FrameTiming::Host60;
+        let samples = emu.audio_input_rate() / FrameTiming::Host60.frame_rate();
+        assert!((samples - CYCLES_PER_FRAME as f64 / 1024.0).abs() < 1e-9);
+    }
+}
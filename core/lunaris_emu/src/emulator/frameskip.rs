@@ -0,0 +1,91 @@
+//! Frame skipping
+//!
+//! A skipped frame runs exactly like a shown one, VCOUNT, IRQs, DMA and
+//! display capture included, but leaves the screens as they were and only
+//! composes the lines capture needs. The fixed count comes from
+//! [`AccuracySettings::frameskip`](crate::AccuracySettings::frameskip);
+//! with [`Config::auto_frameskip`](crate::Config::auto_frameskip) frames
+//! are also skipped while emulation lags behind real time.
+use std::time::{Duration, Instant};
+
+use crate::emulator::Emulator;
+
+/// Most frames auto frameskip drops in a row, so the picture still moves.
+const MAX_AUTO_SKIP: u32 = 4;
+/// Lag (or lead) past which auto frameskip restarts the clock instead of
+/// catching up, e.g. after a pause, a host stall or fast forward.
+const MAX_LAG: Duration = Duration::from_millis(250);
+
+/// Real time the frames since `start` were due at.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FrameClock {
+    start: Instant,
+    frames: u32,
+}
+
+impl Emulator {
+    /// Show one frame in `frameskip + 1`, and with `auto` also skip frames
+    /// while running slower than real time.
+    pub fn set_frameskip(&mut self, frameskip: u32, auto: bool) {
+        self.config.accuracy_overrides.frameskip = Some(frameskip);
+        self.config.auto_frameskip = auto;
+    }
+
+    /// Whether the frame starting now is skipped under the fixed count
+    /// alone, given the frames skipped in a row before it.
+    pub(crate) fn fixed_frame_skip(&self) -> bool {
+        self.gpu.frames_skipped < self.config.accuracy().frameskip
+    }
+
+    /// Decide whether the frame starting now is skipped.
+    pub(crate) fn frameskip_start_frame(&mut self) {
+        match self.skip_frame {
+            true => self.gpu.frames_skipped += 1,
+            false => self.gpu.frames_skipped = 0,
+        }
+        let behind = self.config.auto_frameskip && self.behind_real_time();
+        self.skip_frame =
+            self.fixed_frame_skip() || (behind && self.gpu.frames_skipped < MAX_AUTO_SKIP);
+    }
+
+    /// Tick the frame clock, `true` when more than a frame late.
+    fn behind_real_time(&mut self) -> bool {
+        let now = Instant::now();
+        let frame = self.config.frame_timing.frame_duration();
+        let Some(clock) = &mut self.frame_clock else {
+            self.frame_clock = Some(FrameClock {
+                start: now,
+                frames: 0,
+            });
+            return false;
+        };
+        clock.frames += 1;
+        let due = clock.start + frame * clock.frames;
+        let lag = now.saturating_duration_since(due);
+        if lag > MAX_LAG || due.saturating_duration_since(now) > MAX_LAG {
+            self.frame_clock = None;
+            return false;
+        }
+        lag > frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_frameskip() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.set_frameskip(2, false);
+        let skipped: Vec<bool> = (0..6)
+            .map(|_| {
+                emu.run_frame().unwrap();
+                emu.skip_frame
+            })
+            .collect();
+        assert_eq!(skipped.iter().filter(|&&skip| !skip).count(), 2);
+        assert!(skipped.windows(3).all(|w| w.contains(&false)));
+    }
+}
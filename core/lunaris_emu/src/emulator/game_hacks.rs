@@ -0,0 +1,120 @@
+//! Game-specific compatibility hacks
+//!
+//! A hack is a targeted workaround for one game, keyed by game code and
+//! header checksum so other revisions and romhacks are left alone. Hacks are
+//! a stopgap for a core bug, not a fix: each one names the bug it covers and
+//! is logged whenever it is applied, and [`Config::game_hacks`] turns them
+//! all off to check whether the core handles a game on its own.
+//!
+//! Matching hacks are applied right after [`Emulator::load_rom`];
+//! [`Emulator::active_game_hacks`] lists the ones in effect.
+//!
+//! [`Config::game_hacks`]: crate::Config::game_hacks
+use crate::emulator::Emulator;
+
+/// A workaround for one game.
+#[derive(Debug, Clone, Copy)]
+pub struct GameHack {
+    /// Short identifier shown in logs and [`Emulator::active_game_hacks`]
+    pub name: &'static str,
+    /// Game code at header 0x0C
+    pub game_code: [u8; 4],
+    /// Header CRC16 at 0x15E of the affected dump
+    pub header_checksum: u16,
+    /// The core bug the hack works around
+    pub reason: &'static str,
+    /// Applied once the ROM is loaded and the system powered on
+    pub apply: fn(&mut Emulator),
+}
+
+impl GameHack {
+    /// Whether the hack targets the ROM identified by `game_code` and
+    /// `header_checksum`.
+    pub fn matches(&self, game_code: [u8; 4], header_checksum: u16) -> bool {
+        self.game_code == game_code && self.header_checksum == header_checksum
+    }
+}
+
+/// Hacks shipped with the emulator. Keep this short: every entry is a core
+/// bug waiting for a real fix.
+pub const BUILTIN_GAME_HACKS: &[GameHack] = &[];
+
+impl Emulator {
+    /// Add `hack` to the registry, replacing a hack of the same name. It
+    /// takes effect on the next [`Emulator::load_rom`].
+    pub fn register_game_hack(&mut self, hack: GameHack) {
+        self.game_hacks.retain(|known| known.name != hack.name);
+        self.game_hacks.push(hack);
+    }
+
+    /// Names of the hacks applied to the loaded ROM.
+    pub fn active_game_hacks(&self) -> &[&'static str] {
+        &self.active_game_hacks
+    }
+
+    /// Apply the registered hacks matching the loaded ROM.
+    pub(crate) fn apply_game_hacks(&mut self) {
+        self.active_game_hacks.clear();
+        let game_code = self.cart.game_code();
+        let checksum = self.cart.header_checksum();
+        let hacks: Vec<GameHack> = self
+            .game_hacks
+            .iter()
+            .filter(|hack| hack.matches(game_code, checksum))
+            .copied()
+            .collect();
+
+        for hack in hacks {
+            if !self.config.game_hacks {
+                #[cfg(feature = "tracing")]
+                tracing::info!("Game hack {} not applied, hacks are disabled", hack.name);
+                continue;
+            }
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Game hack {} active for {}: {}",
+                hack.name,
+                String::from_utf8_lossy(&game_code),
+                hack.reason
+            );
+            (hack.apply)(self);
+            self.active_game_hacks.push(hack.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_hacks() {
+        let mut emu = Box::new(Emulator::new());
+        emu.cart.rom = vec![0; 0x200];
+        emu.cart.rom[0x0C..0x10].copy_from_slice(b"ATST");
+        emu.cart.rom[0x15E..0x160].copy_from_slice(&0xBEEF_u16.to_le_bytes());
+
+        let hack = GameHack {
+            name: "test-wram",
+            game_code: *b"ATST",
+            header_checksum: 0xBEEF,
+            reason: "test",
+            apply: |emu| emu.main_ram[0] = 1,
+        };
+        emu.register_game_hack(GameHack {
+            header_checksum: 0,
+            name: "other-revision",
+            ..hack
+        });
+        emu.register_game_hack(hack);
+        emu.apply_game_hacks();
+        assert_eq!(emu.active_game_hacks(), ["test-wram"]);
+        assert_eq!(emu.main_ram[0], 1);
+
+        emu.main_ram[0] = 0;
+        emu.config.game_hacks = false;
+        emu.apply_game_hacks();
+        assert!(emu.active_game_hacks().is_empty());
+        assert_eq!(emu.main_ram[0], 0);
+    }
+}
@@ -21,9 +21,7 @@ impl Emulator {
             if self.gpu.engine_3d.cmd_param_count >= CMD_PARAM_AMOUNTS[cmd_index] {
                 self.gpu.engine_3d.cycles += CMD_CYCLE_AMOUNTS[cmd_index] as i64;
 
-                // Update GEO_BUSY flag:
-                self.gpu.engine_3d.gxstat.geo_busy =
-                    self.gpu.engine_3d.cycles > 0 && !self.gpu.engine_3d.gxpipe.is_empty();
+                self.gpu.engine_3d.update_geo_busy();
 
                 match cmd_index {
                     0x00 => (),
@@ -505,28 +503,14 @@ impl Emulator {
                         #[cfg(feature = "tracing")]
                         tracing::info!("LIGHT_VECTOR");
 
-                        let light_vector = [
-                            ((self.gpu.engine_3d.cmd_params[0] & 0x3FF) << 6) >> 6,
-                            (((self.gpu.engine_3d.cmd_params[0] >> 10) & 0x3FF) << 6) >> 6,
-                            (((self.gpu.engine_3d.cmd_params[0] >> 20) & 0x3FF) << 6) >> 6,
-                        ];
-                        let light_vector_i32 = light_vector.map(|x| x as i32);
-                        let index = (self.gpu.engine_3d.cmd_params[0] >> 30) as usize;
-
-                        for i in 0..3 {
-                            self.gpu.engine_3d.light_direction[index][i] =
-                                ((light_vector_i32[0] * self.gpu.engine_3d.vector_mtx.m[0][i]
-                                    + light_vector_i32[1] * self.gpu.engine_3d.vector_mtx.m[1][i]
-                                    + light_vector_i32[2] * self.gpu.engine_3d.vector_mtx.m[2][i])
-                                    >> 12) as i16;
-                        }
+                        self.gpu.engine_3d.light_vector();
                     }
                     0x33 => {
                         #[cfg(feature = "tracing")]
                         tracing::info!("LIGHT_COLOR: {}", self.gpu.engine_3d.cmd_params[0]);
                         let index = (self.gpu.engine_3d.cmd_params[0] >> 30) as usize;
                         self.gpu.engine_3d.light_color[index] =
-                            (self.gpu.engine_3d.cmd_params[0] & 0x7FF) as u16;
+                            (self.gpu.engine_3d.cmd_params[0] & 0x7FFF) as u16;
                     }
                     0x34 => {
                         #[cfg(feature = "tracing")]
@@ -565,6 +549,7 @@ impl Emulator {
                         self.gpu.engine_3d.viewport(param)
                     }
                     0x70 => self.gpu.engine_3d.box_test(),
+                    0x71 => self.gpu.engine_3d.pos_test(),
                     0x72 => self.gpu.engine_3d.vec_test(),
                     _ => {
                         #[cfg(feature = "tracing")]
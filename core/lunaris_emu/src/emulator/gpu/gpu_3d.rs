@@ -3,10 +3,14 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 //! gpu3d.hpp
 
+use std::time::Instant;
+
 use crate::Emulator;
 use crate::interrupts::Interrupt;
-use lunaris_ds_gpu::gpu_3d::consts::{CMD_PARAM_AMOUNTS, SCANLINES};
+use crate::scheduler::EventKind;
+use lunaris_ds_gpu::gpu_3d::consts::CMD_PARAM_AMOUNTS;
 use lunaris_ds_gpu::gpu_3d::structs::GxCommand;
+use lunaris_ds_mem_const::{CYCLES_PER_LINE, HDRAW_CYCLES, LINES_PER_FRAME, SCANLINES};
 
 impl Emulator {
     /// - Instead of
@@ -33,7 +37,7 @@ impl Emulator {
     }
 
     pub fn read_command(&mut self) -> Option<GxCommand> {
-        let cmd = self.gpu.engine_3d.gxpipe.front().cloned();
+        let cmd = self.gpu.engine_3d.gxpipe.pop_front();
 
         // Refill the pipe if it is at least half-empty
         let gxpipe_len = self.gpu.engine_3d.gxpipe.len();
@@ -43,14 +47,14 @@ impl Emulator {
             {
                 let front = front.clone();
                 self.gpu.engine_3d.gxpipe.push_back(front);
-                self.gpu.engine_3d.gxfifo.pop_back();
+                self.gpu.engine_3d.gxfifo.pop_front();
             }
             if !self.gpu.engine_3d.gxfifo.is_empty()
                 && let Some(front) = self.gpu.engine_3d.gxfifo.front()
             {
                 let front = front.clone();
                 self.gpu.engine_3d.gxpipe.push_back(front);
-                self.gpu.engine_3d.gxfifo.pop_back();
+                self.gpu.engine_3d.gxfifo.pop_front();
             }
 
             self.check_fifo_dma();
@@ -80,19 +84,28 @@ impl Emulator {
         tracing::info!(?cmd.command, ?cmd.param);
 
         match self.gpu.engine_3d.gxfifo.is_empty() && self.gpu.engine_3d.gxpipe.len() < 4 {
-            true => self.gpu.engine_3d.gxpipe.push_back(cmd),
+            true => {
+                self.gpu.engine_3d.gxpipe.push_back(cmd);
+                self.gpu.engine_3d.update_geo_busy();
+            }
             false => {
-                if self.gpu.engine_3d.gxfifo.len() >= 256 {
-                    while self.gpu.engine_3d.gxfifo.len() >= 256 {
-                        self.exec_command();
-                    }
+                // A full FIFO stalls the CPU until there is room again. While
+                // the engine waits for VBlank after SWAP_BUFFERS nothing can
+                // make room, so keep queuing instead of running commands early.
+                while self.gpu.engine_3d.gxfifo.len() >= 256 && !self.gpu.engine_3d.swap_buffers {
+                    self.exec_command();
                 }
                 self.gpu.engine_3d.gxfifo.push_back(cmd);
             }
         }
+        self.gx_schedule();
     }
 
     pub fn set_gxstat(&mut self, word: u32) {
+        // Writing 1 acknowledges a matrix stack overflow
+        if word & (1 << 15) != 0 {
+            self.gpu.engine_3d.gxstat.mtx_overflow = false;
+        }
         self.gpu.engine_3d.gxstat.gxfifo_irq_stat = ((word >> 30) & 0x3) as i32;
         self.check_fifo_irq();
     }
@@ -154,19 +167,50 @@ impl Emulator {
         }
     }
 
-    pub fn gpu_handle_event(&mut self) {
-        match self.gpu_event.id {
-            0 => {
+    /// Compare the new line against both DISPSTAT VCOUNT settings.
+    ///
+    /// The flag and IRQ apply from the start of the matching line.
+    fn check_vcount_match(&mut self) {
+        let line = self.gpu.vertical_count;
+
+        self.gpu.display_status_arm7.is_vcounter = line == self.gpu.display_status_arm7.vcounter;
+        if self.gpu.display_status_arm7.is_vcounter && self.gpu.display_status_arm7.irq_on_vcounter
+        {
+            self.request_interrupt7(Interrupt::VCountMatch);
+        }
+
+        self.gpu.display_status_arm9.is_vcounter = line == self.gpu.display_status_arm9.vcounter;
+        if self.gpu.display_status_arm9.is_vcounter && self.gpu.display_status_arm9.irq_on_vcounter
+        {
+            self.request_interrupt9(Interrupt::VCountMatch);
+        }
+    }
+
+    /// Handle a line event due at `due`.
+    ///
+    /// The next one is scheduled relative to `due` rather than to now, so
+    /// handling it late does not stretch the line and frames stay exactly
+    /// `CYCLES_PER_FRAME` long.
+    pub fn gpu_handle_event(&mut self, event: EventKind, due: u64) {
+        match event {
+            EventKind::HBlankStart => {
                 // Start HBLANK
                 #[cfg(feature = "tracing")]
                 tracing::debug!("Start HBLANK");
 
                 // If the current line is within the visible screen
                 // and the frameskip condition is met, the GPU draws this line.
-                if (self.gpu.vertical_count as usize) < SCANLINES
-                    && self.gpu.frames_skipped >= self.config.frameskip
-                {
-                    self.gpu.draw_scanline();
+                // Skipped frames still run capture and the display FIFO.
+                if (self.gpu.vertical_count as usize) < SCANLINES {
+                    #[cfg(feature = "tracing")]
+                    let _span =
+                        tracing::trace_span!("scanline", line = self.gpu.vertical_count).entered();
+                    let start = Instant::now();
+                    match self.skip_frame {
+                        true => self.gpu.skip_scanline(),
+                        false => self.gpu.draw_scanline(),
+                    }
+                    self.counters.render_time += start.elapsed();
                 }
 
                 self.gpu.display_status_arm7.is_hblank = true;
@@ -182,12 +226,10 @@ impl Emulator {
                     self.hblank_dma_request();
                 }
 
-                #[cfg(feature = "tracing")]
-                tracing::debug!("Add_gpu_event: 1");
-
-                self.add_gpu_event(1, 99 * 6);
+                self.scheduler
+                    .schedule(EventKind::HBlankEnd, due + CYCLES_PER_LINE - HDRAW_CYCLES);
             }
-            1 => {
+            EventKind::HBlankEnd => {
                 // End HBLANK
                 #[cfg(feature = "tracing")]
                 tracing::debug!("End HBLANK");
@@ -195,22 +237,6 @@ impl Emulator {
                 self.gpu.display_status_arm7.is_hblank = false;
                 self.gpu.display_status_arm9.is_hblank = false;
 
-                self.gpu.display_status_arm7.is_vcounter =
-                    self.gpu.vertical_count == self.gpu.display_status_arm7.vcounter;
-                if self.gpu.display_status_arm7.is_vcounter
-                    && self.gpu.display_status_arm7.irq_on_vcounter
-                {
-                    self.request_interrupt7(Interrupt::VCountMatch);
-                }
-
-                self.gpu.display_status_arm9.is_vcounter =
-                    self.gpu.vertical_count == self.gpu.display_status_arm9.vcounter;
-                if self.gpu.display_status_arm9.is_vcounter
-                    && self.gpu.display_status_arm9.irq_on_vcounter
-                {
-                    self.request_interrupt9(Interrupt::VCountMatch);
-                }
-
                 self.gpu.vertical_count += 1;
                 // VBLANK Counter
                 #[cfg(feature = "tracing")]
@@ -222,7 +248,8 @@ impl Emulator {
                     tracing::debug!("Start VBLANK");
 
                     self.gpu.engine_3d.end_of_frame();
-                    self.gpu.frame_complete = true;
+                    self.gx_schedule();
+                    self.gpu.end_frame();
                     if self.gpu.display_status_arm7.irq_on_vblank {
                         self.request_interrupt7(Interrupt::VBlank);
                     }
@@ -234,24 +261,104 @@ impl Emulator {
 
                     self.gpu.engine_upper.vblank_start();
                     self.gpu.engine_lower.vblank_start();
+                    self.vblank_request();
                 }
-                if self.gpu.vertical_count == 263 {
+                if self.gpu.vertical_count == LINES_PER_FRAME {
                     self.gpu.vertical_count = 0;
                     self.gpu.display_status_arm7.is_vblank = false;
                     self.gpu.display_status_arm9.is_vblank = false;
-
-                    match self.gpu.frames_skipped >= self.config.frameskip {
-                        true => self.gpu.frames_skipped = 0,
-                        false => self.gpu.frames_skipped += 1,
-                    }
+                    self.frameskip_start_frame();
+                }
+                self.check_vcount_match();
+                if self.gpu.vertical_count == 0 {
+                    self.display_start_request();
+                }
+                if (self.gpu.vertical_count as usize) < SCANLINES {
+                    self.main_memory_display_request();
                 }
 
-                self.add_gpu_event(0, 256 * 6);
+                self.scheduler
+                    .schedule(EventKind::HBlankStart, due + HDRAW_CYCLES);
             }
-            unknown_id => {
+            _ => {
                 #[cfg(feature = "tracing")]
-                tracing::error!("Unrecognized event.id: {unknown_id}");
+                tracing::error!("Not a line event: {event:?}");
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISPSTAT_IRQ_ON_VCOUNTER: u16 = 1 << 5;
+
+    /// Run line events until VCOUNT reaches `line`.
+    fn run_to_line(emu: &mut Emulator, line: u16) {
+        while emu.gpu.vertical_count != line {
+            emu.gpu_handle_event(EventKind::HBlankEnd, emu.system_timestamp);
+        }
+    }
+
+    #[test]
+    fn test_vcount_match_above_255() {
+        let mut emu = Box::new(Emulator::new());
+        // Compare line 260: 0x04 in bits 8-15, bit 8 in bit 7
+        let dispstat = 0x0400 | 0x80 | DISPSTAT_IRQ_ON_VCOUNTER;
+        emu.gpu.set_dispstat9(dispstat);
+        assert_eq!(emu.gpu.display_status_arm9.vcounter, 260);
+        assert_eq!(emu.gpu.get_dispstat9() & 0xFFB8, dispstat);
+
+        run_to_line(&mut emu, 4);
+        assert!(!emu.gpu.display_status_arm9.is_vcounter);
+        assert_eq!(
+            emu.int9_reg.irq_flags & (1 << Interrupt::VCountMatch as u32),
+            0
+        );
+
+        run_to_line(&mut emu, 260);
+        assert!(emu.gpu.display_status_arm9.is_vcounter);
+        assert_ne!(
+            emu.int9_reg.irq_flags & (1 << Interrupt::VCountMatch as u32),
+            0
+        );
+
+        run_to_line(&mut emu, 261);
+        assert!(!emu.gpu.display_status_arm9.is_vcounter);
+    }
+
+    #[test]
+    fn test_test_results_by_mmio() {
+        const MTX_MODE: u32 = 0x0400_0440;
+        const MTX_IDENTITY: u32 = 0x0400_0454;
+        const POS_TEST: u32 = 0x0400_05C4;
+        const VEC_TEST: u32 = 0x0400_05C8;
+
+        let mut emu = Box::new(Emulator::new());
+        for mode in [0, 2] {
+            emu.arm9_write_word(MTX_MODE, mode);
+            emu.arm9_write_word(MTX_IDENTITY, 0);
+        }
+        // (1.0, 2.0, -1.0)
+        emu.arm9_write_word(POS_TEST, 0x2000_1000);
+        emu.arm9_write_word(POS_TEST, 0xF000);
+        // (0.5, -0.5, 0.0) in 1.0.9
+        emu.arm9_write_word(VEC_TEST, 0x100 | (0x300 << 10));
+        emu.gpu3d_run(10_000);
+
+        let pos: Vec<u32> = (0..4)
+            .map(|i| emu.arm9_read_word(0x0400_0620 + i * 4))
+            .collect();
+        assert_eq!(pos, [0x1000, 0x2000, (-0x1000i32) as u32, 0x1000]);
+        assert_eq!(emu.arm9_read_halfword(0x0400_0630), 0x0800);
+        assert_eq!(emu.arm9_read_halfword(0x0400_0632), 0xF800);
+        assert_eq!(emu.arm9_read_word(0x0400_0634), 0);
+
+        // Identity clip matrix, and the 3x3 vector matrix
+        assert_eq!(emu.arm9_read_word(0x0400_0654), 0x1000);
+        assert_eq!(emu.arm9_read_halfword(0x0400_0658), 0);
+        assert_eq!(emu.arm9_read_word(0x0400_0690), 0x1000);
+        assert_eq!(emu.arm9_read_word(0x0400_0400), 0);
+    }
+}
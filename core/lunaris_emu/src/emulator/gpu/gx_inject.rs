@@ -0,0 +1,301 @@
+//! GX command injection for tests
+//!
+//! Runs geometry commands straight through the command executor, without
+//! FIFO timing or a ROM, so clipping, lighting and matrix code can be tested
+//! in isolation. Only built for tests or with the `gx-test` feature.
+use crate::Emulator;
+use lunaris_ds_gpu::gpu_3d::consts::CMD_PARAM_AMOUNTS;
+use lunaris_ds_gpu::gpu_3d::structs::{GxCommand, Matrix, Polygon, Vertex};
+
+impl Emulator {
+    /// Execute one GX command (e.g. `0x23` VTX_16) with its parameters.
+    ///
+    /// The command runs to completion immediately; geometry engine cycles are
+    /// reset afterwards so the next command does not inherit a busy state.
+    ///
+    /// # Panics
+    /// If `params` does not match the parameter count of `command`.
+    pub fn gx_run_command(&mut self, command: u8, params: &[u32]) {
+        let expected = CMD_PARAM_AMOUNTS[command as usize] as usize;
+        assert_eq!(
+            params.len(),
+            expected,
+            "GX command {command:#04X} takes {expected} parameters"
+        );
+
+        // Parameterless commands still occupy one pipe entry
+        let params = match params.is_empty() {
+            true => &[0][..],
+            false => params,
+        };
+        for &param in params {
+            self.gpu
+                .engine_3d
+                .gxpipe
+                .push_back(GxCommand { command, param });
+        }
+
+        while !self.gpu.engine_3d.gxpipe.is_empty() {
+            self.exec_command();
+        }
+        self.gpu.engine_3d.cycles = 0;
+        self.gpu.engine_3d.gxstat.geo_busy = false;
+    }
+
+    /// Execute a sequence of `(command, params)` pairs.
+    ///
+    /// # Panics
+    /// See [`Self::gx_run_command`].
+    pub fn gx_run_commands(&mut self, commands: &[(u8, &[u32])]) {
+        for &(command, params) in commands {
+            self.gx_run_command(command, params);
+        }
+    }
+
+    /// Vertices submitted since the last SWAP_BUFFERS.
+    pub fn gx_vertices(&self) -> &[Vertex] {
+        let count = self.gpu.engine_3d.geo_vert_count.max(0) as usize;
+        &self.gpu.engine_3d.geo_vert[..count]
+    }
+
+    /// Polygons submitted since the last SWAP_BUFFERS.
+    pub fn gx_polygons(&self) -> &[Polygon] {
+        let count = self.gpu.engine_3d.geo_poly_count.max(0) as usize;
+        &self.gpu.engine_3d.geo_poly[..count]
+    }
+
+    /// Current clip matrix (projection * position).
+    pub fn gx_clip_matrix(&mut self) -> Matrix {
+        self.gpu.engine_3d.update_clip_matrix();
+        self.gpu.engine_3d.clip_mtx.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lunaris_ds_mem_const::{PIXELS_PER_LINE, SCANLINES};
+
+    const MTX_MODE: u8 = 0x10;
+    const MTX_IDENTITY: u8 = 0x15;
+    const MTX_TRANS: u8 = 0x1C;
+    const COLOR: u8 = 0x20;
+    const NORMAL: u8 = 0x21;
+    const POLYGON_ATTR: u8 = 0x29;
+    const DIF_AMB: u8 = 0x30;
+    const SPE_EMI: u8 = 0x31;
+    const LIGHT_VECTOR: u8 = 0x32;
+    const LIGHT_COLOR: u8 = 0x33;
+    const BEGIN_VTXS: u8 = 0x40;
+    const END_VTXS: u8 = 0x41;
+    const VTX_16: u8 = 0x23;
+    const SWAP_BUFFERS: u8 = 0x50;
+    const VIEWPORT: u8 = 0x60;
+    /// POLYGON_ATTR: front and back faces, opaque
+    const OPAQUE: u32 = (0x1F << 16) | 0xC0;
+    const RENDER_1DOT: u32 = 1 << 13;
+
+    /// 1.0 in 4.12 fixed point
+    const ONE: i16 = 0x1000;
+
+    const fn xy(x: i16, y: i16) -> u32 {
+        (x as u16 as u32) | ((y as u16 as u32) << 16)
+    }
+
+    fn identity(emu: &mut Emulator) {
+        for mode in [0, 2] {
+            emu.gx_run_command(MTX_MODE, &[mode]);
+            emu.gx_run_command(MTX_IDENTITY, &[]);
+        }
+    }
+
+    #[test]
+    fn test_translate_matrix() {
+        let mut emu = Emulator::new();
+        identity(&mut emu);
+        emu.gx_run_command(MTX_TRANS, &[0x2000, 0, 0x1000]);
+
+        let clip = emu.gx_clip_matrix();
+        assert_eq!(clip.m[3][0], 0x2000);
+        assert_eq!(clip.m[3][2], 0x1000);
+    }
+
+    #[test]
+    fn test_triangle_inside_view() {
+        let mut emu = Emulator::new();
+        identity(&mut emu);
+
+        emu.gx_run_commands(&[
+            // Front and back faces, opaque
+            (POLYGON_ATTR, &[(0x1F << 16) | 0xC0]),
+            (BEGIN_VTXS, &[0]),
+            (VTX_16, &[xy(0, 0), 0]),
+            (VTX_16, &[xy(ONE / 2, 0), 0]),
+            (VTX_16, &[xy(0, ONE / 2), 0]),
+            (END_VTXS, &[]),
+        ]);
+
+        assert_eq!(emu.gx_polygons().len(), 1);
+        assert_eq!(emu.gx_polygons()[0].vertices, 3);
+        assert_eq!(emu.gx_vertices().len(), 3);
+    }
+
+    #[test]
+    fn test_normal_lighting() {
+        const RED: u32 = 0x001F;
+        let mut emu = Emulator::new();
+        identity(&mut emu);
+
+        emu.gx_run_commands(&[
+            // Light 0 white, pointing away from the viewer along -z
+            (LIGHT_VECTOR, &[0x200 << 20]),
+            (LIGHT_COLOR, &[0x7FFF]),
+            // Red diffuse, dim blue emission
+            (DIF_AMB, &[RED]),
+            (SPE_EMI, &[4 << 26]),
+            (POLYGON_ATTR, &[OPAQUE | 1]),
+            (BEGIN_VTXS, &[0]),
+            // Facing the light
+            (NORMAL, &[0x1FF << 20]),
+        ]);
+        assert_eq!(emu.gpu.engine_3d.light_direction[0], [0, 0, -0x200]);
+        assert_eq!(emu.gpu.engine_3d.current_color, 29 | (4 << 10));
+
+        // Light 0 disabled, emission only
+        emu.gx_run_commands(&[
+            (POLYGON_ATTR, &[OPAQUE]),
+            (BEGIN_VTXS, &[0]),
+            (NORMAL, &[0x1FF << 20]),
+        ]);
+        assert_eq!(emu.gpu.engine_3d.current_color, 4 << 10);
+    }
+
+    /// Draw a triangle from `vertices`.
+    fn draw_triangle(emu: &mut Emulator, attr: u32, vertices: [(i16, i16); 3]) {
+        emu.gx_run_commands(&[(POLYGON_ATTR, &[attr]), (BEGIN_VTXS, &[0])]);
+        for (x, y) in vertices {
+            emu.gx_run_command(VTX_16, &[xy(x, y), 0]);
+        }
+        emu.gx_run_command(END_VTXS, &[]);
+    }
+
+    /// Swap the polygons drawn so far into the render buffers.
+    fn swap(emu: &mut Emulator, flush_mode: u32) {
+        emu.gx_run_command(SWAP_BUFFERS, &[flush_mode]);
+        emu.gpu.engine_3d.end_of_frame();
+    }
+
+    /// Draw a triangle from `vertices` and swap it into the render buffers.
+    fn draw_and_swap(emu: &mut Emulator, attr: u32, vertices: [(i16, i16); 3]) {
+        draw_triangle(emu, attr, vertices);
+        swap(emu, 0);
+    }
+
+    #[test]
+    fn test_viewport_clamped_to_screen() {
+        let mut emu = Emulator::new();
+        identity(&mut emu);
+        // Full screen, Y2 past the last line
+        emu.gx_run_command(VIEWPORT, &[0xFFFF_0000]);
+        draw_and_swap(&mut emu, OPAQUE, [(0, 0), (ONE, 0), (0, ONE)]);
+
+        let engine = &emu.gpu.engine_3d;
+        assert_eq!(engine.rend_poly_count, 1);
+        let coords: Vec<_> = engine.rend_vert[..3]
+            .iter()
+            .map(|v| (v.coords[0], v.coords[1]))
+            .collect();
+        assert_eq!(coords, [(128, 96), (255, 96), (128, 0)]);
+    }
+
+    #[test]
+    fn test_one_dot_depth() {
+        let tiny = [(0, 0), (1, 0), (0, -1)];
+        let mut emu = Emulator::new();
+        identity(&mut emu);
+        emu.gx_run_command(VIEWPORT, &[0xBFFF_0000]);
+
+        // W = 1.0 is behind a threshold of 0
+        emu.arm9_write_halfword(0x0400_0610, 0);
+        draw_and_swap(&mut emu, OPAQUE, tiny);
+        assert_eq!(emu.gpu.engine_3d.rend_poly_count, 0);
+        draw_and_swap(&mut emu, OPAQUE | RENDER_1DOT, tiny);
+        assert_eq!(emu.gpu.engine_3d.rend_poly_count, 1);
+
+        emu.arm9_write_halfword(0x0400_0610, 0x7FFF);
+        draw_and_swap(&mut emu, OPAQUE, tiny);
+        assert_eq!(emu.gpu.engine_3d.rend_poly_count, 1);
+    }
+
+    /// Compose every visible line, as the HBlank events do, then start VBlank.
+    fn draw_frame(emu: &mut Emulator) {
+        for line in 0..SCANLINES as u16 {
+            emu.gpu.vertical_count = line;
+            emu.gpu.draw_scanline();
+        }
+        emu.gpu.engine_upper.vblank_start();
+    }
+
+    #[test]
+    fn test_translucent_polygons() {
+        const DISP3DCNT_ALPHA_BLENDING: u32 = 1 << 3;
+        /// POLYGON_ATTR: front and back faces, alpha 15, polygon ID `id`
+        const fn translucent(id: u32) -> u32 {
+            (id << 24) | (15 << 16) | 0xC0
+        }
+        let upper = [(-ONE, 0), (ONE, 0), (-ONE, ONE)];
+        let lower = [(-ONE, -ONE), (ONE, -ONE), (-ONE, 0)];
+        let full = [(-ONE, -ONE), (ONE, -ONE), (-ONE, ONE)];
+        let ids = |emu: &Emulator| -> Vec<i32> {
+            let engine = &emu.gpu.engine_3d;
+            let polygons = &engine.rend_poly[..engine.rend_poly_count as usize];
+            polygons.iter().map(|poly| poly.attributes.id).collect()
+        };
+
+        let mut emu = Emulator::new();
+        identity(&mut emu);
+        emu.gx_run_command(VIEWPORT, &[0xBFFF_0000]);
+
+        // Opaque polygons come first, translucent ones sorted by Y unless
+        // the flush mode keeps them in order
+        for flush_mode in [0, 1] {
+            draw_triangle(&mut emu, translucent(1), lower);
+            draw_triangle(&mut emu, OPAQUE | (2 << 24), lower);
+            draw_triangle(&mut emu, translucent(3), upper);
+            swap(&mut emu, flush_mode);
+            let expected = match flush_mode {
+                0 => [2, 3, 1],
+                _ => [2, 1, 3],
+            };
+            assert_eq!(ids(&emu), expected);
+        }
+
+        // Blue at alpha 15 over red; a second polygon of the same ID does
+        // not blend again, one of another ID does
+        let pixel = 180 * PIXELS_PER_LINE + 10;
+        let half_blue = 0xFF7E_007E;
+        emu.arm9_write_word(0x0400_0000, 0x0001_0108);
+        emu.arm9_write_halfword(0x0400_0060, DISP3DCNT_ALPHA_BLENDING as u16);
+        emu.arm9_write_halfword(0x0400_0354, 0x7FFF);
+        emu.arm9_write_halfword(0x0400_0304, 0x820F);
+        let frame = |emu: &mut Emulator, second_id: u32| {
+            emu.gx_run_command(COLOR, &[0x001F]);
+            draw_triangle(emu, OPAQUE, full);
+            emu.gx_run_command(COLOR, &[0x7C00]);
+            draw_triangle(emu, translucent(1), full);
+            draw_triangle(emu, translucent(second_id), full);
+            swap(emu, 1);
+            draw_frame(emu);
+            emu.gpu.engine_upper.front_framebuffer[pixel]
+        };
+        assert_eq!(frame(&mut emu, 1), half_blue);
+        assert_eq!(frame(&mut emu, 2), 0xFF3F_00BD);
+    }
+
+    #[test]
+    #[should_panic(expected = "takes 2 parameters")]
+    fn test_param_count_checked() {
+        let mut emu = Emulator::new();
+        emu.gx_run_command(VTX_16, &[0]);
+    }
+}
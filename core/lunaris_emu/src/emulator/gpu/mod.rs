@@ -1,3 +1,5 @@
 pub mod execute;
 pub mod gpu_3d;
+#[cfg(any(test, feature = "gx-test"))]
+pub mod gx_inject;
 pub mod run;
@@ -4,25 +4,119 @@
 //! gpu3d.hpp
 //!
 use crate::Emulator;
+use crate::scheduler::EventKind;
 
 impl Emulator {
-    /// Run 3D rendering for specified cycles
-    pub fn run_3d(&mut self, cycles: i64) {
-        self.gpu3d_run(cycles);
+    /// Run the command due now and schedule the end of the next one.
+    pub(crate) fn gx_handle_event(&mut self) {
+        self.gpu3d_run(self.gpu.engine_3d.cycles.max(0));
+        self.gx_schedule();
     }
 
+    /// Schedule the end of the running geometry command, unless the engine
+    /// is idle, waits for VBlank or already has it scheduled.
+    pub(crate) fn gx_schedule(&mut self) {
+        let engine = &self.gpu.engine_3d;
+        if engine.swap_buffers
+            || (engine.cycles <= 0 && engine.gxpipe.is_empty())
+            || self.scheduler.due_time(EventKind::Gx).is_some()
+        {
+            return;
+        }
+        self.schedule_event(EventKind::Gx, engine.cycles.max(0) as u64);
+    }
+
+    /// Execute queued geometry commands for `cycles_to_run` cycles.
+    ///
+    /// After SWAP_BUFFERS the engine stalls until the next VBlank: the
+    /// commands behind it stay queued in GXPIPE/GXFIFO and GXSTAT reports
+    /// busy until the swap happens.
     pub fn gpu3d_run(&mut self, cycles_to_run: i64) {
         if self.gpu.engine_3d.swap_buffers {
+            // Nothing to catch up on once the stall ends
+            self.gpu.engine_3d.cycles = self.gpu.engine_3d.cycles.max(0);
             return;
         }
         if self.gpu.engine_3d.cycles <= 0 && self.gpu.engine_3d.gxpipe.is_empty() {
             self.gpu.engine_3d.cycles = 0;
+            self.gpu.engine_3d.update_geo_busy();
             return;
         }
 
         self.gpu.engine_3d.cycles -= cycles_to_run;
-        while self.gpu.engine_3d.cycles <= 0 && !self.gpu.engine_3d.gxpipe.is_empty() {
+        while self.gpu.engine_3d.cycles <= 0
+            && !self.gpu.engine_3d.gxpipe.is_empty()
+            && !self.gpu.engine_3d.swap_buffers
+        {
             self.exec_command();
+            self.counters.gx_commands += 1;
+        }
+        self.gpu.engine_3d.update_geo_busy();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MTX_MODE: u32 = 0x0400_0440;
+    const SWAP_BUFFERS: u32 = 0x0400_0540;
+    const GXSTAT_BUSY: u32 = 1 << 27;
+
+    #[test]
+    fn test_busy_until_last_command_done() {
+        const MTX_PUSH: u32 = 0x0400_0444;
+        let mut emu = Box::new(Emulator::new());
+        emu.write_fifo_direct(MTX_PUSH, 0);
+        assert_ne!(emu.gpu.get_gxstat() & GXSTAT_BUSY, 0);
+
+        // MTX_PUSH takes 17 cycles
+        emu.gpu3d_run(10);
+        assert!(emu.gpu.engine_3d.gxpipe.is_empty());
+        assert_ne!(emu.gpu.get_gxstat() & GXSTAT_BUSY, 0);
+        emu.gpu3d_run(10);
+        assert_eq!(emu.gpu.get_gxstat() & GXSTAT_BUSY, 0);
+    }
+
+    #[test]
+    fn test_commands_run_from_scheduled_events() {
+        const MTX_PUSH: u32 = 0x0400_0444;
+        let mut emu = Box::new(Emulator::new());
+        emu.write_fifo_direct(MTX_PUSH, 0);
+        assert_eq!(emu.scheduler.due_time(EventKind::Gx), Some(0));
+
+        // Started right away, done 17 cycles later
+        emu.run_due_events();
+        assert!(emu.gpu.engine_3d.gxpipe.is_empty());
+        assert_eq!(emu.scheduler.due_time(EventKind::Gx), Some(17));
+        emu.system_timestamp = 16;
+        emu.run_due_events();
+        assert_ne!(emu.gpu.get_gxstat() & GXSTAT_BUSY, 0);
+        emu.system_timestamp = 17;
+        emu.run_due_events();
+        assert_eq!(emu.gpu.get_gxstat() & GXSTAT_BUSY, 0);
+        assert_eq!(emu.scheduler.due_time(EventKind::Gx), None);
+    }
+
+    #[test]
+    fn test_swap_buffers_stalls_until_vblank() {
+        let mut emu = Box::new(Emulator::new());
+        emu.write_fifo_direct(SWAP_BUFFERS, 0);
+        emu.write_fifo_direct(MTX_MODE, 2);
+        for _ in 0..300 {
+            emu.write_fifo_direct(MTX_MODE, 1);
         }
+
+        emu.gpu3d_run(100_000);
+        assert!(emu.gpu.engine_3d.swap_buffers);
+        assert_eq!(emu.gpu.engine_3d.mtx_mode, 0);
+        assert_ne!(emu.gpu.get_gxstat() & GXSTAT_BUSY, 0);
+        assert_eq!((emu.gpu.get_gxstat() >> 16) & 0x1FF, 256);
+
+        emu.gpu.engine_3d.end_of_frame();
+        emu.gpu3d_run(100_000);
+        assert_eq!(emu.gpu.engine_3d.mtx_mode, 1);
+        assert!(emu.gpu.engine_3d.gxpipe.is_empty());
+        assert_eq!(emu.gpu.get_gxstat() & GXSTAT_BUSY, 0);
     }
 }
@@ -2,20 +2,41 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 //! emulator.hpp
 //!
+use crate::cpu::arm_cpu::CpuType;
+use crate::debug::{Activity, TraceTrack};
 use crate::emulator::Emulator;
 use crate::interrupts::Interrupt;
+use crate::ipc::FifoIrqs;
 
 impl Emulator {
     /// Request an interrupt for ARM7.
     pub fn request_interrupt7(&mut self, id: Interrupt) {
+        self.trace_event(|trace, now| {
+            trace.instant(TraceTrack::Irq, format!("ARM7 {id:?}"), now, Vec::new());
+        });
+        self.mark_activity(Activity::Irq7);
         self.int7_reg.irq_flags |= 1 << (id as u32);
     }
 
     /// Request an interrupt for ARM9.
     pub fn request_interrupt9(&mut self, id: Interrupt) {
+        self.trace_event(|trace, now| {
+            trace.instant(TraceTrack::Irq, format!("ARM9 {id:?}"), now, Vec::new());
+        });
+        self.mark_activity(Activity::Irq9);
         self.int9_reg.irq_flags |= 1 << (id as u32);
     }
 
+    /// Raise the IPC FIFO interrupts of an access on `cpu`.
+    pub(crate) fn request_fifo_interrupts(&mut self, cpu: CpuType, irqs: FifoIrqs) {
+        for id in irqs.interrupts() {
+            match cpu {
+                CpuType::Arm7 => self.request_interrupt7(id),
+                CpuType::Arm9 => self.request_interrupt9(id),
+            }
+        }
+    }
+
     /// Request a GBA interrupt.
     pub fn request_interrupt_gba(&mut self, id: i32) {
         self.int7_reg.irq_flags |= 1 << (id as u32);
@@ -0,0 +1,93 @@
+//! IPCSYNC and IPC FIFO accesses
+//!
+//! Each access goes through the CPU's own side and the other CPU's side;
+//! the interrupts it raises may land on either CPU.
+use crate::cpu::arm_cpu::CpuType;
+use crate::emulator::Emulator;
+use crate::interrupts::Interrupt;
+
+impl Emulator {
+    /// Read IPCSYNC of `cpu`.
+    pub(crate) fn ipc_sync_read(&self, cpu: CpuType) -> u16 {
+        match cpu {
+            CpuType::Arm9 => self.ipc_sync_nds9.read(),
+            CpuType::Arm7 => self.ipc_sync_nds7.read(),
+        }
+    }
+
+    /// Write IPCSYNC of `cpu`, passing the output to the other CPU and
+    /// raising its sync interrupt if requested and enabled there.
+    pub(crate) fn ipc_sync_write(&mut self, cpu: CpuType, halfword: u16) {
+        let (sync, other) = match cpu {
+            CpuType::Arm9 => (&mut self.ipc_sync_nds9, &mut self.ipc_sync_nds7),
+            CpuType::Arm7 => (&mut self.ipc_sync_nds7, &mut self.ipc_sync_nds9),
+        };
+        let irq = sync.write(halfword);
+        other.receive_input(sync.output);
+        if irq && other.irq_enable {
+            match cpu {
+                CpuType::Arm9 => self.request_interrupt7(Interrupt::IpcSync),
+                CpuType::Arm7 => self.request_interrupt9(Interrupt::IpcSync),
+            }
+        }
+    }
+
+    /// Read IPCFIFOCNT of `cpu`.
+    pub(crate) fn ipc_fifo_read_cnt(&self, cpu: CpuType) -> u16 {
+        match cpu {
+            CpuType::Arm9 => self.fifo9.read_cnt(&self.fifo7),
+            CpuType::Arm7 => self.fifo7.read_cnt(&self.fifo9),
+        }
+    }
+
+    /// Write IPCFIFOCNT of `cpu`.
+    pub(crate) fn ipc_fifo_write_cnt(&mut self, cpu: CpuType, halfword: u16) {
+        let irqs = match cpu {
+            CpuType::Arm9 => self.fifo9.write_cnt(&self.fifo7, halfword),
+            CpuType::Arm7 => self.fifo7.write_cnt(&self.fifo9, halfword),
+        };
+        self.request_fifo_interrupts(cpu, irqs);
+    }
+
+    /// Write IPCFIFOSEND of `cpu`.
+    pub(crate) fn ipc_fifo_send(&mut self, cpu: CpuType, word: u32) {
+        let (irqs, other) = match cpu {
+            CpuType::Arm9 => (self.fifo9.send(&self.fifo7, word), CpuType::Arm7),
+            CpuType::Arm7 => (self.fifo7.send(&self.fifo9, word), CpuType::Arm9),
+        };
+        self.request_fifo_interrupts(other, irqs);
+    }
+
+    /// Read IPCFIFORECV of `cpu`.
+    pub(crate) fn ipc_fifo_receive(&mut self, cpu: CpuType) -> u32 {
+        let ((word, irqs), other) = match cpu {
+            CpuType::Arm9 => (self.fifo9.receive(&mut self.fifo7), CpuType::Arm7),
+            CpuType::Arm7 => (self.fifo7.receive(&mut self.fifo9), CpuType::Arm9),
+        };
+        self.request_fifo_interrupts(other, irqs);
+        word
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ipc_sync_handshake() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+
+        // ARM7 enables its sync IRQ, the ARM9 sends 5 and requests it
+        emu.arm7_write_halfword(0x0400_0180, 1 << 14);
+        emu.arm9_write_halfword(0x0400_0180, (1 << 13) | (5 << 8));
+        assert_eq!(emu.arm7_read_halfword(0x0400_0180), (1 << 14) | 5);
+        assert_eq!(emu.arm9_read_halfword(0x0400_0180), 5 << 8);
+        assert_ne!(emu.int7_reg.irq_flags & (1 << Interrupt::IpcSync as u32), 0);
+
+        // The ARM9 has not enabled its IRQ, so the reply only sets the data
+        emu.arm7_write_word(0x0400_0180, (1 << 14) | (1 << 13) | (0xA << 8));
+        assert_eq!(emu.arm9_read_word(0x0400_0180), (5 << 8) | 0xA);
+        assert_eq!(emu.int9_reg.irq_flags & (1 << Interrupt::IpcSync as u32), 0);
+    }
+}
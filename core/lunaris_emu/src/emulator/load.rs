@@ -8,6 +8,8 @@ use crate::cartridge::CartridgeError;
 use crate::emulator::Emulator;
 use crate::emulator::emu_config::BiosMem;
 use crate::error::{EmuError, FailedReadFileSnafu};
+use crate::firmware::FirmwareOverrides;
+use lunaris_ds_free_bios::firmware::DSType;
 use snafu::ResultExt as _;
 
 impl Emulator {
@@ -28,7 +30,56 @@ impl Emulator {
             })?;
         self.arm7_bios = BiosMem::User(bin);
 
-        self.spi.init(&self.config.firmware_path)
+        self.spi.init(&self.config.firmware_path)?;
+        self.spi
+            .firmware
+            .apply_overrides(&self.firmware_overrides())?;
+        self.spi.power.set_model(self.console_model());
+        Ok(())
+    }
+
+    /// Re-apply `config.firmware_overrides` to the loaded firmware.
+    ///
+    /// Also refreshes the copy of the user settings that direct boot places in
+    /// main RAM, so changes take effect without reloading the ROM.
+    ///
+    /// # Errors
+    /// If the birthday override is not a date; nothing changes then.
+    pub fn apply_firmware_overrides(&mut self) -> Result<(), EmuError> {
+        self.spi
+            .firmware
+            .apply_overrides(&self.firmware_overrides())?;
+        self.spi.power.set_model(self.console_model());
+
+        if self.config.direct_boot_enabled && self.spi.firmware.user_data != 0 {
+            let user = self.spi.firmware.user_data as usize;
+            for i in (0..0x70).step_by(4) {
+                let bytes = &self.spi.firmware.raw_firmware[user + i..user + i + 4];
+                let word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                self.arm7_write_word(0x027FFC80 + i as u32, word);
+            }
+        }
+        Ok(())
+    }
+
+    /// `config.firmware_overrides`, with the console type taken from
+    /// `config.console_model` unless set explicitly.
+    fn firmware_overrides(&self) -> FirmwareOverrides {
+        let mut overrides = self.config.firmware_overrides;
+        overrides.console_type = overrides.console_type.or(self.config.console_model);
+        overrides
+    }
+
+    /// Console model being emulated: `config.console_model`, otherwise the
+    /// console type byte of the loaded firmware, otherwise an original DS.
+    pub fn console_model(&self) -> DSType {
+        self.config
+            .console_model
+            .or_else(|| {
+                let firmware = &self.spi.firmware.raw_firmware;
+                firmware.get(0x1D).and_then(|&b| DSType::from_model_spec(b))
+            })
+            .unwrap_or(DSType::Ds)
     }
 
     /// Load ARM7 BIOS.
@@ -56,7 +107,18 @@ impl Emulator {
     /// Load a ROM file.
     pub fn load_rom(&mut self, rom_path: &Path) -> Result<(), CartridgeError> {
         self.cartridge_load_rom(rom_path)?;
-        self.power_on();
+        self.boot_rom();
         Ok(())
     }
+
+    /// Start the loaded ROM from power on.
+    pub(crate) fn boot_rom(&mut self) {
+        self.power_on();
+        self.apply_game_hacks();
+        self.code_map9.clear();
+        self.code_map7.clear();
+        self.coverage9.clear();
+        self.coverage7.clear();
+        self.analyze_rom_code();
+    }
 }
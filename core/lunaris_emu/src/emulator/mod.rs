@@ -4,38 +4,88 @@
 //!
 //! Core emulator system that manages CPU, memory, and all peripheral devices
 //! Handles the dual-CPU architecture of the Nintendo DS and system timing
+pub mod accuracy;
+mod argv;
+pub mod audio_dump;
+#[cfg(feature = "block_cache")]
+mod block_cache;
+mod breakpoint;
+mod bus;
 mod button;
 mod cartridge;
+pub mod clock_stress;
+mod direct_boot;
 mod dma;
 pub mod emu_config;
+pub mod event;
+pub mod frame;
+pub mod frame_stats;
+pub mod frame_timing;
+mod frameskip;
+pub mod game_hacks;
 mod gpu;
 mod interrupt;
+mod ipc;
 mod load;
+mod page_table;
 mod read;
 mod read_arm7;
 mod read_arm9;
+pub mod run_mode;
 mod runner;
+pub mod save_profile;
+mod scheduler;
+mod slot2;
+mod sound_dma;
+mod spu;
+pub mod state;
+pub mod stats;
 mod timers;
 mod write;
 mod write_arm7;
 mod write_arm9;
+mod write_combine;
 
 use crate::cpu::arm_cpu::ArmCpu;
+#[cfg(feature = "block_cache")]
+use crate::cpu::block_cache::BlockCache;
 use crate::cpu::coprocessor_15::Cp15;
-use lunaris_ds_audio::SPU;
-use lunaris_ds_gpu::gpu_root::{Gpu, register::SchedulerEvent};
+use crate::debug::{
+    ActivityMap, CodeMap, Coverage, CrashRecorder, FrameTrace, GuestCrash, ReverseHistory,
+    WatchHit, Watchpoints,
+};
+use crate::emulator::audio_dump::AudioDump;
+use crate::emulator::event::TimedEvent;
+use crate::emulator::frame::FrameAudio;
+use crate::emulator::frameskip::FrameClock;
+use crate::emulator::game_hacks::{BUILTIN_GAME_HACKS, GameHack};
+use crate::emulator::page_table::PageTable;
+use crate::emulator::run_mode::RunMode;
+use crate::emulator::stats::EmuStats;
+use lunaris_ds_audio::{DirectSound, DynamicRateControl, SPU};
+use lunaris_ds_gpu::gpu_root::Gpu;
 use lunaris_ds_mem_const::*;
 use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::time::Instant;
 
 use crate::cartridge::NDSCart;
+use crate::cheats::Cheat;
 use crate::cpu::arm_cpu::CpuType;
 use crate::dma::NDSDma;
+use crate::frontend::Frontend;
+use crate::input::InputState;
 use crate::interrupts::InterruptRegs;
 use crate::ipc::{IpcFifo, IpcSync};
+use crate::power_management::PowerLed;
 use crate::rtc::RealTimeClock;
+use crate::scheduler::{EventKind, Scheduler};
+use crate::sdcard::SdCard;
+use crate::slot2::Slot2;
 use crate::spi::SPIBus;
 use crate::timers::NDSTiming;
 use crate::wifi::WiFi;
+use crate::wifi::net::WifiTransport;
 use emu_config::{BiosMem, Config, ExtKeyInReg, KeyInputReg, PowCnt2Reg};
 
 /// Core Nintendo DS emulator system
@@ -55,8 +105,12 @@ pub struct Emulator {
     pub rtc: RealTimeClock,
     pub spi: SPIBus,
     pub spu: SPU,
+    /// GBA direct sound FIFOs, only reachable in GBA mode
+    pub direct_sound: DirectSound,
     pub nds_timing: NDSTiming,
     pub wifi: WiFi,
+    /// GBA slot
+    pub slot2: Slot2,
 
     /// Main system RAM (4MB)
     pub main_ram: Vec<u8>,
@@ -70,9 +124,18 @@ pub struct Emulator {
 
     /// Scheduling
     pub system_timestamp: u64,
-    pub next_event_time: u64,
-    pub gpu_event: SchedulerEvent,
-    pub dma_event: SchedulerEvent,
+    pub scheduler: Scheduler,
+
+    /// Per-CPU RAM page tables, rebuilt on WRAMCNT and CP15 writes
+    page_table9: PageTable,
+    page_table7: PageTable,
+    #[cfg(feature = "block_cache")]
+    block_cache9: BlockCache<Emulator>,
+    #[cfg(feature = "block_cache")]
+    block_cache7: BlockCache<Emulator>,
+    /// RAM chunks holding cached code, see [`block_cache`]
+    #[cfg(feature = "block_cache")]
+    code_chunks: Vec<u64>,
 
     /// IPC and FIFO
     pub ipc_sync_nds9: IpcSync,
@@ -80,8 +143,6 @@ pub struct Emulator {
     pub fifo7: IpcFifo,
     pub fifo9: IpcFifo,
 
-    pub fifo7_queue: VecDeque<u32>, // std::queue<uint32_t>
-    pub fifo9_queue: VecDeque<u32>, // std::queue<uint32_t>
     pub aux_spi_cnt: u16,
 
     pub int7_reg: InterruptRegs,
@@ -91,6 +152,21 @@ pub struct Emulator {
     pub key_input: KeyInputReg,
     /// Input state - extended buttons
     pub ext_key_in: ExtKeyInReg,
+    /// The frame being run leaves the screens alone, see [`frameskip`]
+    skip_frame: bool,
+    /// Real time reference of auto frameskip
+    frame_clock: Option<FrameClock>,
+    /// Counters of the frame being run and of the last completed one,
+    /// see [`stats`]
+    counters: EmuStats,
+    last_stats: EmuStats,
+    stats_frame_start: Option<Instant>,
+    /// Action Replay codes, see [`Emulator::add_cheat`]
+    pub(crate) cheats: Vec<Cheat>,
+    /// Buttons the frontend holds, see [`Emulator::set_input`]
+    input: InputState,
+    /// Frames run, for turbo buttons
+    turbo_frame: u8,
 
     /// Power control register
     pub pow_cnt2: PowCnt2Reg,
@@ -129,6 +205,14 @@ pub struct Emulator {
     /// - 0x04000308
     pub bios_prot: u32,
 
+    /// HLE `IntrWait` of each CPU is waiting, run again after every IRQ
+    pub intr_wait9: bool,
+    pub intr_wait7: bool,
+
+    /// ARM7 runs in GBA mode, mapping GBA-only hardware such as direct sound.
+    /// Entered by writing 0x40 to HALTCNT.
+    pub gba_mode: bool,
+
     /// Debugging purposes
     pub hstep_even: bool,
 
@@ -141,6 +225,84 @@ pub struct Emulator {
     pub last_arm9_timestamp: u64,
     /// Last ARM7 execution timestamp
     pub last_arm7_timestamp: u64,
+
+    /// Event trace being recorded, see [`Emulator::trace_frame`].
+    pub trace: Option<FrameTrace>,
+    /// Execution breakpoints, see [`Emulator::add_breakpoint`]
+    pub breakpoints: Vec<(CpuType, u32)>,
+    /// CPU stopped at a breakpoint, see [`Emulator::take_breakpoint_hit`]
+    pub breakpoint_hit: Option<CpuType>,
+    /// Address the pending hit stopped before, `None` when it stopped after
+    /// an instruction
+    pub(crate) hit_before: Option<u32>,
+    /// Instruction the last taken hit stopped before, let through once by
+    /// the breakpoint checks so resuming moves past it
+    pub(crate) resume_past: Option<(CpuType, u32)>,
+    /// Memory watchpoints, see [`Watchpoints`]
+    pub watchpoints: Watchpoints,
+    /// Watchpoint behind the last stop, see [`Emulator::take_watch_hit`]
+    pub(crate) watch_hit: Option<WatchHit>,
+    /// Sinks and input source driven each frame, see [`crate::VideoSink`]
+    pub(crate) frontend: Frontend,
+    /// Snapshots for reverse execution, see [`Emulator::set_reverse_enabled`]
+    pub reverse: Option<ReverseHistory>,
+    /// BKPT stops like a breakpoint instead of raising a prefetch abort, set
+    /// while a debugger is attached
+    pub stop_on_bkpt: bool,
+    /// Activity map being recorded, see [`Emulator::set_activity_map_enabled`]
+    pub activity: Option<ActivityMap>,
+    /// Activity map of the last completed frame
+    pub activity_frame: Option<ActivityMap>,
+    /// Instruction trace and IO histogram kept while crash bundles are
+    /// enabled, see [`Config::crash_bundle_dir`]
+    pub crash_recorder: Option<CrashRecorder>,
+    /// First guest crash since power on, see [`Emulator::last_crash`]
+    pub crash: Option<GuestCrash>,
+    /// Bundle written for `crash`
+    pub crash_bundle: Option<PathBuf>,
+    /// Firmware boot code entry where the ARM9 is handed the cartridge, see
+    /// [`Emulator::apply_boot_patches`]
+    pub boot_handover: Option<u32>,
+
+    /// Mounted SD card image
+    pub sd_card: Option<SdCard>,
+    /// Link to other instances for local wireless play
+    pub wifi_transport: Option<Box<dyn WifiTransport>>,
+
+    /// Cycles short of a whole sample left over by the last frame of
+    /// silence, see [`PausedAudio::Silent`](crate::PausedAudio::Silent)
+    pub silence_cycles: u64,
+    /// Audio resampling ratio control, updated once per frame
+    pub audio_rate: DynamicRateControl,
+    /// Audio dump in progress, see [`Emulator::start_audio_dump`]
+    pub audio_dump: Option<AudioDump>,
+    /// Audio of the frame [`Emulator::run_frame`] is running
+    pub frame_audio: Option<FrameAudio>,
+
+    /// Pause / frame advance state, see [`Emulator::advance_frames`]
+    pub run_mode: RunMode,
+    /// A frame finished and was not taken yet, see
+    /// [`Emulator::take_completed_frame`]
+    pub completed_frame: bool,
+    /// [`Emulator::run`] returned from a frame before it finished, at a
+    /// breakpoint or watchpoint; the next call resumes it
+    pub(crate) frame_in_progress: bool,
+
+    /// Registered hacks, see [`GameHack`]
+    pub game_hacks: Vec<GameHack>,
+    /// Names of the hacks applied to the loaded ROM
+    pub active_game_hacks: Vec<&'static str>,
+
+    /// Events for the frontend, see [`Emulator::poll_event`]
+    pub events: VecDeque<TimedEvent>,
+
+    /// ARM / Thumb code marks for disassembly
+    pub code_map9: CodeMap,
+    pub code_map7: CodeMap,
+
+    /// Executed code, see [`Emulator::coverage`]
+    pub coverage9: Coverage,
+    pub coverage7: Coverage,
 }
 
 impl Default for Emulator {
@@ -164,28 +326,42 @@ impl Emulator {
             rtc: Default::default(),
             spi: Default::default(),
             spu: Default::default(),
+            direct_sound: Default::default(),
             nds_timing: Default::default(),
             wifi: Default::default(),
-            main_ram: vec![0; 1024 * 1024 * 4], // 4MB
-            shared_wram: Default::default(),
-            arm7_wram: Default::default(),
+            slot2: Default::default(),
+            main_ram: vec![0; MAIN_RAM_SIZE],
+            shared_wram: vec![0; SHARED_WRAM_SIZE],
+            arm7_wram: vec![0; ARM7_WRAM_SIZE],
             arm9_bios: Default::default(),
             arm7_bios: Default::default(),
             system_timestamp: Default::default(),
-            next_event_time: Default::default(),
-            gpu_event: Default::default(),
-            dma_event: Default::default(),
+            scheduler: Scheduler::new(),
+            page_table9: Default::default(),
+            page_table7: Default::default(),
+            #[cfg(feature = "block_cache")]
+            block_cache9: Default::default(),
+            #[cfg(feature = "block_cache")]
+            block_cache7: Default::default(),
+            #[cfg(feature = "block_cache")]
+            code_chunks: vec![0; block_cache::CODE_CHUNK_WORDS],
             ipc_sync_nds9: Default::default(),
             ipc_sync_nds7: Default::default(),
             fifo7: Default::default(),
             fifo9: Default::default(),
-            fifo7_queue: Default::default(),
-            fifo9_queue: Default::default(),
             aux_spi_cnt: Default::default(),
             int7_reg: Default::default(),
             int9_reg: Default::default(),
             key_input: Default::default(),
             ext_key_in: Default::default(),
+            skip_frame: false,
+            frame_clock: None,
+            counters: EmuStats::default(),
+            last_stats: EmuStats::default(),
+            stats_frame_start: None,
+            cheats: Vec::new(),
+            input: Default::default(),
+            turbo_frame: 0,
             pow_cnt2: Default::default(),
             dma_fill: Default::default(),
             sio_cnt: Default::default(),
@@ -203,11 +379,46 @@ impl Emulator {
             postflg7: Default::default(),
             postflg9: Default::default(),
             bios_prot: Default::default(),
+            intr_wait9: false,
+            intr_wait7: false,
+            gba_mode: false,
             hstep_even: Default::default(),
             cycles: Default::default(),
             total_timestamp: Default::default(),
             last_arm9_timestamp: Default::default(),
             last_arm7_timestamp: Default::default(),
+            trace: None,
+            breakpoints: Vec::new(),
+            breakpoint_hit: None,
+            hit_before: None,
+            resume_past: None,
+            watchpoints: Watchpoints::default(),
+            watch_hit: None,
+            frontend: Frontend::default(),
+            reverse: None,
+            stop_on_bkpt: false,
+            activity: None,
+            activity_frame: None,
+            crash_recorder: None,
+            crash: None,
+            crash_bundle: None,
+            boot_handover: None,
+            sd_card: None,
+            wifi_transport: None,
+            silence_cycles: 0,
+            audio_rate: DynamicRateControl::new(),
+            audio_dump: None,
+            frame_audio: None,
+            run_mode: RunMode::Running,
+            completed_frame: false,
+            frame_in_progress: false,
+            game_hacks: BUILTIN_GAME_HACKS.to_vec(),
+            active_game_hacks: Vec::new(),
+            events: VecDeque::new(),
+            code_map9: Default::default(),
+            code_map7: Default::default(),
+            coverage9: Default::default(),
+            coverage7: Default::default(),
         }
     }
 
@@ -227,27 +438,11 @@ impl Emulator {
 
     /* ===== Internal helpers (private) ===== */
 
-    /// Check FIFO interrupt for ARM7.
-    ///
-    /// NOTE: It is not used in C++ and has no definition.
-    #[expect(unused)]
-    fn check_fifo7_interrupt(&mut self) {
-        unimplemented!("It is not used in C++ and has no definition.");
-    }
-
-    /// Check FIFO interrupt for ARM9.
-    ///
-    /// NOTE: It is not used in C++ and has no definition.
-    #[expect(unused)]
-    fn check_fifo9_interrupt(&mut self) {
-        unimplemented!("It is not used in C++ and has no definition.");
-    }
-
     /// Start hardware division unit.
     /// Start division operation
     pub fn start_division(&mut self) {
-        if self.div_denom != 0 {
-            self.div_result = self.div_numer / self.div_denom;
+        if let Some(result) = self.div_numer.checked_div(self.div_denom) {
+            self.div_result = result;
             self.div_remresult = self.div_numer % self.div_denom;
         }
     }
@@ -265,63 +460,62 @@ impl Emulator {
             *bg = true;
         }
         self.cycle_count = 0;
+        self.frame_in_progress = false;
+        self.crash_recorder = None;
+        self.crash = None;
+        self.crash_bundle = None;
+        self.boot_handover = None;
+        self.arm9_cp15.power_on();
+        self.arm9.apply_cp15(&self.arm9_cp15);
         self.arm9.power_on();
         self.arm7.power_on();
-        self.arm9_cp15.power_on();
+        self.rebuild_page_tables();
         self.dma.power_on();
 
         self.gpu.power_on();
-        self.add_gpu_event(0, 256 * 6);
 
         self.spu.power_on();
+        self.direct_sound.power_on();
+        self.slot2.power_on();
         self.nds_timing.power_on();
         self.rtc.init();
+        self.spi.power.set_model(self.console_model());
+        self.spi.power.power_on();
+        self.events.clear();
         self.total_timestamp = 20; //Give the processors some time to run
         self.pow_cnt2.speakers = true;
         self.pow_cnt2.wifi = false;
 
         self.system_timestamp = 0;
-        self.next_event_time = 0xffffffff;
-        self.gpu_event.activation_time = 0;
-        self.gpu_event.id = 0;
-        self.dma_event.activation_time = 0;
-        self.dma_event.processing = false;
-        self.dma_event.id = 0;
+        self.scheduler.clear();
+        self.schedule_event(EventKind::HBlankStart, HDRAW_CYCLES);
+        self.schedule_event(EventKind::SpuSample, 0);
 
         self.postflg7 = 0;
         self.postflg9 = 0;
         self.aux_spi_cnt = 0;
+        self.ex_mem_cnt = 0;
+        self.apply_slot2_waitstates();
         self.sio_cnt = 0;
         self.bios_prot = 0;
+        self.intr_wait9 = false;
+        self.intr_wait7 = false;
         self.hstep_even = true;
 
         self.sqrtcnt = 0;
         self.divcnt = 0;
 
-        self.key_input.button_a = false;
-        self.key_input.button_b = false;
-        self.key_input.select = false;
-        self.key_input.start = false;
-        self.key_input.right = false;
-        self.key_input.left = false;
-        self.key_input.up = false;
-        self.key_input.down = false;
-        self.key_input.button_r = false;
-        self.key_input.button_l = false;
-
-        self.ext_key_in.button_x = false;
-        self.ext_key_in.button_y = false;
+        self.turbo_frame = 0;
+        self.skip_frame = false;
+        self.frame_clock = None;
+        self.set_input(InputState::default());
         self.ext_key_in.pen_down = false;
         self.ext_key_in.hinge_closed = false;
 
-        self.ipc_sync_nds7.input = 0;
-        self.ipc_sync_nds9.input = 0;
-        self.fifo7.write_cnt(0);
-        self.fifo7.error = false;
-        self.fifo9.write_cnt(0);
-        self.fifo9.error = false;
-        self.fifo7.recent_word = 0;
-        self.fifo9.recent_word = 0;
+        self.ipc_sync_nds7 = IpcSync::new();
+        self.ipc_sync_nds9 = IpcSync::new();
+        self.fifo7 = IpcFifo::new();
+        self.fifo9 = IpcFifo::new();
 
         // self.main_ram.clear();
         // self.shared_wram.clear();
@@ -336,47 +530,8 @@ impl Emulator {
 
         if self.config.direct_boot_enabled {
             self.direct_boot();
-        }
-    }
-
-    /// Perform a direct boot sequence.
-    pub fn direct_boot(&mut self) {
-        // Write zero to boot flag
-        self.arm7_write_word(0x027FF864, 0);
-
-        // Write shifted value from firmware[0x20]
-        let value_0x20 = u16::from_le_bytes([
-            self.spi.firmware.raw_firmware[0x20],
-            self.spi.firmware.raw_firmware[0x21],
-        ]);
-        self.arm7_write_word(0x027FF868, (value_0x20 as u32) << 3);
-
-        // Write halfword from firmware[0x26]
-        let value_0x26 = u16::from_le_bytes([
-            self.spi.firmware.raw_firmware[0x26],
-            self.spi.firmware.raw_firmware[0x27],
-        ]);
-        self.arm7_write_halfword(0x027FF874, value_0x26);
-
-        // Write halfword from firmware[0x04]
-        let value_0x04 = u16::from_le_bytes([
-            self.spi.firmware.raw_firmware[0x04],
-            self.spi.firmware.raw_firmware[0x05],
-        ]);
-        self.arm7_write_halfword(0x027FF876, value_0x04);
-
-        // Copy USER data block (0x70 bytes, word-aligned)
-        for i in (0..0x70).step_by(4) {
-            let offset = (self.spi.firmware.user_data as usize) + i;
-
-            let word = u32::from_le_bytes([
-                self.spi.firmware.raw_firmware[offset],
-                self.spi.firmware.raw_firmware[offset + 1],
-                self.spi.firmware.raw_firmware[offset + 2],
-                self.spi.firmware.raw_firmware[offset + 3],
-            ]);
-
-            self.arm7_write_word(0x027FFC80 + i as u32, word);
+        } else {
+            self.apply_boot_patches();
         }
     }
 
@@ -441,9 +596,46 @@ impl Emulator {
 
     /* frame, display and dma ===== (public) ===== */
 
-    /// Check if a frame has completed.
-    pub fn frame_complete(&self) -> bool {
-        unimplemented!();
+    /// Whether [`Emulator::run`] finished a frame since the last call,
+    /// clearing the flag. Paused runs finish none.
+    ///
+    /// Frontends paced by the host display check it on each vsync and only
+    /// copy out the screens when a new frame is there, keeping the previous
+    /// one while paused.
+    ///
+    /// ```no_run
+    /// # use lunaris_ds_emu::Emulator;
+    /// # fn wait_vsync() {}
+    /// # fn present(_upper: &[u32], _lower: &[u32]) {}
+    /// let mut emu = Box::new(Emulator::new());
+    /// let (mut upper, mut lower) = (vec![0; 256 * 192], vec![0; 256 * 192]);
+    /// loop {
+    ///     wait_vsync();
+    ///     emu.run();
+    ///     // Nothing new while paused: keep showing the last frame
+    ///     if emu.take_completed_frame() {
+    ///         emu.get_upper_frame(&mut upper);
+    ///         emu.get_lower_frame(&mut lower);
+    ///     }
+    ///     present(&upper, &lower);
+    /// }
+    /// ```
+    pub fn take_completed_frame(&mut self) -> bool {
+        std::mem::take(&mut self.completed_frame)
+    }
+
+    /// Backlight level set by the game, `0..console_model().backlight_levels()`.
+    pub fn backlight_level(&self) -> u8 {
+        self.spi.power.backlight_level()
+    }
+
+    /// Whether the game has the sound amplifier on and unmuted.
+    pub fn sound_enabled(&self) -> bool {
+        self.spi.power.sound_enabled()
+    }
+
+    pub fn power_led(&self) -> PowerLed {
+        self.spi.power.power_led()
     }
 
     /// Check if screens are swapped.
@@ -489,48 +681,31 @@ impl Emulator {
 
     /* ===== add and sys stamp (public) ===== */
 
-    /// Add a GPU event.
-    pub fn add_gpu_event(&mut self, event_id: i32, relative_time: u64) {
-        self.gpu_event.id = event_id;
-        self.gpu_event.activation_time = self.system_timestamp + relative_time;
-
-        if self.gpu_event.activation_time < self.next_event_time {
-            self.next_event_time = self.gpu_event.activation_time;
-        }
-    }
-
-    /// Add a DMA event.
-    pub fn add_dma_event(&mut self, event_id: i32, relative_time: u64) {
-        #[cfg(feature = "tracing")]
-        tracing::debug!("mod.rs: add_dma_event");
-
-        self.dma_event.id = event_id;
-        self.dma_event.processing = true;
-        self.dma_event.activation_time = self.system_timestamp + relative_time;
-
-        if self.dma_event.activation_time < self.next_event_time {
-            self.next_event_time = self.dma_event.activation_time;
-        }
-    }
-
-    /// Recalculate system timestamp.
+    /// Advance the system timestamp by one slice, or less to end it when
+    /// the next scheduled event is due.
     pub fn calculate_system_timestamp(&mut self) {
-        // NOTE: It appears that next_event_time < system_timestamp is in accordance with CorgiDS specifications.
-        // (This is counterintuitive and the reason is unclear.)
-
-        let cycles = self.next_event_time.wrapping_sub(self.system_timestamp) as i32;
-        match cycles {
-            ..=0 | 20.. => self.system_timestamp += 20,
-            _ => self.system_timestamp += cycles as u64,
-        }
+        let slice = self.sync_cycles() as u64;
+        let cycles = match self.scheduler.next_time() {
+            Some(time) if time > self.system_timestamp => (time - self.system_timestamp).min(slice),
+            _ => slice,
+        };
+        self.system_timestamp += cycles;
     }
 
     /* ===== touchscreen (public) ===== */
 
-    /// Handle touchscreen press.
+    /// Handle touchscreen press, with [`DEFAULT_TOUCH_PRESSURE`].
+    ///
+    /// [`DEFAULT_TOUCH_PRESSURE`]: crate::DEFAULT_TOUCH_PRESSURE
     pub fn touchscreen_press(&mut self, x: i32, y: i32) {
-        self.ext_key_in.pen_down = y != 0xfff;
-        self.spi.touchscreen_press(x, y);
+        self.touchscreen_press_with_pressure(x, y, crate::DEFAULT_TOUCH_PRESSURE);
+    }
+
+    /// Handle touchscreen press; `pressure` goes from 0.0 (light) to 1.0
+    /// (firm) and is reported through the Z1/Z2 channels.
+    pub fn touchscreen_press_with_pressure(&mut self, x: i32, y: i32, pressure: f32) {
+        self.spi.touchscreen_press(x, y, pressure);
+        self.ext_key_in.pen_down = self.spi.pen_irq();
     }
 
     /// Call high-level BIOS function.
@@ -546,6 +721,8 @@ impl Emulator {
 
     /* ===== cartrige ===== */
 
+    /// Copy the KEY1 Blowfish table (0x1048 bytes at 0x30 in the ARM7 BIOS)
+    /// into `buffer`.
     pub fn cart_copy_keybuffer(&self, buffer: &mut [u8]) {
         if let Some(bios) = &self.arm7_bios.get(0x30..0x30 + 0x1048) {
             buffer[..0x1048].copy_from_slice(bios);
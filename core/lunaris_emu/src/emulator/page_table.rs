@@ -0,0 +1,312 @@
+//! Page tables of the RAM fast path
+//!
+//! Each CPU maps the 16 KB pages of its first 256 MB to the RAM backing
+//! them, so loads and stores to main RAM, WRAM and the TCMs are one table
+//! lookup and a slice access. Pages holding registers, VRAM, or memory that
+//! does not fill the page are [`Page::Slow`] and go through the full
+//! handlers. The tables are rebuilt when WRAMCNT or the CP15 TCM settings
+//! change.
+use crate::cpu::arm_cpu::CpuType;
+use crate::emulator::Emulator;
+use lunaris_ds_mem_const::*;
+
+const PAGE_SHIFT: u32 = 14;
+const PAGE_SIZE: u32 = 1 << PAGE_SHIFT;
+const PAGE_MASK: u32 = PAGE_SIZE - 1;
+/// Pages from address 0 up to the GBA slot; the BIOS is not mapped.
+const PAGE_COUNT: usize = 0x1000_0000 >> PAGE_SHIFT;
+
+#[cfg(feature = "block_cache")]
+const ITCM_RAM_OFFSET: usize = MAIN_RAM_SIZE + SHARED_WRAM_SIZE + ARM7_WRAM_SIZE;
+#[cfg(feature = "block_cache")]
+const DTCM_RAM_OFFSET: usize = ITCM_RAM_OFFSET + ITCM_MASK as usize + 1;
+/// Bytes of RAM a page may map to, see [`Emulator::ram_offset`]
+#[cfg(feature = "block_cache")]
+pub const RAM_SIZE: usize = DTCM_RAM_OFFSET + DTCM_MASK as usize + 1;
+
+/// Memory a page maps to, with the offset of the page in it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Page {
+    /// Use the full memory handlers
+    #[default]
+    Slow,
+    MainRam(u32),
+    SharedWram(u32),
+    Arm7Wram(u32),
+    Itcm(u32),
+    Dtcm(u32),
+}
+
+/// One CPU's view of its first 256 MB, by page.
+#[derive(Debug, Clone)]
+pub struct PageTable {
+    pages: Vec<Page>,
+}
+
+impl Default for PageTable {
+    fn default() -> Self {
+        Self {
+            pages: vec![Page::Slow; PAGE_COUNT],
+        }
+    }
+}
+
+impl PageTable {
+    /// Page of `address`.
+    pub fn page(&self, address: u32) -> Page {
+        self.pages
+            .get((address >> PAGE_SHIFT) as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Map the pages of `start..end`, `page` giving the mapping of each page
+    /// start.
+    fn map(&mut self, start: u32, end: u32, page: impl Fn(u32) -> Page) {
+        for address in (start..end).step_by(PAGE_SIZE as usize) {
+            self.pages[(address >> PAGE_SHIFT) as usize] = page(address);
+        }
+    }
+
+    /// Main RAM and the shared and ARM7 WRAM of one CPU for WRAMCNT
+    /// `wram_cnt`.
+    fn map_ram(&mut self, cpu_type: CpuType, wram_cnt: u8) {
+        self.pages.fill(Page::Slow);
+        self.map(MAIN_RAM_START, SHARED_WRAM_START, |address| {
+            Page::MainRam(address & MAIN_RAM_MASK)
+        });
+
+        // Offset of the shared WRAM this CPU sees, and its size
+        let shared = match (cpu_type, wram_cnt & 3) {
+            (CpuType::Arm9, 0) | (CpuType::Arm7, 3) => Some((0, 0x8000)),
+            (CpuType::Arm9, 1) | (CpuType::Arm7, 2) => Some((0x4000, 0x4000)),
+            (CpuType::Arm9, 2) | (CpuType::Arm7, 1) => Some((0, 0x4000)),
+            _ => None,
+        };
+        match (cpu_type, shared) {
+            (CpuType::Arm9, None) => {}
+            (_, Some((offset, size))) => {
+                self.map(SHARED_WRAM_START, ARM7_WRAM_START, |address| {
+                    Page::SharedWram(offset + (address & (size - 1)))
+                });
+            }
+            // The ARM7 sees its own WRAM there when it has no shared WRAM
+            (CpuType::Arm7, None) => {
+                self.map(SHARED_WRAM_START, ARM7_WRAM_START, |address| {
+                    Page::Arm7Wram(address & ARM7_WRAM_MASK)
+                });
+            }
+        }
+
+        match cpu_type {
+            CpuType::Arm9 => {
+                self.map(ARM7_WRAM_START, IO_REGS_START, |address| match shared {
+                    Some((offset, size)) => Page::SharedWram(offset + (address & (size - 1))),
+                    None => Page::Slow,
+                });
+            }
+            CpuType::Arm7 => {
+                self.map(ARM7_WRAM_START, IO_REGS_START, |address| {
+                    Page::Arm7Wram(address & ARM7_WRAM_MASK)
+                });
+            }
+        }
+    }
+}
+
+impl Emulator {
+    /// Rebuild the page tables of both CPUs.
+    pub(crate) fn rebuild_page_tables(&mut self) {
+        self.rebuild_page_table9();
+        #[cfg(feature = "block_cache")]
+        self.clear_block_caches();
+        self.page_table7.map_ram(CpuType::Arm7, self.wram_cnt);
+    }
+
+    /// Rebuild the ARM9 page table, with the TCMs over the bus.
+    ///
+    /// A TCM smaller than a page, not aligned to one, or in load mode, where
+    /// reads and writes reach different memory, leaves its pages slow.
+    pub(crate) fn rebuild_page_table9(&mut self) {
+        #[cfg(feature = "block_cache")]
+        self.clear_block_caches();
+        let table = &mut self.page_table9;
+        table.map_ram(CpuType::Arm9, self.wram_cnt);
+        let cp15 = &self.arm9_cp15;
+
+        // Regions are aligned to their size, so one of 16 KB or more
+        // covers whole pages
+        let dtcm_base = cp15.get_dtcm_base();
+        let dtcm_size = cp15.get_dtcm_size();
+        let dtcm_fast = cp15.dtcm_read_size() == dtcm_size && dtcm_size >= PAGE_SIZE;
+        if dtcm_size != 0 {
+            let dtcm_end = dtcm_base.saturating_add(dtcm_size).min(0x1000_0000);
+            table.map(dtcm_base, dtcm_end, |address| match dtcm_fast {
+                true => Page::Dtcm(address & DTCM_MASK),
+                false => Page::Slow,
+            });
+        }
+
+        // The ITCM has priority over the DTCM
+        let itcm_size = cp15.get_itcm_size();
+        let itcm_fast = cp15.itcm_read_size() == itcm_size && itcm_size >= PAGE_SIZE;
+        table.map(0, itcm_size.min(0x1000_0000), |address| match itcm_fast {
+            true => Page::Itcm(address & ITCM_MASK),
+            false => Page::Slow,
+        });
+    }
+
+    fn page_table(&self, cpu_type: CpuType) -> &PageTable {
+        match cpu_type {
+            CpuType::Arm9 => &self.page_table9,
+            CpuType::Arm7 => &self.page_table7,
+        }
+    }
+
+    fn page_memory(&self, page: Page) -> Option<(&[u8], u32)> {
+        match page {
+            Page::Slow => None,
+            Page::MainRam(offset) => Some((&self.main_ram, offset)),
+            Page::SharedWram(offset) => Some((&self.shared_wram, offset)),
+            Page::Arm7Wram(offset) => Some((&self.arm7_wram, offset)),
+            Page::Itcm(offset) => Some((self.arm9_cp15.itcm(), offset)),
+            Page::Dtcm(offset) => Some((self.arm9_cp15.dtcm(), offset)),
+        }
+    }
+
+    fn page_memory_mut(&mut self, page: Page) -> Option<(&mut [u8], u32)> {
+        match page {
+            Page::Slow => None,
+            Page::MainRam(offset) => Some((&mut self.main_ram, offset)),
+            Page::SharedWram(offset) => Some((&mut self.shared_wram, offset)),
+            Page::Arm7Wram(offset) => Some((&mut self.arm7_wram, offset)),
+            Page::Itcm(offset) => Some((self.arm9_cp15.itcm_mut(), offset)),
+            Page::Dtcm(offset) => Some((self.arm9_cp15.dtcm_mut(), offset)),
+        }
+    }
+
+    /// Offset of `address` in all RAM as one, main RAM first, then shared
+    /// WRAM, ARM7 WRAM, ITCM and DTCM. `None` if its page is not RAM.
+    #[cfg(feature = "block_cache")]
+    pub(crate) fn ram_offset(&self, cpu_type: CpuType, address: u32) -> Option<usize> {
+        let (base, offset) = match self.page_table(cpu_type).page(address) {
+            Page::Slow => return None,
+            Page::MainRam(offset) => (0, offset),
+            Page::SharedWram(offset) => (MAIN_RAM_SIZE, offset),
+            Page::Arm7Wram(offset) => (MAIN_RAM_SIZE + SHARED_WRAM_SIZE, offset),
+            Page::Itcm(offset) => (ITCM_RAM_OFFSET, offset),
+            Page::Dtcm(offset) => (DTCM_RAM_OFFSET, offset),
+        };
+        Some(base + (offset + (address & PAGE_MASK)) as usize)
+    }
+
+    /// Read `N` bytes at `address` aligned down to `N`, if its page is RAM.
+    #[inline]
+    pub(crate) fn page_read<const N: usize>(
+        &self,
+        cpu_type: CpuType,
+        address: u32,
+    ) -> Option<[u8; N]> {
+        let (memory, offset) = self.page_memory(self.page_table(cpu_type).page(address))?;
+        let start = (offset + (address & PAGE_MASK & !(N as u32 - 1))) as usize;
+        memory[start..start + N].try_into().ok()
+    }
+
+    /// Write `bytes` at `address` aligned down to their size, returning
+    /// `false` if its page is not RAM.
+    #[inline]
+    pub(crate) fn page_write<const N: usize>(
+        &mut self,
+        cpu_type: CpuType,
+        address: u32,
+        bytes: [u8; N],
+    ) -> bool {
+        let page = self.page_table(cpu_type).page(address);
+        let Some((memory, offset)) = self.page_memory_mut(page) else {
+            return false;
+        };
+        let start = (offset + (address & PAGE_MASK & !(N as u32 - 1))) as usize;
+        memory[start..start + N].copy_from_slice(&bytes);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wram_pages_follow_wramcnt() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        for wram_cnt in 0..4 {
+            emu.arm9_write_byte(0x0400_0247, wram_cnt);
+            // Shared WRAM is 0x0300_0000..0x0380_0000, the ARM7 mirrors
+            // its own WRAM there when it has none
+            emu.shared_wram.fill(0);
+            emu.arm7_wram.fill(0);
+            emu.write_word(0x0300_4000, 0x1111_1111, CpuType::Arm9);
+            emu.write_word(0x0300_0000, 0x2222_2222, CpuType::Arm7);
+
+            let arm9_half = match wram_cnt {
+                0 => Some(0x4000),
+                1 => Some(0x4000),
+                2 => Some(0),
+                _ => None,
+            };
+            let arm7_half = match wram_cnt {
+                1 => Some(0),
+                2 => Some(0x4000),
+                3 => Some(0),
+                _ => None,
+            };
+            let word = |memory: &[u8], offset: usize| {
+                u32::from_le_bytes(memory[offset..offset + 4].try_into().unwrap())
+            };
+            if let Some(offset) = arm9_half {
+                assert_eq!(word(&emu.shared_wram, offset), 0x1111_1111, "{wram_cnt}");
+            }
+            match arm7_half {
+                Some(offset) => assert_eq!(word(&emu.shared_wram, offset), 0x2222_2222),
+                None => assert_eq!(word(&emu.arm7_wram, 0), 0x2222_2222),
+            }
+            // The slow handlers agree with the pages
+            assert_eq!(
+                emu.read_word(0x0300_0000, CpuType::Arm7),
+                emu.arm7_read_word(0x0300_0000),
+                "{wram_cnt}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_tcm_over_main_ram() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.arm9_write_word(0x0200_0000, 0xAAAA_AAAA);
+        emu.arm9_write_word(0x0200_4000, 0xBBBB_BBBB);
+        assert_eq!(emu.read_word(0x0200_0000, CpuType::Arm9), 0xAAAA_AAAA);
+
+        // 16 KB DTCM at 0x0200_0000, ITCM of 32 MB
+        let cp15 = &mut emu.arm9_cp15;
+        cp15.mcr(0, 9, 0x0200_0000 | (5 << 1), 0, 1);
+        cp15.mcr(0, 9, 0x10 << 1, 1, 1);
+        cp15.mcr(0, 1, (1 << 16) | (1 << 18), 0, 0);
+        emu.rebuild_page_table9();
+
+        emu.write_word(0x0200_0000, 0x1234_5678, CpuType::Arm9);
+        assert_eq!(emu.read_word(0x0200_0000, CpuType::Arm9), 0x1234_5678);
+        assert_eq!(emu.arm9_read_word(0x0200_0000), 0xAAAA_AAAA);
+        assert_eq!(emu.read_word(0x0200_4000, CpuType::Arm9), 0xBBBB_BBBB);
+        emu.write_word(0x0100_0000, 0x55, CpuType::Arm9);
+        assert_eq!(emu.read_byte(0x0000_0000, CpuType::Arm9), 0x55);
+
+        // DTCM load mode: writes go to the DTCM, reads to the bus
+        let cp15 = &mut emu.arm9_cp15;
+        cp15.mcr(0, 1, (1 << 16) | (1 << 17), 0, 0);
+        emu.rebuild_page_table9();
+        emu.write_word(0x0200_0000, 0x8765_4321, CpuType::Arm9);
+        assert_eq!(emu.read_word(0x0200_0000, CpuType::Arm9), 0xAAAA_AAAA);
+        assert_eq!(emu.arm9_cp15.dtcm()[..4], 0x8765_4321u32.to_le_bytes());
+    }
+}
@@ -4,26 +4,96 @@ use crate::emulator::Emulator;
 
 impl Emulator {
     pub fn read_word(&mut self, address: u32, cpu_type: CpuType) -> u32 {
-        if self.get_cpu(cpu_type).cpu_id <= 0 {
-            self.arm9_cp15.read_word(address)
-        } else {
-            self.arm7_read_word(address)
+        if let Some(bytes) = self.page_read(cpu_type, address) {
+            return u32::from_le_bytes(bytes);
+        }
+        self.record_io_access(cpu_type, address, false);
+        match cpu_type {
+            CpuType::Arm9 => match self.arm9_cp15.read_word(address) {
+                Some(word) => word,
+                None => self.arm9_read_word(address),
+            },
+            CpuType::Arm7 => self.arm7_read_word(address),
         }
     }
 
-    pub fn read_halfword(&self, address: u32, cpu_type: CpuType) -> u16 {
-        if self.get_cpu(cpu_type).cpu_id <= 0 {
-            self.arm9_cp15.read_halfword(address)
-        } else {
-            self.arm7_read_halfword(address)
+    pub fn read_halfword(&mut self, address: u32, cpu_type: CpuType) -> u16 {
+        if let Some(bytes) = self.page_read(cpu_type, address) {
+            return u16::from_le_bytes(bytes);
+        }
+        self.record_io_access(cpu_type, address, false);
+        match cpu_type {
+            CpuType::Arm9 => match self.arm9_cp15.read_halfword(address) {
+                Some(halfword) => halfword,
+                None => self.arm9_read_halfword(address),
+            },
+            CpuType::Arm7 => self.arm7_read_halfword(address),
         }
     }
 
     pub fn read_byte(&self, address: u32, cpu_type: CpuType) -> u8 {
-        if self.get_cpu(cpu_type).cpu_id <= 0 {
-            self.arm9_cp15.read_byte(address)
-        } else {
-            self.arm7_read_byte(address)
+        if let Some([byte]) = self.page_read(cpu_type, address) {
+            return byte;
         }
+        self.record_io_access(cpu_type, address, false);
+        match cpu_type {
+            CpuType::Arm9 => match self.arm9_cp15.read_byte(address) {
+                Some(byte) => byte,
+                None => self.arm9_read_byte(address),
+            },
+            CpuType::Arm7 => self.arm7_read_byte(address),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arm9_bus_reaches_ram_and_io() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+
+        // No TCM is enabled after power on
+        emu.write_word(0x0200_0000, 0x1234_5678, CpuType::Arm9);
+        assert_eq!(emu.arm9_read_word(0x0200_0000), 0x1234_5678);
+        assert_eq!(emu.read_halfword(0x0200_0002, CpuType::Arm9), 0x1234);
+        emu.write_byte(0x0400_0247, 2, CpuType::Arm9);
+        assert_eq!(emu.wram_cnt, 2);
+        assert_eq!(emu.read_byte(0x0400_0247, CpuType::Arm9), 2);
+    }
+
+    #[test]
+    fn test_tcm_regions() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.arm9_write_word(0x0200_0000, 0xAAAA_AAAA);
+
+        // 16 KB DTCM at 0x0200_0000 in a 32 KB region, 64 KB ITCM region
+        let cp15 = &mut emu.arm9_cp15;
+        cp15.mcr(0, 9, 0x0200_0000 | (6 << 1), 0, 1);
+        cp15.mcr(0, 9, 7 << 1, 1, 1);
+        cp15.mcr(0, 1, (1 << 16) | (1 << 18), 0, 0);
+        emu.rebuild_page_table9();
+
+        // The 32 KB ITCM starts at 0 and is mirrored over its region
+        emu.write_word(0x0000_0100, 0x1111_1111, CpuType::Arm9);
+        assert_eq!(emu.read_word(0x0000_8100, CpuType::Arm9), 0x1111_1111);
+        // The DTCM is mirrored over its region and hides main RAM
+        emu.write_word(0x0200_0000, 0x2222_2222, CpuType::Arm9);
+        assert_eq!(emu.read_word(0x0200_4000, CpuType::Arm9), 0x2222_2222);
+        assert_eq!(emu.arm9_read_word(0x0200_0000), 0xAAAA_AAAA);
+        // Past the region the bus answers
+        assert_eq!(emu.read_word(0x0200_8000, CpuType::Arm9), 0);
+
+        // DTCM load mode: writes reach the DTCM, reads the bus
+        emu.arm9_cp15.mcr(0, 1, (1 << 16) | (1 << 17) | (1 << 18), 0, 0);
+        emu.rebuild_page_table9();
+        emu.write_word(0x0200_0000, 0x3333_3333, CpuType::Arm9);
+        assert_eq!(emu.read_word(0x0200_0000, CpuType::Arm9), 0xAAAA_AAAA);
+        emu.arm9_cp15.mcr(0, 1, (1 << 16) | (1 << 18), 0, 0);
+        emu.rebuild_page_table9();
+        assert_eq!(emu.read_word(0x0200_0000, CpuType::Arm9), 0x3333_3333);
     }
 }
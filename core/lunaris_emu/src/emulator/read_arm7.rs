@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use super::Emulator;
-use crate::interrupts::Interrupt;
+use crate::cpu::arm_cpu::CpuType;
 use lunaris_ds_mem_const::*;
 
 impl Emulator {
@@ -24,25 +24,36 @@ impl Emulator {
                     3 => address & 0x7FFF,            // Entire 32 KB
                     _ => return 0,                    // TODO: Log trancing error
                 } as usize;
+                u32::from_le_bytes(self.arm7_shared_wram()[off..off + 4].try_into().unwrap())
+            }
+            // ARM7 WRAM
+            ARM7_WRAM_START..IO_REGS_START => {
+                let off = (address & ARM7_WRAM_MASK) as usize;
                 u32::from_le_bytes(self.arm7_wram[off..off + 4].try_into().unwrap())
             }
 
+            0x040000B0..0x040000E0 => {
+                self.dma_read_halfword(CpuType::Arm7, address) as u32
+                    | (self.dma_read_halfword(CpuType::Arm7, address + 2) as u32) << 16
+            }
+            0x04000100..0x04000110 => {
+                self.timer_read_halfword(CpuType::Arm7, address) as u32
+                    | (self.timer_read_halfword(CpuType::Arm7, address + 2) as u32) << 16
+            }
             0x04000120 => 0,
-            0x04000180 => self.ipc_sync_nds7.read().into(),
+            0x04000180 => self.ipc_sync_read(CpuType::Arm7).into(),
+            0x04000184 => self.ipc_fifo_read_cnt(CpuType::Arm7).into(),
             0x040001A4 => self.cart.get_romctrl(),
             0x040001C0 => (self.spi.get_spicnt() as u32) | (self.spi.read_spidata() as u32) << 16,
             0x04000208 => self.int7_reg.ime,
             0x04000210 => self.int7_reg.irq_enable,
             0x04000214 => self.int7_reg.irq_flags,
-            0x04100000 => {
-                let word: u32 = self.fifo9.read_queue();
-                if self.fifo9.request_empty_irq {
-                    self.request_interrupt9(Interrupt::IpcFifoEmpty);
-                    self.fifo9.request_empty_irq = false;
-                }
+            0x04100000 => self.ipc_fifo_receive(CpuType::Arm7),
+            0x04100010 => {
+                let word = self.cart.get_output();
+                self.cartridge_schedule();
                 word
             }
-            0x04100010 => self.cart.get_output(),
 
             ..0x4000 => {
                 let arm7_pc = self.arm7.get_pc();
@@ -54,9 +65,17 @@ impl Emulator {
                 let end = (address + 4) as usize;
                 u32::from_le_bytes(self.arm7_bios[start..end].try_into().unwrap())
             }
-            0x04000400..0x04000500 => 0,
+            0x04000400..0x04000500 => self.spu.read_channel_word(address),
+            // WiFi registers are 16-bit wide
+            0x04804000..0x04806000 | 0x04814000..0x04816000 => {
+                self.wifi.read_ram(address) as u32 | (self.wifi.read_ram(address + 2) as u32) << 16
+            }
+            0x04808000..0x04809000 | 0x04818000..0x04819000 => {
+                self.wifi_read_reg(address) as u32 | (self.wifi_read_reg(address + 2) as u32) << 16
+            }
             0x06000000..0x07000000 => self.gpu.read_arm7_u32(address),
-            GBA_ROM_START.. => 0xFFFF_FFFF,
+            GBA_ROM_START..GBA_RAM_END => self.slot2_read_word(CpuType::Arm7, address),
+            GBA_RAM_END.. => 0xFFFF_FFFF,
 
             _ => {
                 #[cfg(feature = "tracing")]
@@ -67,7 +86,7 @@ impl Emulator {
     }
 
     /// ARM7 read 16-bit halfword
-    pub fn arm7_read_halfword(&self, address: u32) -> u16 {
+    pub fn arm7_read_halfword(&mut self, address: u32) -> u16 {
         match address {
             // BIOS (0x00000000..0x00003FFF)
             ..0x4000 => {
@@ -100,7 +119,7 @@ impl Emulator {
                     _ => return 0,
                 } as usize;
 
-                u16::from_le_bytes(self.arm7_wram[off..off + 2].try_into().unwrap())
+                u16::from_le_bytes(self.arm7_shared_wram()[off..off + 2].try_into().unwrap())
             }
 
             // ARM7 WRAM
@@ -112,29 +131,25 @@ impl Emulator {
             // IO Registers
             0x04000004 => self.gpu.get_dispstat7(),
             0x04000006 => self.gpu.get_vcount(),
+            0x04000082 if self.gba_mode => self.direct_sound.get_cnt(),
 
-            0x040000BA => self.dma.read_cnt(4),
-            0x040000C6 => self.dma.read_cnt(5),
-            0x040000D2 => self.dma.read_cnt(6),
-            0x040000DE => self.dma.read_cnt(7),
+            0x040000B0..0x040000E0 => self.dma_read_halfword(CpuType::Arm7, address),
 
-            0x04000100 => self.nds_timing.read_lo(0),
-            0x04000102 => self.nds_timing.read_hi(0),
-            0x04000104 => self.nds_timing.read_lo(1),
-            0x04000106 => self.nds_timing.read_hi(1),
-            0x04000108 => self.nds_timing.read_lo(2),
-            0x0400010A => self.nds_timing.read_hi(2),
-            0x0400010C => self.nds_timing.read_lo(3),
-            0x0400010E => self.nds_timing.read_hi(3),
+            0x04000100..0x04000110 => self.timer_read_halfword(CpuType::Arm7, address),
 
             0x04000128 => self.sio_cnt,
-            0x04000130 => self.key_input.get_value(),
+            0x04000130 => self.key_input.get(),
             0x04000134 => self.r_cnt,
-            0x04000136 => self.ext_key_in.get_value(),
+            // The pen bit follows the touchscreen's /PENIRQ
+            0x04000136 => {
+                let mut ext_key_in = self.ext_key_in;
+                ext_key_in.pen_down = self.spi.pen_irq();
+                ext_key_in.get()
+            }
             0x04000138 => self.rtc.read(),
 
-            0x04000180 => self.ipc_sync_nds7.read(),
-            0x04000184 => self.fifo7.read_cnt(),
+            0x04000180 => self.ipc_sync_read(CpuType::Arm7),
+            0x04000184 => self.ipc_fifo_read_cnt(CpuType::Arm7),
 
             0x040001A0 => self.cart.get_auxspicnt(),
             0x040001A2 => self.cart.read_auxspidata().into(),
@@ -142,9 +157,10 @@ impl Emulator {
             0x040001C0 => self.spi.get_spicnt(),
             0x040001C2 => self.spi.read_spidata().into(),
 
+            0x04000204 => self.ex_mem_cnt,
             0x04000208 => self.int7_reg.ime as u16,
             0x04000300 => self.postflg7.into(),
-            0x04000304 => self.pow_cnt2.get_value(),
+            0x04000304 => self.pow_cnt2.get(),
             0x04000500 => self.spu.get_soundcnt(),
             0x04000504 => self.spu.get_soundbias(),
             0x04000508 => self.spu.get_sndcap0() as u16 | ((self.spu.get_sndcap1() as u16) << 8),
@@ -152,9 +168,9 @@ impl Emulator {
             0x04001080 => 0,
             0x04004700 => 0,
 
-            0x0480815C => self.wifi.get_w_bb_read(),
-            0x0480815E => self.wifi.get_w_bb_busy() as u16,
-            0x04808180 => self.wifi.get_w_rf_busy() as u16,
+            // WiFi RAM and registers
+            0x04804000..0x04806000 | 0x04814000..0x04816000 => self.wifi.read_ram(address),
+            0x04808000..0x04809000 | 0x04818000..0x04819000 => self.wifi_read_reg(address),
 
             // WiFi block
             0x04800000..0x04900000 => 0,
@@ -162,11 +178,12 @@ impl Emulator {
             // GPU
             0x06000000..0x07000000 => self.gpu.read_arm7_u16(address),
 
-            // GBA Slot ROM
-            GBA_ROM_START.. => 0xFFFF,
+            // GBA slot
+            GBA_ROM_START..GBA_RAM_END => self.slot2_read_halfword(CpuType::Arm7, address),
+            GBA_RAM_END.. => 0xFFFF,
 
-            // Unused IO region
-            0x04000400..0x04000500 => 0,
+            // SPU channel region
+            0x04000400..0x04000500 => self.spu.read_channel_halfword(address),
 
             _ => {
                 #[cfg(feature = "tracing")]
@@ -201,7 +218,7 @@ impl Emulator {
                     _ => return 0,
                 } as usize;
 
-                self.arm7_wram[off]
+                self.arm7_shared_wram()[off]
             }
 
             // Direct IO byte reads
@@ -230,6 +247,9 @@ impl Emulator {
             // SPU channel region
             0x04000400..0x04000500 => self.spu.read_channel_byte(address),
 
+            // GBA slot
+            GBA_ROM_START..GBA_RAM_END => self.slot2_read_byte(CpuType::Arm7, address),
+
             // Default case
             _ => {
                 #[cfg(feature = "tracing")]
@@ -238,4 +258,47 @@ impl Emulator {
             }
         }
     }
+
+    /// Memory behind the ARM7's shared WRAM region, its own WRAM when
+    /// WRAMCNT gives it none of the shared WRAM.
+    pub(crate) fn arm7_shared_wram(&self) -> &[u8] {
+        match self.wram_cnt {
+            0 => &self.arm7_wram,
+            _ => &self.shared_wram,
+        }
+    }
+
+    pub(crate) fn arm7_shared_wram_mut(&mut self) -> &mut [u8] {
+        match self.wram_cnt {
+            0 => &mut self.arm7_wram,
+            _ => &mut self.shared_wram,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_wram_follows_wramcnt() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+
+        // WRAMCNT 1 gives the ARM7 the first 16 KB of shared WRAM
+        emu.arm9_write_byte(0x0400_0247, 1);
+        emu.arm7_write_word(0x0300_0000, 0x1111_1111);
+        emu.arm7_write_byte(0x0300_0004, 0x22);
+        assert_eq!(emu.shared_wram[..5], [0x11, 0x11, 0x11, 0x11, 0x22]);
+        assert_eq!(emu.arm7_wram[..5], [0; 5]);
+        assert_eq!(emu.arm7_read_word(0x0300_0000), 0x1111_1111);
+
+        // With none of it, the region mirrors ARM7 WRAM
+        emu.arm9_write_byte(0x0400_0247, 0);
+        emu.arm7_write_halfword(0x0300_0000, 0x3333);
+        emu.arm7_write_byte(0x0300_0004, 0x44);
+        assert_eq!(emu.arm7_wram[..5], [0x33, 0x33, 0, 0, 0x44]);
+        assert_eq!(emu.arm7_read_halfword(0x0380_0000), 0x3333);
+        assert_eq!(emu.shared_wram[..5], [0x11, 0x11, 0x11, 0x11, 0x22]);
+    }
 }
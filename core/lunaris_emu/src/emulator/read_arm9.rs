@@ -1,10 +1,14 @@
 // SPDX-FileCopyrightText: (C) 2017 PSISP
 // SPDX-License-Identifier: GPL-3.0-or-later
 use super::Emulator;
-use crate::interrupts::Interrupt;
+use crate::cpu::arm_cpu::CpuType;
 use lunaris_ds_mem_const::*;
 
 impl Emulator {
+    /// Reads a 32-bit word from the ARM9 bus
+    ///
+    /// The TCMs are not on the bus: DMA reads what is behind them, CPU loads
+    /// check them first in [`Emulator::read_word`].
     pub fn arm9_read_word(&mut self, address: u32) -> u32 {
         match address {
             // ARM9 BIOS
@@ -45,16 +49,20 @@ impl Emulator {
                 self.gpu.get_bghofs_a(3) as u32 | ((self.gpu.get_bgvofs_a(3) as u32) << 16)
             }
             0x04000064 => self.gpu.get_dispcapcnt_a(),
-            0x040000B0 => self.dma.read_source(0),
-            0x040000B8 => self.dma.read_len(0) as u32 | ((self.dma.read_cnt(0) as u32) << 16),
-            0x040000C4 => self.dma.read_len(1) as u32 | ((self.dma.read_cnt(1) as u32) << 16),
-            0x040000D0 => self.dma.read_len(2) as u32 | ((self.dma.read_cnt(2) as u32) << 16),
-            0x040000DC => self.dma.read_len(3) as u32 | ((self.dma.read_cnt(3) as u32) << 16),
+            0x040000B0..0x040000E0 => {
+                self.dma_read_halfword(CpuType::Arm9, address) as u32
+                    | (self.dma_read_halfword(CpuType::Arm9, address + 2) as u32) << 16
+            }
             0x040000E0 => self.dma_fill[0],
+            0x04000100..0x04000110 => {
+                self.timer_read_halfword(CpuType::Arm9, address) as u32
+                    | (self.timer_read_halfword(CpuType::Arm9, address + 2) as u32) << 16
+            }
             0x040000E4 => self.dma_fill[1],
             0x040000E8 => self.dma_fill[2],
             0x040000EC => self.dma_fill[3],
-            0x04000180 => self.ipc_sync_nds9.read().into(),
+            0x04000180 => self.ipc_sync_read(CpuType::Arm9).into(),
+            0x04000184 => self.ipc_fifo_read_cnt(CpuType::Arm9).into(),
             0x040001A4 => self.cart.get_romctrl(),
             0x04000208 => self.int9_reg.ime,
             0x04000210 => self.int9_reg.irq_enable,
@@ -71,10 +79,9 @@ impl Emulator {
             0x040002B4 => self.sqrt_result,
             0x040002B8 => (self.sqrt_param & 0xFFFF_FFFF) as u32,
             0x040002BC => (self.sqrt_param >> 32) as u32,
-            0x04000600 => self.gpu.get_gxstat(),
-            0x04000604 => {
-                self.gpu.get_poly_count() as u32 | ((self.gpu.get_vert_count() as u32) << 16)
-            }
+            // GXFIFO and the command ports are write-only
+            0x0400_0400..0x0400_0600 => 0,
+            0x0400_0600..0x0400_06A4 => self.gpu.read_gx_word(address),
             0x04001000 => self.gpu.get_dispcnt_b(),
             0x04001010 => {
                 self.gpu.get_bghofs_b(0) as u32 | ((self.gpu.get_bgvofs_b(0) as u32) << 16)
@@ -89,33 +96,31 @@ impl Emulator {
                 self.gpu.get_bghofs_b(3) as u32 | ((self.gpu.get_bgvofs_b(3) as u32) << 16)
             }
             0x04004000 | 0x04004008 => 0,
-            0x04100000 => {
-                let word = self.fifo7.read_queue();
-                if self.fifo7.request_empty_irq {
-                    self.request_interrupt7(Interrupt::IpcFifoEmpty);
-                    self.fifo7.request_empty_irq = false;
-                }
+            0x04100000 => self.ipc_fifo_receive(CpuType::Arm9),
+            0x04100010 => {
+                let word = self.cart.get_output();
+                self.cartridge_schedule();
                 word
             }
-            0x04100010 => self.cart.get_output(),
             PALETTE_START..VRAM_BGA_START => {
                 if (address & 0x7FF) < 0x400 {
-                    let lo = self.gpu.read_palette_a(address) as u32;
-                    let hi = self.gpu.read_palette_a(address + 2) as u32;
+                    let lo = self.gpu.read_palette_a(address & 0x3FF) as u32;
+                    let hi = self.gpu.read_palette_a((address & 0x3FF) + 2) as u32;
                     lo | (hi << 16)
                 } else {
-                    let lo = self.gpu.read_palette_b(address) as u32;
-                    let hi = self.gpu.read_palette_b(address + 2) as u32;
+                    let lo = self.gpu.read_palette_b(address & 0x3FF) as u32;
+                    let hi = self.gpu.read_palette_b((address & 0x3FF) + 2) as u32;
                     lo | (hi << 16)
                 }
             }
-            0x04000640..0x04000680 => self.gpu.read_clip_mtx(address),
-            0x04000680..0x040006A4 => self.gpu.read_vec_mtx(address),
 
             VRAM_BGA_START..VRAM_BGB_START => self.gpu.read_bga_u32(address),
             VRAM_BGB_START..VRAM_OBJA_START => self.gpu.read_bgb_u32(address),
-            VRAM_LCDC_A..VRAM_LCDC_END => self.gpu.read_lcdc_u32(address),
+            VRAM_OBJA_START..VRAM_OBJB_START => self.gpu.read_obja_u32(address),
+            VRAM_OBJB_START..VRAM_LCDC_A => self.gpu.read_objb_u32(address),
+            VRAM_LCDC_A..OAM_START => self.gpu.read_lcdc_u32(address),
             OAM_START..GBA_ROM_START => self.gpu.read_oam_u32(address),
+            GBA_ROM_START..GBA_RAM_END => self.slot2_read_word(CpuType::Arm9, address),
 
             _ => {
                 if address >= GBA_ROM_START {
@@ -139,7 +144,7 @@ impl Emulator {
     /// - VRAM for OBJ and LCDC
     /// - I/O registers (GPU, DMA, timers, input, etc.)
     /// - Cartridge SPI and ROM
-    pub fn arm9_read_halfword(&self, address: u32) -> u16 {
+    pub fn arm9_read_halfword(&mut self, address: u32) -> u16 {
         // I/O registers
         match address {
             0xFFFF_0000.. => {
@@ -166,15 +171,16 @@ impl Emulator {
             PALETTE_START..VRAM_BGA_START => {
                 // Palette memory
                 if (address & 0x7FF) < 0x400 {
-                    self.gpu.read_palette_a(address)
+                    self.gpu.read_palette_a(address & 0x3FF)
                 } else {
-                    self.gpu.read_palette_b(address)
+                    self.gpu.read_palette_b(address & 0x3FF)
                 }
             }
             VRAM_OBJA_START..VRAM_OBJB_START => self.gpu.read_obja_u16(address), // VRAM OBJ A/B
             VRAM_OBJB_START..VRAM_LCDC_A => self.gpu.read_objb_u16(address),
-            VRAM_LCDC_A..VRAM_LCDC_END => self.gpu.read_lcdc_u16(address), // VRAM LCDC
+            VRAM_LCDC_A..OAM_START => self.gpu.read_lcdc_u16(address), // VRAM LCDC
             0x0400_0000 => self.gpu.get_dispcnt_a() as u16,
+            0x0400_0002 => (self.gpu.get_dispcnt_a() >> 16) as u16,
             0x0400_0004 => self.gpu.get_dispstat9(),
             0x0400_0006 => self.gpu.get_vcount(),
             0x0400_0008 => self.gpu.get_bgcnt_a(0),
@@ -189,21 +195,15 @@ impl Emulator {
             0x0400_0052 => self.gpu.get_bldalpha_a(),
             0x0400_0060 => self.gpu.get_disp3dcnt(),
             0x0400_006c => self.gpu.get_master_bright_a(),
-            0x0400_00ba => self.dma.read_cnt(0),
-            0x0400_00c6 => self.dma.read_cnt(1),
-            0x0400_00d2 => self.dma.read_cnt(2),
-            0x0400_00de => self.dma.read_cnt(3),
+            0x0400_00B0..0x0400_00E0 => self.dma_read_halfword(CpuType::Arm9, address),
             0x0400_00E0 => (self.dma_fill[0] & 0xFFFF) as u16,
             0x0400_00E4 => (self.dma_fill[1] & 0xFFFF) as u16,
             0x0400_00E8 => (self.dma_fill[2] & 0xFFFF) as u16,
             0x0400_00EC => (self.dma_fill[3] & 0xFFFF) as u16,
-            0x0400_0100 => self.nds_timing.read_lo(4),
-            0x0400_0104 => self.nds_timing.read_lo(5),
-            0x0400_0108 => self.nds_timing.read_lo(6),
-            0x0400_010C => self.nds_timing.read_lo(7),
-            0x0400_0130 => self.key_input.get_value(),
-            0x0400_0180 => self.ipc_sync_nds9.read(),
-            0x0400_0184 => self.fifo9.read_cnt(),
+            0x0400_0100..0x0400_0110 => self.timer_read_halfword(CpuType::Arm9, address),
+            0x0400_0130 => self.key_input.get(),
+            0x0400_0180 => self.ipc_sync_read(CpuType::Arm9),
+            0x0400_0184 => self.ipc_fifo_read_cnt(CpuType::Arm9),
             0x0400_01A0 => self.cart.get_auxspicnt(),
             0x0400_0204 => self.ex_mem_cnt,
             0x0400_0208 => self.int9_reg.ime as u16,
@@ -211,9 +211,12 @@ impl Emulator {
             0x0400_02B0 => self.sqrtcnt,
             0x0400_0300 => self.postflg9.into(),
             0x0400_0304 => self.gpu.get_powcnt1(),
-            0x0400_0604 => self.gpu.get_poly_count(),
-            0x0400_0606 => self.gpu.get_vert_count(),
+            0x0400_0400..0x0400_0600 => 0,
+            0x0400_0600..0x0400_06A4 => {
+                (self.gpu.read_gx_word(address) >> ((address & 0x2) * 8)) as u16
+            }
             0x0400_1000 => (self.gpu.get_dispcnt_b() & 0xffff) as u16,
+            0x0400_1002 => (self.gpu.get_dispcnt_b() >> 16) as u16,
             0x0400_1008 => self.gpu.get_bgcnt_b(0),
             0x0400_100a => self.gpu.get_bgcnt_b(1),
             0x0400_100c => self.gpu.get_bgcnt_b(2),
@@ -225,13 +228,14 @@ impl Emulator {
             0x0400_1050 => self.gpu.get_bldcnt_b(),
             0x0400_1052 => self.gpu.get_bldalpha_b(),
             0x0400_106c => self.gpu.get_master_bright_b(),
-            0x0400_0630..0x0400_0636 => self.gpu.read_vec_test(address),
             _ => {
                 if (VRAM_BGA_START..VRAM_BGB_START).contains(&address) {
                     self.gpu.read_bga_u16(address)
                 } else if (VRAM_BGB_START..VRAM_OBJA_START).contains(&address) {
                     self.gpu.read_bgb_u16(address)
-                } else if address >= GBA_ROM_START {
+                } else if (GBA_ROM_START..GBA_RAM_END).contains(&address) {
+                    self.slot2_read_halfword(CpuType::Arm9, address)
+                } else if address >= GBA_RAM_END {
                     0xFFFF
                 } else {
                     #[cfg(feature = "tracing")]
@@ -274,18 +278,25 @@ impl Emulator {
             0x0400_0247 => self.wram_cnt & 0x3,
             0x0400_0300 => self.postflg9,
             0x0400_4000 => 0,
+            0x0400_0400..0x0400_0600 => 0,
+            0x0400_0600..0x0400_06A4 => {
+                (self.gpu.read_gx_word(address) >> ((address & 0x3) * 8)) as u8
+            }
             PALETTE_START..VRAM_BGA_START => {
                 if (address & 0x7FF) < 0x400 {
-                    (self.gpu.read_palette_a(address) & 0xFF) as u8
+                    (self.gpu.read_palette_a(address & 0x3FF) & 0xFF) as u8
                 } else {
-                    (self.gpu.read_palette_b(address) & 0xFF) as u8
+                    (self.gpu.read_palette_b(address & 0x3FF) & 0xFF) as u8
                 }
             }
             VRAM_BGA_START..VRAM_BGB_START => self.gpu.read_bga_u8(address),
             VRAM_BGB_START..VRAM_OBJA_START => self.gpu.read_bgb_u8(address),
+            VRAM_OBJA_START..VRAM_OBJB_START => self.gpu.read_obja_u8(address),
+            VRAM_OBJB_START..VRAM_LCDC_A => self.gpu.read_objb_u8(address),
             VRAM_LCDC_A..OAM_START => self.gpu.read_lcdc_u8(address), // VRAM LCDC
             OAM_START..GBA_ROM_START => self.gpu.read_oam_u8(address), // OAM
-            GBA_ROM_START.. => 0xFF,                                  // GBA ROM
+            GBA_ROM_START..GBA_RAM_END => self.slot2_read_byte(CpuType::Arm9, address),
+            GBA_RAM_END.. => 0xFF,
             _ => {
                 // Palette memory
                 #[cfg(feature = "tracing")]
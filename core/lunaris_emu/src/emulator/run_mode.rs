@@ -0,0 +1,140 @@
+//! Pause and frame advance
+//!
+//! The run mode is only looked at when [`Emulator::run`] starts a frame, so
+//! pausing never splits a frame, not even one a breakpoint cut short. Frontends keep calling `run` at their usual
+//! cadence; while paused it returns without emulating anything.
+use crate::emulator::Emulator;
+
+/// Execution mode, checked at frame boundaries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RunMode {
+    #[default]
+    Running,
+    Paused,
+    /// Run this many more frames, then pause
+    Advancing(u32),
+}
+
+/// What audio does while paused.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PausedAudio {
+    /// Rate control is frozen; the frontend stops its stream.
+    #[default]
+    Stopped,
+    /// Every skipped frame yields a frame of silence, through the same
+    /// resampler and to the same audio sink, and rate control keeps
+    /// updating, so the frontend keeps its stream running and resumes
+    /// without a rate jump.
+    Silent,
+}
+
+impl Emulator {
+    pub fn is_paused(&self) -> bool {
+        self.run_mode == RunMode::Paused
+    }
+
+    /// Pause at the next frame boundary.
+    pub fn pause(&mut self) {
+        self.run_mode = RunMode::Paused;
+    }
+
+    pub fn resume(&mut self) {
+        self.run_mode = RunMode::Running;
+    }
+
+    /// Run `count` more frames, then pause.
+    ///
+    /// Adds to a frame advance already in progress; `0` pauses right away.
+    pub fn advance_frames(&mut self, count: u32) {
+        self.run_mode = match (self.run_mode, count) {
+            (_, 0) => RunMode::Paused,
+            (RunMode::Advancing(left), count) => RunMode::Advancing(left.saturating_add(count)),
+            (_, count) => RunMode::Advancing(count),
+        };
+    }
+
+    /// Consume the run mode at the start of a frame. Returns whether the
+    /// frame should be emulated.
+    pub(crate) fn begin_frame(&mut self) -> bool {
+        match self.run_mode {
+            RunMode::Running => true,
+            RunMode::Paused => {
+                if self.config.paused_audio == PausedAudio::Silent {
+                    self.audio_rate.update();
+                    self.play_silent_frame();
+                }
+                false
+            }
+            RunMode::Advancing(left) => {
+                self.run_mode = match left {
+                    0 | 1 => RunMode::Paused,
+                    left => RunMode::Advancing(left - 1),
+                };
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance_frames() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.pause();
+        let start = emu.system_timestamp;
+        assert!(!emu.run());
+        assert_eq!(emu.system_timestamp, start);
+        assert!(!emu.take_completed_frame());
+
+        emu.advance_frames(1);
+        emu.advance_frames(1);
+        assert!(emu.run());
+        assert!(emu.run());
+        assert!(emu.take_completed_frame());
+        assert!(!emu.take_completed_frame());
+        let advanced = emu.system_timestamp;
+        assert!(advanced > start);
+        assert!(!emu.run());
+        assert_eq!(emu.system_timestamp, advanced);
+        assert!(!emu.take_completed_frame());
+        assert!(emu.is_paused());
+
+        emu.resume();
+        assert!(emu.run());
+    }
+    #[test]
+    fn test_finish_frame_cut_short() {
+        use crate::cpu::arm_cpu::CpuType;
+
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        // b . at the start of main RAM
+        emu.main_ram[..4].copy_from_slice(&0xEAFF_FFFE_u32.to_le_bytes());
+        emu.arm7.jp(0x0200_0000, false);
+        emu.add_breakpoint(CpuType::Arm7, 0x0200_0000);
+
+        // Finishing the frame does not count as another one
+        emu.advance_frames(2);
+        assert!(emu.run());
+        assert_eq!(emu.take_breakpoint_hit(), Some(CpuType::Arm7));
+        assert_eq!(emu.run_mode, RunMode::Advancing(1));
+        emu.remove_breakpoint(CpuType::Arm7, 0x0200_0000);
+        assert!(emu.run());
+        assert!(emu.take_completed_frame());
+        assert_eq!(emu.run_mode, RunMode::Advancing(1));
+
+        // Nor does pausing keep it from finishing
+        emu.add_breakpoint(CpuType::Arm7, 0x0200_0000);
+        assert!(emu.run());
+        assert!(emu.is_paused());
+        assert_eq!(emu.take_breakpoint_hit(), Some(CpuType::Arm7));
+        emu.remove_breakpoint(CpuType::Arm7, 0x0200_0000);
+        assert!(emu.run());
+        assert!(emu.take_completed_frame());
+        assert!(!emu.run());
+    }
+}
@@ -1,45 +1,118 @@
 use crate::cpu::arm_cpu::CpuType;
-use crate::cpu::interpreter::arm_interpret;
-use crate::cpu::interpreter::thumb_instruction::thumb_interpret;
+use crate::cpu::interpreter::{fetched, interpret};
+use crate::debug::TraceTrack;
 
 use crate::emulator::Emulator;
 
 impl Emulator {
-    /// Run the emulator main loop.
-    pub fn run(&mut self) {
-        self.gpu.start_frame();
-
-        while !self.gpu.is_frame_complete() {
+    /// Run the emulator main loop for one frame.
+    ///
+    /// Returns `false` without emulating anything while paused, see
+    /// [`Emulator::pause`]. Returns early, with the frame unfinished, when a
+    /// CPU stops at a breakpoint, see [`Emulator::add_breakpoint`]; the
+    /// next call finishes that frame, paused or not.
+    pub fn run(&mut self) -> bool {
+        if !self.frame_in_progress {
+            if !self.begin_frame() {
+                return false;
+            }
+            self.set_audio_output_ratio();
+            self.stats_begin_frame();
+            self.frontend_begin_frame();
+            self.frame_in_progress = true;
+        }
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("frame").entered();
+        while !self.gpu.poll_frame() {
+            self.reverse_snapshot();
             // Handle self.ARM9
             self.calculate_system_timestamp();
-            while self.arm9.get_timestamp() < (self.system_timestamp << 1) {
-                self.execute(CpuType::Arm9);
-                self.run_timers9((self.arm9.cycles_ran() >> 1) as i32);
-                self.run_3d(self.arm9.cycles_ran() >> 1);
+            if !self.run_slice() {
+                return true;
             }
+        }
 
-            // Now handle ARM7
-            while self.arm7.get_timestamp() < self.system_timestamp {
-                self.execute(CpuType::Arm7);
-                self.run_timers7(self.arm7.cycles_ran() as i32);
-            }
+        self.flush_save();
+        self.sd_card_end_frame();
+        self.rtc_end_frame();
+        self.wifi_end_frame();
+        self.cheats_end_frame();
+        self.input_end_frame();
+        self.audio_rate.update();
+        self.finish_activity_frame();
+        self.stats_end_frame();
+        self.frontend_end_frame();
+        self.frame_in_progress = false;
+        self.completed_frame = true;
+        true
+    }
 
-            if self.system_timestamp >= self.gpu_event.activation_time {
-                self.gpu_handle_event();
+    /// Run both CPUs up to the system timestamp, then everything that
+    /// catches up with them. Returns `false`, with the rest not caught up,
+    /// if a CPU stopped at a breakpoint.
+    pub(crate) fn run_slice(&mut self) -> bool {
+        let arm9_start = self.arm9.get_timestamp() >> 1;
+        let arm7_start = self.arm7.get_timestamp();
+        match self
+            .config
+            .clock_stress
+            .is_some_and(|stress| stress.arm7_first)
+        {
+            true => {
+                self.run_arm7_slice();
+                self.run_arm9_slice();
             }
-
-            if self.system_timestamp >= self.dma_event.activation_time && self.dma_event.processing
-            {
-                self.dma_handle_event(); // DMA Method
+            false => {
+                self.run_arm9_slice();
+                self.run_arm7_slice();
             }
+        }
+        if self.breakpoint_hit.is_some() {
+            return false;
+        }
+        self.wifi_run(self.arm7.get_timestamp() - arm7_start);
+        self.sample_activity();
+
+        if self.trace.is_some() {
+            let arm9_end = self.arm9.get_timestamp() >> 1;
+            let arm7_end = self.arm7.get_timestamp();
+            self.trace_event(|trace, _| {
+                if arm9_end > arm9_start {
+                    trace.slice(TraceTrack::Arm9, "ARM9", arm9_start, arm9_end);
+                }
+                if arm7_end > arm7_start {
+                    trace.slice(TraceTrack::Arm7, "ARM7", arm7_start, arm7_end);
+                }
+            });
+        }
+
+        self.run_due_events();
+        self.audio_dump_catch_up();
+        self.frame_audio_catch_up();
+        true
+    }
 
-            self.cartridge_run(8);
+    fn run_arm9_slice(&mut self) {
+        let target = self.cpu_sync_target(CpuType::Arm9);
+        // Only while skipping the intro, see Emulator::apply_boot_patches
+        while self.boot_handover.is_some()
+            && self.arm9.get_timestamp() < target
+            && self.breakpoint_hit.is_none()
+        {
+            if !self.check_boot_handover() {
+                self.execute(CpuType::Arm9);
+            }
+        }
+        while self.arm9.get_timestamp() < target && self.breakpoint_hit.is_none() {
+            self.execute(CpuType::Arm9);
         }
+    }
 
-        if let Err(err) = self.cart.save_check() {
-            #[cfg(feature = "tracing")]
-            tracing::error!("{err}");
-        };
+    fn run_arm7_slice(&mut self) {
+        let target = self.cpu_sync_target(CpuType::Arm7);
+        while self.arm7.get_timestamp() < target && self.breakpoint_hit.is_none() {
+            self.execute(CpuType::Arm7);
+        }
     }
 
     pub fn execute(&mut self, cpu_type: CpuType) {
@@ -57,13 +130,16 @@ impl Emulator {
         {
             // ARM7 or ARM9
             let halted = self.get_cpu_mut(cpu_type).halted;
-            let is_dma_active = self.dma_active();
+            let is_dma_active = self.dma.is_active_for(cpu_type == CpuType::Arm9);
 
             // #[cfg(feature = "tracing")]
             // tracing::info!(%halted, %is_dma_active);
 
             if halted || is_dma_active {
-                let timestamp = self.get_timestamp() << (1 - cpu_id);
+                // Skip to the end of the slice, which ends at the next
+                // event that could wake the CPU
+                let now = self.get_cpu(cpu_type).timestamp;
+                let timestamp = self.cpu_sync_target(cpu_type).max(now);
 
                 // Wait until next event
                 let is_interrupt = self.requesting_interrupt(cpu_id);
@@ -73,7 +149,7 @@ impl Emulator {
                 if is_interrupt {
                     arm.halted = false;
                     if !arm.cpsr.irq_disabled && !is_dma_active {
-                        arm.handle_irq();
+                        self.enter_irq(cpu_type);
                     }
                 }
                 return;
@@ -84,34 +160,61 @@ impl Emulator {
         let thumb_on = self.get_cpu_mut(cpu_type).cpsr.thumb_on;
         let pc = self.get_cpu(cpu_type).get_pc();
 
-        if thumb_on {
-            {
-                let value = self.read_halfword(pc - 2, cpu_type) as u32;
-                let arm = self.get_cpu_mut(cpu_type);
+        let instr_addr = pc.wrapping_sub(if thumb_on { 2 } else { 4 });
+        if self.check_reverse_stop(cpu_type, instr_addr)
+            || (!self.resumes_past(cpu_type, instr_addr)
+                && (self.check_breakpoint(cpu_type, instr_addr)
+                    || self.check_execute_watchpoint(cpu_type, instr_addr)))
+        {
+            return;
+        }
 
-                arm.current_instr = value;
-                arm.add_s16_code(pc - 2, 1);
-                arm.regs[15] = pc.wrapping_add(2);
-            }
-            thumb_interpret(self, cpu_type);
-        } else {
-            {
-                let addr = pc.wrapping_sub(4);
-                let value = self.read_word(addr, cpu_type);
-                let arm = self.get_cpu_mut(cpu_type);
+        if self.config.hle_bios
+            && !thumb_on
+            && pc.wrapping_sub(4) == self.hle_irq_return_address(cpu_type)
+        {
+            self.hle_irq_return(cpu_type);
+            return;
+        }
 
-                arm.current_instr = value;
-                arm.add_s32_code(addr, 1);
-                arm.regs[15] = pc.wrapping_add(4);
+        if self.code_map(cpu_type).tracks_execution() {
+            match thumb_on {
+                true => self.mark_as_thumb(cpu_type, instr_addr),
+                false => self.mark_as_arm(cpu_type, instr_addr),
             }
-            arm_interpret(self, cpu_type);
         }
+        if self.coverage(cpu_type).is_enabled() {
+            let size = if thumb_on { 2 } else { 4 };
+            self.coverage_mut(cpu_type)
+                .record(pc.wrapping_sub(size), size);
+        }
+
+        #[cfg(feature = "block_cache")]
+        let mut executed = match self.config.test {
+            true => 0,
+            false => self.execute_cached(cpu_type),
+        };
+        #[cfg(not(feature = "block_cache"))]
+        let mut executed = 0;
+        if executed == 0 {
+            // Fetched without the bus so it does not hit read watchpoints
+            let opcode = match thumb_on {
+                true => self.read_halfword(instr_addr, cpu_type) as u32,
+                false => self.read_word(instr_addr, cpu_type),
+            };
+            let address = fetched(self, cpu_type, opcode);
+            self.record_instruction(cpu_type, address);
+            interpret(self, cpu_type);
+            executed = 1;
+        }
+        self.count_instructions(cpu_type, executed as u64);
+        self.retire_instruction(cpu_type);
 
         let is_interrupt = self.requesting_interrupt(cpu_id);
         let irq_disabled = self.get_cpu(cpu_type).cpsr.irq_disabled;
 
         if is_interrupt && !irq_disabled {
-            self.get_cpu_mut(cpu_type).handle_irq();
+            self.enter_irq(cpu_type);
         }
     }
 }
@@ -123,6 +226,28 @@ mod tests {
     use super::*;
     use lunaris_ds_mem_const::{PIXELS_PER_LINE, SCANLINES};
 
+    #[test]
+    fn test_completed_frame_taken_once() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        // b . at the start of main RAM
+        emu.main_ram[..4].copy_from_slice(&0xEAFF_FFFE_u32.to_le_bytes());
+        emu.arm7.jp(0x0200_0000, false);
+
+        // Cut short, the frame is not complete yet
+        emu.add_breakpoint(CpuType::Arm7, 0x0200_0000);
+        assert!(emu.run());
+        assert_eq!(emu.take_breakpoint_hit(), Some(CpuType::Arm7));
+        assert!(!emu.take_completed_frame());
+
+        emu.remove_breakpoint(CpuType::Arm7, 0x0200_0000);
+        assert!(emu.run());
+        assert!(emu.take_completed_frame());
+        assert!(!emu.take_completed_frame());
+        // The run loop consumed the GPU's flag
+        assert!(!emu.gpu.poll_frame());
+    }
+
     #[test]
     #[ignore = "Since need local nds file"]
     #[quick_tracing::init(test = "test_emulator", level = "trace")]
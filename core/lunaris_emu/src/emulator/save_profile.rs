@@ -0,0 +1,208 @@
+//! Save profiles
+//!
+//! Several playthroughs of one game can keep separate saves. The default
+//! profile is the usual `game.sav` next to the ROM; a named profile is
+//! `<gamecode>.<name>.profile.sav` in the same directory, keyed by the game
+//! code in the ROM header so renaming the ROM keeps its profiles. Select
+//! one through [`Config::save_profile`] before loading the ROM.
+//!
+//! [`Config::save_profile`]: crate::Config::save_profile
+use std::fs::File;
+use std::io::{Read as _, Seek as _, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::cartridge::CartridgeError;
+
+/// Ending of every named profile's save file.
+const PROFILE_SUFFIX: &str = ".profile.sav";
+
+/// Whether `name` can be used as a profile name: non-empty ASCII
+/// alphanumerics, `-` and `_`, so it stays a single path component.
+pub fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+fn check_name(name: &str) -> Result<(), CartridgeError> {
+    match is_valid_profile_name(name) {
+        true => Ok(()),
+        false => Err(CartridgeError::InvalidSaveProfile {
+            name: name.to_string(),
+        }),
+    }
+}
+
+/// Game code as used in file names, other characters than ASCII
+/// alphanumerics replaced by `_`.
+fn profile_key(game_code: [u8; 4]) -> String {
+    game_code
+        .iter()
+        .map(|&b| match b.is_ascii_alphanumeric() {
+            true => b as char,
+            false => '_',
+        })
+        .collect()
+}
+
+/// Game code in the header of the ROM at `rom`.
+///
+/// # Errors
+/// If the ROM could not be opened or is shorter than the game code.
+pub fn read_game_code(rom: &Path) -> Result<[u8; 4], CartridgeError> {
+    let mut game_code = [0; 4];
+    let mut file = File::open(rom).map_err(|source| CartridgeError::OpenRom {
+        path: rom.to_path_buf(),
+        source,
+    })?;
+    file.seek(SeekFrom::Start(0x0C))
+        .and_then(|_| file.read_exact(&mut game_code))
+        .map_err(|source| CartridgeError::ReadRom {
+            path: rom.to_path_buf(),
+            source,
+        })?;
+    Ok(game_code)
+}
+
+fn rom_dir(rom: &Path) -> &Path {
+    match rom.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// Save file of `profile` (`None` for the default one) for the ROM at `rom`
+/// with the header game code `game_code`.
+pub fn save_profile_path(rom: &Path, game_code: [u8; 4], profile: Option<&str>) -> PathBuf {
+    match profile {
+        None => rom.with_extension("sav"),
+        Some(name) => {
+            rom_dir(rom).join(format!("{}.{name}{PROFILE_SUFFIX}", profile_key(game_code)))
+        }
+    }
+}
+
+/// Named profiles that have a save file for the ROM at `rom`, sorted.
+///
+/// # Errors
+/// If the ROM header or the ROM directory could not be read.
+pub fn list_save_profiles(rom: &Path) -> Result<Vec<String>, CartridgeError> {
+    let key = profile_key(read_game_code(rom)?);
+    let dir = rom_dir(rom);
+    let entries = std::fs::read_dir(dir).map_err(|source| CartridgeError::ListSaveProfiles {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+
+    let mut profiles: Vec<String> = entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            let (game, profile) = name.strip_suffix(PROFILE_SUFFIX)?.split_once('.')?;
+            (game == key && is_valid_profile_name(profile)).then(|| profile.to_string())
+        })
+        .collect();
+    profiles.sort();
+    Ok(profiles)
+}
+
+/// Copy the save of profile `from` (`None` for the default one) to the new
+/// profile `to`. An existing `to` is overwritten.
+///
+/// # Errors
+/// If a name is invalid, the ROM header could not be read or the save
+/// could not be copied.
+pub fn clone_save_profile(rom: &Path, from: Option<&str>, to: &str) -> Result<(), CartridgeError> {
+    if let Some(from) = from {
+        check_name(from)?;
+    }
+    check_name(to)?;
+
+    let game_code = read_game_code(rom)?;
+    let from = save_profile_path(rom, game_code, from);
+    let to = save_profile_path(rom, game_code, Some(to));
+    std::fs::copy(&from, &to).map_err(|source| CartridgeError::CopySave { from, to, source })?;
+    Ok(())
+}
+
+/// Delete the save file of the named profile `name`.
+///
+/// # Errors
+/// If the name is invalid, the ROM header could not be read or the file
+/// could not be removed.
+pub fn delete_save_profile(rom: &Path, name: &str) -> Result<(), CartridgeError> {
+    check_name(name)?;
+    let path = save_profile_path(rom, read_game_code(rom)?, Some(name));
+    std::fs::remove_file(&path).map_err(|source| CartridgeError::DeleteSave { path, source })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a ROM whose header carries `game_code`.
+    fn write_rom(path: &Path, game_code: &[u8; 4]) {
+        let mut rom = vec![0; 0x200];
+        rom[0x0C..0x10].copy_from_slice(game_code);
+        std::fs::write(path, rom).unwrap();
+    }
+
+    #[test]
+    fn test_profiles() {
+        let dir = std::env::temp_dir().join(format!("lunaris_profiles_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let rom = dir.join("game.nds");
+        write_rom(&rom, b"AGME");
+        std::fs::write(save_profile_path(&rom, *b"AGME", None), [1, 2, 3]).unwrap();
+        std::fs::write(dir.join("other.b.sav"), [0]).unwrap();
+
+        assert_eq!(list_save_profiles(&rom).unwrap(), Vec::<String>::new());
+        clone_save_profile(&rom, None, "second").unwrap();
+        clone_save_profile(&rom, Some("second"), "a-run").unwrap();
+        assert_eq!(list_save_profiles(&rom).unwrap(), ["a-run", "second"]);
+        assert_eq!(
+            std::fs::read(dir.join("AGME.a-run.profile.sav")).unwrap(),
+            [1, 2, 3]
+        );
+        assert!(matches!(
+            clone_save_profile(&rom, None, "../x"),
+            Err(CartridgeError::InvalidSaveProfile { .. })
+        ));
+
+        delete_save_profile(&rom, "second").unwrap();
+        assert_eq!(list_save_profiles(&rom).unwrap(), ["a-run"]);
+        assert!(matches!(
+            delete_save_profile(&rom, "second"),
+            Err(CartridgeError::DeleteSave { .. })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_profiles_follow_game_code() {
+        let dir = std::env::temp_dir().join(format!("lunaris_game_code_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let game = dir.join("game.nds");
+        let renamed = dir.join("game (renamed).v1.nds");
+        let other = dir.join("other.nds");
+        write_rom(&game, b"AGME");
+        write_rom(&renamed, b"AGME");
+        write_rom(&other, b"#\0\0/");
+        std::fs::write(save_profile_path(&game, *b"AGME", None), [1]).unwrap();
+        std::fs::write(save_profile_path(&other, *b"#\0\0/", None), [2]).unwrap();
+
+        clone_save_profile(&game, None, "main").unwrap();
+        clone_save_profile(&other, None, "alt").unwrap();
+        assert_eq!(
+            save_profile_path(&other, *b"#\0\0/", Some("alt")),
+            dir.join("____.alt.profile.sav")
+        );
+        assert_eq!(list_save_profiles(&renamed).unwrap(), ["main"]);
+        assert_eq!(list_save_profiles(&other).unwrap(), ["alt"]);
+        assert!(matches!(
+            list_save_profiles(&dir.join("missing.nds")),
+            Err(CartridgeError::OpenRom { .. })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
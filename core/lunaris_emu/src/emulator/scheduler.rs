@@ -0,0 +1,84 @@
+//! Dispatch of scheduled events
+use crate::debug::TraceTrack;
+use crate::emulator::Emulator;
+use crate::scheduler::EventKind;
+
+impl Emulator {
+    /// Schedule `kind` `relative_time` system cycles from now, replacing
+    /// its pending event.
+    pub fn schedule_event(&mut self, kind: EventKind, relative_time: u64) {
+        self.scheduler
+            .schedule(kind, self.system_timestamp + relative_time);
+    }
+
+    /// Handle every event due by the current system timestamp, in order.
+    pub(crate) fn run_due_events(&mut self) {
+        while let Some((kind, due)) = self.scheduler.pop_due(self.system_timestamp) {
+            self.trace_scheduled_event(kind, due);
+            self.handle_scheduled_event(kind, due);
+        }
+    }
+
+    /// Handle `kind`, which was due at `due`.
+    pub(crate) fn handle_scheduled_event(&mut self, kind: EventKind, due: u64) {
+        match kind {
+            EventKind::HBlankStart | EventKind::HBlankEnd => self.gpu_handle_event(kind, due),
+            EventKind::Dma => self.dma_handle_event(),
+            EventKind::SpuSample => self.spu_handle_event(due),
+            EventKind::Gx => self.gx_handle_event(),
+            EventKind::CartTransfer => self.cartridge_handle_event(),
+            EventKind::Serial => self.spi_handle_event(),
+            EventKind::TimerOverflow7 => self.timers_handle_event(0, due),
+            EventKind::TimerOverflow9 => self.timers_handle_event(1, due),
+        }
+    }
+
+    fn trace_scheduled_event(&mut self, kind: EventKind, due: u64) {
+        if self.trace.is_none() {
+            return;
+        }
+        match kind {
+            EventKind::HBlankStart | EventKind::HBlankEnd => {
+                let name = match kind {
+                    EventKind::HBlankStart => "HBlank start",
+                    _ => "HBlank end",
+                };
+                let line = self.gpu.vertical_count;
+                self.trace_event(|trace, now| {
+                    trace.instant(
+                        TraceTrack::Scheduler,
+                        name,
+                        now,
+                        vec![("vcount", line as u64), ("scheduled", due)],
+                    );
+                });
+            }
+            EventKind::Dma => {
+                let Some(index) = (self.dma.active_dmas != 0)
+                    .then(|| self.dma.active_dmas.trailing_zeros() as usize)
+                else {
+                    return;
+                };
+                let dma = &self.dma.dmas[index];
+                let args = vec![
+                    ("channel", index as u64),
+                    ("source", dma.internal_source as u64),
+                    ("dest", dma.internal_dest as u64),
+                    ("length", dma.length as u64),
+                    ("timing", dma.cnt.timing as u64),
+                    ("scheduled", due),
+                ];
+                self.trace_event(|trace, now| {
+                    trace.instant(TraceTrack::Dma, "DMA transfer", now, args);
+                });
+            }
+            // Cartridge commands are traced when sent, the rest is too frequent
+            EventKind::SpuSample
+            | EventKind::Gx
+            | EventKind::CartTransfer
+            | EventKind::Serial
+            | EventKind::TimerOverflow7
+            | EventKind::TimerOverflow9 => {}
+        }
+    }
+}
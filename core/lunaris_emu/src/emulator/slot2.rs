@@ -0,0 +1,120 @@
+//! GBA slot memory map
+//!
+//! EXMEMCNT bit 7 gives the slot to one CPU; the other reads zeros. The
+//! ROM area is on a 16-bit bus and the SRAM area on an 8-bit one, so wider
+//! accesses are assembled from narrower ones.
+use crate::cartridge::CartridgeError;
+use crate::cpu::arm_cpu::CpuType;
+use crate::emulator::Emulator;
+use crate::slot2::Slot2Device;
+use lunaris_ds_mem_const::GBA_RAM_START;
+use std::path::Path;
+
+/// First access time of the SRAM and ROM areas, in ARM7 cycles
+const FIRST_ACCESS: [i32; 4] = [10, 8, 6, 18];
+/// Sequential access time of the ROM area, in ARM7 cycles
+const SECOND_ACCESS: [i32; 2] = [6, 4];
+
+impl Emulator {
+    /// Insert `device` in the GBA slot.
+    pub fn insert_slot2(&mut self, device: Slot2Device) {
+        self.slot2.insert(device);
+    }
+
+    /// Insert the GBA cartridge at `rom_path` in the GBA slot.
+    pub fn load_slot2(&mut self, rom_path: &Path) -> Result<(), CartridgeError> {
+        let rom = std::fs::read(rom_path).map_err(|source| CartridgeError::ReadRom {
+            path: rom_path.to_path_buf(),
+            source,
+        })?;
+        self.insert_slot2(Slot2Device::GbaCartridge { rom });
+        Ok(())
+    }
+
+    /// Whether the Rumble Pak motor is on, for frontends to forward it.
+    pub const fn rumble(&self) -> bool {
+        self.slot2.rumble()
+    }
+
+    /// Whether `cpu` owns the GBA slot.
+    const fn slot2_access(&self, cpu: CpuType) -> bool {
+        let arm7 = self.ex_mem_cnt & (1 << 7) != 0;
+        arm7 == matches!(cpu, CpuType::Arm7)
+    }
+
+    /// Apply the EXMEMCNT access times to both CPUs.
+    pub(crate) fn apply_slot2_waitstates(&mut self) {
+        let sram = FIRST_ACCESS[(self.ex_mem_cnt & 3) as usize];
+        let first = FIRST_ACCESS[((self.ex_mem_cnt >> 2) & 3) as usize];
+        let second = SECOND_ACCESS[((self.ex_mem_cnt >> 4) & 1) as usize];
+        for cpu in [&mut self.arm9, &mut self.arm7] {
+            cpu.set_bus16_timing(0x8, first, second);
+            cpu.set_bus16_timing(0x9, first, second);
+            cpu.set_bus16_timing(0xA, sram, sram);
+        }
+    }
+
+    pub(crate) fn slot2_read_byte(&self, cpu: CpuType, address: u32) -> u8 {
+        if !self.slot2_access(cpu) {
+            0
+        } else if address >= GBA_RAM_START {
+            self.slot2.read_ram(address)
+        } else {
+            (self.slot2.read_rom(address) >> ((address & 1) * 8)) as u8
+        }
+    }
+
+    pub(crate) fn slot2_read_halfword(&self, cpu: CpuType, address: u32) -> u16 {
+        if !self.slot2_access(cpu) {
+            0
+        } else if address >= GBA_RAM_START {
+            self.slot2.read_ram(address) as u16 * 0x0101
+        } else {
+            self.slot2.read_rom(address)
+        }
+    }
+
+    pub(crate) fn slot2_read_word(&self, cpu: CpuType, address: u32) -> u32 {
+        if !self.slot2_access(cpu) {
+            0
+        } else if address >= GBA_RAM_START {
+            self.slot2.read_ram(address) as u32 * 0x0101_0101
+        } else {
+            self.slot2.read_rom(address) as u32 | (self.slot2.read_rom(address + 2) as u32) << 16
+        }
+    }
+
+    pub(crate) fn slot2_write_byte(&mut self, cpu: CpuType, address: u32, byte: u8) {
+        if !self.slot2_access(cpu) {
+            return;
+        }
+        if address >= GBA_RAM_START {
+            self.slot2.write_ram(address, byte);
+        } else {
+            self.slot2.write_rom(address, byte as u16 * 0x0101);
+        }
+    }
+
+    pub(crate) fn slot2_write_halfword(&mut self, cpu: CpuType, address: u32, halfword: u16) {
+        if !self.slot2_access(cpu) {
+            return;
+        }
+        if address >= GBA_RAM_START {
+            self.slot2.write_ram(address, halfword as u8);
+        } else {
+            self.slot2.write_rom(address, halfword);
+        }
+    }
+
+    pub(crate) fn slot2_write_word(&mut self, cpu: CpuType, address: u32, word: u32) {
+        if !self.slot2_access(cpu) {
+            return;
+        }
+        if address >= GBA_RAM_START {
+            self.slot2.write_ram(address, word as u8);
+        } else {
+            self.slot2.write_rom(address, word as u16);
+            self.slot2.write_rom(address + 2, (word >> 16) as u16);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! GBA sound FIFO DMA
+//!
+//! In GBA mode, ARM7 DMA1 and DMA2 with start timing 3 feed the direct sound
+//! FIFOs. Each request moves four words to the FIFO whatever the length and
+//! transfer size are set to, and the destination address is not advanced.
+use crate::emulator::Emulator;
+use crate::interrupts::Interrupt;
+
+/// FIFO A and FIFO B.
+pub const SOUND_FIFO_ADDRESSES: [u32; 2] = [0x0400_00A0, 0x0400_00A4];
+/// ARM7 DMA1 and DMA2.
+const SOUND_DMA_CHANNELS: [usize; 2] = [5, 6];
+/// ARM7 start timing 3 (wireless/GBA), as stored in `DmaCnt::timing`.
+const SOUND_DMA_TIMING: u32 = 6;
+const SOUND_DMA_WORDS: usize = 4;
+
+impl Emulator {
+    /// Clock the direct sound FIFOs on an overflow of ARM7 timer 0 or 1.
+    pub(crate) fn direct_sound_timer_overflow(&mut self, timer: usize) {
+        let refill = self.direct_sound.timer_overflow(timer);
+        for (fifo, refill) in refill.into_iter().enumerate() {
+            if refill {
+                self.sound_dma_request(fifo);
+            }
+        }
+    }
+
+    /// Run the sound DMA feeding FIFO `fifo` (0=A, 1=B), if one is armed.
+    ///
+    /// The four words are moved right away instead of through the DMA event,
+    /// so a refill never displaces another pending transfer.
+    fn sound_dma_request(&mut self, fifo: usize) {
+        let address = SOUND_FIFO_ADDRESSES[fifo];
+        let Some(index) = SOUND_DMA_CHANNELS.into_iter().find(|&index| {
+            let dma = &self.dma.dmas[index];
+            dma.cnt.enabled && dma.cnt.timing == SOUND_DMA_TIMING && dma.internal_dest == address
+        }) else {
+            return;
+        };
+
+        for _ in 0..SOUND_DMA_WORDS {
+            let source = self.dma.dmas[index].internal_source;
+            let word = self.arm7_read_word(source);
+            self.arm7_write_word(address, word);
+
+            let dma = &mut self.dma.dmas[index];
+            dma.internal_source = match dma.cnt.source_control {
+                1 => source.wrapping_sub(4),
+                2 => source,
+                _ => source.wrapping_add(4),
+            };
+        }
+
+        let dma = &mut self.dma.dmas[index];
+        if !dma.cnt.repeat {
+            dma.cnt.enabled = false;
+        }
+        if dma.cnt.irq_after_transfer
+            && let Some(interrupt) = Interrupt::from_usize(Interrupt::Dma0 as usize + index - 4)
+        {
+            self.request_interrupt7(interrupt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_overflow_refills_fifo() {
+        let mut emu = Box::new(Emulator::new());
+        emu.dma.power_on();
+        // HALTCNT: GBA mode
+        emu.arm7_write_byte(0x0400_0301, 0x40);
+        assert!(emu.gba_mode);
+        for i in 0..8u32 {
+            emu.arm7_write_word(0x0200_0000 + i * 4, 0x0101_0101 * (i + 1));
+        }
+
+        // FIFO A on timer 0, fed by DMA1: repeat, fixed dest, timing 3
+        emu.arm7_write_halfword(0x0400_0082, 1 << 11);
+        emu.dma.write_source(5, 0x0200_0000);
+        emu.dma.write_dest(5, SOUND_FIFO_ADDRESSES[0]);
+        emu.dma_write_len_cnt(5, (0xF640 << 16) | 4);
+
+        emu.direct_sound_timer_overflow(0);
+        assert_eq!(emu.direct_sound.fifos[0].len(), 16);
+        assert_eq!(emu.dma.dmas[5].internal_source, 0x0200_0010);
+        assert_ne!(emu.int7_reg.irq_flags & (1 << Interrupt::Dma1 as u32), 0);
+
+        // Still at the refill level: the next overflow plays a sample and refills
+        emu.direct_sound_timer_overflow(0);
+        assert_eq!(emu.direct_sound.fifos[0].sample, 1);
+        assert_eq!(emu.direct_sound.fifos[0].len(), 31);
+
+        // Timer 1 clocks nothing
+        emu.direct_sound_timer_overflow(1);
+        assert_eq!(emu.direct_sound.fifos[0].len(), 31);
+    }
+}
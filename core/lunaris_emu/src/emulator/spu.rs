@@ -0,0 +1,148 @@
+//! SPU playback in step with emulation
+//!
+//! The SPU is stepped by a scheduled event every [`CYCLES_PER_SAMPLE`]
+//! system cycles, fetching samples and writing captures through ARM7 memory.
+//! The mixed output is resampled to [`Config::audio_output_rate`] and kept
+//! in the SPU for [`Emulator::get_samples`]. The ratio follows
+//! [`Emulator::audio_input_rate`] and the rate control adjustment, set at
+//! the start of every frame.
+//!
+//! [`Config::audio_output_rate`]: crate::Config::audio_output_rate
+use lunaris_ds_audio::{CYCLES_PER_SAMPLE, SpuBus};
+use lunaris_ds_mem_const::CYCLES_PER_FRAME;
+
+use crate::emulator::Emulator;
+use crate::scheduler::EventKind;
+
+impl SpuBus for Emulator {
+    fn read_byte(&mut self, address: u32) -> u8 {
+        self.arm7_read_byte(address)
+    }
+
+    fn read_halfword(&mut self, address: u32) -> u16 {
+        self.arm7_read_halfword(address)
+    }
+
+    fn read_word(&mut self, address: u32) -> u32 {
+        self.arm7_read_word(address)
+    }
+
+    fn write_byte(&mut self, address: u32, byte: u8) {
+        self.arm7_write_byte(address, byte);
+    }
+
+    fn write_halfword(&mut self, address: u32, halfword: u16) {
+        self.arm7_write_halfword(address, halfword);
+    }
+}
+
+impl Emulator {
+    /// Step the SPU for the sample due at `due` and schedule the next one
+    /// relative to it, so samples stay exactly [`CYCLES_PER_SAMPLE`] apart.
+    pub(crate) fn spu_handle_event(&mut self, due: u64) {
+        let mut spu = std::mem::take(&mut self.spu);
+        spu.step(self);
+        self.spu = spu;
+        self.scheduler
+            .schedule(EventKind::SpuSample, due + CYCLES_PER_SAMPLE);
+    }
+
+    /// Resample the SPU output from here on with the current rates and
+    /// rate control adjustment.
+    pub(crate) fn set_audio_output_ratio(&mut self) {
+        let ratio = self
+            .audio_rate
+            .ratio(self.audio_input_rate(), self.config.audio_output_rate);
+        self.spu.set_output_ratio(ratio);
+    }
+
+    /// Queue a frame of silence for a frame that is not emulated, and hand
+    /// it to the audio sink, see
+    /// [`PausedAudio::Silent`](crate::PausedAudio::Silent).
+    pub(crate) fn play_silent_frame(&mut self) {
+        self.set_audio_output_ratio();
+        let cycles = self.silence_cycles + CYCLES_PER_FRAME;
+        self.spu.push_silence((cycles / CYCLES_PER_SAMPLE) as usize);
+        self.silence_cycles = cycles % CYCLES_PER_SAMPLE;
+        self.frontend_push_audio();
+    }
+
+    /// Take up to `buffer.len() / 2` buffered stereo samples, interleaved
+    /// left then right, at [`Config::audio_output_rate`]. Returns the
+    /// number of `i16` written.
+    ///
+    /// [`Config::audio_output_rate`]: crate::Config::audio_output_rate
+    pub fn get_samples(&mut self, buffer: &mut [i16]) -> usize {
+        self.spu.get_samples(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PausedAudio;
+    use crate::emulator::frame_timing::{DS_FRAME_RATE, FrameTiming};
+
+    #[test]
+    fn test_spu_plays_from_ram() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        for i in 0..4 {
+            emu.arm7_write_word(0x0200_0000 + i * 4, 0x4040_4040);
+        }
+        // Master enable at full volume; channel 0 PCM8 looping, hard left
+        emu.arm7_write_halfword(0x0400_0500, 0x807F);
+        emu.arm7_write_word(0x0400_0404, 0x0200_0000);
+        emu.arm7_write_halfword(0x0400_0408, 0xFE00);
+        emu.arm7_write_word(0x0400_040C, 4);
+        emu.arm7_write_word(0x0400_0400, 0x8800_007F);
+        assert_eq!(emu.arm7_read_word(0x0400_0400), 0x8800_007F);
+
+        emu.run();
+        let mut buffer = vec![0; 2048];
+        let len = emu.get_samples(&mut buffer);
+        assert_ne!(len, 0);
+        assert_eq!(buffer[len - 2..len], [0x3F01, 0]);
+    }
+
+    #[test]
+    fn test_resampled_output() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.config.audio_output_rate = 48000.0;
+        let mut buffer = vec![0; 1 << 16];
+        emu.run();
+        emu.get_samples(&mut buffer);
+
+        // 30 frames are ~0.5014 s of audio at the exact DS rate and half a
+        // second sped up to 60 Hz; frames end on the first CPU slice past
+        // VBLANK, so allow for a slice either way
+        for timing in [FrameTiming::Host60, FrameTiming::Exact] {
+            emu.config.frame_timing = timing;
+            for _ in 0..30 {
+                emu.run();
+            }
+            let samples = emu.get_samples(&mut buffer) / 2;
+            let expected = 30.0 * 48000.0 / timing.frame_rate();
+            assert!(
+                (samples as f64 - expected).abs() < 10.0,
+                "{timing:?} {samples}"
+            );
+        }
+        let expected = 30.0 * 48000.0 / DS_FRAME_RATE;
+
+        // Silence while paused, a frame's worth per skipped frame
+        emu.config.paused_audio = PausedAudio::Silent;
+        emu.pause();
+        for _ in 0..30 {
+            assert!(!emu.run());
+        }
+        let samples = emu.get_samples(&mut buffer) / 2;
+        assert!((samples as f64 - expected).abs() < 2.0, "{samples}");
+        assert!(buffer[..samples * 2].iter().all(|&sample| sample == 0));
+
+        emu.config.paused_audio = PausedAudio::Stopped;
+        emu.run();
+        assert_eq!(emu.get_samples(&mut buffer), 0);
+    }
+}
@@ -0,0 +1,1138 @@
+//! Whole-system savestates
+//!
+//! [`Emulator::save_state`] writes both CPUs, CP15, main RAM, WRAM, the GPU
+//! (VRAM, palettes, OAM, both 2D engines and the 3D geometry and render
+//! lists), the SPU and GBA direct sound, timers, DMA, IPC, interrupts, the
+//! math and WiFi registers, the cartridge interface (ROMCTRL, KEY1 and KEY2
+//! and the transfer in progress), the RTC and the SPI bus with the
+//! transfers of its devices, behind a [`SAVESTATE_MAGIC`] and
+//! [`SAVESTATE_VERSION`] header.
+//!
+//! Not part of a savestate:
+//!
+//! - the ROM, BIOS, firmware image and save memory: load the same ROM
+//!   before [`Emulator::load_state`]
+//! - the host clock the RTC follows, only the offset from it is saved
+//! - the touchscreen pen and microphone, which follow the input
+//! - frontend-side state: config, input, tracing, audio dump and output,
+//!   pause state
+//!
+//! The FIFOs and the geometry lists are saved with their current length, so
+//! the size of a state varies. [`Emulator::max_state_size`] bounds it for
+//! frontends that need a fixed size; a state padded up to it still loads.
+use lunaris_ds_audio::{DirectSound, FIFO_SIZE, SPU};
+use lunaris_ds_gpu::gpu_2d::Gpu2DEngine;
+use lunaris_ds_gpu::gpu_3d::structs::{
+    Gpu3D, GxCommand, Matrix, Polygon, PolygonAttrReg, TexImageParamReg, Vertex,
+};
+use lunaris_ds_gpu::gpu_root::state::GpuCoreState;
+use lunaris_ds_gpu::gpu_root::taint::TaintRegion;
+use lunaris_ds_gpu::gpu_root::{DISPLAY_FIFO_LINE_WORDS, Gpu};
+
+use crate::emulator::Emulator;
+use crate::error::{EmuError, SavestateInvalidSnafu, SavestateVersionSnafu};
+use crate::ipc::IPC_FIFO_DEPTH;
+use crate::savestate::{Savestate, StateReader, StateWriter};
+use crate::scheduler::{EVENT_STATE_SIZE, EventKind};
+
+/// First bytes of every savestate.
+pub const SAVESTATE_MAGIC: [u8; 4] = *b"LNSS";
+/// Bumped whenever the layout changes; other versions are rejected.
+pub const SAVESTATE_VERSION: u32 = 13;
+/// Geometry commands counted in [`Emulator::max_state_size`]. The FIFO
+/// holds 256 and the pipe 4, but writes keep queuing past that while the
+/// engine waits for VBlank after SWAP_BUFFERS.
+pub const MAX_SAVED_GX_COMMANDS: usize = 0x4000;
+/// Entries of the geometry command pipe.
+const GXPIPE_DEPTH: usize = 4;
+
+impl Emulator {
+    /// Serialize the running system, see [`state`](self).
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = StateWriter::new();
+        w.bytes(&SAVESTATE_MAGIC);
+        w.u32(SAVESTATE_VERSION);
+
+        self.arm9.save_state(&mut w);
+        self.arm7.save_state(&mut w);
+        self.arm9_cp15.save_state(&mut w);
+        w.bytes(&self.main_ram);
+        w.bytes(&self.shared_wram);
+        w.bytes(&self.arm7_wram);
+        self.gpu.save_state(&mut w);
+        self.spu.save_state(&mut w);
+        self.nds_timing.save_state(&mut w);
+        self.dma.save_state(&mut w);
+        self.ipc_sync_nds9.save_state(&mut w);
+        self.ipc_sync_nds7.save_state(&mut w);
+        self.fifo9.save_state(&mut w);
+        self.fifo7.save_state(&mut w);
+        self.int9_reg.save_state(&mut w);
+        self.int7_reg.save_state(&mut w);
+        self.wifi.save_state(&mut w);
+        self.slot2.save_state(&mut w);
+        self.cart.save_state(&mut w);
+        self.rtc.save_state(&mut w);
+        self.spi.save_state(&mut w);
+        self.direct_sound.save_state(&mut w);
+
+        self.scheduler.save_state(&mut w);
+        for value in [
+            self.cycle_count,
+            self.system_timestamp,
+            self.total_timestamp,
+            self.last_arm9_timestamp,
+            self.last_arm7_timestamp,
+            self.div_numer,
+            self.div_denom,
+            self.div_result,
+            self.div_remresult,
+            self.sqrt_param,
+        ] {
+            w.u64(value);
+        }
+        for &fill in &self.dma_fill {
+            w.u32(fill);
+        }
+        for value in [
+            self.aux_spi_cnt,
+            self.pow_cnt2.get(),
+            self.sio_cnt,
+            self.r_cnt,
+            self.ex_mem_cnt,
+            self.divcnt,
+            self.sqrtcnt,
+        ] {
+            w.u16(value);
+        }
+        w.u32(self.sqrt_result);
+        w.u32(self.bios_prot);
+        w.u32(self.cycles as u32);
+        w.u8(self.wram_cnt);
+        w.u8(self.postflg9);
+        w.u8(self.postflg7);
+        w.bool(self.gba_mode);
+        w.bool(self.hstep_even);
+        w.bool(self.intr_wait9);
+        w.bool(self.intr_wait7);
+        w.into_inner()
+    }
+
+    /// Upper bound of the length of [`Emulator::save_state`], the same for
+    /// every state of the loaded ROM: each list counted at its capacity.
+    /// Only more than [`MAX_SAVED_GX_COMMANDS`] queued geometry commands
+    /// exceed it.
+    pub fn max_state_size(&self) -> usize {
+        let gx = &self.gpu.engine_3d;
+        let command = encoded_len(&GxCommand::default());
+        self.save_state().len()
+            + spare(gx.gxfifo.len(), MAX_SAVED_GX_COMMANDS, command)
+            + spare(gx.gxpipe.len(), GXPIPE_DEPTH, command)
+            + spare(self.gpu.display_fifo_len(), DISPLAY_FIFO_LINE_WORDS, 4)
+            + spare(self.fifo9.send_queue.len(), IPC_FIFO_DEPTH, 4)
+            + spare(self.fifo7.send_queue.len(), IPC_FIFO_DEPTH, 4)
+            + spare(self.direct_sound.fifos[0].len(), FIFO_SIZE, 1)
+            + spare(self.direct_sound.fifos[1].len(), FIFO_SIZE, 1)
+            + spare(
+                self.scheduler.pending_count(),
+                EventKind::ALL.len(),
+                EVENT_STATE_SIZE,
+            )
+            + spare_prefix(&gx.geo_vert, gx.geo_vert_count)
+            + spare_prefix(&gx.rend_vert, gx.rend_vert_count)
+            + spare_prefix(&gx.vertex_list, gx.vertex_list_count)
+            + spare_prefix(&gx.geo_poly, gx.geo_poly_count)
+            + spare_prefix(&gx.rend_poly, gx.rend_poly_count)
+    }
+
+    /// Restore a state written by [`Emulator::save_state`]. Bytes after the
+    /// state are ignored, so a state padded to [`Emulator::max_state_size`]
+    /// loads as well.
+    ///
+    /// # Errors
+    /// If `data` is not a savestate of this version or is truncated. The
+    /// system may be partially restored then and should be reset.
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), EmuError> {
+        let mut r = StateReader::new(data);
+        let mut magic = [0; 4];
+        r.bytes(&mut magic)?;
+        if magic != SAVESTATE_MAGIC {
+            return SavestateInvalidSnafu.fail();
+        }
+        let version = r.u32()?;
+        if version != SAVESTATE_VERSION {
+            return SavestateVersionSnafu { version }.fail();
+        }
+
+        self.arm9.load_state(&mut r)?;
+        self.arm7.load_state(&mut r)?;
+        self.arm9_cp15.load_state(&mut r)?;
+        self.arm9.apply_cp15(&self.arm9_cp15);
+        r.bytes(&mut self.main_ram)?;
+        r.bytes(&mut self.shared_wram)?;
+        r.bytes(&mut self.arm7_wram)?;
+        self.gpu.load_state(&mut r)?;
+        self.spu.load_state(&mut r)?;
+        self.nds_timing.load_state(&mut r)?;
+        self.dma.load_state(&mut r)?;
+        self.ipc_sync_nds9.load_state(&mut r)?;
+        self.ipc_sync_nds7.load_state(&mut r)?;
+        self.fifo9.load_state(&mut r)?;
+        self.fifo7.load_state(&mut r)?;
+        self.int9_reg.load_state(&mut r)?;
+        self.int7_reg.load_state(&mut r)?;
+        self.wifi.load_state(&mut r)?;
+        self.slot2.load_state(&mut r)?;
+        self.cart.load_state(&mut r)?;
+        self.rtc.load_state(&mut r)?;
+        self.spi.load_state(&mut r)?;
+        self.direct_sound.load_state(&mut r)?;
+
+        self.scheduler.load_state(&mut r)?;
+        for value in [
+            &mut self.cycle_count,
+            &mut self.system_timestamp,
+            &mut self.total_timestamp,
+            &mut self.last_arm9_timestamp,
+            &mut self.last_arm7_timestamp,
+            &mut self.div_numer,
+            &mut self.div_denom,
+            &mut self.div_result,
+            &mut self.div_remresult,
+            &mut self.sqrt_param,
+        ] {
+            *value = r.u64()?;
+        }
+        for fill in &mut self.dma_fill {
+            *fill = r.u32()?;
+        }
+        self.aux_spi_cnt = r.u16()?;
+        self.pow_cnt2.set(r.u16()?);
+        for value in [
+            &mut self.sio_cnt,
+            &mut self.r_cnt,
+            &mut self.ex_mem_cnt,
+            &mut self.divcnt,
+            &mut self.sqrtcnt,
+        ] {
+            *value = r.u16()?;
+        }
+        self.sqrt_result = r.u32()?;
+        self.bios_prot = r.u32()?;
+        self.cycles = r.u32()? as i32;
+        self.wram_cnt = r.u8()?;
+        self.postflg9 = r.u8()?;
+        self.postflg7 = r.u8()?;
+        self.gba_mode = r.bool()?;
+        self.hstep_even = r.bool()?;
+        self.intr_wait9 = r.bool()?;
+        self.intr_wait7 = r.bool()?;
+        self.apply_slot2_waitstates();
+        self.skip_frame = self.fixed_frame_skip();
+        self.rebuild_page_tables();
+        Ok(())
+    }
+}
+
+impl Savestate for Gpu {
+    fn save_state(&self, w: &mut StateWriter) {
+        for region in TaintRegion::ALL {
+            w.bytes(self.memory(region));
+        }
+        let core = self.core_state();
+        w.u64(core.cycles);
+        w.bool(core.frame_ready);
+        w.bytes(&core.vramcnt);
+        w.u16(core.powcnt1);
+        w.u32_list(core.display_fifo.into_iter());
+
+        w.u16(self.display_status_arm9.get());
+        w.u16(self.display_status_arm7.get());
+        w.u16(self.vertical_count);
+        w.u32(self.frames_skipped);
+        self.engine_upper.save_state(w);
+        self.engine_lower.save_state(w);
+        self.engine_3d.save_state(w);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        for region in TaintRegion::ALL {
+            let mut data = vec![0; region.size()];
+            r.bytes(&mut data)?;
+            self.restore_memory(region, &data);
+        }
+        let mut core = GpuCoreState {
+            cycles: r.u64()?,
+            frame_ready: r.bool()?,
+            ..Default::default()
+        };
+        r.bytes(&mut core.vramcnt)?;
+        core.powcnt1 = r.u16()?;
+        core.display_fifo = r.u32_list()?;
+        self.restore_core_state(&core);
+
+        self.display_status_arm9.set(r.u16()?);
+        self.display_status_arm7.set(r.u16()?);
+        self.vertical_count = r.u16()?;
+        self.frames_skipped = r.u32()?;
+        self.engine_upper.load_state(r)?;
+        self.engine_lower.load_state(r)?;
+        self.engine_3d.load_state(r)
+    }
+}
+
+impl Savestate for Gpu2DEngine {
+    fn save_state(&self, w: &mut StateWriter) {
+        for &pixel in &self.front_framebuffer {
+            w.u32(pixel);
+        }
+        w.u32(self.dispcnt.get());
+        let cap = &self.dispcapcnt;
+        for value in [
+            cap.eva,
+            cap.evb,
+            cap.vram_write_block,
+            cap.vram_write_offset,
+            cap.capture_size,
+            cap.vram_read_offset,
+            cap.capture_source,
+        ] {
+            w.u8(value);
+        }
+        w.bool(cap.a_3d_only);
+        w.bool(cap.b_display_fifo);
+        w.bool(cap.enable_busy);
+        w.u32(self.captured_lines as u32);
+
+        for bgcnt in &self.bgcnt {
+            w.u16(bgcnt.get());
+        }
+        for &value in self
+            .bghofs
+            .iter()
+            .chain(&self.bgvofs)
+            .chain(&self.bg2p)
+            .chain(&self.bg3p)
+            .chain(&self.bg2p_internal)
+            .chain(&self.bg3p_internal)
+        {
+            w.u16(value);
+        }
+        for value in [self.bg2x, self.bg2y, self.bg3x, self.bg3y] {
+            w.u32(value);
+        }
+        for value in [
+            self.bg2x_internal,
+            self.bg2y_internal,
+            self.bg3x_internal,
+            self.bg3y_internal,
+        ] {
+            w.u32(value as u32);
+        }
+        for value in [
+            self.win0h,
+            self.win1h,
+            self.win0v,
+            self.win1v,
+            self.mosaic,
+            self.winin.get(),
+            self.winout.get(),
+            self.bldcnt.get(),
+            self.bldalpha,
+            self.master_bright,
+        ] {
+            w.u16(value);
+        }
+        w.bool(self.win0_active);
+        w.bool(self.win1_active);
+        w.u8(self.bldy);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        for pixel in &mut self.front_framebuffer {
+            *pixel = r.u32()?;
+        }
+        self.dispcnt.set(r.u32()?);
+        let cap = &mut self.dispcapcnt;
+        for value in [
+            &mut cap.eva,
+            &mut cap.evb,
+            &mut cap.vram_write_block,
+            &mut cap.vram_write_offset,
+            &mut cap.capture_size,
+            &mut cap.vram_read_offset,
+            &mut cap.capture_source,
+        ] {
+            *value = r.u8()?;
+        }
+        cap.a_3d_only = r.bool()?;
+        cap.b_display_fifo = r.bool()?;
+        cap.enable_busy = r.bool()?;
+        self.captured_lines = r.u32()? as i32;
+
+        for bgcnt in &mut self.bgcnt {
+            bgcnt.set(r.u16()?);
+        }
+        for value in self
+            .bghofs
+            .iter_mut()
+            .chain(&mut self.bgvofs)
+            .chain(&mut self.bg2p)
+            .chain(&mut self.bg3p)
+            .chain(&mut self.bg2p_internal)
+            .chain(&mut self.bg3p_internal)
+        {
+            *value = r.u16()?;
+        }
+        for value in [
+            &mut self.bg2x,
+            &mut self.bg2y,
+            &mut self.bg3x,
+            &mut self.bg3y,
+        ] {
+            *value = r.u32()?;
+        }
+        for value in [
+            &mut self.bg2x_internal,
+            &mut self.bg2y_internal,
+            &mut self.bg3x_internal,
+            &mut self.bg3y_internal,
+        ] {
+            *value = r.u32()? as i32;
+        }
+        for value in [
+            &mut self.win0h,
+            &mut self.win1h,
+            &mut self.win0v,
+            &mut self.win1v,
+            &mut self.mosaic,
+        ] {
+            *value = r.u16()?;
+        }
+        self.winin.set(r.u16()?);
+        self.winout.set(r.u16()?);
+        self.bldcnt.set(r.u16()?);
+        self.bldalpha = r.u16()?;
+        self.master_bright = r.u16()?;
+        self.win0_active = r.bool()?;
+        self.win1_active = r.bool()?;
+        self.bldy = r.u8()?;
+        Ok(())
+    }
+}
+
+impl Savestate for Gpu3D {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u64(self.cycles as u64);
+        let cnt = &self.disp3dcnt;
+        for flag in [
+            cnt.texture_mapping,
+            cnt.highlight_shading,
+            cnt.alpha_test,
+            cnt.alpha_blending,
+            cnt.anti_aliasing,
+            cnt.edge_marking,
+            cnt.fog_color_mode,
+            cnt.fog_enable,
+            cnt.color_buffer_underflow,
+            cnt.ram_overflow,
+            cnt.rear_plane_mode,
+        ] {
+            w.bool(flag);
+        }
+        w.u32(cnt.fog_depth_shift as u32);
+        self.polygon_attr.save_state(w);
+        self.teximage_param.save_state(w);
+        for &color in &self.toon_table {
+            w.u16(color);
+        }
+        let viewport = &self.viewport;
+        w.bytes(&[viewport.x1, viewport.y1, viewport.x2, viewport.y2]);
+        let gxstat = &self.gxstat;
+        for flag in [
+            gxstat.box_pos_vec_busy,
+            gxstat.boxtest_result,
+            gxstat.mtx_stack_busy,
+            gxstat.mtx_overflow,
+            gxstat.geo_busy,
+        ] {
+            w.bool(flag);
+        }
+        w.u32(gxstat.gxfifo_irq_stat as u32);
+        for value in [
+            self.pltt_base,
+            self.polygon_type,
+            self.clear_depth,
+            self.dot_depth,
+            self.clear_color,
+            self.flush_mode as u32,
+        ] {
+            w.u32(value);
+        }
+
+        // Command processing
+        save_list(w, self.gxfifo.iter());
+        save_list(w, self.gxpipe.iter());
+        w.u32_list(self.cmd_params.iter().copied());
+        w.bytes(&[
+            self.param_count,
+            self.cmd_param_count,
+            self.cmd_count,
+            self.total_params,
+        ]);
+        w.u32(self.current_cmd);
+        self.current_poly_attr.save_state(w);
+        w.u32(self.current_color);
+        for &value in self.current_vertex.iter().chain(&self.current_texcoords) {
+            w.u16(value as u16);
+        }
+
+        // Geometry and render lists
+        w.bool(self.swap_buffers);
+        save_prefix(w, &self.geo_vert, self.geo_vert_count);
+        save_prefix(w, &self.rend_vert, self.rend_vert_count);
+        save_prefix(w, &self.geo_poly, self.geo_poly_count);
+        save_prefix(w, &self.rend_poly, self.rend_poly_count);
+        save_prefix(w, &self.vertex_list, self.vertex_list_count);
+        w.u32(self.last_poly_strip.map_or(u32::MAX, |index| index as u32));
+        w.u32(self.consecutive_polygons as u32);
+        w.u32(self.vtx_16_index as u32);
+
+        // Matrices
+        w.u8(self.mtx_mode);
+        for matrix in [
+            &self.projection_mtx,
+            &self.vector_mtx,
+            &self.modelview_mtx,
+            &self.texture_mtx,
+            &self.projection_stack,
+            &self.texture_stack,
+            &self.clip_mtx,
+            &self.mult_params,
+        ] {
+            matrix.save_state(w);
+        }
+        for matrix in self.modelview_stack.iter().chain(&self.vector_stack) {
+            matrix.save_state(w);
+        }
+        w.bool(self.clip_dirty);
+        w.u8(self.model_view_sp);
+        w.u32(self.mult_params_index as u32);
+
+        // Lighting and tests
+        for value in [
+            self.emission_color,
+            self.ambient_color,
+            self.diffuse_color,
+            self.specular_color,
+        ]
+        .iter()
+        .chain(&self.light_color)
+        {
+            w.u16(*value);
+        }
+        for &value in self
+            .light_direction
+            .iter()
+            .flatten()
+            .chain(&self.normal_vector)
+            .chain(&self.vec_test_result)
+        {
+            w.u16(value as u16);
+        }
+        w.bytes(&self.shine_table);
+        w.bool(self.using_shine_table);
+        for &value in &self.pos_test_result {
+            w.u32(value as u32);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.cycles = r.u64()? as i64;
+        let cnt = &mut self.disp3dcnt;
+        for flag in [
+            &mut cnt.texture_mapping,
+            &mut cnt.highlight_shading,
+            &mut cnt.alpha_test,
+            &mut cnt.alpha_blending,
+            &mut cnt.anti_aliasing,
+            &mut cnt.edge_marking,
+            &mut cnt.fog_color_mode,
+            &mut cnt.fog_enable,
+            &mut cnt.color_buffer_underflow,
+            &mut cnt.ram_overflow,
+            &mut cnt.rear_plane_mode,
+        ] {
+            *flag = r.bool()?;
+        }
+        cnt.fog_depth_shift = r.u32()? as i32;
+        self.polygon_attr.load_state(r)?;
+        self.teximage_param.load_state(r)?;
+        for color in &mut self.toon_table {
+            *color = r.u16()?;
+        }
+        let mut viewport = [0; 4];
+        r.bytes(&mut viewport)?;
+        [
+            self.viewport.x1,
+            self.viewport.y1,
+            self.viewport.x2,
+            self.viewport.y2,
+        ] = viewport;
+        let gxstat = &mut self.gxstat;
+        for flag in [
+            &mut gxstat.box_pos_vec_busy,
+            &mut gxstat.boxtest_result,
+            &mut gxstat.mtx_stack_busy,
+            &mut gxstat.mtx_overflow,
+            &mut gxstat.geo_busy,
+        ] {
+            *flag = r.bool()?;
+        }
+        gxstat.gxfifo_irq_stat = r.u32()? as i32;
+        for value in [
+            &mut self.pltt_base,
+            &mut self.polygon_type,
+            &mut self.clear_depth,
+            &mut self.dot_depth,
+            &mut self.clear_color,
+        ] {
+            *value = r.u32()?;
+        }
+        self.flush_mode = r.u32()? as i32;
+
+        self.gxfifo = load_list(r)?;
+        self.gxpipe = load_list(r)?;
+        let params: Vec<u32> = r.u32_list()?;
+        if params.len() != self.cmd_params.len() {
+            return SavestateInvalidSnafu.fail();
+        }
+        self.cmd_params = params;
+        let mut counts = [0; 4];
+        r.bytes(&mut counts)?;
+        [
+            self.param_count,
+            self.cmd_param_count,
+            self.cmd_count,
+            self.total_params,
+        ] = counts;
+        self.current_cmd = r.u32()?;
+        self.current_poly_attr.load_state(r)?;
+        self.current_color = r.u32()?;
+        for value in self
+            .current_vertex
+            .iter_mut()
+            .chain(&mut self.current_texcoords)
+        {
+            *value = r.u16()? as i16;
+        }
+
+        self.swap_buffers = r.bool()?;
+        self.geo_vert_count = load_prefix(r, &mut self.geo_vert)?;
+        self.rend_vert_count = load_prefix(r, &mut self.rend_vert)?;
+        self.geo_poly_count = load_prefix(r, &mut self.geo_poly)?;
+        self.rend_poly_count = load_prefix(r, &mut self.rend_poly)?;
+        self.vertex_list_count = load_prefix(r, &mut self.vertex_list)?;
+        self.last_poly_strip = match r.u32()? {
+            u32::MAX => None,
+            index => Some(index as usize),
+        };
+        self.consecutive_polygons = r.u32()? as i32;
+        self.vtx_16_index = r.u32()? as i32;
+
+        self.mtx_mode = r.u8()?;
+        for matrix in [
+            &mut self.projection_mtx,
+            &mut self.vector_mtx,
+            &mut self.modelview_mtx,
+            &mut self.texture_mtx,
+            &mut self.projection_stack,
+            &mut self.texture_stack,
+            &mut self.clip_mtx,
+            &mut self.mult_params,
+        ] {
+            matrix.load_state(r)?;
+        }
+        for matrix in self
+            .modelview_stack
+            .iter_mut()
+            .chain(&mut self.vector_stack)
+        {
+            matrix.load_state(r)?;
+        }
+        self.clip_dirty = r.bool()?;
+        self.model_view_sp = r.u8()?;
+        self.mult_params_index = r.u32()? as usize;
+
+        for value in [
+            &mut self.emission_color,
+            &mut self.ambient_color,
+            &mut self.diffuse_color,
+            &mut self.specular_color,
+        ]
+        .into_iter()
+        .chain(&mut self.light_color)
+        {
+            *value = r.u16()?;
+        }
+        for value in self
+            .light_direction
+            .iter_mut()
+            .flatten()
+            .chain(&mut self.normal_vector)
+            .chain(&mut self.vec_test_result)
+        {
+            *value = r.u16()? as i16;
+        }
+        r.bytes(&mut self.shine_table)?;
+        self.using_shine_table = r.bool()?;
+        for value in &mut self.pos_test_result {
+            *value = r.u32()? as i32;
+        }
+        Ok(())
+    }
+}
+
+/// Bytes `item` takes in a state.
+fn encoded_len(item: &impl Savestate) -> usize {
+    let mut w = StateWriter::new();
+    item.save_state(&mut w);
+    w.into_inner().len()
+}
+
+/// Bytes `len` entries of `item` bytes miss to reach `max`.
+const fn spare(len: usize, max: usize, item: usize) -> usize {
+    max.saturating_sub(len) * item
+}
+
+/// Bytes a prefix written by [`save_prefix`] grows by at most.
+fn spare_prefix<T: Savestate>(items: &[T], count: i32) -> usize {
+    let item = items.first().map_or(0, encoded_len);
+    spare(count.max(0) as usize, items.len(), item)
+}
+
+/// Write the first `count` of `items`.
+fn save_prefix<T: Savestate>(w: &mut StateWriter, items: &[T], count: i32) {
+    let count = (count.max(0) as usize).min(items.len());
+    w.u32(count as u32);
+    for item in &items[..count] {
+        item.save_state(w);
+    }
+}
+
+/// Read a prefix written by [`save_prefix`] and return its length.
+fn load_prefix<T: Savestate>(r: &mut StateReader<'_>, items: &mut [T]) -> Result<i32, EmuError> {
+    let count = r.u32()? as usize;
+    let Some(prefix) = items.get_mut(..count) else {
+        return SavestateInvalidSnafu.fail();
+    };
+    for item in prefix {
+        item.load_state(r)?;
+    }
+    Ok(count as i32)
+}
+
+fn save_list<'a, T: Savestate + 'a>(
+    w: &mut StateWriter,
+    items: impl ExactSizeIterator<Item = &'a T>,
+) {
+    w.u32(items.len() as u32);
+    for item in items {
+        item.save_state(w);
+    }
+}
+
+fn load_list<T: Savestate + Default, C: FromIterator<T>>(
+    r: &mut StateReader<'_>,
+) -> Result<C, EmuError> {
+    let len = r.u32()? as usize;
+    if len > r.remaining() {
+        return SavestateInvalidSnafu.fail();
+    }
+    (0..len)
+        .map(|_| {
+            let mut item = T::default();
+            item.load_state(r).map(|()| item)
+        })
+        .collect()
+}
+
+impl Savestate for DirectSound {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u16(self.cnt.get());
+        for fifo in &self.fifos {
+            w.u8(fifo.sample as u8);
+            w.u8(fifo.len() as u8);
+            for sample in fifo.queued() {
+                w.u8(sample as u8);
+            }
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.cnt.set(r.u16()?);
+        for fifo in &mut self.fifos {
+            fifo.sample = r.u8()? as i8;
+            let mut samples = vec![0; r.u8()? as usize];
+            if samples.len() > FIFO_SIZE {
+                return SavestateInvalidSnafu.fail();
+            }
+            r.bytes(&mut samples)?;
+            fifo.set_queued(samples.into_iter().map(|sample| sample as i8));
+        }
+        Ok(())
+    }
+}
+
+impl Savestate for GxCommand {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u8(self.command);
+        w.u32(self.param);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.command = r.u8()?;
+        self.param = r.u32()?;
+        Ok(())
+    }
+}
+
+impl Savestate for Matrix {
+    fn save_state(&self, w: &mut StateWriter) {
+        for &value in self.m.iter().flatten() {
+            w.u32(value as u32);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        for value in self.m.iter_mut().flatten() {
+            *value = r.u32()? as i32;
+        }
+        Ok(())
+    }
+}
+
+impl Savestate for Vertex {
+    fn save_state(&self, w: &mut StateWriter) {
+        for &value in self
+            .coords
+            .iter()
+            .chain(&self.colors)
+            .chain(&self.final_colors)
+            .chain(&self.texcoords)
+        {
+            w.u32(value as u32);
+        }
+        w.bool(self.clipped);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        for value in self
+            .coords
+            .iter_mut()
+            .chain(&mut self.colors)
+            .chain(&mut self.final_colors)
+            .chain(&mut self.texcoords)
+        {
+            *value = r.u32()? as i32;
+        }
+        self.clipped = r.bool()?;
+        Ok(())
+    }
+}
+
+impl Savestate for Polygon {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u16(self.vert_index);
+        w.u8(self.vertices);
+        w.u16(self.top_y);
+        w.u16(self.bottom_y);
+        self.attributes.save_state(w);
+        self.texparams.save_state(w);
+        w.u32(self.palette_base);
+        w.bool(self.translucent);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.vert_index = r.u16()?;
+        self.vertices = r.u8()?;
+        self.top_y = r.u16()?;
+        self.bottom_y = r.u16()?;
+        self.attributes.load_state(r)?;
+        self.texparams.load_state(r)?;
+        self.palette_base = r.u32()?;
+        self.translucent = r.bool()?;
+        Ok(())
+    }
+}
+
+impl Savestate for PolygonAttrReg {
+    fn save_state(&self, w: &mut StateWriter) {
+        for value in [self.light_enable, self.polygon_mode, self.alpha, self.id] {
+            w.u32(value as u32);
+        }
+        for flag in [
+            self.render_back,
+            self.render_front,
+            self.set_new_trans_depth,
+            self.render_1dot,
+            self.render_far_intersect,
+            self.depth_test_equal,
+            self.fog_enable,
+        ] {
+            w.bool(flag);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        for value in [
+            &mut self.light_enable,
+            &mut self.polygon_mode,
+            &mut self.alpha,
+            &mut self.id,
+        ] {
+            *value = r.u32()? as i32;
+        }
+        for flag in [
+            &mut self.render_back,
+            &mut self.render_front,
+            &mut self.set_new_trans_depth,
+            &mut self.render_1dot,
+            &mut self.render_far_intersect,
+            &mut self.depth_test_equal,
+            &mut self.fog_enable,
+        ] {
+            *flag = r.bool()?;
+        }
+        Ok(())
+    }
+}
+
+impl Savestate for TexImageParamReg {
+    fn save_state(&self, w: &mut StateWriter) {
+        for value in [
+            self.vram_offset,
+            self.s_size,
+            self.t_size,
+            self.format,
+            self.transformation_mode,
+        ] {
+            w.u32(value as u32);
+        }
+        for flag in [
+            self.repeat_s,
+            self.repeat_t,
+            self.flip_s,
+            self.flip_t,
+            self.color0_transparent,
+        ] {
+            w.bool(flag);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        for value in [
+            &mut self.vram_offset,
+            &mut self.s_size,
+            &mut self.t_size,
+            &mut self.format,
+            &mut self.transformation_mode,
+        ] {
+            *value = r.u32()? as i32;
+        }
+        for flag in [
+            &mut self.repeat_s,
+            &mut self.repeat_t,
+            &mut self.flip_s,
+            &mut self.flip_t,
+            &mut self.color0_transparent,
+        ] {
+            *flag = r.bool()?;
+        }
+        Ok(())
+    }
+}
+
+impl Savestate for SPU {
+    fn save_state(&self, w: &mut StateWriter) {
+        for channel in (0..16).filter_map(|index| self.get_channel(index)) {
+            let cnt = &channel.channel_cnt;
+            for value in [
+                cnt.volume,
+                cnt.divider,
+                cnt.panning,
+                cnt.wave_duty,
+                cnt.repeat_mode,
+                cnt.format,
+                channel.sound_source,
+                channel.sound_len,
+                channel.position as u32,
+                channel.timer,
+            ] {
+                w.u32(value);
+            }
+            w.bool(cnt.hold_sample);
+            w.bool(cnt.busy);
+            for value in [
+                channel.sound_timer,
+                channel.sound_pnt,
+                channel.sample as u16,
+                channel.adpcm_value as u16,
+                channel.adpcm_loop_value as u16,
+                channel.noise,
+            ] {
+                w.u16(value);
+            }
+            w.u8(channel.adpcm_index);
+            w.u8(channel.adpcm_loop_index);
+            w.u8(channel.adpcm_byte);
+        }
+        w.u16(self.get_soundcnt());
+        w.u16(self.get_soundbias());
+        w.u8(self.get_sndcap0());
+        w.u8(self.get_sndcap1());
+        for index in 0..2 {
+            let capture = self.capture(index);
+            w.u32(capture.destination);
+            w.u16(capture.len);
+            w.u32(capture.position);
+            w.u32(capture.timer);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        for index in 0..16 {
+            let Some(channel) = self.get_channel_mut(index) else {
+                break;
+            };
+            let cnt = &mut channel.channel_cnt;
+            for value in [
+                &mut cnt.volume,
+                &mut cnt.divider,
+                &mut cnt.panning,
+                &mut cnt.wave_duty,
+                &mut cnt.repeat_mode,
+                &mut cnt.format,
+                &mut channel.sound_source,
+                &mut channel.sound_len,
+            ] {
+                *value = r.u32()?;
+            }
+            channel.position = r.u32()? as i32;
+            channel.timer = r.u32()?;
+            cnt.hold_sample = r.bool()?;
+            cnt.busy = r.bool()?;
+            channel.sound_timer = r.u16()?;
+            channel.sound_pnt = r.u16()?;
+            channel.sample = r.u16()? as i16;
+            channel.adpcm_value = r.u16()? as i16;
+            channel.adpcm_loop_value = r.u16()? as i16;
+            channel.noise = r.u16()?;
+            channel.adpcm_index = r.u8()?;
+            channel.adpcm_loop_index = r.u8()?;
+            channel.adpcm_byte = r.u8()?;
+        }
+        self.set_soundcnt(r.u16()?);
+        self.set_soundbias(r.u16()?);
+        self.set_sndcap0(r.u8()?);
+        self.set_sndcap1(r.u8()?);
+        for index in 0..2 {
+            let capture = self.capture_mut(index);
+            capture.destination = r.u32()?;
+            capture.len = r.u16()?;
+            capture.position = r.u32()?;
+            capture.timer = r.u32()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cartridge::key2::Key2;
+
+    #[test]
+    fn test_savestate_round_trip() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.run();
+        emu.main_ram[0x1234] = 0x56;
+        emu.gpu.write_palette_a(2, 0x7FFF);
+        emu.gpu.engine_3d.modelview_mtx.m[1][2] = -7;
+        emu.fifo9.send_queue.push_back(0xCAFE);
+        emu.arm7.regs[3] = 0x1234_5678;
+        emu.cart.romctrl.set(0x00A0_2000);
+        emu.cart.key2_card = Some(Key2::new(0x12_3456, 0x65_4321));
+        emu.cart.bytes_left = 0x1FC;
+        emu.rtc.set_offset(3600);
+        emu.spi.set_spicnt(0x8A01);
+        emu.direct_sound.fifos[1].write_word(0x0403_0201);
+        let state = emu.save_state();
+
+        let mut restored = Box::new(Emulator::new());
+        restored.power_on();
+        restored.load_state(&state).unwrap();
+        assert_eq!(restored.main_ram[0x1234], 0x56);
+        assert_eq!(restored.gpu.read_palette_a(2), 0x7FFF);
+        assert_eq!(restored.gpu.engine_3d.modelview_mtx.m[1][2], -7);
+        assert_eq!(restored.fifo9.send_queue, [0xCAFE]);
+        assert_eq!(restored.arm7.regs, emu.arm7.regs);
+        assert_eq!(restored.arm9.regs, emu.arm9.regs);
+        assert_eq!(restored.system_timestamp, emu.system_timestamp);
+        assert_eq!(restored.cart.romctrl.get(), 0x00A0_2000);
+        assert_eq!(restored.cart.key2_card, emu.cart.key2_card);
+        assert_eq!(restored.cart.bytes_left, 0x1FC);
+        assert_eq!(restored.rtc.offset(), 3600);
+        assert_eq!(restored.spi.get_spicnt(), 0x8A01);
+        assert_eq!(
+            restored.direct_sound.fifos[1].queued().collect::<Vec<_>>(),
+            [1, 2, 3, 4]
+        );
+        assert_eq!(restored.save_state(), state);
+
+        // Both run on identically
+        emu.run();
+        restored.run();
+        assert_eq!(restored.save_state(), emu.save_state());
+
+        assert!(restored.load_state(&state[..state.len() / 2]).is_err());
+        assert!(restored.load_state(b"NOPE\x01\0\0\0").is_err());
+    }
+
+    #[test]
+    fn test_max_state_size() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.run();
+        let max = emu.max_state_size();
+        let mut padded = emu.save_state();
+        assert!(padded.len() < max);
+        padded.resize(max, 0);
+        emu.load_state(&padded).unwrap();
+
+        // Every list at its capacity
+        let gx = &mut emu.gpu.engine_3d;
+        gx.gxfifo
+            .resize(MAX_SAVED_GX_COMMANDS, GxCommand::default());
+        gx.gxpipe.resize(GXPIPE_DEPTH, GxCommand::default());
+        gx.geo_vert_count = gx.geo_vert.len() as i32;
+        gx.rend_vert_count = gx.rend_vert.len() as i32;
+        gx.vertex_list_count = gx.vertex_list.len() as i32;
+        gx.geo_poly_count = gx.geo_poly.len() as i32;
+        gx.rend_poly_count = gx.rend_poly.len() as i32;
+        for word in 0..DISPLAY_FIFO_LINE_WORDS as u32 {
+            emu.gpu.write_disp_mmem_fifo(word);
+        }
+        emu.fifo9.send_queue.resize(IPC_FIFO_DEPTH, 0);
+        emu.fifo7.send_queue.resize(IPC_FIFO_DEPTH, 0);
+        for fifo in &mut emu.direct_sound.fifos {
+            fifo.set_queued([0; FIFO_SIZE]);
+        }
+        for kind in EventKind::ALL {
+            emu.scheduler.schedule(kind, u64::MAX);
+        }
+        assert_eq!(emu.save_state().len(), max);
+        assert_eq!(emu.max_state_size(), max);
+    }
+}
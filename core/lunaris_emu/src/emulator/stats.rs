@@ -0,0 +1,69 @@
+//! Performance counters
+//!
+//! Counts where each frame's work goes, so a slow game can be narrowed down
+//! to CPU, DMA, geometry or rendering load. The counters only add to
+//! integers and take two clock readings per scanline. With the `tracing`
+//! feature, frames and scanline rendering also get trace-level spans.
+use std::time::{Duration, Instant};
+
+use crate::cpu::arm_cpu::CpuType;
+use crate::emulator::Emulator;
+
+/// Work done during one frame, see [`Emulator::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmuStats {
+    pub arm9_instructions: u64,
+    pub arm7_instructions: u64,
+    /// Bytes moved by the DMA channels of both CPUs
+    pub dma_bytes: u64,
+    /// Geometry commands the 3D engine executed
+    pub gx_commands: u64,
+    /// Host time spent composing scanlines
+    pub render_time: Duration,
+    /// Host time the whole frame took
+    pub frame_time: Duration,
+}
+
+impl Emulator {
+    /// Counters of the last completed frame.
+    pub const fn stats(&self) -> EmuStats {
+        self.last_stats
+    }
+
+    pub(crate) const fn count_instructions(&mut self, cpu_type: CpuType, count: u64) {
+        match cpu_type {
+            CpuType::Arm9 => self.counters.arm9_instructions += count,
+            CpuType::Arm7 => self.counters.arm7_instructions += count,
+        }
+    }
+
+    /// Start timing a frame, unless one is in progress after a breakpoint.
+    pub(crate) fn stats_begin_frame(&mut self) {
+        self.stats_frame_start.get_or_insert_with(Instant::now);
+    }
+
+    /// Publish the counters of the frame that just completed.
+    pub(crate) fn stats_end_frame(&mut self) {
+        if let Some(start) = self.stats_frame_start.take() {
+            self.counters.frame_time = start.elapsed();
+        }
+        self.last_stats = std::mem::take(&mut self.counters);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.run_frame().unwrap();
+        let stats = emu.stats();
+        assert!(stats.arm9_instructions > 0);
+        assert!(stats.arm7_instructions > 0);
+        assert!(stats.frame_time >= stats.render_time);
+        assert!(stats.render_time > Duration::ZERO);
+    }
+}
@@ -1,84 +1,188 @@
+use crate::cpu::arm_cpu::CpuType;
 use crate::emulator::Emulator;
 use crate::interrupts::Interrupt;
+use crate::scheduler::EventKind;
+use crate::timers::NDSTiming;
 
 impl Emulator {
     /// Handle timer overflow
+    ///
+    /// The counter has already been reloaded; this raises the interrupt
+    /// and clocks the next timer if it counts up.
     pub fn overflow(&mut self, index: usize) {
-        // Reload counter with reload value
-        self.nds_timing.timers[index].counter = self.nds_timing.timers[index].reload_value;
-
         // Overflow -> request interrupt
         if self.nds_timing.timers[index].irq_on_overflow {
-            if index < 4 {
-                let id = (Interrupt::Timer0 as usize) + index;
-                if let Some(id) = Interrupt::from_usize(id) {
-                    self.request_interrupt7(id);
-                } else {
-                    #[cfg(feature = "tracing")]
-                    tracing::warn!("Invalid Interrupt value: {id}");
+            let id = (Interrupt::Timer0 as usize) + index % 4;
+            if let Some(id) = Interrupt::from_usize(id) {
+                match NDSTiming::group(index) {
+                    0 => self.request_interrupt7(id),
+                    _ => self.request_interrupt9(id),
                 }
             } else {
-                let id = (Interrupt::Timer0 as usize) + index - 4;
-                if let Some(id) = Interrupt::from_usize(id) {
-                    self.request_interrupt9(id as Interrupt);
-                } else {
-                    #[cfg(feature = "tracing")]
-                    tracing::warn!("Invalid Interrupt value: {id}");
-                }
+                #[cfg(feature = "tracing")]
+                tracing::warn!("Invalid Interrupt value: {id}");
             }
         }
 
+        // ARM7 timers 0 and 1 clock the GBA direct sound FIFOs
+        if self.gba_mode && index < 2 {
+            self.direct_sound_timer_overflow(index);
+        }
+
         // Count-up timing behavior
-        if index != 3 && index != 7 {
-            let count_up_timing = self.nds_timing.timers[index + 1].count_up_timing;
-            let enabled = self.nds_timing.timers[index + 1].enabled;
-            let count_up = count_up_timing && enabled;
-
-            if count_up {
-                if self.nds_timing.timers[index + 1].counter == 0xFFFF {
-                    self.overflow(index + 1); // recursion baby
-                } else {
-                    self.nds_timing.timers[index + 1].counter += 1;
-                }
-            }
+        if index % 4 != 3 && self.nds_timing.count_up(index + 1) {
+            self.overflow(index + 1); // recursion baby
         }
     }
 
-    /// Run ARM9 timers for specified cycles
-    pub fn run_timers9(&mut self, cycles: i32) {
-        // Run timers 0-3 (ARM9)
-        for i in 0..4 {
-            if self.nds_timing.timers[i].enabled {
-                self.run_timer(cycles, i);
+    /// Handle the overflow event of the timers of `group`, due at `due`.
+    pub(crate) fn timers_handle_event(&mut self, group: usize, due: u64) {
+        self.run_timer_group(group, due);
+    }
+
+    /// Run the timers of `group` up to system time `now`, handling every
+    /// overflow they reach in order, then schedule the next one.
+    fn run_timer_group(&mut self, group: usize, now: u64) {
+        if !self.nds_timing.advance_to(group, now) {
+            return;
+        }
+        while self.nds_timing.has_pending(group) {
+            let overflowed = self.nds_timing.step(group);
+            for i in 0..4 {
+                if overflowed & (1 << i) != 0 {
+                    self.overflow(group * 4 + i);
+                }
             }
         }
+        self.schedule_timer_overflow(group);
     }
 
-    /// Run ARM7 timers for specified cycles
-    pub fn run_timers7(&mut self, cycles: i32) {
-        // Run timers 4-7 (ARM7)
-        for i in 4..8 {
-            if self.nds_timing.timers[i].enabled {
-                self.run_timer(cycles, i);
-            }
+    /// Schedule the overflow event of `group` at its next overflow, or
+    /// cancel it while no timer ticks.
+    fn schedule_timer_overflow(&mut self, group: usize) {
+        let kind = match group {
+            0 => EventKind::TimerOverflow7,
+            _ => EventKind::TimerOverflow9,
+        };
+        match self.nds_timing.next_overflow(group) {
+            Some(time) => self.scheduler.schedule(kind, time),
+            None => self.scheduler.cancel(kind),
         }
     }
 
-    /// Run individual timer
-    pub fn run_timer(&mut self, cycles: i32, index: usize) {
-        if !self.nds_timing.timers[index].count_up_timing {
-            self.nds_timing.timers[index].cycles_left -= cycles;
-            let old_timer = self.nds_timing.timers[index].counter;
+    /// Run the timers of `cpu_type` up to the time it has reached.
+    fn timers_catch_up(&mut self, cpu_type: CpuType) -> usize {
+        let group = match cpu_type {
+            CpuType::Arm7 => 0,
+            CpuType::Arm9 => 1,
+        };
+        self.run_timer_group(group, self.cpu_system_time(cpu_type));
+        group
+    }
 
-            while self.nds_timing.timers[index].cycles_left <= 0 {
-                self.nds_timing.timers[index].counter += 1;
-                self.nds_timing.timers[index].cycles_left += self.nds_timing.timer_clock_divs
-                    [self.nds_timing.timers[index].clock_div as usize];
-            }
+    /// Timer register index of `address` in 0x04000100..0x04000110.
+    const fn timer_index(cpu_type: CpuType, address: u32) -> usize {
+        let base = match cpu_type {
+            CpuType::Arm7 => 0,
+            CpuType::Arm9 => 4,
+        };
+        base + ((address >> 2) & 3) as usize
+    }
 
-            if self.nds_timing.timers[index].counter < old_timer {
-                self.overflow(index);
+    /// Read a halfword of the timer registers of one CPU.
+    pub(crate) fn timer_read_halfword(&mut self, cpu_type: CpuType, address: u32) -> u16 {
+        self.timers_catch_up(cpu_type);
+        let index = Self::timer_index(cpu_type, address);
+        match address & 2 {
+            0 => self.nds_timing.read_lo(index),
+            _ => self.nds_timing.read_hi(index),
+        }
+    }
+
+    /// Write a halfword to the timer registers of one CPU.
+    pub(crate) fn timer_write_halfword(&mut self, cpu_type: CpuType, address: u32, halfword: u16) {
+        let group = self.timers_catch_up(cpu_type);
+        let index = Self::timer_index(cpu_type, address);
+        match address & 2 {
+            0 => self.nds_timing.write_lo(halfword, index),
+            _ => {
+                // Starting a timer or changing its prescaler moves the
+                // next overflow
+                self.nds_timing.write_hi(halfword, index);
+                self.schedule_timer_overflow(group);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Move the system and both CPUs to `time`, handling the events due.
+    fn run_to(emu: &mut Emulator, time: u64) {
+        emu.system_timestamp = time;
+        emu.arm9.timestamp = time << 1;
+        emu.arm7.timestamp = time;
+        emu.run_due_events();
+    }
+
+    #[test]
+    fn test_prescaler_and_cascade() {
+        let mut emu = Box::new(Emulator::new());
+        emu.nds_timing.power_on();
+
+        // ARM9 timer 0: F/64 from 0xFFF0, IRQ; timer 1 counts up, IRQ
+        emu.arm9_write_word(0x0400_0100, 0x00C1_FFF0);
+        emu.arm9_write_word(0x0400_0104, 0x00C4_FFFF);
+        assert_eq!(
+            emu.scheduler.due_time(EventKind::TimerOverflow9),
+            Some(64 * 16)
+        );
+        run_to(&mut emu, 64 * 8 + 10);
+        assert_eq!(emu.arm9_read_halfword(0x0400_0100), 0xFFF8);
+        run_to(&mut emu, 64 * 16 - 1);
+        assert_eq!(emu.int9_reg.irq_flags, 0);
+
+        // The overflow event raises the IRQ and carries timer 1
+        run_to(&mut emu, 64 * 16);
+        let timers = (1 << Interrupt::Timer0 as u32) | (1 << Interrupt::Timer1 as u32);
+        assert_eq!(emu.int9_reg.irq_flags & timers, timers);
+        assert_eq!(emu.int7_reg.irq_flags & timers, 0);
+        assert_eq!(
+            emu.scheduler.due_time(EventKind::TimerOverflow9),
+            Some(64 * 32)
+        );
+
+        // Events handled late still count every overflow
+        run_to(&mut emu, 64 * 48 + 10);
+        assert_eq!(emu.arm9_read_halfword(0x0400_0100), 0xFFF0);
+        assert_eq!(emu.arm9_read_halfword(0x0400_0104), 0xFFFF);
+        assert_eq!(
+            emu.scheduler.due_time(EventKind::TimerOverflow9),
+            Some(64 * 64)
+        );
+
+        // ARM7 timer 3 at F/1024, read back through its registers
+        let start = emu.system_timestamp;
+        emu.arm7_write_halfword(0x0400_010C, 0x1000);
+        emu.arm7_write_halfword(0x0400_010E, 0x0083);
+        let overflow = start + 0xF000 * 1024;
+        assert_eq!(
+            emu.scheduler.due_time(EventKind::TimerOverflow7),
+            Some(overflow)
+        );
+        run_to(&mut emu, start + 1024 * 3);
+        assert_eq!(emu.arm7_read_word(0x0400_010C), 0x0083_1003);
+
+        // A new prescaler moves the overflow, stopping cancels it
+        emu.arm7_write_halfword(0x0400_010E, 0x0082);
+        let overflow = start + 1024 * 3 + (0x1_0000 - 0x1003) * 256;
+        assert_eq!(
+            emu.scheduler.due_time(EventKind::TimerOverflow7),
+            Some(overflow)
+        );
+        emu.arm7_write_halfword(0x0400_010E, 0x0002);
+        assert_eq!(emu.scheduler.due_time(EventKind::TimerOverflow7), None);
+    }
+}
@@ -3,26 +3,53 @@ use crate::emulator::Emulator;
 
 impl Emulator {
     pub fn write_word(&mut self, address: u32, word: u32, cpu_type: CpuType) {
-        if self.get_cpu(cpu_type).cpu_id <= 0 {
-            self.arm9_cp15.write_word(address, word)
-        } else {
-            self.arm7_write_word(address, word)
+        if self.page_write(cpu_type, address, word.to_le_bytes()) {
+            #[cfg(feature = "block_cache")]
+            self.code_written(cpu_type, address);
+            return;
+        }
+        self.record_io_access(cpu_type, address, true);
+        match cpu_type {
+            CpuType::Arm9 => {
+                if !self.arm9_cp15.write_word(address, word) {
+                    self.arm9_write_word(address, word)
+                }
+            }
+            CpuType::Arm7 => self.arm7_write_word(address, word),
         }
     }
 
     pub fn write_halfword(&mut self, address: u32, halfword: u16, cpu_type: CpuType) {
-        if self.get_cpu(cpu_type).cpu_id <= 0 {
-            self.arm9_cp15.write_halfword(address, halfword)
-        } else {
-            self.arm7_write_halfword(address, halfword)
+        if self.page_write(cpu_type, address, halfword.to_le_bytes()) {
+            #[cfg(feature = "block_cache")]
+            self.code_written(cpu_type, address);
+            return;
+        }
+        self.record_io_access(cpu_type, address, true);
+        match cpu_type {
+            CpuType::Arm9 => {
+                if !self.arm9_cp15.write_halfword(address, halfword) {
+                    self.arm9_write_halfword(address, halfword)
+                }
+            }
+            CpuType::Arm7 => self.arm7_write_halfword(address, halfword),
         }
     }
 
     pub fn write_byte(&mut self, address: u32, byte: u8, cpu_type: CpuType) {
-        if self.get_cpu(cpu_type).cpu_id <= 0 {
-            self.arm9_cp15.write_byte(address, byte as u32)
-        } else {
-            self.arm7_write_byte(address, byte)
+        if self.page_write(cpu_type, address, [byte]) {
+            #[cfg(feature = "block_cache")]
+            self.code_written(cpu_type, address);
+            return;
+        }
+        self.record_io_access(cpu_type, address, true);
+        match cpu_type {
+            CpuType::Arm9 => {
+                if !self.arm9_cp15.write_byte(address, byte) {
+                    self.arm9_write_byte(address, byte)
+                }
+            }
+            CpuType::Arm7 => self.arm7_write_byte(address, byte),
         }
     }
 }
@@ -1,7 +1,7 @@
 // SPDX-FileCopyrightText: (C) 2017 PSISP
 // SPDX-License-Identifier: GPL-3.0-or-later
 use super::Emulator;
-use crate::interrupts::Interrupt;
+use crate::cpu::arm_cpu::CpuType;
 use lunaris_ds_mem_const::*;
 
 impl Emulator {
@@ -28,7 +28,7 @@ impl Emulator {
                     _ => return,
                 } as usize;
 
-                self.arm7_wram[off..off + 4].copy_from_slice(&word.to_le_bytes())
+                self.arm7_shared_wram_mut()[off..off + 4].copy_from_slice(&word.to_le_bytes())
             }
 
             // ARM7 WRAM
@@ -37,63 +37,32 @@ impl Emulator {
                 self.arm7_wram[off..off + 4].copy_from_slice(&word.to_le_bytes())
             }
 
-            // Direct IO write (word)
-            0x040000B0 => self.dma.write_source(4, word),
-            0x040000B4 => self.dma.write_dest(4, word),
-            0x040000B8 => self.dma_write_len_cnt(4, word),
-
-            0x040000BC => self.dma.write_source(5, word),
-            0x040000C0 => self.dma.write_dest(5, word),
-            0x040000C4 => self.dma_write_len_cnt(5, word),
-
-            0x040000C8 => self.dma.write_source(6, word),
-            0x040000CC => self.dma.write_dest(6, word),
-            0x040000D0 => self.dma_write_len_cnt(6, word),
+            // GBA direct sound FIFO A/B
+            0x040000A0 | 0x040000A4 if self.gba_mode => {
+                self.direct_sound.fifos[((address >> 2) & 1) as usize].write_word(word)
+            }
 
-            0x040000D4 => self.dma.write_source(7, word),
-            0x040000D8 => self.dma.write_dest(7, word),
-            0x040000DC => self.dma_write_len_cnt(7, word),
+            // DMA registers
+            0x040000B0..0x040000E0 => self.dma_write_word(CpuType::Arm7, address, word),
 
             // Timers: LO/HIGH packed in one word
-            0x04000100 => {
-                self.nds_timing.write_lo((word & 0xFFFF) as u16, 0);
-                self.nds_timing.write_hi((word >> 16) as u16, 0)
-            }
-            0x04000104 => {
-                self.nds_timing.write_lo((word & 0xFFFF) as u16, 1);
-                self.nds_timing.write_hi((word >> 16) as u16, 1)
-            }
-            0x04000108 => {
-                self.nds_timing.write_lo((word & 0xFFFF) as u16, 2);
-                self.nds_timing.write_hi((word >> 16) as u16, 2)
-            }
-            0x0400010C => {
-                self.nds_timing.write_lo((word & 0xFFFF) as u16, 3);
-                self.nds_timing.write_hi((word >> 16) as u16, 3)
+            0x04000100..0x04000110 => {
+                self.timer_write_halfword(CpuType::Arm7, address, word as u16);
+                self.timer_write_halfword(CpuType::Arm7, address + 2, (word >> 16) as u16);
             }
 
             0x04000120 => {} // SIODATA32 ignored
             0x04000128 => {} // write ignored
 
-            0x04000180 => {
-                self.ipc_sync_nds7.write(word as u16);
-                self.ipc_sync_nds9.receive_input(word as u16);
+            0x04000180 => self.ipc_sync_write(CpuType::Arm7, word as u16),
+            0x04000184 => self.ipc_fifo_write_cnt(CpuType::Arm7, word as u16),
+            0x04000188 => self.ipc_fifo_send(CpuType::Arm7, word),
 
-                // IPCSYNC interrupt
-                if (word & (1 << 13)) != 0 && self.ipc_sync_nds9.irq_enable {
-                    self.request_interrupt9(Interrupt::IpcSync)
-                }
+            0x040001A4 => {
+                self.cart.set_romctrl(word);
+                self.trace_cart_command();
+                self.cartridge_schedule();
             }
-
-            0x04000188 => {
-                self.fifo9.write_queue(word);
-                if self.fifo9.request_nempty_irq {
-                    self.request_interrupt9(Interrupt::IpcFifoNempty);
-                    self.fifo9.request_nempty_irq = false;
-                }
-            }
-
-            0x040001A4 => self.cart.set_romctrl(word),
             0x040001B0 => self.cart.set_lo_key2_seed0(word),
             0x040001B4 => self.cart.set_lo_key2_seed1(word),
 
@@ -105,15 +74,30 @@ impl Emulator {
             0x04000308 => self.bios_prot = word & !1,
 
             0x04000500 => self.spu.set_soundcnt((word & 0xFFFF) as u16),
-            0x04000510 => {}
-            0x04000518 => {}
+            0x04000510 => self.spu.set_capture_destination(0, word),
+            0x04000514 => self.spu.set_capture_len(0, word as u16),
+            0x04000518 => self.spu.set_capture_destination(1, word),
+            0x0400051C => self.spu.set_capture_len(1, word as u16),
 
             // SPU channel write region
             0x04000400..0x04000500 => self.spu.write_channel_word(address, word),
 
+            // WiFi registers are 16-bit wide
+            0x04804000..0x04806000 | 0x04814000..0x04816000 => {
+                self.wifi.write_ram(address, word as u16);
+                self.wifi.write_ram(address + 2, (word >> 16) as u16);
+            }
+            0x04808000..0x04809000 | 0x04818000..0x04819000 => {
+                self.wifi_write_reg(address, word as u16);
+                self.wifi_write_reg(address + 2, (word >> 16) as u16);
+            }
+
             // GPU VRAM write (ARM7)
             0x06000000..0x07000000 => self.gpu.write_arm7_u32(address, word),
 
+            // GBA slot
+            GBA_ROM_START..GBA_RAM_END => self.slot2_write_word(CpuType::Arm7, address, word),
+
             // Default
             _ => {
                 #[cfg(feature = "tracing")]
@@ -144,7 +128,7 @@ impl Emulator {
                     _ => return,
                 } as usize;
 
-                self.arm7_wram[off..off + 2].copy_from_slice(&halfword.to_le_bytes())
+                self.arm7_shared_wram_mut()[off..off + 2].copy_from_slice(&halfword.to_le_bytes())
             }
 
             // ARM7 WRAM
@@ -156,75 +140,42 @@ impl Emulator {
             // IO register halfword writes
             0x04000004 => self.gpu.set_dispstat7(halfword),
 
-            0x040000BA => self.dma_write_cnt(4, halfword),
-            0x040000C6 => self.dma_write_cnt(5, halfword),
-            0x040000D2 => self.dma_write_cnt(6, halfword),
-            0x040000DE => self.dma_write_cnt(7, halfword),
+            // GBA SOUNDCNT_H
+            0x04000082 if self.gba_mode => self.direct_sound.set_cnt(halfword),
 
-            0x04000100 => self.nds_timing.write_lo(halfword, 0),
-            0x04000102 => self.nds_timing.write_hi(halfword, 0),
-            0x04000104 => self.nds_timing.write_lo(halfword, 1),
-            0x04000106 => self.nds_timing.write_hi(halfword, 1),
-            0x04000108 => self.nds_timing.write_lo(halfword, 2),
-            0x0400010A => self.nds_timing.write_hi(halfword, 2),
-            0x0400010C => self.nds_timing.write_lo(halfword, 3),
-            0x0400010E => self.nds_timing.write_hi(halfword, 3),
+            0x040000B0..0x040000E0 => self.dma_write_halfword(CpuType::Arm7, address, halfword),
+
+            0x04000100..0x04000110 => self.timer_write_halfword(CpuType::Arm7, address, halfword),
 
             0x04000128 => self.sio_cnt = halfword,
             0x04000134 => self.r_cnt = halfword,
 
             0x04000138 => self.rtc.write(halfword, false),
 
-            0x04000180 => {
-                self.ipc_sync_nds7.write(halfword);
-                self.ipc_sync_nds9.receive_input(halfword);
-
-                // Trigger IPCSYNC interrupt if enabled
-                if (halfword & (1 << 13)) != 0 && self.ipc_sync_nds9.irq_enable {
-                    self.request_interrupt9(Interrupt::IpcSync)
-                }
-            }
-
-            0x04000184 => {
-                self.fifo7.write_cnt(halfword);
-
-                // FIFO empty IRQ
-                if self.fifo7.request_empty_irq {
-                    self.request_interrupt7(Interrupt::IpcFifoEmpty);
-                    self.fifo7.request_empty_irq = false;
-                }
-
-                // FIFO non-empty IRQ
-                if self.fifo7.request_nempty_irq {
-                    self.request_interrupt7(Interrupt::IpcFifoNempty);
-                    self.fifo7.request_nempty_irq = false;
-                }
-            }
+            0x04000180 => self.ipc_sync_write(CpuType::Arm7, halfword),
+            0x04000184 => self.ipc_fifo_write_cnt(CpuType::Arm7, halfword),
 
             0x040001A0 => self.cart.set_auxspicnt(halfword),
 
             0x040001A2 => {
                 #[cfg(feature = "tracing")]
                 tracing::info!("AUXSPIDATA: {:04X}", halfword);
-                self.cart.set_auxspidata((halfword & 0xFF) as u8)
+                self.cart.set_auxspidata((halfword & 0xFF) as u8);
+                self.trace_cart_command();
             }
 
             0x040001B8 => self.cart.set_hi_key2_seed0(halfword.into()),
             0x040001BA => self.cart.set_hi_key2_seed1(halfword.into()),
 
             0x040001C0 => self.spi.set_spicnt(halfword),
-            0x040001C2 => {
-                if self.spi.write_spidata((halfword & 0xFF) as u8) {
-                    self.requesting_interrupt(7);
-                }
-            }
+            0x040001C2 => self.write_spidata((halfword & 0xFF) as u8),
 
             0x04000206 => {} // WIFIWAITCNT TODO
 
             0x04000208 => self.int7_reg.ime = (halfword & 0x1) as u32,
 
             0x04000300 => self.postflg7 = (halfword & 1) as u8,
-            0x04000304 => self.pow_cnt2.set_value(halfword),
+            0x04000304 => self.pow_cnt2.set(halfword),
 
             0x04000500 => self.spu.set_soundcnt(halfword),
             0x04000504 => self.spu.set_soundbias(halfword),
@@ -234,19 +185,19 @@ impl Emulator {
                 self.spu.set_sndcap1((halfword >> 8) as u8)
             }
 
-            0x04000514 => {}
-            0x0400051C => {}
+            0x04000514 => self.spu.set_capture_len(0, halfword),
+            0x0400051C => self.spu.set_capture_len(1, halfword),
 
             // Debug port (DS Lite firmware)
             0x04001080 => {}
 
-            // WiFi registers
-            0x04808036 => self.wifi.set_w_power_us(halfword),
-            0x04808158 => self.wifi.set_w_bb_cnt(halfword),
-            0x0480815A => self.wifi.set_w_bb_write(halfword),
-            0x04808160 => self.wifi.set_w_bb_mode(halfword),
-            0x04808168 => self.wifi.set_w_bb_power(halfword),
-            0x04808184 => self.wifi.set_w_rf_cnt(halfword),
+            // WiFi RAM and registers
+            0x04804000..0x04806000 | 0x04814000..0x04816000 => {
+                self.wifi.write_ram(address, halfword)
+            }
+            0x04808000..0x04809000 | 0x04818000..0x04819000 => {
+                self.wifi_write_reg(address, halfword)
+            }
 
             // SPU channel writes
             0x04000400..0x04000500 => self.spu.write_channel_halfword(address, halfword),
@@ -254,6 +205,11 @@ impl Emulator {
             // WiFi block (ignored)
             0x04800000..0x04900000 => {}
 
+            // GBA slot
+            GBA_ROM_START..GBA_RAM_END => {
+                self.slot2_write_halfword(CpuType::Arm7, address, halfword);
+            }
+
             // Default
             _ => {
                 #[cfg(feature = "tracing")]
@@ -288,12 +244,14 @@ impl Emulator {
                     _ => return,
                 } as usize;
 
-                self.shared_wram[off] = byte
+                self.arm7_shared_wram_mut()[off] = byte
             }
 
             // IO register byte writes
             0x04000138 => self.rtc.write(byte as u16, true),
 
+            0x04000181 => self.ipc_sync_write(CpuType::Arm7, (byte as u16) << 8),
+
             0x040001A1 => self.cart.set_hi_auxspicnt(byte),
 
             // Cart command sequence
@@ -301,11 +259,7 @@ impl Emulator {
                 .cart
                 .receive_command(byte, (address - 0x040001A8) as usize),
 
-            0x040001C2 => {
-                if self.spi.write_spidata(byte) {
-                    self.requesting_interrupt(7);
-                }
-            }
+            0x040001C2 => self.write_spidata(byte),
 
             0x04000208 => self.int7_reg.ime = (byte & 0x1) as u32,
 
@@ -317,6 +271,11 @@ impl Emulator {
                         // ARM7 halt request
                         self.arm7.halt()
                     }
+                    0x40 => {
+                        // Switch to GBA mode, which maps the direct sound
+                        // hardware
+                        self.gba_mode = true;
+                    }
                     _ => {
                         #[cfg(feature = "tracing")]
                         tracing::error!("Unrecognized HALTCNT value {:02X} for ARM7", byte);
@@ -342,6 +301,9 @@ impl Emulator {
             // Ignore BIOS writes
             0x00000000..0x00004000 => {}
 
+            // GBA slot
+            GBA_ROM_START..GBA_RAM_END => self.slot2_write_byte(CpuType::Arm7, address, byte),
+
             // Default
             _ => {
                 #[cfg(feature = "tracing")]
@@ -1,4 +1,4 @@
-use crate::interrupts::Interrupt;
+use crate::cpu::arm_cpu::CpuType;
 
 // SPDX-FileCopyrightText: (C) 2017 PSISP
 // SPDX-License-Identifier: GPL-3.0-or-later
@@ -15,6 +15,9 @@ impl Emulator {
     /// - DMA registers
     /// - IPC, FIFO
     /// - Cartridge AUX SPI registers
+    ///
+    /// The TCMs are not on the bus: DMA writes land behind them, CPU stores
+    /// check them first in [`Emulator::write_word`].
     pub fn arm9_write_word(&mut self, address: u32, word: u32) {
         // GPU / DMA / IPC / cartridge / I/O registers
         match address {
@@ -44,31 +47,31 @@ impl Emulator {
                 self.gpu.set_bgcnt_a((word & 0xFFFF) as u16, 2);
                 self.gpu.set_bgcnt_a((word >> 16) as u16, 3);
             }
+            0x0400_0064 => self.gpu.set_dispcapcnt(word),
+            0x0400_0068 => self.gpu.write_disp_mmem_fifo(word),
             0x0400_000E..=0x0400_0070 => { /* GPU other registers, implement similarly */ }
-            0x0400_00B0..=0x0400_00DC => { /* DMA registers, call self.dma.write_* */ }
+            0x0400_00B0..0x0400_00E0 => self.dma_write_word(CpuType::Arm9, address, word),
             0x0400_00E0..=0x0400_00EC => {
                 let i = ((address - 0x0400_00E0) / 4) as usize;
                 self.dma_fill[i] = word;
             }
-            0x0400_0180 => {
-                self.ipc_sync_nds9.write(word as u16);
-                self.ipc_sync_nds7.receive_input(word as u16);
-                if word & (1 << 13) != 0 && self.ipc_sync_nds7.irq_enable {
-                    self.request_interrupt7(Interrupt::IpcSync);
-                }
-            }
-            0x0400_0188 => {
-                self.fifo7.write_queue(word);
-                if self.fifo7.request_nempty_irq {
-                    self.request_interrupt7(Interrupt::IpcFifoNempty);
-                    self.fifo7.request_nempty_irq = false;
-                }
+            0x0400_0100..0x0400_0110 => {
+                self.timer_write_halfword(CpuType::Arm9, address, word as u16);
+                self.timer_write_halfword(CpuType::Arm9, address + 2, (word >> 16) as u16);
             }
+            0x0400_0180 => self.ipc_sync_write(CpuType::Arm9, word as u16),
+            0x0400_0184 => self.ipc_fifo_write_cnt(CpuType::Arm9, word as u16),
+            0x0400_0188 => self.ipc_fifo_send(CpuType::Arm9, word),
             0x0400_01A0 => {
                 self.cart.set_auxspicnt((word & 0xFFFF) as u16);
                 self.cart.set_auxspidata(((word >> 16) & 0xFF) as u8);
+                self.trace_cart_command();
+            }
+            0x0400_01A4 => {
+                self.cart.set_romctrl(word);
+                self.trace_cart_command();
+                self.cartridge_schedule();
             }
-            0x0400_01A4 => self.cart.set_romctrl(word),
             0x0400_01A8 => {
                 self.cart.receive_command((word >> 24) as u8, 3);
                 self.cart.receive_command(((word >> 16) & 0xFF) as u8, 2);
@@ -81,6 +84,8 @@ impl Emulator {
                 self.cart.receive_command(((word >> 8) & 0xFF) as u8, 5);
                 self.cart.receive_command((word & 0xFF) as u8, 4);
             }
+            0x0400_01B0 => self.cart.set_lo_key2_seed0(word),
+            0x0400_01B4 => self.cart.set_lo_key2_seed1(word),
             0x0400_0208 => self.int9_reg.ime = word & 0x1,
             0x0400_0210 => self.int9_reg.irq_enable = word,
             0x0400_0214 => {
@@ -98,6 +103,7 @@ impl Emulator {
             0x0400_0304 => self.gpu.set_powcnt1((word & 0xFFFF) as u16),
             0x0400_0350 => self.gpu.set_clear_color(word),
             0x0400_0600 => self.set_gxstat(word), // self.gpu.set_gxstat(word) for Gpu3D
+            0x0400_0610 => self.gpu.set_dot_depth((word & 0xFFFF) as u16),
             0x0400_1000 => self.gpu.set_dispcnt_b(word),
             0x0400_0330..0x0400_0340 | 0x0400_0360..0x0400_0380 => {} // FOG_TABLE / EDGE_COLOR (TODO)
             0x0400_0380..0x0400_03C0 => {
@@ -110,11 +116,15 @@ impl Emulator {
             0x0400_0440..0x0400_05CC => self.write_fifo_direct(address, word),
             PALETTE_START..VRAM_BGA_START => {
                 if (address & 0x7FF) < 0x400 {
-                    self.gpu.write_palette_a(address, (word & 0xFFFF) as u16);
-                    self.gpu.write_palette_a(address + 2, (word >> 16) as u16);
+                    self.gpu
+                        .write_palette_a(address & 0x3FF, (word & 0xFFFF) as u16);
+                    self.gpu
+                        .write_palette_a((address & 0x3FF) + 2, (word >> 16) as u16);
                 } else {
-                    self.gpu.write_palette_b(address, (word & 0xFFFF) as u16);
-                    self.gpu.write_palette_b(address + 2, (word >> 16) as u16);
+                    self.gpu
+                        .write_palette_b(address & 0x3FF, (word & 0xFFFF) as u16);
+                    self.gpu
+                        .write_palette_b((address & 0x3FF) + 2, (word >> 16) as u16);
                 }
             }
             VRAM_BGA_START..VRAM_BGB_START => {
@@ -134,11 +144,16 @@ impl Emulator {
                 self.gpu.write_objb(address, (word & 0xFFFF) as u16);
                 self.gpu.write_objb(address + 2, (word >> 16) as u16);
             }
+            VRAM_LCDC_A..OAM_START => {
+                self.gpu.write_lcdc(address, (word & 0xFFFF) as u16);
+                self.gpu.write_lcdc(address + 2, (word >> 16) as u16);
+            }
 
             OAM_START..GBA_ROM_START => {
                 self.gpu.write_oam(address, (word & 0xFFFF) as u16);
                 self.gpu.write_oam(address + 2, (word >> 16) as u16);
             }
+            GBA_ROM_START..GBA_RAM_END => self.slot2_write_word(CpuType::Arm9, address, word),
             _ => {
                 #[cfg(feature = "tracing")]
                 tracing::warn!("(9) Unrecognized word write of ${word:08X} to ${address:08X}");
@@ -169,8 +184,8 @@ impl Emulator {
 
             // Palette memory
             PALETTE_START..VRAM_BGA_START => match address & 0x7FF {
-                ..0x400 => self.gpu.write_palette_a(address, halfword),
-                _ => self.gpu.write_palette_b(address, halfword),
+                ..0x400 => self.gpu.write_palette_a(address & 0x3FF, halfword),
+                _ => self.gpu.write_palette_b(address & 0x3FF, halfword),
             },
 
             // LCDC VRAM
@@ -221,7 +236,8 @@ impl Emulator {
                     _ => {} // Undefined memory, do nothing
                 }
             }
-            0x04000000 => self.gpu.set_dispcnt_a(halfword as u32),
+            0x04000000 => self.gpu.set_dispcnt_a_lo(halfword),
+            0x04000002 => self.gpu.set_dispcnt_a_hi(halfword),
             0x04000004 => self.gpu.set_dispstat9(halfword),
             0x04000008 => self.gpu.set_bgcnt_a(halfword, 0),
             0x0400000A => self.gpu.set_bgcnt_a(halfword, 1),
@@ -255,39 +271,17 @@ impl Emulator {
             0x04000054 => self.gpu.set_bldy_a(halfword as u8),
             0x04000060 => self.gpu.set_disp3dcnt(halfword),
             0x0400006C => self.gpu.set_master_bright_a(halfword),
-            0x040000BA => self.dma_write_cnt(0, halfword), // DMA
-            0x040000C6 => self.dma_write_cnt(1, halfword), // DMA
-            0x040000D0 => self.dma.write_len(2, halfword),
-            0x040000D2 => self.dma_write_cnt(2, halfword), // DMA
-            0x040000DE => self.dma_write_cnt(3, halfword), // DMA
-            0x04000100 => self.nds_timing.write_lo(halfword, 4),
-            0x04000102 => self.nds_timing.write_hi(halfword, 4),
-            0x04000104 => self.nds_timing.write_lo(halfword, 5),
-            0x04000106 => self.nds_timing.write_hi(halfword, 5),
-            0x04000108 => self.nds_timing.write_lo(halfword, 6),
-            0x0400010A => self.nds_timing.write_hi(halfword, 6),
-            0x0400010C => self.nds_timing.write_lo(halfword, 7),
-            0x0400010E => self.nds_timing.write_hi(halfword, 7),
-            0x04000180 => {
-                self.ipc_sync_nds9.write(halfword);
-                self.ipc_sync_nds7.receive_input(halfword);
-                if (halfword & (1 << 13) != 0) && self.ipc_sync_nds7.irq_enable {
-                    self.request_interrupt7(Interrupt::IpcSync);
-                }
-            }
-            0x04000184 => {
-                self.fifo9.write_cnt(halfword);
-                if self.fifo9.request_empty_irq {
-                    self.request_interrupt9(Interrupt::IpcFifoEmpty);
-                    self.fifo9.request_empty_irq = false;
-                }
-                if self.fifo9.request_nempty_irq {
-                    self.request_interrupt9(Interrupt::IpcFifoNempty);
-                    self.fifo9.request_nempty_irq = false;
-                }
-            }
+            0x040000B0..0x040000E0 => self.dma_write_halfword(CpuType::Arm9, address, halfword),
+            0x04000100..0x04000110 => self.timer_write_halfword(CpuType::Arm9, address, halfword),
+            0x04000180 => self.ipc_sync_write(CpuType::Arm9, halfword),
+            0x04000184 => self.ipc_fifo_write_cnt(CpuType::Arm9, halfword),
             0x040001A0 => self.cart.set_auxspicnt(halfword),
-            0x04000204 => self.ex_mem_cnt = halfword,
+            0x040001B8 => self.cart.set_hi_key2_seed0(halfword.into()),
+            0x040001BA => self.cart.set_hi_key2_seed1(halfword.into()),
+            0x04000204 => {
+                self.ex_mem_cnt = halfword;
+                self.apply_slot2_waitstates();
+            }
             0x04000208 => self.int9_reg.ime = (halfword & 0x1) as u32,
             0x04000248 => {
                 self.gpu.set_vramcnt_h(halfword as u8);
@@ -305,9 +299,16 @@ impl Emulator {
             0x04000304 => self.gpu.set_powcnt1(halfword),
             0x04000340 => {} // TODO: ALPHA_TEST_REF
             0x04000354 => self.gpu.set_clear_depth(halfword as u32),
+            0x04000600 => {
+                let irq_stat = self.gpu.get_gxstat() & 0xC000_0000;
+                self.set_gxstat(halfword as u32 | irq_stat);
+            }
+            0x04000602 => self.set_gxstat((halfword as u32) << 16),
+            0x04000610 => self.gpu.set_dot_depth(halfword),
             0x04000356 => {} // TODO: CLRIMAGE_OFFSET
             0x0400035C => {} // TODO: FOG_OFFSET
-            0x04001000 => self.gpu.set_dispcnt_b(halfword as u32),
+            0x04001000 => self.gpu.set_dispcnt_b_lo(halfword),
+            0x04001002 => self.gpu.set_dispcnt_b_hi(halfword),
             0x04001008 => self.gpu.set_bgcnt_b(halfword, 0),
             0x0400100A => self.gpu.set_bgcnt_b(halfword, 1),
             0x0400100C => self.gpu.set_bgcnt_b(halfword, 2),
@@ -344,7 +345,10 @@ impl Emulator {
                 // TOON table
                 self.gpu.set_toon_table((address & 0x3F) >> 1, halfword);
             }
-            GBA_ROM_START.. => {} // GBA ROM
+            GBA_ROM_START..GBA_RAM_END => {
+                self.slot2_write_halfword(CpuType::Arm9, address, halfword);
+            }
+            GBA_RAM_END.. => {}
             _ => {
                 #[cfg(feature = "tracing")]
                 tracing::warn!(
@@ -376,17 +380,21 @@ impl Emulator {
                 self.main_ram[idx] = byte;
             }
             0x0400004C => self.gpu.set_mosaic_a(byte as u16),
+            0x04000054 => self.gpu.set_bldy_a(byte),
+            0x04000181 => self.ipc_sync_write(CpuType::Arm9, (byte as u16) << 8),
             0x040001A1 => self.cart.set_hi_auxspicnt(byte),
             0x040001A2 => {
                 #[cfg(feature = "tracing")]
                 tracing::debug!("AUXSPIDATA: {byte:02X}");
                 self.cart.set_auxspidata(byte);
+                self.trace_cart_command();
             }
             0x040001A8..=0x040001AF => {
                 self.cart
                     .receive_command(byte, (address - 0x040001A8) as usize);
             }
             0x04000208 => self.int9_reg.ime = (byte & 0x1) as u32,
+            0x04000603 => self.set_gxstat((byte as u32) << 24),
             0x04000240 => self.gpu.set_vramcnt_a(byte),
             0x04000241 => self.gpu.set_vramcnt_b(byte),
             0x04000242 => self.gpu.set_vramcnt_c(byte),
@@ -394,7 +402,10 @@ impl Emulator {
             0x04000244 => self.gpu.set_vramcnt_e(byte),
             0x04000245 => self.gpu.set_vramcnt_f(byte),
             0x04000246 => self.gpu.set_vramcnt_g(byte),
-            0x04000247 => self.wram_cnt = byte & 0x3,
+            0x04000247 => {
+                self.wram_cnt = byte & 0x3;
+                self.rebuild_page_tables();
+            }
             0x04000248 => self.gpu.set_vramcnt_h(byte),
             0x04000249 => self.gpu.set_vramcnt_i(byte),
             0x0400104C => self.gpu.set_mosaic_b(byte as u16),
@@ -411,6 +422,7 @@ impl Emulator {
                 #[cfg(feature = "tracing")]
                 tracing::warn!("\nWarning: 8-bit write to VRAM ${address:08X}");
             }
+            GBA_ROM_START..GBA_RAM_END => self.slot2_write_byte(CpuType::Arm9, address, byte),
             _ => {
                 // Unrecognized byte write
                 #[cfg(feature = "tracing")]
@@ -419,3 +431,28 @@ impl Emulator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_bus_access() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+
+        // Palette A at 0x05000000, B at 0x05000400, mirrored every 2K
+        emu.arm9_write_word(0x0500_0000, 0x7FFF_001F);
+        emu.arm9_write_halfword(0x0500_0402, 0x03E0);
+        emu.arm9_write_halfword(0x0500_0FFE, 0x7C00);
+        assert_eq!(emu.gpu.read_palette_a(0), 0x001F);
+        assert_eq!(emu.gpu.read_palette_a(2), 0x7FFF);
+        assert_eq!(emu.gpu.read_palette_b(2), 0x03E0);
+        assert_eq!(emu.gpu.read_palette_b(0x3FE), 0x7C00);
+
+        assert_eq!(emu.arm9_read_word(0x0500_0800), 0x7FFF_001F);
+        assert_eq!(emu.arm9_read_halfword(0x0500_0402), 0x03E0);
+        assert_eq!(emu.arm9_read_byte(0x0500_0403), 0x03);
+        assert_eq!(emu.arm9_read_byte(0x0500_0001), 0x00);
+    }
+}
@@ -0,0 +1,178 @@
+//! Write-combining for block stores to VRAM.
+//!
+//! STM and DMA know the whole run of a transfer up front. When the run lands
+//! in a single VRAM bank mapped to LCDC (where textures and decompressed
+//! graphics are usually uploaded), it is copied as one slice instead of going
+//! through the bank lookup once per halfword.
+use lunaris_ds_mem_const::{
+    MAIN_RAM_MASK, MAIN_RAM_START, OAM_START, SHARED_WRAM_START, VRAM_LCDC_A,
+};
+
+use crate::cpu::arm_cpu::CpuType;
+use crate::emulator::Emulator;
+
+/// Shorter runs are not worth combining.
+const MIN_COMBINE_LEN: u32 = 8;
+
+/// Whether a `len` byte store at `address` may be combined.
+const fn is_combinable(address: u32, len: u32) -> bool {
+    len >= MIN_COMBINE_LEN
+        && address >= VRAM_LCDC_A
+        && address.wrapping_add(len) > address
+        && address + len <= OAM_START
+}
+
+impl Emulator {
+    /// Store `words` at consecutive ARM9 bus addresses from `address`.
+    pub fn arm9_write_words(&mut self, address: u32, words: &[u32]) {
+        let len = (words.len() * 4) as u32;
+        if is_combinable(address, len) {
+            let bytes: Vec<u8> = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+            if self.gpu.write_lcdc_block(address, &bytes) {
+                return;
+            }
+        }
+
+        for (i, &word) in words.iter().enumerate() {
+            self.arm9_write_word(address.wrapping_add(i as u32 * 4), word);
+        }
+    }
+
+    /// Store `halfwords` at consecutive ARM9 bus addresses from `address`.
+    pub fn arm9_write_halfwords(&mut self, address: u32, halfwords: &[u16]) {
+        let len = (halfwords.len() * 2) as u32;
+        if is_combinable(address, len) {
+            let bytes: Vec<u8> = halfwords
+                .iter()
+                .flat_map(|half| half.to_le_bytes())
+                .collect();
+            if self.gpu.write_lcdc_block(address, &bytes) {
+                return;
+            }
+        }
+
+        for (i, &halfword) in halfwords.iter().enumerate() {
+            self.arm9_write_halfword(address.wrapping_add(i as u32 * 2), halfword);
+        }
+    }
+
+    /// Store the words of an STM, lowest address first.
+    ///
+    /// TCM takes priority over the bus, so runs touching it are never
+    /// combined.
+    pub(crate) fn write_words(&mut self, address: u32, words: &[u32], cpu_type: CpuType) {
+        let len = (words.len() * 4) as u32;
+        if cpu_type == CpuType::Arm9
+            && is_combinable(address, len)
+            && !self.arm9_cp15.overlaps_tcm(address, len)
+        {
+            self.arm9_write_words(address, words);
+            return;
+        }
+
+        for (i, &word) in words.iter().enumerate() {
+            self.write_word(address.wrapping_add(i as u32 * 4), word, cpu_type);
+        }
+    }
+
+    /// Copy `len` bytes from main RAM to LCDC VRAM for a DMA, if possible.
+    ///
+    /// Returns `false` without transferring anything when either side is not
+    /// a plain contiguous range.
+    pub(crate) fn dma_copy_to_vram(&mut self, source: u32, dest: u32, len: u32) -> bool {
+        if !is_combinable(dest, len) || !(MAIN_RAM_START..SHARED_WRAM_START).contains(&source) {
+            return false;
+        }
+        let start = (source & MAIN_RAM_MASK) as usize;
+        let Some(data) = self.main_ram.get(start..start + len as usize) else {
+            return false;
+        };
+        self.gpu.write_lcdc_block(dest, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::arm_cpu::Reg;
+
+    #[test]
+    fn test_combined_matches_per_word() {
+        let words: Vec<u32> = (0..64).map(|i| 0x0101_0101 * i).collect();
+
+        let mut combined = Box::new(Emulator::new());
+        let mut reference = Box::new(Emulator::new());
+        for emu in [&mut combined, &mut reference] {
+            // Bank A to LCDC
+            emu.arm9_write_byte(0x0400_0240, 0x80);
+        }
+
+        combined.arm9_write_words(VRAM_LCDC_A + 0x100, &words);
+        for (i, &word) in words.iter().enumerate() {
+            reference.arm9_write_word(VRAM_LCDC_A + 0x100 + i as u32 * 4, word);
+        }
+
+        for offset in (0..0x200).step_by(4) {
+            let address = VRAM_LCDC_A + offset;
+            assert_eq!(
+                combined.arm9_read_word(address),
+                reference.arm9_read_word(address)
+            );
+        }
+        assert_eq!(combined.arm9_read_word(VRAM_LCDC_A + 0x104), 0x0101_0101);
+    }
+
+    #[test]
+    fn test_stm_to_vram() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        emu.arm9_write_byte(0x0400_0240, 0x80);
+        // stmia r0!, {r1-r12}; stmdb r0, {r1-r4}
+        emu.arm9_write_word(0x0200_1000, 0xE8A0_1FFE);
+        emu.arm9_write_word(0x0200_1004, 0xE900_001E);
+        emu.arm9.set_register(Reg::new(0), VRAM_LCDC_A + 0x100);
+        for i in 1..13 {
+            emu.arm9.set_register(Reg::new(i), 0x0101_0101 * i);
+        }
+        emu.arm9.jp(0x0200_1000, false);
+
+        emu.execute(CpuType::Arm9);
+        assert_eq!(emu.arm9.get_register(Reg::new(0)), VRAM_LCDC_A + 0x130);
+        emu.execute(CpuType::Arm9);
+        let stored: Vec<u32> = (0..13)
+            .map(|i| emu.arm9_read_word(VRAM_LCDC_A + 0x100 + i * 4))
+            .collect();
+        let mut expected: Vec<u32> = (1..9).map(|i| 0x0101_0101 * i).collect();
+        expected.extend((1..5).map(|i| 0x0101_0101 * i));
+        expected.push(0);
+        assert_eq!(stored, expected);
+    }
+
+    fn start_dma3(emu: &mut Emulator, source: u32, dest: u32, words: u16) {
+        emu.dma.dmas[3].source = source;
+        emu.dma.dmas[3].destination = dest;
+        emu.dma.write_len(3, words.into());
+        // Enable, 32-bit, immediate
+        emu.dma_write_cnt(3, 0x8400);
+        emu.dma_handle_event();
+    }
+
+    #[test]
+    fn test_dma_length() {
+        let mut emu = Box::new(Emulator::new());
+        emu.dma.power_on();
+        for i in 0..8 {
+            emu.arm9_write_word(0x0200_0000 + i * 4, i + 1);
+        }
+
+        // RAM to VRAM (one block) and RAM to RAM (unit by unit)
+        emu.arm9_write_byte(0x0400_0240, 0x80);
+        start_dma3(&mut emu, 0x0200_0000, VRAM_LCDC_A, 4);
+        start_dma3(&mut emu, 0x0200_0000, 0x0210_0000, 4);
+
+        for (base, label) in [(VRAM_LCDC_A, "vram"), (0x0210_0000, "ram")] {
+            let copied: Vec<u32> = (0..5).map(|i| emu.arm9_read_word(base + i * 4)).collect();
+            assert_eq!(copied, [1, 2, 3, 4, 0], "{label}");
+        }
+    }
+}
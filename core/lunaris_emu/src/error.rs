@@ -16,4 +16,51 @@ pub enum EmuError {
         source: std::io::Error,
         path: PathBuf,
     },
+
+    /// Sector access outside the SD card image.
+    #[snafu(display("SD card access out of range: {count} sector(s) at LBA {lba}"))]
+    SdSectorOutOfRange { lba: u64, count: u64 },
+
+    /// No SD card image is mounted.
+    #[snafu(display("No SD card image is mounted"))]
+    SdCardNotMounted,
+
+    /// Failed to open or configure the WiFi bridge socket.
+    #[snafu(display("Failed to open WiFi socket on {address}"))]
+    WifiSocket {
+        source: std::io::Error,
+        address: std::net::SocketAddr,
+    },
+
+    /// Savestate data ended before all state was read.
+    #[snafu(display("Savestate data is truncated"))]
+    SavestateTruncated,
+
+    /// Data is not a savestate or holds out of range values.
+    #[snafu(display("Savestate data is invalid"))]
+    SavestateInvalid,
+
+    /// Savestate written by an incompatible version.
+    #[snafu(display("Unsupported savestate version {version}"))]
+    SavestateVersion { version: u32 },
+
+    /// Not an IPS, UPS or BPS patch.
+    #[snafu(display("Unknown patch format"))]
+    PatchUnknownFormat,
+
+    /// Patch is truncated or corrupt.
+    #[snafu(display("Patch data is invalid"))]
+    PatchInvalid,
+
+    /// Patch was made for another ROM.
+    #[snafu(display("Patch does not match the loaded ROM"))]
+    PatchChecksum,
+
+    /// Firmware birthday override that is not a date.
+    #[snafu(display("Invalid firmware birthday: month {month}, day {day}"))]
+    FirmwareBirthday { month: u8, day: u8 },
+
+    /// Action Replay code that is not pairs of 8-digit hex words.
+    #[snafu(display("Invalid Action Replay code: {code}"))]
+    CheatInvalid { code: String },
 }
@@ -2,9 +2,50 @@
 //! Handles firmware data loading, CRC verification, and SPI data transfer
 use std::{fs::File, io::Read as _};
 
-use crate::error::{EmuError, FailedReadFileSnafu};
+use crate::error::{EmuError, FailedReadFileSnafu, FirmwareBirthdaySnafu};
+use crate::savestate::{Savestate, StateReader, StateWriter};
+use crate::touchscreen::TouchCalibration;
+use lunaris_ds_free_bios::firmware::DSType;
 use snafu::ResultExt as _;
 
+/// Firmware user settings language (bits 0-2 of the flags at USER+0x64)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirmwareLanguage {
+    Japanese = 0,
+    English = 1,
+    French = 2,
+    German = 3,
+    Italian = 4,
+    Spanish = 5,
+    /// iQue only
+    Chinese = 6,
+}
+
+/// Values patched over the firmware user settings presented to games.
+///
+/// `None` keeps whatever the loaded firmware image contains.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FirmwareOverrides {
+    pub language: Option<FirmwareLanguage>,
+    /// Birthday as (month 1-12, day 1-31), February 29 included
+    pub birthday: Option<(u8, u8)>,
+    /// Favorite color (0-15)
+    pub favorite_color: Option<u8>,
+    /// Console type byte at header offset 0x1D
+    pub console_type: Option<DSType>,
+}
+
+/// Whether `month`/`day` is a day of the year, leap day included.
+const fn is_birthday(month: u8, day: u8) -> bool {
+    let days = match month {
+        2 => 29,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => 0,
+    };
+    day >= 1 && day <= days
+}
+
 /// Firmware commands
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum FirmwareCommand {
@@ -115,16 +156,13 @@ impl Firmware {
             }
         }
 
-        // Patch user configuration fields (exact offsets preserved)
-        self.write_u16((self.user_data + 0x58) as usize, 0);
-        self.write_u16((self.user_data + 0x5A) as usize, 0);
-        self.raw_firmware[(self.user_data + 0x5C) as usize] = 0;
-        self.raw_firmware[(self.user_data + 0x5D) as usize] = 0;
-
-        self.write_u16((self.user_data + 0x5E) as usize, 255 << 4);
-        self.write_u16((self.user_data + 0x60) as usize, 191 << 4);
-        self.raw_firmware[(self.user_data + 0x62) as usize] = 255;
-        self.raw_firmware[(self.user_data + 0x63) as usize] = 191;
+        // Give firmware without a usable touchscreen calibration (such as
+        // the free one) the identity calibration the TSC falls back to
+        if !self.touch_calibration().is_valid() {
+            let user = self.user_data as usize;
+            self.raw_firmware[user + 0x58..user + 0x64]
+                .copy_from_slice(&TouchCalibration::default().to_user_settings());
+        }
 
         // Recalculate USER data CRC
         let user_crc =
@@ -165,6 +203,55 @@ impl Firmware {
         Ok(Self::SIZE)
     }
 
+    /// Patch `overrides` into the active user settings block and header.
+    ///
+    /// The user settings CRC is recomputed so the patched block stays valid.
+    /// Does nothing before firmware has been loaded.
+    ///
+    /// # Errors
+    /// If the birthday is not a date; nothing is patched then.
+    pub fn apply_overrides(&mut self, overrides: &FirmwareOverrides) -> Result<(), EmuError> {
+        if let Some((month, day)) = overrides.birthday
+            && !is_birthday(month, day)
+        {
+            return FirmwareBirthdaySnafu { month, day }.fail();
+        }
+        if self.user_data == 0 {
+            return Ok(());
+        }
+        let user = self.user_data as usize;
+
+        if let Some(console_type) = overrides.console_type {
+            self.raw_firmware[0x1D] = console_type.model_spec();
+        }
+        if let Some(color) = overrides.favorite_color {
+            self.raw_firmware[user + 0x02] = color & 0xF;
+        }
+        if let Some((month, day)) = overrides.birthday {
+            self.raw_firmware[user + 0x03] = month;
+            self.raw_firmware[user + 0x04] = day;
+        }
+        if let Some(language) = overrides.language {
+            let flags = self.read_u16(user + 0x64);
+            self.write_u16(user + 0x64, (flags & !0x7) | language as u16);
+        }
+
+        let user_crc = Self::create_crc(&self.raw_firmware[user..], 0x70, 0xFFFF);
+        self.write_u16(user + 0x72, user_crc);
+        Ok(())
+    }
+
+    /// Touchscreen calibration of the active user settings.
+    pub(crate) fn touch_calibration(&self) -> TouchCalibration {
+        match self.user_data {
+            0 => TouchCalibration::default(),
+            user => {
+                let user = user as usize;
+                TouchCalibration::from_user_settings(&self.raw_firmware[user + 0x58..user + 0x64])
+            }
+        }
+    }
+
     /// Read a little-endian u16 from firmware
     fn read_u16(&self, offset: usize) -> u16 {
         u16::from_le_bytes([self.raw_firmware[offset], self.raw_firmware[offset + 1]])
@@ -277,3 +364,77 @@ impl Default for Firmware {
         Self::new()
     }
 }
+
+/// Only the transfer in progress; the image is loaded with the ROM.
+impl Savestate for Firmware {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u8(self.status_reg);
+        w.u8(self.command_id as u8);
+        w.u32(self.address);
+        w.u32(self.total_args as u32);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.status_reg = r.u8()?;
+        self.command_id = FirmwareCommand::from_value(r.u8()? as u32);
+        self.address = r.u32()?;
+        self.total_args = r.u32()? as i32;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_keep_user_crc_valid() {
+        let mut firmware = Firmware::new();
+        firmware.load_firmware("").unwrap();
+        let user = firmware.user_data as usize;
+
+        firmware
+            .apply_overrides(&FirmwareOverrides {
+                language: Some(FirmwareLanguage::German),
+                birthday: Some((12, 24)),
+                favorite_color: Some(7),
+                console_type: Some(DSType::Lite),
+            })
+            .unwrap();
+
+        assert_eq!(firmware.raw_firmware[0x1D], 0x20);
+        assert_eq!(firmware.raw_firmware[user + 0x02], 7);
+        assert_eq!(firmware.raw_firmware[user + 0x03], 12);
+        assert_eq!(firmware.raw_firmware[user + 0x04], 24);
+        assert_eq!(firmware.read_u16(user + 0x64) & 0x7, 3);
+        assert!(firmware.verify_crc(0xFFFF, user, 0x70, user + 0x72));
+    }
+
+    #[test]
+    fn test_overrides_reject_invalid_birthday() {
+        let mut firmware = Firmware::new();
+        firmware.load_firmware("").unwrap();
+        let raw = firmware.raw_firmware.clone();
+
+        for birthday in [(0, 1), (13, 1), (1, 0), (1, 32), (2, 30), (4, 31)] {
+            let result = firmware.apply_overrides(&FirmwareOverrides {
+                birthday: Some(birthday),
+                favorite_color: Some(7),
+                ..Default::default()
+            });
+            assert!(
+                matches!(result, Err(EmuError::FirmwareBirthday { .. })),
+                "{birthday:?}"
+            );
+        }
+        assert_eq!(firmware.raw_firmware, raw);
+
+        for birthday in [(2, 29), (12, 31)] {
+            let overrides = FirmwareOverrides {
+                birthday: Some(birthday),
+                ..Default::default()
+            };
+            firmware.apply_overrides(&overrides).unwrap();
+        }
+    }
+}
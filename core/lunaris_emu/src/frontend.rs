@@ -0,0 +1,168 @@
+//! Push-based frontend interface
+//!
+//! Instead of polling [`Emulator::take_completed_frame`], copying the
+//! screens out and pulling audio with [`Emulator::get_samples`], a frontend
+//! can hand the emulator sinks that [`Emulator::run`] drives itself:
+//!
+//! - a [`VideoSink`] gets both screens, borrowed straight from the
+//!   renderer, and the [`Timestamps`] at the end of every completed frame
+//! - an [`AudioSink`] gets the SPU output of the frame at the same time;
+//!   with one set, nothing is left for [`Emulator::get_samples`]
+//! - an [`InputSource`] is polled for buttons and the touchscreen at the
+//!   start of every frame
+//!
+//! Each is optional, so a frontend can mix them with the pull calls.
+use crate::emulator::Emulator;
+use crate::emulator::event::Timestamps;
+use crate::input::InputState;
+
+/// Receives the screens of each completed frame.
+pub trait VideoSink: std::fmt::Debug + Send {
+    /// Both screens, ARGB8888, [`SCREEN_PIXELS`](crate::SCREEN_PIXELS)
+    /// long each. Only valid for the call: upload or copy them here.
+    ///
+    /// `time` is when the frame ended, as [`Emulator::timestamps`] then.
+    fn present(&mut self, upper: &[u32], lower: &[u32], time: Timestamps);
+}
+
+/// Receives the SPU output.
+pub trait AudioSink: std::fmt::Debug + Send {
+    /// Left/right samples at
+    /// [`Config::audio_output_rate`](crate::Config::audio_output_rate),
+    /// oldest first. A frame's audio may come in more than one call.
+    fn push_samples(&mut self, samples: &[[i16; 2]]);
+}
+
+/// Supplies the input of each frame.
+pub trait InputSource: std::fmt::Debug + Send {
+    /// Buttons held for the frame starting now.
+    fn buttons(&mut self) -> InputState;
+
+    /// Touchscreen point for the frame starting now, `None` while released.
+    fn touch(&mut self) -> Option<(i32, i32)>;
+}
+
+/// Sinks and source set on the emulator.
+#[derive(Debug, Default)]
+pub(crate) struct Frontend {
+    video: Option<Box<dyn VideoSink>>,
+    audio: Option<Box<dyn AudioSink>>,
+    input: Option<Box<dyn InputSource>>,
+}
+
+impl Emulator {
+    /// Present completed frames to `sink`, or stop with `None`.
+    pub fn set_video_sink(&mut self, sink: Option<Box<dyn VideoSink>>) {
+        self.frontend.video = sink;
+    }
+
+    /// Push audio to `sink` every frame, or stop with `None`.
+    pub fn set_audio_sink(&mut self, sink: Option<Box<dyn AudioSink>>) {
+        self.frontend.audio = sink;
+    }
+
+    /// Poll `source` for input every frame, or stop with `None`.
+    pub fn set_input_source(&mut self, source: Option<Box<dyn InputSource>>) {
+        self.frontend.input = source;
+    }
+
+    /// Apply the input of the frame starting now.
+    pub(crate) fn frontend_begin_frame(&mut self) {
+        let Some(source) = &mut self.frontend.input else {
+            return;
+        };
+        let buttons = source.buttons();
+        let touch = source.touch();
+        self.set_input(buttons);
+        match touch {
+            Some((x, y)) => self.touchscreen_press(x, y),
+            // Y 0xFFF releases the pen
+            None => self.touchscreen_press(0, 0xFFF),
+        }
+    }
+
+    /// Hand the completed frame to the sinks.
+    pub(crate) fn frontend_end_frame(&mut self) {
+        let time = self.timestamps();
+        if let Some(sink) = &mut self.frontend.video {
+            sink.present(self.gpu.upper_screen(), self.gpu.lower_screen(), time);
+        }
+        self.frontend_push_audio();
+    }
+
+    /// Hand the buffered audio to the sink.
+    pub(crate) fn frontend_push_audio(&mut self) {
+        if let Some(sink) = &mut self.frontend.audio {
+            self.spu.drain_samples(|samples| sink.push_samples(samples));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::input::Button;
+
+    #[derive(Debug, Default)]
+    struct Recorder {
+        frames: Arc<Mutex<Vec<Timestamps>>>,
+        samples: Arc<Mutex<usize>>,
+    }
+
+    impl VideoSink for Recorder {
+        fn present(&mut self, upper: &[u32], lower: &[u32], time: Timestamps) {
+            assert_eq!(upper.len(), lower.len());
+            self.frames.lock().unwrap().push(time);
+        }
+    }
+
+    impl AudioSink for Recorder {
+        fn push_samples(&mut self, samples: &[[i16; 2]]) {
+            *self.samples.lock().unwrap() += samples.len();
+        }
+    }
+
+    #[derive(Debug)]
+    struct HoldA;
+
+    impl InputSource for HoldA {
+        fn buttons(&mut self) -> InputState {
+            let mut input = InputState::default();
+            input.set_held(Button::A, true);
+            input
+        }
+
+        fn touch(&mut self) -> Option<(i32, i32)> {
+            Some((128, 96))
+        }
+    }
+
+    #[test]
+    fn test_frontend_sinks() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        let video = Recorder::default();
+        let audio = Recorder::default();
+        let (frames, samples) = (Arc::clone(&video.frames), Arc::clone(&audio.samples));
+        emu.set_video_sink(Some(Box::new(video)));
+        emu.set_audio_sink(Some(Box::new(audio)));
+        emu.set_input_source(Some(Box::new(HoldA)));
+
+        emu.run();
+        assert_eq!(frames.lock().unwrap()[..], [emu.timestamps()]);
+        emu.run();
+        let frames = frames.lock().unwrap();
+        assert_eq!(frames.len(), 2);
+        // Stamped with the scheduler time the frame ended at
+        assert_eq!(frames[1], emu.timestamps());
+        assert_eq!(frames[1].system, emu.system_timestamp);
+        assert!(frames[1].system > frames[0].system);
+        assert!(frames[1].arm9 > frames[0].arm9);
+        assert!(frames[1].arm7 > frames[0].arm7);
+        assert!(*samples.lock().unwrap() > 0);
+        assert_eq!(emu.get_samples(&mut [0; 2]), 0);
+        assert!(emu.input().is_held(Button::A));
+    }
+}
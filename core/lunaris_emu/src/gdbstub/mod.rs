@@ -0,0 +1,566 @@
+//! GDB remote debugging
+//!
+//! Serves the GDB remote serial protocol over TCP so homebrew can be
+//! debugged with `arm-none-eabi-gdb`:
+//!
+//! ```text
+//! (gdb) target remote localhost:2345
+//! ```
+//!
+//! The two CPUs are exposed as threads, 1 for the ARM9 and 2 for the ARM7;
+//! `thread 2` switches registers, memory and stepping to the ARM7. Register
+//! numbering follows GDB's classic ARM layout (r0-r15, f0-f7, fps, cpsr),
+//! with the FPA registers reading as zero. Memory goes through
+//! [`Emulator::read_word`] / [`Emulator::write_word`], so it is the selected
+//! CPU's view, I/O side effects included.
+//!
+//! Software and hardware breakpoints both map to
+//! [`Emulator::add_breakpoint`], and BKPT instructions in the ROM stop the
+//! CPU as well while GDB is attached. Write, read and access watchpoints
+//! map to [`Emulator::watchpoints`] and stop after the accessing
+//! instruction. A step executes one instruction of the selected CPU while
+//! the rest of the system stands still, continuing runs whole frames until a
+//! breakpoint hits or GDB sends an interrupt.
+mod packet;
+
+use std::io;
+use std::net::{TcpListener, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::cpu::arm_cpu::{CpuType, PsrMode, REG_PC};
+use crate::debug::{WatchAccess, WatchHit, Watchpoint};
+use crate::emulator::Emulator;
+use crate::gdbstub::packet::{Connection, Incoming, decode_hex_bytes, encode_hex_bytes, parse_hex};
+
+/// SIGINT, reported when GDB interrupted the target
+const SIGINT: u8 = 2;
+/// SIGTRAP, reported for breakpoints and steps
+const SIGTRAP: u8 = 5;
+
+/// Registers in GDB's classic ARM layout: r0-r15, f0-f7, fps, cpsr.
+const REGISTER_COUNT: usize = 26;
+/// First FPA register
+const REG_F0: usize = 16;
+const REG_FPS: usize = 24;
+const REG_CPSR: usize = 25;
+/// Size of an FPA register
+const FPA_SIZE: usize = 12;
+
+/// Largest memory transfer, keeps replies within the advertised packet size
+const MAX_MEMORY_TRANSFER: u32 = 0x800;
+
+/// What the connection has to do after a packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Action {
+    Reply(String),
+    Step,
+    Continue,
+    Detach,
+    Kill,
+}
+
+/// Protocol state independent of the connection.
+#[derive(Debug)]
+struct Session {
+    /// CPU register and memory packets apply to
+    cpu: CpuType,
+    /// Signal of the last stop
+    last_signal: u8,
+    /// Watchpoint behind the last stop
+    last_watch: Option<WatchHit>,
+}
+
+impl Session {
+    const fn new() -> Self {
+        Self {
+            cpu: CpuType::Arm9,
+            last_signal: SIGTRAP,
+            last_watch: None,
+        }
+    }
+
+    fn handle(&mut self, emu: &mut Emulator, packet: &[u8]) -> Action {
+        let reply = |text: &str| Action::Reply(text.to_owned());
+        let Some((&command, args)) = packet.split_first() else {
+            return reply("");
+        };
+        match command {
+            b'?' => Action::Reply(self.stop_reply(self.last_signal)),
+            b'g' => Action::Reply(self.read_registers(emu)),
+            b'G' => match self.write_registers(emu, args) {
+                Some(()) => reply("OK"),
+                None => reply("E01"),
+            },
+            b'p' => match parse_hex(args).and_then(|reg| self.read_register(emu, reg as usize)) {
+                Some(value) => Action::Reply(value),
+                None => reply("E01"),
+            },
+            b'P' => match self.write_register_packet(emu, args) {
+                Some(()) => reply("OK"),
+                None => reply("E01"),
+            },
+            b'm' => match self.read_memory(emu, args) {
+                Some(data) => Action::Reply(data),
+                None => reply("E01"),
+            },
+            b'M' => match self.write_memory(emu, args) {
+                Some(()) => reply("OK"),
+                None => reply("E01"),
+            },
+            b'Z' | b'z' => match self.breakpoint(emu, command == b'Z', args) {
+                Some(true) => reply("OK"),
+                Some(false) => reply(""),
+                None => reply("E01"),
+            },
+            b'H' => match args.split_first().and_then(|(_, id)| thread_cpu(id)) {
+                Some(cpu) => {
+                    self.cpu = cpu.unwrap_or(self.cpu);
+                    reply("OK")
+                }
+                None => reply("E01"),
+            },
+            b'T' => match thread_cpu(args) {
+                Some(Some(_)) => reply("OK"),
+                _ => reply("E01"),
+            },
+            b's' => Action::Step,
+            b'c' => Action::Continue,
+            b'D' => Action::Detach,
+            b'k' => Action::Kill,
+            b'q' | b'Q' => self.query(packet),
+            // Everything else, including `vCont`, is unsupported; GDB falls
+            // back to the packets above
+            _ => reply(""),
+        }
+    }
+
+    fn query(&mut self, packet: &[u8]) -> Action {
+        let text = String::from_utf8_lossy(packet);
+        let (name, args) = text.split_once([':', ',']).unwrap_or((&text, ""));
+        Action::Reply(match name {
+            "qSupported" => format!(
+                "PacketSize={:x};QStartNoAckMode+",
+                MAX_MEMORY_TRANSFER * 2 + 32
+            ),
+            "qAttached" => "1".to_owned(),
+            "qC" => format!("QC{:x}", thread_id(self.cpu)),
+            "qfThreadInfo" => "m1,2".to_owned(),
+            "qsThreadInfo" => "l".to_owned(),
+            "qThreadExtraInfo" => match thread_cpu(args.as_bytes()) {
+                Some(Some(CpuType::Arm9)) => encode_hex_bytes(b"ARM9"),
+                Some(Some(CpuType::Arm7)) => encode_hex_bytes(b"ARM7"),
+                _ => "E01".to_owned(),
+            },
+            // Handled by `GdbStub::serve`, which also switches acks off
+            "QStartNoAckMode" => "OK".to_owned(),
+            _ => String::new(),
+        })
+    }
+
+    fn stop_reply(&self, signal: u8) -> String {
+        let mut reply = format!("T{signal:02x}thread:{:x};", thread_id(self.cpu));
+        if let Some(hit) = self.last_watch.filter(|_| signal == SIGTRAP) {
+            let kind = match hit.access {
+                WatchAccess::Write => "watch",
+                _ => "rwatch",
+            };
+            reply += &format!("{kind}:{:x};", hit.address);
+        }
+        reply
+    }
+
+    fn read_registers(&self, emu: &Emulator) -> String {
+        (0..REGISTER_COUNT)
+            .filter_map(|reg| self.read_register(emu, reg))
+            .collect()
+    }
+
+    fn read_register(&self, emu: &Emulator, reg: usize) -> Option<String> {
+        let cpu = emu.get_cpu(self.cpu);
+        let value = match reg {
+            0..REG_F0 if reg == REG_PC.index() => current_pc(emu, self.cpu),
+            0..REG_F0 => cpu.regs[reg],
+            REG_F0..REG_FPS => return Some("00".repeat(FPA_SIZE)),
+            REG_FPS => 0,
+            REG_CPSR => cpu.get_cpsr().get(),
+            _ => return None,
+        };
+        Some(encode_hex_bytes(&value.to_le_bytes()))
+    }
+
+    fn write_registers(&mut self, emu: &mut Emulator, args: &[u8]) -> Option<()> {
+        let data = decode_hex_bytes(args)?;
+        let mut offset = 0;
+        for reg in 0..REGISTER_COUNT {
+            let size = match reg {
+                REG_F0..REG_FPS => FPA_SIZE,
+                _ => 4,
+            };
+            let Some(bytes) = data.get(offset..offset + size) else {
+                break;
+            };
+            if size == 4 {
+                let value = u32::from_le_bytes(bytes.try_into().ok()?);
+                self.write_register(emu, reg, value)?;
+            }
+            offset += size;
+        }
+        Some(())
+    }
+
+    fn write_register_packet(&mut self, emu: &mut Emulator, args: &[u8]) -> Option<()> {
+        let split = args.iter().position(|&byte| byte == b'=')?;
+        let reg = parse_hex(&args[..split])? as usize;
+        let bytes = decode_hex_bytes(&args[split + 1..])?;
+        match reg {
+            REG_F0..=REG_FPS => Some(()),
+            _ => self.write_register(emu, reg, u32::from_le_bytes(bytes.try_into().ok()?)),
+        }
+    }
+
+    fn write_register(&mut self, emu: &mut Emulator, reg: usize, value: u32) -> Option<()> {
+        let cpu = emu.get_cpu_mut(self.cpu);
+        match reg {
+            0..REG_F0 if reg == REG_PC.index() => {
+                // r15 runs ahead of the next instruction by the prefetch
+                cpu.regs[REG_PC.index()] = match cpu.cpsr.thumb_on {
+                    true => (value & !1).wrapping_add(2),
+                    false => (value & !3).wrapping_add(4),
+                };
+            }
+            0..REG_F0 => cpu.regs[reg] = value,
+            REG_F0..=REG_FPS => {}
+            REG_CPSR => {
+                let mode = PsrMode::from_u32(value & 0x1F)?;
+                cpu.update_reg_mode(mode);
+                cpu.cpsr.set(value);
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn read_memory(&self, emu: &mut Emulator, args: &[u8]) -> Option<String> {
+        let (address, length) = address_length(args)?;
+        let mut data = Vec::with_capacity(length as usize);
+        let mut word = None;
+        for address in (0..length).map(|offset| address.wrapping_add(offset)) {
+            let aligned = address & !3;
+            let value = match word {
+                Some((cached, value)) if cached == aligned => value,
+                _ => emu.read_word(aligned, self.cpu),
+            };
+            word = Some((aligned, value));
+            data.push((value >> ((address & 3) * 8)) as u8);
+        }
+        Some(encode_hex_bytes(&data))
+    }
+
+    fn write_memory(&self, emu: &mut Emulator, args: &[u8]) -> Option<()> {
+        let split = args.iter().position(|&byte| byte == b':')?;
+        let (address, length) = address_length(&args[..split])?;
+        let data = decode_hex_bytes(&args[split + 1..])?;
+        if data.len() != length as usize {
+            return None;
+        }
+
+        let mut offset = 0;
+        while offset < data.len() {
+            let address = address.wrapping_add(offset as u32);
+            let aligned = address & !3;
+            let shift = (address & 3) as usize;
+            let count = (4 - shift).min(data.len() - offset);
+
+            // Partial words are merged with what is there
+            let mut bytes = match count {
+                4 => [0; 4],
+                _ => emu.read_word(aligned, self.cpu).to_le_bytes(),
+            };
+            bytes[shift..shift + count].copy_from_slice(&data[offset..offset + count]);
+            emu.write_word(aligned, u32::from_le_bytes(bytes), self.cpu);
+            offset += count;
+        }
+        Some(())
+    }
+
+    /// `Z`/`z` packets; `Some(false)` for unsupported kinds.
+    fn breakpoint(&self, emu: &mut Emulator, insert: bool, args: &[u8]) -> Option<bool> {
+        let mut fields = args.split(|&byte| byte == b',');
+        let kind = fields.next()?;
+        let address = parse_hex(fields.next()?)?;
+        let (read, write) = match kind {
+            // Software and hardware breakpoints behave the same here
+            b"0" | b"1" => {
+                match insert {
+                    true => emu.add_breakpoint(self.cpu, address),
+                    false => {
+                        emu.remove_breakpoint(self.cpu, address);
+                    }
+                }
+                return Some(true);
+            }
+            b"2" => (false, true),
+            b"3" => (true, false),
+            b"4" => (true, true),
+            _ => return Some(false),
+        };
+        let watchpoint = Watchpoint {
+            cpu: self.cpu,
+            start: address,
+            len: parse_hex(fields.next()?)?,
+            read,
+            write,
+            execute: false,
+        };
+        match insert {
+            true => emu.watchpoints.add(watchpoint),
+            false => {
+                emu.watchpoints.remove(&watchpoint);
+            }
+        }
+        Some(true)
+    }
+}
+
+/// A GDB client connected to the emulator.
+pub struct GdbStub {
+    connection: Connection,
+    session: Session,
+}
+
+impl GdbStub {
+    /// Wait for GDB to connect on `address`.
+    pub fn listen(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        Self::accept(&listener)
+    }
+
+    /// Wait for GDB to connect to `listener`.
+    pub fn accept(listener: &TcpListener) -> io::Result<Self> {
+        let (stream, _) = listener.accept()?;
+        #[cfg(feature = "tracing")]
+        tracing::info!("GDB connected from {:?}", stream.peer_addr());
+        Ok(Self {
+            connection: Connection::new(stream)?,
+            session: Session::new(),
+        })
+    }
+
+    /// Serve GDB until it detaches or disconnects. The emulator only runs
+    /// while GDB lets it continue.
+    pub fn serve(&mut self, emu: &mut Emulator) -> io::Result<()> {
+        emu.stop_on_bkpt = true;
+        let result = self.serve_packets(emu);
+        emu.stop_on_bkpt = false;
+        #[cfg(feature = "tracing")]
+        tracing::info!("GDB disconnected");
+        result
+    }
+
+    fn serve_packets(&mut self, emu: &mut Emulator) -> io::Result<()> {
+        while let Some(incoming) = self.connection.receive()? {
+            let packet = match incoming {
+                Incoming::Packet(packet) => packet,
+                // Already stopped
+                Incoming::Interrupt => {
+                    self.session.last_signal = SIGINT;
+                    let reply = self.session.stop_reply(SIGINT);
+                    self.connection.send(&reply)?;
+                    continue;
+                }
+            };
+
+            match self.session.handle(emu, &packet) {
+                Action::Reply(reply) => {
+                    self.connection.send(&reply)?;
+                    if packet == b"QStartNoAckMode" {
+                        self.connection.no_ack = true;
+                    }
+                }
+                Action::Step => {
+                    emu.step_instruction(self.session.cpu);
+                    if let Some(cpu) = emu.take_breakpoint_hit() {
+                        self.session.cpu = cpu;
+                    }
+                    self.session.last_watch = emu.take_watch_hit();
+                    self.session.last_signal = SIGTRAP;
+                    self.connection.send(&self.session.stop_reply(SIGTRAP))?;
+                }
+                Action::Continue => {
+                    let signal = self.run_until_stop(emu)?;
+                    self.session.last_signal = signal;
+                    self.connection.send(&self.session.stop_reply(signal))?;
+                }
+                Action::Detach => {
+                    self.connection.send("OK")?;
+                    break;
+                }
+                Action::Kill => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Run until a breakpoint hits or GDB interrupts, returns the signal to
+    /// report.
+    fn run_until_stop(&mut self, emu: &mut Emulator) -> io::Result<u8> {
+        // Breakpoints GDB left inserted do not stop it where it stands
+        emu.resume_at_pc(self.session.cpu);
+        loop {
+            if let Some(cpu) = emu.take_breakpoint_hit() {
+                self.session.cpu = cpu;
+                self.session.last_watch = emu.take_watch_hit();
+                return Ok(SIGTRAP);
+            }
+            if self.connection.poll_interrupt()? {
+                return Ok(SIGINT);
+            }
+            if !emu.run() {
+                // Paused by the frontend, wait for it or for GDB
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
+}
+
+const fn thread_id(cpu: CpuType) -> u32 {
+    match cpu {
+        CpuType::Arm9 => 1,
+        CpuType::Arm7 => 2,
+    }
+}
+
+/// CPU of a thread id; `Some(None)` for "any thread" (0 or -1).
+fn thread_cpu(id: &[u8]) -> Option<Option<CpuType>> {
+    if id == b"-1" {
+        return Some(None);
+    }
+    match parse_hex(id)? {
+        0 => Some(None),
+        1 => Some(Some(CpuType::Arm9)),
+        2 => Some(Some(CpuType::Arm7)),
+        _ => None,
+    }
+}
+
+/// Address of the next instruction, r15 minus the prefetch.
+fn current_pc(emu: &Emulator, cpu_type: CpuType) -> u32 {
+    let cpu = emu.get_cpu(cpu_type);
+    let prefetch = if cpu.cpsr.thumb_on { 2 } else { 4 };
+    cpu.get_pc().wrapping_sub(prefetch)
+}
+
+/// `addr,length` of memory packets.
+fn address_length(args: &[u8]) -> Option<(u32, u32)> {
+    let split = args.iter().position(|&byte| byte == b',')?;
+    let address = parse_hex(&args[..split])?;
+    let length = parse_hex(&args[split + 1..])?;
+    (length <= MAX_MEMORY_TRANSFER).then_some((address, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(session: &mut Session, emu: &mut Emulator, packet: &str) -> String {
+        match session.handle(emu, packet.as_bytes()) {
+            Action::Reply(reply) => reply,
+            action => panic!("{packet}: unexpected {action:?}"),
+        }
+    }
+
+    #[test]
+    fn test_session() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        let mut session = Session::new();
+
+        assert_eq!(reply(&mut session, &mut emu, "Hg2"), "OK");
+        assert_eq!(session.cpu, CpuType::Arm7);
+        assert_eq!(reply(&mut session, &mut emu, "qC"), "QC2");
+
+        // Registers
+        assert_eq!(reply(&mut session, &mut emu, "P3=78563412"), "OK");
+        assert_eq!(emu.arm7.regs[3], 0x1234_5678);
+        assert_eq!(reply(&mut session, &mut emu, "Pf=00000002"), "OK");
+        assert_eq!(reply(&mut session, &mut emu, "pf"), "00000002");
+        assert_eq!(emu.arm7.get_pc(), 0x0200_0004);
+        let registers = reply(&mut session, &mut emu, "g");
+        assert_eq!(registers.len(), (16 * 4 + 8 * FPA_SIZE + 2 * 4) * 2);
+        assert_eq!(&registers[3 * 8..4 * 8], "78563412");
+
+        // Memory, including a write that is not word aligned
+        assert_eq!(reply(&mut session, &mut emu, "M2000001,2:aabb"), "OK");
+        assert_eq!(reply(&mut session, &mut emu, "m2000000,4"), "00aabb00");
+        assert_eq!(emu.main_ram[..4], [0x00, 0xAA, 0xBB, 0x00]);
+        assert_eq!(reply(&mut session, &mut emu, "m2000000,fffff"), "E01");
+
+        // Breakpoints
+        assert_eq!(reply(&mut session, &mut emu, "Z0,2000000,4"), "OK");
+        assert_eq!(emu.breakpoints, [(CpuType::Arm7, 0x0200_0000)]);
+        assert_eq!(reply(&mut session, &mut emu, "z0,2000000,4"), "OK");
+        assert!(emu.breakpoints.is_empty());
+        assert_eq!(reply(&mut session, &mut emu, "Z9,2000000,4"), "");
+
+        // Watchpoints
+        assert_eq!(reply(&mut session, &mut emu, "Z2,2000000,4"), "OK");
+        assert_eq!(reply(&mut session, &mut emu, "Z4,2000010,2"), "OK");
+        assert_eq!(emu.watchpoints.iter().count(), 2);
+        assert_eq!(reply(&mut session, &mut emu, "z2,2000000,4"), "OK");
+        assert_eq!(reply(&mut session, &mut emu, "z4,2000010,2"), "OK");
+        assert!(emu.watchpoints.is_empty());
+
+        assert_eq!(session.handle(&mut emu, b"c"), Action::Continue);
+        assert_eq!(reply(&mut session, &mut emu, "?"), "T05thread:2;");
+    }
+    #[test]
+    fn test_step_and_continue_over_breakpoint() {
+        let mut emu = Box::new(Emulator::new());
+        emu.power_on();
+        // mov r0, #1; add r0, r0, #1; b .-8
+        emu.main_ram[..4].copy_from_slice(&0xE3A0_0001_u32.to_le_bytes());
+        emu.main_ram[4..8].copy_from_slice(&0xE280_0001_u32.to_le_bytes());
+        emu.main_ram[8..12].copy_from_slice(&0xEAFF_FFFC_u32.to_le_bytes());
+        emu.arm7.jp(0x0200_0000, false);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let gdb = std::thread::spawn(move || {
+            let stream = std::net::TcpStream::connect(address).unwrap();
+            let mut connection = packet::Connection::new(stream).unwrap();
+            // Stopped on the breakpoint it left inserted, then stepping onto
+            // it and off it again
+            let packets = [
+                "Hg2",
+                "Z0,2000000,4",
+                "c",
+                "p0",
+                "s",
+                "s",
+                "s",
+                "p0",
+                "s",
+                "p0",
+            ];
+            let replies = packets.map(|packet| {
+                connection.send(packet).unwrap();
+                match connection.receive().unwrap() {
+                    Some(packet::Incoming::Packet(reply)) => String::from_utf8(reply).unwrap(),
+                    incoming => panic!("{packet}: unexpected {incoming:?}"),
+                }
+            });
+            connection.send("k").unwrap();
+            replies
+        });
+        let mut stub = GdbStub::accept(&listener).unwrap();
+        stub.serve(&mut emu).unwrap();
+
+        let replies = gdb.join().unwrap();
+        assert_eq!(replies[2], "T05thread:2;");
+        assert_eq!(replies[3], "02000000");
+        assert_eq!(replies[4..7], ["T05thread:2;"; 3]);
+        assert_eq!(replies[7], "02000000");
+        assert_eq!(replies[9], "01000000");
+        assert_eq!(emu.arm7.get_pc(), 0x0200_0008);
+    }
+}
@@ -0,0 +1,165 @@
+//! GDB remote serial protocol framing
+//!
+//! Packets are `$payload#cs`, where `cs` is the payload byte sum modulo 256
+//! in hex. Every packet is acknowledged with `+` (or `-` to ask for a
+//! resend) until no-ack mode is negotiated. A bare 0x03 byte outside a
+//! packet asks the target to stop.
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::TcpStream;
+
+/// Stop request sent by GDB while the target runs.
+const INTERRUPT: u8 = 0x03;
+
+/// What GDB sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Incoming {
+    Packet(Vec<u8>),
+    Interrupt,
+}
+
+/// A GDB client connection.
+pub(crate) struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// Acks were turned off with `QStartNoAckMode`
+    pub no_ack: bool,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            writer: stream.try_clone()?,
+            reader: BufReader::new(stream),
+            no_ack: false,
+        })
+    }
+
+    /// Wait for the next packet or interrupt, `None` once GDB disconnected.
+    pub fn receive(&mut self) -> io::Result<Option<Incoming>> {
+        loop {
+            let Some(byte) = self.read_byte()? else {
+                return Ok(None);
+            };
+            match byte {
+                b'$' => {}
+                INTERRUPT => return Ok(Some(Incoming::Interrupt)),
+                // Acks, and anything between packets
+                _ => continue,
+            }
+
+            let mut payload = Vec::new();
+            let mut sum = 0u8;
+            loop {
+                let Some(byte) = self.read_byte()? else {
+                    return Ok(None);
+                };
+                if byte == b'#' {
+                    break;
+                }
+                sum = sum.wrapping_add(byte);
+                payload.push(byte);
+            }
+            let mut checksum = [0; 2];
+            self.reader.read_exact(&mut checksum)?;
+
+            let valid = parse_hex(&checksum).is_some_and(|checksum| checksum == sum as u32);
+            if !self.no_ack {
+                self.writer.write_all(if valid { b"+" } else { b"-" })?;
+            }
+            if valid {
+                return Ok(Some(Incoming::Packet(unescape(&payload))));
+            }
+        }
+    }
+
+    pub fn send(&mut self, payload: &str) -> io::Result<()> {
+        let sum = payload.bytes().fold(0u8, u8::wrapping_add);
+        write!(self.writer, "${payload}#{sum:02x}")?;
+        self.writer.flush()
+    }
+
+    /// Whether GDB asked to stop, without blocking.
+    pub fn poll_interrupt(&mut self) -> io::Result<bool> {
+        self.reader.get_ref().set_nonblocking(true)?;
+        let pending = match self.reader.fill_buf() {
+            Ok(buffer) => buffer.first().copied(),
+            Err(error) if error.kind() == ErrorKind::WouldBlock => None,
+            Err(error) => {
+                self.reader.get_ref().set_nonblocking(false)?;
+                return Err(error);
+            }
+        };
+        self.reader.get_ref().set_nonblocking(false)?;
+
+        // Anything else is left for `receive`
+        if pending == Some(INTERRUPT) {
+            self.reader.consume(1);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        match self.reader.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+}
+
+/// Undo the `}` escaping of binary packet data.
+fn unescape(payload: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(payload.len());
+    let mut bytes = payload.iter();
+    while let Some(&byte) = bytes.next() {
+        match byte {
+            b'}' => data.extend(bytes.next().map(|byte| byte ^ 0x20)),
+            _ => data.push(byte),
+        }
+    }
+    data
+}
+
+/// Parse a big-endian hex number, as used for addresses and lengths.
+pub(crate) fn parse_hex(text: &[u8]) -> Option<u32> {
+    if text.is_empty() || text.len() > 8 {
+        return None;
+    }
+    text.iter().try_fold(0u32, |value, &digit| {
+        Some(value << 4 | (digit as char).to_digit(16)?)
+    })
+}
+
+/// Decode hex byte pairs, as used for memory and register contents.
+pub(crate) fn decode_hex_bytes(text: &[u8]) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    text.chunks(2)
+        .map(|pair| parse_hex(pair).map(|byte| byte as u8))
+        .collect()
+}
+
+pub(crate) fn encode_hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(parse_hex(b"2000000"), Some(0x0200_0000));
+        assert_eq!(parse_hex(b"fFfF"), Some(0xFFFF));
+        assert_eq!(parse_hex(b""), None);
+        assert_eq!(parse_hex(b"123456789"), None);
+        assert_eq!(parse_hex(b"12g"), None);
+        assert_eq!(decode_hex_bytes(b"01ff"), Some(vec![0x01, 0xFF]));
+        assert_eq!(decode_hex_bytes(b"01f"), None);
+        assert_eq!(encode_hex_bytes(&[0x01, 0xFF]), "01ff");
+        assert_eq!(unescape(b"a}\x03b"), b"a#b");
+    }
+}
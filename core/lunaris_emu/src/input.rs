@@ -0,0 +1,141 @@
+//! Button input
+//!
+//! Frontends describe the held buttons with an [`InputState`], either
+//! building it each frame or updating it with [`InputEvent`]s from their
+//! key mapping, and hand it to the emulator in one call. Buttons can be
+//! marked turbo, autofiring while held.
+
+/// Frames a turbo button stays pressed, then released.
+pub const TURBO_PERIOD: u8 = 2;
+
+/// DS buttons, the touchscreen and hinge excepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+    A,
+    B,
+    Select,
+    Start,
+    Right,
+    Left,
+    Up,
+    Down,
+    R,
+    L,
+    X,
+    Y,
+}
+
+impl Button {
+    pub const ALL: [Self; 12] = [
+        Self::A,
+        Self::B,
+        Self::Select,
+        Self::Start,
+        Self::Right,
+        Self::Left,
+        Self::Up,
+        Self::Down,
+        Self::R,
+        Self::L,
+        Self::X,
+        Self::Y,
+    ];
+
+    pub(crate) const fn mask(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+/// Change of one button, as a key mapping produces them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    Pressed(Button),
+    Released(Button),
+    /// Make a button autofire while held, or stop it
+    Turbo(Button, bool),
+}
+
+/// Held and turbo buttons, see [`Emulator::set_input`](crate::Emulator::set_input).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputState {
+    /// Held buttons, bit `n` for `Button` `n`
+    held: u16,
+    /// Buttons that autofire while held
+    turbo: u16,
+    /// Report left+right and up+down held together, which a D-pad cannot
+    /// do and some games crash on
+    pub allow_opposing: bool,
+}
+
+impl InputState {
+    pub const fn is_held(&self, button: Button) -> bool {
+        self.held & button.mask() != 0
+    }
+
+    pub const fn is_turbo(&self, button: Button) -> bool {
+        self.turbo & button.mask() != 0
+    }
+
+    pub const fn set_held(&mut self, button: Button, held: bool) {
+        match held {
+            true => self.held |= button.mask(),
+            false => self.held &= !button.mask(),
+        }
+    }
+
+    pub const fn set_turbo(&mut self, button: Button, turbo: bool) {
+        match turbo {
+            true => self.turbo |= button.mask(),
+            false => self.turbo &= !button.mask(),
+        }
+    }
+
+    pub const fn apply(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Pressed(button) => self.set_held(button, true),
+            InputEvent::Released(button) => self.set_held(button, false),
+            InputEvent::Turbo(button, turbo) => self.set_turbo(button, turbo),
+        }
+    }
+
+    /// Buttons the game sees pressed on turbo frame `turbo_frame`: turbo
+    /// buttons are released every other [`TURBO_PERIOD`] frames, and
+    /// opposing directions cancel out unless allowed.
+    pub(crate) fn pressed(&self, turbo_frame: u8) -> u16 {
+        let mut pressed = self.held;
+        if (turbo_frame / TURBO_PERIOD) % 2 == 1 {
+            pressed &= !self.turbo;
+        }
+        if !self.allow_opposing {
+            for (a, b) in [(Button::Left, Button::Right), (Button::Up, Button::Down)] {
+                let both = a.mask() | b.mask();
+                if pressed & both == both {
+                    pressed &= !both;
+                }
+            }
+        }
+        pressed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_state() {
+        let mut input = InputState::default();
+        input.apply(InputEvent::Pressed(Button::A));
+        input.apply(InputEvent::Pressed(Button::Left));
+        input.apply(InputEvent::Pressed(Button::Right));
+        assert_eq!(input.pressed(0), Button::A.mask());
+        input.allow_opposing = true;
+        assert_eq!(input.pressed(0).count_ones(), 3);
+
+        input.apply(InputEvent::Released(Button::Left));
+        input.apply(InputEvent::Turbo(Button::A, true));
+        assert_eq!(input.pressed(0), Button::A.mask() | Button::Right.mask());
+        assert_eq!(input.pressed(TURBO_PERIOD), Button::Right.mask());
+        assert!(input.is_held(Button::A) && input.is_turbo(Button::A));
+    }
+}
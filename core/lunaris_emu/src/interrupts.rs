@@ -1,3 +1,6 @@
+use crate::error::EmuError;
+use crate::savestate::{Savestate, StateReader, StateWriter};
+
 /// Interrupt identifier for different interrupt sources
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Interrupt {
@@ -73,3 +76,18 @@ impl InterruptRegs {
         unimplemented!("It is not used in C++ and has no definition.");
     }
 }
+
+impl Savestate for InterruptRegs {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u32(self.ime);
+        w.u32(self.irq_enable);
+        w.u32(self.irq_flags);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.ime = r.u32()?;
+        self.irq_enable = r.u32()?;
+        self.irq_flags = r.u32()?;
+        Ok(())
+    }
+}
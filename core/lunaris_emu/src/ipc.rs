@@ -6,7 +6,14 @@
 //! Enables communication between ARM7 and ARM9 processors
 use std::collections::VecDeque;
 
+use crate::error::EmuError;
+use crate::interrupts::Interrupt;
+use crate::savestate::{Savestate, StateReader, StateWriter};
+
 /// IPC Synchronization register
+///
+/// Each CPU writes four bits to bits 8-11 of its IPCSYNC, which the other
+/// CPU reads back in bits 0-3 of its own.
 #[derive(Debug, Clone, Copy)]
 pub struct IpcSync {
     /// Input data from other CPU
@@ -30,23 +37,25 @@ impl IpcSync {
     /// Read sync register value
     pub fn read(&self) -> u16 {
         let mut value = 0u16;
-        value |= ((self.input & 0xF) as u16) << 8;
-        value |= (self.output & 0xF) as u16;
+        value |= (self.input & 0xF) as u16;
+        value |= ((self.output & 0xF) as u16) << 8;
         if self.irq_enable {
             value |= 1 << 14;
         }
         value
     }
 
-    /// Receive input from other CPU
-    pub fn receive_input(&mut self, halfword: u16) {
-        self.input = ((halfword >> 8) & 0xF) as u32;
+    /// Receive the output of the other CPU
+    pub fn receive_input(&mut self, output: u32) {
+        self.input = output & 0xF;
     }
 
-    /// Write to sync register
-    pub fn write(&mut self, halfword: u16) {
-        self.output = (halfword & 0xF) as u32;
+    /// Write to sync register, returning whether bit 13 requests an
+    /// interrupt on the other CPU.
+    pub fn write(&mut self, halfword: u16) -> bool {
+        self.output = ((halfword >> 8) & 0xF) as u32;
         self.irq_enable = (halfword & (1 << 14)) != 0;
+        halfword & (1 << 13) != 0
     }
 }
 
@@ -56,28 +65,47 @@ impl Default for IpcSync {
     }
 }
 
-/// IPC FIFO (First-In-First-Out) queue for data transfer
+/// Words each direction of the IPC FIFO holds.
+pub const IPC_FIFO_DEPTH: usize = 16;
+
+/// FIFO interrupts an access raises on one CPU.
+///
+/// Both are edges: send-empty fires when the other CPU takes the last word
+/// or when it is enabled on an empty FIFO, receive-not-empty when a word
+/// arrives in an empty FIFO or when it is enabled on a non-empty one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FifoIrqs {
+    pub send_empty: bool,
+    pub receive_not_empty: bool,
+}
+
+impl FifoIrqs {
+    pub fn interrupts(self) -> impl Iterator<Item = Interrupt> {
+        [
+            (self.send_empty, Interrupt::IpcFifoEmpty),
+            (self.receive_not_empty, Interrupt::IpcFifoNempty),
+        ]
+        .into_iter()
+        .filter_map(|(raised, id)| raised.then_some(id))
+    }
+}
+
+/// One CPU's side of the IPC FIFO: IPCFIFOCNT and the words it sent.
+///
+/// A CPU receives from the other side's send queue, so the accesses take
+/// both sides.
 #[derive(Debug, Clone)]
 pub struct IpcFifo {
-    /// Queue for sending data to other CPU
-    ///
-    /// - NOTE: ptr
+    /// Words sent to the other CPU and not yet read by it
     pub send_queue: VecDeque<u32>,
-    /// Queue for receiving data from other CPU
-    /// - NOTE: ptr
-    pub receive_queue: VecDeque<u32>,
 
-    /// Most recently read data
+    /// Most recently received word, read again from an empty or disabled FIFO
     pub recent_word: u32,
 
     /// Send queue is empty IRQ enable
     pub send_empty_irq: bool,
-    /// Request to empty send queue
-    pub request_empty_irq: bool,
     /// Receive queue not empty IRQ enable
     pub receive_nempty_irq: bool,
-    /// Request not empty IRQ
-    pub request_nempty_irq: bool,
 
     /// Error flag (write to full queue or read from empty)
     pub error: bool,
@@ -89,45 +117,36 @@ impl IpcFifo {
     /// Create new IPC FIFO
     pub fn new() -> Self {
         IpcFifo {
-            send_queue: VecDeque::with_capacity(16),
-            receive_queue: VecDeque::with_capacity(16),
+            send_queue: VecDeque::with_capacity(IPC_FIFO_DEPTH),
             recent_word: 0,
             send_empty_irq: false,
-            request_empty_irq: false,
             receive_nempty_irq: false,
-            request_nempty_irq: false,
             error: false,
             enabled: false,
         }
     }
 
-    /// Read control register
-    pub fn read_cnt(&self) -> u16 {
+    /// Read control register; `other` is the other CPU's side.
+    pub fn read_cnt(&self, other: &IpcFifo) -> u16 {
         let mut value = 0u16;
         if self.send_queue.is_empty() {
             value |= 1 << 0;
         }
-        if self.send_queue.len() >= 16 {
+        if self.send_queue.len() >= IPC_FIFO_DEPTH {
             value |= 1 << 1;
-        } // Full
+        }
         if self.send_empty_irq {
             value |= 1 << 2;
         }
-        if self.request_empty_irq {
-            value |= 1 << 3;
-        }
-        if !self.receive_queue.is_empty() {
+        if other.send_queue.is_empty() {
             value |= 1 << 8;
         }
-        if self.receive_queue.len() >= 16 {
+        if other.send_queue.len() >= IPC_FIFO_DEPTH {
             value |= 1 << 9;
-        } // Full
+        }
         if self.receive_nempty_irq {
             value |= 1 << 10;
         }
-        if self.request_nempty_irq {
-            value |= 1 << 11;
-        }
         if self.error {
             value |= 1 << 14;
         }
@@ -137,51 +156,70 @@ impl IpcFifo {
         value
     }
 
-    /// Write control register
-    pub fn write_cnt(&mut self, value: u16) {
-        self.send_empty_irq = (value & (1 << 2)) != 0;
-        self.request_empty_irq = (value & (1 << 3)) != 0;
-        self.receive_nempty_irq = (value & (1 << 10)) != 0;
-        self.request_nempty_irq = (value & (1 << 11)) != 0;
-        self.error = (value & (1 << 14)) != 0; // Can be cleared by writing 0
-        self.enabled = (value & (1 << 15)) != 0;
+    /// Write control register, returning the IRQs of this CPU that an
+    /// enable raised.
+    ///
+    /// Bit 3 flushes the send queue and writing 1 to bit 14 acknowledges an
+    /// error.
+    pub fn write_cnt(&mut self, other: &IpcFifo, value: u16) -> FifoIrqs {
+        if value & (1 << 3) != 0 {
+            self.send_queue.clear();
+        }
+        if value & (1 << 14) != 0 {
+            self.error = false;
+        }
+
+        let send_empty_irq = value & (1 << 2) != 0;
+        let receive_nempty_irq = value & (1 << 10) != 0;
+        let irqs = FifoIrqs {
+            send_empty: send_empty_irq && !self.send_empty_irq && self.send_queue.is_empty(),
+            receive_not_empty: receive_nempty_irq
+                && !self.receive_nempty_irq
+                && !other.send_queue.is_empty(),
+        };
+        self.send_empty_irq = send_empty_irq;
+        self.receive_nempty_irq = receive_nempty_irq;
+        self.enabled = value & (1 << 15) != 0;
+        irqs
     }
 
-    /// Read from receive queue
-    pub fn read_queue(&mut self) -> u32 {
+    /// Read IPCFIFORECV: take a word sent by `other`, returning it and the
+    /// IRQs of the other CPU.
+    pub fn receive(&mut self, other: &mut IpcFifo) -> (u32, FifoIrqs) {
         if !self.enabled {
-            return self.recent_word;
+            return (self.recent_word, FifoIrqs::default());
         }
 
-        match self.send_queue.front() {
-            Some(&word) => {
-                self.send_queue.pop_back();
-                if self.send_queue.is_empty() && self.send_empty_irq {
-                    self.request_empty_irq = true;
-                }
-
-                word
+        match other.send_queue.pop_front() {
+            Some(word) => {
+                self.recent_word = word;
+                let irqs = FifoIrqs {
+                    send_empty: other.send_queue.is_empty() && other.send_empty_irq,
+                    receive_not_empty: false,
+                };
+                (word, irqs)
             }
             None => {
                 self.error = true;
-                self.recent_word
+                (self.recent_word, FifoIrqs::default())
             }
         }
     }
 
-    /// Write to send queue
-    pub fn write_queue(&mut self, word: u32) {
+    /// Write IPCFIFOSEND, returning the IRQs of the other CPU.
+    pub fn send(&mut self, other: &IpcFifo, word: u32) -> FifoIrqs {
         if !self.enabled {
-            return;
+            return FifoIrqs::default();
         }
 
-        if self.receive_queue.len() >= 16 {
+        if self.send_queue.len() >= IPC_FIFO_DEPTH {
             self.error = true;
-        } else {
-            if self.receive_queue.is_empty() && self.request_nempty_irq {
-                self.request_nempty_irq = true;
-            }
-            self.receive_queue.push_back(word);
+            return FifoIrqs::default();
+        }
+        self.send_queue.push_back(word);
+        FifoIrqs {
+            send_empty: false,
+            receive_not_empty: self.send_queue.len() == 1 && other.receive_nempty_irq,
         }
     }
 
@@ -192,17 +230,7 @@ impl IpcFifo {
 
     /// Check if send queue is full
     pub fn send_full(&self) -> bool {
-        self.send_queue.len() >= 16
-    }
-
-    /// Check if receive queue has data
-    pub fn receive_not_empty(&self) -> bool {
-        !self.receive_queue.is_empty()
-    }
-
-    /// Check if receive queue is full
-    pub fn receive_full(&self) -> bool {
-        self.receive_queue.len() >= 16
+        self.send_queue.len() >= IPC_FIFO_DEPTH
     }
 
     /// Get send queue size
@@ -210,11 +238,6 @@ impl IpcFifo {
         self.send_queue.len()
     }
 
-    /// Get receive queue size
-    pub fn receive_queue_size(&self) -> usize {
-        self.receive_queue.len()
-    }
-
     /// Clear error flag
     pub fn clear_error(&mut self) {
         self.error = false;
@@ -226,3 +249,222 @@ impl Default for IpcFifo {
         Self::new()
     }
 }
+
+impl Savestate for IpcSync {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u32(self.input);
+        w.u32(self.output);
+        w.bool(self.irq_enable);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.input = r.u32()?;
+        self.output = r.u32()?;
+        self.irq_enable = r.bool()?;
+        Ok(())
+    }
+}
+
+impl Savestate for IpcFifo {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u32_list(self.send_queue.iter().copied());
+        w.u32(self.recent_word);
+        for flag in [
+            self.send_empty_irq,
+            self.receive_nempty_irq,
+            self.error,
+            self.enabled,
+        ] {
+            w.bool(flag);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.send_queue = r.u32_list()?;
+        self.recent_word = r.u32()?;
+        for flag in [
+            &mut self.send_empty_irq,
+            &mut self.receive_nempty_irq,
+            &mut self.error,
+            &mut self.enabled,
+        ] {
+            *flag = r.bool()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::arm_cpu::CpuType;
+    use crate::emulator::Emulator;
+
+    const ENABLE: u16 = 1 << 15;
+    const SEND_EMPTY_IRQ: u16 = 1 << 2;
+    const FLUSH: u16 = 1 << 3;
+    const RECV_IRQ: u16 = 1 << 10;
+    const ACK_ERROR: u16 = 1 << 14;
+
+    const NONE: FifoIrqs = FifoIrqs {
+        send_empty: false,
+        receive_not_empty: false,
+    };
+    const SEND_EMPTY: FifoIrqs = FifoIrqs {
+        send_empty: true,
+        receive_not_empty: false,
+    };
+    const RECV: FifoIrqs = FifoIrqs {
+        send_empty: false,
+        receive_not_empty: true,
+    };
+
+    /// Bus accesses of one CPU; every scenario runs with the ARM9 sending
+    /// to the ARM7 and the other way round.
+    fn cnt(emu: &mut Emulator, cpu: CpuType, value: u16) {
+        match cpu {
+            CpuType::Arm9 => emu.arm9_write_halfword(0x0400_0184, value),
+            CpuType::Arm7 => emu.arm7_write_halfword(0x0400_0184, value),
+        }
+    }
+
+    fn read_cnt(emu: &mut Emulator, cpu: CpuType) -> u16 {
+        match cpu {
+            CpuType::Arm9 => emu.arm9_read_halfword(0x0400_0184),
+            CpuType::Arm7 => emu.arm7_read_halfword(0x0400_0184),
+        }
+    }
+
+    fn send(emu: &mut Emulator, cpu: CpuType, word: u32) {
+        match cpu {
+            CpuType::Arm9 => emu.arm9_write_word(0x0400_0188, word),
+            CpuType::Arm7 => emu.arm7_write_word(0x0400_0188, word),
+        }
+    }
+
+    fn receive(emu: &mut Emulator, cpu: CpuType) -> u32 {
+        match cpu {
+            CpuType::Arm9 => emu.arm9_read_word(0x0410_0000),
+            CpuType::Arm7 => emu.arm7_read_word(0x0410_0000),
+        }
+    }
+
+    /// FIFO interrupts raised on `cpu` since the last call.
+    fn take_irqs(emu: &mut Emulator, cpu: CpuType) -> FifoIrqs {
+        let flags = match cpu {
+            CpuType::Arm9 => &mut emu.int9_reg.irq_flags,
+            CpuType::Arm7 => &mut emu.int7_reg.irq_flags,
+        };
+        let empty = 1 << Interrupt::IpcFifoEmpty as u32;
+        let nempty = 1 << Interrupt::IpcFifoNempty as u32;
+        let irqs = FifoIrqs {
+            send_empty: *flags & empty != 0,
+            receive_not_empty: *flags & nempty != 0,
+        };
+        *flags &= !(empty | nempty);
+        irqs
+    }
+
+    fn both_directions(scenario: impl Fn(&mut Emulator, CpuType, CpuType)) {
+        for (sender, receiver) in [
+            (CpuType::Arm9, CpuType::Arm7),
+            (CpuType::Arm7, CpuType::Arm9),
+        ] {
+            let mut emu = Box::new(Emulator::new());
+            emu.power_on();
+            scenario(&mut emu, sender, receiver);
+        }
+    }
+
+    #[test]
+    fn test_ipc_fifo_fill_and_drain_edges() {
+        both_directions(|emu, sender, receiver| {
+            cnt(emu, sender, ENABLE | SEND_EMPTY_IRQ);
+            assert_eq!(take_irqs(emu, sender), SEND_EMPTY);
+            cnt(emu, receiver, ENABLE | RECV_IRQ);
+            assert_eq!(take_irqs(emu, receiver), NONE);
+
+            // Only the word arriving in an empty FIFO interrupts the receiver
+            for i in 0..IPC_FIFO_DEPTH as u32 {
+                send(emu, sender, i);
+                let expected = if i == 0 { RECV } else { NONE };
+                assert_eq!(take_irqs(emu, receiver), expected, "{sender:?} send {i}");
+                assert_eq!(take_irqs(emu, sender), NONE);
+            }
+            assert_eq!(read_cnt(emu, sender) & 0x3, 0x2);
+            assert_eq!(read_cnt(emu, receiver) & 0x300, 0x200);
+
+            // Sending to a full FIFO is an error and the word is lost
+            send(emu, sender, 0xDEAD);
+            assert_eq!(take_irqs(emu, receiver), NONE);
+            assert_ne!(read_cnt(emu, sender) & ACK_ERROR, 0);
+
+            // Only taking the last word interrupts the sender
+            for i in 0..IPC_FIFO_DEPTH as u32 {
+                assert_eq!(receive(emu, receiver), i);
+                let last = i == IPC_FIFO_DEPTH as u32 - 1;
+                let expected = if last { SEND_EMPTY } else { NONE };
+                assert_eq!(take_irqs(emu, sender), expected, "{receiver:?} receive {i}");
+                assert_eq!(take_irqs(emu, receiver), NONE);
+            }
+            assert_eq!(read_cnt(emu, sender) & 0x3, 0x1);
+            assert_eq!(read_cnt(emu, receiver) & 0x300, 0x100);
+
+            // Reading an empty FIFO repeats the last word without another edge
+            assert_eq!(receive(emu, receiver), 15);
+            assert_eq!(take_irqs(emu, sender), NONE);
+            assert_ne!(read_cnt(emu, receiver) & ACK_ERROR, 0);
+            cnt(emu, receiver, ENABLE | RECV_IRQ | ACK_ERROR);
+            assert_eq!(read_cnt(emu, receiver) & ACK_ERROR, 0);
+        });
+    }
+
+    #[test]
+    fn test_ipc_fifo_enable_edges() {
+        both_directions(|emu, sender, receiver| {
+            cnt(emu, sender, ENABLE);
+            cnt(emu, receiver, ENABLE);
+            send(emu, sender, 1);
+
+            // Enabling fires when the condition already holds, rewriting does not
+            cnt(emu, receiver, ENABLE | RECV_IRQ);
+            assert_eq!(take_irqs(emu, receiver), RECV);
+            cnt(emu, receiver, ENABLE | RECV_IRQ);
+            assert_eq!(take_irqs(emu, receiver), NONE);
+            cnt(emu, sender, ENABLE | SEND_EMPTY_IRQ);
+            assert_eq!(take_irqs(emu, sender), NONE);
+            assert_eq!(receive(emu, receiver), 1);
+            assert_eq!(take_irqs(emu, sender), SEND_EMPTY);
+            cnt(emu, sender, ENABLE | SEND_EMPTY_IRQ);
+            assert_eq!(take_irqs(emu, sender), NONE);
+            cnt(emu, sender, ENABLE);
+            cnt(emu, sender, ENABLE | SEND_EMPTY_IRQ);
+            assert_eq!(take_irqs(emu, sender), SEND_EMPTY);
+
+            // Flushing empties the send queue without an interrupt
+            send(emu, sender, 2);
+            send(emu, sender, 3);
+            assert_eq!(take_irqs(emu, receiver), RECV);
+            cnt(emu, sender, ENABLE | SEND_EMPTY_IRQ | FLUSH);
+            assert_eq!(take_irqs(emu, sender), NONE);
+            assert_eq!(read_cnt(emu, receiver) & 0x100, 0x100);
+            send(emu, sender, 4);
+            assert_eq!(take_irqs(emu, receiver), RECV);
+        });
+    }
+
+    #[test]
+    fn test_ipc_fifo_disabled() {
+        both_directions(|emu, sender, receiver| {
+            cnt(emu, receiver, RECV_IRQ);
+            send(emu, sender, 1);
+            assert_eq!(take_irqs(emu, receiver), NONE);
+            assert_eq!(read_cnt(emu, sender) & 0x1, 0x1);
+
+            cnt(emu, sender, ENABLE);
+            send(emu, sender, 2);
+            assert_eq!(receive(emu, receiver), 0);
+            assert_eq!(read_cnt(emu, receiver) & (ACK_ERROR | 0x100), 0);
+        });
+    }
+}
@@ -1,18 +1,125 @@
 // SPDX-FileCopyrightText: (C) 2017 PSISP
 // SPDX-License-Identifier: GPL-3.0-or-later
+//! Nintendo DS emulator core
+//!
+//! Without the default `ds` feature only the ARM7TDMI and ARM946E-S
+//! interpreters are built, for use on any memory map: implement [`Bus`] and
+//! drive a core with [`step`]. `examples/step_blob.rs` runs a raw code blob
+//! that way.
+#![forbid(unsafe_code)]
+#[cfg(feature = "ds")]
 mod bios;
+#[cfg(feature = "ds")]
+mod boot_patch;
+#[cfg(feature = "ds")]
 mod cartridge;
+#[cfg(feature = "ds")]
+mod cheats;
 mod cpu;
+#[cfg(feature = "ds")]
+pub mod debug;
+#[cfg(feature = "ds")]
 mod dma;
+#[cfg(feature = "ds")]
 mod emulator;
 mod error;
+#[cfg(feature = "ds")]
 mod firmware;
+#[cfg(feature = "ds")]
+mod frontend;
+#[cfg(feature = "ds")]
+pub mod gdbstub;
+#[cfg(feature = "ds")]
+mod input;
+#[cfg(feature = "ds")]
 mod interrupts;
+#[cfg(feature = "ds")]
 mod ipc;
+#[cfg(feature = "ds")]
+mod microphone;
+#[cfg(feature = "ds")]
+mod patch;
+#[cfg(feature = "ds")]
+mod power_management;
+#[cfg(feature = "ds")]
 mod rtc;
+mod savestate;
+#[cfg(feature = "ds")]
+mod scheduler;
+#[cfg(feature = "ds")]
+mod sdcard;
+#[cfg(feature = "ds")]
+mod slot2;
+#[cfg(feature = "ds")]
 mod spi;
+#[cfg(feature = "ds")]
 mod timers;
+#[cfg(feature = "ds")]
 mod touchscreen;
+#[cfg(feature = "ds")]
+mod util;
+#[cfg(feature = "ds")]
 mod wifi;
 
-pub use emulator::{Emulator, emu_config::Config};
+pub use cpu::arm_cpu::{ArmCpu, CpuType, PsrFlags, PsrMode, Reg};
+pub use cpu::bus::Bus;
+pub use cpu::coprocessor_15::Cp15;
+pub use cpu::disassemble;
+pub use cpu::disassemble::{disasm_arm, disasm_thumb, disasm_thumb_bl};
+#[cfg(feature = "fuzzing")]
+pub use cpu::fuzz;
+pub use cpu::step;
+pub use error::EmuError;
+pub use savestate::{Savestate, StateReader, StateWriter};
+
+#[cfg(feature = "ds")]
+pub use boot_patch::BiosRevision;
+#[cfg(feature = "ds")]
+pub use cartridge::{
+    Banner, CartridgeError, FatEntry, HEADER_SIZE, ICON_SIZE, Overlay, RomFile, RomHeader,
+};
+#[cfg(feature = "ds")]
+pub use cheats::Cheat;
+#[cfg(feature = "ds")]
+pub use emulator::{
+    Emulator,
+    accuracy::{AccuracyOverrides, AccuracyPreset, AccuracySettings},
+    audio_dump,
+    clock_stress::{CLOCK_STRESS_ENV, ClockStress},
+    emu_config::Config,
+    event::{SystemEvent, TimedEvent, Timestamps},
+    frame::{FrameResult, SCREEN_PIXELS},
+    frame_stats::FrameStats,
+    frame_timing::{DS_AUDIO_RATE, DS_FRAME_RATE, FrameTiming},
+    game_hacks::{BUILTIN_GAME_HACKS, GameHack},
+    run_mode::{PausedAudio, RunMode},
+    save_profile,
+    state::{MAX_SAVED_GX_COMMANDS, SAVESTATE_MAGIC, SAVESTATE_VERSION},
+    stats::EmuStats,
+};
+#[cfg(feature = "ds")]
+pub use firmware::{FirmwareLanguage, FirmwareOverrides};
+#[cfg(feature = "ds")]
+pub use frontend::{AudioSink, InputSource, VideoSink};
+#[cfg(feature = "ds")]
+pub use input::{Button, InputEvent, InputState, TURBO_PERIOD};
+#[cfg(feature = "ds")]
+pub use lunaris_ds_audio::{AudioSyncStats, DynamicRateControl};
+#[cfg(feature = "ds")]
+pub use lunaris_ds_free_bios::firmware::DSType;
+#[cfg(feature = "ds")]
+pub use microphone::{MicBuffer, MicrophoneSource};
+#[cfg(feature = "ds")]
+pub use patch::{PatchFormat, apply_patch};
+#[cfg(feature = "ds")]
+pub use power_management::PowerLed;
+#[cfg(feature = "ds")]
+pub use sdcard::{FlushPolicy, SD_SECTOR_SIZE, SdCard};
+#[cfg(feature = "ds")]
+pub use slot2::{GBA_SRAM_SIZE, Slot2Device};
+#[cfg(feature = "ds")]
+pub use touchscreen::{DEFAULT_TOUCH_PRESSURE, TouchCalibration, pressure_to_z};
+#[cfg(feature = "ds")]
+pub use util::crc32;
+#[cfg(feature = "ds")]
+pub use wifi::net::{UdpTransport, WifiTransport};
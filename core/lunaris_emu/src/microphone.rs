@@ -0,0 +1,91 @@
+//! Microphone
+//!
+//! The microphone is wired to the AUX input of the touchscreen controller,
+//! through the amplifier of the power management chip. Games sample it by
+//! reading that ADC channel, usually from a timer interrupt, and each read
+//! takes one sample from the [`MicrophoneSource`] set with
+//! [`Emulator::set_microphone`].
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::emulator::Emulator;
+
+/// AUX reading with the microphone silent or its amplifier off.
+pub(crate) const MIC_SILENCE: u16 = 0x800;
+/// Samples a [`MicBuffer`] holds before dropping the oldest, 0.25 s at the
+/// rate games usually sample at.
+const MIC_BUFFER_LEN: usize = 4096;
+
+/// Audio from the frontend for the microphone.
+pub trait MicrophoneSource: std::fmt::Debug + Send {
+    /// Next PCM sample, taken each time the game reads the microphone.
+    /// Must not block.
+    fn sample(&mut self) -> i16;
+}
+
+/// Queue the frontend pushes recorded PCM samples into, from any thread.
+/// Clones share the queue: keep one and hand another to the emulator.
+/// Reads past the end repeat the last sample.
+#[derive(Debug, Clone, Default)]
+pub struct MicBuffer {
+    queue: Arc<Mutex<VecDeque<i16>>>,
+    last: i16,
+}
+
+impl MicBuffer {
+    /// Queue `samples`, dropping the oldest beyond the buffer length.
+    pub fn push(&self, samples: &[i16]) {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        queue.extend(samples);
+        let excess = queue.len().saturating_sub(MIC_BUFFER_LEN);
+        queue.drain(..excess);
+    }
+}
+
+impl MicrophoneSource for MicBuffer {
+    fn sample(&mut self) -> i16 {
+        let mut queue = self.queue.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(sample) = queue.pop_front() {
+            self.last = sample;
+        }
+        self.last
+    }
+}
+
+/// 12-bit AUX reading of `sample` through the amplifier, `None` when it is
+/// off. Full scale at the lowest gain (20x) spans the ADC; higher gains
+/// clip.
+pub(crate) fn mic_to_adc(sample: i16, gain: Option<u8>) -> u16 {
+    let Some(gain) = gain else {
+        return MIC_SILENCE;
+    };
+    let level = ((sample as i32) << gain) >> 4;
+    (MIC_SILENCE as i32 + level).clamp(0, 0xFFF) as u16
+}
+
+impl Emulator {
+    /// Feed the microphone from `source`, or silence it with `None`.
+    pub fn set_microphone(&mut self, source: Option<Box<dyn MicrophoneSource>>) {
+        self.spi.set_microphone(source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mic_buffer() {
+        let buffer = MicBuffer::default();
+        let mut source = buffer.clone();
+        buffer.push(&[100, -100]);
+        assert_eq!(source.sample(), 100);
+        assert_eq!(source.sample(), -100);
+        assert_eq!(source.sample(), -100);
+
+        assert_eq!(mic_to_adc(i16::MAX, Some(0)), 0xFFF);
+        assert_eq!(mic_to_adc(-0x100, Some(1)), 0x7E0);
+        assert_eq!(mic_to_adc(i16::MIN, Some(3)), 0);
+        assert_eq!(mic_to_adc(0x1000, None), MIC_SILENCE);
+    }
+}
@@ -0,0 +1,334 @@
+//! ROM patches
+//!
+//! Applies IPS, UPS and BPS patches, as translations and hacks ship, to
+//! the ROM image in memory. The file on disk is left alone. UPS and BPS
+//! carry CRC32s of the ROM they apply to and of the result, which are
+//! checked.
+use crate::emulator::Emulator;
+use crate::error::{EmuError, PatchChecksumSnafu, PatchInvalidSnafu, PatchUnknownFormatSnafu};
+use crate::util::crc32;
+
+/// IPS end marker, in place of a record offset.
+const IPS_EOF: &[u8; 3] = b"EOF";
+/// Largest DS ROM, 4 Gbit; bigger targets are malformed patches.
+const MAX_ROM_SIZE: usize = 0x2000_0000;
+/// Source, target and patch CRC32s ending UPS and BPS patches.
+const FOOTER_SIZE: usize = 12;
+
+/// Patch file format, told apart by the magic bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Ups,
+    Bps,
+}
+
+impl PatchFormat {
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        match patch {
+            [b'P', b'A', b'T', b'C', b'H', ..] => Some(Self::Ips),
+            [b'U', b'P', b'S', b'1', ..] => Some(Self::Ups),
+            [b'B', b'P', b'S', b'1', ..] => Some(Self::Bps),
+            _ => None,
+        }
+    }
+}
+
+/// Apply `patch` to `rom` and return the patched image.
+///
+/// # Errors
+///
+/// When the format is unknown, the patch is malformed, or the checksums
+/// show it was made for another ROM.
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, EmuError> {
+    match PatchFormat::detect(patch) {
+        Some(PatchFormat::Ips) => apply_ips(rom, patch).ok_or_else(|| PatchInvalidSnafu.build()),
+        Some(PatchFormat::Ups) => apply_ups(rom, patch),
+        Some(PatchFormat::Bps) => apply_bps(rom, patch),
+        None => PatchUnknownFormatSnafu.fail(),
+    }
+}
+
+/// Cursor over a patch, `None` past its end.
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    const fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    const fn at_end(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn be(&mut self, len: usize) -> Option<usize> {
+        Some(self.bytes(len)?.iter().fold(0, |n, &b| n << 8 | b as usize))
+    }
+
+    /// UPS/BPS variable length number: 7 bits per byte, the last one
+    /// flagged with bit 7, each continuation adding one.
+    fn varint(&mut self) -> Option<usize> {
+        let mut value = 0_usize;
+        let mut shift = 1_usize;
+        loop {
+            let byte = self.u8()?;
+            value = value.checked_add((byte as usize & 0x7F).checked_mul(shift)?)?;
+            if byte & 0x80 != 0 {
+                return Some(value);
+            }
+            shift = shift.checked_mul(0x80)?;
+            value = value.checked_add(shift)?;
+        }
+    }
+}
+
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Option<Vec<u8>> {
+    let mut target = rom.to_vec();
+    let mut r = Reader::new(patch, 5);
+    loop {
+        let record = r.bytes(3)?;
+        if record == IPS_EOF {
+            break;
+        }
+        let offset = record.iter().fold(0, |n, &b| n << 8 | b as usize);
+        match r.be(2)? {
+            // Run length record
+            0 => {
+                let end = offset + r.be(2)?;
+                let fill = r.u8()?;
+                if target.len() < end {
+                    target.resize(end, 0);
+                }
+                target[offset..end].fill(fill);
+            }
+            len => {
+                let data = r.bytes(len)?;
+                if target.len() < offset + len {
+                    target.resize(offset + len, 0);
+                }
+                target[offset..offset + len].copy_from_slice(data);
+            }
+        }
+    }
+    // Optional truncation size after the end marker
+    if let Some(size) = r.be(3) {
+        target.truncate(size);
+    }
+    Some(target)
+}
+
+/// Split off and check the UPS/BPS footer, returning the patch body and the
+/// expected target CRC32.
+fn check_footer<'a>(rom: &[u8], patch: &'a [u8]) -> Result<(&'a [u8], u32), EmuError> {
+    let Some(body_len) = patch.len().checked_sub(FOOTER_SIZE) else {
+        return PatchInvalidSnafu.fail();
+    };
+    let crc = |offset: usize| {
+        let bytes = &patch[body_len + offset..body_len + offset + 4];
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+    if crc32(&patch[..body_len + 8]) != crc(8) {
+        return PatchInvalidSnafu.fail();
+    }
+    if crc32(rom) != crc(0) {
+        return PatchChecksumSnafu.fail();
+    }
+    Ok((&patch[..body_len], crc(4)))
+}
+
+fn check_target(target: Vec<u8>, crc: u32) -> Result<Vec<u8>, EmuError> {
+    match crc32(&target) == crc {
+        true => Ok(target),
+        false => PatchInvalidSnafu.fail(),
+    }
+}
+
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, EmuError> {
+    let (body, target_crc) = check_footer(rom, patch)?;
+    let patched = || {
+        let mut r = Reader::new(body, 4);
+        let _source_size = r.varint()?;
+        let target_size = r.varint()?;
+        if target_size > MAX_ROM_SIZE {
+            return None;
+        }
+        let mut target = rom.to_vec();
+        target.resize(target_size, 0);
+        let mut pos = 0_usize;
+        while !r.at_end() {
+            pos = pos.checked_add(r.varint()?)?;
+            // XOR run up to and including a zero byte
+            loop {
+                let byte = r.u8()?;
+                if let Some(out) = target.get_mut(pos) {
+                    *out ^= byte;
+                }
+                pos += 1;
+                if byte == 0 {
+                    break;
+                }
+            }
+        }
+        Some(target)
+    };
+    check_target(
+        patched().ok_or_else(|| PatchInvalidSnafu.build())?,
+        target_crc,
+    )
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, EmuError> {
+    let (body, target_crc) = check_footer(rom, patch)?;
+    let patched = || {
+        let mut r = Reader::new(body, 4);
+        let _source_size = r.varint()?;
+        let target_size = r.varint()?;
+        let metadata_size = r.varint()?;
+        r.bytes(metadata_size)?;
+        if target_size > MAX_ROM_SIZE {
+            return None;
+        }
+
+        let mut target = Vec::with_capacity(target_size);
+        let (mut source_rel, mut target_rel) = (0_usize, 0_usize);
+        let relative = |r: &mut Reader<'_>, base: usize| {
+            let data = r.varint()?;
+            match data & 1 {
+                0 => base.checked_add(data >> 1),
+                _ => base.checked_sub(data >> 1),
+            }
+        };
+        while !r.at_end() {
+            let data = r.varint()?;
+            let len = (data >> 2) + 1;
+            match data & 3 {
+                // Source read: the ROM at the same position
+                0 => {
+                    let start = target.len();
+                    target.extend_from_slice(rom.get(start..start.checked_add(len)?)?);
+                }
+                // Target read: bytes from the patch
+                1 => target.extend_from_slice(r.bytes(len)?),
+                // Source copy
+                2 => {
+                    source_rel = relative(&mut r, source_rel)?;
+                    let end = source_rel.checked_add(len)?;
+                    target.extend_from_slice(rom.get(source_rel..end)?);
+                    source_rel = end;
+                }
+                // Target copy, may overlap what it produces
+                _ => {
+                    target_rel = relative(&mut r, target_rel)?;
+                    for _ in 0..len {
+                        target.push(*target.get(target_rel)?);
+                        target_rel += 1;
+                    }
+                }
+            }
+            if target.len() > target_size {
+                return None;
+            }
+        }
+        (target.len() == target_size).then_some(target)
+    };
+    check_target(
+        patched().ok_or_else(|| PatchInvalidSnafu.build())?,
+        target_crc,
+    )
+}
+
+impl Emulator {
+    /// Patch the loaded ROM with an IPS, UPS or BPS `patch` and restart it.
+    ///
+    /// # Errors
+    ///
+    /// See [`apply_patch`]; the ROM is left unchanged.
+    pub fn patch_rom(&mut self, patch: &[u8]) -> Result<(), EmuError> {
+        self.cart.rom = apply_patch(&self.cart.rom, patch)?;
+        self.cart.rom_size = self.cart.rom.len() as u64;
+        self.boot_rom();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut value: usize, out: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte | 0x80);
+                return;
+            }
+            out.push(byte);
+            value -= 1;
+        }
+    }
+
+    fn with_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend(crc32(source).to_le_bytes());
+        patch.extend(crc32(target).to_le_bytes());
+        patch.extend(crc32(&patch).to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn test_patches() {
+        let rom = b"Hello, world".to_vec();
+        let target = b"Hello, WORLD!!!".to_vec();
+
+        let mut ips = b"PATCH".to_vec();
+        ips.extend([0, 0, 7, 0, 5]);
+        ips.extend(b"WORLD");
+        ips.extend([0, 0, 12, 0, 0, 0, 3, b'!']);
+        ips.extend(IPS_EOF);
+        assert_eq!(apply_patch(&rom, &ips).unwrap(), target);
+
+        let mut ups = b"UPS1".to_vec();
+        varint(rom.len(), &mut ups);
+        varint(target.len(), &mut ups);
+        varint(7, &mut ups);
+        ups.extend([0x20; 5]);
+        ups.extend(b"!!!\0");
+        let ups = with_footer(ups, &rom, &target);
+        assert_eq!(apply_patch(&rom, &ups).unwrap(), target);
+        assert!(matches!(
+            apply_patch(b"Other", &ups),
+            Err(EmuError::PatchChecksum)
+        ));
+
+        let mut bps = b"BPS1".to_vec();
+        varint(rom.len(), &mut bps);
+        varint(target.len(), &mut bps);
+        varint(0, &mut bps);
+        // Source read 7, target read "WORLD!", target copy 2 from the "!"
+        varint(6 << 2, &mut bps);
+        varint((5 << 2) | 1, &mut bps);
+        bps.extend(b"WORLD!");
+        varint((1 << 2) | 3, &mut bps);
+        varint(12 << 1, &mut bps);
+        let bps = with_footer(bps, &rom, &target);
+        assert_eq!(apply_patch(&rom, &bps).unwrap(), target);
+
+        assert!(matches!(
+            apply_patch(&rom, b"junk"),
+            Err(EmuError::PatchUnknownFormat)
+        ));
+    }
+}
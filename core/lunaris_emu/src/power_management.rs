@@ -0,0 +1,221 @@
+//! SPI power management device
+//!
+//! Register access is two bytes: an index byte (bit 7 set for reads)
+//! followed by the data byte. Which registers exist depends on the console
+//! model; games probe register 4 to tell a DS Lite from an original DS.
+use lunaris_ds_free_bios::firmware::DSType;
+
+use crate::error::EmuError;
+use crate::savestate::{Savestate, StateReader, StateWriter};
+
+/// Control register bits
+const CONTROL_SOUND_AMP: u8 = 1 << 0;
+const CONTROL_SOUND_MUTE: u8 = 1 << 1;
+const CONTROL_BACKLIGHTS: u8 = 0x0C;
+const CONTROL_LED_BLINK: u8 = 1 << 4;
+const CONTROL_LED_FAST: u8 = 1 << 5;
+const CONTROL_SHUTDOWN: u8 = 1 << 6;
+
+/// Control register (0): sound amplifier, backlights, power LED, shutdown
+const REG_CONTROL: usize = 0;
+/// Battery status (1): bit 0 set when the battery is low
+const REG_BATTERY: usize = 1;
+/// Microphone amplifier enable (2)
+const REG_MIC_AMP: usize = 2;
+/// Microphone amplifier gain (3)
+const REG_MIC_GAIN: usize = 3;
+/// Backlight level (4), DS Lite only
+const REG_BACKLIGHT: usize = 4;
+
+/// Power LED state, control register bits 4-5
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerLed {
+    On,
+    BlinkSlow,
+    BlinkFast,
+}
+
+/// Power management chip
+#[derive(Debug)]
+pub struct PowerManagement {
+    model: DSType,
+    /// Index byte of the transfer in progress
+    index: Option<u8>,
+    regs: [u8; 5],
+    /// Shutdown bit written since the last [`PowerManagement::take_shutdown_request`]
+    shutdown_requested: bool,
+}
+
+impl Default for PowerManagement {
+    fn default() -> Self {
+        Self::new(DSType::Ds)
+    }
+}
+
+impl PowerManagement {
+    pub fn new(model: DSType) -> Self {
+        let mut pm = Self {
+            model,
+            index: None,
+            regs: [0; 5],
+            shutdown_requested: false,
+        };
+        pm.power_on();
+        pm
+    }
+
+    /// Reset to the power-on register values.
+    pub fn power_on(&mut self) {
+        self.index = None;
+        self.shutdown_requested = false;
+        // Sound amplifier and both backlights on
+        self.regs[REG_CONTROL] = 0x0D;
+        self.regs[REG_BATTERY] = 0;
+        self.regs[REG_MIC_AMP] = 0;
+        self.regs[REG_MIC_GAIN] = 0;
+        // Brightest level
+        self.regs[REG_BACKLIGHT] = 0x03;
+    }
+
+    pub fn set_model(&mut self, model: DSType) {
+        self.model = model;
+    }
+
+    /// Backlight level in `0..model.backlight_levels()`.
+    pub fn backlight_level(&self) -> u8 {
+        match self.model.is_lite() {
+            true => self.regs[REG_BACKLIGHT] & 0x3,
+            // On/off only
+            false => u8::from(self.regs[REG_CONTROL] & CONTROL_BACKLIGHTS != 0),
+        }
+    }
+
+    /// Whether the sound amplifier is on and not muted.
+    pub fn sound_enabled(&self) -> bool {
+        self.regs[REG_CONTROL] & (CONTROL_SOUND_AMP | CONTROL_SOUND_MUTE) == CONTROL_SOUND_AMP
+    }
+
+    /// Microphone amplifier gain as a power of two over 20x, `None` while
+    /// the amplifier is off.
+    pub fn mic_gain(&self) -> Option<u8> {
+        (self.regs[REG_MIC_AMP] & 1 != 0).then_some(self.regs[REG_MIC_GAIN] & 0x3)
+    }
+
+    pub fn power_led(&self) -> PowerLed {
+        let control = self.regs[REG_CONTROL];
+        match (
+            control & CONTROL_LED_BLINK != 0,
+            control & CONTROL_LED_FAST != 0,
+        ) {
+            (false, _) => PowerLed::On,
+            (true, false) => PowerLed::BlinkSlow,
+            (true, true) => PowerLed::BlinkFast,
+        }
+    }
+
+    /// Whether the shutdown bit was written since the last call.
+    pub fn take_shutdown_request(&mut self) -> bool {
+        core::mem::take(&mut self.shutdown_requested)
+    }
+
+    fn has_register(&self, index: usize) -> bool {
+        match index {
+            REG_CONTROL..=REG_MIC_GAIN => true,
+            REG_BACKLIGHT => self.model.is_lite(),
+            _ => false,
+        }
+    }
+
+    /// Exchange one byte over SPI.
+    pub fn transfer_data(&mut self, input: u8) -> u8 {
+        let Some(index) = self.index.take() else {
+            self.index = Some(input);
+            return 0;
+        };
+
+        let reg = (index & 0x7F) as usize;
+        if !self.has_register(reg) {
+            return 0;
+        }
+        if index & 0x80 != 0 {
+            return self.regs[reg];
+        }
+
+        if reg == REG_CONTROL && input & CONTROL_SHUTDOWN != 0 {
+            #[cfg(feature = "tracing")]
+            tracing::info!("Power management: shutdown requested");
+            self.shutdown_requested = true;
+        }
+        self.regs[reg] = match reg {
+            REG_BATTERY => self.regs[reg],
+            REG_MIC_AMP => input & 1,
+            REG_MIC_GAIN => input & 3,
+            // Bit 3 (external power) is read-only
+            REG_BACKLIGHT => (self.regs[reg] & 0x08) | (input & 0x07),
+            _ => input & 0x7F,
+        };
+        0
+    }
+}
+
+/// The model comes from the config.
+impl Savestate for PowerManagement {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.bytes(&self.regs);
+        w.bool(self.index.is_some());
+        w.u8(self.index.unwrap_or_default());
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        r.bytes(&mut self.regs)?;
+        let selected = r.bool()?;
+        let index = r.u8()?;
+        self.index = selected.then_some(index);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(pm: &mut PowerManagement, reg: u8) -> u8 {
+        pm.transfer_data(0x80 | reg);
+        pm.transfer_data(0)
+    }
+
+    #[test]
+    fn test_backlight_register_depends_on_model() {
+        let mut phat = PowerManagement::new(DSType::Ds);
+        phat.transfer_data(REG_BACKLIGHT as u8);
+        phat.transfer_data(0x01);
+        assert_eq!(read(&mut phat, REG_BACKLIGHT as u8), 0);
+        assert_eq!(read(&mut phat, REG_CONTROL as u8), 0x0D);
+        assert_eq!(phat.backlight_level(), 1);
+
+        let mut lite = PowerManagement::new(DSType::Lite);
+        lite.transfer_data(REG_BACKLIGHT as u8);
+        lite.transfer_data(0x01);
+        assert_eq!(read(&mut lite, REG_BACKLIGHT as u8), 0x01);
+        assert_eq!(lite.backlight_level(), 1);
+    }
+
+    #[test]
+    fn test_control_register() {
+        let mut pm = PowerManagement::default();
+        assert!(pm.sound_enabled());
+        assert_eq!(pm.power_led(), PowerLed::On);
+
+        // Mute, fast blink
+        pm.transfer_data(REG_CONTROL as u8);
+        pm.transfer_data(0x0D | CONTROL_SOUND_MUTE | CONTROL_LED_BLINK | CONTROL_LED_FAST);
+        assert!(!pm.sound_enabled());
+        assert_eq!(pm.power_led(), PowerLed::BlinkFast);
+        assert!(!pm.take_shutdown_request());
+
+        pm.transfer_data(REG_CONTROL as u8);
+        pm.transfer_data(CONTROL_SHUTDOWN);
+        assert!(pm.take_shutdown_request());
+        assert!(!pm.take_shutdown_request());
+    }
+}
@@ -2,14 +2,29 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 //! rtc.hpp
 //!
-/// Real Time Clock (RTC) for Nintendo DS
-/// Manages date, time, and alarm functionality via bit-banged serial protocol
-/// Alarm settings
+//! The S-3511A real-time clock, talked to by bit-banging RTC_CR
+//! (0x04000138). A transfer starts with a command byte naming one of eight
+//! registers and whether it is read, followed by its data bytes, all sent
+//! least significant bit first.
+//!
+//! The date and time follow the host clock plus an offset, which writes to
+//! the clock registers and [`RealTimeClock::set_offset`] adjust. Alarms and
+//! the per-minute interrupt are checked once per frame against it, and
+//! raise the ARM7 RTC interrupt when RCNT routes the /INT pin there.
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+use crate::emulator::Emulator;
+use crate::error::EmuError;
+use crate::interrupts::Interrupt;
+use crate::savestate::{Savestate, StateReader, StateWriter};
+
+/// Alarm settings, as the BCD register bytes; bit 7 of each enables its
+/// comparison.
 #[derive(Debug, Clone, Copy)]
 pub struct Alarm {
     /// Day of week (0=Sunday, 6=Saturday)
     pub day_of_week: u8,
-    /// Hour (0-23)
+    /// Hour, with bit 6 as PM flag in 12 hour mode
     pub hour: u8,
     /// Minute (0-59)
     pub minute: u8,
@@ -24,6 +39,13 @@ impl Alarm {
             minute: 0,
         }
     }
+
+    /// Whether every enabled field equals the clock registers given.
+    const fn matches(&self, day_of_week: u8, hour: u8, minute: u8, hour_mask: u8) -> bool {
+        (self.day_of_week & 0x80 == 0 || self.day_of_week & 0x7 == day_of_week)
+            && (self.hour & 0x80 == 0 || self.hour & hour_mask == hour & hour_mask)
+            && (self.minute & 0x80 == 0 || self.minute & 0x7F == minute)
+    }
 }
 
 impl Default for Alarm {
@@ -37,6 +59,30 @@ fn byte_to_bcd(byte: u8) -> u8 {
     (byte / 10 * 16) + (byte % 10)
 }
 
+const fn bcd_to_byte(bcd: u8) -> u8 {
+    (bcd >> 4) * 10 + (bcd & 0xF)
+}
+
+/// Local time of the host.
+fn host_now() -> NaiveDateTime {
+    chrono::Local::now().naive_local()
+}
+
+/// Stopped host clock, see [`RealTimeClock::use_fixed_clock`].
+fn fixed_clock() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2000, 1, 1)
+        .unwrap_or_default()
+        .and_time(NaiveTime::MIN)
+}
+
+/// Status register 1: 24 hour mode
+const STAT1_24_HOUR: u8 = 0x02;
+/// Status register 1: INT1 and INT2 raised, cleared by reading
+const STAT1_INT1: u8 = 0x10;
+const STAT1_INT2: u8 = 0x20;
+/// Status register 2: INT2 (alarm 2) enable
+const STAT2_INT2_ENABLE: u8 = 0x40;
+
 /// Real Time Clock
 /// Provides date/time tracking and alarm functionality
 #[derive(Debug)]
@@ -46,34 +92,35 @@ pub struct RealTimeClock {
     /// Status register 2
     stat2_reg: u8,
 
-    /// Current year (0-99, representing 2000-2099)
-    year: u8,
-    /// Current month (1-12)
-    month: u8,
-    /// Current day (1-31)
-    day: u8,
-    /// Current day of week (0=Sunday, 6=Saturday)
-    day_of_week: u8,
-
-    /// Current hour (0-23)
-    hour: u8,
-    /// Current minute (0-59)
-    minute: u8,
-    /// Current second (0-59)
-    second: u8,
+    /// Seconds the clock runs ahead of the host clock
+    offset: i64,
+    /// Source of host time, replaced in tests
+    host_clock: fn() -> NaiveDateTime,
+    /// Minute the interrupts were last checked in
+    last_minute: Option<i64>,
 
     /// Alarm 1 settings
     alarm1: Alarm,
     /// Alarm 2 settings
     alarm2: Alarm,
+    /// INT1 register outside of alarm mode: frequency duty setting
+    int1_frequency: u8,
+    /// Clock adjustment register
+    clock_adjust: u8,
+    /// Free register
+    free_reg: u8,
 
     // Bit-banging I/O state
     /// I/O register for serial communication
     io_reg: u16,
     /// Internal output buffer for serial data
     internal_output: [u8; 7],
+    /// Data bytes written after the command
+    internal_input: [u8; 7],
     /// Current command being processed
     command: u32,
+    /// Whether the current command reads
+    reading: bool,
     /// Input data accumulator
     input: u32,
     /// Current bit position in input
@@ -99,21 +146,21 @@ impl RealTimeClock {
             stat1_reg: 0,
             stat2_reg: 0,
 
-            year: 0,
-            month: 1,
-            day: 1,
-            day_of_week: 0,
-
-            hour: 0,
-            minute: 0,
-            second: 0,
+            offset: 0,
+            host_clock: host_now,
+            last_minute: None,
 
             alarm1: Alarm::new(),
             alarm2: Alarm::new(),
+            int1_frequency: 0,
+            clock_adjust: 0,
+            free_reg: 0,
 
             io_reg: 0,
             internal_output: [0u8; 7],
+            internal_input: [0u8; 7],
             command: 0,
+            reading: false,
             input: 0,
             input_bit_num: 0,
             input_index: 0,
@@ -122,78 +169,189 @@ impl RealTimeClock {
         }
     }
 
-    /// Initialize RTC with default values
+    /// Initialize RTC registers; the clock offset is kept.
     pub fn init(&mut self) {
-        // Initialize to default date/time (2000-01-01 00:00:00)
-
-        //Place a hardcoded date into the RTC
-        //For those curious, this is January 1st, 2005, a couple of months after the release of the NDS
-        self.year = 0x05;
-        self.month = 0x1;
-        self.day = 0x1;
-        self.day_of_week = 0x0;
-
-        //Set the time to midnight
-        self.hour = 0x12;
-        self.minute = 0x0;
-        self.second = 0x0;
-
         self.io_reg = 0;
         self.stat1_reg = 0;
         self.stat2_reg = 0;
+        self.alarm1 = Alarm::new();
+        self.alarm2 = Alarm::new();
+        self.int1_frequency = 0;
+        self.clock_adjust = 0;
+        self.free_reg = 0;
+        self.last_minute = None;
     }
 
-    /// Process input data from serial protocol
-    pub fn interpret_input(&mut self) {
-        if self.input_index == 0 {
-            self.command = (self.input & 0x70) >> 4;
+    /// Seconds the clock runs ahead of the host clock (negative: behind).
+    pub const fn offset(&self) -> i64 {
+        self.offset
+    }
 
-            if (self.input & 0x80) != 0 {
-                match self.command {
-                    0 => self.internal_output[0] = self.stat1_reg,
-                    1 => {
-                        if (self.stat2_reg & 0x4) != 0 {
-                            self.internal_output[0] = self.alarm1.day_of_week;
-                            self.internal_output[1] = self.alarm1.hour;
-                            self.internal_output[2] = self.alarm1.minute;
-                        } else {
-                            self.internal_output[0] = self.alarm1.minute;
-                        }
-                    }
-                    2 => {
-                        use chrono::{Datelike, Local, Timelike};
+    /// Set the clock to the host clock plus `seconds`.
+    pub const fn set_offset(&mut self, seconds: i64) {
+        self.offset = seconds;
+    }
 
-                        let now = Local::now();
+    /// Stop following the host clock: the clock stands still at
+    /// 2000-01-01 00:00:00 plus the offset, so runs are reproducible.
+    pub fn use_fixed_clock(&mut self) {
+        self.host_clock = fixed_clock;
+    }
 
-                        // tm_year - 100
-                        // C: tm_year = years since 1900
-                        // e.g.: 2025 → 125 → 125 - 100 = 25
-                        self.internal_output[0] = byte_to_bcd((now.year() - 2000) as u8);
+    /// Current date and time of the clock.
+    pub fn now(&self) -> NaiveDateTime {
+        (self.host_clock)() + Duration::seconds(self.offset)
+    }
 
-                        // tm_mon + 1 (1 based index)
-                        self.internal_output[1] = byte_to_bcd(now.month() as u8);
+    /// Set the clock to `time`, keeping it running from there.
+    pub fn set_now(&mut self, time: NaiveDateTime) {
+        self.offset = (time - (self.host_clock)()).num_seconds();
+    }
 
-                        // tm_mday
-                        self.internal_output[2] = byte_to_bcd(now.day() as u8);
+    /// Hour register value: BCD in 12 or 24 hour mode, with the PM flag in
+    /// bit 6 either way.
+    fn hour_register(&self, hour: u8) -> u8 {
+        let value = match self.stat1_reg & STAT1_24_HOUR {
+            0 => byte_to_bcd(hour % 12),
+            _ => byte_to_bcd(hour),
+        };
+        value | ((hour >= 12) as u8) << 6
+    }
 
-                        // tm_wday (C: 0 = Sunday)
-                        self.internal_output[3] =
-                            byte_to_bcd(now.weekday().num_days_from_sunday() as u8);
+    /// Hour of a written hour register.
+    const fn hour_from_register(&self, value: u8) -> u8 {
+        let hour = bcd_to_byte(value & 0x3F);
+        match self.stat1_reg & STAT1_24_HOUR {
+            0 => hour % 12 + if value & 0x40 != 0 { 12 } else { 0 },
+            _ => hour,
+        }
+    }
 
-                        // hour
-                        let hour = now.hour() as u8;
+    /// Date and time registers, in the order command 2 sends them.
+    fn date_time_registers(&self) -> [u8; 7] {
+        let now = self.now();
+        [
+            byte_to_bcd(now.year().rem_euclid(100) as u8),
+            byte_to_bcd(now.month() as u8),
+            byte_to_bcd(now.day() as u8),
+            byte_to_bcd(now.weekday().num_days_from_sunday() as u8),
+            self.hour_register(now.hour() as u8),
+            byte_to_bcd(now.minute() as u8),
+            byte_to_bcd(now.second() as u8),
+        ]
+    }
 
-                        if (self.stat1_reg & 0x2) > 0 {
-                            self.internal_output[4] = byte_to_bcd(hour); // 24 hour mode
-                        } else {
-                            self.internal_output[4] = byte_to_bcd(hour % 12); // 12 hour mode
-                        }
+    /// Time of written hour, minute and second registers, if valid.
+    fn time_from_registers(&self, registers: &[u8]) -> Option<NaiveTime> {
+        NaiveTime::from_hms_opt(
+            self.hour_from_register(registers[0]) as u32,
+            bcd_to_byte(registers[1] & 0x7F) as u32,
+            bcd_to_byte(registers[2] & 0x7F) as u32,
+        )
+    }
 
-                        self.internal_output[4] |= ((hour >= 12) as u8) << 6; // PM flag (bit 6)
-                        self.internal_output[5] = byte_to_bcd(now.minute() as u8); // minute
-                        self.internal_output[6] = byte_to_bcd(now.second() as u8); // second
-                    }
+    /// Set the clock from written registers. The day of week follows from
+    /// the date and is ignored.
+    fn write_date_time(&mut self, date: Option<[u8; 3]>, time: &[u8]) {
+        let date = match date {
+            Some([year, month, day]) => NaiveDate::from_ymd_opt(
+                2000 + bcd_to_byte(year) as i32,
+                bcd_to_byte(month & 0x1F) as u32,
+                bcd_to_byte(day & 0x3F) as u32,
+            ),
+            None => Some(self.now().date()),
+        };
+        match (date, self.time_from_registers(time)) {
+            (Some(date), Some(time)) => self.set_now(date.and_time(time)),
+            _ => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!("Invalid RTC date/time written: {date:?} {time:02X?}");
+            }
+        }
+    }
 
+    /// Reset through status register 1 bit 0: registers cleared and the
+    /// clock back to 2000-01-01 00:00:00.
+    fn reset(&mut self) {
+        self.init();
+        self.set_now(
+            NaiveDate::from_ymd_opt(2000, 1, 1)
+                .unwrap()
+                .and_time(NaiveTime::MIN),
+        );
+    }
+
+    /// INT1 mode, status register 2 bits 0-2 (bit 3 selects 32kHz output).
+    const fn int1_mode(&self) -> u8 {
+        self.stat2_reg & 0x7
+    }
+
+    /// Check the interrupts once per minute of the clock. Returns whether
+    /// an interrupt line was raised.
+    pub(crate) fn update(&mut self) -> bool {
+        let now = self.now();
+        let minute = now.and_utc().timestamp().div_euclid(60);
+        let new_minute = self.last_minute.is_some_and(|last| last != minute);
+        self.last_minute = Some(minute);
+        if !new_minute {
+            return false;
+        }
+
+        let day_of_week = now.weekday().num_days_from_sunday() as u8;
+        let hour = self.hour_register(now.hour() as u8);
+        let minute = byte_to_bcd(now.minute() as u8);
+        let hour_mask = match self.stat1_reg & STAT1_24_HOUR {
+            0 => 0x7F,
+            _ => 0x3F,
+        };
+
+        let mut raised = 0;
+        match self.int1_mode() {
+            // Per-minute edge and steady interrupts
+            2 | 3 | 5 => raised |= STAT1_INT1,
+            4 if self.alarm1.matches(day_of_week, hour, minute, hour_mask) => raised |= STAT1_INT1,
+            // The selected frequency interrupt is not emulated
+            _ => {}
+        }
+        if self.stat2_reg & STAT2_INT2_ENABLE != 0
+            && self.alarm2.matches(day_of_week, hour, minute, hour_mask)
+        {
+            raised |= STAT1_INT2;
+        }
+
+        self.stat1_reg |= raised;
+        raised != 0
+    }
+
+    /// Process input data from serial protocol
+    pub fn interpret_input(&mut self) {
+        if self.input_index == 0 {
+            // The fixed code 0110 comes first; sent MSB first, it ends up
+            // in the high nibble
+            let mut input = self.input as u8;
+            if input & 0xF0 == 0x60 {
+                input = input.reverse_bits();
+            }
+            self.command = ((input & 0x70) >> 4) as u32;
+            self.reading = (input & 0x80) != 0;
+
+            if self.reading {
+                match self.command {
+                    0 => {
+                        self.internal_output[0] = self.stat1_reg;
+                        // Interrupt and power flags clear on read
+                        self.stat1_reg &= 0x0F;
+                    }
+                    1 => match self.int1_mode() {
+                        4 => {
+                            self.internal_output[0] = self.alarm1.day_of_week;
+                            self.internal_output[1] = self.alarm1.hour;
+                            self.internal_output[2] = self.alarm1.minute;
+                        }
+                        _ => self.internal_output[0] = self.int1_frequency,
+                    },
+                    2 => self.internal_output = self.date_time_registers(),
+                    3 => self.internal_output[0] = self.clock_adjust,
                     4 => self.internal_output[0] = self.stat2_reg,
                     5 => {
                         self.internal_output[0] = self.alarm2.day_of_week;
@@ -201,58 +359,51 @@ impl RealTimeClock {
                         self.internal_output[2] = self.alarm2.minute;
                     }
                     6 => {
-                        self.internal_output[0] = self.hour;
-                        self.internal_output[1] = self.minute;
-                        self.internal_output[2] = self.second;
-                    }
-
-                    _ => {
-                        #[cfg(feature = "tracing")]
-                        tracing::error!("Unrecognized read from RTC command {}", self.command);
+                        let registers = self.date_time_registers();
+                        self.internal_output[..3].copy_from_slice(&registers[4..]);
                     }
+                    _ => self.internal_output[0] = self.free_reg,
                 }
             }
-        } else {
-            match self.command {
-                0 => {
-                    self.stat1_reg = self.input as u8;
-                }
-
-                1 => {
-                    if (self.stat2_reg & 0x4) != 0 {
-                        match self.input_index {
-                            1 => self.alarm1.day_of_week = self.input as u8,
-                            2 => self.alarm1.hour = self.input as u8,
-                            3 => self.alarm1.minute = self.input as u8,
-                            _ => {}
-                        }
-                    } else if self.input_index == 1 {
-                        self.alarm1.minute = self.input as u8;
-                    }
-                }
-                2 => {} // do nothing
-                3 => {} // TODO: clock adjustment
-
-                4 => {
-                    if self.input_index == 1 {
-                        self.stat2_reg = self.input as u8;
+        } else if !self.reading {
+            let index = self.input_index as usize - 1;
+            let value = self.input as u8;
+            if let Some(slot) = self.internal_input.get_mut(index) {
+                *slot = value;
+            }
+            match (self.command, index) {
+                (0, 0) => {
+                    if value & 0x01 != 0 {
+                        self.reset();
                     }
+                    self.stat1_reg = (self.stat1_reg & 0xF0) | (value & 0x0E);
                 }
 
-                5 => match self.input_index {
-                    1 => self.alarm2.day_of_week = self.input as u8,
-                    2 => self.alarm2.hour = self.input as u8,
-                    3 => self.alarm2.minute = self.input as u8,
+                (1, _) if self.int1_mode() == 4 => match index {
+                    0 => self.alarm1.day_of_week = value,
+                    1 => self.alarm1.hour = value,
+                    2 => self.alarm1.minute = value,
                     _ => {}
                 },
-                _ => {
-                    #[cfg(feature = "tracing")]
-                    tracing::error!(
-                        "Unrecognized write ${:02X} to RTC command {}",
-                        self.input,
-                        self.command
-                    );
+                (1, 0) => self.int1_frequency = value,
+
+                (2, 6) => {
+                    let [year, month, day, _, hour, minute, second] = self.internal_input;
+                    self.write_date_time(Some([year, month, day]), &[hour, minute, second]);
                 }
+                (3, 0) => self.clock_adjust = value,
+                (4, 0) => self.stat2_reg = value,
+
+                (5, 0) => self.alarm2.day_of_week = value,
+                (5, 1) => self.alarm2.hour = value,
+                (5, 2) => self.alarm2.minute = value,
+
+                (6, 2) => {
+                    let time = self.internal_input;
+                    self.write_date_time(None, &time[..3]);
+                }
+                (7, 0) => self.free_reg = value,
+                _ => {}
             }
         }
 
@@ -311,7 +462,7 @@ impl RealTimeClock {
             // Advance to next output byte
             if self.output_bit_num == 8 {
                 self.output_bit_num = 0;
-                if self.output_index < 7 {
+                if self.output_index < 6 {
                     self.output_index += 1;
                 }
             }
@@ -328,3 +479,197 @@ impl RealTimeClock {
         }
     }
 }
+
+/// The host clock stays; the offset from it is saved.
+impl Savestate for RealTimeClock {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u64(self.offset as u64);
+        w.bool(self.last_minute.is_some());
+        w.u64(self.last_minute.unwrap_or_default() as u64);
+        for alarm in [&self.alarm1, &self.alarm2] {
+            w.bytes(&[alarm.day_of_week, alarm.hour, alarm.minute]);
+        }
+        w.bytes(&[
+            self.stat1_reg,
+            self.stat2_reg,
+            self.int1_frequency,
+            self.clock_adjust,
+            self.free_reg,
+        ]);
+
+        // Transfer in progress
+        w.u16(self.io_reg);
+        w.bytes(&self.internal_output);
+        w.bytes(&self.internal_input);
+        w.bool(self.reading);
+        for value in [
+            self.command,
+            self.input,
+            self.input_bit_num,
+            self.input_index,
+            self.output_bit_num,
+            self.output_index,
+        ] {
+            w.u32(value);
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.offset = r.u64()? as i64;
+        let has_last_minute = r.bool()?;
+        let last_minute = r.u64()? as i64;
+        self.last_minute = has_last_minute.then_some(last_minute);
+        for alarm in [&mut self.alarm1, &mut self.alarm2] {
+            alarm.day_of_week = r.u8()?;
+            alarm.hour = r.u8()?;
+            alarm.minute = r.u8()?;
+        }
+        for value in [
+            &mut self.stat1_reg,
+            &mut self.stat2_reg,
+            &mut self.int1_frequency,
+            &mut self.clock_adjust,
+            &mut self.free_reg,
+        ] {
+            *value = r.u8()?;
+        }
+
+        self.io_reg = r.u16()?;
+        r.bytes(&mut self.internal_output)?;
+        r.bytes(&mut self.internal_input)?;
+        self.reading = r.bool()?;
+        for value in [
+            &mut self.command,
+            &mut self.input,
+            &mut self.input_bit_num,
+            &mut self.input_index,
+            &mut self.output_bit_num,
+            &mut self.output_index,
+        ] {
+            *value = r.u32()?;
+        }
+        Ok(())
+    }
+}
+
+impl Emulator {
+    /// Check the RTC interrupts, once per frame. The /INT pin reaches the
+    /// ARM7 through SI when RCNT is in GPIO mode with its interrupt enabled.
+    pub(crate) fn rtc_end_frame(&mut self) {
+        if self.rtc.update() && self.r_cnt & 0xC100 == 0x8100 {
+            self.request_interrupt7(Interrupt::Rtc);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Host time of the tests: Thursday 2024-02-29 12:00:00.
+    fn test_clock() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 2, 29)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
+    /// Bit-bang command `command` (write) with `data`.
+    fn write_command(rtc: &mut RealTimeClock, command: u8, data: &[u8]) {
+        rtc.write(0x16, false);
+        for byte in [0x06 | command << 4].iter().chain(data) {
+            for bit in 0..8 {
+                let value = (byte >> bit) as u16 & 1;
+                rtc.write(0x14 | value, false);
+                rtc.write(0x16 | value, false);
+            }
+        }
+        rtc.write(0x12, false);
+    }
+
+    /// Bit-bang command `command` (read), returning `len` bytes.
+    fn read_command(rtc: &mut RealTimeClock, command: u8, len: usize) -> Vec<u8> {
+        rtc.write(0x16, false);
+        for bit in 0..8 {
+            let value = ((0x86 | command << 4) >> bit) as u16 & 1;
+            rtc.write(0x14 | value, false);
+            rtc.write(0x16 | value, false);
+        }
+        let bytes = (0..len)
+            .map(|_| {
+                (0..8).fold(0, |byte, bit| {
+                    rtc.write(0x04, false);
+                    let value = (rtc.read() & 1) as u8;
+                    rtc.write(0x06, false);
+                    byte | value << bit
+                })
+            })
+            .collect();
+        rtc.write(0x02, false);
+        bytes
+    }
+
+    #[test]
+    fn test_date_time() {
+        let mut rtc = RealTimeClock::new();
+        rtc.host_clock = test_clock;
+        // 12 hour mode: 12:00 is 0 PM
+        assert_eq!(
+            read_command(&mut rtc, 2, 7),
+            [0x24, 0x02, 0x29, 0x04, 0x40, 0x00, 0x00]
+        );
+
+        // 24 hour mode; Wednesday 2025-12-31 23:59:58
+        write_command(&mut rtc, 0, &[STAT1_24_HOUR]);
+        write_command(&mut rtc, 2, &[0x25, 0x12, 0x31, 0x00, 0x23, 0x59, 0x58]);
+        assert_eq!(
+            read_command(&mut rtc, 2, 7),
+            [0x25, 0x12, 0x31, 0x03, 0x63, 0x59, 0x58]
+        );
+        assert_eq!(read_command(&mut rtc, 6, 3), [0x63, 0x59, 0x58]);
+
+        // Time only, 08:30:00
+        write_command(&mut rtc, 6, &[0x08, 0x30, 0x00]);
+        assert_eq!(rtc.now().to_string(), "2025-12-31 08:30:00");
+
+        // Reset
+        write_command(&mut rtc, 0, &[0x01]);
+        assert_eq!(rtc.now().to_string(), "2000-01-01 00:00:00");
+        assert_eq!(read_command(&mut rtc, 0, 1), [0]);
+    }
+
+    #[test]
+    fn test_alarm_interrupt() {
+        let mut emu = Box::new(Emulator::new());
+        emu.rtc.host_clock = test_clock;
+        emu.r_cnt = 0x8100;
+        // 24 hour mode, INT2 enabled; alarm 2 on Thursdays at 12:01
+        write_command(&mut emu.rtc, 0, &[STAT1_24_HOUR]);
+        write_command(&mut emu.rtc, 4, &[STAT2_INT2_ENABLE]);
+        write_command(&mut emu.rtc, 5, &[0x84, 0x92, 0x81]);
+        assert_eq!(read_command(&mut emu.rtc, 5, 3), [0x84, 0x92, 0x81]);
+
+        emu.rtc_end_frame();
+        emu.rtc.set_offset(30);
+        emu.rtc_end_frame();
+        assert_eq!(emu.int7_reg.irq_flags, 0);
+
+        emu.rtc.set_offset(60);
+        emu.rtc_end_frame();
+        assert_eq!(emu.int7_reg.irq_flags, 1 << Interrupt::Rtc as u32);
+        assert_eq!(
+            read_command(&mut emu.rtc, 0, 1),
+            [STAT1_INT2 | STAT1_24_HOUR]
+        );
+        assert_eq!(read_command(&mut emu.rtc, 0, 1), [STAT1_24_HOUR]);
+
+        // Per-minute interrupt on INT1
+        write_command(&mut emu.rtc, 4, &[0x02]);
+        emu.rtc.set_offset(120);
+        assert!(emu.rtc.update());
+        assert_eq!(
+            read_command(&mut emu.rtc, 0, 1),
+            [STAT1_INT1 | STAT1_24_HOUR]
+        );
+    }
+}
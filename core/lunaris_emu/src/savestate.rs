@@ -0,0 +1,147 @@
+//! Savestate serialization
+//!
+//! Components write their state as a flat little-endian byte stream through
+//! [`StateWriter`] and read it back in the same order with [`StateReader`].
+//! There is no self-description; the stream is versioned as a whole.
+use snafu::OptionExt as _;
+
+use crate::error::{EmuError, SavestateTruncatedSnafu};
+
+/// Component that can be saved to and restored from a savestate.
+pub trait Savestate {
+    fn save_state(&self, w: &mut StateWriter);
+
+    /// Restore state written by [`Savestate::save_state`].
+    ///
+    /// # Errors
+    /// If the data ends early. `self` may be partially restored then.
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError>;
+}
+
+/// Little-endian savestate writer.
+#[derive(Debug, Default)]
+pub struct StateWriter {
+    buf: Vec<u8>,
+}
+
+impl StateWriter {
+    pub const fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.buf.push(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Length-prefixed list of words, read back with [`StateReader::u32_list`].
+    pub fn u32_list(&mut self, words: impl ExactSizeIterator<Item = u32>) {
+        self.u32(words.len() as u32);
+        for word in words {
+            self.u32(word);
+        }
+    }
+}
+
+/// Little-endian savestate reader.
+#[derive(Debug)]
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Bytes not consumed yet.
+    pub const fn remaining(&self) -> usize {
+        self.data.len()
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], EmuError> {
+        let (head, tail) = self
+            .data
+            .split_first_chunk::<N>()
+            .context(SavestateTruncatedSnafu)?;
+        self.data = tail;
+        Ok(*head)
+    }
+
+    /// # Errors
+    /// If the data ends early.
+    pub fn u8(&mut self) -> Result<u8, EmuError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    /// # Errors
+    /// If the data ends early.
+    pub fn bool(&mut self) -> Result<bool, EmuError> {
+        Ok(self.u8()? != 0)
+    }
+
+    /// # Errors
+    /// If the data ends early.
+    pub fn u16(&mut self) -> Result<u16, EmuError> {
+        self.take().map(u16::from_le_bytes)
+    }
+
+    /// # Errors
+    /// If the data ends early.
+    pub fn u32(&mut self) -> Result<u32, EmuError> {
+        self.take().map(u32::from_le_bytes)
+    }
+
+    /// # Errors
+    /// If the data ends early.
+    pub fn u64(&mut self) -> Result<u64, EmuError> {
+        self.take().map(u64::from_le_bytes)
+    }
+
+    /// Fill `out` completely.
+    ///
+    /// # Errors
+    /// If the data ends early.
+    pub fn bytes(&mut self, out: &mut [u8]) -> Result<(), EmuError> {
+        let (head, tail) = self
+            .data
+            .split_at_checked(out.len())
+            .context(SavestateTruncatedSnafu)?;
+        out.copy_from_slice(head);
+        self.data = tail;
+        Ok(())
+    }
+
+    /// # Errors
+    /// If the data ends early.
+    pub fn u32_list<T: FromIterator<u32>>(&mut self) -> Result<T, EmuError> {
+        let len = self.u32()? as usize;
+        if len > self.remaining() / 4 {
+            return SavestateTruncatedSnafu.fail();
+        }
+        (0..len).map(|_| self.u32()).collect()
+    }
+}
@@ -0,0 +1,257 @@
+//! Event scheduler
+//!
+//! Events are due at a system timestamp and come out of a binary heap in
+//! order, ties in the order they were scheduled. Each [`EventKind`] is
+//! pending at most once: scheduling it again moves it and cancelling drops
+//! it. The heap entries left behind are skipped when they reach the top.
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use snafu::OptionExt as _;
+
+use crate::error::{EmuError, SavestateInvalidSnafu};
+use crate::savestate::{Savestate, StateReader, StateWriter};
+
+/// What to do when an event is due.
+///
+/// A subsystem that needs to run at an exact time adds its kind here and
+/// its handler to `Emulator::handle_scheduled_event`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EventKind {
+    /// End of the visible part of a line
+    HBlankStart,
+    /// End of a line
+    HBlankEnd,
+    /// Run the active DMA channels
+    Dma,
+    /// Mix the next SPU sample
+    SpuSample,
+    /// End of the running geometry command
+    Gx,
+    /// Next word of a cartridge ROM transfer
+    CartTransfer,
+    /// End of an SPI transfer
+    Serial,
+    /// Next overflow of the ARM7 timers
+    TimerOverflow7,
+    /// Next overflow of the ARM9 timers
+    TimerOverflow9,
+}
+
+impl EventKind {
+    pub const ALL: [Self; 9] = [
+        Self::HBlankStart,
+        Self::HBlankEnd,
+        Self::Dma,
+        Self::SpuSample,
+        Self::Gx,
+        Self::CartTransfer,
+        Self::Serial,
+        Self::TimerOverflow7,
+        Self::TimerOverflow9,
+    ];
+
+    const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Entry {
+    time: u64,
+    /// Keeps events due at the same time in the order they were scheduled
+    seq: u64,
+    kind: EventKind,
+}
+
+/// Bytes of one pending event in a savestate: kind, time and sequence.
+pub const EVENT_STATE_SIZE: usize = 1 + 8 + 8;
+
+/// Heap entries beyond which moved and cancelled entries are dropped.
+const COMPACT_THRESHOLD: usize = 16 * EventKind::ALL.len();
+
+/// Pending events by system timestamp.
+#[derive(Debug, Clone, Default)]
+pub struct Scheduler {
+    queue: BinaryHeap<Reverse<Entry>>,
+    /// The live entry of each kind
+    pending: [Option<Entry>; EventKind::ALL.len()],
+    next_seq: u64,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop every pending event.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Schedule `kind` at `time`, replacing its pending event if any.
+    pub fn schedule(&mut self, kind: EventKind, time: u64) {
+        let entry = Entry {
+            time,
+            seq: self.next_seq,
+            kind,
+        };
+        self.next_seq += 1;
+        self.pending[kind.index()] = Some(entry);
+        self.queue.push(Reverse(entry));
+
+        if self.queue.len() > COMPACT_THRESHOLD {
+            self.queue = self
+                .pending
+                .iter()
+                .flatten()
+                .copied()
+                .map(Reverse)
+                .collect();
+        }
+    }
+
+    /// Drop the pending event of `kind`.
+    pub fn cancel(&mut self, kind: EventKind) {
+        self.pending[kind.index()] = None;
+    }
+
+    /// When `kind` is due, if it is pending.
+    pub fn due_time(&self, kind: EventKind) -> Option<u64> {
+        self.pending[kind.index()].map(|entry| entry.time)
+    }
+
+    /// Number of pending events.
+    pub fn pending_count(&self) -> usize {
+        self.pending.iter().flatten().count()
+    }
+
+    /// When the earliest pending event is due.
+    pub fn next_time(&mut self) -> Option<u64> {
+        self.skip_stale();
+        self.queue.peek().map(|Reverse(entry)| entry.time)
+    }
+
+    /// Take the earliest event due at or before `now`, with its due time.
+    pub fn pop_due(&mut self, now: u64) -> Option<(EventKind, u64)> {
+        self.skip_stale();
+        let Reverse(entry) = *self.queue.peek()?;
+        if entry.time > now {
+            return None;
+        }
+        self.queue.pop();
+        self.pending[entry.kind.index()] = None;
+        Some((entry.kind, entry.time))
+    }
+
+    fn skip_stale(&mut self) {
+        while let Some(Reverse(entry)) = self.queue.peek() {
+            if self.pending[entry.kind.index()] == Some(*entry) {
+                break;
+            }
+            self.queue.pop();
+        }
+    }
+}
+
+impl Savestate for Scheduler {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u64(self.next_seq);
+        let pending: Vec<_> = self.pending.iter().flatten().collect();
+        w.u32(pending.len() as u32);
+        for entry in pending {
+            w.u8(entry.kind.index() as u8);
+            w.u64(entry.time);
+            w.u64(entry.seq);
+        }
+    }
+
+    /// Pending events come back with their sequence numbers, so ties still
+    /// come out in the order they were scheduled.
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.clear();
+        self.next_seq = r.u64()?;
+        let count = r.u32()? as usize;
+        if count > EventKind::ALL.len() {
+            return SavestateInvalidSnafu.fail();
+        }
+        for _ in 0..count {
+            let kind = EventKind::ALL.get(r.u8()? as usize).copied();
+            let entry = Entry {
+                time: r.u64()?,
+                seq: r.u64()?,
+                kind: kind.context(SavestateInvalidSnafu)?,
+            };
+            if entry.seq >= self.next_seq || self.pending[entry.kind.index()].is_some() {
+                return SavestateInvalidSnafu.fail();
+            }
+            self.pending[entry.kind.index()] = Some(entry);
+            self.queue.push(Reverse(entry));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduler_order_and_cancel() {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule(EventKind::HBlankEnd, 30);
+        scheduler.schedule(EventKind::Dma, 10);
+        scheduler.schedule(EventKind::HBlankStart, 10);
+        assert_eq!(scheduler.next_time(), Some(10));
+
+        // Ties come out in scheduling order, later events wait
+        assert_eq!(scheduler.pop_due(20), Some((EventKind::Dma, 10)));
+        assert_eq!(scheduler.pop_due(20), Some((EventKind::HBlankStart, 10)));
+        assert_eq!(scheduler.pop_due(20), None);
+
+        // Moving and cancelling leave no trace
+        scheduler.schedule(EventKind::HBlankEnd, 40);
+        scheduler.schedule(EventKind::Dma, 35);
+        scheduler.cancel(EventKind::Dma);
+        assert_eq!(scheduler.due_time(EventKind::Dma), None);
+        assert_eq!(scheduler.next_time(), Some(40));
+        assert_eq!(scheduler.pop_due(100), Some((EventKind::HBlankEnd, 40)));
+        assert_eq!(scheduler.pop_due(100), None);
+        assert_eq!(scheduler.next_time(), None);
+    }
+
+    #[test]
+    fn test_scheduler_compacts() {
+        let mut scheduler = Scheduler::new();
+        for time in 0..1000 {
+            scheduler.schedule(EventKind::Dma, 1000 - time);
+        }
+        assert!(scheduler.queue.len() <= COMPACT_THRESHOLD);
+        assert_eq!(scheduler.pop_due(u64::MAX), Some((EventKind::Dma, 1)));
+        assert_eq!(scheduler.pop_due(u64::MAX), None);
+    }
+    #[test]
+    fn test_scheduler_state_keeps_order() {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule(EventKind::Serial, 10);
+        scheduler.schedule(EventKind::Dma, 20);
+        scheduler.schedule(EventKind::HBlankStart, 10);
+        scheduler.schedule(EventKind::Gx, 5);
+        scheduler.cancel(EventKind::Gx);
+        let mut w = StateWriter::new();
+        scheduler.save_state(&mut w);
+        let data = w.into_inner();
+
+        let mut loaded = Scheduler::new();
+        loaded.load_state(&mut StateReader::new(&data)).unwrap();
+        assert_eq!(loaded.due_time(EventKind::Gx), None);
+        // The tie still comes out in scheduling order, not kind order, and
+        // new events go after the restored ones
+        loaded.schedule(EventKind::SpuSample, 20);
+        assert_eq!(loaded.pop_due(30), Some((EventKind::Serial, 10)));
+        assert_eq!(loaded.pop_due(30), Some((EventKind::HBlankStart, 10)));
+        assert_eq!(loaded.pop_due(30), Some((EventKind::Dma, 20)));
+        assert_eq!(loaded.pop_due(30), Some((EventKind::SpuSample, 20)));
+        assert_eq!(loaded.pop_due(30), None);
+    }
+}
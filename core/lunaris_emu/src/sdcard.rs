@@ -0,0 +1,398 @@
+//! SD card image
+//!
+//! Sector-level access to a raw FAT image on the host. This is the backing
+//! store for DLDI homebrew and DSi mode; writes are kept in memory as dirty
+//! sectors and written back according to [`FlushPolicy`], or never for a
+//! sandboxed card (see [`SdCard::open_sandboxed`]).
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read as _, Seek as _, SeekFrom, Write as _};
+use std::path::{Path, PathBuf};
+
+use snafu::{OptionExt as _, ResultExt as _};
+
+use crate::emulator::Emulator;
+use crate::error::{
+    EmuError, FailedReadFileSnafu, FailedWriteFileSnafu, SdCardNotMountedSnafu,
+    SdSectorOutOfRangeSnafu,
+};
+
+/// Size of one SD card sector in bytes.
+pub const SD_SECTOR_SIZE: usize = 512;
+
+/// When dirty sectors are written back to the host image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Every write goes straight to the image.
+    WriteThrough,
+    /// Dirty sectors are written at the end of every emulated frame.
+    #[default]
+    EveryFrame,
+    /// Only on [`SdCard::flush`] or unmount.
+    Manual,
+}
+
+/// Raw SD card image opened from the host filesystem.
+#[derive(Debug)]
+pub struct SdCard {
+    path: PathBuf,
+    file: File,
+    sector_count: u64,
+    read_only: bool,
+    /// Writes stay in `dirty` for good; the image is opened read-only.
+    sandboxed: bool,
+    policy: FlushPolicy,
+    /// Sectors written by the guest but not yet flushed.
+    dirty: BTreeMap<u64, Box<[u8; SD_SECTOR_SIZE]>>,
+}
+
+impl SdCard {
+    /// Open a raw image. Falls back to read-only when the file is not writable.
+    ///
+    /// # Errors
+    /// If the image could not be opened.
+    pub fn open(path: &Path, policy: FlushPolicy) -> Result<Self, EmuError> {
+        let (file, read_only) = match OpenOptions::new().read(true).write(true).open(path) {
+            Ok(file) => (file, false),
+            Err(_) => (
+                File::open(path).with_context(|_| FailedReadFileSnafu { path })?,
+                true,
+            ),
+        };
+        let len = file
+            .metadata()
+            .with_context(|_| FailedReadFileSnafu { path })?
+            .len();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            sector_count: len / SD_SECTOR_SIZE as u64,
+            read_only,
+            sandboxed: false,
+            policy,
+            dirty: BTreeMap::new(),
+        })
+    }
+
+    /// Open a raw image without ever writing to it. Guest writes are kept in
+    /// memory and read back, but are lost when the card is dropped.
+    ///
+    /// # Errors
+    /// If the image could not be opened.
+    pub fn open_sandboxed(path: &Path) -> Result<Self, EmuError> {
+        let file = File::open(path).with_context(|_| FailedReadFileSnafu { path })?;
+        let len = file
+            .metadata()
+            .with_context(|_| FailedReadFileSnafu { path })?
+            .len();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            sector_count: len / SD_SECTOR_SIZE as u64,
+            read_only: false,
+            sandboxed: true,
+            policy: FlushPolicy::Manual,
+            dirty: BTreeMap::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub const fn sector_count(&self) -> u64 {
+        self.sector_count
+    }
+
+    pub const fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub const fn is_sandboxed(&self) -> bool {
+        self.sandboxed
+    }
+
+    pub const fn flush_policy(&self) -> FlushPolicy {
+        self.policy
+    }
+
+    pub const fn set_flush_policy(&mut self, policy: FlushPolicy) {
+        self.policy = policy;
+    }
+
+    /// Number of sectors waiting to be written back.
+    pub fn dirty_sectors(&self) -> usize {
+        self.dirty.len()
+    }
+
+    fn check_range(&self, lba: u64, len: usize) -> Result<u64, EmuError> {
+        let count = len.div_ceil(SD_SECTOR_SIZE) as u64;
+        if lba.saturating_add(count) > self.sector_count {
+            return SdSectorOutOfRangeSnafu { lba, count }.fail();
+        }
+        Ok(count)
+    }
+
+    /// Read whole sectors starting at `lba` into `buf`.
+    ///
+    /// `buf.len()` should be a multiple of [`SD_SECTOR_SIZE`].
+    ///
+    /// # Errors
+    /// If the range is outside the image or the host read failed.
+    pub fn read_sectors(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), EmuError> {
+        self.check_range(lba, buf.len())?;
+
+        self.file
+            .seek(SeekFrom::Start(lba * SD_SECTOR_SIZE as u64))
+            .and_then(|_| self.file.read_exact(buf))
+            .with_context(|_| FailedReadFileSnafu { path: &self.path })?;
+
+        // Unflushed writes take precedence over the image contents.
+        for (i, chunk) in buf.chunks_mut(SD_SECTOR_SIZE).enumerate() {
+            if let Some(sector) = self.dirty.get(&(lba + i as u64)) {
+                chunk.copy_from_slice(&sector[..chunk.len()]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Write sectors starting at `lba`. A last partial sector only replaces
+    /// its first bytes.
+    ///
+    /// Writes to a read-only image are dropped, as on a locked card.
+    ///
+    /// # Errors
+    /// If the range is outside the image, the rest of a partial sector could
+    /// not be read or a write-through failed.
+    pub fn write_sectors(&mut self, lba: u64, data: &[u8]) -> Result<(), EmuError> {
+        self.check_range(lba, data.len())?;
+        if self.read_only {
+            return Ok(());
+        }
+
+        for (i, chunk) in data.chunks(SD_SECTOR_SIZE).enumerate() {
+            let lba = lba + i as u64;
+            // A partial sector keeps the rest of what is on the card
+            if chunk.len() < SD_SECTOR_SIZE && !self.dirty.contains_key(&lba) {
+                let mut sector = Box::new([0; SD_SECTOR_SIZE]);
+                self.read_sectors(lba, &mut sector[..])?;
+                self.dirty.insert(lba, sector);
+            }
+            let sector = self
+                .dirty
+                .entry(lba)
+                .or_insert_with(|| Box::new([0; SD_SECTOR_SIZE]));
+            sector[..chunk.len()].copy_from_slice(chunk);
+        }
+
+        if self.policy == FlushPolicy::WriteThrough {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write all dirty sectors back to the image. Does nothing when sandboxed.
+    ///
+    /// # Errors
+    /// If the host write failed; sectors that were not written stay dirty.
+    pub fn flush(&mut self) -> Result<(), EmuError> {
+        if self.sandboxed {
+            return Ok(());
+        }
+        while let Some((lba, sector)) = self.dirty.pop_first() {
+            let result = self
+                .file
+                .seek(SeekFrom::Start(lba * SD_SECTOR_SIZE as u64))
+                .and_then(|_| self.file.write_all(&sector[..]));
+            if let Err(err) = result {
+                self.dirty.insert(lba, sector);
+                return Err(err).with_context(|_| FailedWriteFileSnafu { path: &self.path });
+            }
+        }
+        self.file
+            .sync_data()
+            .with_context(|_| FailedWriteFileSnafu { path: &self.path })
+    }
+}
+
+impl Drop for SdCard {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            #[cfg(feature = "tracing")]
+            tracing::error!("{err}");
+        }
+    }
+}
+
+impl Emulator {
+    /// Mount a raw SD card image, replacing (and flushing) any mounted one.
+    ///
+    /// The card is sandboxed in read-only mode, see [`Config::read_only`].
+    ///
+    /// # Errors
+    /// If the image could not be opened or the old one could not be flushed;
+    /// the old card then stays mounted.
+    ///
+    /// [`Config::read_only`]: crate::Config::read_only
+    pub fn mount_sd_card(&mut self, path: &Path, policy: FlushPolicy) -> Result<(), EmuError> {
+        let card = match self.config.read_only {
+            true => SdCard::open_sandboxed(path)?,
+            false => SdCard::open(path, policy)?,
+        };
+        if let Some(old) = &mut self.sd_card {
+            old.flush()?;
+        }
+        self.sd_card = Some(card);
+        Ok(())
+    }
+
+    /// Flush and remove the mounted SD card image.
+    ///
+    /// # Errors
+    /// If no card is mounted or flushing failed; the card then stays
+    /// mounted with its unwritten sectors.
+    pub fn unmount_sd_card(&mut self) -> Result<(), EmuError> {
+        self.sd_card
+            .as_mut()
+            .context(SdCardNotMountedSnafu)?
+            .flush()?;
+        self.sd_card = None;
+        Ok(())
+    }
+
+    /// Flush the SD card if its policy asks for it at the end of a frame.
+    pub(crate) fn sd_card_end_frame(&mut self) {
+        if let Some(card) = &mut self.sd_card
+            && card.flush_policy() == FlushPolicy::EveryFrame
+            && card.dirty_sectors() != 0
+            && let Err(err) = card.flush()
+        {
+            #[cfg(feature = "tracing")]
+            tracing::error!("{err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirty_sectors_flush() {
+        let path = std::env::temp_dir().join(format!("lunaris_sd_{}.img", std::process::id()));
+        std::fs::write(&path, vec![0u8; SD_SECTOR_SIZE * 4]).unwrap();
+
+        let mut card = SdCard::open(&path, FlushPolicy::Manual).unwrap();
+        assert_eq!(card.sector_count(), 4);
+
+        card.write_sectors(2, &[0xAB; SD_SECTOR_SIZE]).unwrap();
+        let mut buf = [0u8; SD_SECTOR_SIZE * 2];
+        card.read_sectors(1, &mut buf).unwrap();
+        assert_eq!(buf[0], 0);
+        assert_eq!(buf[SD_SECTOR_SIZE], 0xAB);
+        assert_eq!(std::fs::read(&path).unwrap()[SD_SECTOR_SIZE * 2], 0);
+
+        card.flush().unwrap();
+        assert_eq!(card.dirty_sectors(), 0);
+        assert_eq!(std::fs::read(&path).unwrap()[SD_SECTOR_SIZE * 2], 0xAB);
+        assert!(card.write_sectors(4, &[0; SD_SECTOR_SIZE]).is_err());
+
+        drop(card);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_partial_sector_write() {
+        let path = std::env::temp_dir().join(format!("lunaris_sd_part_{}.img", std::process::id()));
+        std::fs::write(&path, vec![0x11u8; SD_SECTOR_SIZE * 2]).unwrap();
+
+        let mut card = SdCard::open(&path, FlushPolicy::WriteThrough).unwrap();
+        card.write_sectors(0, &[0xAB; SD_SECTOR_SIZE + 4]).unwrap();
+        let image = std::fs::read(&path).unwrap();
+        assert_eq!(image[SD_SECTOR_SIZE - 1], 0xAB);
+        assert_eq!(
+            image[SD_SECTOR_SIZE..SD_SECTOR_SIZE + 5],
+            [0xAB, 0xAB, 0xAB, 0xAB, 0x11]
+        );
+        assert_eq!(image[SD_SECTOR_SIZE * 2 - 1], 0x11);
+
+        drop(card);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_mount_keeps_card_that_fails_to_flush() {
+        let old_path =
+            std::env::temp_dir().join(format!("lunaris_sd_old_{}.img", std::process::id()));
+        let new_path =
+            std::env::temp_dir().join(format!("lunaris_sd_new_{}.img", std::process::id()));
+        std::fs::write(&old_path, vec![0u8; SD_SECTOR_SIZE]).unwrap();
+        std::fs::write(&new_path, vec![0u8; SD_SECTOR_SIZE]).unwrap();
+
+        // Writable as far as the card knows, but the host handle is not
+        let mut old = SdCard::open(&old_path, FlushPolicy::Manual).unwrap();
+        old.file = File::open(&old_path).unwrap();
+        old.write_sectors(0, &[0xAB; SD_SECTOR_SIZE]).unwrap();
+
+        let mut emu = Box::new(Emulator::new());
+        emu.sd_card = Some(old);
+        assert!(emu.mount_sd_card(&new_path, FlushPolicy::Manual).is_err());
+        let card = emu.sd_card.as_mut().unwrap();
+        assert_eq!(card.path(), old_path);
+        assert_eq!(card.dirty_sectors(), 1);
+
+        // Nothing left to lose once the write went nowhere
+        card.dirty.clear();
+        emu.mount_sd_card(&new_path, FlushPolicy::Manual).unwrap();
+        assert_eq!(emu.sd_card.as_ref().unwrap().path(), new_path);
+
+        emu.sd_card = None;
+        std::fs::remove_file(&old_path).unwrap();
+        std::fs::remove_file(&new_path).unwrap();
+    }
+
+    #[test]
+    fn test_unmount_keeps_card_that_fails_to_flush() {
+        let path =
+            std::env::temp_dir().join(format!("lunaris_sd_unmount_{}.img", std::process::id()));
+        std::fs::write(&path, vec![0u8; SD_SECTOR_SIZE]).unwrap();
+
+        let mut card = SdCard::open(&path, FlushPolicy::Manual).unwrap();
+        card.file = File::open(&path).unwrap();
+        card.write_sectors(0, &[0xAB; SD_SECTOR_SIZE]).unwrap();
+
+        let mut emu = Box::new(Emulator::new());
+        emu.sd_card = Some(card);
+        assert!(emu.unmount_sd_card().is_err());
+        assert_eq!(emu.sd_card.as_ref().unwrap().dirty_sectors(), 1);
+
+        // Retried once the host handle is writable again
+        emu.sd_card.as_mut().unwrap().file = File::options().write(true).open(&path).unwrap();
+        emu.unmount_sd_card().unwrap();
+        assert!(emu.sd_card.is_none());
+        assert_eq!(std::fs::read(&path).unwrap()[0], 0xAB);
+        assert!(emu.unmount_sd_card().is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_sandboxed_card_keeps_image() {
+        let path = std::env::temp_dir().join(format!("lunaris_sd_ro_{}.img", std::process::id()));
+        std::fs::write(&path, vec![0u8; SD_SECTOR_SIZE * 2]).unwrap();
+
+        let mut emu = Box::new(Emulator::new());
+        emu.config.read_only = true;
+        emu.mount_sd_card(&path, FlushPolicy::WriteThrough).unwrap();
+        let card = emu.sd_card.as_mut().unwrap();
+        card.write_sectors(1, &[0xAB; SD_SECTOR_SIZE]).unwrap();
+        let mut buf = [0u8; SD_SECTOR_SIZE];
+        card.read_sectors(1, &mut buf).unwrap();
+        assert_eq!(buf[0], 0xAB);
+
+        emu.unmount_sd_card().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), vec![0u8; SD_SECTOR_SIZE * 2]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+//! Slot-2 (GBA slot) devices
+//!
+//! The GBA slot maps cartridge ROM at 0x08000000-0x09FFFFFF over a 16-bit
+//! bus and SRAM at 0x0A000000-0x0A00FFFF over an 8-bit one, for the CPU
+//! EXMEMCNT bit 7 gives it to. DS games look there for GBA cartridges
+//! (dual-slot features) and for add-ons: the Rumble Pak and the Memory
+//! Expansion Pak.
+//!
+//! An empty slot floats: ROM reads return the halfword address and SRAM
+//! reads 0xFF.
+use crate::error::{EmuError, SavestateInvalidSnafu};
+use crate::savestate::{Savestate, StateReader, StateWriter};
+
+/// Size of the GBA cartridge SRAM.
+pub const GBA_SRAM_SIZE: usize = 0x1_0000;
+/// Size of the Memory Expansion Pak RAM.
+const EXPANSION_RAM_SIZE: usize = 0x80_0000;
+/// Offset of the Memory Expansion Pak RAM in the ROM area
+const EXPANSION_RAM_START: u32 = 0x0100_0000;
+/// Offset of the Memory Expansion Pak RAM enable register in the ROM area
+const EXPANSION_ENABLE: u32 = 0x0024_0000;
+
+/// What is inserted in the GBA slot.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Slot2Device {
+    #[default]
+    Empty,
+    /// GBA cartridge with plain SRAM; flash and EEPROM saves are not
+    /// emulated
+    GbaCartridge { rom: Vec<u8> },
+    /// DS Rumble Pak
+    RumblePak,
+    /// Memory Expansion Pak, 8 MB of RAM
+    ExpansionPak,
+}
+
+impl Slot2Device {
+    const fn tag(&self) -> u8 {
+        match self {
+            Self::Empty => 0,
+            Self::GbaCartridge { .. } => 1,
+            Self::RumblePak => 2,
+            Self::ExpansionPak => 3,
+        }
+    }
+}
+
+/// GBA slot and the state of the device in it.
+#[derive(Debug, Default)]
+pub struct Slot2 {
+    device: Slot2Device,
+    /// GBA cartridge SRAM or Expansion Pak RAM
+    ram: Vec<u8>,
+    /// Expansion Pak RAM is mapped
+    ram_enabled: bool,
+    /// Rumble Pak motor on
+    rumble: bool,
+}
+
+impl Slot2 {
+    /// Insert `device`, removing the one in the slot.
+    pub fn insert(&mut self, device: Slot2Device) {
+        self.ram = match device {
+            Slot2Device::GbaCartridge { .. } => vec![0xFF; GBA_SRAM_SIZE],
+            Slot2Device::ExpansionPak => vec![0; EXPANSION_RAM_SIZE],
+            Slot2Device::Empty | Slot2Device::RumblePak => Vec::new(),
+        };
+        self.device = device;
+        self.power_on();
+    }
+
+    pub const fn device(&self) -> &Slot2Device {
+        &self.device
+    }
+
+    /// Reset the device, keeping battery backed SRAM.
+    pub fn power_on(&mut self) {
+        if self.device == Slot2Device::ExpansionPak {
+            self.ram.fill(0);
+        }
+        self.ram_enabled = true;
+        self.rumble = false;
+    }
+
+    /// Whether the Rumble Pak motor is on.
+    pub const fn rumble(&self) -> bool {
+        self.rumble
+    }
+
+    /// Read the halfword at `address` of the ROM area.
+    pub fn read_rom(&self, address: u32) -> u16 {
+        let offset = address & 0x01FF_FFFE;
+        let open_bus = (offset >> 1) as u16;
+        match &self.device {
+            Slot2Device::Empty => open_bus,
+            Slot2Device::GbaCartridge { rom } => rom
+                .get(offset as usize..offset as usize + 2)
+                .map_or(open_bus, |bytes| u16::from_le_bytes([bytes[0], bytes[1]])),
+            // Detected by data bit 1 being pulled low
+            Slot2Device::RumblePak => 0xFFFD,
+            Slot2Device::ExpansionPak => self.expansion_read(offset),
+        }
+    }
+
+    /// Memory Expansion Pak: a GBA-like ID header, the RAM enable register
+    /// and the RAM.
+    fn expansion_read(&self, offset: u32) -> u16 {
+        match offset {
+            0xB0 | 0xB8 | 0xBA | 0xBC | 0x1FFFC => 0xFFFF,
+            0xB2 => 0x0000,
+            0xB4 => 0x2400,
+            0xB6 => 0x2424,
+            0xBE => 0x7FFF,
+            0x1FFFE => 0x007F,
+            EXPANSION_ENABLE => self.ram_enabled as u16,
+            EXPANSION_RAM_START.. if self.ram_enabled => {
+                let offset = (offset - EXPANSION_RAM_START) as usize;
+                self.ram
+                    .get(offset..offset + 2)
+                    .map_or(0xFFFF, |bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+            }
+            _ => 0xFFFF,
+        }
+    }
+
+    /// Write the halfword at `address` of the ROM area.
+    pub fn write_rom(&mut self, address: u32, value: u16) {
+        let offset = address & 0x01FF_FFFE;
+        match self.device {
+            Slot2Device::RumblePak => self.rumble = value & 2 != 0,
+            Slot2Device::ExpansionPak if offset == EXPANSION_ENABLE => {
+                self.ram_enabled = value & 1 != 0;
+            }
+            Slot2Device::ExpansionPak if offset >= EXPANSION_RAM_START && self.ram_enabled => {
+                let offset = (offset - EXPANSION_RAM_START) as usize;
+                if let Some(bytes) = self.ram.get_mut(offset..offset + 2) {
+                    bytes.copy_from_slice(&value.to_le_bytes());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Read the byte at `address` of the SRAM area.
+    pub fn read_ram(&self, address: u32) -> u8 {
+        match self.device {
+            Slot2Device::GbaCartridge { .. } => self.ram[address as usize % GBA_SRAM_SIZE],
+            _ => 0xFF,
+        }
+    }
+
+    /// Write the byte at `address` of the SRAM area.
+    pub fn write_ram(&mut self, address: u32, value: u8) {
+        if let Slot2Device::GbaCartridge { .. } = self.device {
+            self.ram[address as usize % GBA_SRAM_SIZE] = value;
+        }
+    }
+}
+
+/// Only the state of the device is saved; a state must be loaded with the
+/// same kind of device inserted.
+impl Savestate for Slot2 {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u8(self.device.tag());
+        w.bytes(&self.ram);
+        w.bool(self.ram_enabled);
+        w.bool(self.rumble);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        if r.u8()? != self.device.tag() {
+            return SavestateInvalidSnafu.fail();
+        }
+        r.bytes(&mut self.ram)?;
+        self.ram_enabled = r.bool()?;
+        self.rumble = r.bool()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slot2_devices() {
+        let mut slot2 = Slot2::default();
+        assert_eq!(slot2.read_rom(0x0800_0102), 0x81);
+
+        slot2.insert(Slot2Device::GbaCartridge {
+            rom: vec![0x34, 0x12],
+        });
+        assert_eq!(slot2.read_rom(0x0800_0000), 0x1234);
+        assert_eq!(slot2.read_rom(0x0800_0002), 1);
+        slot2.write_ram(0x0A01_0005, 0x56);
+        assert_eq!(slot2.read_ram(0x0A00_0005), 0x56);
+
+        slot2.insert(Slot2Device::RumblePak);
+        slot2.write_rom(0x0800_0000, 2);
+        assert!(slot2.rumble());
+
+        slot2.insert(Slot2Device::ExpansionPak);
+        assert_eq!(slot2.read_rom(0x0800_00B6), 0x2424);
+        slot2.write_rom(0x0900_0010, 0xBEEF);
+        assert_eq!(slot2.read_rom(0x0900_0010), 0xBEEF);
+        slot2.write_rom(0x0824_0000, 0);
+        slot2.write_rom(0x0900_0010, 0);
+        assert_eq!(slot2.read_rom(0x0900_0010), 0xFFFF);
+        slot2.write_rom(0x0824_0000, 1);
+        assert_eq!(slot2.read_rom(0x0900_0010), 0xBEEF);
+    }
+}
@@ -2,7 +2,9 @@
 //! Manages communication with Firmware, Touchscreen, and other SPI devices
 
 use crate::error::EmuError;
-use crate::{firmware::Firmware, touchscreen::TouchScreen};
+use crate::microphone::{MicrophoneSource, mic_to_adc};
+use crate::savestate::{Savestate, StateReader, StateWriter};
+use crate::{firmware::Firmware, power_management::PowerManagement, touchscreen::TouchScreen};
 
 /// SPI Control Register
 #[derive(Debug, Clone, Copy)]
@@ -85,6 +87,9 @@ pub struct SPIBus {
     // pub(crate) emulator: EmulatorRef,
     pub(crate) firmware: Firmware,
     touchscreen: TouchScreen,
+    pub(crate) power: PowerManagement,
+    /// Audio the touchscreen controller AUX channel samples
+    microphone: Option<Box<dyn MicrophoneSource>>,
 
     /// SPI control register
     spicnt: RegSpiCnt,
@@ -99,6 +104,8 @@ impl SPIBus {
         SPIBus {
             firmware: Firmware::new(),
             touchscreen: TouchScreen::new(),
+            power: PowerManagement::default(),
+            microphone: None,
             spicnt: RegSpiCnt::new(),
             output: 0,
         }
@@ -112,6 +119,9 @@ impl SPIBus {
         self.spicnt.busy = false;
         self.spicnt.enabled = false;
         self.touchscreen.power_on();
+        self.touchscreen
+            .set_calibration(self.firmware.touch_calibration());
+        self.power.power_on();
 
         Ok(())
     }
@@ -121,11 +131,22 @@ impl SPIBus {
         self.spicnt.busy = false;
         self.spicnt.enabled = false;
         self.touchscreen.power_on();
+        self.power.power_on();
         Ok(())
     }
 
-    pub fn touchscreen_press(&mut self, x: i32, y: i32) {
-        self.touchscreen.press_event(x, y);
+    pub fn touchscreen_press(&mut self, x: i32, y: i32, pressure: f32) {
+        self.touchscreen.press_event(x, y, pressure);
+    }
+
+    /// Feed the microphone from `source`, or silence it with `None`.
+    pub fn set_microphone(&mut self, source: Option<Box<dyn MicrophoneSource>>) {
+        self.microphone = source;
+    }
+
+    /// Touchscreen /PENIRQ, see [`TouchScreen::pen_irq`].
+    pub fn pen_irq(&self) -> bool {
+        self.touchscreen.pen_irq()
     }
 
     /// Read from SPI data register
@@ -135,25 +156,48 @@ impl SPIBus {
 
     /// Write to SPI data register
     ///
-    /// true => must call `emulator.requesting_interrupt(7);`
+    /// Returns whether a transfer started; it stays busy until
+    /// [`SPIBus::finish_transfer`] is called [`SPIBus::transfer_cycles`]
+    /// later.
     pub fn write_spidata(&mut self, data: u8) -> bool {
         if self.spicnt.enabled {
-            self.spicnt.busy = false;
+            self.spicnt.busy = true;
 
             // Process transfer based on device selection
             self.output = match self.spicnt.device {
-                1 => self.firmware.transfer_data(data),    // Firmware device
-                2 => self.touchscreen.transfer_data(data), // Touchscreen device
-                _ => 0,                                    // Power management or unknown device
+                0 => self.power.transfer_data(data),    // Power management
+                1 => self.firmware.transfer_data(data), // Firmware device
+                2 => {
+                    // Sample the microphone when the AUX channel is selected
+                    if data & 0x80 != 0 && (data >> 4) & 0x7 == 6 {
+                        let sample = self.microphone.as_mut().map_or(0, |mic| mic.sample());
+                        self.touchscreen
+                            .set_aux(mic_to_adc(sample, self.power.mic_gain()));
+                    }
+                    self.touchscreen.transfer_data(data) // Touchscreen device
+                }
+                _ => 0, // Unknown device
             };
-
-            if self.spicnt.irq_after_transfer {
-                return true;
-            }
+            return true;
         }
         false
     }
 
+    /// System cycles one byte takes at the selected baud rate, 4MHz
+    /// halved per step.
+    pub const fn transfer_cycles(&self) -> u64 {
+        64 << self.spicnt.bandwidth
+    }
+
+    /// End the transfer in progress.
+    ///
+    /// true => must call `emulator.requesting_interrupt(7);`
+    pub const fn finish_transfer(&mut self) -> bool {
+        let finished = self.spicnt.busy;
+        self.spicnt.busy = false;
+        finished && self.spicnt.irq_after_transfer
+    }
+
     /// Get SPI control register
     pub fn get_spicnt(&self) -> u16 {
         self.spicnt.get()
@@ -164,3 +208,23 @@ impl SPIBus {
         self.spicnt.set(value);
     }
 }
+
+impl Savestate for SPIBus {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u16(self.spicnt.get());
+        w.bool(self.spicnt.busy);
+        w.u8(self.output);
+        self.firmware.save_state(w);
+        self.touchscreen.save_state(w);
+        self.power.save_state(w);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.spicnt.set(r.u16()?);
+        self.spicnt.busy = r.bool()?;
+        self.output = r.u8()?;
+        self.firmware.load_state(r)?;
+        self.touchscreen.load_state(r)?;
+        self.power.load_state(r)
+    }
+}
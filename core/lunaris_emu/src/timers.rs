@@ -1,5 +1,12 @@
 //! Timer system for Nintendo DS
 //! Manages 8 timers (4 per CPU) with frequency division and overflow interrupts
+//!
+//! Timers are not stepped one tick at a time. Each group is only run up to
+//! a system time with [`NDSTiming::advance_to`] when its next overflow is
+//! due, which the emulator schedules from [`NDSTiming::next_overflow`], or
+//! when a register is accessed.
+use crate::error::EmuError;
+use crate::savestate::{Savestate, StateReader, StateWriter};
 
 /// Timer frequency divisor enumeration
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -17,7 +24,7 @@ pub enum Divisor {
 impl Divisor {
     /// Get the divisor value as integer
     #[inline]
-    pub fn to_u32(self) -> u32 {
+    pub const fn to_u32(self) -> u32 {
         match self {
             Divisor::F1 => 1,
             Divisor::F64 => 64,
@@ -42,12 +49,12 @@ impl Divisor {
 /// Individual timer register
 #[derive(Debug, Clone, Copy)]
 pub struct TimerReg {
-    /// Current counter value
+    /// Current counter value, as of the last sync
     pub counter: u16,
     /// Value to reload counter on overflow
     pub reload_value: u16,
-    /// Cycles remaining before increment
-    pub cycles_left: i32,
+    /// Cycles counted towards the next increment
+    pub prescaler: u32,
     /// Clock frequency divisor
     pub clock_div: Divisor,
     /// Count up mode: increment when previous timer overflows
@@ -64,7 +71,7 @@ impl TimerReg {
         TimerReg {
             counter: 0,
             reload_value: 0,
-            cycles_left: 0,
+            prescaler: 0,
             clock_div: Divisor::F1,
             count_up_timing: false,
             irq_on_overflow: false,
@@ -91,26 +98,33 @@ impl TimerReg {
     /// Set control register value
     #[inline]
     fn set_control(&mut self, value: u16) {
-        match Divisor::from_u32((value & 0x3) as u32) {
-            Some(divisor) => {
-                self.clock_div = divisor;
-                self.cycles_left = divisor.to_u32() as i32;
-            }
-            None => {
-                #[cfg(feature = "tracing")]
-                tracing::error!("Invalid NDSTiming Divisor value: None")
-            }
-        };
+        self.clock_div = Divisor::from_u32((value & 0x3) as u32).unwrap_or(Divisor::F1);
         self.count_up_timing = (value & (1 << 2)) != 0;
         self.irq_on_overflow = (value & (1 << 6)) != 0;
         let enable = (value & (1 << 7)) != 0;
 
-        // If timer is being newly enabled, reload the counter
+        // If timer is being newly enabled, reload the counter and restart
+        // the prescaler
         if !self.enabled && enable {
             self.counter = self.reload_value;
+            self.prescaler = 0;
         }
         self.enabled = enable;
     }
+
+    /// Counter and prescaler after `cycles` more cycles, for a timer that
+    /// is ticking. The counter can go past 0xFFFF.
+    const fn advanced(&self, cycles: u64) -> (u64, u32) {
+        let div = self.clock_div.to_u32() as u64;
+        let total = self.prescaler as u64 + cycles;
+        (self.counter as u64 + total / div, (total % div) as u32)
+    }
+
+    /// Cycles until the counter overflows, for a timer that is ticking.
+    const fn cycles_to_overflow(&self) -> u64 {
+        let div = self.clock_div.to_u32() as u64;
+        (0x10000 - self.counter as u64) * div - self.prescaler as u64
+    }
 }
 
 impl Default for TimerReg {
@@ -122,11 +136,16 @@ impl Default for TimerReg {
 /// NDS Timing and Timer system
 #[derive(Debug)]
 pub struct NDSTiming {
-    /// Timer clock divisor values for each of 4 timers
-    pub(crate) timer_clock_divs: [i32; 4],
-
-    /// Timer registers (0-3 for ARM9, 4-7 for ARM7)
+    /// Timer registers (0-3 for ARM7, 4-7 for ARM9)
     pub(crate) timers: [TimerReg; 8],
+
+    /// Cycles of the ARM7 and ARM9 timers (indexed by [`NDSTiming::group`])
+    /// that are not yet applied to the counters
+    pending: [u64; 2],
+    /// System time each group has been run up to, pending cycles included
+    synced: [u64; 2],
+    /// Cycles after the last sync until the first overflow of each group
+    deadline: [u64; 2],
 }
 
 impl Default for NDSTiming {
@@ -139,22 +158,128 @@ impl NDSTiming {
     /// Create new timing system
     pub fn new() -> Self {
         NDSTiming {
-            timer_clock_divs: [0; 4],
             timers: [TimerReg::new(); 8],
+            pending: [0; 2],
+            synced: [0; 2],
+            deadline: [u64::MAX; 2],
         }
     }
 
     /// Power on timing system
     pub fn power_on(&mut self) {
-        for timer in &mut self.timers {
-            *timer = TimerReg::new();
+        *self = Self::new();
+    }
+
+    /// Timer group of timer `index`: 0 for the ARM7, 1 for the ARM9.
+    pub const fn group(index: usize) -> usize {
+        index / 4
+    }
+
+    /// Whether timer `index` counts cycles. Count-up is ignored for the
+    /// first timer of each CPU.
+    const fn ticking(&self, index: usize) -> bool {
+        let timer = &self.timers[index];
+        timer.enabled && (!timer.count_up_timing || index.is_multiple_of(4))
+    }
+
+    fn update_deadline(&mut self, group: usize) {
+        self.deadline[group] = (group * 4..group * 4 + 4)
+            .filter(|&i| self.ticking(i))
+            .map(|i| self.timers[i].cycles_to_overflow())
+            .min()
+            .unwrap_or(u64::MAX);
+    }
+
+    /// Run `group` up to system time `now`; earlier times are ignored.
+    ///
+    /// Returns `true` once a timer reached its overflow, then
+    /// [`NDSTiming::step`] has to be called until nothing is pending.
+    pub fn advance_to(&mut self, group: usize, now: u64) -> bool {
+        if now > self.synced[group] {
+            self.pending[group] += now - self.synced[group];
+            self.synced[group] = now;
+        }
+        self.pending[group] >= self.deadline[group]
+    }
+
+    /// System time of the next overflow in `group`, if a timer ticks.
+    pub fn next_overflow(&self, group: usize) -> Option<u64> {
+        (self.deadline[group] != u64::MAX)
+            .then(|| self.synced[group] + self.deadline[group].saturating_sub(self.pending[group]))
+    }
+
+    /// Whether cycles of `group` still have to be applied by
+    /// [`NDSTiming::step`].
+    pub const fn has_pending(&self, group: usize) -> bool {
+        self.pending[group] != 0
+    }
+
+    /// Apply pending cycles of `group` up to the next overflow.
+    ///
+    /// Overflowed timers are reloaded; returns them as a bit mask (bit 0 =
+    /// first timer of the group) for the caller to raise their interrupts
+    /// and cascade.
+    pub fn step(&mut self, group: usize) -> u8 {
+        let cycles = self.pending[group].min(self.deadline[group]);
+        self.pending[group] -= cycles;
+
+        let mut overflowed = 0;
+        for i in 0..4 {
+            let index = group * 4 + i;
+            if !self.ticking(index) {
+                continue;
+            }
+            let timer = &mut self.timers[index];
+            let (counter, prescaler) = timer.advanced(cycles);
+            timer.prescaler = prescaler;
+            timer.counter = match counter {
+                0x10000.. => {
+                    overflowed |= 1 << i;
+                    timer.reload_value
+                }
+                _ => counter as u16,
+            };
+        }
+        self.update_deadline(group);
+        overflowed
+    }
+
+    /// Apply pending cycles before a register write. No timer can overflow
+    /// here, since `advance_to` callers step as soon as one does.
+    fn catch_up(&mut self, group: usize) {
+        let overflowed = self.step(group);
+        debug_assert_eq!(overflowed, 0);
+    }
+
+    /// Count one overflow of the previous timer into count-up timer
+    /// `index`. Returns `true` if it overflowed in turn and was reloaded.
+    pub fn count_up(&mut self, index: usize) -> bool {
+        let timer = &mut self.timers[index];
+        if !timer.enabled || !timer.count_up_timing || index.is_multiple_of(4) {
+            return false;
+        }
+        match timer.counter.checked_add(1) {
+            Some(counter) => {
+                timer.counter = counter;
+                false
+            }
+            None => {
+                timer.counter = timer.reload_value;
+                true
+            }
         }
     }
 
     /// Read timer counter low byte (0x4000100 + index*4)
     pub fn read_lo(&self, index: usize) -> u16 {
         if index < 8 {
-            self.timers[index].counter
+            match self.ticking(index) {
+                true => {
+                    let pending = self.pending[Self::group(index)];
+                    self.timers[index].advanced(pending).0 as u16
+                }
+                false => self.timers[index].counter,
+            }
         } else {
             #[cfg(feature = "tracing")]
             tracing::warn!("fn read_lo: out-of bounds {index}");
@@ -173,12 +298,6 @@ impl NDSTiming {
         }
     }
 
-    /// Write 32-bit word to timer
-    pub fn write(&mut self, value: u32, index: usize) {
-        self.write_lo((value & 0xFFFF) as u16, index);
-        self.write_hi((value >> 16) as u16, index);
-    }
-
     /// Write counter value (low 16 bits)
     pub fn write_lo(&mut self, value: u16, index: usize) {
         self.timers[index].reload_value = value;
@@ -186,7 +305,46 @@ impl NDSTiming {
 
     /// Write control register (high 16 bits)
     pub fn write_hi(&mut self, value: u16, index: usize) {
-        let timer = &mut self.timers[index];
-        timer.set_control(value);
+        let group = Self::group(index);
+        self.catch_up(group);
+        self.timers[index].set_control(value);
+        self.update_deadline(group);
+    }
+}
+
+impl Savestate for NDSTiming {
+    fn save_state(&self, w: &mut StateWriter) {
+        for (&pending, &synced) in self.pending.iter().zip(&self.synced) {
+            w.u64(pending);
+            w.u64(synced);
+        }
+        for timer in &self.timers {
+            w.u16(timer.counter);
+            w.u16(timer.reload_value);
+            w.u32(timer.prescaler);
+            w.u16(timer.get_control());
+        }
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        for (pending, synced) in self.pending.iter_mut().zip(&mut self.synced) {
+            *pending = r.u64()?;
+            *synced = r.u64()?;
+        }
+        for timer in &mut self.timers {
+            timer.counter = r.u16()?;
+            timer.reload_value = r.u16()?;
+            timer.prescaler = r.u32()?;
+            // Not `set_control`, which would restart the prescaler
+            let control = r.u16()?;
+            timer.clock_div = Divisor::from_u32(control as u32).unwrap_or(Divisor::F1);
+            timer.count_up_timing = control & (1 << 2) != 0;
+            timer.irq_on_overflow = control & (1 << 6) != 0;
+            timer.enabled = control & (1 << 7) != 0;
+        }
+        for group in 0..2 {
+            self.update_deadline(group);
+        }
+        Ok(())
     }
 }
@@ -1,8 +1,126 @@
+use crate::error::EmuError;
+use crate::microphone::MIC_SILENCE;
+use crate::savestate::{Savestate, StateReader, StateWriter};
+
+/// Pressure used when the frontend only reports a position.
+pub const DEFAULT_TOUCH_PRESSURE: f32 = 0.5;
+
+/// Resistance of the X plate in ohms, as in the TSC2046 pressure formula.
+const X_PLATE_OHMS: f32 = 400.0;
+/// Touch resistance at pressure 0.0 (barely touching) and 1.0 (firm).
+const LIGHT_TOUCH_OHMS: f32 = 2000.0;
+const FIRM_TOUCH_OHMS: f32 = 200.0;
+/// Z2 reading; Z1 is derived from it and grows as the plates are pressed
+/// together.
+const Z2: f32 = 0xC00 as f32;
+
+/// Z1/Z2 readings for a touch at X reading `x_adc` with `pressure` from
+/// 0.0 (light) to 1.0 (firm), following the TSC2046 relation
+/// `R_touch = R_x_plate * X / 4096 * (Z2 / Z1 - 1)` that games use.
+pub fn pressure_to_z(x_adc: u16, pressure: f32) -> (u16, u16) {
+    let pressure = pressure.clamp(0.0, 1.0);
+    let resistance = LIGHT_TOUCH_OHMS + (FIRM_TOUCH_OHMS - LIGHT_TOUCH_OHMS) * pressure;
+    let x = f32::from(x_adc.max(1)) / 4096.0;
+    let z1 = Z2 / (resistance / (X_PLATE_OHMS * x) + 1.0);
+    ((z1 as u16).max(1), Z2 as u16)
+}
+
+/// Touchscreen calibration from the firmware user settings (0x58..0x64):
+/// two screen points and the ADC readings measured at them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TouchCalibration {
+    /// ADC X/Y readings at the first point
+    pub adc1: (u16, u16),
+    /// First point in pixels
+    pub screen1: (u8, u8),
+    /// ADC X/Y readings at the second point
+    pub adc2: (u16, u16),
+    /// Second point in pixels
+    pub screen2: (u8, u8),
+}
+
+impl Default for TouchCalibration {
+    /// Pixel `n` reads as ADC value `n << 4`.
+    fn default() -> Self {
+        Self {
+            adc1: (0, 0),
+            screen1: (0, 0),
+            adc2: (255 << 4, 191 << 4),
+            screen2: (255, 191),
+        }
+    }
+}
+
+impl TouchCalibration {
+    /// Parse the 12 calibration bytes of the user settings.
+    pub fn from_user_settings(bytes: &[u8]) -> Self {
+        let halfword = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        Self {
+            adc1: (halfword(0), halfword(2)),
+            screen1: (bytes[4], bytes[5]),
+            adc2: (halfword(6), halfword(8)),
+            screen2: (bytes[10], bytes[11]),
+        }
+    }
+
+    /// The 12 calibration bytes of the user settings.
+    pub fn to_user_settings(self) -> [u8; 12] {
+        let mut bytes = [0; 12];
+        bytes[0..2].copy_from_slice(&self.adc1.0.to_le_bytes());
+        bytes[2..4].copy_from_slice(&self.adc1.1.to_le_bytes());
+        bytes[4] = self.screen1.0;
+        bytes[5] = self.screen1.1;
+        bytes[6..8].copy_from_slice(&self.adc2.0.to_le_bytes());
+        bytes[8..10].copy_from_slice(&self.adc2.1.to_le_bytes());
+        bytes[10] = self.screen2.0;
+        bytes[11] = self.screen2.1;
+        bytes
+    }
+
+    /// Whether both axes have two distinct points to interpolate between.
+    pub const fn is_valid(&self) -> bool {
+        self.screen1.0 != self.screen2.0
+            && self.screen1.1 != self.screen2.1
+            && self.adc1.0 != self.adc2.0
+            && self.adc1.1 != self.adc2.1
+    }
+
+    /// ADC X/Y readings for a touch at pixel (`x`, `y`), interpolated
+    /// linearly through the two points like games map them back.
+    pub fn to_adc(&self, x: i32, y: i32) -> (u16, u16) {
+        let calibration = match self.is_valid() {
+            true => *self,
+            false => Self::default(),
+        };
+        let axis = |pixel: i32, adc1: u16, screen1: u8, adc2: u16, screen2: u8| {
+            let scale = (adc2 as f32 - adc1 as f32) / (screen2 as f32 - screen1 as f32);
+            let adc = adc1 as f32 + (pixel - screen1 as i32) as f32 * scale;
+            adc.round().clamp(0.0, 4095.0) as u16
+        };
+        (
+            axis(
+                x,
+                calibration.adc1.0,
+                calibration.screen1.0,
+                calibration.adc2.0,
+                calibration.screen2.0,
+            ),
+            axis(
+                y,
+                calibration.adc1.1,
+                calibration.screen1.1,
+                calibration.adc2.1,
+                calibration.screen2.1,
+            ),
+        )
+    }
+}
+
 /// Touch screen controller emulation.
 ///
 /// This struct emulates the Nintendo DS touchscreen SPI device.
 /// It faithfully reproduces the behavior of the original C++
-/// implementation from CorgiDS.
+/// implementation from CorgiDS, plus the Z1/Z2 pressure channels.
 #[derive(Debug, Default)]
 pub struct TouchScreen {
     // Control byte written by SPI
@@ -17,6 +135,19 @@ pub struct TouchScreen {
     // Latched touch coordinates
     press_x: u16,
     press_y: u16,
+
+    // Latched pressure readings
+    press_z1: u16,
+    press_z2: u16,
+
+    // Pen touching the screen
+    touched: bool,
+
+    // AUX input reading (microphone)
+    aux: u16,
+
+    // Calibration the coordinates are converted with
+    calibration: TouchCalibration,
 }
 
 impl TouchScreen {
@@ -31,6 +162,11 @@ impl TouchScreen {
             output_coords: 0,
             press_x: 0,
             press_y: 0xFFF,
+            press_z1: 0,
+            press_z2: 0xFFF,
+            touched: false,
+            aux: MIC_SILENCE,
+            calibration: TouchCalibration::default(),
         }
     }
 
@@ -45,27 +181,49 @@ impl TouchScreen {
 
         self.press_x = 0;
         self.press_y = 0xFFF;
+        self.press_z1 = 0;
+        self.press_z2 = 0xFFF;
+        self.touched = false;
+        self.aux = MIC_SILENCE;
+    }
+
+    /// Reading of the AUX channel for the next conversion, see
+    /// [`crate::microphone`].
+    pub fn set_aux(&mut self, aux: u16) {
+        self.aux = aux;
+    }
+
+    /// Use the firmware's `calibration` for later touches.
+    pub fn set_calibration(&mut self, calibration: TouchCalibration) {
+        self.calibration = calibration;
+    }
+
+    /// State of the /PENIRQ output: low (`true`) while the pen touches the
+    /// screen, unless power-down bit PD0 of the last control byte disabled
+    /// it.
+    pub fn pen_irq(&self) -> bool {
+        self.touched && self.control_byte & 0x1 == 0
     }
 
     /// Register a touch press event.
     ///
-    /// The coordinates are latched internally and converted
-    /// to 12-bit ADC values by shifting left by 4 bits,
-    /// exactly like the original implementation.
+    /// The pixel coordinates are converted to 12-bit ADC values with the
+    /// calibration, see [`TouchCalibration::to_adc`].
     ///
-    /// A Y value of `0xFFF` indicates "no touch".
-    pub fn press_event(&mut self, x: i32, y: i32) {
-        self.press_x = x as u16;
-        self.press_y = y as u16;
-
-        if y == 0xFFF {
+    /// A Y value of `0xFFF` indicates "no touch". `pressure` goes from 0.0
+    /// (light) to 1.0 (firm), see [`pressure_to_z`].
+    pub fn press_event(&mut self, x: i32, y: i32, pressure: f32) {
+        self.touched = y != 0xFFF;
+        if !self.touched {
+            self.press_x = 0;
+            self.press_y = 0xFFF;
+            self.press_z1 = 0;
+            self.press_z2 = 0xFFF;
             return;
         }
 
-        self.press_x <<= 4;
-        self.press_y <<= 4;
-
-        // printf("\nTouchscreen: ($%04X, $%04X)", press_x, press_y);
+        (self.press_x, self.press_y) = self.calibration.to_adc(x, y);
+        (self.press_z1, self.press_z2) = pressure_to_z(self.press_x, pressure);
     }
 
     /// Transfer one byte over the touchscreen SPI interface.
@@ -94,9 +252,11 @@ impl TouchScreen {
 
             // Select ADC channel
             match channel {
-                1 => self.output_coords = self.press_y, // Touch Y
-                5 => self.output_coords = self.press_x, // Touch X
-                6 => self.output_coords = 0x800,        // Battery / auxiliary channel
+                1 => self.output_coords = self.press_y,  // Touch Y
+                3 => self.output_coords = self.press_z1, // Touch Z1
+                4 => self.output_coords = self.press_z2, // Touch Z2
+                5 => self.output_coords = self.press_x,  // Touch X
+                6 => self.output_coords = self.aux,      // Auxiliary channel (microphone)
                 _ => self.output_coords = 0xFFF,
             }
 
@@ -120,3 +280,108 @@ impl TouchScreen {
         self.data_pos = 0;
     }
 }
+
+/// Only the transfer in progress; the pen follows the input and the
+/// calibration the firmware.
+impl Savestate for TouchScreen {
+    fn save_state(&self, w: &mut StateWriter) {
+        w.u8(self.control_byte);
+        w.u16(self.output_coords);
+        w.u32(self.data_pos as u32);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        self.control_byte = r.u8()?;
+        self.output_coords = r.u16()?;
+        self.data_pos = r.u32()? as i32;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Select `channel` in 12-bit mode and read the result.
+    fn read_channel(ts: &mut TouchScreen, channel: u8) -> u16 {
+        ts.transfer_data(0x80 | (channel << 4));
+        let hi = ts.transfer_data(0) as u16;
+        let lo = ts.transfer_data(0) as u16;
+        (hi << 5) | (lo >> 3)
+    }
+
+    /// Touch resistance a game would compute from the readings.
+    fn resistance(ts: &mut TouchScreen) -> f32 {
+        let x = read_channel(ts, 5) as f32;
+        let z1 = read_channel(ts, 3) as f32;
+        let z2 = read_channel(ts, 4) as f32;
+        X_PLATE_OHMS * x / 4096.0 * (z2 / z1 - 1.0)
+    }
+
+    #[test]
+    fn test_pressure_channels() {
+        let mut ts = TouchScreen::new();
+        ts.press_event(128, 96, 0.1);
+        let light = resistance(&mut ts);
+        ts.press_event(128, 96, 0.9);
+        let firm = resistance(&mut ts);
+        assert!(firm < light);
+        assert!((firm - 380.0).abs() < 20.0);
+
+        ts.press_event(0, 0xFFF, 1.0);
+        assert_eq!(read_channel(&mut ts, 3), 0);
+    }
+
+    #[test]
+    fn test_calibration() {
+        // A real console's calibration
+        let calibration = TouchCalibration {
+            adc1: (0x02DF, 0x032B),
+            screen1: (0x20, 0x20),
+            adc2: (0x0D3B, 0x0CE7),
+            screen2: (0xE0, 0xA0),
+        };
+        let bytes = calibration.to_user_settings();
+        assert_eq!(TouchCalibration::from_user_settings(&bytes), calibration);
+
+        // Back to pixels the way libnds does it
+        let to_pixel = |raw: u16, adc1: u16, adc2: u16, screen1: u8, screen2: u8| {
+            let (raw, adc1, adc2) = (raw as i64, adc1 as i64, adc2 as i64);
+            let scale = ((screen2 as i64 - screen1 as i64) << 19) / (adc2 - adc1);
+            let offset = ((adc1 + adc2) * scale - ((screen1 as i64 + screen2 as i64) << 19)) / 2;
+            (raw * scale - offset + scale / 2) >> 19
+        };
+        let mut ts = TouchScreen::new();
+        ts.set_calibration(calibration);
+        // Between the two points; outside, the truncated libnds scale
+        // drifts off by a pixel
+        for (x, y) in (0x20..=0xE0).zip((0x20..=0xA0).cycle()) {
+            ts.press_event(x, y, DEFAULT_TOUCH_PRESSURE);
+            assert!(ts.pen_irq());
+            let (adc_x, adc_y) = (read_channel(&mut ts, 5), read_channel(&mut ts, 1));
+            assert_eq!(to_pixel(adc_x, 0x02DF, 0x0D3B, 0x20, 0xE0), x as i64);
+            assert_eq!(to_pixel(adc_y, 0x032B, 0x0CE7, 0x20, 0xA0), y as i64);
+        }
+
+        // No usable calibration: pixel << 4
+        ts.set_calibration(TouchCalibration::from_user_settings(&[0; 12]));
+        ts.press_event(100, 50, DEFAULT_TOUCH_PRESSURE);
+        assert_eq!(read_channel(&mut ts, 5), 100 << 4);
+        assert_eq!(read_channel(&mut ts, 1), 50 << 4);
+    }
+
+    #[test]
+    fn test_pen_irq() {
+        let mut ts = TouchScreen::new();
+        assert!(!ts.pen_irq());
+        ts.press_event(10, 10, DEFAULT_TOUCH_PRESSURE);
+        assert!(ts.pen_irq());
+        // PD0 set: ADC kept on, /PENIRQ disabled
+        ts.transfer_data(0x80 | (5 << 4) | 0x1);
+        assert!(!ts.pen_irq());
+        ts.transfer_data(0x80 | (5 << 4));
+        assert!(ts.pen_irq());
+        ts.press_event(0, 0xFFF, 0.0);
+        assert!(!ts.pen_irq());
+    }
+}
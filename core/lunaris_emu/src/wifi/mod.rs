@@ -0,0 +1,834 @@
+//! WiFi Controller for Nintendo DS
+//!
+//! There is no radio: what is emulated is enough for the firmware and games
+//! to initialize WiFi: the W_MODE_RST / power handshake, baseband and RF
+//! chip access, the 8KB WiFi RAM with its RX and TX circular buffer ports,
+//! and the two timers (microsecond counter with compare, and the beacon
+//! countdowns) with their interrupts. Timers are driven by emulated ARM7
+//! cycles only, so runs are deterministic.
+//!
+//! Frames queued through W_TXREQ_SET are collected for
+//! [`Emulator::wifi_take_sent_frames`] and frames handed to
+//! [`Emulator::wifi_receive_frame`] are written into the RX buffer. At the
+//! end of each frame both directions go through the [`net::WifiTransport`]
+//! set with [`Emulator::set_wifi_transport`], if any.
+pub mod net;
+
+use crate::emulator::Emulator;
+use crate::error::EmuError;
+use crate::interrupts::Interrupt;
+use crate::savestate::{Savestate, StateReader, StateWriter};
+use lunaris_ds_mem_const::SYSTEM_CLOCK_HZ;
+use net::WifiTransport;
+
+/// Size of the register window at 0x04808000 in halfwords.
+const REG_COUNT: usize = 0x800;
+
+/// Size of the WiFi RAM at 0x04804000 in bytes.
+const RAM_SIZE: usize = 0x2000;
+
+/// Size of the RX and TX frame headers in WiFi RAM.
+const FRAME_HEADER_LEN: usize = 12;
+
+/// W_IF / W_IE bits raised by the RX/TX side.
+const IRQ_RX_COMPLETE: u16 = 1 << 0;
+const IRQ_TX_COMPLETE: u16 = 1 << 1;
+const IRQ_TXBUF_COUNT: u16 = 1 << 8;
+const IRQ_RXBUF_COUNT: u16 = 1 << 9;
+
+/// W_IF / W_IE bits raised by the timers.
+const IRQ_POST_BEACON: u16 = 1 << 13;
+const IRQ_BEACON: u16 = 1 << 14;
+const IRQ_PRE_BEACON: u16 = 1 << 15;
+
+/// WiFi Controller
+/// This is a stub implementation for WiFi hardware
+/// Full WiFi emulation is not currently implemented
+#[derive(Debug)]
+pub struct WiFi {
+    /// Power control register (0x0236)
+    w_power_us: u16,
+
+    /// Baseband write register (0x015A)
+    w_bb_write: u16,
+    /// Baseband read register (0x015C)
+    w_bb_read: u16,
+    /// Baseband mode register (0x0160)
+    w_bb_mode: u16,
+    /// Baseband power register (0x0168)
+    w_bb_power: u16,
+
+    /// RF control register (0x0184)
+    w_rf_cnt: u16,
+
+    /// Baseband busy flag
+    bb_busy: bool,
+    /// RF busy flag
+    rf_busy: bool,
+
+    /// Baseband chip registers
+    bb_regs: [u8; 0x100],
+    /// RF chip registers, 18 bits each
+    rf_regs: [u32; 0x20],
+    /// RF data (0x017C, 0x017E)
+    w_rf_data2: u16,
+    w_rf_data1: u16,
+
+    /// Mode / reset register (0x0004)
+    w_mode_rst: u16,
+    /// Interrupt flags (0x0010)
+    w_if: u16,
+    /// Interrupt enable (0x0012)
+    w_ie: u16,
+    /// Power state (0x003C), bit 9 is computed on read
+    w_powerstate: u16,
+    /// Forced power state (0x0040)
+    w_powerforce: u16,
+    /// RF pins (0x019C)
+    w_rf_pins: u16,
+    /// RF status (0x0214)
+    w_rf_status: u16,
+
+    /// Microsecond counter enable (0x00E8)
+    w_us_countcnt: u16,
+    /// Microsecond compare enable (0x00EA)
+    w_us_comparecnt: u16,
+    /// Microsecond counter (0x00F8..0x00FE)
+    us_count: u64,
+    /// Microsecond compare value (0x00F0..0x00F6)
+    us_compare: u64,
+    /// Beacon interval in ms (0x008C)
+    w_beacon_int: u16,
+    /// Pre-beacon time in us (0x0110)
+    w_pre_beacon: u16,
+    /// Countdown to the next beacon in ms (0x0134)
+    w_beacon_count1: u16,
+    /// Countdown after the beacon in ms (0x011C)
+    w_beacon_count2: u16,
+    /// ARM7 cycles not yet converted to microseconds, scaled by 1_000_000
+    cycle_remainder: u64,
+
+    /// Other registers, read back as written
+    regs: Box<[u16; REG_COUNT]>,
+    /// WiFi RAM, holding the RX and TX buffers
+    ram: Box<[u8; RAM_SIZE]>,
+    /// Frames transmitted since the last [`WiFi::take_sent_frames`],
+    /// without TX header and FCS. Not part of savestates.
+    sent_frames: Vec<Vec<u8>>,
+}
+
+impl Default for WiFi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WiFi {
+    /// Create new WiFi controller
+    pub fn new() -> Self {
+        WiFi {
+            w_power_us: 0,
+            w_bb_write: 0,
+            w_bb_read: 0,
+            w_bb_mode: 0,
+            w_bb_power: 0,
+            w_rf_cnt: 0,
+            bb_busy: false,
+            rf_busy: false,
+            bb_regs: [0; 0x100],
+            rf_regs: [0; 0x20],
+            w_rf_data2: 0,
+            w_rf_data1: 0,
+            w_mode_rst: 0,
+            w_if: 0,
+            w_ie: 0,
+            w_powerstate: 0,
+            w_powerforce: 0,
+            w_rf_pins: 0x0004,
+            w_rf_status: 0,
+            w_us_countcnt: 0,
+            w_us_comparecnt: 0,
+            us_count: 0,
+            us_compare: 0,
+            w_beacon_int: 0,
+            w_pre_beacon: 0,
+            w_beacon_count1: 0,
+            w_beacon_count2: 0,
+            cycle_remainder: 0,
+            regs: Box::new([0; REG_COUNT]),
+            ram: Box::new([0; RAM_SIZE]),
+            sent_frames: Vec::new(),
+        }
+    }
+
+    /// Perform baseband read operation
+    fn bb_read(&mut self, index: u16) {
+        match index {
+            // Chip ID
+            0 => self.w_bb_read = 0x6D,
+            _ => self.w_bb_read = self.bb_regs[index as usize & 0xFF] as u16,
+        }
+    }
+
+    /// Perform baseband write operation
+    fn bb_write(&mut self, index: u16) {
+        // Registers 0x01..0x0C, 0x13..0x15, 0x1B..0x26, 0x28..0x4C, 0x4E..0x5C,
+        // 0x62..0x63, 0x65, 0x67..0x68 are writable; the rest is read-only.
+        let writable = matches!(
+            index,
+            0x01..=0x0C
+                | 0x13..=0x15
+                | 0x1B..=0x26
+                | 0x28..=0x4C
+                | 0x4E..=0x5C
+                | 0x62..=0x63
+                | 0x65
+                | 0x67..=0x68
+        );
+        if writable {
+            self.bb_regs[index as usize] = self.w_bb_write as u8;
+        }
+    }
+
+    /// Set power control register
+    pub fn set_w_power_us(&mut self, value: u16) {
+        self.w_power_us = value & 0x3;
+    }
+
+    /// Set baseband control register
+    pub fn set_w_bb_cnt(&mut self, value: u16) {
+        // Baseband count/control
+        let index = value & 0xFF;
+        let direction = value >> 12;
+
+        if direction == 5 {
+            self.bb_write(index);
+        } else if direction == 6 {
+            self.bb_read(index);
+        }
+    }
+
+    /// Set baseband write register
+    pub fn set_w_bb_write(&mut self, value: u16) {
+        self.w_bb_write = value;
+    }
+
+    /// Set baseband mode register
+    pub fn set_w_bb_mode(&mut self, value: u16) {
+        self.w_bb_mode = value;
+    }
+
+    /// Set baseband power register
+    pub fn set_w_bb_power(&mut self, value: u16) {
+        self.w_bb_power = value;
+    }
+
+    /// Set RF control register
+    pub fn set_w_rf_cnt(&mut self, value: u16) {
+        self.w_rf_cnt = value;
+    }
+
+    /// Serial transfer to the RF chip, started by writing W_RF_DATA1.
+    ///
+    /// W_RF_DATA2 bits 2-6 select the register and bit 7 reads it; the
+    /// 18-bit value is W_RF_DATA1 plus W_RF_DATA2 bits 0-1.
+    fn rf_transfer(&mut self) {
+        let index = (self.w_rf_data2 >> 2) as usize & 0x1F;
+        if self.w_rf_data2 & 0x80 != 0 {
+            let data = self.rf_regs[index];
+            self.w_rf_data1 = data as u16;
+            self.w_rf_data2 = (self.w_rf_data2 & !0x3) | ((data >> 16) as u16 & 0x3);
+        } else {
+            self.rf_regs[index] = self.w_rf_data1 as u32 | ((self.w_rf_data2 as u32 & 0x3) << 16);
+        }
+    }
+
+    /// Check if RF is busy
+    pub fn get_w_rf_busy(&self) -> bool {
+        self.rf_busy
+    }
+
+    /// Get baseband read register
+    pub fn get_w_bb_read(&self) -> u16 {
+        self.w_bb_read
+    }
+
+    /// Check if baseband is busy
+    pub fn get_w_bb_busy(&self) -> bool {
+        self.bb_busy
+    }
+
+    /// Set mode / reset register.
+    ///
+    /// Bit 0 switches between initial (0) and normal (1) mode; the RF side
+    /// reports the matching status immediately, which is what the firmware
+    /// and SDK init code wait for.
+    pub fn set_w_mode_rst(&mut self, value: u16) {
+        let old = self.w_mode_rst;
+        self.w_mode_rst = value & 0x0FFF;
+
+        if value & 1 != 0 && old & 1 == 0 {
+            self.w_rf_pins = 0x0046;
+            self.w_rf_status = 9;
+        } else if value & 1 == 0 && old & 1 != 0 {
+            self.w_rf_pins = 0x0004;
+            self.w_rf_status = 0;
+        }
+
+        if value & (1 << 13) != 0 {
+            // Reset TX/RX state; only the interrupt state exists here
+            self.w_if = 0;
+        }
+        if value & (1 << 14) != 0 {
+            self.w_us_countcnt = 0;
+            self.w_us_comparecnt = 0;
+        }
+    }
+
+    /// Whether the transceiver is powered down (W_POWERSTATE bit 9).
+    fn powered_down(&self) -> bool {
+        if self.w_powerforce & 0x8000 != 0 {
+            return self.w_powerforce & 1 != 0;
+        }
+        self.w_power_us & 1 != 0
+    }
+
+    fn reg(&self, offset: u32) -> u16 {
+        self.regs[offset as usize >> 1]
+    }
+
+    fn set_reg(&mut self, offset: u32, value: u16) {
+        self.regs[offset as usize >> 1] = value;
+    }
+
+    /// Read a halfword of WiFi RAM at `offset` from 0x04804000.
+    pub fn read_ram(&self, offset: u32) -> u16 {
+        let offset = offset as usize & (RAM_SIZE - 2);
+        u16::from_le_bytes([self.ram[offset], self.ram[offset + 1]])
+    }
+
+    /// Write a halfword of WiFi RAM at `offset` from 0x04804000.
+    pub fn write_ram(&mut self, offset: u32, value: u16) {
+        let offset = offset as usize & (RAM_SIZE - 2);
+        self.ram[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Next halfword of the RX buffer through W_RXBUF_RD_DATA.
+    ///
+    /// Advances W_RXBUF_RD_ADDR, wrapping from W_RXBUF_END to
+    /// W_RXBUF_BEGIN and skipping W_RXBUF_GAP, and counts down
+    /// W_RXBUF_COUNT.
+    fn read_rx_data(&mut self) -> u16 {
+        let count = self.reg(0x05C);
+        if count != 0 {
+            self.set_reg(0x05C, count - 1);
+            if count == 1 {
+                self.w_if |= IRQ_RXBUF_COUNT;
+            }
+        }
+
+        let begin = self.reg(0x050) & 0x1FFE;
+        let end = self.reg(0x052) & 0x1FFE;
+        let mut address = self.reg(0x058) & 0x1FFE;
+        let value = self.read_ram(address as u32);
+
+        address += 2;
+        if address == end {
+            address = begin;
+        }
+        if address == self.reg(0x062) & 0x1FFE {
+            address += self.reg(0x064) << 1;
+            if address >= end {
+                address = address + begin - end;
+            }
+        }
+        self.set_reg(0x058, address & 0x1FFE);
+        self.set_reg(0x060, value);
+        value
+    }
+
+    /// Store a halfword in the TX buffer through W_TXBUF_WR_DATA.
+    fn write_tx_data(&mut self, value: u16) {
+        let mut address = self.reg(0x068) & 0x1FFE;
+        self.write_ram(address as u32, value);
+
+        address += 2;
+        if address == self.reg(0x074) & 0x1FFE {
+            address += self.reg(0x076) << 1;
+        }
+        self.set_reg(0x068, address & 0x1FFE);
+
+        let count = self.reg(0x06C);
+        if count != 0 {
+            self.set_reg(0x06C, count - 1);
+            if count == 1 {
+                self.w_if |= IRQ_TXBUF_COUNT;
+            }
+        }
+    }
+
+    /// Send the frames of the TX slots requested in `slots` (W_TXREQ bits:
+    /// 0 = LOC1, 2 = LOC2, 3 = LOC3) whose W_TXBUF_LOCn bit 15 is set.
+    ///
+    /// Each slot points at a TX header in WiFi RAM; the frame is taken out
+    /// whole, the header status is marked done and TX complete is raised.
+    fn transmit(&mut self, slots: u16) {
+        for (bit, loc_offset) in [(0, 0x0A0), (2, 0x0A4), (3, 0x0A8)] {
+            let loc = self.reg(loc_offset);
+            if slots & (1 << bit) == 0 || loc & 0x8000 == 0 {
+                continue;
+            }
+
+            let header = (loc as usize & 0xFFF) << 1;
+            // Frame length including the 4-byte FCS
+            let len = self.read_ram((header + 0xA) as u32) as usize;
+            let frame = (0..len.saturating_sub(4))
+                .map(|i| self.ram[(header + FRAME_HEADER_LEN + i) & (RAM_SIZE - 1)])
+                .collect();
+            self.sent_frames.push(frame);
+
+            self.write_ram(header as u32, 0x0001);
+            self.set_reg(loc_offset, loc & !0x8000);
+            self.set_reg(0x0B0, self.reg(0x0B0) & !(1 << bit));
+            self.set_reg(0x0B8, 0x0001 | (bit << 8));
+            self.w_if |= IRQ_TX_COMPLETE;
+        }
+    }
+
+    /// Frames sent since the last call, without TX header and FCS.
+    pub fn take_sent_frames(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.sent_frames)
+    }
+
+    /// Queue a received IEEE 802.11 frame (without FCS) in the RX buffer.
+    ///
+    /// Needs normal mode and W_RXCNT bit 15. The frame gets an RX header and
+    /// is written at W_RXBUF_WRCSR, which then moves to the next word.
+    /// Returns `false` if the frame was dropped.
+    pub fn receive_frame(&mut self, frame: &[u8]) -> bool {
+        if self.w_mode_rst & 1 == 0 || self.reg(0x030) & 0x8000 == 0 {
+            return false;
+        }
+
+        let begin = self.reg(0x050) & 0x1FFE;
+        let end = self.reg(0x052) & 0x1FFE;
+        if begin >= end {
+            return false;
+        }
+
+        let mut header = [0u8; FRAME_HEADER_LEN];
+        // Flags: data frame; 2Mbit/s; length; max RSSI
+        header[0..2].copy_from_slice(&0x0010u16.to_le_bytes());
+        header[6..8].copy_from_slice(&0x0014u16.to_le_bytes());
+        header[8..10].copy_from_slice(&(frame.len() as u16).to_le_bytes());
+        header[10..12].copy_from_slice(&0x0040u16.to_le_bytes());
+
+        let mut address = (self.reg(0x054) << 1) & 0x1FFE;
+        let bytes = header.iter().chain(frame).copied().collect::<Vec<_>>();
+        // Frames start on word boundaries
+        let len = bytes.len().next_multiple_of(4);
+        if len >= (end - begin) as usize {
+            return false;
+        }
+        for i in (0..len).step_by(2) {
+            let lo = bytes.get(i).copied().unwrap_or(0);
+            let hi = bytes.get(i + 1).copied().unwrap_or(0);
+            self.write_ram(address as u32, u16::from_le_bytes([lo, hi]));
+            address += 2;
+            if address == end {
+                address = begin;
+            }
+        }
+
+        self.set_reg(0x054, address >> 1);
+        self.w_if |= IRQ_RX_COMPLETE;
+        true
+    }
+
+    /// Read a register at `offset` from 0x04808000.
+    pub fn read_reg(&mut self, offset: u32) -> u16 {
+        let offset = offset & 0xFFE;
+        match offset {
+            // W_ID
+            0x000 => 0x1440,
+            0x004 => self.w_mode_rst,
+            0x010 => self.w_if,
+            0x012 => self.w_ie,
+            0x036 => self.w_power_us,
+            0x03C => (self.w_powerstate & 0x3) | ((self.powered_down() as u16) << 9),
+            0x040 => self.w_powerforce,
+            0x08C => self.w_beacon_int,
+            0x0E8 => self.w_us_countcnt,
+            0x0EA => self.w_us_comparecnt,
+            0x0F0..=0x0F6 => (self.us_compare >> ((offset - 0x0F0) * 8)) as u16,
+            0x060 => self.read_rx_data(),
+            0x0F8..=0x0FE => (self.us_count >> ((offset - 0x0F8) * 8)) as u16,
+            0x110 => self.w_pre_beacon,
+            0x11C => self.w_beacon_count2,
+            0x134 => self.w_beacon_count1,
+            0x15A => self.w_bb_write,
+            0x15C => self.get_w_bb_read(),
+            0x15E => self.get_w_bb_busy() as u16,
+            0x160 => self.w_bb_mode,
+            0x168 => self.w_bb_power,
+            0x17C => self.w_rf_data2,
+            0x17E => self.w_rf_data1,
+            0x180 => self.get_w_rf_busy() as u16,
+            0x184 => self.w_rf_cnt,
+            0x19C => self.w_rf_pins,
+            0x214 => self.w_rf_status,
+            _ => self.regs[offset as usize >> 1],
+        }
+    }
+
+    /// Write a register at `offset` from 0x04808000.
+    pub fn write_reg(&mut self, offset: u32, value: u16) {
+        let offset = offset & 0xFFE;
+        match offset {
+            0x000 => {}
+            0x004 => self.set_w_mode_rst(value),
+            // Writing 1 acknowledges
+            0x010 => self.w_if &= !value,
+            0x012 => self.w_ie = value,
+            // W_RXCNT: bit 0 latches W_RXBUF_WR_ADDR into W_RXBUF_WRCSR
+            0x030 => {
+                self.set_reg(0x030, value & 0xFF0E);
+                if value & 1 != 0 {
+                    self.set_reg(0x054, self.reg(0x056) & 0x0FFF);
+                }
+            }
+            0x036 => self.set_w_power_us(value),
+            // Bit 1 requests power-up, which completes instantly
+            0x03C => self.w_powerstate = value & 0x3 & !0x2,
+            0x040 => self.w_powerforce = value & 0x8001,
+            0x056 => self.set_reg(0x056, value & 0x0FFF),
+            0x070 => self.write_tx_data(value),
+            0x08C => self.w_beacon_int = value & 0x3FF,
+            0x0AC => self.set_reg(0x0B0, self.reg(0x0B0) & !value),
+            0x0AE => {
+                self.set_reg(0x0B0, self.reg(0x0B0) | (value & 0xF));
+                if self.w_mode_rst & 1 != 0 {
+                    self.transmit(value);
+                }
+            }
+            0x0E8 => self.w_us_countcnt = value & 1,
+            0x0EA => self.w_us_comparecnt = value & 1,
+            0x0F0..=0x0F6 => {
+                let shift = (offset - 0x0F0) * 8;
+                // Bit 0 only triggers an immediate beacon, compare has 1024us steps
+                let value = match offset {
+                    0x0F0 => value & 0xFC00,
+                    _ => value,
+                };
+                self.us_compare =
+                    (self.us_compare & !(0xFFFF << shift)) | ((value as u64) << shift);
+            }
+            0x0F8..=0x0FE => {
+                let shift = (offset - 0x0F8) * 8;
+                self.us_count = (self.us_count & !(0xFFFF << shift)) | ((value as u64) << shift);
+            }
+            0x110 => self.w_pre_beacon = value,
+            0x11C => self.w_beacon_count2 = value,
+            0x134 => self.w_beacon_count1 = value,
+            0x158 => self.set_w_bb_cnt(value),
+            0x15A => self.set_w_bb_write(value),
+            0x160 => self.set_w_bb_mode(value),
+            0x168 => self.set_w_bb_power(value),
+            0x17C => self.w_rf_data2 = value,
+            0x17E => {
+                self.w_rf_data1 = value;
+                self.rf_transfer();
+            }
+            0x184 => self.set_w_rf_cnt(value),
+            // Read-only
+            0x054 | 0x060 | 0x0B0 | 0x15C | 0x15E | 0x180 | 0x19C | 0x214 => {}
+            _ => self.regs[offset as usize >> 1] = value,
+        }
+    }
+
+    /// Advance the timers by `cycles` ARM7 cycles.
+    ///
+    /// Returns `true` if an enabled interrupt was raised.
+    pub fn run(&mut self, cycles: u64) -> bool {
+        if self.powered_down() || self.w_mode_rst & 1 == 0 || self.w_us_countcnt & 1 == 0 {
+            return false;
+        }
+
+        self.cycle_remainder += cycles * 1_000_000;
+        let elapsed_us = self.cycle_remainder / SYSTEM_CLOCK_HZ;
+        self.cycle_remainder %= SYSTEM_CLOCK_HZ;
+
+        let old_if = self.w_if;
+        for _ in 0..elapsed_us {
+            self.tick_us();
+        }
+        (self.w_if & !old_if & self.w_ie) != 0
+    }
+
+    fn tick_us(&mut self) {
+        self.us_count = self.us_count.wrapping_add(1);
+
+        if self.w_us_comparecnt & 1 != 0 && self.us_count == self.us_compare {
+            self.w_if |= IRQ_BEACON;
+            self.w_beacon_count1 = self.w_beacon_int;
+            self.us_compare = self
+                .us_compare
+                .wrapping_add((self.w_beacon_int as u64) << 10);
+        }
+
+        // The beacon countdowns tick in 1024us time units
+        if self.us_count & 0x3FF != 0 {
+            return;
+        }
+        if self.w_beacon_count1 != 0 {
+            self.w_beacon_count1 -= 1;
+            if self.w_beacon_count1 == self.w_pre_beacon >> 10 {
+                self.w_if |= IRQ_PRE_BEACON;
+            }
+        }
+        if self.w_beacon_count2 != 0 {
+            self.w_beacon_count2 -= 1;
+            if self.w_beacon_count2 == 0 {
+                self.w_if |= IRQ_POST_BEACON;
+            }
+        }
+    }
+}
+
+impl Savestate for WiFi {
+    fn save_state(&self, w: &mut StateWriter) {
+        for value in [
+            self.w_power_us,
+            self.w_bb_write,
+            self.w_bb_read,
+            self.w_bb_mode,
+            self.w_bb_power,
+            self.w_rf_cnt,
+            self.w_mode_rst,
+            self.w_if,
+            self.w_ie,
+            self.w_powerstate,
+            self.w_powerforce,
+            self.w_rf_pins,
+            self.w_rf_status,
+            self.w_us_countcnt,
+            self.w_us_comparecnt,
+            self.w_beacon_int,
+            self.w_pre_beacon,
+            self.w_beacon_count1,
+            self.w_beacon_count2,
+            self.w_rf_data2,
+            self.w_rf_data1,
+        ] {
+            w.u16(value);
+        }
+        w.bool(self.bb_busy);
+        w.bool(self.rf_busy);
+        w.bytes(&self.bb_regs);
+        for &reg in &self.rf_regs {
+            w.u32(reg);
+        }
+        w.u64(self.us_count);
+        w.u64(self.us_compare);
+        w.u64(self.cycle_remainder);
+        for &reg in self.regs.iter() {
+            w.u16(reg);
+        }
+        w.bytes(&self.ram[..]);
+    }
+
+    fn load_state(&mut self, r: &mut StateReader<'_>) -> Result<(), EmuError> {
+        for value in [
+            &mut self.w_power_us,
+            &mut self.w_bb_write,
+            &mut self.w_bb_read,
+            &mut self.w_bb_mode,
+            &mut self.w_bb_power,
+            &mut self.w_rf_cnt,
+            &mut self.w_mode_rst,
+            &mut self.w_if,
+            &mut self.w_ie,
+            &mut self.w_powerstate,
+            &mut self.w_powerforce,
+            &mut self.w_rf_pins,
+            &mut self.w_rf_status,
+            &mut self.w_us_countcnt,
+            &mut self.w_us_comparecnt,
+            &mut self.w_beacon_int,
+            &mut self.w_pre_beacon,
+            &mut self.w_beacon_count1,
+            &mut self.w_beacon_count2,
+            &mut self.w_rf_data2,
+            &mut self.w_rf_data1,
+        ] {
+            *value = r.u16()?;
+        }
+        self.bb_busy = r.bool()?;
+        self.rf_busy = r.bool()?;
+        r.bytes(&mut self.bb_regs)?;
+        for reg in &mut self.rf_regs {
+            *reg = r.u32()?;
+        }
+        self.us_count = r.u64()?;
+        self.us_compare = r.u64()?;
+        self.cycle_remainder = r.u64()?;
+        for reg in self.regs.iter_mut() {
+            *reg = r.u16()?;
+        }
+        r.bytes(&mut self.ram[..])?;
+        Ok(())
+    }
+}
+
+impl Emulator {
+    /// Advance the WiFi timers after the ARM7 ran `cycles` cycles.
+    pub(crate) fn wifi_run(&mut self, cycles: u64) {
+        if self.pow_cnt2.wifi && self.wifi.run(cycles) {
+            self.request_interrupt7(Interrupt::Wifi);
+        }
+    }
+
+    /// Read a WiFi register; the RX data port has side effects.
+    pub(crate) fn wifi_read_reg(&mut self, offset: u32) -> u16 {
+        let old_if = self.wifi.w_if;
+        let value = self.wifi.read_reg(offset);
+        self.wifi_raise_irq(old_if);
+        value
+    }
+
+    /// Write a WiFi register.
+    pub(crate) fn wifi_write_reg(&mut self, offset: u32, value: u16) {
+        let old_if = self.wifi.w_if;
+        self.wifi.write_reg(offset, value);
+        self.wifi_raise_irq(old_if);
+    }
+
+    fn wifi_raise_irq(&mut self, old_if: u16) {
+        if self.pow_cnt2.wifi && (self.wifi.w_if & !old_if & self.wifi.w_ie) != 0 {
+            self.request_interrupt7(Interrupt::Wifi);
+        }
+    }
+
+    /// Hand a received IEEE 802.11 frame (without FCS) to the WiFi
+    /// controller, see [`WiFi::receive_frame`].
+    pub fn wifi_receive_frame(&mut self, frame: &[u8]) -> bool {
+        let old_if = self.wifi.w_if;
+        let received = self.wifi.receive_frame(frame);
+        self.wifi_raise_irq(old_if);
+        received
+    }
+
+    /// Frames the game sent since the last call, see
+    /// [`WiFi::take_sent_frames`].
+    pub fn wifi_take_sent_frames(&mut self) -> Vec<Vec<u8>> {
+        self.wifi.take_sent_frames()
+    }
+
+    /// Connect to other instances for local wireless play, or disconnect
+    /// with `None`.
+    pub fn set_wifi_transport(&mut self, transport: Option<Box<dyn WifiTransport>>) {
+        self.wifi_transport = transport;
+    }
+
+    /// Exchange frames with the transport, once per frame. Frames sent
+    /// without a transport are dropped.
+    pub(crate) fn wifi_end_frame(&mut self) {
+        let sent = self.wifi.take_sent_frames();
+        let Some(transport) = &mut self.wifi_transport else {
+            return;
+        };
+        for frame in &sent {
+            transport.send(frame);
+        }
+        let received = std::iter::from_fn(|| transport.receive()).collect::<Vec<_>>();
+        for frame in received {
+            self.wifi_receive_frame(&frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_handshake_and_timer() {
+        let mut wifi = WiFi::new();
+        wifi.write_reg(0x036, 0);
+        wifi.write_reg(0x004, 1);
+        assert_eq!(wifi.read_reg(0x214), 9);
+        assert_eq!(wifi.read_reg(0x03C) & (1 << 9), 0);
+
+        // Compare match after 2048us raises the beacon IRQ
+        wifi.write_reg(0x012, IRQ_BEACON);
+        wifi.write_reg(0x0E8, 1);
+        wifi.write_reg(0x0EA, 1);
+        wifi.write_reg(0x0F0, 0x0800);
+        assert!(!wifi.run(SYSTEM_CLOCK_HZ * 2047 / 1_000_000));
+        assert!(wifi.run(SYSTEM_CLOCK_HZ / 1000));
+        assert_eq!(wifi.read_reg(0x010), IRQ_BEACON);
+
+        // Round trip through a savestate
+        let mut w = StateWriter::new();
+        wifi.save_state(&mut w);
+        let data = w.into_inner();
+        let mut restored = WiFi::new();
+        restored.load_state(&mut StateReader::new(&data)).unwrap();
+        assert_eq!(restored.read_reg(0x0F8), wifi.read_reg(0x0F8));
+        assert_eq!(restored.read_reg(0x010), IRQ_BEACON);
+        assert!(
+            restored
+                .load_state(&mut StateReader::new(&data[..10]))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_rf_and_buffers() {
+        let mut wifi = WiFi::new();
+        wifi.write_reg(0x004, 1);
+
+        // RF register 5 write, then read back
+        wifi.write_reg(0x17C, (5 << 2) | 0x2);
+        wifi.write_reg(0x17E, 0x1234);
+        wifi.write_reg(0x17C, (5 << 2) | 0x80);
+        wifi.write_reg(0x17E, 0);
+        assert_eq!(wifi.read_reg(0x17E), 0x1234);
+        assert_eq!(wifi.read_reg(0x17C) & 0x3, 0x2);
+
+        // TX: header at 0x0000 with an 8-byte frame plus FCS
+        wifi.write_reg(0x068, 0x0000);
+        wifi.write_reg(0x06C, 10);
+        let header = [0, 0, 0, 0, 0x14, 12];
+        let body = [0x0201, 0x0403, 0x0605, 0x0807];
+        for value in header.into_iter().chain(body) {
+            wifi.write_reg(0x070, value);
+        }
+        assert_eq!(wifi.read_reg(0x010), IRQ_TXBUF_COUNT);
+        wifi.write_reg(0x0A0, 0x8000);
+        wifi.write_reg(0x0AE, 1);
+        assert_eq!(wifi.take_sent_frames(), [vec![1, 2, 3, 4, 5, 6, 7, 8]]);
+        assert_eq!(wifi.read_ram(0), 0x0001);
+        assert_eq!(wifi.read_reg(0x0A0), 0);
+
+        // RX: buffer 0x0C00..0x0C20, frame wraps around the end
+        wifi.write_reg(0x050, 0x4C00);
+        wifi.write_reg(0x052, 0x4C20);
+        wifi.write_reg(0x056, 0x0C18 >> 1);
+        wifi.write_reg(0x030, 0x8001);
+        assert!(wifi.receive_frame(&[0xAA, 0xBB, 0xCC]));
+        assert_eq!(wifi.read_reg(0x054), 0x0C08 >> 1);
+        assert_ne!(wifi.read_reg(0x010) & IRQ_RX_COMPLETE, 0);
+
+        wifi.write_reg(0x058, 0x0C18);
+        wifi.write_reg(0x05C, 8);
+        let data = (0..8).map(|_| wifi.read_reg(0x060)).collect::<Vec<_>>();
+        assert_eq!(data[4], 3);
+        assert_eq!(data[6..], [0xBBAA, 0x00CC]);
+        assert_eq!(wifi.read_reg(0x058), 0x0C08);
+        assert_ne!(wifi.read_reg(0x010) & IRQ_RXBUF_COUNT, 0);
+
+        // Too large for the buffer
+        assert!(!wifi.receive_frame(&[0; 0x20]));
+    }
+}
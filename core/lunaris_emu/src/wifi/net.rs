@@ -0,0 +1,165 @@
+//! Local wireless bridge
+//!
+//! Carries the 802.11 frames of the [`WiFi`](super::WiFi) controller between
+//! emulator instances. Frames are exchanged once per emulated frame through
+//! a [`WifiTransport`]; [`UdpTransport`] sends each frame as one datagram to
+//! a list of peers, any of which may be a multicast group.
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+
+use snafu::ResultExt as _;
+
+use crate::error::{EmuError, WifiSocketSnafu};
+
+/// First bytes of every datagram sent by [`UdpTransport`].
+const PACKET_MAGIC: [u8; 4] = *b"LNWF";
+/// Magic plus sender id.
+const PACKET_HEADER_LEN: usize = 8;
+/// Largest frame the WiFi RAM can hold, plus the header.
+const MAX_PACKET_LEN: usize = 0x2000 + PACKET_HEADER_LEN;
+
+/// Link between emulator instances for local wireless play.
+pub trait WifiTransport: std::fmt::Debug + Send {
+    /// Send a frame (without FCS) to every other instance.
+    fn send(&mut self, frame: &[u8]);
+
+    /// Next frame received from another instance. Must not block.
+    fn receive(&mut self) -> Option<Vec<u8>>;
+}
+
+/// Frames over UDP, one datagram each.
+///
+/// Every instance binds its own address and sends to all peers. Several
+/// instances on one host need distinct ports; across hosts a multicast group
+/// as the only peer lets all instances share one port. Datagrams looped back
+/// from this instance are dropped.
+#[derive(Debug)]
+pub struct UdpTransport {
+    socket: UdpSocket,
+    local_addr: SocketAddr,
+    peers: Vec<SocketAddr>,
+    /// Tells our own multicast loopback apart from other instances
+    instance_id: u32,
+}
+
+impl UdpTransport {
+    /// Bind a non-blocking socket to `address` and send to `peers`.
+    ///
+    /// # Errors
+    /// If the socket could not be bound or a multicast group not joined.
+    pub fn bind(address: SocketAddr, peers: &[SocketAddr]) -> Result<Self, EmuError> {
+        let socket = UdpSocket::bind(address).context(WifiSocketSnafu { address })?;
+        socket
+            .set_nonblocking(true)
+            .context(WifiSocketSnafu { address })?;
+        let local_addr = socket.local_addr().context(WifiSocketSnafu { address })?;
+
+        let mut transport = Self {
+            socket,
+            local_addr,
+            peers: Vec::new(),
+            instance_id: (std::process::id() << 16) ^ local_addr.port() as u32,
+        };
+        for &peer in peers {
+            transport.add_peer(peer)?;
+        }
+        Ok(transport)
+    }
+
+    /// Also send to `peer`, joining it if it is an IPv4 multicast group.
+    ///
+    /// # Errors
+    /// If the multicast group could not be joined.
+    pub fn add_peer(&mut self, peer: SocketAddr) -> Result<(), EmuError> {
+        if let IpAddr::V4(group) = peer.ip()
+            && group.is_multicast()
+        {
+            let interface = match self.local_addr.ip() {
+                IpAddr::V4(ip) => ip,
+                IpAddr::V6(_) => std::net::Ipv4Addr::UNSPECIFIED,
+            };
+            self.socket
+                .join_multicast_v4(&group, &interface)
+                .context(WifiSocketSnafu { address: peer })?;
+        }
+        self.peers.push(peer);
+        Ok(())
+    }
+
+    /// Address the socket is bound to, with the port picked by the system
+    /// when binding port 0.
+    pub const fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl WifiTransport for UdpTransport {
+    fn send(&mut self, frame: &[u8]) {
+        let mut packet = Vec::with_capacity(PACKET_HEADER_LEN + frame.len());
+        packet.extend_from_slice(&PACKET_MAGIC);
+        packet.extend_from_slice(&self.instance_id.to_le_bytes());
+        packet.extend_from_slice(frame);
+
+        for peer in &self.peers {
+            if let Err(_err) = self.socket.send_to(&packet, peer) {
+                #[cfg(feature = "tracing")]
+                tracing::warn!("WiFi: failed to send frame to {peer}: {_err}");
+            }
+        }
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        let mut buf = [0u8; MAX_PACKET_LEN];
+        loop {
+            let len = match self.socket.recv_from(&mut buf) {
+                Ok((len, _)) => len,
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => return None,
+                Err(_err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("WiFi: failed to receive frame: {_err}");
+                    return None;
+                }
+            };
+
+            let packet = &buf[..len];
+            if len < PACKET_HEADER_LEN || packet[..4] != PACKET_MAGIC {
+                continue;
+            }
+            if packet[4..8] == self.instance_id.to_le_bytes() {
+                continue;
+            }
+            return Some(packet[PACKET_HEADER_LEN..].to_vec());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receive_within(transport: &mut UdpTransport) -> Option<Vec<u8>> {
+        for _ in 0..100 {
+            if let Some(frame) = transport.receive() {
+                return Some(frame);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        None
+    }
+
+    #[test]
+    fn test_udp_transport() {
+        let localhost = SocketAddr::from(([127, 0, 0, 1], 0));
+        let mut a = UdpTransport::bind(localhost, &[]).unwrap();
+        let mut b = UdpTransport::bind(localhost, &[a.local_addr()]).unwrap();
+        a.add_peer(b.local_addr()).unwrap();
+        // Sending to ourselves is filtered out
+        a.add_peer(a.local_addr()).unwrap();
+
+        a.send(&[1, 2, 3]);
+        assert_eq!(receive_within(&mut b).as_deref(), Some(&[1, 2, 3][..]));
+        b.send(&[4, 5]);
+        assert_eq!(receive_within(&mut a).as_deref(), Some(&[4, 5][..]));
+        assert_eq!(a.receive(), None);
+        assert_eq!(b.receive(), None);
+    }
+}
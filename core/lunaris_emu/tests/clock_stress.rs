@@ -0,0 +1,98 @@
+//! Clock ratio stress runs
+//!
+//! Runs the emulator under skewed ARM9:ARM7 clock ratios and sync intervals
+//! (see [`ClockStress`]). The ratios come from `LUNARIS_CLOCK_STRESS` when
+//! it is set, which is how the CI stress job picks its variant; otherwise a
+//! built-in set is used. The vendored test ROMs must report the same
+//! results and leave the same screens as with the default clocks.
+//!
+//! ```sh
+//! LUNARIS_CLOCK_STRESS=1/2,4,arm7-first cargo test -p lunaris_ds_emu --test clock_stress
+//! ```
+mod common;
+
+use lunaris_ds_emu::{ClockStress, Emulator};
+use lunaris_ds_mem_const::{PIXELS_PER_LINE, SCANLINES};
+use lunaris_ds_test_support::frame_hash;
+
+use crate::common::run_rom_on;
+
+const FRAMES: usize = 10;
+
+/// Vendored test ROMs, see `tests/roms/build.sh`
+const ROMS: [&str; 2] = ["cpu_arm.nds", "gpu_2d.nds"];
+
+fn stress_variants() -> Vec<ClockStress> {
+    match ClockStress::from_env() {
+        Some(stress) => vec![stress],
+        None => ["3/2,7", "1/2,4,arm7-first", "1,1"]
+            .into_iter()
+            .filter_map(ClockStress::parse)
+            .collect(),
+    }
+}
+
+fn new_emulator(stress: Option<ClockStress>) -> Box<Emulator> {
+    let mut emu = Box::new(Emulator::new());
+    emu.config.clock_stress = stress;
+    emu
+}
+
+/// Length of each frame in system cycles.
+fn frame_lengths(emu: &mut Emulator) -> Vec<u64> {
+    (0..FRAMES)
+        .map(|_| {
+            let start = emu.system_timestamp;
+            emu.run();
+            emu.system_timestamp - start
+        })
+        .collect()
+}
+
+/// Hashes of both screens of the last frame.
+fn screen_hashes(emu: &mut Emulator) -> (u64, u64) {
+    let mut upper = vec![0; PIXELS_PER_LINE * SCANLINES];
+    let mut lower = vec![0; PIXELS_PER_LINE * SCANLINES];
+    emu.get_upper_frame(&mut upper);
+    emu.get_lower_frame(&mut lower);
+    (frame_hash(&upper), frame_hash(&lower))
+}
+
+#[test]
+fn test_stress_keeps_frame_timing() {
+    let mut reference = new_emulator(None);
+    reference.power_on();
+    let expected = frame_lengths(&mut reference);
+
+    // Events are handled at slice boundaries, so the frame length moves by
+    // up to a slice per event, but the LCD cadence must not change.
+    for stress in stress_variants() {
+        let mut emu = new_emulator(Some(stress));
+        emu.power_on();
+        for (frame, (length, expected)) in frame_lengths(&mut emu)
+            .into_iter()
+            .zip(&expected)
+            .enumerate()
+        {
+            assert!(
+                length.abs_diff(*expected) * 100 < *expected,
+                "{stress:?} frame {frame}: {length} cycles, expected about {expected}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_stress_matches_reference() {
+    for rom in ROMS {
+        let mut reference = new_emulator(None);
+        let matrix = run_rom_on(&mut reference, rom);
+        let hashes = screen_hashes(&mut reference);
+
+        for stress in stress_variants() {
+            let mut emu = new_emulator(Some(stress));
+            assert_eq!(run_rom_on(&mut emu, rom), matrix, "{rom} {stress:?}");
+            assert_eq!(screen_hashes(&mut emu), hashes, "{rom} {stress:?}");
+        }
+    }
+}
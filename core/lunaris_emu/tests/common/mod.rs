@@ -0,0 +1,119 @@
+//! Result block of the vendored test ROMs
+//!
+//! The test ROM suites run the small ROMs in `tests/roms/`, assembled from
+//! the sources next to them by `tests/roms/build.sh`. They report through a
+//! block in main RAM at [`RESULT_BASE`]:
+//!
+//! | offset | size  | content                                   |
+//! |--------|-------|-------------------------------------------|
+//! | 0x00   | 4     | magic `LTST`                              |
+//! | 0x04   | 4     | number of tests `n`                       |
+//! | 0x08   | 4     | non-zero once all tests have run          |
+//! | 0x0C   | n     | status per test: 0 not run, 1 pass, 2 fail |
+//! | 0x0C+n | ...   | NUL separated test names                  |
+//!
+//! The block is written by the macros in `tests/roms/test_rom.inc`.
+
+// Each test binary uses only part of this module
+#![allow(dead_code)]
+
+use std::path::Path;
+
+use lunaris_ds_emu::{ClockStress, CpuType, Emulator};
+
+/// Main RAM address of the result block written by the test ROMs.
+pub const RESULT_BASE: u32 = 0x0230_0000;
+pub const RESULT_MAGIC: u32 = u32::from_le_bytes(*b"LTST");
+/// Give up after this many frames if the ROM never reports completion.
+pub const MAX_FRAMES: u32 = 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    NotRun,
+    Pass,
+    Fail,
+}
+
+impl Status {
+    pub const fn from_byte(byte: u8) -> Self {
+        match byte {
+            1 => Self::Pass,
+            2 => Self::Fail,
+            _ => Self::NotRun,
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::NotRun => "not-run",
+            Self::Pass => "pass",
+            Self::Fail => "fail",
+        }
+    }
+}
+
+/// Pass/fail matrix, in the order the ROM reports it.
+pub type Matrix = Vec<(String, Status)>;
+
+/// The reported matrix, `None` until the ROM has finished.
+pub fn read_matrix(emu: &mut Emulator) -> Option<Matrix> {
+    if emu.read_word(RESULT_BASE, CpuType::Arm9) != RESULT_MAGIC
+        || emu.read_word(RESULT_BASE + 8, CpuType::Arm9) == 0
+    {
+        return None;
+    }
+
+    let count = emu.read_word(RESULT_BASE + 4, CpuType::Arm9);
+    let mut name_addr = RESULT_BASE + 0x0C + count;
+    let mut matrix = Vec::with_capacity(count as usize);
+    for i in 0..count {
+        let status = Status::from_byte(emu.read_byte(RESULT_BASE + 0x0C + i, CpuType::Arm9));
+
+        let mut name = String::new();
+        loop {
+            let ch = emu.read_byte(name_addr, CpuType::Arm9);
+            name_addr += 1;
+            if ch == 0 || name.len() >= 64 {
+                break;
+            }
+            name.push(ch as char);
+        }
+        if name.is_empty() {
+            name = format!("test_{i}");
+        }
+        matrix.push((name, status));
+    }
+    Some(matrix)
+}
+
+/// Boot `tests/roms/<rom>` and run it until it reports its results.
+///
+/// # Panics
+/// If the ROM cannot be loaded or does not finish in [`MAX_FRAMES`].
+pub fn run_rom(rom: &str) -> Matrix {
+    let mut emu = Box::new(Emulator::new());
+    // Results must not depend on the clock ratio, see tests/clock_stress.rs
+    emu.config.clock_stress = ClockStress::from_env();
+    run_rom_on(&mut emu, rom)
+}
+
+/// [`run_rom`] on an emulator the caller has configured.
+///
+/// # Panics
+/// If the ROM cannot be loaded or does not finish in [`MAX_FRAMES`].
+pub fn run_rom_on(emu: &mut Emulator, rom: &str) -> Matrix {
+    let rom_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/roms")
+        .join(rom);
+    // No save file next to the vendored ROMs
+    emu.config.read_only = true;
+    emu.load_rom(&rom_path).unwrap();
+
+    for _ in 0..MAX_FRAMES {
+        emu.run();
+        if let Some(matrix) = read_matrix(emu) {
+            return matrix;
+        }
+    }
+    panic!("{rom} did not finish in {MAX_FRAMES} frames");
+}
@@ -0,0 +1,71 @@
+//! ARM / Thumb CPU test ROM suite
+//!
+//! Runs the instruction test ROMs in `tests/roms/` headless and requires
+//! every test they report to pass. The ROMs are our own, assembled with
+//! `tests/roms/build.sh`; ARMwrestler and rockwrestler are not vendored.
+//!
+//! - `cpu_arm`: ARM and Thumb ALU ops and their flags, LDR/STR addressing
+//!   modes and LDM/STM, including the writeback and base-in-list edge cases
+//! - `cpu_armv5`: the ARMv5TE additions on the ARM9 (CLZ, saturating and
+//!   DSP multiplies, LDRD/STRD, BLX, interworking loads)
+//!
+//! The ROMs report through the result block described in `tests/common`.
+mod common;
+
+use lunaris_ds_emu::{CpuType, Emulator};
+
+use crate::common::{Matrix, RESULT_BASE, RESULT_MAGIC, Status, read_matrix, run_rom};
+
+/// One line per test that did not pass.
+fn failures(matrix: &Matrix) -> String {
+    matrix
+        .iter()
+        .filter(|(_, status)| *status != Status::Pass)
+        .map(|(name, status)| format!("  {name}: {}\n", status.as_str()))
+        .collect()
+}
+
+fn run_suite(rom: &str) {
+    let matrix = run_rom(rom);
+    assert!(!matrix.is_empty(), "{rom} reported no tests");
+    let failures = failures(&matrix);
+    assert!(failures.is_empty(), "{rom} failed:\n{failures}");
+}
+
+#[test]
+fn test_cpu_arm() {
+    run_suite("cpu_arm.nds");
+}
+
+#[test]
+fn test_cpu_armv5() {
+    run_suite("cpu_armv5.nds");
+}
+
+fn write_result(emu: &mut Emulator, offset: u32, bytes: &[u8]) {
+    for (i, byte) in bytes.iter().enumerate() {
+        emu.write_byte(RESULT_BASE + offset + i as u32, *byte, CpuType::Arm9);
+    }
+}
+
+#[test]
+fn test_read_result_block() {
+    let mut emu = Box::new(Emulator::new());
+    write_result(&mut emu, 0x00, &RESULT_MAGIC.to_le_bytes());
+    write_result(&mut emu, 0x04, &3u32.to_le_bytes());
+    write_result(&mut emu, 0x0C, &[1, 2, 0]);
+    write_result(&mut emu, 0x0F, b"adcs carry\0ldm ^\0\0");
+    assert_eq!(read_matrix(&mut emu), None);
+
+    write_result(&mut emu, 0x08, &1u32.to_le_bytes());
+    let matrix = read_matrix(&mut emu).unwrap();
+    assert_eq!(
+        matrix,
+        [
+            ("adcs carry".to_string(), Status::Pass),
+            ("ldm ^".to_string(), Status::Fail),
+            ("test_2".to_string(), Status::NotRun),
+        ]
+    );
+    assert_eq!(failures(&matrix), "  ldm ^: fail\n  test_2: not-run\n");
+}
@@ -0,0 +1,215 @@
+//! The interpreters on a bus other than the DS
+//!
+//! Builds with and without the `ds` feature.
+use lunaris_ds_emu::{ArmCpu, Bus, CpuType, PsrMode, step};
+
+/// One core and 64KB of RAM at address 0.
+struct FlatBus {
+    cpu: ArmCpu,
+    ram: Vec<u8>,
+}
+
+impl FlatBus {
+    fn new(cpu_type: CpuType, code: &[u8]) -> Self {
+        let cpu_id = match cpu_type {
+            CpuType::Arm9 => 0,
+            CpuType::Arm7 => 1,
+        };
+        let mut bus = Self {
+            cpu: ArmCpu::new(cpu_id, cpu_type),
+            ram: vec![0; 0x1_0000],
+        };
+        bus.ram[..code.len()].copy_from_slice(code);
+        bus.cpu.power_on();
+        bus.cpu.update_reg_mode(PsrMode::System);
+        bus.cpu.cpsr.mode = PsrMode::System;
+        bus
+    }
+
+    fn index(&self, address: u32) -> usize {
+        address as usize % self.ram.len()
+    }
+}
+
+impl Bus for FlatBus {
+    fn get_cpu(&self, _cpu_type: CpuType) -> &ArmCpu {
+        &self.cpu
+    }
+
+    fn get_cpu_mut(&mut self, _cpu_type: CpuType) -> &mut ArmCpu {
+        &mut self.cpu
+    }
+
+    fn read_word(&mut self, address: u32, _cpu_type: CpuType) -> u32 {
+        let i = self.index(address & !3);
+        u32::from_le_bytes(self.ram[i..i + 4].try_into().unwrap())
+    }
+
+    fn read_halfword(&mut self, address: u32, _cpu_type: CpuType) -> u16 {
+        let i = self.index(address & !1);
+        u16::from_le_bytes([self.ram[i], self.ram[i + 1]])
+    }
+
+    fn read_byte(&mut self, address: u32, _cpu_type: CpuType) -> u8 {
+        self.ram[self.index(address)]
+    }
+
+    fn write_word(&mut self, address: u32, word: u32, _cpu_type: CpuType) {
+        let i = self.index(address & !3);
+        self.ram[i..i + 4].copy_from_slice(&word.to_le_bytes());
+    }
+
+    fn write_halfword(&mut self, address: u32, halfword: u16, _cpu_type: CpuType) {
+        let i = self.index(address & !1);
+        self.ram[i..i + 2].copy_from_slice(&halfword.to_le_bytes());
+    }
+
+    fn write_byte(&mut self, address: u32, byte: u8, _cpu_type: CpuType) {
+        let i = self.index(address);
+        self.ram[i] = byte;
+    }
+}
+
+fn words(code: &[u32]) -> Vec<u8> {
+    code.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// Run the ARM instructions in `code` once each, starting from `regs`.
+fn run_arm(cpu_type: CpuType, code: &[u32], regs: &[(usize, u32)]) -> FlatBus {
+    let mut bus = FlatBus::new(cpu_type, &words(code));
+    for &(reg, value) in regs {
+        bus.cpu.regs[reg] = value;
+    }
+    bus.cpu.jp(0, false);
+    for _ in code {
+        step(&mut bus, cpu_type);
+    }
+    bus
+}
+
+#[test]
+fn test_arm_blob() {
+    // mov r0, #0; mov r1, #10; loop: add r0, r0, r1; subs r1, r1, #1;
+    // bne loop; str r0, [r2]; b .
+    let code = words(&[
+        0xE3A0_0000,
+        0xE3A0_100A,
+        0xE080_0001,
+        0xE251_1001,
+        0x1AFF_FFFC,
+        0xE582_0000,
+        0xEAFF_FFFE,
+    ]);
+    for cpu_type in [CpuType::Arm9, CpuType::Arm7] {
+        let mut bus = FlatBus::new(cpu_type, &code);
+        bus.cpu.regs[2] = 0x8000;
+        bus.cpu.jp(0, false);
+        for _ in 0..40 {
+            step(&mut bus, cpu_type);
+        }
+        assert_eq!(bus.cpu.regs[0], 55);
+        assert_eq!(bus.read_word(0x8000, cpu_type), 55);
+        // Spinning on the final branch, PC one fetch ahead
+        assert_eq!(bus.cpu.get_pc(), 0x18 + 4);
+    }
+}
+
+#[test]
+fn test_thumb_blob() {
+    // movs r0, #0; movs r1, #10; loop: adds r0, r0, r1; subs r1, #1;
+    // bne loop; strh r0, [r2]; b .
+    let code: Vec<u8> = [0x2000u16, 0x210A, 0x1840, 0x3901, 0xD1FC, 0x8010, 0xE7FE]
+        .iter()
+        .flat_map(|halfword| halfword.to_le_bytes())
+        .collect();
+    let mut bus = FlatBus::new(CpuType::Arm7, &code);
+    bus.cpu.regs[2] = 0x8000;
+    bus.cpu.jp(1, true);
+    for _ in 0..40 {
+        step(&mut bus, CpuType::Arm7);
+    }
+    assert!(bus.cpu.cpsr.thumb_on);
+    assert_eq!(bus.read_halfword(0x8000, CpuType::Arm7), 55);
+}
+
+#[test]
+fn test_exceptions() {
+    // BKPT is a prefetch abort on the ARM9 and undefined on the ARM7, both
+    // taken by the default Bus handlers
+    for (cpu_type, mode) in [
+        (CpuType::Arm9, PsrMode::Abort),
+        (CpuType::Arm7, PsrMode::Undefined),
+    ] {
+        let mut bus = FlatBus::new(cpu_type, &words(&[0xE120_0070]));
+        bus.cpu.jp(0, false);
+        step(&mut bus, cpu_type);
+        assert_eq!(bus.cpu.cpsr.mode, mode);
+    }
+}
+
+#[test]
+fn test_register_shift_amount() {
+    // Only the bottom byte of Rs counts: mov r0, r1, lsl r2
+    let bus = run_arm(CpuType::Arm9, &[0xE1A0_0211], &[(1, 1), (2, 0x101)]);
+    assert_eq!(bus.cpu.regs[0], 2);
+
+    // Thumb lsl r0, r1
+    let mut bus = FlatBus::new(CpuType::Arm7, &0x4088u16.to_le_bytes());
+    bus.cpu.regs[0] = 1;
+    bus.cpu.regs[1] = 0x101;
+    bus.cpu.jp(1, true);
+    step(&mut bus, CpuType::Arm7);
+    assert_eq!(bus.cpu.regs[0], 2);
+}
+
+#[test]
+fn test_armv5_only_ops() {
+    // clz r0, r1 and qadd r3, r1, r2 run on the ARM9 only
+    let code = [0xE16F_0F11, 0xE102_3051];
+    let regs = [(1, 0x7FFF_FFFF), (2, 1)];
+    let bus = run_arm(CpuType::Arm9, &code, &regs);
+    assert_eq!(bus.cpu.regs[0], 1);
+    assert_eq!(bus.cpu.regs[3], 0x7FFF_FFFF);
+    assert!(bus.cpu.cpsr.sticky_overflow);
+
+    for instruction in code {
+        let bus = run_arm(CpuType::Arm7, &[instruction], &regs);
+        assert_eq!(bus.cpu.cpsr.mode, PsrMode::Undefined);
+    }
+}
+
+#[test]
+fn test_doubling_saturating_ops() {
+    // qdsub r0, r1, r2: 1 - 2 * 3
+    let bus = run_arm(CpuType::Arm9, &[0xE162_0051], &[(1, 1), (2, 3)]);
+    assert_eq!(bus.cpu.regs[0], -5i32 as u32);
+    assert!(!bus.cpu.cpsr.sticky_overflow);
+
+    // qdadd r0, r1, r2: doubling 0x40000000 saturates on its own
+    let bus = run_arm(CpuType::Arm9, &[0xE142_0051], &[(1, 0), (2, 0x4000_0000)]);
+    assert_eq!(bus.cpu.regs[0], 0x7FFF_FFFF);
+    assert!(bus.cpu.cpsr.sticky_overflow);
+}
+
+#[test]
+fn test_smlalxy() {
+    // smlalbt r0, r1, r2, r3: r1:r0 += bottom(r2) * top(r3)
+    let regs = [(0, 0xFFFF_FFFF), (1, 0), (2, 0xFFFE), (3, 0x0003_0000)];
+    let bus = run_arm(CpuType::Arm9, &[0xE141_03C2], &regs);
+    // 0xFFFFFFFF + -2 * 3
+    assert_eq!(bus.cpu.regs[0], 0xFFFF_FFF9);
+    assert_eq!(bus.cpu.regs[1], 0);
+}
+
+#[test]
+fn test_doubleword_transfers() {
+    // strd r2, [r0, #8]!; ldrd r4, [r0], #-8
+    let code = [0xE1E0_20F8, 0xE040_40D8];
+    let regs = [(0, 0x100), (2, 0x11), (3, 0x22)];
+    let mut bus = run_arm(CpuType::Arm9, &code, &regs);
+    assert_eq!(bus.read_word(0x108, CpuType::Arm9), 0x11);
+    assert_eq!(bus.read_word(0x10C, CpuType::Arm9), 0x22);
+    assert_eq!(bus.cpu.regs[4], 0x11);
+    assert_eq!(bus.cpu.regs[5], 0x22);
+    assert_eq!(bus.cpu.regs[0], 0x100);
+}
@@ -0,0 +1,151 @@
+//! GPU / geometry test ROM suite
+//!
+//! Runs the GPU test ROMs in `tests/roms/` headless and compares their
+//! pass/fail matrix against the files in `tests/expected/`. The ROMs are
+//! our own, assembled with `tests/roms/build.sh`; AGBtests and dsgxtest are
+//! not vendored.
+//!
+//! - `gpu_2d`: VRAM bank mapping, palette and OAM mirrors, DISPSTAT and
+//!   VCOUNT timing and DMA into VRAM
+//! - `gpu_geometry`: matrix commands and stacks, the position, vector and
+//!   box tests and the polygon RAM count
+//!
+//! Unlike the CPU suites not every test has to pass, but the matrix has to
+//! match exactly: a test missing from either side fails like a changed one.
+//! Set `LUNARIS_BLESS=1` to (re)write the expected files from the current
+//! results after checking that a change is an improvement.
+//!
+//! The ROMs report through the result block described in `tests/common`.
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use lunaris_ds_test_support::bless_enabled;
+
+use crate::common::{Matrix, Status, run_rom};
+
+impl Status {
+    fn parse(text: &str) -> Option<Self> {
+        match text {
+            "not-run" => Some(Self::NotRun),
+            "pass" => Some(Self::Pass),
+            "fail" => Some(Self::Fail),
+            _ => None,
+        }
+    }
+}
+
+fn parse_expected(text: &str) -> Matrix {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (name, status) = line.rsplit_once(char::is_whitespace)?;
+            Some((name.trim().to_string(), Status::parse(status)?))
+        })
+        .collect()
+}
+
+fn format_expected(rom: &str, matrix: &Matrix) -> String {
+    let mut out = format!("# Expected results of {rom}\n# <test name> <pass|fail|not-run>\n");
+    for (name, status) in matrix {
+        out.push_str(&format!("{name} {}\n", status.as_str()));
+    }
+    out
+}
+
+fn expected_path(rom: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/expected")
+        .join(Path::new(rom).with_extension("txt"))
+}
+
+fn run_suite(rom: &str) {
+    let matrix = run_rom(rom);
+
+    let path = expected_path(rom);
+    if bless_enabled() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, format_expected(rom, &matrix)).unwrap();
+        return;
+    }
+
+    let expected = parse_expected(
+        &std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("{}: {err}, run with LUNARIS_BLESS=1", path.display())),
+    );
+
+    let diff = matrix_diff(&expected, &matrix);
+    assert!(diff.is_empty(), "{rom} results changed:\n{diff}");
+}
+
+/// One line per test whose status differs, or that only one side has.
+fn matrix_diff(expected: &Matrix, actual: &Matrix) -> String {
+    let status_of = |matrix: &Matrix, name: &str| {
+        matrix
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, status)| *status)
+    };
+
+    let mut diff = String::new();
+    for (name, status) in actual {
+        let want = status_of(expected, name);
+        if want != Some(*status) {
+            diff.push_str(&format!(
+                "  {name}: expected {}, got {}\n",
+                want.map_or("<missing>", Status::as_str),
+                status.as_str()
+            ));
+        }
+    }
+    for (name, status) in expected {
+        if status_of(actual, name).is_none() {
+            diff.push_str(&format!(
+                "  {name}: expected {}, got <missing>\n",
+                status.as_str()
+            ));
+        }
+    }
+    diff
+}
+
+#[test]
+fn test_gpu_2d() {
+    run_suite("gpu_2d.nds");
+}
+
+#[test]
+fn test_gpu_geometry() {
+    run_suite("gpu_geometry.nds");
+}
+
+#[test]
+fn test_expected_format_round_trip() {
+    let matrix = vec![
+        ("matrix push pop".to_string(), Status::Pass),
+        ("box_test".to_string(), Status::Fail),
+        ("fog".to_string(), Status::NotRun),
+    ];
+    let text = format_expected("gpu_geometry.nds", &matrix);
+    assert_eq!(parse_expected(&text), matrix);
+}
+
+#[test]
+fn test_matrix_diff() {
+    let expected = vec![
+        ("box_test".to_string(), Status::Fail),
+        ("fog".to_string(), Status::Pass),
+    ];
+    assert_eq!(matrix_diff(&expected, &expected), "");
+
+    let actual = vec![
+        ("box_test".to_string(), Status::Pass),
+        ("toon".to_string(), Status::Pass),
+    ];
+    assert_eq!(
+        matrix_diff(&expected, &actual),
+        "  box_test: expected fail, got pass\n  toon: expected <missing>, got pass\n  \
+         fog: expected pass, got <missing>\n"
+    );
+}
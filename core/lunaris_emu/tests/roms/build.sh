@@ -0,0 +1,19 @@
+#!/bin/sh
+# Assemble the test ROMs next to their sources. Needs llvm-mc and
+# llvm-objcopy; the built ROMs are committed so the tests don't.
+set -eu
+cd "$(dirname "$0")"
+tmp=$(mktemp -d)
+trap 'rm -rf "$tmp"' EXIT
+
+for src in *.s; do
+    rom=${src%.s}
+    llvm-mc -triple=armv5te-none-eabi -filetype=obj -o "$tmp/$rom.o" "$src"
+    # There is no linker step, every address must resolve in the assembler
+    if llvm-readelf -r "$tmp/$rom.o" | grep -q "^Relocation section"; then
+        echo "$src: unresolved relocations" >&2
+        llvm-readelf -r "$tmp/$rom.o" >&2
+        exit 1
+    fi
+    llvm-objcopy -O binary -j .text "$tmp/$rom.o" "$rom.nds"
+done
@@ -0,0 +1,491 @@
+@ ARM and Thumb instruction tests
+@
+@ ALU results and flags including the shifter carry out, multiplies,
+@ LDR/STR addressing modes and LDM/STM with the writeback and base-in-list
+@ cases that are the same on ARMv4 and ARMv5.
+
+    .include "test_rom.inc"
+
+    rom_start "CPU ARM", "ZCPA"
+    b run_tests
+
+    .thumb
+thumb_adds:
+    adds r2, r1, r2
+    bx lr
+thumb_adcs:
+    adcs r2, r1
+    bx lr
+thumb_lsls:
+    lsls r2, r5
+    bx lr
+thumb_negs:
+    negs r2, r1
+    bx lr
+thumb_muls:
+    muls r2, r1
+    bx lr
+thumb_add_hi:
+    add r2, r8
+    bx lr
+thumb_push_pop:
+    push {r4, r5}
+    movs r4, #0
+    movs r5, #0
+    pop {r4, r5}
+    bx lr
+thumb_sp_relative:
+    sub sp, #8
+    movs r2, #0x5A
+    str r2, [sp, #4]
+    movs r2, #0
+    ldr r2, [sp, #4]
+    add sp, #8
+    bx lr
+thumb_sign_extend:
+    ldrsh r2, [r1, r5]
+    ldrsb r6, [r1, r5]
+    bx lr
+thumb_bl:
+    mov r6, lr
+    bl thumb_leaf
+    bx r6
+thumb_leaf:
+    movs r2, #0x42
+    bx lr
+    .arm
+    .p2align 2
+
+run_tests:
+    test "adds overflow"
+    msr cpsr_f, #0
+    ldr r1, =0x7FFFFFFF
+    adds r2, r1, #1
+    expect r2, 0x80000000, 0x90000000
+
+    test "adcs carry in"
+    msr cpsr_f, #0x20000000
+    mvn r1, #0
+    adcs r2, r1, #0
+    expect r2, 0, 0x60000000
+
+    test "subs borrow"
+    msr cpsr_f, #0
+    mov r1, #0
+    subs r2, r1, #1
+    expect r2, 0xFFFFFFFF, 0x80000000
+
+    test "subs equal sets carry"
+    msr cpsr_f, #0
+    mov r1, #5
+    subs r2, r1, #5
+    expect r2, 0, 0x60000000
+
+    test "subs signed overflow"
+    msr cpsr_f, #0
+    mov r1, #0x80000000
+    subs r2, r1, #1
+    expect r2, 0x7FFFFFFF, 0x30000000
+
+    test "sbcs carry clear"
+    msr cpsr_f, #0
+    mov r1, #5
+    sbcs r2, r1, #3
+    expect r2, 1, 0x20000000
+
+    test "rscs"
+    msr cpsr_f, #0x20000000
+    mov r1, #3
+    rscs r2, r1, #10
+    expect r2, 7, 0x20000000
+
+    test "lsl carry out"
+    msr cpsr_f, #0
+    mov r1, #0x80000000
+    movs r2, r1, lsl #1
+    expect r2, 0, 0x60000000
+
+    test "lsr #32"
+    msr cpsr_f, #0
+    ldr r1, =0x80000001
+    movs r2, r1, lsr #32
+    expect r2, 0, 0x60000000
+
+    test "asr #32"
+    msr cpsr_f, #0
+    mov r1, #0x80000000
+    movs r2, r1, asr #32
+    expect r2, 0xFFFFFFFF, 0xA0000000
+
+    test "asr negative"
+    msr cpsr_f, #0
+    ldr r1, =0x80000010
+    movs r2, r1, asr #4
+    expect r2, 0xF8000001, 0x80000000
+
+    test "asr by register 33"
+    msr cpsr_f, #0
+    mov r1, #0x80000000
+    mov r5, #33
+    movs r2, r1, asr r5
+    expect r2, 0xFFFFFFFF, 0xA0000000
+
+    test "lsl by register 32"
+    msr cpsr_f, #0
+    mov r1, #1
+    mov r5, #32
+    movs r2, r1, lsl r5
+    expect r2, 0, 0x60000000
+
+    test "lsl by register 33"
+    msr cpsr_f, #0x20000000
+    mov r1, #1
+    mov r5, #33
+    movs r2, r1, lsl r5
+    expect r2, 0, 0x40000000
+
+    test "lsr by register 0 keeps carry"
+    msr cpsr_f, #0x20000000
+    mov r1, #3
+    mov r5, #0
+    movs r2, r1, lsr r5
+    expect r2, 3, 0x20000000
+
+    test "shift by register uses bottom byte"
+    msr cpsr_f, #0
+    mov r1, #0x10
+    ldr r5, =0x80000101
+    movs r2, r1, lsl r5
+    expect r2, 0x20, 0
+
+    test "lsr by register 33"
+    msr cpsr_f, #0x20000000
+    mov r1, #0x80000000
+    mov r5, #33
+    movs r2, r1, lsr r5
+    expect r2, 0, 0x40000000
+
+    test "ror by register 32"
+    msr cpsr_f, #0
+    mov r1, #0x80000000
+    mov r5, #32
+    movs r2, r1, ror r5
+    expect r2, 0x80000000, 0xA0000000
+
+    test "ror by register 33"
+    msr cpsr_f, #0
+    mov r1, #3
+    mov r5, #33
+    movs r2, r1, ror r5
+    expect r2, 0x80000001, 0xA0000000
+
+    test "rrx"
+    msr cpsr_f, #0x20000000
+    mov r1, #2
+    movs r2, r1, rrx
+    expect r2, 0x80000001, 0x80000000
+
+    test "rotated immediate carry"
+    msr cpsr_f, #0
+    movs r2, #0x80000000
+    expect r2, 0x80000000, 0xA0000000
+
+    test "logical op keeps overflow"
+    msr cpsr_f, #0x10000000
+    mov r1, #3
+    ands r2, r1, #1
+    expect r2, 1, 0x10000000
+
+    test "tst"
+    msr cpsr_f, #0
+    mov r1, #6
+    tst r1, #1
+    expect r1, 6, 0x40000000
+
+    test "teq"
+    msr cpsr_f, #0
+    mov r1, #5
+    teq r1, #5
+    expect r1, 5, 0x40000000
+
+    test "cmn"
+    msr cpsr_f, #0
+    mvn r1, #0
+    cmn r1, #1
+    expect r1, 0xFFFFFFFF, 0x60000000
+    pool
+
+    test "muls zero"
+    msr cpsr_f, #0
+    mov r1, #0
+    mov r5, #7
+    muls r2, r5, r1
+    expect r2, 0, 0x40000000
+
+    test "mla"
+    mov r1, #3
+    mov r5, #4
+    mov r6, #5
+    mla r2, r1, r5, r6
+    expect2 r2, 17, r2, 17
+
+    test "umull"
+    mvn r1, #0
+    mov r5, #2
+    umull r2, r6, r1, r5
+    expect2 r2, 0xFFFFFFFE, r6, 1
+
+    test "smull negative"
+    mvn r1, #1
+    mov r5, #3
+    smull r2, r6, r1, r5
+    expect2 r2, 0xFFFFFFFA, r6, 0xFFFFFFFF
+
+    test "smlal"
+    mov r2, #10
+    mov r6, #0
+    mvn r1, #1
+    mov r5, #3
+    smlal r2, r6, r1, r5
+    expect2 r2, 4, r6, 0
+
+    test "umlal carry"
+    mvn r2, #0
+    mov r6, #0
+    mov r1, #1
+    mov r5, #1
+    umlal r2, r6, r1, r5
+    expect2 r2, 0, r6, 1
+
+    test "conditions"
+    msr cpsr_f, #0x40000000
+    mov r2, #0
+    addne r2, r2, #1
+    addeq r2, r2, #2
+    addgt r2, r2, #4
+    addle r2, r2, #8
+    expect2 r2, 10, r2, 10
+
+    test "pc reads ahead"
+pc_read:
+    add r2, pc, #0
+    expect2 r2, pc_read + 8 - arm9_start + ARM9_BASE, r2, pc_read + 8 - arm9_start + ARM9_BASE
+
+    test "msr flags"
+    msr cpsr_f, #0xF0000000
+    mrs r2, cpsr
+    and r2, r2, #0xF0000000
+    cmp r2, #0xF0000000
+    pass_if eq
+
+    test "banked sp"
+    mov r5, sp
+    mrs r1, cpsr
+    bic r2, r1, #0x1F
+    orr r2, r2, #0x12
+    msr cpsr_c, r2
+    mov r6, sp
+    ldr sp, =0x0BADF00D
+    msr cpsr_c, r1
+    cmp sp, r5
+    cmpne r6, r5
+    pass_if eq
+    pool
+
+    test "str ldr"
+    ldr r1, =SCRATCH
+    ldr r2, =0x12345678
+    str r2, [r1]
+    mov r2, #0
+    ldr r2, [r1]
+    expect2 r2, 0x12345678, r2, 0x12345678
+
+    test "ldr misaligned rotates"
+    ldr r2, [r1, #1]
+    expect2 r2, 0x78123456, r2, 0x78123456
+
+    test "strb ldrb"
+    mov r2, #0xAB
+    strb r2, [r1, #2]
+    mov r2, #0
+    ldrb r2, [r1, #2]
+    ldr r5, [r1]
+    expect2 r2, 0xAB, r5, 0x12AB5678
+
+    test "ldrh ldrsh"
+    ldr r2, =0x8001
+    strh r2, [r1]
+    ldrh r5, [r1]
+    ldrsh r6, [r1]
+    expect2 r5, 0x8001, r6, 0xFFFF8001
+
+    test "ldrsb"
+    mov r2, #0x80
+    strb r2, [r1]
+    ldrsb r5, [r1]
+    expect2 r5, 0xFFFFFF80, r5, 0xFFFFFF80
+
+    test "pre-index writeback"
+    ldr r1, =SCRATCH
+    mov r2, #0x55
+    str r2, [r1, #4]
+    ldr r5, [r1, #4]!
+    expect2 r5, 0x55, r1, SCRATCH + 4
+
+    test "post-index"
+    ldr r5, [r1], #-4
+    expect2 r5, 0x55, r1, SCRATCH
+
+    test "shifted register offset"
+    mov r6, #1
+    ldr r5, [r1, r6, lsl #2]
+    expect2 r5, 0x55, r1, SCRATCH
+
+    test "negative offset writeback"
+    ldr r1, =SCRATCH + 8
+    mov r6, #4
+    ldr r5, [r1, -r6]!
+    expect2 r5, 0x55, r1, SCRATCH + 4
+    pool
+
+    test "ldmia writeback"
+    ldr r1, =SCRATCH
+    mov r2, #1
+    mov r5, #2
+    stmia r1, {r2, r5}
+    mov r2, #0
+    mov r5, #0
+    ldmia r1!, {r2, r5}
+    cmp r2, #1
+    cmpeq r5, #2
+    ldreq r4, =SCRATCH + 8
+    cmpeq r1, r4
+    pass_if eq
+
+    test "stmdb writeback"
+    ldr r1, =SCRATCH + 8
+    mov r2, #3
+    mov r5, #4
+    stmdb r1!, {r2, r5}
+    ldr r6, [r1]
+    ldr r7, [r1, #4]
+    ldr r4, =SCRATCH
+    cmp r1, r4
+    cmpeq r6, #3
+    cmpeq r7, #4
+    pass_if eq
+
+    test "ldmib ldmda"
+    ldr r1, =SCRATCH
+    ldmib r1, {r2}
+    ldmda r1, {r5}
+    expect2 r2, 4, r5, 3
+
+    test "stm base first in list"
+    ldr r1, =SCRATCH
+    mov r2, #9
+    stmia r1!, {r1, r2}
+    ldr r5, =SCRATCH
+    ldr r6, [r5]
+    ldr r7, [r5, #4]
+    cmp r6, r5
+    cmpeq r7, #9
+    addeq r5, r5, #8
+    cmpeq r1, r5
+    pass_if eq
+
+    test "ldm base in list"
+    ldr r1, =SCRATCH
+    mov r2, #7
+    str r2, [r1]
+    mov r2, #8
+    str r2, [r1, #4]
+    ldmia r1, {r1, r2}
+    expect2 r1, 7, r2, 8
+
+    test "swp"
+    ldr r1, =SCRATCH
+    mov r2, #0x11
+    str r2, [r1]
+    mov r5, #0x22
+    swp r6, r5, [r1]
+    ldr r7, [r1]
+    expect2 r6, 0x11, r7, 0x22
+
+    test "swpb"
+    ldr r5, =0x133
+    swpb r6, r5, [r1]
+    ldr r7, [r1]
+    expect2 r6, 0x22, r7, 0x33
+    pool
+
+    test "thumb adds overflow"
+    msr cpsr_f, #0
+    ldr r1, =0x7FFFFFFF
+    mov r2, #1
+    blx_imm thumb_adds
+    expect r2, 0x80000000, 0x90000000
+
+    test "thumb adcs"
+    msr cpsr_f, #0x20000000
+    mov r1, #1
+    mov r2, #1
+    blx_imm thumb_adcs
+    expect r2, 3, 0
+
+    test "thumb lsls by register 33"
+    msr cpsr_f, #0x20000000
+    mov r2, #1
+    mov r5, #33
+    blx_imm thumb_lsls
+    expect r2, 0, 0x40000000
+
+    test "thumb negs zero"
+    msr cpsr_f, #0
+    mov r1, #0
+    blx_imm thumb_negs
+    expect r2, 0, 0x60000000
+
+    test "thumb muls"
+    msr cpsr_f, #0
+    mov r1, #3
+    mov r2, #5
+    blx_imm thumb_muls
+    expect r2, 15, 0
+
+    test "thumb add high register"
+    msr cpsr_f, #0xF0000000
+    mov r2, #3
+    mov r8, #5
+    blx_imm thumb_add_hi
+    expect r2, 8, 0xF0000000
+
+    test "thumb push pop"
+    mov r4, #0x12
+    mov r5, #0x34
+    mov r6, sp
+    blx_imm thumb_push_pop
+    cmp r4, #0x12
+    cmpeq r5, #0x34
+    cmpeq r6, sp
+    pass_if eq
+
+    test "thumb sp relative"
+    mov r2, #0
+    blx_imm thumb_sp_relative
+    expect2 r2, 0x5A, r2, 0x5A
+
+    test "thumb sign extend"
+    ldr r1, =SCRATCH
+    mov r5, #0
+    ldr r2, =0x80FF
+    strh r2, [r1]
+    blx_imm thumb_sign_extend
+    expect2 r2, 0xFFFF80FF, r6, 0xFFFFFFFF
+
+    test "thumb bl"
+    mov r2, #0
+    blx_imm thumb_bl
+    expect2 r2, 0x42, r2, 0x42
+
+    rom_end
@@ -0,0 +1,195 @@
+@ ARMv5TE instruction tests
+@
+@ The ARM9 additions to ARMv4T: CLZ, saturating arithmetic and the Q flag,
+@ the DSP multiplies, LDRD/STRD, BLX and loads to PC that switch between
+@ ARM and Thumb.
+
+    .include "test_rom.inc"
+
+    rom_start "CPU ARMV5", "ZCP5"
+    b run_tests
+
+    .thumb
+thumb_set:
+    movs r2, #0x42
+    bx lr
+thumb_lr:
+    mov r2, lr
+    bx lr
+thumb_pop_pc:
+    push {lr}
+    movs r2, #0x43
+    pop {pc}
+thumb_blx_reg:
+    push {lr}
+    blx r5
+    pop {pc}
+    .arm
+    .p2align 2
+arm_leaf:
+    mov r2, #0x44
+    bx lr
+
+run_tests:
+    test "clz"
+    mov r1, #0
+    clz r2, r1
+    mov r1, #0x10000
+    clz r5, r1
+    mvn r1, #0
+    clz r6, r1
+    cmp r2, #32
+    cmpeq r5, #15
+    cmpeq r6, #0
+    pass_if eq
+
+    test "qadd saturates"
+    msr cpsr_f, #0
+    ldr r1, =0x7FFFFFFF
+    mov r5, #1
+    qadd r2, r1, r5
+    expect r2, 0x7FFFFFFF, 0x08000000
+
+    test "qadd"
+    msr cpsr_f, #0
+    mov r1, #1
+    mov r5, #2
+    qadd r2, r1, r5
+    expect r2, 3, 0
+
+    test "q flag is sticky"
+    msr cpsr_f, #0x08000000
+    mov r1, #1
+    mov r5, #2
+    qadd r2, r1, r5
+    expect r2, 3, 0x08000000
+
+    test "qsub saturates"
+    msr cpsr_f, #0
+    mov r1, #0x80000000
+    mov r5, #1
+    qsub r2, r1, r5
+    expect r2, 0x80000000, 0x08000000
+
+    test "qdadd saturates double"
+    msr cpsr_f, #0
+    mov r1, #1
+    mov r5, #0x40000000
+    qdadd r2, r1, r5
+    expect r2, 0x7FFFFFFF, 0x08000000
+
+    test "qdsub"
+    msr cpsr_f, #0
+    mov r1, #0
+    mov r5, #3
+    qdsub r2, r1, r5
+    expect r2, 0xFFFFFFFA, 0
+
+    test "smulbb"
+    ldr r1, =0x0003FFFE
+    ldr r5, =0x7FFF0005
+    smulbb r2, r1, r5
+    expect2 r2, 0xFFFFFFF6, r2, 0xFFFFFFF6
+
+    test "smultt"
+    smultt r2, r1, r5
+    expect2 r2, 0x17FFD, r2, 0x17FFD
+
+    test "smulbt"
+    smulbt r2, r1, r5
+    expect2 r2, 0xFFFF0002, r2, 0xFFFF0002
+
+    test "smlabb overflow sets q"
+    msr cpsr_f, #0
+    ldr r1, =0x7FFF
+    ldr r5, =0x7FFF
+    ldr r6, =0x7FFFFFFF
+    smlabb r2, r1, r5, r6
+    expect r2, 0xBFFF0000, 0x08000000
+
+    test "smulwb"
+    mov r1, #0x20000
+    ldr r5, =0xFFFF
+    smulwb r2, r1, r5
+    expect2 r2, 0xFFFFFFFE, r2, 0xFFFFFFFE
+
+    test "smlawt"
+    mov r1, #0x10000
+    mov r5, #0x30000
+    mov r6, #5
+    smlawt r2, r1, r5, r6
+    expect2 r2, 8, r2, 8
+
+    test "smlalbb"
+    mvn r2, #0
+    mov r6, #0
+    mov r1, #1
+    mov r5, #1
+    smlalbb r2, r6, r1, r5
+    expect2 r2, 0, r6, 1
+    pool
+
+    test "strd ldrd"
+    ldr r1, =SCRATCH
+    ldr r2, =0x11111111
+    ldr r3, =0x22222222
+    strd r2, r3, [r1]
+    ldrd r6, r7, [r1]
+    ldr r5, [r1, #4]
+    cmp r5, r3
+    cmpeq r6, r2
+    cmpeq r7, r3
+    pass_if eq
+
+    test "strd writeback"
+    ldr r1, =SCRATCH
+    strd r2, r3, [r1, #8]!
+    ldr r5, [r1]
+    expect2 r1, SCRATCH + 8, r5, 0x11111111
+
+    test "blx immediate"
+    mov r2, #0
+    blx_imm thumb_set
+    expect2 r2, 0x42, r2, 0x42
+
+    test "blx register"
+    mov r2, #0
+    abs_addr r5, thumb_set + 1
+    blx r5
+    expect2 r2, 0x42, r2, 0x42
+
+    test "blx sets lr"
+    abs_addr r5, thumb_lr + 1
+    blx r5
+blx_return:
+    expect2 r2, blx_return - arm9_start + ARM9_BASE, r2, blx_return - arm9_start + ARM9_BASE
+
+    test "ldr pc interworks"
+    mov r2, #0
+    ldr r1, =SCRATCH
+    abs_addr r5, thumb_set + 1
+    str r5, [r1]
+    abs_addr lr, ldr_return
+    ldr pc, [r1]
+ldr_return:
+    expect2 r2, 0x42, r2, 0x42
+
+    test "ldm pc interworks"
+    mov r2, #0
+    abs_addr lr, ldm_return
+    ldmia r1, {pc}
+ldm_return:
+    expect2 r2, 0x42, r2, 0x42
+
+    test "thumb pop pc interworks"
+    mov r2, #0
+    blx_imm thumb_pop_pc
+    expect2 r2, 0x43, r2, 0x43
+
+    test "thumb blx register"
+    mov r2, #0
+    abs_addr r5, arm_leaf
+    blx_imm thumb_blx_reg
+    expect2 r2, 0x44, r2, 0x44
+
+    rom_end
@@ -0,0 +1,215 @@
+@ 2D engine and video memory tests
+@
+@ VRAM bank mapping, palette and OAM mirrors, DISPSTAT and VCOUNT timing
+@ and DMA into VRAM.
+
+    .include "test_rom.inc"
+
+    .equ DISPSTAT, 0x04000004
+    .equ VCOUNT, 0x04000006
+    .equ DMA3SAD, 0x040000D4
+    .equ VRAMCNT, 0x04000240
+    .equ POWCNT1, 0x04000304
+    .equ DISPCNT_B, 0x04001000
+    .equ LCDC, 0x06800000
+
+@ Map VRAM bank `bank` (0 for A) with VRAMCNT value `value`; clobbers r1, r2
+.macro vramcnt bank, value
+    ldr r1, =VRAMCNT + \bank
+    mov r2, #\value
+    strb r2, [r1]
+.endm
+
+@ Spin until VCOUNT equals `line`, for at most about two frames; clobbers
+@ r1 to r3
+.macro wait_vcount line
+    ldr r1, =VCOUNT
+    ldr r2, =0x100000
+4:
+    subs r2, r2, #1
+    beq 5f
+    ldrh r3, [r1]
+    cmp r3, #\line
+    bne 4b
+5:
+.endm
+
+    rom_start "GPU 2D", "ZG2D"
+
+    test "vram lcdc"
+    vramcnt 0, 0x80
+    ldr r5, =LCDC
+    ldr r6, =0x12345678
+    str r6, [r5]
+    ldr r7, [r5]
+    expect2 r7, 0x12345678, r7, 0x12345678
+
+    test "vram bank a as engine a bg"
+    vramcnt 0, 0x81
+    ldr r5, =0x06000000
+    ldr r6, =0xCAFEF00D
+    str r6, [r5]
+    vramcnt 0, 0x80
+    ldr r5, =LCDC
+    ldr r7, [r5]
+    expect2 r7, 0xCAFEF00D, r7, 0xCAFEF00D
+
+    test "vram bank offset"
+    vramcnt 0, 0x89
+    ldr r5, =0x06020000
+    ldr r6, =0x0BADBEEF
+    str r6, [r5]
+    vramcnt 0, 0x80
+    ldr r5, =LCDC
+    ldr r7, [r5]
+    expect2 r7, 0x0BADBEEF, r7, 0x0BADBEEF
+
+    test "vram bank b lcdc"
+    vramcnt 1, 0x80
+    ldr r5, =0x06820000
+    ldr r6, =0x55AA55AA
+    str r6, [r5]
+    ldr r7, [r5]
+    expect2 r7, 0x55AA55AA, r7, 0x55AA55AA
+
+    test "vram bank c as engine b bg"
+    vramcnt 2, 0x84
+    ldr r5, =0x06200000
+    ldr r6, =0x13579BDF
+    str r6, [r5]
+    vramcnt 2, 0x80
+    ldr r5, =0x06840000
+    ldr r7, [r5]
+    expect2 r7, 0x13579BDF, r7, 0x13579BDF
+
+    test "vram halfword write"
+    vramcnt 0, 0x80
+    ldr r5, =LCDC
+    ldr r6, =0x12345678
+    str r6, [r5]
+    ldr r6, =0xABCD
+    strh r6, [r5, #2]
+    ldr r7, [r5]
+    expect2 r7, 0xABCD5678, r7, 0xABCD5678
+
+    test "vram byte write ignored"
+    mov r6, #0xFF
+    strb r6, [r5]
+    ldr r7, [r5]
+    expect2 r7, 0xABCD5678, r7, 0xABCD5678
+
+    test "vram unmapped reads zero"
+    vramcnt 0, 0x00
+    ldr r5, =LCDC
+    ldr r7, [r5]
+    expect2 r7, 0, r7, 0
+    pool
+
+    test "palette mirror"
+    ldr r5, =0x05000000
+    ldr r6, =0x7FFF
+    strh r6, [r5, #2]
+    add r5, r5, #0x800
+    ldrh r7, [r5, #2]
+    expect2 r7, 0x7FFF, r7, 0x7FFF
+
+    test "palette engine b"
+    ldr r5, =0x05000400
+    ldr r6, =0x001F03E0
+    str r6, [r5]
+    ldr r7, [r5]
+    ldr r5, =0x05000000
+    ldr r8, [r5]
+    expect2 r7, 0x001F03E0, r8, 0x7FFF0000
+
+    test "oam mirror"
+    ldr r5, =0x07000000
+    ldr r6, =0x01234567
+    str r6, [r5]
+    add r5, r5, #0x800
+    ldr r7, [r5]
+    expect2 r7, 0x01234567, r7, 0x01234567
+
+    test "powcnt1"
+    ldr r5, =POWCNT1
+    ldr r6, =0x820F
+    strh r6, [r5]
+    ldrh r7, [r5]
+    expect2 r7, 0x820F, r7, 0x820F
+
+    test "vblank flag"
+    wait_vcount 192
+    ldr r5, =DISPSTAT
+    ldrh r6, [r5]
+    wait_vcount 10
+    ldrh r7, [r5]
+    and r6, r6, #1
+    and r7, r7, #1
+    expect2 r6, 1, r7, 0
+
+    test "vcount match flag"
+    ldr r5, =DISPSTAT
+    mov r6, #100 << 8
+    strh r6, [r5]
+    wait_vcount 100
+    ldrh r6, [r5]
+    wait_vcount 101
+    ldrh r7, [r5]
+    and r6, r6, #4
+    and r7, r7, #4
+    expect2 r6, 4, r7, 0
+
+    test "vcount match high bit"
+    mov r6, #(260 & 0xFF) << 8
+    orr r6, r6, #(260 >> 8) << 7
+    strh r6, [r5]
+    wait_vcount 260
+    ldrh r6, [r5]
+    and r6, r6, #4
+    expect2 r6, 4, r6, 4
+
+    test "hblank flag"
+    ldr r5, =DISPSTAT
+    ldr r1, =0x10000
+6:
+    subs r1, r1, #1
+    beq 7f
+    ldrh r6, [r5]
+    tst r6, #2
+    beq 6b
+7:
+    and r6, r6, #2
+    expect2 r6, 2, r6, 2
+
+    test "vcount in range"
+    ldr r5, =VCOUNT
+    ldrh r6, [r5]
+    ldr r7, =263
+    cmp r6, r7
+    pass_if lo
+    pool
+
+    test "dma3 fill vram"
+    vramcnt 0, 0x80
+    ldr r1, =SCRATCH
+    ldr r2, =0xA5A5A5A5
+    str r2, [r1]
+    ldr r5, =DMA3SAD
+    ldr r6, =LCDC + 0x100
+    @ Enable, 32 bit, fixed source, incrementing destination, 4 words
+    ldr r7, =0x85000004
+    stmia r5, {r1, r6, r7}
+    nop
+    nop
+    ldr r7, [r6, #12]
+    ldr r8, [r6, #16]
+    expect2 r7, 0xA5A5A5A5, r8, 0
+
+    test "dispcnt engine b"
+    ldr r5, =DISPCNT_B
+    ldr r6, =0x00010F05
+    str r6, [r5]
+    ldr r7, [r5]
+    expect2 r7, 0x00010F05, r7, 0x00010F05
+
+    rom_end
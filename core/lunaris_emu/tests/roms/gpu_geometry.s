@@ -0,0 +1,280 @@
+@ Geometry engine tests
+@
+@ Matrix commands and stacks read back through CLIPMTX_RESULT and GXSTAT,
+@ the position, vector and box tests, packed FIFO commands and the polygon
+@ RAM count.
+
+    .include "test_rom.inc"
+
+    .equ VCOUNT, 0x04000006
+    .equ POWCNT1, 0x04000304
+    .equ GXFIFO, 0x04000400
+    .equ MTX_MODE, 0x04000440
+    .equ MTX_PUSH, 0x04000444
+    .equ MTX_POP, 0x04000448
+    .equ MTX_IDENTITY, 0x04000454
+    .equ MTX_SCALE, 0x0400046C
+    .equ MTX_TRANS, 0x04000470
+    .equ POLYGON_ATTR, 0x040004A4
+    .equ BEGIN_VTXS, 0x04000500
+    .equ END_VTXS, 0x04000504
+    .equ VTX_16, 0x0400048C
+    .equ SWAP_BUFFERS, 0x04000540
+    .equ BOX_TEST, 0x040005C0
+    .equ POS_TEST, 0x040005C4
+    .equ VEC_TEST, 0x040005C8
+    .equ GXSTAT, 0x04000600
+    .equ RAM_COUNT, 0x04000604
+    .equ POS_RESULT, 0x04000620
+    .equ VEC_RESULT, 0x04000630
+    .equ CLIPMTX_RESULT, 0x04000640
+
+@ Write `value` to the geometry register `reg`; clobbers r1 and r2
+.macro gx reg, value
+    ldr r1, =\reg
+    ldr r2, =\value
+    str r2, [r1]
+.endm
+
+@ Spin until the geometry engine is idle; clobbers r1 to r3
+.macro gx_wait
+    ldr r1, =GXSTAT
+    ldr r2, =0x100000
+4:
+    subs r2, r2, #1
+    beq 5f
+    ldr r3, [r1]
+    tst r3, #1 << 27
+    bne 4b
+5:
+.endm
+
+@ Both matrices the clip matrix is made of set to identity
+.macro identity
+    gx MTX_MODE, 0
+    gx MTX_IDENTITY, 0
+    gx MTX_MODE, 2
+    gx MTX_IDENTITY, 0
+    gx_wait
+.endm
+
+@ Load clip matrix element `index` into `reg`
+.macro clip reg, index
+    ldr \reg, =CLIPMTX_RESULT + \index * 4
+    ldr \reg, [\reg]
+.endm
+
+@ Matrix stack levels from GXSTAT into r6, position level in bits 0-4 and
+@ projection level in bit 5
+.macro stack_levels
+    ldr r6, =GXSTAT
+    ldr r6, [r6]
+    mov r6, r6, lsr #8
+    and r6, r6, #0x3F
+.endm
+
+    rom_start "GPU GEOMETRY", "ZG3D"
+    gx POWCNT1, 0x820F
+
+    test "fifo empty"
+    gx_wait
+    ldr r5, =GXSTAT
+    ldr r6, [r5]
+    and r6, r6, #3 << 25
+    expect2 r6, 3 << 25, r6, 3 << 25
+
+    test "identity clip matrix"
+    identity
+    clip r5, 0
+    clip r6, 1
+    clip r7, 15
+    cmp r6, #0
+    ldreq r4, =0x1000
+    cmpeq r5, r4
+    cmpeq r7, r4
+    pass_if eq
+
+    test "scale"
+    identity
+    gx MTX_MODE, 1
+    gx MTX_SCALE, 0x2000
+    gx MTX_SCALE, 0x3000
+    gx MTX_SCALE, 0x4000
+    gx_wait
+    clip r5, 0
+    clip r6, 5
+    clip r7, 10
+    ldr r4, =0x2000
+    cmp r5, r4
+    ldreq r4, =0x3000
+    cmpeq r6, r4
+    ldreq r4, =0x4000
+    cmpeq r7, r4
+    pass_if eq
+
+    test "translate"
+    identity
+    gx MTX_TRANS, 0x1000
+    gx MTX_TRANS, 0x2000
+    gx MTX_TRANS, 0xFFFFD000
+    gx_wait
+    clip r5, 12
+    clip r6, 13
+    clip r7, 14
+    ldr r4, =0x1000
+    cmp r5, r4
+    ldreq r4, =0x2000
+    cmpeq r6, r4
+    ldreq r4, =0xFFFFD000
+    cmpeq r7, r4
+    pass_if eq
+    pool
+
+    test "position stack level"
+    identity
+    gx MTX_PUSH, 0
+    gx_wait
+    stack_levels
+    mov r7, r6
+    gx MTX_POP, 1
+    gx_wait
+    stack_levels
+    expect2 r7, 1, r6, 0
+
+    test "projection stack level"
+    gx MTX_MODE, 0
+    gx MTX_PUSH, 0
+    gx_wait
+    stack_levels
+    mov r7, r6
+    gx MTX_POP, 1
+    gx_wait
+    stack_levels
+    expect2 r7, 0x20, r6, 0
+
+    test "pop restores matrix"
+    identity
+    gx MTX_PUSH, 0
+    gx MTX_SCALE, 0x2000
+    gx MTX_SCALE, 0x2000
+    gx MTX_SCALE, 0x2000
+    gx MTX_POP, 1
+    gx_wait
+    clip r5, 0
+    expect2 r5, 0x1000, r5, 0x1000
+
+    test "packed identity"
+    gx MTX_SCALE, 0x2000
+    gx MTX_SCALE, 0x2000
+    gx MTX_SCALE, 0x2000
+    @ One MTX_IDENTITY (0x15) in a packed word
+    gx GXFIFO, 0x15
+    gx_wait
+    clip r5, 0
+    expect2 r5, 0x1000, r5, 0x1000
+    pool
+
+    test "position test"
+    identity
+    gx POS_TEST, 0x20001000
+    gx POS_TEST, 0x3000
+    gx_wait
+    ldr r5, =POS_RESULT
+    ldmia r5, {r6-r9}
+    ldr r4, =0x1000
+    cmp r6, r4
+    cmpeq r9, r4
+    ldreq r4, =0x2000
+    cmpeq r7, r4
+    ldreq r4, =0x3000
+    cmpeq r8, r4
+    pass_if eq
+
+    test "vector test"
+    identity
+    @ x 0.5, y -0.5, z 0 in 1.9 fixed point
+    gx VEC_TEST, (0x300 << 10) | 0x100
+    gx_wait
+    ldr r5, =VEC_RESULT
+    ldrh r6, [r5]
+    ldrh r7, [r5, #2]
+    expect2 r6, 0x0800, r7, 0xF800
+
+    test "box test inside"
+    identity
+    @ -0.5 to 0.5 on every axis
+    gx BOX_TEST, 0xF800F800
+    gx BOX_TEST, 0x1000F800
+    gx BOX_TEST, 0x10001000
+    gx_wait
+    ldr r5, =GXSTAT
+    ldr r6, [r5]
+    and r6, r6, #3
+    expect2 r6, 2, r6, 2
+
+    test "box test outside"
+    @ 2.0 to 2.5 on x
+    gx BOX_TEST, 0x00002000
+    gx BOX_TEST, 0x08000000
+    gx BOX_TEST, 0x08000800
+    gx_wait
+    ldr r5, =GXSTAT
+    ldr r6, [r5]
+    and r6, r6, #3
+    expect2 r6, 0, r6, 0
+    pool
+
+    test "polygon ram count"
+    identity
+    @ Both sides drawn, opaque
+    gx POLYGON_ATTR, 0x001F00C0
+    gx BEGIN_VTXS, 0
+    gx VTX_16, 0x00000000
+    gx VTX_16, 0
+    gx VTX_16, 0x00001000
+    gx VTX_16, 0
+    gx VTX_16, 0x10000000
+    gx VTX_16, 0
+    gx END_VTXS, 0
+    gx_wait
+    ldr r5, =RAM_COUNT
+    ldr r6, [r5]
+    expect2 r6, 0x00030001, r6, 0x00030001
+
+    test "swap buffers clears ram count"
+    gx SWAP_BUFFERS, 0
+    @ The swap waits for VBlank, then a new frame
+    gx_wait
+    ldr r1, =VCOUNT
+6:
+    ldrh r2, [r1]
+    cmp r2, #192
+    bne 6b
+7:
+    ldrh r2, [r1]
+    cmp r2, #10
+    bne 7b
+    ldr r5, =RAM_COUNT
+    ldr r6, [r5]
+    expect2 r6, 0, r6, 0
+
+    test "position stack overflow"
+    identity
+    gx MTX_MODE, 1
+    mov r9, #32
+6:
+    gx MTX_PUSH, 0
+    subs r9, r9, #1
+    bne 6b
+    gx_wait
+    ldr r5, =GXSTAT
+    ldr r6, [r5]
+    @ Acknowledge the error
+    mov r7, #1 << 15
+    str r7, [r5]
+    ldr r7, [r5]
+    and r6, r6, #1 << 15
+    and r7, r7, #1 << 15
+    expect2 r6, 1 << 15, r7, 0
+
+    rom_end
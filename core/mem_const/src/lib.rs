@@ -32,13 +32,14 @@ pub const VRAM_BGA_START: u32 = 0x0600_0000;
 /// Start address of background VRAM B
 pub const VRAM_BGB_START: u32 = 0x0620_0000;
 
-/// Start address of VRAM B (C subregion)
+/// Start address of VRAM C when mapped as background VRAM B
 pub const VRAM_BGB_C: u32 = 0x0620_0000;
 
-/// Start address of VRAM B (H subregion)
+/// Start address of VRAM H when mapped as background VRAM B; C and H are
+/// alternative banks for the same addresses
 pub const VRAM_BGB_H: u32 = 0x0620_0000;
 
-/// Start address of VRAM B (I subregion)
+/// Start address of VRAM I when mapped as background VRAM B, after H
 pub const VRAM_BGB_I: u32 = 0x0620_8000;
 
 /// Start address of object VRAM A
@@ -86,6 +87,9 @@ pub const GBA_ROM_START: u32 = 0x0800_0000;
 /// Start address of GBA RAM memory
 pub const GBA_RAM_START: u32 = 0x0A00_0000;
 
+/// End address of GBA RAM memory, exclusive
+pub const GBA_RAM_END: u32 = 0x0B00_0000;
+
 // Memory Sizes
 
 /// Size of VRAM A in bytes: 128KiB
@@ -115,6 +119,50 @@ pub const VRAM_H_SIZE: u32 = 1024 * 32;
 /// Size of VRAM I in bytes: 16KiB
 pub const VRAM_I_SIZE: u32 = 1024 * 16;
 
+// VRAM bank sizes as `usize`, for allocating and indexing the banks
+
+/// [`VRAM_A_SIZE`] as `usize`
+pub const VRAM_A_LEN: usize = VRAM_A_SIZE as usize;
+
+/// [`VRAM_B_SIZE`] as `usize`
+pub const VRAM_B_LEN: usize = VRAM_B_SIZE as usize;
+
+/// [`VRAM_C_SIZE`] as `usize`
+pub const VRAM_C_LEN: usize = VRAM_C_SIZE as usize;
+
+/// [`VRAM_D_SIZE`] as `usize`
+pub const VRAM_D_LEN: usize = VRAM_D_SIZE as usize;
+
+/// [`VRAM_E_SIZE`] as `usize`
+pub const VRAM_E_LEN: usize = VRAM_E_SIZE as usize;
+
+/// [`VRAM_F_SIZE`] as `usize`
+pub const VRAM_F_LEN: usize = VRAM_F_SIZE as usize;
+
+/// [`VRAM_G_SIZE`] as `usize`
+pub const VRAM_G_LEN: usize = VRAM_G_SIZE as usize;
+
+/// [`VRAM_H_SIZE`] as `usize`
+pub const VRAM_H_LEN: usize = VRAM_H_SIZE as usize;
+
+/// [`VRAM_I_SIZE`] as `usize`
+pub const VRAM_I_LEN: usize = VRAM_I_SIZE as usize;
+
+/// Size of main RAM in bytes: 4MiB
+pub const MAIN_RAM_SIZE: usize = 1024 * 1024 * 4;
+
+/// Size of shared WRAM in bytes: 32KiB
+pub const SHARED_WRAM_SIZE: usize = 1024 * 32;
+
+/// Size of ARM7 WRAM in bytes: 64KiB
+pub const ARM7_WRAM_SIZE: usize = 1024 * 64;
+
+/// Size of the standard palette of one engine in bytes, BG and OBJ: 1KiB
+pub const PALETTE_SIZE: usize = 1024;
+
+/// Size of OAM in bytes, both engines: 2KiB
+pub const OAM_SIZE: usize = 1024 * 2;
+
 /// Size of ARM9 BIOS in bytes: 4KiB
 pub const BIOS9_SIZE: usize = 1024 * 4;
 
@@ -169,3 +217,26 @@ pub const PIXELS_PER_LINE: usize = 256;
 
 /// Number of vertical scanlines
 pub const SCANLINES: usize = 192;
+
+// LCD timing
+
+/// ARM7 / system bus clock, the clock the scheduler counts in
+pub const SYSTEM_CLOCK_HZ: u64 = 33_513_982;
+
+/// System cycles per dot clock
+pub const CYCLES_PER_DOT: u64 = 6;
+
+/// Dots per line, visible (256) and horizontal blank (99)
+pub const DOTS_PER_LINE: u64 = 355;
+
+/// Lines per frame, visible (192) and vertical blank (71)
+pub const LINES_PER_FRAME: u16 = 263;
+
+/// System cycles of the visible part of a line, until HBLANK starts
+pub const HDRAW_CYCLES: u64 = PIXELS_PER_LINE as u64 * CYCLES_PER_DOT;
+
+/// System cycles per line (2130)
+pub const CYCLES_PER_LINE: u64 = DOTS_PER_LINE * CYCLES_PER_DOT;
+
+/// System cycles per frame (560190)
+pub const CYCLES_PER_FRAME: u64 = CYCLES_PER_LINE * LINES_PER_FRAME as u64;
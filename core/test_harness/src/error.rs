@@ -0,0 +1,25 @@
+use std::path::PathBuf;
+
+use lunaris_ds_emu::CartridgeError;
+
+#[derive(Debug, snafu::Snafu)]
+#[snafu(visibility(pub))]
+pub enum HarnessError {
+    /// ROM could not be loaded.
+    #[snafu(display("{}: {source}", path.display()))]
+    LoadRom {
+        path: PathBuf,
+        source: CartridgeError,
+    },
+
+    /// Manifest could not be read or written.
+    #[snafu(display("Failed to access {}: {source}", path.display()))]
+    ManifestIo {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    /// Manifest line is malformed.
+    #[snafu(display("Manifest line {line}: {message}"))]
+    ParseManifest { line: usize, message: String },
+}
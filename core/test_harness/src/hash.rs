@@ -0,0 +1,112 @@
+use std::fmt;
+use std::str::FromStr;
+
+use lunaris_ds_test_support::{Fnv1a, frame_sha256};
+
+/// SHA-256 digest, shown and parsed as 64 lowercase hex digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sha256Hash(pub [u8; 32]);
+
+impl Sha256Hash {
+    /// Hash of the RGB channels of `pixels`, see [`frame_sha256`].
+    pub fn of_screen(pixels: &[u32]) -> Self {
+        Self(frame_sha256(pixels))
+    }
+}
+
+impl fmt::Display for Sha256Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl FromStr for Sha256Hash {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.len() != 64 || !text.is_ascii() {
+            return Err(());
+        }
+        let mut hash = [0; 32];
+        for (byte, digits) in hash.iter_mut().zip(text.as_bytes().chunks_exact(2)) {
+            let digits = std::str::from_utf8(digits).map_err(|_| ())?;
+            *byte = u8::from_str_radix(digits, 16).map_err(|_| ())?;
+        }
+        Ok(Self(hash))
+    }
+}
+
+/// 64-bit FNV-1a over the samples of a run, left then right.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AudioChecksum(Fnv1a);
+
+impl AudioChecksum {
+    pub(crate) const fn new() -> Self {
+        Self(Fnv1a::new())
+    }
+
+    pub(crate) fn update(&mut self, samples: &[i16]) {
+        self.0
+            .update(samples.iter().flat_map(|sample| sample.to_le_bytes()));
+    }
+
+    pub(crate) const fn finish(self) -> u64 {
+        self.0.finish()
+    }
+}
+
+/// Outcome of a run: both screens after the last frame and all audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RunHashes {
+    pub upper: Sha256Hash,
+    pub lower: Sha256Hash,
+    pub audio: u64,
+}
+
+impl RunHashes {
+    /// Names of the parts that differ from `expected`: `upper`, `lower`
+    /// and `audio`.
+    pub fn mismatches(&self, expected: &Self) -> Vec<&'static str> {
+        [
+            ("upper", self.upper == expected.upper),
+            ("lower", self.lower == expected.lower),
+            ("audio", self.audio == expected.audio),
+        ]
+        .into_iter()
+        .filter_map(|(name, same)| (!same).then_some(name))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashes() {
+        let black = Sha256Hash::of_screen(&[0xFF00_0000; 4]);
+        // Only RGB is hashed
+        assert_eq!(black, Sha256Hash::of_screen(&[0x0000_0000; 4]));
+        assert_ne!(black, Sha256Hash::of_screen(&[0xFF00_0001; 4]));
+
+        let text = black.to_string();
+        assert_eq!(text.len(), 64);
+        assert_eq!(text.parse(), Ok(black));
+        assert_eq!("abc".parse::<Sha256Hash>(), Err(()));
+        assert_eq!(Sha256Hash::from_str(&"g".repeat(64)), Err(()));
+
+        let mut audio = AudioChecksum::new();
+        audio.update(&[1, -1]);
+        let run = RunHashes {
+            upper: black,
+            lower: black,
+            audio: audio.finish(),
+        };
+        let expected = RunHashes {
+            lower: Sha256Hash::default(),
+            ..run
+        };
+        assert_eq!(run.mismatches(&run), Vec::<&str>::new());
+        assert_eq!(run.mismatches(&expected), ["lower"]);
+    }
+}
@@ -0,0 +1,24 @@
+//! Headless regression harness
+//!
+//! Runs ROMs for a fixed number of frames and reduces each run to
+//! [`RunHashes`]: a SHA-256 of both screens after the last frame and a
+//! checksum of all audio produced. A [`Manifest`] lists ROMs with their
+//! expected hashes, so rendering or CPU changes can be checked against test
+//! ROMs such as the AGS aging cartridge or rockwrestler:
+//!
+//! ```sh
+//! lunaris-test-harness run ags.nds -n 600   # print a manifest line
+//! lunaris-test-harness check roms.manifest  # compare every entry
+//! ```
+//!
+//! The real-time clock is stopped for these runs, so the hashes do not
+//! depend on the host time.
+mod error;
+mod hash;
+mod manifest;
+mod run;
+
+pub use error::HarnessError;
+pub use hash::{RunHashes, Sha256Hash};
+pub use manifest::{Manifest, ManifestEntry};
+pub use run::{run_frames, run_rom};
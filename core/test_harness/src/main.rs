@@ -0,0 +1,165 @@
+//! Regression runner
+//!
+//! `run` prints the manifest line for one ROM; `check` runs every entry of a
+//! manifest and reports the ones whose hashes changed. After checking that a
+//! change is an improvement, `check --bless` writes the new hashes back.
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use lunaris_test_harness::{HarnessError, Manifest, ManifestEntry, run_rom};
+
+const USAGE: &str = "\
+Usage: lunaris-test-harness run [-n <N>] <ROM>
+       lunaris-test-harness check [--bless] <MANIFEST>
+
+Options:
+  -n, --frames <N>  Frames to run [default: 60]
+      --bless       Write the current hashes to the manifest instead of failing
+  -h, --help        Print this help
+
+Exit status: 0 when every entry matched, 1 on mismatches, 2 on errors";
+
+const DEFAULT_FRAMES: u32 = 60;
+const EXIT_MISMATCH: u8 = 1;
+const EXIT_ERROR: u8 = 2;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Command {
+    Run { rom: PathBuf, frames: u32 },
+    Check { manifest: PathBuf, bless: bool },
+}
+
+/// Parse the arguments following the program name; `None` asks for help.
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Command>, String> {
+    let mut args = args.into_iter();
+    let command = args.next().ok_or("No command given")?;
+    let mut path = None;
+    let mut frames = DEFAULT_FRAMES;
+    let mut bless = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-n" | "--frames" if command == "run" => {
+                let text = args.next().ok_or_else(|| format!("{arg} needs a value"))?;
+                frames = text
+                    .parse()
+                    .map_err(|_| format!("Invalid frame count '{text}'"))?;
+            }
+            "--bless" if command == "check" => bless = true,
+            option if option.starts_with('-') => {
+                return Err(format!("Unknown option '{option}'"));
+            }
+            _ if path.is_some() => return Err(format!("Unexpected argument '{arg}'")),
+            _ => path = Some(PathBuf::from(arg)),
+        }
+    }
+
+    match command.as_str() {
+        "-h" | "--help" => Ok(None),
+        "run" => Ok(Some(Command::Run {
+            rom: path.ok_or("No ROM given")?,
+            frames,
+        })),
+        "check" => Ok(Some(Command::Check {
+            manifest: path.ok_or("No manifest given")?,
+            bless,
+        })),
+        other => Err(format!("Unknown command '{other}'")),
+    }
+}
+
+/// Run every entry, printing one line each. Returns whether all matched.
+fn check(path: &Path, bless: bool) -> Result<bool, HarnessError> {
+    let mut manifest = Manifest::load(path)?;
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut all_matched = true;
+
+    for entry in &mut manifest.entries {
+        let actual = run_rom(&base.join(&entry.rom), entry.frames)?;
+        let mismatches = actual.mismatches(&entry.expected);
+        let rom = entry.rom.display();
+        match (mismatches.is_empty(), bless) {
+            (true, _) => println!("ok    {rom}"),
+            (false, true) => println!("bless {rom}: {}", mismatches.join(", ")),
+            (false, false) => {
+                println!("FAIL  {rom}: {} changed", mismatches.join(", "));
+                all_matched = false;
+            }
+        }
+        entry.expected = actual;
+    }
+
+    if bless {
+        manifest.save(path)?;
+    }
+    Ok(all_matched)
+}
+
+fn main() -> ExitCode {
+    let command = match parse(std::env::args().skip(1)) {
+        Ok(Some(command)) => command,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("lunaris-test-harness: {message}\n\n{USAGE}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+
+    let result = match command {
+        Command::Run { rom, frames } => run_rom(&rom, frames).map(|expected| {
+            let entry = ManifestEntry {
+                rom,
+                frames,
+                expected,
+            };
+            println!("{entry}");
+            true
+        }),
+        Command::Check { manifest, bless } => check(&manifest, bless),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(EXIT_MISMATCH),
+        Err(err) => {
+            eprintln!("lunaris-test-harness: {err}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(line: &str) -> Result<Option<Command>, String> {
+        parse(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(
+            parse_str("run -n 600 ags.nds"),
+            Ok(Some(Command::Run {
+                rom: PathBuf::from("ags.nds"),
+                frames: 600
+            }))
+        );
+        assert_eq!(
+            parse_str("check --bless roms.manifest"),
+            Ok(Some(Command::Check {
+                manifest: PathBuf::from("roms.manifest"),
+                bless: true
+            }))
+        );
+        assert_eq!(parse_str("--help"), Ok(None));
+        assert!(parse_str("").is_err());
+        assert!(parse_str("run").is_err());
+        assert!(parse_str("run --bless a.nds").is_err());
+        assert!(parse_str("check a b").is_err());
+        assert!(parse_str("verify a").is_err());
+    }
+}
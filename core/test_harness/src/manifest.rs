@@ -0,0 +1,162 @@
+//! Expected results
+//!
+//! One ROM per line, `#` starts a comment:
+//!
+//! ```text
+//! # frames  upper screen SHA-256  lower screen SHA-256  audio  ROM
+//! 600 3f1c…e0 9ab2…4d 0123456789abcdef ags.nds
+//! ```
+//!
+//! The audio checksum is 16 hex digits. The ROM path takes the rest of the
+//! line and is relative to the manifest.
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use snafu::ResultExt as _;
+
+use crate::error::{HarnessError, ManifestIoSnafu};
+use crate::hash::RunHashes;
+
+/// ROM, how long to run it, and the hashes it should end with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub rom: PathBuf,
+    pub frames: u32,
+    pub expected: RunHashes,
+}
+
+impl fmt::Display for ManifestEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {:016x} {}",
+            self.frames,
+            self.expected.upper,
+            self.expected.lower,
+            self.expected.audio,
+            self.rom.display()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+fn parse_error(line: usize, message: impl Into<String>) -> HarnessError {
+    HarnessError::ParseManifest {
+        line,
+        message: message.into(),
+    }
+}
+
+impl Manifest {
+    /// Parse manifest text; comments and blank lines are dropped.
+    ///
+    /// # Errors
+    /// On a line with missing or malformed fields.
+    pub fn parse(text: &str) -> Result<Self, HarnessError> {
+        let mut entries = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut rest = line;
+            let mut field = |name: &str| {
+                let (field, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                rest = tail.trim_start();
+                Some(field)
+                    .filter(|field| !field.is_empty())
+                    .ok_or_else(|| parse_error(line_number, format!("Missing {name}")))
+            };
+            let frames = field("frame count")?;
+            let upper = field("upper screen hash")?;
+            let lower = field("lower screen hash")?;
+            let audio = field("audio checksum")?;
+            if rest.is_empty() {
+                return Err(parse_error(line_number, "Missing ROM path"));
+            }
+            let rom = rest;
+
+            let invalid = |name: &str, value: &str| {
+                parse_error(line_number, format!("Invalid {name} '{value}'"))
+            };
+            entries.push(ManifestEntry {
+                rom: PathBuf::from(rom),
+                frames: frames.parse().map_err(|_| invalid("frame count", frames))?,
+                expected: RunHashes {
+                    upper: upper
+                        .parse()
+                        .map_err(|()| invalid("upper screen hash", upper))?,
+                    lower: lower
+                        .parse()
+                        .map_err(|()| invalid("lower screen hash", lower))?,
+                    audio: u64::from_str_radix(audio, 16)
+                        .map_err(|_| invalid("audio checksum", audio))?,
+                },
+            });
+        }
+        Ok(Self { entries })
+    }
+
+    /// Read and parse the manifest at `path`.
+    ///
+    /// # Errors
+    /// If the file could not be read or is malformed.
+    pub fn load(path: &Path) -> Result<Self, HarnessError> {
+        let text = std::fs::read_to_string(path).context(ManifestIoSnafu { path })?;
+        Self::parse(&text)
+    }
+
+    /// Write the manifest to `path`, replacing it.
+    ///
+    /// # Errors
+    /// If the file could not be written.
+    pub fn save(&self, path: &Path) -> Result<(), HarnessError> {
+        std::fs::write(path, self.to_string()).context(ManifestIoSnafu { path })
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# frames upper-sha256 lower-sha256 audio rom")?;
+        self.entries
+            .iter()
+            .try_for_each(|entry| writeln!(f, "{entry}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let upper = "01".repeat(32);
+        let lower = "ab".repeat(32);
+        let text = format!(
+            "# expected\n\n600  {upper}\t{lower} 00000000000000ff roms/AGS aging.nds  # cart\n"
+        );
+        let manifest = Manifest::parse(&text).unwrap();
+        assert_eq!(manifest.entries.len(), 1);
+        let entry = &manifest.entries[0];
+        assert_eq!(entry.rom, PathBuf::from("roms/AGS aging.nds"));
+        assert_eq!(entry.frames, 600);
+        assert_eq!(entry.expected.lower.0, [0xAB; 32]);
+        assert_eq!(entry.expected.audio, 0xFF);
+        assert_eq!(Manifest::parse(&manifest.to_string()).unwrap(), manifest);
+
+        assert!(matches!(
+            Manifest::parse(&format!("60 {upper} {lower} 0")),
+            Err(HarnessError::ParseManifest { line: 1, .. })
+        ));
+        assert!(matches!(
+            Manifest::parse(&format!("\nsixty {upper} {lower} 0 a.nds")),
+            Err(HarnessError::ParseManifest { line: 2, .. })
+        ));
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use lunaris_ds_emu::Emulator;
+use lunaris_ds_mem_const::{PIXELS_PER_LINE, SCANLINES};
+use snafu::ResultExt as _;
+
+use crate::error::{HarnessError, LoadRomSnafu};
+use crate::hash::{AudioChecksum, RunHashes, Sha256Hash};
+
+/// Load `rom` into a fresh emulator and run it for `frames` frames.
+///
+/// # Errors
+/// If the ROM could not be loaded.
+pub fn run_rom(rom: &Path, frames: u32) -> Result<RunHashes, HarnessError> {
+    let mut emu = Box::new(Emulator::new());
+    emu.rtc.use_fixed_clock();
+    emu.load_rom(rom).context(LoadRomSnafu { path: rom })?;
+    Ok(run_frames(&mut emu, frames))
+}
+
+/// Run `frames` frames on an emulator that is already set up, draining and
+/// checksumming the audio as it goes.
+pub fn run_frames(emu: &mut Emulator, frames: u32) -> RunHashes {
+    let mut audio = AudioChecksum::new();
+    let mut buffer = vec![0; 4096];
+    for _ in 0..frames {
+        emu.run();
+        loop {
+            let len = emu.get_samples(&mut buffer);
+            audio.update(&buffer[..len]);
+            if len < buffer.len() {
+                break;
+            }
+        }
+    }
+
+    let mut screen = vec![0; PIXELS_PER_LINE * SCANLINES];
+    emu.get_upper_frame(&mut screen);
+    let upper = Sha256Hash::of_screen(&screen);
+    emu.get_lower_frame(&mut screen);
+    let lower = Sha256Hash::of_screen(&screen);
+    RunHashes {
+        upper,
+        lower,
+        audio: audio.finish(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boot() -> Box<Emulator> {
+        let mut emu = Box::new(Emulator::new());
+        emu.rtc.use_fixed_clock();
+        emu.power_on();
+        emu
+    }
+
+    #[test]
+    fn test_runs_are_reproducible() {
+        let first = run_frames(&mut boot(), 3);
+        assert_eq!(run_frames(&mut boot(), 3), first);
+        assert!(matches!(
+            run_rom(Path::new("missing.nds"), 1),
+            Err(HarnessError::LoadRom { .. })
+        ));
+    }
+}
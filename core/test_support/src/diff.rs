@@ -0,0 +1,271 @@
+use core::fmt;
+
+use crate::frame::Frame;
+
+/// Tile size used to group mismatching pixels into regions.
+const TILE: u32 = 16;
+
+/// Rectangle in pixels, `x..x + width`, `y..y + height`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Result of comparing two frames.
+#[derive(Debug, Clone)]
+pub struct FrameDiff {
+    width: u32,
+    height: u32,
+    tolerance: u8,
+    /// Largest per-channel difference seen.
+    max_delta: u8,
+    /// `true` for every pixel outside the tolerance.
+    mask: Vec<bool>,
+}
+
+const fn channels(pixel: u32) -> [u8; 3] {
+    [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]
+}
+
+impl FrameDiff {
+    /// Compare `actual` against `expected`.
+    ///
+    /// A pixel matches if every RGB channel differs by at most `tolerance`,
+    /// which absorbs rounding differences (e.g. in blending) without hiding
+    /// real rendering bugs. Frames of different size never match.
+    pub fn compare(expected: &Frame, actual: &Frame, tolerance: u8) -> Self {
+        let (width, height) = (expected.width, expected.height);
+        if (width, height) != (actual.width, actual.height) {
+            return Self {
+                width,
+                height,
+                tolerance,
+                max_delta: u8::MAX,
+                mask: vec![true; expected.pixels.len()],
+            };
+        }
+
+        let mut max_delta = 0;
+        let mask = expected
+            .pixels
+            .iter()
+            .zip(&actual.pixels)
+            .map(|(&e, &a)| {
+                let delta = channels(e)
+                    .into_iter()
+                    .zip(channels(a))
+                    .map(|(e, a)| e.abs_diff(a))
+                    .max()
+                    .unwrap_or(0);
+                max_delta = max_delta.max(delta);
+                delta > tolerance
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            tolerance,
+            max_delta,
+            mask,
+        }
+    }
+
+    /// `true` if no pixel is outside the tolerance.
+    pub fn is_match(&self) -> bool {
+        self.mismatched() == 0
+    }
+
+    /// Number of pixels outside the tolerance.
+    pub fn mismatched(&self) -> usize {
+        self.mask.iter().filter(|&&m| m).count()
+    }
+
+    pub const fn max_delta(&self) -> u8 {
+        self.max_delta
+    }
+
+    fn is_mismatch(&self, x: u32, y: u32) -> bool {
+        self.mask
+            .get(y as usize * self.width as usize + x as usize)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    const fn tiles(&self) -> (u32, u32) {
+        (self.width.div_ceil(TILE), self.height.div_ceil(TILE))
+    }
+
+    /// Whether any pixel of tile `(tx, ty)` mismatches.
+    fn tile_dirty(&self, tx: u32, ty: u32) -> bool {
+        let ys = ty * TILE..((ty + 1) * TILE).min(self.height);
+        ys.into_iter().any(|y| {
+            (tx * TILE..((tx + 1) * TILE).min(self.width)).any(|x| self.is_mismatch(x, y))
+        })
+    }
+
+    /// Bounding boxes of groups of neighbouring mismatching tiles.
+    pub fn regions(&self) -> Vec<Rect> {
+        let (tiles_x, tiles_y) = self.tiles();
+        let mut dirty: Vec<bool> = (0..tiles_y)
+            .flat_map(|ty| (0..tiles_x).map(move |tx| (tx, ty)))
+            .map(|(tx, ty)| self.tile_dirty(tx, ty))
+            .collect();
+
+        let mut regions = Vec::new();
+        for start in 0..dirty.len() {
+            if !dirty[start] {
+                continue;
+            }
+
+            // Flood fill the group of dirty tiles starting here
+            dirty[start] = false;
+            let mut stack = vec![start];
+            let (mut x0, mut y0, mut x1, mut y1) = (u32::MAX, u32::MAX, 0, 0);
+            while let Some(index) = stack.pop() {
+                let (tx, ty) = (index as u32 % tiles_x, index as u32 / tiles_x);
+                x0 = x0.min(tx);
+                y0 = y0.min(ty);
+                x1 = x1.max(tx);
+                y1 = y1.max(ty);
+
+                let neighbours = [
+                    (tx.wrapping_sub(1), ty),
+                    (tx + 1, ty),
+                    (tx, ty.wrapping_sub(1)),
+                    (tx, ty + 1),
+                ];
+                for (nx, ny) in neighbours {
+                    if nx < tiles_x && ny < tiles_y {
+                        let n = (ny * tiles_x + nx) as usize;
+                        if dirty[n] {
+                            dirty[n] = false;
+                            stack.push(n);
+                        }
+                    }
+                }
+            }
+
+            // Shrink the tile box to the mismatching pixels inside it
+            let xs = x0 * TILE..((x1 + 1) * TILE).min(self.width);
+            let ys = y0 * TILE..((y1 + 1) * TILE).min(self.height);
+            let (mut px0, mut py0, mut px1, mut py1) = (u32::MAX, u32::MAX, 0, 0);
+            for y in ys {
+                for x in xs.clone() {
+                    if self.is_mismatch(x, y) {
+                        px0 = px0.min(x);
+                        py0 = py0.min(y);
+                        px1 = px1.max(x);
+                        py1 = py1.max(y);
+                    }
+                }
+            }
+            regions.push(Rect {
+                x: px0,
+                y: py0,
+                width: px1 - px0 + 1,
+                height: py1 - py0 + 1,
+            });
+        }
+        regions
+    }
+
+    /// Visualise the diff: matching pixels are dimmed copies of `base`,
+    /// mismatching ones are solid red.
+    pub fn to_frame(&self, base: &Frame) -> Frame {
+        let pixels = self
+            .mask
+            .iter()
+            .enumerate()
+            .map(|(i, &mismatch)| {
+                if mismatch {
+                    return 0xFFFF_0000;
+                }
+                let [r, g, b] = channels(base.pixels.get(i).copied().unwrap_or(0));
+                0xFF00_0000 | (u32::from(r / 4) << 16) | (u32::from(g / 4) << 8) | u32::from(b / 4)
+            })
+            .collect();
+
+        Frame {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+}
+
+impl fmt::Display for FrameDiff {
+    /// Summary, region list and a tile map (`#` = mismatching 16x16 tile).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} of {} pixels differ by more than {} (max delta {})",
+            self.mismatched(),
+            self.mask.len(),
+            self.tolerance,
+            self.max_delta
+        )?;
+        for rect in self.regions() {
+            writeln!(
+                f,
+                "  region x={} y={} {}x{}",
+                rect.x, rect.y, rect.width, rect.height
+            )?;
+        }
+
+        let (tiles_x, tiles_y) = self.tiles();
+        for ty in 0..tiles_y {
+            f.write_str("  ")?;
+            for tx in 0..tiles_x {
+                f.write_str(if self.tile_dirty(tx, ty) { "#" } else { "." })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tolerance_and_regions() {
+        let expected = Frame {
+            width: 64,
+            height: 32,
+            pixels: vec![0xFF10_2030; 64 * 32],
+        };
+        let mut actual = expected.clone();
+        // Within tolerance
+        actual.pixels[0] = 0xFF11_2030;
+        // Two separate 2x2 blocks
+        for (x, y) in [(5, 5), (6, 5), (5, 6), (6, 6), (50, 20), (51, 21)] {
+            actual.pixels[y * 64 + x] = 0xFFFF_FFFF;
+        }
+
+        let diff = FrameDiff::compare(&expected, &actual, 2);
+        assert_eq!(diff.mismatched(), 6);
+        assert_eq!(
+            diff.regions(),
+            [
+                Rect {
+                    x: 5,
+                    y: 5,
+                    width: 2,
+                    height: 2
+                },
+                Rect {
+                    x: 50,
+                    y: 20,
+                    width: 2,
+                    height: 2
+                },
+            ]
+        );
+        assert!(FrameDiff::compare(&expected, &expected, 0).is_match());
+    }
+}
@@ -0,0 +1,23 @@
+use std::path::PathBuf;
+
+#[derive(Debug, snafu::Snafu)]
+#[snafu(visibility(pub))]
+pub enum TestSupportError {
+    /// Failed to read or decode a PNG.
+    #[snafu(display("Failed to load {}: {source}", path.display()))]
+    LoadPng {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+
+    /// Failed to encode or write a PNG.
+    #[snafu(display("Failed to save {}: {source}", path.display()))]
+    SavePng {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+
+    /// Pixel buffer length does not match the frame size.
+    #[snafu(display("Expected {width}x{height} pixels, got {len}"))]
+    SizeMismatch { width: u32, height: u32, len: usize },
+}
@@ -0,0 +1,132 @@
+use sha2::{Digest as _, Sha256};
+
+use crate::error::{SizeMismatchSnafu, TestSupportError};
+
+/// One screen (or any image) in the emulator's `0xAARRGGBB` pixel format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u32>,
+}
+
+impl Frame {
+    /// Wrap a pixel buffer, e.g. from `Emulator::get_upper_frame`.
+    ///
+    /// # Errors
+    /// If `pixels.len()` is not `width * height`.
+    pub fn new(width: u32, height: u32, pixels: Vec<u32>) -> Result<Self, TestSupportError> {
+        if pixels.len() != width as usize * height as usize {
+            return SizeMismatchSnafu {
+                width,
+                height,
+                len: pixels.len(),
+            }
+            .fail();
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// Pixel at `(x, y)`; `None` outside the frame.
+    pub fn get(&self, x: u32, y: u32) -> Option<u32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.pixels
+            .get(y as usize * self.width as usize + x as usize)
+            .copied()
+    }
+
+    /// Hash of the visible pixels, see [`frame_hash`].
+    pub fn hash(&self) -> u64 {
+        frame_hash(&self.pixels)
+    }
+}
+
+/// 64-bit FNV-1a, fed in pieces.
+///
+/// Used for frame hashes and by the harness for its audio checksum, so the
+/// stored values share one definition.
+#[derive(Debug, Clone, Copy)]
+pub struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    pub const fn new() -> Self {
+        Self(Self::OFFSET)
+    }
+
+    pub fn update(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        for byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    pub const fn finish(self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The bytes frame hashes cover: R, G and B of each pixel. Alpha is
+/// ignored since the renderer does not define it consistently.
+fn rgb_bytes(pixels: &[u32]) -> impl Iterator<Item = u8> + '_ {
+    pixels
+        .iter()
+        .flat_map(|pixel| pixel.to_le_bytes().into_iter().take(3))
+}
+
+/// 64-bit FNV-1a over the RGB channels of `pixels`.
+///
+/// The value is stable across platforms and releases, so it can be stored
+/// in expected-results files.
+pub fn frame_hash(pixels: &[u32]) -> u64 {
+    let mut hash = Fnv1a::new();
+    hash.update(rgb_bytes(pixels));
+    hash.finish()
+}
+
+/// SHA-256 over the same RGB channels as [`frame_hash`], for stored
+/// hashes that must not collide.
+pub fn frame_sha256(pixels: &[u32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for byte in rgb_bytes(pixels) {
+        hasher.update([byte]);
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_ignores_alpha() {
+        let a = [0xFF12_3456, 0xFF00_0000];
+        let b = [0x0012_3456, 0x8000_0000];
+        assert_eq!(frame_hash(&a), frame_hash(&b));
+        assert_ne!(frame_hash(&a), frame_hash(&[0xFF12_3457, 0xFF00_0000]));
+        assert_eq!(frame_sha256(&a), frame_sha256(&b));
+        assert_ne!(frame_sha256(&a), frame_sha256(&[0xFF12_3457, 0xFF00_0000]));
+    }
+
+    #[test]
+    fn test_hash_values() {
+        // FNV-1a and SHA-256 of the bytes 56 34 12
+        let pixels = [0xFF12_3456];
+        assert_eq!(frame_hash(&pixels), 0x7CC7_D219_EA9C_39AF);
+        assert_eq!(frame_sha256(&pixels)[..4], [0x48, 0x1F, 0x52, 0x19]);
+    }
+}
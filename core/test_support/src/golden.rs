@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+
+use image::{ImageBuffer, Rgba};
+use snafu::ResultExt as _;
+
+use crate::diff::FrameDiff;
+use crate::error::{LoadPngSnafu, SavePngSnafu, TestSupportError};
+use crate::frame::Frame;
+
+/// Whether golden files should be (re)written instead of compared.
+///
+/// Enabled by setting `LUNARIS_BLESS` to any value.
+pub fn bless_enabled() -> bool {
+    std::env::var_os("LUNARIS_BLESS").is_some()
+}
+
+/// Save `frame` as an RGBA PNG.
+///
+/// # Errors
+/// If the file could not be encoded or written.
+pub fn save_png(path: &Path, frame: &Frame) -> Result<(), TestSupportError> {
+    // 0xAARRGGBB -> [R, G, B, A]
+    let raw: Vec<u8> = frame
+        .pixels
+        .iter()
+        .flat_map(|px| {
+            let [b, g, r, a] = px.to_le_bytes();
+            [r, g, b, a]
+        })
+        .collect();
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(image::ImageError::IoError)
+            .with_context(|_| SavePngSnafu { path })?;
+    }
+    ImageBuffer::<Rgba<u8>, _>::from_raw(frame.width, frame.height, raw)
+        .map_or_else(
+            || {
+                Err(image::ImageError::Parameter(
+                    image::error::ParameterError::from_kind(
+                        image::error::ParameterErrorKind::DimensionMismatch,
+                    ),
+                ))
+            },
+            |img| img.save(path),
+        )
+        .with_context(|_| SavePngSnafu { path })
+}
+
+/// Load a PNG as a [`Frame`].
+///
+/// # Errors
+/// If the file could not be read or decoded.
+pub fn load_png(path: &Path) -> Result<Frame, TestSupportError> {
+    let img = image::open(path)
+        .with_context(|_| LoadPngSnafu { path })?
+        .into_rgba8();
+
+    let pixels = img
+        .pixels()
+        .map(|Rgba([r, g, b, a])| u32::from_le_bytes([*b, *g, *r, *a]))
+        .collect();
+
+    Ok(Frame {
+        width: img.width(),
+        height: img.height(),
+        pixels,
+    })
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{stem}.{suffix}.png"))
+}
+
+/// Compare `frame` with the golden PNG at `path`.
+///
+/// When blessing (see [`bless_enabled`]), `frame` is written as the new
+/// golden instead. On mismatch the actual frame and a diff image are written
+/// next to the golden as `<name>.actual.png` and `<name>.diff.png`.
+///
+/// # Panics
+/// If the frame does not match, the golden file does not exist yet outside
+/// blessing, or a file could not be read or written.
+pub fn assert_golden(path: &Path, frame: &Frame, tolerance: u8) {
+    if bless_enabled() {
+        save_png(path, frame)
+            .unwrap_or_else(|err| panic!("failed to bless {}: {err}", path.display()));
+        return;
+    }
+    assert!(
+        path.exists(),
+        "{} does not exist, rerun with LUNARIS_BLESS=1 to create it",
+        path.display()
+    );
+
+    let golden =
+        load_png(path).unwrap_or_else(|err| panic!("failed to read {}: {err}", path.display()));
+    let diff = FrameDiff::compare(&golden, frame, tolerance);
+    if diff.is_match() {
+        return;
+    }
+
+    let actual_path = sibling(path, "actual");
+    let diff_path = sibling(path, "diff");
+    for (path, frame) in [(&actual_path, frame), (&diff_path, &diff.to_frame(&golden))] {
+        save_png(path, frame)
+            .unwrap_or_else(|err| panic!("failed to write {}: {err}", path.display()));
+    }
+    panic!(
+        "{} does not match\n{diff}  actual: {}\n  diff:   {}",
+        path.display(),
+        actual_path.display(),
+        diff_path.display()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_round_trip() {
+        let path = std::env::temp_dir().join(format!("lunaris_golden_{}.png", std::process::id()));
+        let frame = Frame {
+            width: 2,
+            height: 2,
+            pixels: vec![0xFF12_3456, 0xFF00_0000, 0xFFFF_FFFF, 0x80AB_CDEF],
+        };
+
+        save_png(&path, &frame).unwrap();
+        assert_eq!(load_png(&path).unwrap(), frame);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,22 @@
+//! Test helpers shared by the compat harness, GPU tests and fuzzers.
+//!
+//! - [`Frame`]: a screen capture in the emulator's `0xAARRGGBB` format
+//! - [`frame_hash`], [`frame_sha256`]: stable hashes for quick equality
+//!   checks and stored expectations, plus the [`Fnv1a`] behind the former
+//! - [`FrameDiff`]: per-pixel comparison with tolerance and a printable
+//!   summary of the mismatching regions
+//! - [`assert_golden`]: compare against a golden PNG, (re)writing it when
+//!   blessing
+//! - [`arm_alu_reference`]: reference model of the ARM data-processing ALU
+//!   for flag tests
+mod alu;
+mod diff;
+mod error;
+mod frame;
+mod golden;
+
+pub use alu::{AluFlags, AluResult, arm_alu_reference};
+pub use diff::{FrameDiff, Rect};
+pub use error::TestSupportError;
+pub use frame::{Fnv1a, Frame, frame_hash, frame_sha256};
+pub use golden::{assert_golden, bless_enabled, load_png, save_png};